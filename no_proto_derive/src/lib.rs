@@ -0,0 +1,374 @@
+//! Derive macros for [NoProto](https://docs.rs/no_proto).
+//!
+//! Enable the `derive` feature of `no_proto` to use these, you shouldn't need to depend on this crate directly.
+//!
+//! `#[derive(NP_Record)]` maps a plain struct with named fields onto a NoProto table.  Each field becomes a column with the same name, the schema JSON is generated for you and the `fill_buffer`/`from_buffer` methods replace the `set`/`get` calls you would otherwise write by hand.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::{NP_Factory, NP_Record};
+//!
+//! #[derive(NP_Record, Debug, PartialEq)]
+//! struct User {
+//!     name: String,
+//!     age: u16,
+//!     email: Option<String>,
+//!     todos: Vec<String>
+//! }
+//!
+//! assert_eq!(User::schema(), r#"{"type":"table","columns":[["name",{"type":"string"}],["age",{"type":"u16"}],["email",{"type":"string"}],["todos",{"type":"list","of":{"type":"string"}}]]}"#);
+//!
+//! let factory = NP_Factory::new(User::schema())?;
+//!
+//! let user = User {
+//!     name: String::from("Billy Joel"),
+//!     age: 71,
+//!     email: None,
+//!     todos: vec![String::from("Write a rust library.")]
+//! };
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! user.fill_buffer(&mut new_buffer)?;
+//! assert_eq!(new_buffer.get::<&str>(&["todos", "0"])?, Some("Write a rust library."));
+//!
+//! assert_eq!(User::from_buffer(&new_buffer)?, user);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! #### Supported field types
+//! - `bool`, `i8` to `i64`, `u8` to `u64`, `f32`, `f64`
+//! - `String` (string), `Vec<u8>` (bytes) and `NP_Date` (date)
+//! - `Option<T>` of any type above, the column is cleared when the value is `None`
+//! - `Vec<T>` of any type above (besides `u8`), stored as a list, longer lists already in the buffer are truncated to the length of the `Vec`
+//!
+//! Columns missing from a buffer are read back as `Default::default()`, except for `Option` fields which are read back as `None`.
+//!
+//! Generic structs, tuple structs and enums aren't supported, implement `NP_Record` by hand for those.
+//!
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+/// Generates schema JSON and buffer conversions for a struct.
+///
+/// Check out the documentation [here](./index.html).
+///
+#[proc_macro_derive(NP_Record)]
+pub fn derive_np_record(input: TokenStream) -> TokenStream {
+    match parse_struct(input) {
+        Ok((name, fields)) => expand(&name, &fields),
+        Err(message) => compile_error(&message)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    Number(&'static str, &'static str), // rust type, schema type
+    Str,
+    Bytes,
+    Date
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Required(Scalar),
+    Optional(Scalar),
+    List(Scalar)
+}
+
+struct Field {
+    name: String,
+    kind: Kind
+}
+
+fn compile_error(message: &str) -> TokenStream {
+    format!("compile_error!({:?});", message).parse().unwrap()
+}
+
+fn parse_struct(input: TokenStream) -> Result<(String, Vec<Field>), String> {
+    let mut tokens = input.into_iter().peekable();
+    let mut name: Option<String> = None;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) => {
+                let ident = ident.to_string();
+                if ident == "enum" || ident == "union" {
+                    return Err(String::from("NP_Record can only be derived for structs!"));
+                }
+                if ident == "struct" {
+                    match tokens.next() {
+                        Some(TokenTree::Ident(x)) => { name = Some(x.to_string()); },
+                        _ => return Err(String::from("NP_Record expected a struct name!"))
+                    }
+                    break;
+                }
+            },
+            _ => { } // attributes, visibility
+        }
+    }
+
+    let name = match name {
+        Some(x) => x,
+        None => return Err(String::from("NP_Record can only be derived for structs!"))
+    };
+
+    match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+            Ok((name, parse_fields(group.stream())?))
+        },
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            Err(String::from("NP_Record can't be derived for generic structs!"))
+        },
+        _ => Err(String::from("NP_Record can only be derived for structs with named fields!"))
+    }
+}
+
+fn parse_fields(input: TokenStream) -> Result<Vec<Field>, String> {
+    let mut fields: Vec<Field> = Vec::new();
+
+    let mut current: Vec<TokenTree> = Vec::new();
+    let mut depth = 0usize;
+
+    for token in input.into_iter() {
+        match &token {
+            TokenTree::Punct(punct) if punct.as_char() == '<' => { depth += 1; },
+            TokenTree::Punct(punct) if punct.as_char() == '>' => { depth = depth.saturating_sub(1); },
+            TokenTree::Punct(punct) if punct.as_char() == ',' && depth == 0 => {
+                fields.push(parse_field(&current)?);
+                current.clear();
+                continue;
+            },
+            _ => { }
+        }
+        current.push(token);
+    }
+
+    if current.len() > 0 {
+        fields.push(parse_field(&current)?);
+    }
+
+    if fields.len() > 255 {
+        return Err(String::from("NP_Record structs are limited to 255 fields!"));
+    }
+
+    Ok(fields)
+}
+
+fn parse_field(tokens: &[TokenTree]) -> Result<Field, String> {
+    let mut index = 0usize;
+
+    // skip attributes and visibility
+    loop {
+        match tokens.get(index) {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '#' => { index += 2; },
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "pub" => {
+                index += 1;
+                if let Some(TokenTree::Group(group)) = tokens.get(index) {
+                    if group.delimiter() == Delimiter::Parenthesis { index += 1; }
+                }
+            },
+            _ => break
+        }
+    }
+
+    let name = match tokens.get(index) {
+        Some(TokenTree::Ident(ident)) => ident.to_string().trim_start_matches("r#").to_string(),
+        _ => return Err(String::from("NP_Record expected a field name!"))
+    };
+
+    match tokens.get(index + 1) {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => { },
+        _ => return Err(format!("NP_Record expected a type for field '{}'!", name))
+    }
+
+    let type_str: String = tokens[(index + 2)..].iter().map(|t| t.to_string()).collect::<Vec<String>>().join("").replace(' ', "");
+
+    match parse_kind(&type_str) {
+        Some(kind) => Ok(Field { name, kind }),
+        None => Err(format!("NP_Record doesn't support the type '{}' used by field '{}'!", type_str, name))
+    }
+}
+
+fn parse_kind(type_str: &str) -> Option<Kind> {
+    if let Some(inner) = generic_inner(type_str, "Option") {
+        return parse_scalar(inner).map(Kind::Optional);
+    }
+
+    if let Some(inner) = generic_inner(type_str, "Vec") {
+        if last_segment(inner) == "u8" {
+            return Some(Kind::Required(Scalar::Bytes));
+        }
+        return parse_scalar(inner).map(Kind::List);
+    }
+
+    parse_scalar(type_str).map(Kind::Required)
+}
+
+fn generic_inner<'a>(type_str: &'a str, wrapper: &str) -> Option<&'a str> {
+    let open = type_str.find('<')?;
+    if last_segment(&type_str[..open]) == wrapper && type_str.ends_with('>') {
+        Some(&type_str[(open + 1)..(type_str.len() - 1)])
+    } else {
+        None
+    }
+}
+
+fn last_segment(type_str: &str) -> &str {
+    match type_str.rfind("::") {
+        Some(x) => &type_str[(x + 2)..],
+        None => type_str
+    }
+}
+
+fn parse_scalar(type_str: &str) -> Option<Scalar> {
+    if let Some(inner) = generic_inner(type_str, "Vec") {
+        if last_segment(inner) == "u8" {
+            return Some(Scalar::Bytes);
+        }
+        return None;
+    }
+
+    match last_segment(type_str) {
+        "bool"    => Some(Scalar::Number("bool", "bool")),
+        "i8"      => Some(Scalar::Number("i8", "i8")),
+        "i16"     => Some(Scalar::Number("i16", "i16")),
+        "i32"     => Some(Scalar::Number("i32", "i32")),
+        "i64"     => Some(Scalar::Number("i64", "i64")),
+        "u8"      => Some(Scalar::Number("u8", "u8")),
+        "u16"     => Some(Scalar::Number("u16", "u16")),
+        "u32"     => Some(Scalar::Number("u32", "u32")),
+        "u64"     => Some(Scalar::Number("u64", "u64")),
+        "f32"     => Some(Scalar::Number("f32", "float")),
+        "f64"     => Some(Scalar::Number("f64", "double")),
+        "String"  => Some(Scalar::Str),
+        "NP_Date" => Some(Scalar::Date),
+        _ => None
+    }
+}
+
+fn scalar_schema(scalar: Scalar) -> String {
+    let type_str = match scalar {
+        Scalar::Number(_, schema_type) => schema_type,
+        Scalar::Str => "string",
+        Scalar::Bytes => "bytes",
+        Scalar::Date => "date"
+    };
+    format!("{{\"type\":\"{}\"}}", type_str)
+}
+
+// expression converting `value` (a reference to the field value) into something `NP_Buffer::set` accepts
+fn scalar_set(scalar: Scalar) -> &'static str {
+    match scalar {
+        Scalar::Number(_, _) => "*value",
+        Scalar::Str => "value.as_str()",
+        Scalar::Bytes => "value.as_slice()",
+        Scalar::Date => "*value"
+    }
+}
+
+// expression reading an `Option` of the owned field type from `path`
+fn scalar_get(scalar: Scalar) -> String {
+    match scalar {
+        Scalar::Number(rust_type, _) => format!("buffer.get::<{}>(path)?", rust_type),
+        Scalar::Str => String::from("buffer.get::<&str>(path)?.map(|x| ::no_proto::record::__private::ToString::to_string(x))"),
+        Scalar::Bytes => String::from("buffer.get::<&[u8]>(path)?.map(|x| x.to_vec())"),
+        Scalar::Date => String::from("buffer.get::<::no_proto::pointer::date::NP_Date>(path)?")
+    }
+}
+
+fn expand(name: &str, fields: &[Field]) -> TokenStream {
+
+    let mut schema = String::from("{\"type\":\"table\",\"columns\":[");
+    let mut fill = String::new();
+    let mut from = String::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let column = &field.name;
+        let ident = if is_keyword(column) { format!("r#{}", column) } else { column.clone() };
+
+        if i > 0 { schema.push(','); }
+
+        match field.kind {
+            Kind::Required(scalar) => {
+                schema.push_str(&format!("[\"{}\",{}]", column, scalar_schema(scalar)));
+                fill.push_str(&format!(
+                    "{{ let value = &self.{}; buffer.set(&[\"{}\"], {})?; }}\n",
+                    ident, column, scalar_set(scalar)
+                ));
+                from.push_str(&format!(
+                    "{}: {{ let path: &[&str] = &[\"{}\"]; {}.unwrap_or_default() }},\n",
+                    ident, column, scalar_get(scalar)
+                ));
+            },
+            Kind::Optional(scalar) => {
+                schema.push_str(&format!("[\"{}\",{}]", column, scalar_schema(scalar)));
+                fill.push_str(&format!(
+                    "if let Some(value) = &self.{} {{ buffer.set(&[\"{}\"], {})?; }} else {{ buffer.del(&[\"{}\"])?; }}\n",
+                    ident, column, scalar_set(scalar), column
+                ));
+                from.push_str(&format!(
+                    "{}: {{ let path: &[&str] = &[\"{}\"]; {} }},\n",
+                    ident, column, scalar_get(scalar)
+                ));
+            },
+            Kind::List(scalar) => {
+                schema.push_str(&format!("[\"{}\",{{\"type\":\"list\",\"of\":{}}}]", column, scalar_schema(scalar)));
+                // items past the end of the vec are left over from an older value, they're removed first
+                fill.push_str(&format!(
+                    "buffer.list_truncate(&[\"{}\"], self.{}.len())?;\nfor (index, value) in self.{}.iter().enumerate() {{ let index = ::no_proto::record::__private::ToString::to_string(&index); buffer.set(&[\"{}\", index.as_str()], {})?; }}\n",
+                    column, ident, ident, column, scalar_set(scalar)
+                ));
+                from.push_str(&format!(
+                    "{}: {{
+                        let mut list = ::no_proto::record::__private::Vec::new();
+                        if let Some(length) = buffer.length(&[\"{}\"])? {{
+                            for index in 0..length {{
+                                let index = ::no_proto::record::__private::ToString::to_string(&index);
+                                let path: &[&str] = &[\"{}\", index.as_str()];
+                                list.push({}.unwrap_or_default());
+                            }}
+                        }}
+                        list
+                    }},\n",
+                    ident, column, column, scalar_get(scalar)
+                ));
+            }
+        }
+    }
+
+    schema.push_str("]}");
+
+    let output = format!("
+        impl ::no_proto::record::NP_Record for {name} {{
+            fn schema() -> &'static str {{
+                {schema:?}
+            }}
+
+            fn fill_buffer(&self, buffer: &mut ::no_proto::buffer::NP_Buffer) -> ::core::result::Result<(), ::no_proto::error::NP_Error> {{
+                {fill}
+                ::core::result::Result::Ok(())
+            }}
+
+            fn from_buffer(buffer: &::no_proto::buffer::NP_Buffer) -> ::core::result::Result<Self, ::no_proto::error::NP_Error> {{
+                ::core::result::Result::Ok({name} {{
+                    {from}
+                }})
+            }}
+        }}
+    ", name = name, schema = schema, fill = fill, from = from);
+
+    match output.parse() {
+        Ok(x) => x,
+        Err(_e) => compile_error("NP_Record failed to generate code for this struct!")
+    }
+}
+
+fn is_keyword(ident: &str) -> bool {
+    match ident {
+        "as" | "break" | "const" | "continue" | "crate" | "else" | "enum" | "extern" | "false" | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "self" | "Self" | "static" | "struct" | "super" | "trait" | "true" | "type" | "unsafe" | "use" | "where" | "while" | "async" | "await" | "dyn" | "abstract" | "become" | "box" | "do" | "final" | "macro" | "override" | "priv" | "typeof" | "unsized" | "virtual" | "yield" | "try" => true,
+        _ => false
+    }
+}
@@ -0,0 +1,101 @@
+use no_proto::error::NP_Error;
+use no_proto::pointer::date::NP_Date;
+use no_proto::{NP_Factory, NP_Record};
+
+#[derive(NP_Record, Debug, PartialEq, Default)]
+struct Ship {
+    name: String,
+    crew: Option<u8>,
+    launched: Option<NP_Date>,
+    notes: Option<String>,
+    payload: Vec<u8>,
+    parts: Vec<String>,
+    stages: Vec<u16>
+}
+
+#[derive(NP_Record, Debug, PartialEq)]
+struct Keywords {
+    r#type: String,
+    r#match: Option<bool>,
+    pub r#in: Vec<i32>
+}
+
+#[test]
+fn options_work() -> Result<(), NP_Error> {
+    let factory = NP_Factory::new(Ship::schema())?;
+
+    let ship = Ship { name: String::from("Kerbal X"), crew: Some(3), notes: Some(String::from("big")), ..Default::default() };
+    let mut buffer = factory.empty_buffer(None);
+    ship.fill_buffer(&mut buffer)?;
+    assert_eq!(buffer.get::<u8>(&["crew"])?, Some(3));
+    assert_eq!(buffer.get::<NP_Date>(&["launched"])?, None);
+    assert_eq!(Ship::from_buffer(&buffer)?, ship);
+
+    // `None` clears a value that was set before
+    let ship = Ship { crew: None, launched: Some(NP_Date::new(1000)), ..ship };
+    ship.fill_buffer(&mut buffer)?;
+    assert_eq!(buffer.get::<u8>(&["crew"])?, None);
+    assert_eq!(Ship::from_buffer(&buffer)?, ship);
+
+    Ok(())
+}
+
+#[test]
+fn bytes_work() -> Result<(), NP_Error> {
+    assert!(Ship::schema().contains(r#"["payload",{"type":"bytes"}]"#));
+
+    let factory = NP_Factory::new(Ship::schema())?;
+    let ship = Ship { payload: vec![0, 1, 2, 255], ..Default::default() };
+    let mut buffer = factory.empty_buffer(None);
+    ship.fill_buffer(&mut buffer)?;
+    assert_eq!(buffer.get::<&[u8]>(&["payload"])?, Some(&[0u8, 1, 2, 255][..]));
+    assert_eq!(Ship::from_buffer(&buffer)?, ship);
+
+    Ok(())
+}
+
+#[test]
+fn lists_work() -> Result<(), NP_Error> {
+    let factory = NP_Factory::new(Ship::schema())?;
+
+    let long = Ship { parts: vec![String::from("pod"), String::from("tank"), String::from("engine")], stages: vec![1, 2, 3, 4], ..Default::default() };
+    let mut buffer = factory.empty_buffer(None);
+    long.fill_buffer(&mut buffer)?;
+    assert_eq!(Ship::from_buffer(&buffer)?, long);
+
+    // filling the same buffer with shorter lists doesn't leave the old items behind
+    let short = Ship { parts: vec![String::from("chute")], stages: Vec::new(), ..Default::default() };
+    short.fill_buffer(&mut buffer)?;
+    assert_eq!(buffer.length(&["parts"])?, Some(1));
+    assert_eq!(buffer.get::<&str>(&["parts", "1"])?, None);
+    assert_eq!(Ship::from_buffer(&buffer)?, short);
+
+    // and a closed buffer opened again round trips the same way
+    let mut buffer = factory.open_buffer(buffer.close());
+    long.fill_buffer(&mut buffer)?;
+    short.fill_buffer(&mut buffer)?;
+    assert_eq!(Ship::from_buffer(&factory.open_buffer(buffer.close()))?, short);
+
+    Ok(())
+}
+
+#[test]
+fn raw_identifiers_work() -> Result<(), NP_Error> {
+    assert_eq!(Keywords::schema(), r#"{"type":"table","columns":[["type",{"type":"string"}],["match",{"type":"bool"}],["in",{"type":"list","of":{"type":"i32"}}]]}"#);
+
+    let factory = NP_Factory::new(Keywords::schema())?;
+    let value = Keywords { r#type: String::from("rocket"), r#match: Some(true), r#in: vec![-1, 7] };
+    let mut buffer = factory.empty_buffer(None);
+    value.fill_buffer(&mut buffer)?;
+    assert_eq!(buffer.get::<&str>(&["type"])?, Some("rocket"));
+    assert_eq!(buffer.get::<i32>(&["in", "1"])?, Some(7));
+    assert_eq!(Keywords::from_buffer(&buffer)?, value);
+
+    Ok(())
+}
+
+#[test]
+fn compile_errors_work() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,9 @@
+use no_proto::NP_Record;
+
+#[derive(NP_Record)]
+enum Color {
+    Red,
+    Blue
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use no_proto::NP_Record;
+
+#[derive(NP_Record)]
+struct Wrapper<T> {
+    value: T
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use no_proto::NP_Record;
+
+#[derive(NP_Record)]
+struct Grid {
+    rows: Vec<Vec<String>>
+}
+
+fn main() {}
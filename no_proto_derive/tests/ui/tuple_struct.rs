@@ -0,0 +1,6 @@
+use no_proto::NP_Record;
+
+#[derive(NP_Record)]
+struct Point(u8, u8);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use no_proto::NP_Record;
+
+#[derive(NP_Record)]
+struct User {
+    name: String,
+    friends: std::collections::HashMap<String, u8>
+}
+
+fn main() {}
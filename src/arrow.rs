@@ -0,0 +1,295 @@
+//! Apache Arrow columnar export
+//!
+//! With the `arrow` feature enabled, a list of tables can be converted into an [`arrow_array::RecordBatch`](https://docs.rs/arrow-array/latest/arrow_array/struct.RecordBatch.html), one column per table column.
+//!
+//! The batch is built with the `arrow-array` and `arrow-schema` crates, so it can be handed straight to other Arrow libraries.  Every field in the batch schema is nullable.
+//!
+//! Nested tables are flattened into columns named `parent.child` and geo values become two `Float64` columns ending in `.lat` and `.lng`.  Lists, maps and tuples inside the rows can't be represented as flat columns and produce an error.  Values that haven't been set are null, even if the schema has a default for them.
+//!
+//! | NoProto type                  | Arrow type                                 |
+//! |-------------------------------|--------------------------------------------|
+//! | integers, `float`, `double`   | matching `Int`, `UInt` and `Float` types   |
+//! | `bool`                        | `Boolean`                                  |
+//! | `string`, `option`            | `Utf8`                                     |
+//! | `bytes`                       | `Binary`                                   |
+//! | `uuid`, `ulid`                | `FixedSizeBinary(16)`                      |
+//! | `decimal`                     | `Decimal128` with the schema `exp` as its scale |
+//! | `date`                        | `Timestamp` in milliseconds                |
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use arrow_array::Array;
+//! use arrow_array::cast::AsArray;
+//! use arrow_array::types::UInt8Type;
+//! use arrow_schema::DataType;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "list",
+//!    "of": {"type": "table", "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "uint8"}]
+//!     ]}
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["0", "name"], "Jeb")?;
+//! new_buffer.set(&["0", "age"], 30u8)?;
+//! new_buffer.set(&["1", "name"], "Bob")?;
+//!
+//! let batch = new_buffer.to_arrow(&[])?;
+//! assert_eq!(batch.num_rows(), 2);
+//! assert_eq!(batch.schema().field(0).name(), "name");
+//! assert_eq!(batch.schema().field(1).data_type(), &DataType::UInt8);
+//!
+//! let names = batch.column(0).as_string::<i32>();
+//! assert_eq!(names.value(0), "Jeb");
+//! assert_eq!(names.value(1), "Bob");
+//!
+//! let ages = batch.column(1).as_primitive::<UInt8Type>();
+//! assert_eq!(ages.null_count(), 1);
+//! assert_eq!(ages.value(0), 30);
+//! assert_eq!(ages.is_null(1), true);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::owned::NP_Value_Owned;
+use crate::schema::NP_Parsed_Schema;
+use alloc::string::{String, ToString};
+use alloc::borrow::ToOwned;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use arrow_array::{ArrayRef, RecordBatch, RecordBatchOptions};
+use arrow_array::{BooleanArray, StringArray, BinaryArray, FixedSizeBinaryArray, Decimal128Array, TimestampMillisecondArray};
+use arrow_array::{Int8Array, Int16Array, Int32Array, Int64Array, UInt8Array, UInt16Array, UInt32Array, UInt64Array, Float32Array, Float64Array};
+use arrow_schema::{ArrowError, DataType, Field, Schema, TimeUnit};
+
+impl From<ArrowError> for NP_Error {
+    fn from(err: ArrowError) -> NP_Error {
+        NP_Error::Other(err.to_string())
+    }
+}
+
+/// Where the value for a column comes from
+enum Leaf {
+    Value(usize),
+    Lat,
+    Lng
+}
+
+/// Find the flat columns for the table at `address`
+fn flatten(schema: &Vec<NP_Parsed_Schema>, address: usize, prefix: &str, path: &mut Vec<String>, leaves: &mut Vec<(String, Vec<String>, Leaf)>) -> Result<(), NP_Error> {
+    let columns = match &schema[address] {
+        NP_Parsed_Schema::Table { columns, .. } => columns,
+        _ => return Err(NP_Error::SchemaMismatch("Arrow export needs a list of tables!".into()))
+    };
+
+    for (_index, column, addr) in columns {
+        let mut name = prefix.to_owned();
+        name.push_str(column);
+        path.push(column.clone());
+        match &schema[*addr] {
+            NP_Parsed_Schema::Table { .. } => {
+                name.push('.');
+                flatten(schema, *addr, &name, path, leaves)?;
+            },
+            NP_Parsed_Schema::Geo { .. } => {
+                let mut lat = name.clone();
+                lat.push_str(".lat");
+                leaves.push((lat, path.clone(), Leaf::Lat));
+                name.push_str(".lng");
+                leaves.push((name, path.clone(), Leaf::Lng));
+            },
+            NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } | NP_Parsed_Schema::Tuple { .. } | NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::None => {
+                let mut err = String::from("Arrow export can't flatten column: ");
+                err.push_str(&name);
+                return Err(NP_Error::SchemaMismatch(err));
+            },
+            _ => leaves.push((name, path.clone(), Leaf::Value(*addr)))
+        }
+        path.pop();
+    }
+
+    Ok(())
+}
+
+fn arrow_type(schema: &NP_Parsed_Schema) -> DataType {
+    match schema {
+        NP_Parsed_Schema::Boolean { .. } => DataType::Boolean,
+        NP_Parsed_Schema::Int8 { .. } => DataType::Int8,
+        NP_Parsed_Schema::Int16 { .. } => DataType::Int16,
+        NP_Parsed_Schema::Int32 { .. } => DataType::Int32,
+        NP_Parsed_Schema::Int64 { .. } => DataType::Int64,
+        NP_Parsed_Schema::Uint8 { .. } => DataType::UInt8,
+        NP_Parsed_Schema::Uint16 { .. } => DataType::UInt16,
+        NP_Parsed_Schema::Uint32 { .. } => DataType::UInt32,
+        NP_Parsed_Schema::Uint64 { .. } => DataType::UInt64,
+        NP_Parsed_Schema::Float { .. } => DataType::Float32,
+        NP_Parsed_Schema::Bytes { .. } => DataType::Binary,
+        NP_Parsed_Schema::Uuid { .. } | NP_Parsed_Schema::Ulid { .. } => DataType::FixedSizeBinary(16),
+        NP_Parsed_Schema::Decimal { exp, .. } => DataType::Decimal128(38, *exp as i8),
+        NP_Parsed_Schema::Date { .. } => DataType::Timestamp(TimeUnit::Millisecond, None),
+        NP_Parsed_Schema::UTF8String { .. } | NP_Parsed_Schema::Enum { .. } => DataType::Utf8,
+        _ => DataType::Float64
+    }
+}
+
+/// Get the owned value at `path` inside a row
+fn find<'row>(row: &'row NP_Value_Owned, path: &[String]) -> Option<&'row NP_Value_Owned> {
+    match path.first() {
+        None => Some(row),
+        Some(key) => match row {
+            NP_Value_Owned::Table(entries) => entries.iter().find(|(name, _value)| name == key).and_then(|(_name, value)| find(value, &path[1..])),
+            _ => None
+        }
+    }
+}
+
+
+fn mismatch() -> NP_Error {
+    NP_Error::SchemaMismatch("Value doesn't match the Arrow column type!".into())
+}
+
+/// Build the Arrow array for one column, `None` and `Null` values are null slots
+fn column_array(data_type: &DataType, leaf: &Leaf, values: &[Option<&NP_Value_Owned>]) -> Result<ArrayRef, NP_Error> {
+
+    macro_rules! column {
+        ($array: ty, |$value: ident| $convert: expr) => {
+            values.iter().map(|value| match value {
+                None | Some(NP_Value_Owned::Null) => Ok(None),
+                Some($value) => $convert.map(Some).ok_or_else(mismatch)
+            }).collect::<Result<$array, NP_Error>>()?
+        }
+    }
+
+    Ok(match (data_type, leaf) {
+        (DataType::Float64, Leaf::Lat) => Arc::new(column!(Float64Array, |x| match x { NP_Value_Owned::Geo(x) => Some(x.lat), _ => None })),
+        (DataType::Float64, Leaf::Lng) => Arc::new(column!(Float64Array, |x| match x { NP_Value_Owned::Geo(x) => Some(x.lng), _ => None })),
+        (DataType::Boolean, _) => Arc::new(column!(BooleanArray, |x| match x { NP_Value_Owned::Bool(x) => Some(*x), _ => None })),
+        (DataType::Int8, _) => Arc::new(column!(Int8Array, |x| match x { NP_Value_Owned::Int8(x) => Some(*x), _ => None })),
+        (DataType::Int16, _) => Arc::new(column!(Int16Array, |x| match x { NP_Value_Owned::Int16(x) => Some(*x), _ => None })),
+        (DataType::Int32, _) => Arc::new(column!(Int32Array, |x| match x { NP_Value_Owned::Int32(x) => Some(*x), _ => None })),
+        (DataType::Int64, _) => Arc::new(column!(Int64Array, |x| match x { NP_Value_Owned::Int64(x) => Some(*x), _ => None })),
+        (DataType::UInt8, _) => Arc::new(column!(UInt8Array, |x| match x { NP_Value_Owned::Uint8(x) => Some(*x), _ => None })),
+        (DataType::UInt16, _) => Arc::new(column!(UInt16Array, |x| match x { NP_Value_Owned::Uint16(x) => Some(*x), _ => None })),
+        (DataType::UInt32, _) => Arc::new(column!(UInt32Array, |x| match x { NP_Value_Owned::Uint32(x) => Some(*x), _ => None })),
+        (DataType::UInt64, _) => Arc::new(column!(UInt64Array, |x| match x { NP_Value_Owned::Uint64(x) => Some(*x), _ => None })),
+        (DataType::Float32, _) => Arc::new(column!(Float32Array, |x| match x { NP_Value_Owned::Float(x) => Some(*x), _ => None })),
+        (DataType::Float64, _) => Arc::new(column!(Float64Array, |x| match x { NP_Value_Owned::Double(x) => Some(*x), _ => None })),
+        (DataType::Utf8, _) => Arc::new(column!(StringArray, |x| match x { NP_Value_Owned::String(x) | NP_Value_Owned::Enum(x) => Some(x.as_str()), _ => None })),
+        (DataType::Binary, _) => Arc::new(column!(BinaryArray, |x| match x { NP_Value_Owned::Bytes(x) => Some(x.as_slice()), _ => None })),
+        (DataType::Timestamp(TimeUnit::Millisecond, None), _) => Arc::new(column!(TimestampMillisecondArray, |x| match x { NP_Value_Owned::Date(x) => Some(x.value as i64), _ => None })),
+        (DataType::Decimal128(precision, scale), _) => {
+            let array: Decimal128Array = column!(Decimal128Array, |x| match x { NP_Value_Owned::Dec(x) => Some(x.num as i128), _ => None });
+            Arc::new(array.with_precision_and_scale(*precision, *scale)?)
+        },
+        (DataType::FixedSizeBinary(size), _) => {
+            let bytes = values.iter().map(|value| match value {
+                None | Some(NP_Value_Owned::Null) => Ok(None),
+                Some(NP_Value_Owned::Uuid(x)) => Ok(Some(&x.value[..])),
+                Some(NP_Value_Owned::Ulid(x)) => Ok(Some(&x.as_bytes()[..])),
+                Some(_) => Err(mismatch())
+            }).collect::<Result<Vec<_>, NP_Error>>()?;
+            Arc::new(FixedSizeBinaryArray::try_from_sparse_iter_with_size(bytes.into_iter(), *size)?)
+        },
+        _ => return Err(mismatch())
+    })
+}
+
+/// Convert rows from the list schema at `address` into a record batch
+pub(crate) fn rows_to_batch(schema: &Vec<NP_Parsed_Schema>, address: usize, rows: &[NP_Value_Owned]) -> Result<RecordBatch, NP_Error> {
+
+    let of = match &schema[address] {
+        NP_Parsed_Schema::List { of, .. } => *of,
+        _ => return Err(NP_Error::SchemaMismatch("Arrow export needs a list of tables!".into()))
+    };
+
+    let mut leaves = Vec::new();
+    flatten(schema, of, "", &mut Vec::new(), &mut leaves)?;
+
+    let mut fields = Vec::with_capacity(leaves.len());
+    let mut columns = Vec::with_capacity(leaves.len());
+
+    for (name, path, leaf) in leaves.iter() {
+        let data_type = match leaf {
+            Leaf::Value(addr) => arrow_type(&schema[*addr]),
+            _ => DataType::Float64
+        };
+        let values: Vec<Option<&NP_Value_Owned>> = rows.iter().map(|row| find(row, path)).collect();
+        columns.push(column_array(&data_type, leaf, &values)?);
+        fields.push(Field::new(name.as_str(), data_type, true));
+    }
+
+    let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+    Ok(RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)?)
+}
+
+#[test]
+fn arrow_export_works() -> Result<(), NP_Error> {
+    use crate::pointer::{dec::NP_Dec, date::NP_Date, geo::NP_Geo};
+    use arrow_array::Array;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Decimal128Type, Float64Type, TimestampMillisecondType};
+
+    let factory = crate::NP_Factory::new(r#"{
+        "type": "list",
+        "of": {"type": "table", "columns": [
+            ["ok",    {"type": "bool"}],
+            ["price", {"type": "dec", "exp": 2}],
+            ["when",  {"type": "date"}],
+            ["where", {"type": "geo4"}],
+            ["owner", {"type": "table", "columns": [["name", {"type": "string"}]]}],
+            ["id",    {"type": "uuid"}]
+        ]}
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0", "ok"], true)?;
+    buffer.set(&["0", "price"], NP_Dec::new(150, 2))?;
+    buffer.set(&["2", "ok"], true)?;
+    buffer.set(&["2", "when"], NP_Date::new(5))?;
+    buffer.set(&["2", "where"], NP_Geo::new(4, 1.0, 2.0))?;
+    buffer.set(&["2", "owner", "name"], "Jeb")?;
+    buffer.set(&["2", "id"], &crate::pointer::uuid::NP_UUID { value: [7u8; 16] })?;
+
+    let batch = buffer.to_arrow(&[])?;
+    assert_eq!(batch.num_rows(), 3);
+
+    let schema = batch.schema();
+    let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+    assert_eq!(names, alloc::vec!["ok", "price", "when", "where.lat", "where.lng", "owner.name", "id"]);
+    assert!(schema.fields().iter().all(|f| f.is_nullable()));
+
+    let ok = batch.column(0).as_boolean();
+    assert_eq!((ok.value(0), ok.is_null(1), ok.value(2)), (true, true, true));
+
+    assert_eq!(schema.field(1).data_type(), &DataType::Decimal128(38, 2));
+    let price = batch.column(1).as_primitive::<Decimal128Type>();
+    assert_eq!(price.value(0), 150);
+    assert_eq!(price.value_as_string(0), "1.50");
+    assert_eq!(price.null_count(), 2);
+
+    assert_eq!(batch.column(2).as_primitive::<TimestampMillisecondType>().value(2), 5);
+    assert_eq!(batch.column(4).as_primitive::<Float64Type>().value(2), 2.0);
+
+    let owner = batch.column(5).as_string::<i32>();
+    assert_eq!(owner.null_count(), 2);
+    assert_eq!(owner.value(2), "Jeb");
+
+    assert_eq!(schema.field(6).data_type(), &DataType::FixedSizeBinary(16));
+    assert_eq!(batch.column(6).as_fixed_size_binary().value(2), &[7u8; 16]);
+
+    // empty lists give an empty batch with the same schema
+    let empty = factory.empty_buffer(None).to_arrow(&[])?;
+    assert_eq!(empty.num_rows(), 0);
+    assert_eq!(empty.schema(), schema);
+
+    // lists inside rows can't be flattened
+    let nested = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "table", "columns": [["tags", {"type": "list", "of": {"type": "string"}}]]}}"#)?;
+    assert!(nested.empty_buffer(None).to_arrow(&[]).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,622 @@
+//! Read and write buffers on async streams
+//!
+//! `read_buffer` and `write_buffer` send buffers over async streams like sockets, each buffer is written with a 4 byte big endian length in front of it so the reader knows where it ends.  This module requires the `async` feature.
+//!
+//! `NP_Codec` does the same framing on byte queues for codec based stacks like `tokio_util::codec`, with the `tokio-util` feature it implements the `Decoder` and `Encoder` traits so it works with `Framed` directly.
+//!
+//! NoProto doesn't depend on an async runtime, streams are used through the `NP_Async_Read` and `NP_Async_Write` traits instead.  They have the same poll methods as the `AsyncRead` and `AsyncWrite` traits of the `futures` crate, so implementing them for the stream type of any runtime is a few lines that call the runtime's own poll methods.  They're implemented for `&[u8]` and `Vec<u8>` out of the box.
+//!
+//! Streams of the common runtimes work through a wrapper instead, each one behind it's own feature:
+//! - `futures-io`: `NP_Futures_Stream` for `futures::io::AsyncRead` and `AsyncWrite` streams, like the ones from async-std and smol
+//! - `tokio`: `NP_Tokio_Stream` for `tokio::io::AsyncRead` and `AsyncWrite` streams, like `tokio::net::TcpStream`
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::async_io::{read_buffer, write_buffer};
+//! # fn block_on<F: core::future::Future>(future: F) -> F::Output {
+//! #     let mut future = Box::pin(future);
+//! #     let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+//! #     loop { if let core::task::Poll::Ready(x) = future.as_mut().poll(&mut cx) { return x; } }
+//! # }
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! block_on(async {
+//!     let mut new_buffer = factory.empty_buffer(None);
+//!     new_buffer.set(&["name"], "Jeb")?;
+//!
+//!     // any NP_Async_Write works here, like a socket
+//!     let mut socket: Vec<u8> = Vec::new();
+//!     write_buffer(&mut socket, &new_buffer).await?;
+//!     write_buffer(&mut socket, &new_buffer).await?;
+//!
+//!     // and any NP_Async_Read works here
+//!     let mut stream = &socket[..];
+//!     while let Some(buffer) = read_buffer(&factory, &mut stream, 1024).await? {
+//!         assert_eq!(buffer.get::<&str>(&["name"])?, Some("Jeb"));
+//!     }
+//!
+//!     Ok::<(), NP_Error>(())
+//! })?;
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use alloc::vec::Vec;
+use core::future::poll_fn;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io;
+
+/// Length of the prefix in front of each buffer
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// Streams buffers can be read from
+pub trait NP_Async_Read {
+    /// Read bytes into `buf`, returning how many were read.  Reading zero bytes into a buffer that isn't empty means the stream ended.
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>>;
+}
+
+/// Streams buffers can be written to
+pub trait NP_Async_Write {
+    /// Write bytes from `buf`, returning how many were written
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>;
+    /// Flush written bytes to their destination
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+impl NP_Async_Read for &[u8] {
+    fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let len = core::cmp::min(self.len(), buf.len());
+        buf[0..len].copy_from_slice(&self[0..len]);
+        *self = &self[len..];
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl NP_Async_Write for Vec<u8> {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.get_mut().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: NP_Async_Read + Unpin + ?Sized> NP_Async_Read for &mut T {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self).poll_read(cx, buf)
+    }
+}
+
+impl<T: NP_Async_Write + Unpin + ?Sized> NP_Async_Write for &mut T {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut **self).poll_flush(cx)
+    }
+}
+
+/// Stream implementing the `AsyncRead` and `AsyncWrite` traits of the `futures-io` crate, so buffers can be read and written on it.
+///
+/// This type requires the `futures-io` feature.
+///
+/// ```
+/// use no_proto::error::NP_Error;
+/// use no_proto::NP_Factory;
+/// use no_proto::async_io::{read_buffer, write_buffer, NP_Futures_Stream};
+/// # fn block_on<F: core::future::Future>(future: F) -> F::Output {
+/// #     let mut future = Box::pin(future);
+/// #     let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+/// #     loop { if let core::task::Poll::Ready(x) = future.as_mut().poll(&mut cx) { return x; } }
+/// # }
+///
+/// let factory = NP_Factory::new(r#"{"type": "string"}"#)?;
+///
+/// block_on(async {
+///     let mut new_buffer = factory.empty_buffer(None);
+///     new_buffer.set(&[], "hello")?;
+///
+///     // any futures::io::AsyncWrite works here, like an async-std TcpStream
+///     let mut socket = NP_Futures_Stream(Vec::new());
+///     write_buffer(&mut socket, &new_buffer).await?;
+///
+///     let mut stream = NP_Futures_Stream(&socket.0[..]);
+///     let buffer = read_buffer(&factory, &mut stream, 1024).await?.unwrap();
+///     assert_eq!(buffer.get::<&str>(&[])?, Some("hello"));
+///
+///     Ok::<(), NP_Error>(())
+/// })?;
+///
+/// # Ok::<(), NP_Error>(())
+/// ```
+///
+#[cfg(feature = "futures-io")]
+#[derive(Debug, Clone, Default)]
+pub struct NP_Futures_Stream<S>(pub S);
+
+#[cfg(feature = "futures-io")]
+impl<S: futures_io::AsyncRead + Unpin> NP_Async_Read for NP_Futures_Stream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<S: futures_io::AsyncWrite + Unpin> NP_Async_Write for NP_Futures_Stream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+}
+
+/// Stream implementing the `AsyncRead` and `AsyncWrite` traits of tokio, so buffers can be read and written on it.
+///
+/// This type requires the `tokio` feature.
+///
+/// ```
+/// use no_proto::error::NP_Error;
+/// use no_proto::NP_Factory;
+/// use no_proto::async_io::{read_buffer, write_buffer, NP_Tokio_Stream};
+/// # fn block_on<F: core::future::Future>(future: F) -> F::Output {
+/// #     let mut future = Box::pin(future);
+/// #     let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+/// #     loop { if let core::task::Poll::Ready(x) = future.as_mut().poll(&mut cx) { return x; } }
+/// # }
+///
+/// let factory = NP_Factory::new(r#"{"type": "string"}"#)?;
+///
+/// block_on(async {
+///     let mut new_buffer = factory.empty_buffer(None);
+///     new_buffer.set(&[], "hello")?;
+///
+///     // any tokio::io::AsyncWrite works here, like a tokio TcpStream
+///     let mut socket = NP_Tokio_Stream(Vec::new());
+///     write_buffer(&mut socket, &new_buffer).await?;
+///
+///     let mut stream = NP_Tokio_Stream(&socket.0[..]);
+///     let buffer = read_buffer(&factory, &mut stream, 1024).await?.unwrap();
+///     assert_eq!(buffer.get::<&str>(&[])?, Some("hello"));
+///
+///     Ok::<(), NP_Error>(())
+/// })?;
+///
+/// # Ok::<(), NP_Error>(())
+/// ```
+///
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Default)]
+pub struct NP_Tokio_Stream<S>(pub S);
+
+#[cfg(feature = "tokio")]
+impl<S: tokio::io::AsyncRead + Unpin> NP_Async_Read for NP_Tokio_Stream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let mut read_buf = tokio::io::ReadBuf::new(buf);
+        Pin::new(&mut self.0).poll_read(cx, &mut read_buf).map_ok(|()| read_buf.filled().len())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: tokio::io::AsyncWrite + Unpin> NP_Async_Write for NP_Tokio_Stream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+}
+
+/// Fill `buf` from the reader, returns less than `buf.len()` if the stream ended first
+async fn read_full<R: NP_Async_Read + Unpin + ?Sized>(reader: &mut R, buf: &mut [u8]) -> Result<usize, NP_Error> {
+    let mut done = 0usize;
+    while done < buf.len() {
+        let read = poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut buf[done..])).await?;
+        if read == 0 {
+            break;
+        }
+        done += read;
+    }
+    Ok(done)
+}
+
+/// Read the next length prefixed buffer from a stream.
+///
+/// Returns `None` if the stream ended before another buffer started.  Fails if the buffer is longer than `max_size` bytes, before any room for it is allocated, or if the stream ends part way through a buffer.
+///
+/// Bytes from a stream can't be trusted, so the buffer is opened with `NP_Factory::open_buffer_untrusted` and fails with a `Damaged` error if it's structure is broken.
+///
+pub async fn read_buffer<'factory, R: NP_Async_Read + Unpin + ?Sized>(factory: &'factory NP_Factory, reader: &mut R, max_size: usize) -> Result<Option<NP_Buffer<'factory>>, NP_Error> {
+    let mut prefix = [0u8; LENGTH_PREFIX_SIZE];
+    match read_full(reader, &mut prefix).await? {
+        0 => return Ok(None),
+        LENGTH_PREFIX_SIZE => { },
+        _ => return Err(NP_Error::Io("Stream ended inside a buffer length!".into()))
+    }
+
+    let len = u32::from_be_bytes(prefix) as usize;
+    if len > max_size {
+        return Err(NP_Error::OutOfBounds("Buffer from stream is larger than the max size!".into()));
+    }
+
+    let mut bytes = alloc::vec![0u8; len];
+    if read_full(reader, &mut bytes).await? != len {
+        return Err(NP_Error::Io("Stream ended before the end of the buffer!".into()));
+    }
+
+    Ok(Some(factory.open_buffer_untrusted(bytes)?))
+}
+
+/// Write a buffer to a stream with it's length in front of it, then flush the stream.
+///
+pub async fn write_buffer<W: NP_Async_Write + Unpin + ?Sized>(writer: &mut W, buffer: &NP_Buffer<'_>) -> Result<(), NP_Error> {
+    let bytes = buffer.read_bytes();
+    if bytes.len() > u32::MAX as usize {
+        return Err(NP_Error::OutOfBounds("Buffer is too large for a length prefix!".into()));
+    }
+
+    for part in [&(bytes.len() as u32).to_be_bytes()[..], bytes].iter() {
+        let mut done = 0usize;
+        while done < part.len() {
+            let written = poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, &part[done..])).await?;
+            if written == 0 {
+                return Err(NP_Error::Io("Stream stopped taking bytes part way through a buffer!".into()));
+            }
+            done += written;
+        }
+    }
+
+    poll_fn(|cx| Pin::new(&mut *writer).poll_flush(cx)).await?;
+    Ok(())
+}
+
+/// Length prefixed framing for byte queues, the same framing `read_buffer` and `write_buffer` use.
+///
+/// `decode` and `encode` work like the methods of the `Decoder` and `Encoder` traits in `tokio_util::codec`: `decode` takes the first whole buffer off the front of the received bytes and returns `None` until one has arrived, `encode` appends a buffer to the bytes waiting to be sent.
+///
+/// With the `tokio-util` feature the codec implements those traits too, so it can be handed to `Framed`, `FramedRead` or `FramedWrite` as is.  Decoded items are `NP_Buffer`s, buffers and buffer references can be encoded.
+///
+/// ```
+/// use no_proto::error::NP_Error;
+/// use no_proto::NP_Factory;
+/// use no_proto::async_io::NP_Codec;
+///
+/// let factory = NP_Factory::new(r#"{"type": "string"}"#)?;
+/// let mut codec = NP_Codec::new(&factory, 1024);
+///
+/// let mut new_buffer = factory.empty_buffer(None);
+/// new_buffer.set(&[], "hello")?;
+///
+/// let mut sent: Vec<u8> = Vec::new();
+/// codec.encode(&new_buffer, &mut sent)?;
+///
+/// // bytes arrive a few at a time
+/// let mut received: Vec<u8> = Vec::new();
+/// received.extend_from_slice(&sent[0..6]);
+/// assert!(codec.decode(&mut received)?.is_none());
+///
+/// received.extend_from_slice(&sent[6..]);
+/// let buffer = codec.decode(&mut received)?.unwrap();
+/// assert_eq!(buffer.get::<&str>(&[])?, Some("hello"));
+/// assert!(received.is_empty());
+///
+/// # Ok::<(), NP_Error>(())
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct NP_Codec<'factory> {
+    factory: &'factory NP_Factory,
+    max_size: usize
+}
+
+impl<'factory> NP_Codec<'factory> {
+
+    /// Create a codec for buffers of a factory, buffers longer than `max_size` bytes fail to decode
+    pub fn new(factory: &'factory NP_Factory, max_size: usize) -> Self {
+        NP_Codec { factory, max_size }
+    }
+
+    /// Take the first buffer off the front of `src`, or `None` if the whole buffer hasn't arrived yet.
+    ///
+    /// Fails as soon as the length prefix says the buffer is longer than the max size.  Buffers are opened with `NP_Factory::open_buffer_untrusted`, a buffer with a broken structure fails with a `Damaged` error and is taken off `src` so the next one can still be decoded.
+    ///
+    pub fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<NP_Buffer<'factory>>, NP_Error> {
+        let len = match self.frame_len(src)? {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+
+        let bytes = src[LENGTH_PREFIX_SIZE..(LENGTH_PREFIX_SIZE + len)].to_vec();
+        src.drain(0..(LENGTH_PREFIX_SIZE + len));
+        Ok(Some(self.factory.open_buffer_untrusted(bytes)?))
+    }
+
+    /// Append a buffer with it's length prefix to `dst`
+    pub fn encode(&mut self, buffer: &NP_Buffer<'_>, dst: &mut Vec<u8>) -> Result<(), NP_Error> {
+        let prefix = Self::length_prefix(buffer)?;
+        dst.reserve(LENGTH_PREFIX_SIZE + buffer.read_bytes().len());
+        dst.extend_from_slice(&prefix);
+        dst.extend_from_slice(buffer.read_bytes());
+        Ok(())
+    }
+
+    /// Length of the buffer at the front of `src`, or `None` if the whole buffer hasn't arrived yet
+    fn frame_len(&self, src: &[u8]) -> Result<Option<usize>, NP_Error> {
+        if src.len() < LENGTH_PREFIX_SIZE {
+            return Ok(None);
+        }
+
+        let len = u32::from_be_bytes([src[0], src[1], src[2], src[3]]) as usize;
+        if len > self.max_size {
+            return Err(NP_Error::OutOfBounds("Buffer from stream is larger than the max size!".into()));
+        }
+        if src.len() < LENGTH_PREFIX_SIZE + len {
+            return Ok(None);
+        }
+
+        Ok(Some(len))
+    }
+
+    fn length_prefix(buffer: &NP_Buffer<'_>) -> Result<[u8; LENGTH_PREFIX_SIZE], NP_Error> {
+        let len = buffer.read_bytes().len();
+        if len > u32::MAX as usize {
+            return Err(NP_Error::OutOfBounds("Buffer is too large for a length prefix!".into()));
+        }
+        Ok((len as u32).to_be_bytes())
+    }
+}
+
+#[cfg(feature = "tokio-util")]
+impl<'factory> tokio_util::codec::Decoder for NP_Codec<'factory> {
+    type Item = NP_Buffer<'factory>;
+    type Error = NP_Error;
+
+    fn decode(&mut self, src: &mut tokio_util::bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let len = match self.frame_len(src)? {
+            Some(x) => x,
+            None => {
+                // make room for the rest of the buffer so it arrives in as few reads as possible
+                if src.len() >= LENGTH_PREFIX_SIZE {
+                    let len = u32::from_be_bytes([src[0], src[1], src[2], src[3]]) as usize;
+                    src.reserve(LENGTH_PREFIX_SIZE + len - src.len());
+                }
+                return Ok(None);
+            }
+        };
+
+        let frame = src.split_to(LENGTH_PREFIX_SIZE + len);
+        Ok(Some(self.factory.open_buffer_untrusted(frame[LENGTH_PREFIX_SIZE..].to_vec())?))
+    }
+}
+
+#[cfg(feature = "tokio-util")]
+impl<'buffer> tokio_util::codec::Encoder<&NP_Buffer<'buffer>> for NP_Codec<'_> {
+    type Error = NP_Error;
+
+    fn encode(&mut self, buffer: &NP_Buffer<'buffer>, dst: &mut tokio_util::bytes::BytesMut) -> Result<(), Self::Error> {
+        let prefix = Self::length_prefix(buffer)?;
+        dst.reserve(LENGTH_PREFIX_SIZE + buffer.read_bytes().len());
+        dst.extend_from_slice(&prefix);
+        dst.extend_from_slice(buffer.read_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio-util")]
+impl<'buffer> tokio_util::codec::Encoder<NP_Buffer<'buffer>> for NP_Codec<'_> {
+    type Error = NP_Error;
+
+    fn encode(&mut self, buffer: NP_Buffer<'buffer>, dst: &mut tokio_util::bytes::BytesMut) -> Result<(), Self::Error> {
+        tokio_util::codec::Encoder::encode(self, &buffer, dst)
+    }
+}
+
+#[test]
+fn async_io_works() -> Result<(), NP_Error> {
+    use core::future::Future;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = alloc::boxed::Box::pin(future);
+        let mut cx = Context::from_waker(core::task::Waker::noop());
+        loop {
+            if let Poll::Ready(x) = future.as_mut().poll(&mut cx) {
+                return x;
+            }
+        }
+    }
+
+    // a stream that moves one byte per poll and is pending every other poll
+    struct Trickle { bytes: Vec<u8>, read: usize, ready: bool }
+
+    impl NP_Async_Read for Trickle {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if self.read == self.bytes.len() || buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            buf[0] = self.bytes[self.read];
+            self.read += 1;
+            Poll::Ready(Ok(1))
+        }
+    }
+
+    impl NP_Async_Write for Trickle {
+        fn poll_write(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            self.bytes.push(buf[0]);
+            Poll::Ready(Ok(1))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+
+    block_on(async {
+        let mut stream = Trickle { bytes: Vec::new(), read: 0, ready: false };
+        for item in ["one", "two"].iter() {
+            let mut buffer = factory.empty_buffer(None);
+            buffer.list_push(&[], *item)?;
+            write_buffer(&mut stream, &buffer).await?;
+        }
+        let bytes = stream.bytes.clone();
+
+        assert_eq!(read_buffer(&factory, &mut stream, 1024).await?.unwrap().get::<&str>(&["0"])?, Some("one"));
+        assert_eq!(read_buffer(&factory, &mut stream, 1024).await?.unwrap().get::<&str>(&["0"])?, Some("two"));
+        assert!(read_buffer(&factory, &mut stream, 1024).await?.is_none());
+
+        // buffers past the max size and cut off streams fail
+        assert!(read_buffer(&factory, &mut &bytes[..], 4).await.is_err());
+        assert!(read_buffer(&factory, &mut &bytes[0..2], 1024).await.is_err());
+        assert!(read_buffer(&factory, &mut &bytes[0..10], 1024).await.is_err());
+
+        // the codec reads the same frames
+        let mut codec = NP_Codec::new(&factory, 1024);
+        let mut received = bytes.clone();
+        assert_eq!(codec.decode(&mut received)?.unwrap().get::<&str>(&["0"])?, Some("one"));
+        let mut sent: Vec<u8> = Vec::new();
+        let two = codec.decode(&mut received)?.unwrap();
+        codec.encode(&two, &mut sent)?;
+        assert!(received.is_empty() && codec.decode(&mut received)?.is_none());
+        assert_eq!(&sent[..], &bytes[(bytes.len() - sent.len())..]);
+        assert!(NP_Codec::new(&factory, 4).decode(&mut bytes[0..4].to_vec()).is_err());
+
+        // a whole frame with a damaged buffer in it fails without losing the frames after it
+        let mut damaged = factory.empty_buffer(None);
+        damaged.list_push(&[], "three")?;
+        let damaged = damaged.close();
+        let damaged = &damaged[..(damaged.len() - 2)];
+        let mut received: Vec<u8> = Vec::new();
+        received.extend_from_slice(&(damaged.len() as u32).to_be_bytes());
+        received.extend_from_slice(damaged);
+        received.extend_from_slice(&sent);
+        assert!(matches!(read_buffer(&factory, &mut &received[..], 1024).await, Err(NP_Error::Damaged(_))));
+        assert!(matches!(codec.decode(&mut received), Err(NP_Error::Damaged(_))));
+        assert_eq!(codec.decode(&mut received)?.unwrap().get::<&str>(&["0"])?, Some("two"));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn runtime_streams_work() -> Result<(), NP_Error> {
+    use core::future::Future;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = alloc::boxed::Box::pin(future);
+        let mut cx = Context::from_waker(core::task::Waker::noop());
+        loop {
+            if let Poll::Ready(x) = future.as_mut().poll(&mut cx) {
+                return x;
+            }
+        }
+    }
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.list_push(&[], "one")?;
+
+    block_on(async {
+        #[cfg(feature = "futures-io")]
+        {
+            let mut socket = NP_Futures_Stream(Vec::new());
+            write_buffer(&mut socket, &buffer).await?;
+            write_buffer(&mut socket, &buffer).await?;
+            let mut stream = NP_Futures_Stream(&socket.0[..]);
+            assert_eq!(read_buffer(&factory, &mut stream, 1024).await?.unwrap().get::<&str>(&["0"])?, Some("one"));
+            assert_eq!(read_buffer(&factory, &mut stream, 1024).await?.unwrap().get::<&str>(&["0"])?, Some("one"));
+            assert!(read_buffer(&factory, &mut stream, 1024).await?.is_none());
+            assert!(read_buffer(&factory, &mut NP_Futures_Stream(&socket.0[0..6]), 1024).await.is_err());
+        }
+
+        #[cfg(feature = "tokio")]
+        {
+            let mut socket = NP_Tokio_Stream(Vec::new());
+            write_buffer(&mut socket, &buffer).await?;
+            write_buffer(&mut socket, &buffer).await?;
+            let mut stream = NP_Tokio_Stream(&socket.0[..]);
+            assert_eq!(read_buffer(&factory, &mut stream, 1024).await?.unwrap().get::<&str>(&["0"])?, Some("one"));
+            assert_eq!(read_buffer(&factory, &mut stream, 1024).await?.unwrap().get::<&str>(&["0"])?, Some("one"));
+            assert!(read_buffer(&factory, &mut stream, 1024).await?.is_none());
+            assert!(read_buffer(&factory, &mut NP_Tokio_Stream(&socket.0[0..6]), 1024).await.is_err());
+        }
+
+        Ok(())
+    })
+}
+
+#[cfg(feature = "tokio-util")]
+#[test]
+fn tokio_codec_works() -> Result<(), NP_Error> {
+    use tokio_util::bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    let mut codec = NP_Codec::new(&factory, 1024);
+
+    let mut sent = BytesMut::new();
+    for item in ["one", "two"].iter() {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.list_push(&[], *item)?;
+        Encoder::encode(&mut codec, &buffer, &mut sent)?;
+        Encoder::encode(&mut codec, buffer, &mut sent)?;
+    }
+
+    // the trait and the byte queue methods make the same frames
+    let mut queue: Vec<u8> = Vec::new();
+    let mut buffer = factory.empty_buffer(None);
+    buffer.list_push(&[], "one")?;
+    NP_Codec::encode(&mut codec, &buffer, &mut queue)?;
+    assert_eq!(&sent[0..queue.len()], &queue[..]);
+
+    // bytes arrive a few at a time
+    let mut received = BytesMut::new();
+    let mut items: Vec<alloc::string::String> = Vec::new();
+    for chunk in sent.chunks(5) {
+        received.extend_from_slice(chunk);
+        while let Some(buffer) = Decoder::decode(&mut codec, &mut received)? {
+            items.push(buffer.get::<&str>(&["0"])?.unwrap_or_default().into());
+        }
+    }
+    assert_eq!(items, ["one", "one", "two", "two"]);
+    assert!(Decoder::decode_eof(&mut codec, &mut received)?.is_none());
+
+    // leftover bytes at the end of the stream and buffers past the max size fail
+    received.extend_from_slice(&sent[0..6]);
+    assert!(Decoder::decode_eof(&mut codec, &mut received).is_err());
+    let mut large = BytesMut::from(&sent[..]);
+    assert!(matches!(Decoder::decode(&mut NP_Codec::new(&factory, 4), &mut large), Err(NP_Error::OutOfBounds(_))));
+
+    // frames with damaged buffers fail
+    let mut damaged = BytesMut::from(&sent[..]);
+    let root = LENGTH_PREFIX_SIZE + crate::memory::NP_Memory::existing(damaged[LENGTH_PREFIX_SIZE..].to_vec(), &factory.schema.parsed).root;
+    // past the end, 0xffff would be a deleted value
+    damaged[root] = 0xff;
+    damaged[root + 1] = 0xf0;
+    assert!(matches!(Decoder::decode(&mut codec, &mut damaged), Err(NP_Error::Damaged(_))));
+
+    Ok(())
+}
@@ -0,0 +1,405 @@
+//! Apache Avro schema and data conversion
+//!
+//! With the `avro` feature enabled, schemas can be converted into [Avro](https://avro.apache.org/docs/current/spec.html) schemas and buffers can be transcoded into Avro binary data for that schema.
+//!
+//! Tables and tuples become records, lists become arrays and maps become maps.  Values in a table or tuple might not be set, so every record field is a union of `null` and it's type with a `null` default; list items are unions too since lists can have empty items.  Only values that have been set in the buffer are exported, unset values with a default in the schema are written as `null`.
+//!
+//! Types are mapped like this:
+//!
+//! | NoProto type                          | Avro type                                          |
+//! |---------------------------------------|----------------------------------------------------|
+//! | `int8`, `int16`, `int32`, `uint8`, `uint16` | `int`                                        |
+//! | `int64`, `uint32`, `uint64`           | `long`, `uint64` values above the `long` range produce an error |
+//! | `float`, `double`, `bool`             | `float`, `double`, `boolean`                       |
+//! | `decimal`                             | `bytes` with `decimal` logical type, the scale is the schema `exp` |
+//! | `date`                                | `long` with `timestamp-millis` logical type        |
+//! | `uuid`                                | `string` with `uuid` logical type                  |
+//! | `string`, `bytes`, `ulid`             | `string`, `bytes`, `bytes`                         |
+//! | `option`                              | `enum`, or `string` if a choice isn't a valid Avro name |
+//! | `geo`                                 | record with `lat` and `lng` doubles                |
+//!
+//! Record and enum names are built from the name passed in and the column names leading to them.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "uint8"}]
+//!     ]
+//! }"#)?;
+//!
+//! assert_eq!(factory.schema.to_avro_schema("User")?.stringify(), concat!(
+//!     r#"{"type":"record","name":"User","fields":["#,
+//!     r#"{"name":"name","type":["null","string"],"default":null},"#,
+//!     r#"{"name":"age","type":["null","int"],"default":null}"#,
+//!     r#"]}"#
+//! ));
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//!
+//! // name is the second union branch, age is null
+//! assert_eq!(new_buffer.avro_encode()?, vec![0x02, 0x06, b'J', b'e', b'b', 0x00]);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::owned::NP_Value_Owned;
+use crate::schema::NP_Parsed_Schema;
+use crate::pointer::option::NP_Enum;
+use crate::json_flex::{JSMAP, NP_JSON};
+use alloc::string::{String, ToString};
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+
+/// Turn a column name into part of a record name
+fn name_part(name: &str) -> String {
+    let mut ident = String::new();
+    let mut upper = true;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            ident.push(if upper { c.to_ascii_uppercase() } else { c });
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    ident
+}
+
+/// Avro names must match `[A-Za-z_][A-Za-z0-9_]*`
+fn is_avro_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false
+    }
+}
+
+fn field_name(name: &str) -> String {
+    let mut ident: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if is_avro_name(&ident) == false {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+fn type_json(avro_type: &str) -> NP_JSON {
+    NP_JSON::String(avro_type.to_owned())
+}
+
+fn logical_json(avro_type: &str, logical_type: &str) -> JSMAP {
+    let mut json = JSMAP::new();
+    json.insert("type".to_owned(), type_json(avro_type));
+    json.insert("logicalType".to_owned(), type_json(logical_type));
+    json
+}
+
+fn nullable(avro_schema: NP_JSON) -> NP_JSON {
+    NP_JSON::Array(alloc::vec![type_json("null"), avro_schema])
+}
+
+fn record_json(name: &str, fields: Vec<(String, NP_JSON)>, nullable_fields: bool) -> NP_JSON {
+    let mut json = JSMAP::new();
+    json.insert("type".to_owned(), type_json("record"));
+    json.insert("name".to_owned(), NP_JSON::String(name.to_owned()));
+    json.insert("fields".to_owned(), NP_JSON::Array(fields.into_iter().map(|(field, field_type)| {
+        let mut field_json = JSMAP::new();
+        field_json.insert("name".to_owned(), NP_JSON::String(field));
+        if nullable_fields {
+            field_json.insert("type".to_owned(), nullable(field_type));
+            field_json.insert("default".to_owned(), NP_JSON::Null);
+        } else {
+            field_json.insert("type".to_owned(), field_type);
+        }
+        NP_JSON::Dictionary(field_json)
+    }).collect()));
+    NP_JSON::Dictionary(json)
+}
+
+/// Get the enum symbols if every choice is a valid Avro name
+fn enum_symbols(choices: &Vec<NP_Enum>) -> Option<Vec<String>> {
+    let mut symbols = Vec::new();
+    for choice in choices {
+        if let NP_Enum::Some(x) = choice {
+            if is_avro_name(x) == false {
+                return None;
+            }
+            symbols.push(x.clone());
+        }
+    }
+    Some(symbols)
+}
+
+/// Convert the schema at `address` into an Avro schema, `name` is used for records and enums
+pub(crate) fn schema_to_avro(schema: &Vec<NP_Parsed_Schema>, address: usize, name: &str) -> Result<NP_JSON, NP_Error> {
+    Ok(match &schema[address] {
+        NP_Parsed_Schema::Int8 { .. } | NP_Parsed_Schema::Int16 { .. } | NP_Parsed_Schema::Int32 { .. } |
+        NP_Parsed_Schema::Uint8 { .. } | NP_Parsed_Schema::Uint16 { .. } => type_json("int"),
+        NP_Parsed_Schema::Int64 { .. } | NP_Parsed_Schema::Uint32 { .. } | NP_Parsed_Schema::Uint64 { .. } => type_json("long"),
+        NP_Parsed_Schema::Float { .. } => type_json("float"),
+        NP_Parsed_Schema::Double { .. } => type_json("double"),
+        NP_Parsed_Schema::Boolean { .. } => type_json("boolean"),
+        NP_Parsed_Schema::UTF8String { .. } => type_json("string"),
+        NP_Parsed_Schema::Bytes { .. } | NP_Parsed_Schema::Ulid { .. } => type_json("bytes"),
+        NP_Parsed_Schema::Uuid { .. } => NP_JSON::Dictionary(logical_json("string", "uuid")),
+        NP_Parsed_Schema::Date { .. } => NP_JSON::Dictionary(logical_json("long", "timestamp-millis")),
+        NP_Parsed_Schema::Decimal { exp, .. } => {
+            let mut json = logical_json("bytes", "decimal");
+            // every i64 fits in 19 digits
+            json.insert("precision".to_owned(), NP_JSON::Integer(19));
+            json.insert("scale".to_owned(), NP_JSON::Integer(*exp as i64));
+            NP_JSON::Dictionary(json)
+        },
+        NP_Parsed_Schema::Enum { choices, .. } => match enum_symbols(choices) {
+            Some(symbols) => {
+                let mut json = JSMAP::new();
+                json.insert("type".to_owned(), type_json("enum"));
+                json.insert("name".to_owned(), NP_JSON::String(name.to_owned()));
+                json.insert("symbols".to_owned(), NP_JSON::Array(symbols.into_iter().map(NP_JSON::String).collect()));
+                NP_JSON::Dictionary(json)
+            },
+            None => type_json("string")
+        },
+        NP_Parsed_Schema::Geo { .. } => {
+            record_json(name, alloc::vec![("lat".to_owned(), type_json("double")), ("lng".to_owned(), type_json("double"))], false)
+        },
+        NP_Parsed_Schema::Table { columns, .. } => {
+            let mut fields = Vec::new();
+            for (_index, column, addr) in columns {
+                let mut child_name = name.to_owned();
+                child_name.push_str(&name_part(column));
+                fields.push((field_name(column), schema_to_avro(schema, *addr, &child_name)?));
+            }
+            record_json(name, fields, true)
+        },
+        NP_Parsed_Schema::Tuple { values, .. } => {
+            let mut fields = Vec::new();
+            for (index, addr) in values.iter().enumerate() {
+                let mut field = String::from("value");
+                field.push_str(&index.to_string());
+                let mut child_name = name.to_owned();
+                child_name.push_str(&name_part(&field));
+                fields.push((field, schema_to_avro(schema, *addr, &child_name)?));
+            }
+            record_json(name, fields, true)
+        },
+        NP_Parsed_Schema::List { of, .. } => {
+            let mut json = JSMAP::new();
+            let mut child_name = name.to_owned();
+            child_name.push_str("Item");
+            json.insert("type".to_owned(), type_json("array"));
+            json.insert("items".to_owned(), nullable(schema_to_avro(schema, *of, &child_name)?));
+            NP_JSON::Dictionary(json)
+        },
+        NP_Parsed_Schema::Map { value, .. } => {
+            let mut json = JSMAP::new();
+            let mut child_name = name.to_owned();
+            child_name.push_str("Value");
+            json.insert("type".to_owned(), type_json("map"));
+            json.insert("values".to_owned(), schema_to_avro(schema, *value, &child_name)?);
+            NP_JSON::Dictionary(json)
+        },
+        NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::None => return Err(NP_Error::SchemaMismatch("Schema can't be represented in Avro: any types have no Avro equivalent".into()))
+    })
+}
+
+fn write_long(out: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        out.push((zigzag as u8) | 0x80);
+        zigzag >>= 7;
+    }
+    out.push(zigzag as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_long(out, bytes.len() as i64);
+    out.extend_from_slice(bytes);
+}
+
+/// Avro decimals are the shortest big endian two's complement bytes of the unscaled number
+fn decimal_bytes(num: i64) -> Vec<u8> {
+    let bytes = num.to_be_bytes();
+    let mut start = 0;
+    while start < 7 {
+        let redundant = (bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0) || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0);
+        if redundant == false {
+            break;
+        }
+        start += 1;
+    }
+    bytes[start..].to_vec()
+}
+
+fn encode_error(schema: &NP_Parsed_Schema) -> NP_Error {
+    let mut err = String::from("TypeError: Value doesn't match Avro schema of type (");
+    err.push_str(schema.get_type_data().0);
+    err.push_str(")");
+    NP_Error::SchemaMismatch(err)
+}
+
+/// Write a union of `null` and the schema at `address`
+fn encode_nullable(out: &mut Vec<u8>, value: &NP_Value_Owned, schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<(), NP_Error> {
+    if let NP_Value_Owned::Null = value {
+        write_long(out, 0);
+        Ok(())
+    } else {
+        write_long(out, 1);
+        encode(out, value, schema, address)
+    }
+}
+
+/// Write a value as Avro binary data for the schema at `address`
+pub(crate) fn encode(out: &mut Vec<u8>, value: &NP_Value_Owned, schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<(), NP_Error> {
+
+    let this_schema = &schema[address];
+
+    match (this_schema, value) {
+        (_, NP_Value_Owned::Int8(x)) => write_long(out, *x as i64),
+        (_, NP_Value_Owned::Int16(x)) => write_long(out, *x as i64),
+        (_, NP_Value_Owned::Int32(x)) => write_long(out, *x as i64),
+        (_, NP_Value_Owned::Int64(x)) => write_long(out, *x),
+        (_, NP_Value_Owned::Uint8(x)) => write_long(out, *x as i64),
+        (_, NP_Value_Owned::Uint16(x)) => write_long(out, *x as i64),
+        (_, NP_Value_Owned::Uint32(x)) => write_long(out, *x as i64),
+        (_, NP_Value_Owned::Uint64(x)) => {
+            if *x > i64::MAX as u64 {
+                return Err(NP_Error::InvalidValue("uint64 value is too large for an Avro long!".into()));
+            }
+            write_long(out, *x as i64)
+        },
+        (_, NP_Value_Owned::Float(x)) => out.extend_from_slice(&x.to_le_bytes()),
+        (_, NP_Value_Owned::Double(x)) => out.extend_from_slice(&x.to_le_bytes()),
+        (_, NP_Value_Owned::Bool(x)) => out.push(*x as u8),
+        (_, NP_Value_Owned::String(x)) => write_bytes(out, x.as_bytes()),
+        (_, NP_Value_Owned::Bytes(x)) => write_bytes(out, x),
+        (_, NP_Value_Owned::Ulid(x)) => write_bytes(out, x.as_bytes()),
+        (_, NP_Value_Owned::Uuid(x)) => write_bytes(out, x.to_string().as_bytes()),
+        (_, NP_Value_Owned::Date(x)) => write_long(out, x.value as i64),
+        (_, NP_Value_Owned::Dec(x)) => write_bytes(out, &decimal_bytes(x.num)),
+        (_, NP_Value_Owned::Geo(x)) => {
+            out.extend_from_slice(&x.lat.to_le_bytes());
+            out.extend_from_slice(&x.lng.to_le_bytes());
+        },
+        (NP_Parsed_Schema::Enum { choices, .. }, NP_Value_Owned::Enum(x)) => match enum_symbols(choices) {
+            Some(symbols) => match symbols.iter().position(|symbol| symbol == x) {
+                Some(index) => write_long(out, index as i64),
+                None => return Err(encode_error(this_schema))
+            },
+            None => write_bytes(out, x.as_bytes())
+        },
+        (NP_Parsed_Schema::Table { columns, .. }, NP_Value_Owned::Table(_)) | (NP_Parsed_Schema::Table { columns, .. }, NP_Value_Owned::Null) => {
+            let entries = match value {
+                NP_Value_Owned::Table(entries) => entries.as_slice(),
+                _ => &[]
+            };
+            for (_index, column, addr) in columns {
+                match entries.iter().find(|(key, _value)| key == column) {
+                    Some((_key, item)) => encode_nullable(out, item, schema, *addr)?,
+                    None => write_long(out, 0)
+                }
+            }
+        },
+        (NP_Parsed_Schema::Tuple { values, .. }, NP_Value_Owned::Tuple(_)) | (NP_Parsed_Schema::Tuple { values, .. }, NP_Value_Owned::Null) => {
+            let items = match value {
+                NP_Value_Owned::Tuple(items) => items.as_slice(),
+                _ => &[]
+            };
+            for (index, addr) in values.iter().enumerate() {
+                match items.get(index) {
+                    Some(item) => encode_nullable(out, item, schema, *addr)?,
+                    None => write_long(out, 0)
+                }
+            }
+        },
+        (NP_Parsed_Schema::List { of, .. }, NP_Value_Owned::List(items)) => {
+            if items.len() > 0 {
+                write_long(out, items.len() as i64);
+                for item in items {
+                    encode_nullable(out, item, schema, *of)?;
+                }
+            }
+            write_long(out, 0);
+        },
+        (NP_Parsed_Schema::Map { value: of, .. }, NP_Value_Owned::Map(entries)) => {
+            if entries.len() > 0 {
+                write_long(out, entries.len() as i64);
+                for (key, item) in entries {
+                    write_bytes(out, key.as_bytes());
+                    encode(out, item, schema, *of)?;
+                }
+            }
+            write_long(out, 0);
+        },
+        (NP_Parsed_Schema::List { .. }, NP_Value_Owned::Null) | (NP_Parsed_Schema::Map { .. }, NP_Value_Owned::Null) => write_long(out, 0),
+        _ => return Err(encode_error(this_schema))
+    }
+
+    Ok(())
+}
+
+#[test]
+fn avro_encoding_works() -> Result<(), NP_Error> {
+    use crate::pointer::{dec::NP_Dec, date::NP_Date, geo::NP_Geo, uuid::NP_UUID};
+
+    let factory = crate::NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id",     {"type": "uuid"}],
+            ["when",   {"type": "date"}],
+            ["where",  {"type": "geo4"}],
+            ["price",  {"type": "dec", "exp": 2}],
+            ["color",  {"type": "option", "choices": ["red", "blue"]}],
+            ["size",   {"type": "option", "choices": ["x-large", "small"]}],
+            ["pair",   {"type": "tuple", "values": [{"type": "i8"}, {"type": "bool"}]}],
+            ["scores", {"type": "list", "of": {"type": "u16"}}],
+            ["meta",   {"type": "map", "value": {"type": "string"}}]
+        ]
+    }"#)?;
+
+    assert_eq!(factory.schema.to_avro_schema("Order")?.stringify(), concat!(
+        r#"{"type":"record","name":"Order","fields":["#,
+        r#"{"name":"id","type":["null",{"type":"string","logicalType":"uuid"}],"default":null},"#,
+        r#"{"name":"when","type":["null",{"type":"long","logicalType":"timestamp-millis"}],"default":null},"#,
+        r#"{"name":"where","type":["null",{"type":"record","name":"OrderWhere","fields":[{"name":"lat","type":"double"},{"name":"lng","type":"double"}]}],"default":null},"#,
+        r#"{"name":"price","type":["null",{"type":"bytes","logicalType":"decimal","precision":19,"scale":2}],"default":null},"#,
+        r#"{"name":"color","type":["null",{"type":"enum","name":"OrderColor","symbols":["red","blue"]}],"default":null},"#,
+        r#"{"name":"size","type":["null","string"],"default":null},"#,
+        r#"{"name":"pair","type":["null",{"type":"record","name":"OrderPair","fields":[{"name":"value0","type":["null","int"],"default":null},{"name":"value1","type":["null","boolean"],"default":null}]}],"default":null},"#,
+        r#"{"name":"scores","type":["null",{"type":"array","items":["null","int"]}],"default":null},"#,
+        r#"{"name":"meta","type":["null",{"type":"map","values":"string"}],"default":null}"#,
+        r#"]}"#
+    ));
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["when"], NP_Date::new(1000))?;
+    buffer.set(&["price"], NP_Dec::new(-129, 2))?;
+    buffer.set(&["color"], crate::pointer::option::NP_Enum::new("blue"))?;
+    buffer.set(&["pair", "1"], true)?;
+    buffer.set(&["scores", "1"], 300u16)?;
+
+    assert_eq!(buffer.avro_encode()?, alloc::vec![
+        0x00,                      // id
+        0x02, 0xd0, 0x0f,          // when
+        0x00,                      // where
+        0x02, 0x04, 0xff, 0x7f,    // price
+        0x02, 0x02,                // color
+        0x00,                      // size
+        0x02, 0x00, 0x02, 0x01,    // pair
+        0x02, 0x04, 0x00, 0x02, 0xd8, 0x04, 0x00, // scores
+        0x00                       // meta
+    ]);
+
+    buffer.set(&["where"], NP_Geo::new(4, 1.0, 2.0))?;
+    buffer.set(&["id"], &NP_UUID::generate(2))?;
+    assert!(buffer.avro_encode()?.len() > 40);
+
+    Ok(())
+}
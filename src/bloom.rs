@@ -0,0 +1,235 @@
+//! Test if a map key or list value might be in a buffer without opening it
+//!
+//! Scanning many stored buffers for the few that have a given map key means opening every one of them.  `NP_Buffer::bloom_filter` hashes the keys of a map (or the values of a list) into a small set of bits that can be stored next to the buffer, readers check the bits first and only open buffers that might hold the key.
+//!
+//! Bloom filters have false positives but never false negatives: `might_contain` is always `true` for keys that were in the buffer, and is usually `false` for keys that weren't.  More bits make false positives less likely.
+//!
+//! Map keys are hashed as is.  List values of strings and bytes are hashed as they're read from the buffer, other list values are hashed in their [sortable encoding](../sortable/index.html).
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::bloom::NP_Bloom_Filter;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["scores", {"type": "map", "value": {"type": "u32"}}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["scores", "jeb"], 20u32)?;
+//! new_buffer.set(&["scores", "bill"], 30u32)?;
+//! new_buffer.set(&["tags", "0"], "pilot")?;
+//!
+//! let filter = new_buffer.bloom_filter(&["scores"], 64)?;
+//! assert!(filter.might_contain(b"jeb"));
+//! assert!(filter.might_contain(b"bill"));
+//!
+//! // store the filter bytes somewhere, then check them later
+//! let filter_bytes: Vec<u8> = filter.to_bytes();
+//! let filter = NP_Bloom_Filter::from_bytes(&filter_bytes)?;
+//! assert!(filter.might_contain(b"jeb"));
+//!
+//! let tags = new_buffer.bloom_filter(&["tags"], 64)?;
+//! assert!(tags.might_contain(b"pilot"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::collection::list::NP_List;
+use crate::collection::map::NP_Map;
+use crate::error::NP_Error;
+use crate::hashmap::murmurhash3_x86_32;
+use crate::memory::NP_Memory;
+use crate::pointer::{NP_Cursor, NP_Value};
+use crate::pointer::bytes::NP_Bytes;
+use crate::pointer::string::NP_String;
+use crate::schema::NP_Parsed_Schema;
+use crate::sortable;
+use alloc::vec::Vec;
+
+/// Most hashes set for each key
+const BLOOM_MAX_HASHES: usize = 16;
+
+/// A set of bits that might contain the keys of a map or values of a list
+///
+/// Check out documentation [here](../bloom/index.html).
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NP_Bloom_Filter {
+    hashes: u8,
+    bits: Vec<u8>
+}
+
+impl NP_Bloom_Filter {
+
+    /// Hash every key of the map or value of the list at `cursor` into `bits` bits
+    pub(crate) fn build(cursor: Option<NP_Cursor>, schema_addr: usize, bits: usize, memory: &NP_Memory) -> Result<Self, NP_Error> {
+        if bits == 0 {
+            return Err(NP_Error::InvalidValue("Bloom filters need at least one bit!".into()));
+        }
+
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+
+        let is_set = |child: &NP_Cursor| child.get_value(memory).get_addr_value() != 0;
+
+        match &memory.schema[schema_addr] {
+            NP_Parsed_Schema::Map { .. } => {
+                if let Some(cursor) = cursor.filter(is_set) {
+                    let mut map_iter = NP_Map::new_iter(&cursor, memory);
+                    while let Some((key, item)) = map_iter.step_iter(memory) {
+                        if is_set(&item) {
+                            keys.push(key.as_bytes().to_vec());
+                        }
+                    }
+                }
+            },
+            NP_Parsed_Schema::List { of, .. } => {
+                match &memory.schema[*of] {
+                    NP_Parsed_Schema::None | NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } => {
+                        return Err(NP_Error::SchemaMismatch("Bloom filters need a map or a list of scalars!".into()));
+                    },
+                    _ => { }
+                }
+                if let Some(cursor) = cursor.filter(is_set) {
+                    let mut list_iter = NP_List::new_iter(&cursor, memory, true, 0);
+                    while let Some((_index, item)) = list_iter.step_iter(memory) {
+                        let item = match item {
+                            Some(x) if is_set(&x) => x,
+                            _ => continue
+                        };
+                        keys.push(match &memory.schema[*of] {
+                            NP_Parsed_Schema::UTF8String { .. } => NP_String::into_value(&item, memory)?.unwrap_or("").as_bytes().to_vec(),
+                            NP_Parsed_Schema::Bytes { .. } => NP_Bytes::into_value(&item, memory)?.unwrap_or(&[]).to_vec(),
+                            _ => {
+                                let mut key: Vec<u8> = Vec::new();
+                                sortable::encode(Some(item), *of, memory, &mut key)?;
+                                key
+                            }
+                        });
+                    }
+                }
+            },
+            _ => return Err(NP_Error::SchemaMismatch("Bloom filters need a map or a list of scalars!".into()))
+        }
+
+        // about `ln(2) * bits / keys` hashes keeps false positives lowest
+        let bits = ((bits + 7) / 8) * 8;
+        let hashes = match keys.len() {
+            0 => 1,
+            count => (bits * 693 / (count * 1000)).max(1).min(BLOOM_MAX_HASHES)
+        };
+
+        let mut filter = NP_Bloom_Filter { hashes: hashes as u8, bits: alloc::vec![0u8; bits / 8] };
+        for key in keys.iter() {
+            filter.insert(key);
+        }
+        Ok(filter)
+    }
+
+    /// Bit positions for a key, double hashing from two murmur hashes
+    fn positions(&self, key: &[u8]) -> impl Iterator<Item = usize> {
+        let bits = self.bits.len() * 8;
+        let first = murmurhash3_x86_32(key, 0) as usize;
+        let second = murmurhash3_x86_32(key, first as u32) as usize | 1;
+        (0..(self.hashes as usize)).map(move |x| first.wrapping_add(x.wrapping_mul(second)) % bits)
+    }
+
+    fn insert(&mut self, key: &[u8]) {
+        let positions: Vec<usize> = self.positions(key).collect();
+        for bit in positions {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Might this key be in the map or list?  Never `false` for keys that are.
+    pub fn might_contain(&self, key: &[u8]) -> bool {
+        self.positions(key).all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    /// How many bits are in the filter
+    pub fn bits(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// How many bits are set for each key
+    pub fn hashes(&self) -> usize {
+        self.hashes as usize
+    }
+
+    /// Get the bytes of this filter to store them
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.bits.len() + 1);
+        bytes.push(self.hashes);
+        bytes.extend_from_slice(&self.bits);
+        bytes
+    }
+
+    /// Open a filter from bytes made by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NP_Error> {
+        match bytes.split_first() {
+            Some((hashes, bits)) if *hashes > 0 && (*hashes as usize) <= BLOOM_MAX_HASHES && bits.len() > 0 => {
+                Ok(NP_Bloom_Filter { hashes: *hashes, bits: bits.to_vec() })
+            },
+            _ => Err(NP_Error::Damaged("Bytes are not a bloom filter!".into()))
+        }
+    }
+}
+
+#[test]
+fn bloom_filters_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use alloc::string::ToString;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["words", {"type": "map", "value": {"type": "bool"}}],
+            ["ids", {"type": "list", "of": {"type": "u16"}}],
+            ["users", {"type": "list", "of": {"type": "table", "columns": [["id", {"type": "u16"}]]}}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+
+    // missing collections make empty filters
+    let empty = buffer.bloom_filter(&["words"], 10)?;
+    assert_eq!(empty.bits(), 16);
+    assert!(!empty.might_contain(b"anything"));
+
+    for x in 0..100u16 {
+        buffer.set(&["words", x.to_string().as_str()], true)?;
+        buffer.set(&["ids", x.to_string().as_str()], x * 3)?;
+    }
+
+    let words = buffer.bloom_filter(&["words"], 1024)?;
+    assert_eq!(words.hashes(), 7);
+    let mut false_positives = 0;
+    for x in 0..1000u16 {
+        let found = words.might_contain(x.to_string().as_bytes());
+        if x < 100 {
+            assert!(found);
+        } else if found {
+            false_positives += 1;
+        }
+    }
+    assert!(false_positives < 50);
+
+    let ids = buffer.bloom_filter(&["ids"], 1024)?;
+    assert!(ids.might_contain(&297u16.to_be_bytes()));
+    assert!(ids.might_contain(&buffer.sortable_bytes(&["ids", "5"])?));
+
+    assert_eq!(NP_Bloom_Filter::from_bytes(&ids.to_bytes())?, ids);
+    assert!(NP_Bloom_Filter::from_bytes(&[3]).is_err());
+    assert!(NP_Bloom_Filter::from_bytes(&[]).is_err());
+
+    assert!(buffer.bloom_filter(&["users"], 64).is_err());
+    assert!(buffer.bloom_filter(&["words"], 0).is_err());
+    assert!(buffer.bloom_filter(&["names"], 64).is_err());
+
+    Ok(())
+}
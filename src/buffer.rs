@@ -6,21 +6,39 @@ use crate::collection::tuple::NP_Tuple;
 use crate::{pointer::{NP_Scalar}};
 use crate::{collection::map::NP_Map};
 use crate::{pointer::NP_Value};
-use crate::pointer::NP_Cursor;
-use crate::{schema::NP_Parsed_Schema, collection::table::NP_Table};
+use crate::pointer::{NP_Cursor, NP_NULL_ADDR, NP_TOMBSTONE_ADDR};
+use crate::{schema::{NP_Parsed_Schema, NP_TypeKeys}, collection::table::NP_Table};
 use alloc::vec::Vec;
+use alloc::boxed::Box;
 use crate::{collection::{list::NP_List}};
 use crate::error::NP_Error;
-use crate::memory::{NP_Memory};
-use crate::{json_flex::NP_JSON};
+use crate::memory::{NP_Memory, NP_HEADER_FINGERPRINT, header_version};
+use crate::{json_flex::{NP_JSON, NP_JSON_Options}};
 use crate::alloc::borrow::ToOwned;
+use crate::owned::NP_Value_Owned;
+use crate::validation::{NP_Validation_Error, NP_Constraints};
+use crate::integrity::NP_Integrity_Report;
+use crate::path::{NP_Path, NP_Path_Step};
+use crate::sortable;
+use crate::index::NP_Index;
+use crate::bloom::NP_Bloom_Filter;
+use crate::sync::NP_Buffer_Sync;
+use crate::finished::{NP_Finished_Buffer, NP_Freeze_Options};
+use crate::projection::NP_Projection;
+use crate::pointer::{any::NP_AnyValue, dec::NP_Dec, geo::NP_Geo, date::NP_Date, option::NP_Enum, uuid::NP_UUID, numbers::{NP_Coerce, increment_value}};
+use alloc::string::{String, ToString};
+use core::convert::TryFrom;
+use core::cmp::Ordering;
 
-/// The address location of the root pointer.
+/// The address location of the root pointer in buffers without a header.
 #[doc(hidden)]
 pub const ROOT_PTR_ADDR: usize = 1;
 /// Maximum size of list collections
 #[doc(hidden)]
 pub const LIST_MAX_SIZE: usize = core::u16::MAX as usize;
+/// Bytes written at a time by `write_to`
+#[cfg(feature = "std")]
+const WRITE_CHUNK_SIZE: usize = 8192;
 
 /// Buffers contain the bytes of each object and allow you to perform reads, updates, deletes and compaction.
 /// 
@@ -30,7 +48,29 @@ pub struct NP_Buffer<'buffer> {
     memory: NP_Memory<'buffer>,
     cursor: NP_Cursor,
     sortable: bool,
-    backup_cursor: NP_Cursor
+    backup_cursor: NP_Cursor,
+    journal: Option<NP_Journal>,
+    observers: Vec<NP_Observer>
+}
+
+/// Callback registered with `NP_Buffer::on_change`, `'static` so observers don't keep borrows alive until the buffer is dropped
+type NP_Observer = Box<dyn FnMut(&[&str], &NP_Value_Owned, &NP_Value_Owned) + Send>;
+
+/// A change recorded by the journal of a buffer, see `NP_Buffer::start_journal`
+#[derive(Debug, Clone)]
+pub struct NP_Change {
+    /// Path to the value that changed, relative to the buffer cursor at the time of the change.  Changes that made new list or map items have the path of the list or map instead.
+    pub path: Vec<String>,
+    /// Copy of the value before the change, `NP_Value_Owned::Null` if there wasn't one
+    pub old: NP_Value_Owned,
+    cursor: NP_Cursor
+}
+
+/// Changes that can be undone and redone, newest last
+#[derive(Debug, Clone, Default)]
+struct NP_Journal {
+    undo: Vec<NP_Change>,
+    redo: Vec<NP_Change>
 }
 
 /// When calling `maybe_compact` on a buffer, this struct is provided to help make a choice on wether to compact or not.
@@ -44,17 +84,138 @@ pub struct NP_Size_Data {
     pub wasted_bytes: usize
 }
 
+/// Where the live and dead bytes of a buffer are, provided by `frag_stats`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NP_Frag_Stats {
+    /// The size of the existing buffer
+    pub total_bytes: usize,
+    /// Bytes used by the header, the root pointer and every value, vtable and item that can be reached from the root
+    pub live_bytes: usize,
+    /// Bytes that can't be reached anymore, like old values that were overwritten or deleted
+    pub dead_bytes: usize,
+    /// How many separate runs of dead bytes there are
+    pub dead_regions: usize,
+    /// Length of the longest run of dead bytes
+    pub largest_dead_region: usize
+}
+
+/// How `fold_numeric` combines the values it reads
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NP_Fold {
+    /// Add every value together
+    Sum,
+    /// Smallest value
+    Min,
+    /// Largest value
+    Max,
+    /// Mean of the values
+    Avg
+}
+
+/// What `del` leaves behind and how long compaction keeps it, set with `set_tombstones`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NP_Tombstones {
+    /// Deleted values are cleared like they were never set (the default)
+    Off,
+    /// Deleted values leave a tombstone until the next `compact` or `canonicalize`
+    UntilCompaction,
+    /// Deleted values leave a tombstone that compaction keeps, until a new value is set in it's place
+    Keep
+}
+
+/// Bytes used by a value and it's descendants, provided by `calc_bytes_report`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NP_Size_Report {
+    /// Column name, map key or list/tuple index of the value, empty for the root
+    pub key: String,
+    /// Bytes used by the value's pointer, data and descendants
+    pub bytes: usize,
+    /// Known wasted bytes, these can't be traced to a value so they're only counted on the root
+    pub wasted_bytes: usize,
+    /// Reports for every value set inside this collection
+    pub children: Vec<NP_Size_Report>
+}
+
+impl NP_Size_Report {
+
+    /// Get the report for a value below this one
+    pub fn find(&self, path: &[&str]) -> Option<&NP_Size_Report> {
+        let mut report = self;
+        for key in path {
+            report = report.children.iter().find(|child| child.key == *key)?;
+        }
+        Some(report)
+    }
+
+    fn walk(key: String, cursor: &NP_Cursor, memory: &NP_Memory) -> Result<Self, NP_Error> {
+
+        let bytes = NP_Cursor::calc_size(cursor, memory)?;
+        let mut children: Vec<NP_Size_Report> = Vec::new();
+
+        let is_set = |child: &NP_Cursor| child.get_value(memory).get_addr_value() != 0;
+
+        if is_set(cursor) {
+            match &memory.schema[cursor.schema_addr] {
+                NP_Parsed_Schema::Table { .. } => {
+                    let mut table_iter = NP_Table::new_iter(cursor, memory);
+                    while let Some((_index, key, item)) = table_iter.step_iter(memory) {
+                        if let Some(child) = item {
+                            if is_set(&child) {
+                                children.push(Self::walk(key.to_owned(), &child, memory)?);
+                            }
+                        }
+                    }
+                },
+                NP_Parsed_Schema::Tuple { .. } => {
+                    let mut tuple_iter = NP_Tuple::new_iter(cursor, memory);
+                    while let Some((index, item)) = tuple_iter.step_iter(memory) {
+                        if let Some(child) = item {
+                            if is_set(&child) {
+                                children.push(Self::walk(index.to_string(), &child, memory)?);
+                            }
+                        }
+                    }
+                },
+                NP_Parsed_Schema::List { .. } => {
+                    let mut list_iter = NP_List::new_iter(cursor, memory, true, 0);
+                    while let Some((index, item)) = list_iter.step_iter(memory) {
+                        if let Some(child) = item {
+                            if is_set(&child) {
+                                children.push(Self::walk(index.to_string(), &child, memory)?);
+                            }
+                        }
+                    }
+                },
+                NP_Parsed_Schema::Map { .. } => {
+                    let mut map_iter = NP_Map::new_iter(cursor, memory);
+                    while let Some((key, child)) = map_iter.step_iter(memory) {
+                        if is_set(&child) {
+                            children.push(Self::walk(key.to_owned(), &child, memory)?);
+                        }
+                    }
+                },
+                _ => { }
+            }
+        }
+
+        Ok(NP_Size_Report { key, bytes, wasted_bytes: 0, children })
+    }
+}
+
 impl<'buffer> NP_Buffer<'buffer> {
 
     #[doc(hidden)]
     pub fn _new(memory: NP_Memory<'buffer>) -> Self { // make new buffer
 
         let mut is_sortable: bool = false;
+        let root = memory.root;
         // is the root a sortable tuple?  if so, create its children and vtables
         match memory.schema[0] {
             NP_Parsed_Schema::Tuple { sortable, .. } => {
                 if sortable {
-                    NP_Tuple::select(NP_Cursor::new(ROOT_PTR_ADDR, 0, 0), 0, true, &memory).unwrap_or(None);
+                    if !memory.is_read_only() {
+                        NP_Tuple::select(NP_Cursor::new(root, 0, 0), 0, true, &memory).unwrap_or(None);
+                    }
                     is_sortable = true;
                 }
             },
@@ -62,16 +223,20 @@ impl<'buffer> NP_Buffer<'buffer> {
         };
 
         NP_Buffer {
-            cursor: NP_Cursor::new(ROOT_PTR_ADDR, 0, 0),
+            cursor: NP_Cursor::new(root, 0, 0),
             memory: memory,
             sortable: is_sortable,
-            backup_cursor: NP_Cursor::new(ROOT_PTR_ADDR, 0, 0)
+            backup_cursor: NP_Cursor::new(root, 0, 0),
+            journal: None,
+            observers: Vec::new()
         }
     }
 
 
     /// Copy an object at the provided path and all it's children into JSON.
     /// 
+    /// The path is relative to the buffer cursor and can point at any value, including a single list item or a nested collection.  Only the value at the path and it's children are copied, the rest of the buffer isn't touched.
+    /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
@@ -81,16 +246,22 @@ impl<'buffer> NP_Buffer<'buffer> {
     ///    "type": "table",
     ///    "columns": [
     ///         ["age", {"type": "uint8"}],
-    ///         ["name", {"type": "string"}]
+    ///         ["name", {"type": "string"}],
+    ///         ["friends", {"type": "list", "of": {"type": "table", "columns": [
+    ///             ["name", {"type": "string"}]
+    ///         ]}}]
     ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
     /// new_buffer.set(&["name"], "Jeb Kermin");
     /// new_buffer.set(&["age"], 30u8);
+    /// new_buffer.set(&["friends", "0", "name"], "Bill Kerman");
     /// 
-    /// assert_eq!("{\"age\":30,\"name\":\"Jeb Kermin\"}", new_buffer.json_encode(&[])?.stringify());
+    /// assert_eq!("{\"age\":30,\"name\":\"Jeb Kermin\",\"friends\":[{\"name\":\"Bill Kerman\"}]}", new_buffer.json_encode(&[])?.stringify());
     /// assert_eq!("\"Jeb Kermin\"", new_buffer.json_encode(&["name"])?.stringify());
+    /// // export a single list item
+    /// assert_eq!("{\"name\":\"Bill Kerman\"}", new_buffer.json_encode(&["friends", "0"])?.stringify());
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
@@ -107,962 +278,4004 @@ impl<'buffer> NP_Buffer<'buffer> {
 
     }
 
-    /// Moves the underlying bytes out of the buffer, consuming the buffer in the process.
+    /// Copy an object at the provided path and all it's children into JSON, using the provided options.
+    /// 
+    /// When `emit_defaults` is `false` only values that have been set in the buffer are included, default values from the schema are left out.  Values set with `set_null` are still included as `null`, so the only nulls left are the intentional ones.  Pass the same options to `stringify_with` to control the formatting.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use no_proto::json_flex::NP_JSON_Options;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "string"
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["age", {"type": "uint8", "default": 20}],
+    ///         ["name", {"type": "string"}]
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set initial value
-    /// new_buffer.set(&[], "hello")?;
-    /// // close buffer and get bytes
-    /// let bytes: Vec<u8> = new_buffer.close();
-    /// assert_eq!([0, 0, 3, 0, 5, 104, 101, 108, 108, 111].to_vec(), bytes);
+    /// new_buffer.set(&["name"], "Jeb Kermin");
+    /// 
+    /// let options = NP_JSON_Options { emit_defaults: false, indent: 2, ..Default::default() };
+    /// 
+    /// assert_eq!("{\"age\":20,\"name\":\"Jeb Kermin\"}", new_buffer.json_encode(&[])?.stringify());
+    /// assert_eq!("{\n  \"name\": \"Jeb Kermin\"\n}", new_buffer.json_encode_with(&[], &options)?.stringify_with(&options));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn close(self) -> Vec<u8> {
-        self.memory.dump()
+    pub fn json_encode_with(&self, path: &[&str], options: &NP_JSON_Options) -> Result<NP_JSON, NP_Error> {
+
+        if options.emit_defaults {
+            return self.json_encode(path);
+        }
+
+        match self.select(self.cursor.clone(), false, path)? {
+            Some(x) => Ok(NP_Value_Owned::from_cursor_with_nulls(&x, &self.memory)?.to_json()),
+            None => Ok(NP_JSON::Null)
+        }
     }
 
-    /// If the buffer is sortable, this provides only the sortable elements of the buffer.
-    /// There is typically 10 bytes or more in front of the buffer that are identical between all the sortable buffers for a given schema.
+    /// Copy an object at the provided path and all it's children into JSON, with every value marked `"sensitive": true` in the schema replaced by `"***"`.
     /// 
-    /// This calculates how many leading identical bytes there are and returns only the bytes following them.  This allows your sortable buffers to be only as large as they need to be.
-    /// 
-    /// This operation fails if the buffer is not sortable.
+    /// Everything inside a sensitive table, list, map or tuple is masked with it, and values that aren't set stay `null`.  Use this to log or dump buffers that hold personal data.  Sensitive markers aren't part of compiled schemas, buffers from factories created with `NP_Factory::new_compiled` aren't redacted.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "tuple",
-    ///    "sorted": true,
-    ///    "values": [
-    ///         {"type": "u8"},
-    ///         {"type": "string", "size": 6}
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["email", {"type": "string", "sensitive": true}],
+    ///         ["cards", {"type": "list", "of": {"type": "u64", "sensitive": true}}],
+    ///         ["phone", {"type": "string", "sensitive": true}]
     ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set initial value
-    /// new_buffer.set(&["0"], 55u8)?;
-    /// new_buffer.set(&["1"], "hello")?;
-    /// 
-    /// // the buffer with it's vtables take up 20 bytes!
-    /// assert_eq!(new_buffer.read_bytes().len(), 20usize);
-    /// 
-    /// // close buffer and get sortable bytes
-    /// let bytes: Vec<u8> = new_buffer.close_sortable()?;
-    /// // with close_sortable() we only get the bytes we care about!
-    /// assert_eq!([55, 104, 101, 108, 108, 111, 32].to_vec(), bytes);
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
+    /// new_buffer.set(&["email"], "jeb@kerbal.space")?;
+    /// new_buffer.set(&["cards", "0"], 4111111111111111u64)?;
     /// 
-    /// // you can always re open the sortable buffers with this call
-    /// let new_buffer = factory.open_sortable_buffer(bytes)?;
-    /// assert_eq!(new_buffer.get(&["0"])?, Some(55u8));
-    /// assert_eq!(new_buffer.get(&["1"])?, Some("hello "));
+    /// assert_eq!("{\"name\":\"Jeb Kermin\",\"email\":\"***\",\"cards\":[\"***\"],\"phone\":null}", new_buffer.json_encode_redacted(&[])?.stringify());
+    /// assert_eq!("\"***\"", new_buffer.json_encode_redacted(&["email"])?.stringify());
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn close_sortable(self) -> Result<Vec<u8>, NP_Error> {
-        match &self.memory.schema[0] {
-            NP_Parsed_Schema::Tuple { values, sortable, .. } => {
-                if *sortable == false {
-                    Err(NP_Error::new("Attempted to close_sortable() on buffer that isn't sortable!"))
-                } else {
-                    let mut vtables = 1usize;
-                    let mut length = values.len();
-                    while length > 4 {
-                        vtables +=1;
-                        length -= 4;
-                    }
-                    let root_offset = ROOT_PTR_ADDR + 2 + (vtables * 10);
+    pub fn json_encode_redacted(&self, path: &[&str]) -> Result<NP_JSON, NP_Error> {
 
-                    let closed_vec = self.memory.dump();
-                    
-                    Ok(closed_vec[root_offset..].to_vec())
-                }
-            },
-            _ => Err(NP_Error::new("Attempted to close_sortable() on buffer that isn't sortable!"))
+        let mut json = self.json_encode(path)?;
+
+        if let Some(schema_addr) = self.schema_addr_at(path) {
+            // a sensitive parent masks the value even when the path points inside it
+            let address = (0..path.len())
+                .filter_map(|x| self.schema_addr_at(&path[..x]))
+                .find(|addr| NP_Constraints::is_sensitive(self.memory.constraints, *addr))
+                .unwrap_or(schema_addr);
+            NP_Constraints::redact(self.memory.schema, self.memory.constraints, address, &mut json);
         }
-    }
 
-    /// Read the bytes of the buffer immutably.  No touching!
-    /// 
-    pub fn read_bytes(&self) -> &Vec<u8> {
-        self.memory.read_bytes()
+        Ok(json)
     }
 
-    /// Move buffer cursor to new location.  Cursors can only be moved into children.  If you need to move up reset the cursor to root, then move back down to the desired level.
+    /// Stream each item of the list at the provided path into a writer as newline delimited JSON, one item per line.
     /// 
-    /// This also creates objects/collections along the path as needed.  If you attempt to move into a path that doesn't exist, this method will return `false`.  Otherwise it will return `true` of the path requested exists or is something that can be made to exist.
+    /// Items are encoded one at a time so large lists can be exported without building the whole JSON tree in memory.  Empty list slots are written as `null` lines so line numbers match list indexes.
     /// 
-    pub fn move_cursor(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
-
-        let value_cursor = self.select(self.cursor.clone(), true, path)?;
+    /// Returns how many lines were written, an unset list writes nothing.  This method requires the `std` feature.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "table", "columns": [["id", {"type": "uint32"}]]}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["0", "id"], 10u32)?;
+    /// new_buffer.set(&["2", "id"], 30u32)?;
+    /// 
+    /// let mut output: Vec<u8> = Vec::new();
+    /// 
+    /// assert_eq!(3, new_buffer.json_lines(&[], &mut output)?);
+    /// assert_eq!("{\"id\":10}\nnull\n{\"id\":30}\n", String::from_utf8(output)?);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    #[cfg(feature = "std")]
+    pub fn json_lines<W: std::io::Write>(&self, path: &[&str], writer: &mut W) -> Result<usize, NP_Error> {
 
-        let cursor = if let Some(x) = value_cursor {
-            x
-        } else {
-            return Ok(false);
+        let list_cursor = match self.select(self.cursor.clone(), false, path)? {
+            Some(x) => x,
+            None => return Ok(0)
         };
 
-        self.cursor = cursor;
+        match self.memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { .. } => { },
+            _ => return Err(NP_Error::SchemaMismatch("Trying to export json lines from non list item!".into()))
+        }
 
-        Ok(true)
-    }
+        if list_cursor.get_value(&self.memory).get_addr_value() == 0 {
+            return Ok(0)
+        }
 
-    /// Backup the current cursor's location
-    /// 
-    pub fn backup_cursor(&mut self) {
-        self.backup_cursor = self.cursor.clone();
-    }
+        let mut lines = 0usize;
 
-    /// Restore the backed up cursor location
-    /// 
-    pub fn restore_cursor(&mut self) {
-        self.cursor = self.backup_cursor.clone();
-    }
+        let mut list_iter = NP_List::new_iter(&list_cursor, &self.memory, false, 0);
 
-    /// Moves cursor position to root of buffer, the default.
-    /// 
-    pub fn cursor_to_root(&mut self) {
-        self.cursor = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
+        while let Some((_index, item)) = NP_List::step_iter(&mut list_iter, &self.memory) {
+            let json = match &item {
+                Some(item_cursor) => NP_Cursor::json_encode(item_cursor, &self.memory),
+                None => NP_JSON::Null
+            };
+            writer.write_all(json.stringify().as_bytes())?;
+            writer.write_all(b"\n")?;
+            lines += 1;
+        }
+
+        Ok(lines)
     }
 
-    /// Used to set scalar values inside the buffer.
+    /// Write the list of tables at the provided path into a writer as CSV, starting with a header row of column names.
     /// 
-    /// The type that you cast the request to will be compared to the schema, if it doesn't match the schema the request will fail.
+    /// Nested tables are flattened into `parent.child` columns and geo values are split into `.lat` and `.lng` columns.  Decimals are written with their exponent (`1.50`), dates as ISO 8601 UTC timestamps and uuids in their dashed string format.  Cells with commas, quotes or line breaks are quoted, values that haven't been set and empty list slots are written as empty cells.
+    /// 
+    /// Returns how many rows were written, not counting the header.  This method requires the `std` feature.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use no_proto::pointer::dec::NP_Dec;
     /// 
-    /// // a list where each item is a map where each key has a value containing a list of strings
     /// let factory: NP_Factory = NP_Factory::new(r#"{
     ///    "type": "list",
-    ///    "of": {"type": "map", "value": {
-    ///         "type": "list", "of": {"type": "string"}
-    ///     }}
+    ///    "of": {"type": "table", "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["price", {"type": "dec", "exp": 2}]
+    ///     ]}
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // third item in the top level list -> key "alpha" of map at 3rd element -> 9th element of list at "alpha" key
-    /// // 
-    /// new_buffer.set(&["3", "alpha", "9"], "look at all this nesting madness")?;
+    /// new_buffer.set(&["0", "name"], "Widget, large")?;
+    /// new_buffer.set(&["0", "price"], NP_Dec::new(1050, 2))?;
+    /// new_buffer.set(&["1", "name"], "Gear")?;
     /// 
-    /// // get the same item we just set
-    /// let message = new_buffer.get::<&str>(&["3", "alpha", "9"])?;
+    /// let mut output: Vec<u8> = Vec::new();
     /// 
-    /// assert_eq!(message, Some("look at all this nesting madness"));
+    /// assert_eq!(2, new_buffer.to_csv(&[], &mut output)?);
+    /// assert_eq!("name,price\n\"Widget, large\",10.50\nGear,\n", String::from_utf8(output)?);
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn set<X: 'buffer>(&mut self, path: &[&str], value: X) -> Result<bool, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
-        let value_cursor = self.select(self.cursor.clone(), true, path)?;
-        match value_cursor {
-            Some(x) => {
+    #[cfg(feature = "std")]
+    pub fn to_csv<W: std::io::Write>(&self, path: &[&str], writer: &mut W) -> Result<usize, NP_Error> {
 
-                // type does not match schema
-                if X::type_idx().1 != *self.memory.schema[x.schema_addr].get_type_key() {
-                    let mut err = "TypeError: Attempted to set value for type (".to_owned();
-                    err.push_str(X::type_idx().0);
-                    err.push_str(") into schema of type (");
-                    err.push_str(self.memory.schema[x.schema_addr].get_type_data().0);
-                    err.push_str(")\n");
-                    return Err(NP_Error::new(err));
-                }
+        let list_cursor = match self.select(self.cursor.clone(), false, path)? {
+            Some(x) => x,
+            None => return Err(NP_Error::BadPath("Can't find list to export as CSV!".into()))
+        };
 
-                X::set_value(x, &self.memory, value)?;
-                Ok(true)
+        let of = match self.memory.schema[list_cursor.schema_addr] {
+            NP_Parsed_Schema::List { of, .. } => of,
+            _ => return Err(NP_Error::SchemaMismatch("Trying to export CSV from non list item!".into()))
+        };
+
+        let mut columns = Vec::new();
+        crate::csv::columns(&self.memory.schema, of, "", &mut Vec::new(), &mut columns)?;
+
+        let mut line = String::new();
+        for (index, column) in columns.iter().enumerate() {
+            if index > 0 {
+                line.push(',');
             }
-            None => Ok(false)
+            crate::csv::escape(&column.name, &mut line);
+        }
+        line.push('\n');
+        writer.write_all(line.as_bytes())?;
+
+        if list_cursor.get_value(&self.memory).get_addr_value() == 0 {
+            return Ok(0)
+        }
+
+        let mut rows = 0usize;
+
+        let mut list_iter = NP_List::new_iter(&list_cursor, &self.memory, false, 0);
+
+        while let Some((_index, item)) = NP_List::step_iter(&mut list_iter, &self.memory) {
+            let row = match &item {
+                Some(item_cursor) => Some(NP_Value_Owned::from_cursor(item_cursor, &self.memory)?),
+                None => None
+            };
+            line.clear();
+            crate::csv::write_row(row.as_ref(), &columns, &mut line);
+            writer.write_all(line.as_bytes())?;
+            rows += 1;
         }
+
+        Ok(rows)
     }
 
-    
-    /// Get an iterator for a collection
+    /// Copy the value at the buffer cursor and all it's children into an owned value tree.
+    ///
+    /// Unlike `json_encode`, types like uuid, decimal and date keep their own variants and default values from the schema aren't included.
+    ///
+    /// Check out documentation [here](../owned/index.html).
+    ///
+    pub fn to_owned_tree(&self) -> Result<NP_Value_Owned, NP_Error> {
+        NP_Value_Owned::from_cursor(&self.cursor, &self.memory)
+    }
+
+    /// Copy the whole buffer into an owned value tree, no matter where the cursor is
+    pub(crate) fn root_owned_tree(&self) -> Result<NP_Value_Owned, NP_Error> {
+        NP_Value_Owned::from_cursor(&NP_Cursor::new(self.memory.root, 0, 0), &self.memory)
+    }
+
+    /// Write an owned value tree into the buffer at the buffer cursor.
+    ///
+    /// Every value in the tree is set into the buffer, `Null` values delete whatever is at their location.  If a value doesn't match the schema an error is returned; values set before the error stay in the buffer.
+    ///
+    /// Check out documentation [here](../owned/index.html).
+    ///
+    pub fn from_owned_tree(&mut self, tree: &NP_Value_Owned) -> Result<(), NP_Error> {
+        if self.is_watched() {
+            return self.journaled(&[], |buffer| buffer.from_owned_tree(tree));
+        }
+        tree.write_to(self, &mut Vec::new())
+    }
+
+    /// Copy the values at the paths of a projection into a new buffer of the projection's smaller schema.
+    ///
+    /// Check out documentation [here](../projection/index.html).
+    ///
+    pub fn project<'projection>(&self, projection: &'projection NP_Projection) -> Result<NP_Buffer<'projection>, NP_Error> {
+        projection.project(self)
+    }
+
+    /// Export the value at the buffer cursor and all it's children as MessagePack.
     /// 
+    /// Only values that have been set are exported, default values from the schema are left out.  This method requires the `msgpack` feature.
+    /// 
+    /// Check out documentation [here](../msgpack/index.html).
+    /// 
+    #[cfg(feature = "msgpack")]
+    pub fn msgpack_encode(&self) -> Result<Vec<u8>, NP_Error> {
+        let mut bytes = Vec::new();
+        crate::msgpack::encode(&self.to_owned_tree()?, &mut bytes);
+        Ok(bytes)
+    }
+
+    /// Export the value at the buffer cursor and all it's children as CBOR.
+    /// 
+    /// Only values that have been set are exported, default values from the schema are left out.  This method requires the `cbor` feature.
+    /// 
+    /// Check out documentation [here](../cbor/index.html).
+    /// 
+    #[cfg(feature = "cbor")]
+    pub fn cbor_encode(&self) -> Result<Vec<u8>, NP_Error> {
+        let mut bytes = Vec::new();
+        crate::cbor::encode(&self.to_owned_tree()?, &mut bytes);
+        Ok(bytes)
+    }
+
+    /// Export the table at the buffer cursor and all it's children as a protobuf message.
+    /// 
+    /// Only values that have been set are exported, default values from the schema are left out.  This method requires the `protobuf` feature.
+    /// 
+    /// Check out documentation [here](../protobuf/index.html).
+    /// 
+    #[cfg(feature = "protobuf")]
+    pub fn protobuf_encode(&self) -> Result<Vec<u8>, NP_Error> {
+        let mut bytes = Vec::new();
+        crate::protobuf::encode_message(&mut bytes, &self.to_owned_tree()?, &self.memory.schema, self.cursor.schema_addr)?;
+        Ok(bytes)
+    }
+
+    /// Export the value at the buffer cursor and all it's children as Avro binary data.
+    /// 
+    /// The data matches the schema from `NP_Schema::to_avro_schema`, values that haven't been set are written as `null`.  This method requires the `avro` feature.
+    /// 
+    /// Check out documentation [here](../avro/index.html).
+    /// 
+    #[cfg(feature = "avro")]
+    pub fn avro_encode(&self) -> Result<Vec<u8>, NP_Error> {
+        let mut bytes = Vec::new();
+        crate::avro::encode(&mut bytes, &self.to_owned_tree()?, &self.memory.schema, self.cursor.schema_addr)?;
+        Ok(bytes)
+    }
+
+    /// Convert a list of tables at the provided path into an Arrow `RecordBatch`, one column per table column.
+    /// 
+    /// Nested tables are flattened into `parent.child` columns and values that haven't been set are null.  This method requires the `arrow` feature.
+    /// 
+    /// Check out documentation [here](../arrow/index.html).
+    /// 
+    #[cfg(feature = "arrow")]
+    pub fn to_arrow(&self, path: &[&str]) -> Result<arrow_array::RecordBatch, NP_Error> {
+
+        let list_cursor = match self.select(self.cursor.clone(), false, path)? {
+            Some(x) => x,
+            None => return Err(NP_Error::BadPath("Can't find list to export as arrow!".into()))
+        };
+
+        let rows = match NP_Value_Owned::from_cursor(&list_cursor, &self.memory)? {
+            NP_Value_Owned::List(rows) => rows,
+            _ => Vec::new()
+        };
+
+        crate::arrow::rows_to_batch(&self.memory.schema, list_cursor.schema_addr, &rows)
+    }
+
+    /// Moves the underlying bytes out of the buffer, consuming the buffer in the process.
     /// 
-    /// ## List Example
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
     /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "list",
-    ///     "of": {"type": "string"}
+    ///    "type": "string"
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value at 1 index
-    /// new_buffer.set(&["1"], "hello")?;
-    /// // set value at 4 index
-    /// new_buffer.set(&["4"], "world")?;
-    /// // push value onto the end
-    /// new_buffer.list_push(&[], "!")?;
-    /// 
-    /// // get iterator of root (list item)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     match item.index {
-    ///         0 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         1 => assert_eq!(item.get::<&str>().unwrap(), Some("hello")),
-    ///         2 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         3 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         4 => assert_eq!(item.get::<&str>().unwrap(), Some("world")),
-    ///         5 => assert_eq!(item.get::<&str>().unwrap(), Some("!")),
-    ///         _ => panic!()
-    ///     };
-    /// });
+    /// // set initial value
+    /// new_buffer.set(&[], "hello")?;
+    /// // close buffer and get bytes
+    /// let bytes: Vec<u8> = new_buffer.close();
+    /// assert_eq!([0, 0, 3, 0, 5, 104, 101, 108, 108, 111].to_vec(), bytes);
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Table Example
+    pub fn close(self) -> Vec<u8> {
+        self.memory.dump()
+    }
+
+    /// Write the bytes `close` would return into a writer, like a file or socket, without consuming the buffer.
+    ///
+    /// Bytes are written in 8kb chunks straight from the buffer.  If `compact` is true the buffer is compacted into a temporary copy first and the compacted bytes are written instead, the buffer itself isn't changed.
+    ///
+    /// Returns how many bytes were written.  This method requires the `std` feature.
+    ///
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
-    /// 
+    ///
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "table",
-    ///    "columns": [
-    ///         ["age", {"type": "uint8"}],
-    ///         ["name", {"type": "string"}],
-    ///         ["job", {"type": "string"}],
-    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
-    ///     ]
+    ///    "type": "string"
     /// }"#)?;
-    /// 
+    ///
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value of age
-    /// new_buffer.set(&["age"], 20u8)?;
-    /// // set value of name
-    /// new_buffer.set(&["name"], "Bill Kerman")?;
-    /// // push value onto tags list
-    /// new_buffer.list_push(&["tags"], "rocket")?;
+    /// new_buffer.set(&[], "hello")?;
+    /// new_buffer.set(&[], "hello, world")?;
+    ///
+    /// let mut file: Vec<u8> = Vec::new();
+    /// assert_eq!(24, new_buffer.write_to(&mut file, false)?);
+    /// assert_eq!(&file[..], new_buffer.read_bytes());
+    ///
+    /// // compacting leaves out the old value
+    /// let mut compacted: Vec<u8> = Vec::new();
+    /// assert_eq!(17, new_buffer.write_to(&mut compacted, true)?);
+    /// assert_eq!(factory.open_buffer(compacted).get::<&str>(&[])?, Some("hello, world"));
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    ///
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&self, writer: &mut W, compact: bool) -> Result<usize, NP_Error> {
+
+        let write_chunks = |bytes: &[u8], writer: &mut W| -> Result<usize, NP_Error> {
+            for chunk in bytes.chunks(WRITE_CHUNK_SIZE) {
+                writer.write_all(chunk)?;
+            }
+            Ok(bytes.len())
+        };
+
+        if !compact {
+            return write_chunks(self.memory.read_bytes(), writer);
+        }
+
+        let compacted = NP_Memory::new_with_header(Some(self.calc_bytes()?.after_compaction), self.memory.schema, self.memory.header()).with_constraints(self.memory.constraints).with_tombstones(self.memory.tombstones);
+        NP_Cursor::compact(NP_Cursor::new(self.memory.root, 0, 0), &self.memory, NP_Cursor::new(compacted.root, 0, 0), &compacted)?;
+
+        write_chunks(compacted.read_bytes(), writer)
+    }
+
+    /// Close the buffer and append a 32 byte HMAC-SHA256 tag over the closed bytes.
     /// 
-    /// // get iterator of root (table)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     
-    ///     match item.key {
-    ///         "name" => assert_eq!(item.get::<&str>().unwrap(), Some("Bill Kerman")),
-    ///         "age" =>  assert_eq!(item.get::<u8>().unwrap(), Some(20)),
-    ///         "job" => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         "tags" => { /* tags column is list, can't do anything with it here */ },
-    ///         _ => { panic!() }
-    ///     };
-    /// });
+    /// Open the signed bytes with `NP_Factory::open_verified` and the same key, it fails if any byte was changed.  This method requires the `hmac` feature.
     /// 
-    /// // we can also loop through items of the tags list
-    /// new_buffer.get_iter(&["tags"])?.unwrap().into_iter().for_each(|item| {
-    ///     assert_eq!(item.index, 0);
-    ///     assert_eq!(item.get::<&str>().unwrap(), Some("rocket"));
-    /// });
+    /// Check out documentation [here](../hmac/index.html).
     /// 
-    /// # Ok::<(), NP_Error>(()) 
-    /// ```
+    #[cfg(feature = "hmac")]
+    pub fn close_signed(self, key: &[u8]) -> Vec<u8> {
+        crate::hmac::sign(self.memory.dump(), key)
+    }
+
+    /// Close the buffer and compress the bytes with zlib or zstd.
+    /// 
+    /// A small header in front of the compressed bytes says which codec was used, open them again with `NP_Factory::open_compressed`.  This method requires the `zlib` or `zstd` feature.
+    /// 
+    /// Check out documentation [here](../compression/index.html).
+    /// 
+    #[cfg(any(feature = "zlib", feature = "zstd"))]
+    pub fn close_compressed(self, codec: crate::compression::NP_Compression) -> Vec<u8> {
+        crate::compression::compress(&self.memory.dump(), codec)
+    }
+
+    /// Split the buffer bytes into frames of at most `max_frame_size` bytes, for links like UDP or BLE that limit packet sizes.
+    /// 
+    /// Each frame has an 8 byte header with it's sequence number, the number of frames and a checksum.  Put the frames back together with `NP_Factory::reassemble`.  Fails if `max_frame_size` doesn't leave room after the header, or if the buffer would need more than 65535 frames.
+    /// 
+    /// Check out documentation [here](../fragment/index.html).
+    /// 
+    pub fn fragment(&self, max_frame_size: usize) -> Result<Vec<Vec<u8>>, NP_Error> {
+        crate::fragment::fragment(self.memory.read_bytes(), max_frame_size)
+    }
+
+    /// If the buffer is sortable, this provides only the sortable elements of the buffer.
+    /// There is typically 10 bytes or more in front of the buffer that are identical between all the sortable buffers for a given schema.
+    /// 
+    /// The leading identical bytes are left out and each value of the tuple is written in it's [sortable encoding](../sortable/index.html), one after another.  This allows your sortable buffers to be only as large as they need to be.
+    /// 
+    /// This operation fails if the buffer is not sortable.
     /// 
-    /// ## Map Example
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
     /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "map",
-    ///    "value": {"type": "string"}
+    ///    "type": "tuple",
+    ///    "sorted": true,
+    ///    "values": [
+    ///         {"type": "u8"},
+    ///         {"type": "string", "size": 6}
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value of color key
-    /// new_buffer.set(&["color"], "blue")?;
-    /// // set value of sport key
-    /// new_buffer.set(&["sport"], "soccor")?;
+    /// // set initial value
+    /// new_buffer.set(&["0"], 55u8)?;
+    /// new_buffer.set(&["1"], "hello")?;
     /// 
-    /// // get iterator of root (map)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     
-    ///     match item.key {
-    ///         "color" => assert_eq!(item.get::<&str>().unwrap(), Some("blue")),
-    ///         "sport" => assert_eq!(item.get::<&str>().unwrap(), Some("soccor")),
-    ///         _ => panic!()
-    ///     }
-    /// });
+    /// // the buffer with it's vtables take up 20 bytes!
+    /// assert_eq!(new_buffer.read_bytes().len(), 20usize);
+    /// 
+    /// // close buffer and get sortable bytes
+    /// let bytes: Vec<u8> = new_buffer.close_sortable()?;
+    /// // with close_sortable() we only get the bytes we care about!
+    /// assert_eq!([55, 104, 101, 108, 108, 111, 32].to_vec(), bytes);
+    /// 
+    /// // you can always re open the sortable buffers with this call
+    /// let new_buffer = factory.open_sortable_buffer(bytes)?;
+    /// assert_eq!(new_buffer.get(&["0"])?, Some(55u8));
+    /// assert_eq!(new_buffer.get(&["1"])?, Some("hello "));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Tuple Example
+    pub fn close_sortable(self) -> Result<Vec<u8>, NP_Error> {
+        self.sortable_key("close_sortable()")
+    }
+
+    /// Compare the values of two sortable buffers in the same order their `close_sortable` bytes sort in.
+    /// 
+    /// Only the sortable values are compared, buffer headers and vtables are skipped.  Both buffers should come from the same schema.
+    /// 
+    /// This operation fails if either buffer is not sortable.
+    /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use core::cmp::Ordering;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
     ///    "type": "tuple",
-    ///     "values": [
+    ///    "sorted": true,
+    ///    "values": [
     ///         {"type": "string"},
-    ///         {"type": "u8"},
-    ///         {"type": "bool"}
+    ///         {"type": "i32"}
     ///     ]
     /// }"#)?;
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value at 0 index
-    /// new_buffer.set(&["0"], "hello")?;
-    /// // set value at 2 index
-    /// new_buffer.set(&["2"], false)?;
+    /// let mut first = factory.empty_buffer(None);
+    /// first.set(&["0"], "apple")?;
+    /// first.set(&["1"], 20i32)?;
     /// 
-    /// // get iterator of root (tuple item)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     match item.index {
-    ///         0 => assert_eq!(item.get::<&str>().unwrap(), Some("hello")),
-    ///         1 => assert_eq!(item.get::<u8>().unwrap(), None),
-    ///         2 => assert_eq!(item.get::<bool>().unwrap(), Some(false)),
-    ///         _ => panic!()
-    ///     };
-    /// });
+    /// let mut second = factory.empty_buffer(None);
+    /// second.set(&["0"], "apple")?;
+    /// second.set(&["1"], -5i32)?;
+    /// 
+    /// assert_eq!(first.cmp_sortable(&second)?, Ordering::Greater);
+    /// 
+    /// // the second buffer is larger, but "apple" sorts before "apples"
+    /// second.set(&["0"], "apples")?;
+    /// assert_eq!(first.cmp_sortable(&second)?, Ordering::Less);
+    /// assert_eq!(first.cmp_sortable(&first)?, Ordering::Equal);
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn get_iter<'iter>(&'iter self, path: &'iter [&str]) -> Result<Option<NP_Generic_Iterator<'iter>>, NP_Error> {
-
-        let value = self.select(self.cursor.clone(), false, path)?;
-
-        let value = if let Some(x) = value {
-            x
-        } else {
-            return Ok(None);
-        };
-
-        let value_data = value.get_value(&self.memory);
-
-        // value doesn't exist
-        if value_data.get_addr_value() == 0 {
-            return Ok(None);
-        }
-
-        Ok(Some(NP_Generic_Iterator::new(value, &self.memory)?))
+    pub fn cmp_sortable(&self, other: &NP_Buffer) -> Result<Ordering, NP_Error> {
+        Ok(self.sortable_key("cmp_sortable()")?.cmp(&other.sortable_key("cmp_sortable()")?))
     }
 
-    /// Push a value onto the end of a list.
-    /// The path provided must resolve to a list type, and the type being pushed must match the schema
+    /// Compare the values of two buffers, starting at each buffer's cursor.
     /// 
-    /// This is the most efficient way to add values to a list type.
+    /// Buffers with the same values can have different bytes, depending on the order the values were written in and how many updates they've had since the last compaction.  This walks both buffers and compares them value by value instead.  Values that aren't set are equal to values set to the schema default, list items and map keys only in one buffer are compared against an unset value.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "list",
-    ///     "of": {"type": "string"}
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["level", {"type": "u8", "default": 1}],
+    ///         ["scores", {"type": "map", "value": {"type": "u32"}}]
+    ///     ]
     /// }"#)?;
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// new_buffer.set(&["3"], "launch")?;
-    /// new_buffer.list_push(&[], "this")?;
-    /// new_buffer.list_push(&[], "rocket")?;
+    /// let mut first = factory.empty_buffer(None);
+    /// first.set(&["name"], "Jebediah")?;
+    /// first.set(&["name"], "Jeb")?;
+    /// first.set(&["scores", "moon"], 20u32)?;
+    /// first.set(&["scores", "mars"], 50u32)?;
     /// 
-    /// // get iterator of root (list item)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     match item.index {
-    ///         0 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         1 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         2 => assert_eq!(item.get::<&str>().unwrap(), None),
-    ///         3 => assert_eq!(item.get::<&str>().unwrap(), Some("launch")),
-    ///         4 => assert_eq!(item.get::<&str>().unwrap(), Some("this")),
-    ///         5 => assert_eq!(item.get::<&str>().unwrap(), Some("rocket")),
-    ///         _ => panic!()
-    ///     };
-    /// });
+    /// let mut second = factory.empty_buffer(None);
+    /// second.set(&["scores", "mars"], 50u32)?;
+    /// second.set(&["scores", "moon"], 20u32)?;
+    /// second.set(&["name"], "Jeb")?;
+    /// second.set(&["level"], 1u8)?;
     /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// new_buffer.list_push(&[], "launch")?;
-    /// new_buffer.list_push(&[], "this")?;
-    /// new_buffer.list_push(&[], "rocket")?;
+    /// assert_ne!(first.read_bytes(), second.read_bytes());
+    /// assert!(first.deep_eq(&second)?);
     /// 
-    /// // get iterator of root (list item)
-    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
-    ///     match item.index {
-    ///         0 => assert_eq!(item.get::<&str>().unwrap(), Some("launch")),
-    ///         1 => assert_eq!(item.get::<&str>().unwrap(), Some("this")),
-    ///         2 => assert_eq!(item.get::<&str>().unwrap(), Some("rocket")),
-    ///         _ => panic!()
-    ///     };
-    /// });
+    /// second.set(&["scores", "duna"], 10u32)?;
+    /// assert!(!first.deep_eq(&second)?);
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn list_push<X>(&mut self, path: &[&str], value: X) -> Result<Option<u16>, NP_Error> where X: NP_Value<'buffer> + NP_Scalar {
-
-        let list_cursor = if path.len() == 0 { self.cursor.clone() } else { match self.select(self.cursor.clone(), true, path)? {
-            Some(x) => x,
-            None => return Ok(None)
-        }};
-
-        match self.memory.schema[list_cursor.schema_addr] {
-            NP_Parsed_Schema::List { of, .. } => {
-
-                let of_schema = &self.memory.schema[of];
+    pub fn deep_eq(&self, other: &NP_Buffer) -> Result<bool, NP_Error> {
+        NP_Cursor::deep_eq(Some(self.cursor), self.cursor.schema_addr, &self.memory, Some(other.cursor), other.cursor.schema_addr, &other.memory)
+    }
 
-                // type does not match schema
-                if X::type_idx().1 != *of_schema.get_type_key() {
-                    let mut err = "TypeError: Attempted to set value for type (".to_owned();
-                    err.push_str(X::type_idx().0);
-                    err.push_str(") into schema of type (");
-                    err.push_str(of_schema.get_type_data().0);
-                    err.push_str(")\n");
-                    return Err(NP_Error::new(err));
-                }
+    /// Encode the values of a sortable root tuple, `method` names the caller in errors
+    fn sortable_key(&self, method: &str) -> Result<Vec<u8>, NP_Error> {
+        match &self.memory.schema[0] {
+            NP_Parsed_Schema::Tuple { sortable: true, .. } => {
+                let mut key: Vec<u8> = Vec::new();
+                sortable::encode(Some(NP_Cursor::new(self.memory.root, 0, 0)), 0, &self.memory, &mut key)?;
+                Ok(key)
             },
-            _ => return Err(NP_Error::new("Trying to push onto non list item!"))
+            _ => {
+                let mut err = String::from("Attempted to ");
+                err.push_str(method);
+                err.push_str(" on buffer that isn't sortable!");
+                Err(NP_Error::SchemaMismatch(err))
+            }
         }
+    }
 
-        match NP_List::push(&list_cursor, &self.memory, None)? {
-            Some((index, new_item_addr)) => {
-                X::set_value(new_item_addr, &self.memory, value)?;
-                Ok(Some(index))
+    /// Sortable bytes of the first `count` values of the root tuple
+    pub(crate) fn sortable_prefix(&self, count: usize) -> Result<Vec<u8>, NP_Error> {
+        match &self.memory.schema[0] {
+            NP_Parsed_Schema::Tuple { sortable: true, values, .. } => {
+                if count > values.len() {
+                    return Err(NP_Error::InvalidValue("Attempted to get sortable range with more values than the tuple has!".into()));
+                }
+                let mut key: Vec<u8> = Vec::new();
+                for (index, child) in values.iter().enumerate().take(count) {
+                    let child_cursor = NP_Tuple::select(NP_Cursor::new(self.memory.root, 0, 0), index, false, &self.memory)?;
+                    sortable::encode(child_cursor, *child, &self.memory, &mut key)?;
+                }
+                Ok(key)
             },
-            None => Ok(None)
+            _ => Err(NP_Error::SchemaMismatch("Attempted to get sortable range on buffer that isn't sortable!".into()))
         }
     }
 
+    /// Set every value of a sortable buffer from bytes made by `close_sortable`
+    pub(crate) fn set_sortable(&mut self, bytes: &[u8]) -> Result<(), NP_Error> {
+        // values never take more space in the buffer than they do in the sortable bytes
+        self.memory.reserve(bytes.len());
+        let mut offset = 0usize;
+        sortable::decode(bytes, &mut offset, NP_Cursor::new(self.memory.root, 0, 0), &self.memory)?;
+        if offset != bytes.len() {
+            return Err(NP_Error::Damaged("Sortable bytes continue past the last value!".into()));
+        }
+        Ok(())
+    }
 
-    /// Get length of String, Bytes, Table, Tuple, List or Map Type
+    /// Check every value in the buffer against the schema.
     /// 
-    /// If the type found at the path provided does not support length operations, you'll get `None`.
-    /// 
-    /// If there is no value at the path provodid, you will get `None`.
+    /// Use this on buffers from untrusted sources before processing them.  Every value that doesn't match it's schema is reported with it's path, including the optional `min`, `max`, `min_length`, `max_length` and `required` schema constraints.
     /// 
-    /// If an item is found and it's length is zero, you can expect `Some(0)`.
+    /// The whole buffer is checked, regardless of where the cursor is.  Check out documentation [here](../validation/index.html).
     /// 
-    /// ## String Example
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
-    /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "string"
-    /// }"#)?;
+    /// let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "i16", "min": -10, "max": 10}}"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set initial value
-    /// new_buffer.set(&[], "hello")?;
-    /// // get length of value at root (String)
-    /// assert_eq!(new_buffer.length(&[])?, Some(5));
+    /// new_buffer.set(&["0"], 5i16)?;
+    /// new_buffer.set(&["3"], 50i16)?;
+    /// 
+    /// let errors = new_buffer.validate().unwrap_err();
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].path, vec!["3"]);
+    /// assert_eq!(errors[0].message, "Value 50 is more than max 10");
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Collection (List) Example
+    pub fn validate(&self) -> Result<(), Vec<NP_Validation_Error>> {
+        crate::validation::validate(&self.memory)
+    }
+
+    /// Check the structure of the buffer, see `NP_Factory::open_buffer_untrusted`
+    pub(crate) fn check_integrity(&self) -> Result<(), NP_Error> {
+        crate::integrity::check(&self.memory)
+    }
+
+    /// Walk the whole buffer and report every dangling address, overlapping value, looping link and length that runs past the end of the buffer.
+    ///
+    /// An empty report means the buffer is safe to read and update.  Check out the [integrity docs](../integrity/index.html) for everything that's checked.
+    ///
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
-    /// 
-    /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "list",
-    ///     "of": {"type": "string"}
-    /// }"#)?;
-    /// 
-    /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set value at 9th index
-    /// new_buffer.set(&["9"], "hello")?;
-    /// // get length of value at root (List)
-    /// assert_eq!(new_buffer.length(&[])?, Some(10));
-    /// 
-    /// # Ok::<(), NP_Error>(()) 
-    /// ```
-    /// 
-    /// ## Collection (Table) Example
-    /// ```
-    /// use no_proto::error::NP_Error;
-    /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
-    /// 
+    /// use no_proto::integrity::NP_Damage_Kind;
+    ///
     /// let factory: NP_Factory = NP_Factory::new(r#"{
     ///    "type": "table",
     ///    "columns": [
-    ///         ["age", {"type": "u8"}],
     ///         ["name", {"type": "string"}]
     ///     ]
     /// }"#)?;
-    /// 
+    ///
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // get length of value at root (Table)
-    /// assert_eq!(new_buffer.length(&[])?, Some(2));
-    /// 
-    /// # Ok::<(), NP_Error>(()) 
+    /// new_buffer.set(&["name"], "Bill Kerman")?;
+    /// assert!(new_buffer.verify()?.is_ok());
+    ///
+    /// // lose the end of the buffer
+    /// let mut bytes = new_buffer.close();
+    /// bytes.truncate(bytes.len() - 3);
+    ///
+    /// let damaged = factory.open_buffer(bytes);
+    /// let report = damaged.verify()?;
+    /// assert_eq!(report.problems.len(), 1);
+    /// assert_eq!(report.problems[0].kind, NP_Damage_Kind::Length);
+    /// assert_eq!(report.problems[0].path, vec!["name"]);
+    ///
+    /// # Ok::<(), NP_Error>(())
     /// ```
+    ///
+    pub fn verify(&self) -> Result<NP_Integrity_Report, NP_Error> {
+        Ok(crate::integrity::report(&self.memory))
+    }
+
+    /// Get the schema fingerprint stamped into the buffer header, if there is one.
+    /// 
+    /// Buffers from factories created with `with_fingerprint` carry the fingerprint of the schema they were written with.
+    /// 
+    pub fn fingerprint(&self) -> Option<[u8; 8]> {
+        let header = self.memory.header();
+        if header.len() < 9 || header[0] & NP_HEADER_FINGERPRINT == 0 {
+            return None;
+        }
+        let mut fingerprint = [0u8; 8];
+        fingerprint.copy_from_slice(&header[1..9]);
+        Some(fingerprint)
+    }
+
+    /// Get the schema version stamped into the buffer header, if there is one.
+    /// 
+    /// Buffers from factories created with `with_version` carry the version number of the schema they were written with.
+    /// 
+    pub fn get_version(&self) -> Option<u32> {
+        header_version(self.memory.header()).map(|(version, _flags)| version)
+    }
+
+    /// Get the user flag bits stored next to the schema version in the buffer header.
+    /// 
+    /// Returns `None` if the buffer header doesn't have a version.
+    /// 
+    pub fn get_user_flags(&self) -> Option<u8> {
+        header_version(self.memory.header()).map(|(_version, flags)| flags)
+    }
+
+    /// Set the user flag bits stored next to the schema version in the buffer header.
+    /// 
+    /// The flags are free for applications to use, NoProto doesn't read them.  This fails if the buffer header doesn't have a version.
     /// 
-    /// ## Collection (Map) Example
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
-    /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "map",
-    ///    "value": {"type": "string"}
-    /// }"#)?;
+    /// let factory = NP_Factory::new(r#"{"type": "string"}"#)?.with_version(3);
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set values
-    /// new_buffer.set(&["foo"], "bar")?;
-    /// new_buffer.set(&["foo2"], "bar2")?;
-    /// // get length of value at root (Map)
-    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// assert_eq!(new_buffer.get_version(), Some(3));
+    /// assert_eq!(new_buffer.get_user_flags(), Some(0));
+    /// new_buffer.set_user_flags(0b1010)?;
+    /// 
+    /// let new_buffer = factory.open_buffer(new_buffer.close());
+    /// assert_eq!(new_buffer.get_user_flags(), Some(0b1010));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    /// ## Collection (Tuple) Example
+    pub fn set_user_flags(&mut self, flags: u8) -> Result<(), NP_Error> {
+        self.check_writable()?;
+        if header_version(self.memory.header()).is_none() {
+            return Err(NP_Error::SchemaMismatch("Buffer header doesn't have a version!".into()));
+        }
+        // user flags are the last header byte
+        let root = self.memory.root;
+        self.memory.get_bytes_mut(root - 1, 1)?[0] = flags;
+        Ok(())
+    }
+
+    /// Read the bytes of the buffer immutably.  No touching!
+    /// 
+    pub fn read_bytes(&self) -> &[u8] {
+        self.memory.read_bytes()
+    }
+
+    /// Is this buffer read only?  Buffers opened with `open_buffer_read_only` can't be changed.
+    /// 
+    pub fn is_read_only(&self) -> bool {
+        self.memory.is_read_only()
+    }
+
+    /// Get a read only copy of this buffer as it is right now without copying the bytes.
+    ///
+    /// The snapshot and this buffer share the same bytes until this buffer is used again while the snapshot is still alive, at that point this buffer takes one copy of the bytes and the snapshot keeps the originals.  Dropping snapshots before using the buffer again means nothing gets copied.  The bytes are shared as a whole, any change copies the entire buffer once per snapshot.
+    ///
+    /// Snapshots of buffers opened from borrowed bytes copy the bytes right away, since the borrowed bytes can be changed under the snapshot.
+    ///
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
-    /// 
+    ///
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "tuple",
-    ///    "values": [
-    ///         {"type": "string"}, 
-    ///         {"type": "string"}
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["age",  {"type": "u8"}]
     ///     ]
     /// }"#)?;
-    /// 
+    ///
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // get length of value at root (Tuple)
-    /// assert_eq!(new_buffer.length(&[])?, Some(2));
-    /// 
-    /// # Ok::<(), NP_Error>(()) 
+    /// new_buffer.set(&["name"], "Jeb")?;
+    ///
+    /// let snapshot = new_buffer.snapshot();
+    /// new_buffer.set(&["name"], "Bill")?;
+    /// new_buffer.set(&["age"], 30u8)?;
+    ///
+    /// // the snapshot still has the old values
+    /// assert_eq!(snapshot.get::<&str>(&["name"])?, Some("Jeb"));
+    /// assert_eq!(snapshot.get::<u8>(&["age"])?, None);
+    /// assert_eq!(new_buffer.get::<&str>(&["name"])?, Some("Bill"));
+    ///
+    /// // and it can't be changed
+    /// assert!(snapshot.is_read_only());
+    ///
+    /// # Ok::<(), NP_Error>(())
     /// ```
+    ///
+    pub fn snapshot(&self) -> NP_Buffer<'buffer> {
+        NP_Buffer::_new(self.memory.snapshot())
+    }
+
+    /// Finish this buffer so it can be read from many threads at once.  Check out the `sync` module for an example.
+    ///
+    pub fn into_sync(self) -> NP_Buffer_Sync<'buffer> {
+        NP_Buffer_Sync::new(self.memory)
+    }
+
+    /// Finish this buffer so it can't be changed anymore.  The finished buffer has every read method of `NP_Buffer`.  Check out the `finished` module for an example.
+    ///
+    pub fn freeze(self) -> NP_Finished_Buffer<'buffer> {
+        // the snapshot takes over the bytes of owned buffers without copying them
+        NP_Finished_Buffer::new(NP_Buffer::_new(self.memory.snapshot()), false)
+    }
+
+    /// Finish this buffer, compacting it first and keeping a checksum of the bytes if the options ask for it.
+    ///
+    pub fn freeze_with(mut self, options: &NP_Freeze_Options) -> Result<NP_Finished_Buffer<'buffer>, NP_Error> {
+        if options.compact {
+            self.compact(None)?;
+        }
+        Ok(NP_Finished_Buffer::new(NP_Buffer::_new(self.memory.snapshot()), options.checksum))
+    }
+
+    fn check_writable(&self) -> Result<(), NP_Error> {
+        if self.memory.is_read_only() {
+            return Err(NP_Error::ReadOnly("Read only buffers can't be changed!".into()));
+        }
+        Ok(())
+    }
+
+    /// Move buffer cursor to new location.  Cursors can only be moved into children.  If you need to move up reset the cursor to root, then move back down to the desired level.
     /// 
-    pub fn length(&self, path: &[&str]) -> Result<Option<usize>, NP_Error> {
-        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+    /// This also creates objects/collections along the path as needed.  If you attempt to move into a path that doesn't exist, this method will return `false`.  Otherwise it will return `true` of the path requested exists or is something that can be made to exist.
+    /// 
+    /// Read only buffers don't create anything, so the path has to exist already.
+    /// 
+    pub fn move_cursor(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
 
-        let found_cursor = if let Some(x) = value_cursor {
+        let value_cursor = self.select(self.cursor.clone(), !self.memory.is_read_only(), path)?;
+
+        let cursor = if let Some(x) = value_cursor {
             x
         } else {
-            return Ok(None);
+            return Ok(false);
         };
 
-        let addr_value = found_cursor.get_value(&self.memory).get_addr_value();
-
+        self.cursor = cursor;
 
-        match &self.memory.schema[found_cursor.schema_addr] {
-            NP_Parsed_Schema::List { of, .. } => {
-                if addr_value == 0 {
-                    return Ok(None);
-                }
+        Ok(true)
+    }
 
-                let list_data = NP_List::get_list(addr_value as usize, &self.memory);
-                let tail_addr = list_data.get_tail() as usize;
-                if tail_addr == 0 {
-                    Ok(Some(0))
-                } else {
-                    let tail_cursor = NP_Cursor::new(tail_addr, *of, found_cursor.schema_addr);
-                    let cursor_data = tail_cursor.get_value(&self.memory);
-                    Ok(Some(cursor_data.get_index() as usize + 1))
-                }
-            },
-            NP_Parsed_Schema::Map { .. } => {
-                if addr_value == 0 {
-                    return Ok(None);
-                }
-                let mut count = 0usize;
-                let mut map_iter = NP_Map::new_iter(&found_cursor, &self.memory);
+    /// Backup the current cursor's location
+    /// 
+    pub fn backup_cursor(&mut self) {
+        self.backup_cursor = self.cursor.clone();
+    }
 
-                // key is maybe in map
-                while let Some((_ikey, _item)) = map_iter.step_iter(&self.memory) {
-                    count += 1;
-                }
+    /// Restore the backed up cursor location
+    /// 
+    pub fn restore_cursor(&mut self) {
+        self.cursor = self.backup_cursor.clone();
+    }
 
-                Ok(Some(count))
-            },
-            NP_Parsed_Schema::Table { columns, ..} => {
-                Ok(Some(columns.len()))
-            },
-            NP_Parsed_Schema::Tuple { values, .. } => {
-                Ok(Some(values.len()))
-            },
-            NP_Parsed_Schema::Bytes {  size, ..} => {
-                if *size > 0 {
-                    Ok(Some(*size as usize))
-                } else {
-                    let length_bytes = self.memory.get_2_bytes(addr_value as usize).unwrap_or(&[0u8; 2]);
-                    Ok(Some(u16::from_be_bytes(*length_bytes) as usize))
-                }
-            },
-            NP_Parsed_Schema::UTF8String { size, .. } => {
-                if *size > 0 {
-                    Ok(Some(*size as usize))
-                } else {
-                    let length_bytes = self.memory.get_2_bytes(addr_value as usize).unwrap_or(&[0u8; 2]);
-                    Ok(Some(u16::from_be_bytes(*length_bytes) as usize))
-                }
-            },
-            _ => {
-                Ok(None)
-            }
-        }
-  
+    /// Moves cursor position to root of buffer, the default.
+    /// 
+    pub fn cursor_to_root(&mut self) {
+        self.cursor = NP_Cursor::new(self.memory.root, 0, 0);
     }
 
-    /// Clear an inner value from the buffer.
-    /// This can also be used to clear deeply nested collection objects or scalar objects.
+    /// Used to set scalar values inside the buffer.
+    /// 
+    /// The type that you cast the request to will be compared to the schema, if it doesn't match the schema the request will fail.
     /// 
-    /// Returns `true` if it found a value to delete (and deleted it), `false` otherwise.
+    /// The value only has to live as long as this borrow of the buffer, not as long as the buffer itself.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
     /// use no_proto::buffer::NP_Size_Data;
     /// 
+    /// // a list where each item is a map where each key has a value containing a list of strings
     /// let factory: NP_Factory = NP_Factory::new(r#"{
     ///    "type": "list",
-    ///     "of": {"type": "string"}
+    ///    "of": {"type": "map", "value": {
+    ///         "type": "list", "of": {"type": "string"}
+    ///     }}
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set index 0
-    /// new_buffer.set(&["0"], "hello")?;
-    /// // del index 0
-    /// new_buffer.del(&["0"])?;
-    /// // value is gone now!
-    /// assert_eq!(None, new_buffer.get::<&str>(&["0"])?);
+    /// // third item in the top level list -> key "alpha" of map at 3rd element -> 9th element of list at "alpha" key
+    /// // 
+    /// new_buffer.set(&["3", "alpha", "9"], "look at all this nesting madness")?;
+    /// 
+    /// // get the same item we just set
+    /// let message = new_buffer.get::<&str>(&["3", "alpha", "9"])?;
+    /// 
+    /// assert_eq!(message, Some("look at all this nesting madness"));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn del(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
+    pub fn set<'set, X: 'set>(&'set mut self, path: &[&str], value: X) -> Result<bool, NP_Error> where X: NP_Value<'set> + NP_Scalar {
+        let schema_addr = match self.schema_addr_at(path) {
+            Some(x) => x,
+            None => return self.select(self.cursor, true, path).map(|_| false)
+        };
 
-        let value_cursor = self.select(self.cursor.clone(), false, path)?;
-        
-        match value_cursor {
-            Some(x) => {
-                if self.sortable {
-                    match &self.memory.schema[x.schema_addr] {
-                        NP_Parsed_Schema::Table { .. } => { return Ok(false) },
-                        NP_Parsed_Schema::Tuple { .. } => { return Ok(false) },
-                        NP_Parsed_Schema::List { .. } => { return Ok(false) },
-                        NP_Parsed_Schema::Map { .. } => { return Ok(false) },
-                        _ => NP_Cursor::set_default(x, &self.memory)?
-                    }
-                } else {
-                    // clear value address in buffer
-                    x.get_value(&self.memory).set_addr_value(0);
-                }
+        // type does not match schema
+        if X::type_idx().1 != *self.memory.schema[schema_addr].get_type_key() {
+            let mut err = "TypeError: Attempted to set value for type (".to_owned();
+            err.push_str(X::type_idx().0);
+            err.push_str(") into schema of type (");
+            err.push_str(self.memory.schema[schema_addr].get_type_data().0);
+            err.push_str(")\n");
+            return Err(NP_Error::SchemaMismatch(err));
+        }
 
-                Ok(true)
-            }
-            None => Ok(false)
+        // recorded before the path is made, so undo takes away the collections and items it makes
+        let old = self.record(path)?;
+
+        let value_cursor = match self.select(self.cursor.clone(), true, path) {
+            Ok(Some(x)) => x,
+            Ok(None) => return Err(self.unrecord(NP_Error::new("unreachable"))),
+            Err(e) => return Err(self.unrecord(e))
+        };
+
+        if let Err(e) = X::set_value(value_cursor, &self.memory, value) {
+            return Err(self.unrecord(e));
         }
+        self.notify(path, old)?;
+        Ok(true)
     }
-  
-    /// Retrieve an inner value from the buffer. 
+
+    /// Set the value of an `any` field, along with it's type.
     /// 
-    /// The type that you cast the request to will be compared to the schema, if it doesn't match the schema the request will fail.
+    /// Works like `set`, the value can be a different type each time it's set.
+    /// 
+    /// Check out documentation [here](../pointer/any/index.html).
+    /// 
+    pub fn set_any(&mut self, path: &[&str], value: NP_AnyValue) -> Result<bool, NP_Error> {
+        self.set(path, value)
+    }
+
+    /// Add `delta` to an integer value in one step and get the new value back.
+    /// 
+    /// Values that haven't been set start from their schema default or zero.  When the result doesn't fit in the integer type, the `overflow` setting of the schema decides if the increment fails (the default), stops at the smallest or largest value, or wraps around.  The new value is returned as an `i128` since that holds every integer type.
+    /// 
+    /// Returns `None` if the path doesn't exist in the schema and an error if the value isn't an integer.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
     /// 
-    /// // a list where each item is a map where each key has a value containing a list of strings
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "list",
-    ///    "of": {"type": "map", "value": {
-    ///         "type": "list", "of": {"type": "string"}
-    ///     }}
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["visits", {"type": "u32", "default": 10}],
+    ///         ["level",  {"type": "u8", "overflow": "saturate"}],
+    ///         ["tick",   {"type": "i8", "overflow": "wrap"}],
+    ///         ["stock",  {"type": "u16"}]
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // third item in the top level list -> key "alpha" of map at 3rd element -> 9th element of list at "alpha" key
-    /// // 
-    /// new_buffer.set(&["3", "alpha", "9"], "who would build a schema like this")?;
+    /// assert_eq!(new_buffer.increment(&["visits"], 1)?, Some(11));
+    /// assert_eq!(new_buffer.increment(&["visits"], -5)?, Some(6));
+    /// assert_eq!(new_buffer.get::<u32>(&["visits"])?, Some(6));
     /// 
-    /// // get the same item we just set
-    /// let message = new_buffer.get::<&str>(&["3", "alpha", "9"])?;
+    /// // saturating counters stop at the ends of their range
+    /// assert_eq!(new_buffer.increment(&["level"], 300)?, Some(255));
+    /// assert_eq!(new_buffer.increment(&["level"], -1000)?, Some(0));
     /// 
-    /// assert_eq!(message, Some("who would build a schema like this"));
+    /// // wrapping counters go around
+    /// assert_eq!(new_buffer.increment(&["tick"], 127)?, Some(127));
+    /// assert_eq!(new_buffer.increment(&["tick"], 1)?, Some(-128));
+    /// 
+    /// // everything else fails without changing the value
+    /// new_buffer.set(&["stock"], 2u16)?;
+    /// assert!(new_buffer.increment(&["stock"], -3).is_err());
+    /// assert_eq!(new_buffer.get::<u16>(&["stock"])?, Some(2));
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn get<'get, X: 'get>(&'get self, path: &[&str]) -> Result<Option<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
-        let value_cursor = self.select(self.cursor.clone(), false, path)?;
-
-        match value_cursor {
-            Some(x) => {
-                                
-                // type does not match schema
-                if X::type_idx().1 != *self.memory.schema[x.schema_addr].get_type_key() {
-                    let mut err = "TypeError: Attempted to get value for type (".to_owned();
-                    err.push_str(X::type_idx().0);
-                    err.push_str(") for schema of type (");
-                    err.push_str(self.memory.schema[x.schema_addr].get_type_data().0);
-                    err.push_str(")\n");
-                    return Err(NP_Error::new(err));
-                }
-
-                match X::into_value(&x, &self.memory)? {
-                    Some(x) => {
-                        Ok(Some(x))
-                    },
-                    None => { // no value found here, return default from schema
-                        match X::schema_default(&self.memory.schema[x.schema_addr]) {
-                            Some(y) => {
-                                Ok(Some(y))
-                            },
-                            None => { // no default in schema, no value to provide
-                                Ok(None)
-                            }
-                        }                        
-                    }
-                }
-            }
+    pub fn increment(&mut self, path: &[&str], delta: i64) -> Result<Option<i128>, NP_Error> {
+        if self.is_watched() {
+            return self.journaled(path, |buffer| buffer.increment(path, delta));
+        }
+        match self.select(self.cursor.clone(), true, path)? {
+            Some(x) => Ok(Some(increment_value(x, &self.memory, delta)?)),
             None => Ok(None)
         }
     }
 
-    /// This performs a compaction if the closure provided as the second argument returns `true`.
-    /// Compaction is a pretty expensive operation (requires full copy of the whole buffer) so should be done sparingly.
-    /// The closure is provided an argument that contains the original size of the buffer, how many bytes could be saved by compaction, and how large the new buffer would be after compaction.  The closure should return `true` to perform compaction, `false` otherwise.
+    /// Set a JSON value into the buffer.
     /// 
-    /// The first argument, new_capacity, is the capacity of the underlying Vec<u8> that we'll be copying the data into.  The default is the size of the old buffer.
+    /// Scalars, objects and arrays can all be set, objects and arrays are followed into the matching collections in the schema.  JSON values are expected in the same format `json_encode` provides them, every value is checked against the schema and an error is returned if it doesn't fit.  Numbers and dates can also be provided as strings, and whole number floats can be set into integer types.  JSON `null` values delete whatever is at their location.  Values set before an error was found stay in the buffer.
     /// 
-    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// Returns `false` if the path doesn't exist in the schema.
     /// 
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
-    /// use no_proto::buffer::NP_Size_Data;
+    /// use no_proto::json_flex::json_decode;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "string"
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["age",   {"type": "uint8"}],
+    ///         ["tags",  {"type": "list", "of": {"type": "string"}}]
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set initial value
-    /// new_buffer.set(&[], "hello")?;
-    /// // using 9 bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 10,
-    ///     after_compaction: 10,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
-    /// // update the value
-    /// new_buffer.set(&[], "hello, world")?;
-    /// // now using 25 bytes, with 7 bytes of wasted space
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 24,
-    ///     after_compaction: 17,
-    ///     wasted_bytes: 7
-    /// }, new_buffer.calc_bytes()?);
-    /// // compact to save space
-    /// new_buffer.maybe_compact(None, |compact_data| {
-    ///     // only compact if wasted bytes are greater than 5
-    ///     if compact_data.wasted_bytes > 5 {
-    ///         true
-    ///     } else {
-    ///         false
-    ///     }
-    /// })?;
-    /// // back down to 18 bytes with no wasted bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 17,
-    ///     after_compaction: 17,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
+    /// 
+    /// let json = json_decode(String::from(r#"{"name": "Jeb Kermin", "age": 30, "tags": ["pilot", "brave"]}"#))?;
+    /// new_buffer.set_json(&[], &json)?;
+    /// 
+    /// assert_eq!(new_buffer.get::<&str>(&["tags", "1"])?, Some("brave"));
+    /// assert_eq!("{\"name\":\"Jeb Kermin\",\"age\":30,\"tags\":[\"pilot\",\"brave\"]}", new_buffer.json_encode(&[])?.stringify());
+    /// 
+    /// // values must match the schema
+    /// let json = json_decode(String::from(r#"{"age": 300}"#))?;
+    /// assert!(new_buffer.set_json(&[], &json).is_err());
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn maybe_compact<F>(&mut self, new_capacity: Option<u32>, mut callback: F) -> Result<(), NP_Error> where F: FnMut(NP_Size_Data) -> bool {
+    pub fn set_json(&mut self, path: &[&str], value: &NP_JSON) -> Result<bool, NP_Error> {
+        if self.schema_addr_at(path).is_none() {
+            return Ok(false);
+        }
 
-        let bytes_data = self.calc_bytes()?;
+        if self.is_watched() {
+            return self.journaled(path, |buffer| buffer.set_json(path, value));
+        }
 
-        if callback(bytes_data) {
-            self.compact(new_capacity)?;
+        let mut json_path: Vec<String> = path.iter().map(|x| (*x).to_owned()).collect();
+        self.set_json_at(&mut json_path, value)?;
+        Ok(true)
+    }
+
+    fn set_json_at(&mut self, path: &mut Vec<String>, value: &NP_JSON) -> Result<(), NP_Error> {
+
+        let schema: &'buffer Vec<NP_Parsed_Schema> = self.memory.schema;
+
+        let schema_addr = {
+            let path_refs: Vec<&str> = path.iter().map(|x| x.as_str()).collect();
+            match self.schema_addr_at(&path_refs) {
+                Some(x) => x,
+                None => {
+                    let mut err = "Error setting JSON, path not found: ".to_owned();
+                    err.push_str(&path.join("."));
+                    return Err(NP_Error::BadPath(err));
+                }
+            }
+        };
+
+        let type_error = || {
+            let mut err = "TypeError: Attempted to set JSON value into schema of type (".to_owned();
+            err.push_str(schema[schema_addr].get_type_data().0);
+            err.push_str(") at path (");
+            err.push_str(&path.join("."));
+            err.push_str(")\n");
+            NP_Error::SchemaMismatch(err)
+        };
+
+        macro_rules! set_scalar {
+            ($value: expr) => {{
+                let value = $value;
+                let path_refs: Vec<&str> = path.iter().map(|x| x.as_str()).collect();
+                self.set(&path_refs, value)?;
+                return Ok(());
+            }}
         }
 
-        return Ok(());
+        // whole numbers can be provided as integers, floats without a fraction or strings
+        let json_integer = |json: &NP_JSON| -> Option<i64> {
+            match json {
+                NP_JSON::Integer(x) => Some(*x),
+                NP_JSON::Float(x) if *x >= (i64::MIN as f64) && *x < (i64::MAX as f64) && (*x as i64) as f64 == *x => Some(*x as i64),
+                NP_JSON::String(x) => x.trim().parse::<i64>().ok(),
+                _ => None
+            }
+        };
+
+        let json_number = |json: &NP_JSON| -> Option<f64> {
+            match json {
+                NP_JSON::Integer(x) => Some(*x as f64),
+                NP_JSON::Float(x) => Some(*x),
+                NP_JSON::String(x) => x.trim().parse::<f64>().ok(),
+                _ => None
+            }
+        };
+
+        macro_rules! set_int {
+            ($t: ty) => {
+                match json_integer(value).and_then(|x| <$t>::try_from(x).ok()) {
+                    Some(x) => set_scalar!(x),
+                    None => Err(type_error())
+                }
+            }
+        }
+
+        if let NP_JSON::Null = value {
+            let path_refs: Vec<&str> = path.iter().map(|x| x.as_str()).collect();
+            self.del(&path_refs)?;
+            return Ok(());
+        }
+
+        match &schema[schema_addr] {
+            NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Map { .. } => {
+                match value {
+                    NP_JSON::Dictionary(map) => {
+                        for (key, item) in &map.values {
+                            path.push(key.clone());
+                            self.set_json_at(path, item)?;
+                            path.pop();
+                        }
+                        Ok(())
+                    },
+                    _ => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Tuple { .. } => {
+                match value {
+                    NP_JSON::Array(items) => {
+                        for (index, item) in items.iter().enumerate() {
+                            if let NP_JSON::Null = item { continue; }
+                            path.push(index.to_string());
+                            self.set_json_at(path, item)?;
+                            path.pop();
+                        }
+                        Ok(())
+                    },
+                    _ => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::UTF8String { .. } => {
+                match value {
+                    NP_JSON::String(x) => set_scalar!(x.as_str()),
+                    _ => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::Any { .. } => {
+                match NP_AnyValue::from_json(value) {
+                    Some(x) => set_scalar!(x),
+                    None => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::Enum { .. } => {
+                match value {
+                    NP_JSON::String(x) => set_scalar!(NP_Enum::new(x.as_str())),
+                    _ => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::Uuid { .. } => {
+                match value {
+                    NP_JSON::String(x) => set_scalar!(&NP_UUID::from_string(x)?),
+                    _ => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::Bytes { .. } => {
+                match value {
+                    NP_JSON::Array(items) => {
+                        let mut bytes: Vec<u8> = Vec::with_capacity(items.len());
+                        for item in items {
+                            match item {
+                                NP_JSON::Integer(x) if *x >= 0 && *x <= 255 => bytes.push(*x as u8),
+                                _ => return Err(type_error())
+                            }
+                        }
+                        set_scalar!(bytes.as_slice())
+                    },
+                    _ => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::Int8 { .. } => set_int!(i8),
+            NP_Parsed_Schema::Int16 { .. } => set_int!(i16),
+            NP_Parsed_Schema::Int32 { .. } => set_int!(i32),
+            NP_Parsed_Schema::Int64 { .. } => set_int!(i64),
+            NP_Parsed_Schema::Uint8 { .. } => set_int!(u8),
+            NP_Parsed_Schema::Uint16 { .. } => set_int!(u16),
+            NP_Parsed_Schema::Uint32 { .. } => set_int!(u32),
+            NP_Parsed_Schema::Uint64 { .. } => set_int!(u64),
+            NP_Parsed_Schema::Date { .. } => {
+                match (json_integer(value).and_then(|x| u64::try_from(x).ok()), value) {
+                    (Some(x), _) => set_scalar!(NP_Date::new(x)),
+                    (None, NP_JSON::String(x)) => set_scalar!(NP_Date::from_rfc3339(x)?),
+                    _ => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::Float { .. } => {
+                match json_number(value) {
+                    Some(x) => set_scalar!(x as f32),
+                    None => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::Double { .. } => {
+                match json_number(value) {
+                    Some(x) => set_scalar!(x),
+                    None => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::Boolean { .. } => {
+                match value {
+                    NP_JSON::True => set_scalar!(true),
+                    NP_JSON::False => set_scalar!(false),
+                    _ => Err(type_error())
+                }
+            },
+            NP_Parsed_Schema::Decimal { exp, .. } => {
+                let mut dec: NP_Dec = match value {
+                    NP_JSON::Dictionary(map) => {
+                        match (map.get("num"), map.get("exp")) {
+                            (Some(NP_JSON::Integer(num)), Some(NP_JSON::Integer(num_exp))) if *num_exp >= 0 && *num_exp <= 255 => NP_Dec::new(*num, *num_exp as u8),
+                            _ => return Err(type_error())
+                        }
+                    },
+                    NP_JSON::Integer(x) => NP_Dec::new(*x, 0),
+                    NP_JSON::String(x) => NP_Dec::from_str(x, *exp)?,
+                    _ => match json_number(value) {
+                        Some(x) => x.into(),
+                        None => return Err(type_error())
+                    }
+                };
+                dec.shift_exp(*exp);
+                set_scalar!(dec)
+            },
+            NP_Parsed_Schema::Geo { size, .. } => {
+                match value {
+                    NP_JSON::Dictionary(map) => {
+                        match (map.get("lat").and_then(json_number), map.get("lng").and_then(json_number)) {
+                            (Some(lat), Some(lng)) => set_scalar!(NP_Geo::new(*size, lat, lng)),
+                            _ => Err(type_error())
+                        }
+                    },
+                    _ => Err(type_error())
+                }
+            },
+            _ => Err(type_error())
+        }
     }
 
-    /// Compacts a buffer to remove an unused bytes or free space after a mutation.
-    /// This is a pretty expensive operation (requires full copy of the whole buffer) so should be done sparingly.
-    /// 
-    /// The first argument, new_capacity, is the capacity of the underlying Vec<u8> that we'll be copying the data into.  The default is the size of the old buffer.
+    
+    /// Get an iterator for a collection
     /// 
-    /// **WARNING** Your cursor location and backup will be reset to the root.
     /// 
+    /// ## List Example
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
     /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "string"
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// // set initial value
-    /// new_buffer.set(&[], "hello")?;
-    /// // using 11 bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 10,
-    ///     after_compaction: 10,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
-    /// // update the value
-    /// new_buffer.set(&[], "hello, world")?;
-    /// // now using 25 bytes, with 7 bytes of wasted bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 24,
-    ///     after_compaction: 17,
-    ///     wasted_bytes: 7
-    /// }, new_buffer.calc_bytes()?);
-    /// // compact to save space
-    /// new_buffer.compact(None)?;
-    /// // back down to 18 bytes with no wasted bytes
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 17,
-    ///     after_compaction: 17,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
+    /// // set value at 1 index
+    /// new_buffer.set(&["1"], "hello")?;
+    /// // set value at 4 index
+    /// new_buffer.set(&["4"], "world")?;
+    /// // push value onto the end
+    /// new_buffer.list_push(&[], "!")?;
+    /// 
+    /// // get iterator of root (list item)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     match item.index {
+    ///         0 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         1 => assert_eq!(item.get::<&str>().unwrap(), Some("hello")),
+    ///         2 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         3 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         4 => assert_eq!(item.get::<&str>().unwrap(), Some("world")),
+    ///         5 => assert_eq!(item.get::<&str>().unwrap(), Some("!")),
+    ///         _ => panic!()
+    ///     };
+    /// });
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn compact<'compact>(&mut self, new_capacity: Option<u32>) -> Result<(), NP_Error> {
-
-        let capacity = match new_capacity {
-            Some(x) => { x as usize },
-            None => self.memory.read_bytes().len()
-        };
-
-        let old_root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
-
-        let new_bytes = NP_Memory::new(Some(capacity), self.memory.schema);
-        let new_root  = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
-
-        NP_Cursor::compact(old_root, &self.memory, new_root, &new_bytes)?;
-
-        self.cursor = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
-        self.backup_cursor = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
-
-        self.memory = new_bytes;
-
-        Ok(())
-    }
-
-    /// Recursively measures how many bytes each element in the buffer is using.
-    /// This will let you know how many bytes can be saved from a compaction.
-    /// 
+    /// ## Table Example
     /// ```
     /// use no_proto::error::NP_Error;
     /// use no_proto::NP_Factory;
     /// use no_proto::buffer::NP_Size_Data;
     /// 
     /// let factory: NP_Factory = NP_Factory::new(r#"{
-    ///    "type": "string"
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["age", {"type": "uint8"}],
+    ///         ["name", {"type": "string"}],
+    ///         ["job", {"type": "string"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
     /// }"#)?;
     /// 
     /// let mut new_buffer = factory.empty_buffer(None);
-    /// new_buffer.set(&[], "hello")?;
-    /// assert_eq!(NP_Size_Data {
-    ///     current_buffer: 10,
-    ///     after_compaction: 10,
-    ///     wasted_bytes: 0
-    /// }, new_buffer.calc_bytes()?);
+    /// // set value of age
+    /// new_buffer.set(&["age"], 20u8)?;
+    /// // set value of name
+    /// new_buffer.set(&["name"], "Bill Kerman")?;
+    /// // push value onto tags list
+    /// new_buffer.list_push(&["tags"], "rocket")?;
+    /// 
+    /// // get iterator of root (table)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     
+    ///     match item.key {
+    ///         "name" => assert_eq!(item.get::<&str>().unwrap(), Some("Bill Kerman")),
+    ///         "age" =>  assert_eq!(item.get::<u8>().unwrap(), Some(20)),
+    ///         "job" => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         "tags" => { /* tags column is list, can't do anything with it here */ },
+    ///         _ => { panic!() }
+    ///     };
+    /// });
+    /// 
+    /// // we can also loop through items of the tags list
+    /// new_buffer.get_iter(&["tags"])?.unwrap().into_iter().for_each(|item| {
+    ///     assert_eq!(item.index, 0);
+    ///     assert_eq!(item.get::<&str>().unwrap(), Some("rocket"));
+    /// });
     /// 
     /// # Ok::<(), NP_Error>(()) 
     /// ```
     /// 
-    pub fn calc_bytes<'bytes>(&self) -> Result<NP_Size_Data, NP_Error> {
+    /// ## Map Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "map",
+    ///    "value": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set value of color key
+    /// new_buffer.set(&["color"], "blue")?;
+    /// // set value of sport key
+    /// new_buffer.set(&["sport"], "soccor")?;
+    /// 
+    /// // get iterator of root (map)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     
+    ///     match item.key {
+    ///         "color" => assert_eq!(item.get::<&str>().unwrap(), Some("blue")),
+    ///         "sport" => assert_eq!(item.get::<&str>().unwrap(), Some("soccor")),
+    ///         _ => panic!()
+    ///     }
+    /// });
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Tuple Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "tuple",
+    ///     "values": [
+    ///         {"type": "string"},
+    ///         {"type": "u8"},
+    ///         {"type": "bool"}
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set value at 0 index
+    /// new_buffer.set(&["0"], "hello")?;
+    /// // set value at 2 index
+    /// new_buffer.set(&["2"], false)?;
+    /// 
+    /// // get iterator of root (tuple item)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     match item.index {
+    ///         0 => assert_eq!(item.get::<&str>().unwrap(), Some("hello")),
+    ///         1 => assert_eq!(item.get::<u8>().unwrap(), None),
+    ///         2 => assert_eq!(item.get::<bool>().unwrap(), Some(false)),
+    ///         _ => panic!()
+    ///     };
+    /// });
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get_iter<'iter>(&'iter self, path: &'iter [&str]) -> Result<Option<NP_Generic_Iterator<'iter>>, NP_Error> {
 
-        let root = NP_Cursor::new(ROOT_PTR_ADDR, 0, 0);
-        let real_bytes = NP_Cursor::calc_size(&root, &self.memory)? + ROOT_PTR_ADDR;
-        let total_size = self.memory.read_bytes().len();
-        if total_size >= real_bytes {
-            return Ok(NP_Size_Data {
-                current_buffer: total_size,
-                after_compaction: real_bytes,
-                wasted_bytes: total_size - real_bytes
-            });
+        let value = self.select(self.cursor.clone(), false, path)?;
+
+        let value = if let Some(x) = value {
+            x
         } else {
-            return Err(NP_Error::new("Error calculating bytes!"));
+            return Ok(None);
+        };
+
+        let value_data = value.get_value(&self.memory);
+
+        // value doesn't exist
+        if value_data.get_addr_value() == 0 {
+            return Ok(None);
         }
+
+        Ok(Some(NP_Generic_Iterator::new(value, &self.memory)?))
     }
 
-    fn select(&self, cursor: NP_Cursor, make_path: bool, path: &[&str]) -> Result<Option<NP_Cursor>, NP_Error> {
+    /// Push a value onto the end of a list.
+    /// The path provided must resolve to a list type, and the type being pushed must match the schema
+    /// 
+    /// This is the most efficient way to add values to a list type.
+    /// 
+    /// Like `set`, the value only has to live as long as this borrow of the buffer.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["3"], "launch")?;
+    /// new_buffer.list_push(&[], "this")?;
+    /// new_buffer.list_push(&[], "rocket")?;
+    /// 
+    /// // get iterator of root (list item)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     match item.index {
+    ///         0 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         1 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         2 => assert_eq!(item.get::<&str>().unwrap(), None),
+    ///         3 => assert_eq!(item.get::<&str>().unwrap(), Some("launch")),
+    ///         4 => assert_eq!(item.get::<&str>().unwrap(), Some("this")),
+    ///         5 => assert_eq!(item.get::<&str>().unwrap(), Some("rocket")),
+    ///         _ => panic!()
+    ///     };
+    /// });
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.list_push(&[], "launch")?;
+    /// new_buffer.list_push(&[], "this")?;
+    /// new_buffer.list_push(&[], "rocket")?;
+    /// 
+    /// // get iterator of root (list item)
+    /// new_buffer.get_iter(&[])?.unwrap().into_iter().for_each(|item| {
+    ///     match item.index {
+    ///         0 => assert_eq!(item.get::<&str>().unwrap(), Some("launch")),
+    ///         1 => assert_eq!(item.get::<&str>().unwrap(), Some("this")),
+    ///         2 => assert_eq!(item.get::<&str>().unwrap(), Some("rocket")),
+    ///         _ => panic!()
+    ///     };
+    /// });
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_push<'push, X: 'push>(&'push mut self, path: &[&str], value: X) -> Result<Option<u16>, NP_Error> where X: NP_Value<'push> + NP_Scalar {
 
-        let mut loop_cursor = cursor;
+        let schema_addr = match self.schema_addr_at(path) {
+            Some(x) => x,
+            None => return self.select(self.cursor, true, path).map(|_| None)
+        };
 
-        let mut path_index = 0usize;
-        
-        loop {
-            
-            if path.len() == path_index {
-                return Ok(Some(loop_cursor));
+        match self.memory.schema[schema_addr] {
+            NP_Parsed_Schema::List { of, .. } => {
+
+                let of_schema = &self.memory.schema[of];
+
+                // type does not match schema
+                if X::type_idx().1 != *of_schema.get_type_key() {
+                    let mut err = "TypeError: Attempted to set value for type (".to_owned();
+                    err.push_str(X::type_idx().0);
+                    err.push_str(") into schema of type (");
+                    err.push_str(of_schema.get_type_data().0);
+                    err.push_str(")\n");
+                    return Err(NP_Error::SchemaMismatch(err));
+                }
+            },
+            _ => return Err(NP_Error::SchemaMismatch("Trying to push onto non list item!".into()))
+        }
+
+        // recorded before the path is made, so undo takes away the collections and items it makes
+        let old = self.record(path)?;
+
+        let list_cursor = if path.len() == 0 { self.cursor.clone() } else { match self.select(self.cursor.clone(), true, path) {
+            Ok(Some(x)) => x,
+            Ok(None) => return Err(self.unrecord(NP_Error::new("unreachable"))),
+            Err(e) => return Err(self.unrecord(e))
+        }};
+
+        match NP_List::push(&list_cursor, &self.memory, None) {
+            Ok(Some((index, new_item_addr))) => {
+                if let Err(e) = X::set_value(new_item_addr, &self.memory, value) {
+                    return Err(self.unrecord(e));
+                }
+                self.notify(path, old)?;
+                Ok(Some(index))
+            },
+            Ok(None) => Ok(None),
+            Err(e) => Err(self.unrecord(e))
+        }
+    }
+
+
+    /// Insert a value into a list so the list stays sorted.
+    /// 
+    /// The list is walked from the front and the new value goes in front of the first item that sorts after it, that item and every item behind it move up one index.  Values that sort the same as the new value stay in front of it.  Values compare in their [sortable encoding](../sortable/index.html), items of the list that aren't set compare as the schema default.
+    /// 
+    /// The path provided must resolve to a list of scalars.  Use `list_insert_sorted_by` for lists of tables.  Returns the index the value was inserted at, or `None` if the path doesn't exist.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.list_insert_sorted(&[], "mun")?;
+    /// new_buffer.list_insert_sorted(&[], "duna")?;
+    /// assert_eq!(new_buffer.list_insert_sorted(&[], "eve")?, Some(1));
+    /// 
+    /// assert_eq!("[\"duna\",\"eve\",\"mun\"]", new_buffer.json_encode(&[])?.stringify());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_insert_sorted<'insert, X: 'insert>(&'insert mut self, path: &[&str], value: X) -> Result<Option<u16>, NP_Error> where X: NP_Value<'insert> + NP_Scalar {
+        self.list_insert_sorted_by(path, &[], value)
+    }
+
+    /// Insert a new item into a list of tables or tuples so the list stays sorted by the value at `by` in each item.
+    /// 
+    /// A new item is made with the value at `by`, other values can be set into the item at the returned index afterwards.  Items are ordered the same way `list_insert_sorted` orders them.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "table", "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["age",  {"type": "u8"}]
+    ///     ]}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// for (name, age) in [("Jeb", 30u8), ("Bill", 25), ("Bob", 40), ("Val", 28)].iter() {
+    ///     let index = new_buffer.list_insert_sorted_by(&[], &["age"], *age)?.unwrap().to_string();
+    ///     new_buffer.set(&[index.as_str(), "name"], *name)?;
+    /// }
+    /// 
+    /// assert_eq!(new_buffer.get::<&str>(&["0", "name"])?, Some("Bill"));
+    /// assert_eq!(new_buffer.get::<&str>(&["1", "name"])?, Some("Val"));
+    /// assert_eq!(new_buffer.get::<&str>(&["3", "name"])?, Some("Bob"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_insert_sorted_by<'insert, X: 'insert>(&'insert mut self, path: &[&str], by: &[&str], value: X) -> Result<Option<u16>, NP_Error> where X: NP_Value<'insert> + NP_Scalar {
+
+        let schema_addr = match self.schema_addr_at(path) {
+            Some(x) => x,
+            None => return self.select(self.cursor, true, path).map(|_| None)
+        };
+
+        let key_schema = self.list_key_schema(schema_addr, by)?;
+
+        // type does not match schema
+        if X::type_idx().1 != *self.memory.schema[key_schema].get_type_key() {
+            let mut err = "TypeError: Attempted to set value for type (".to_owned();
+            err.push_str(X::type_idx().0);
+            err.push_str(") into schema of type (");
+            err.push_str(self.memory.schema[key_schema].get_type_data().0);
+            err.push_str(")\n");
+            return Err(NP_Error::SchemaMismatch(err));
+        }
+
+        // recorded before the path is made, so undo takes away the collections and items it makes
+        let old = self.record(path)?;
+
+        let list_cursor = if path.len() == 0 { self.cursor.clone() } else { match self.select(self.cursor.clone(), true, path) {
+            Ok(Some(x)) => x,
+            Ok(None) => return Err(self.unrecord(NP_Error::new("unreachable"))),
+            Err(e) => return Err(self.unrecord(e))
+        }};
+
+        // push the new item onto the end, then move it where it belongs
+        let (new_index, new_item) = match NP_List::push(&list_cursor, &self.memory, None)? {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+
+        match self.select(new_item, true, by)? {
+            Some(key_cursor) => { X::set_value(key_cursor, &self.memory, value)?; },
+            None => return Err(NP_Error::new("unreachable"))
+        }
+
+        let new_key = self.list_item_key(new_item, by, key_schema)?;
+
+        let mut items = NP_List::items(&list_cursor, &self.memory);
+        let last = items.len() - 1;
+
+        let mut position = last;
+        for (x, (_index, item)) in items.iter().enumerate().take(last) {
+            if self.list_item_key(*item, by, key_schema)? > new_key {
+                position = x;
+                break;
             }
+        }
 
-            // now select into collections
-            match &self.memory.schema[loop_cursor.schema_addr] {
-                NP_Parsed_Schema::Table {  .. } => {
-                    if let Some(next) = NP_Table::select(loop_cursor, path[path_index], make_path, &self.memory)? {
-                        loop_cursor = next;
-                        path_index += 1;
-                    } else {
-                        return Ok(None);
-                    }
-                },
-                NP_Parsed_Schema::Tuple { .. } => {
-                    match path[path_index].parse::<usize>() {
-                        Ok(x) => {
-                            if let Some(next) = NP_Tuple::select(loop_cursor, x, make_path, &self.memory)? {
-                                loop_cursor = next;
-                                path_index += 1;
-                            } else {
-                                return Ok(None);
-                            }
-                        },
-                        Err(_e) => {
-                            return Err(NP_Error::new("Need a number to index into tuple, string found!"))
-                        }
-                    }
-                },
-                NP_Parsed_Schema::List { .. } => {
-                    match path[path_index].parse::<usize>() {
+        if position == last {
+            self.notify(path, old)?;
+            return Ok(Some(new_index));
+        }
+
+        items.pop();
+        let insert_index = items[position].0;
+        for item in items[position..].iter_mut() {
+            item.0 += 1;
+        }
+        items.insert(position, (insert_index, new_item));
+        NP_List::relink(&list_cursor, &self.memory, &items)?;
+
+        self.notify(path, old)?;
+        Ok(Some(insert_index as u16))
+    }
+
+    /// Remove every item of a list at or past index `len`.
+    /// 
+    /// The items are unlinked from the list in one pass, the space they used is wasted until the buffer is compacted.  Returns `false` if there's no list at the path.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.json_buffer(r#"["moho", "eve", "kerbin", "duna"]"#)?;
+    /// 
+    /// assert!(new_buffer.list_truncate(&[], 2)?);
+    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// assert_eq!("[\"moho\",\"eve\"]", new_buffer.json_encode(&[])?.stringify());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_truncate(&mut self, path: &[&str], len: usize) -> Result<bool, NP_Error> {
+        self.list_remove_range(path, len..256)
+    }
+
+    /// Remove the items of a list with indexes in `range`, items past the range move down to fill the gap.
+    /// 
+    /// Like `Vec::drain`, but the removed items aren't returned.  The list is relinked in one pass and the space the items used is wasted until the buffer is compacted.  Returns `false` if there's no list at the path.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.json_buffer(r#"["moho", "eve", "kerbin", "duna"]"#)?;
+    /// 
+    /// assert!(new_buffer.list_remove_range(&[], 1..3)?);
+    /// assert_eq!("[\"moho\",\"duna\"]", new_buffer.json_encode(&[])?.stringify());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_remove_range(&mut self, path: &[&str], range: core::ops::Range<usize>) -> Result<bool, NP_Error> {
+
+        if self.is_watched() {
+            return self.journaled(path, |buffer| buffer.list_remove_range(path, range));
+        }
+
+        let list_cursor = match self.list_at(path)? {
+            Some(x) => x,
+            None => return Ok(false)
+        };
+
+        if range.start >= range.end {
+            return Ok(true);
+        }
+
+        let removed = range.end - range.start;
+
+        let items: Vec<(usize, NP_Cursor)> = NP_List::items(&list_cursor, &self.memory).into_iter().filter_map(|(index, item)| {
+            if index < range.start {
+                Some((index, item))
+            } else if index >= range.end {
+                Some((index - removed, item))
+            } else {
+                None
+            }
+        }).collect();
+
+        NP_List::relink(&list_cursor, &self.memory, &items)?;
+
+        Ok(true)
+    }
+
+    /// Insert JSON values into a list at index `at`, the items at and past `at` move up to make room.
+    /// 
+    /// Values are checked against the schema the same way `set_json` checks them, `null` values leave a gap in the list.  The list can't hold items past index 255.  Returns `false` if there's no list at the path.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::json_flex::NP_JSON;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.json_buffer(r#"["moho", "duna"]"#)?;
+    /// 
+    /// let planets = [NP_JSON::String("eve".to_owned()), NP_JSON::String("kerbin".to_owned())];
+    /// assert!(new_buffer.list_splice(&[], 1, &planets)?);
+    /// assert_eq!("[\"moho\",\"eve\",\"kerbin\",\"duna\"]", new_buffer.json_encode(&[])?.stringify());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_splice(&mut self, path: &[&str], at: usize, items: &[NP_JSON]) -> Result<bool, NP_Error> {
+
+        if self.is_watched() {
+            return self.journaled(path, |buffer| buffer.list_splice(path, at, items));
+        }
+
+        let list_cursor = match self.list_at(path)? {
+            Some(x) => x,
+            None => {
+                if self.schema_addr_at(path).is_none() {
+                    return Ok(false);
+                }
+                // make the list so the values have somewhere to go
+                match self.select(self.cursor.clone(), true, path)? {
+                    Some(x) => {
+                        NP_List::make_list(&x, &self.memory)?;
+                        x
+                    },
+                    None => return Ok(false)
+                }
+            }
+        };
+
+        let moved: Vec<(usize, NP_Cursor)> = NP_List::items(&list_cursor, &self.memory).into_iter().map(|(index, item)| {
+            if index >= at { (index + items.len(), item) } else { (index, item) }
+        }).collect();
+
+        if at + items.len() > 256 || moved.last().map(|(index, _)| *index > 255).unwrap_or(false) {
+            return Err(NP_Error::OutOfBounds("Index cannot be greater than 255!".into()));
+        }
+
+        NP_List::relink(&list_cursor, &self.memory, &moved)?;
+
+        let mut item_path: Vec<String> = path.iter().map(|x| (*x).to_owned()).collect();
+        item_path.push(String::new());
+        for (x, value) in items.iter().enumerate() {
+            if let Some(last) = item_path.last_mut() {
+                *last = (at + x).to_string();
+            }
+            self.set_json_at(&mut item_path, value)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Cursor of the list at `path`, `None` if the list hasn't been made.  Fails if there's something other than a list at the path.
+    fn list_at(&self, path: &[&str]) -> Result<Option<NP_Cursor>, NP_Error> {
+        self.check_writable()?;
+
+        match self.schema_addr_at(path).map(|addr| &self.memory.schema[addr]) {
+            Some(NP_Parsed_Schema::List { .. }) => { },
+            Some(_) => return Err(NP_Error::SchemaMismatch("Attempted list operation on non list item!".into())),
+            None => return Ok(None)
+        }
+
+        match self.select(self.cursor.clone(), false, path)? {
+            Some(x) if x.get_value(&self.memory).get_addr_value() != 0 => Ok(Some(x)),
+            _ => Ok(None)
+        }
+    }
+
+    /// Sort the items of a list by the value at `by` inside each item, use an empty `by` for lists of scalars.
+    /// 
+    /// The items are reordered in place by relinking them, no values are copied.  Sorted items take the indexes the list already has in order, so gaps in the list stay where they are.  Values compare in their [sortable encoding](../sortable/index.html), items that don't have a value compare as the schema default and items that sort the same keep their order.
+    /// 
+    /// Returns `false` if there's no list at the path.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["crew", {"type": "list", "of": {"type": "table", "columns": [
+    ///             ["name", {"type": "string"}],
+    ///             ["age",  {"type": "u8"}]
+    ///         ]}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.json_buffer(r#"{"crew": [
+    ///     {"name": "Jeb", "age": 30},
+    ///     {"name": "Bill", "age": 25},
+    ///     {"name": "Bob", "age": 40}
+    /// ]}"#)?;
+    /// 
+    /// assert!(new_buffer.list_sort(&["crew"], &["age"])?);
+    /// assert_eq!(new_buffer.get::<&str>(&["crew", "0", "name"])?, Some("Bill"));
+    /// assert_eq!(new_buffer.get::<&str>(&["crew", "2", "name"])?, Some("Bob"));
+    /// 
+    /// new_buffer.list_sort(&["crew"], &["name"])?;
+    /// assert_eq!(new_buffer.get::<&str>(&["crew", "0", "name"])?, Some("Bill"));
+    /// assert_eq!(new_buffer.get::<&str>(&["crew", "1", "name"])?, Some("Bob"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_sort(&mut self, path: &[&str], by: &[&str]) -> Result<bool, NP_Error> {
+
+        if self.is_watched() {
+            return self.journaled(path, |buffer| buffer.list_sort(path, by));
+        }
+
+        self.check_writable()?;
+
+        let list_cursor = match self.select(self.cursor.clone(), false, path)? {
+            Some(x) => x,
+            None => return Ok(false)
+        };
+
+        let key_schema = self.list_key_schema(list_cursor.schema_addr, by)?;
+
+        if list_cursor.get_value(&self.memory).get_addr_value() == 0 {
+            return Ok(false);
+        }
+
+        let items = NP_List::items(&list_cursor, &self.memory);
+
+        let mut keyed: Vec<(Vec<u8>, NP_Cursor)> = Vec::with_capacity(items.len());
+        for (_index, item) in items.iter() {
+            keyed.push((self.list_item_key(*item, by, key_schema)?, *item));
+        }
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let sorted: Vec<(usize, NP_Cursor)> = items.iter().zip(keyed.into_iter()).map(|((index, _), (_, item))| (*index, item)).collect();
+        NP_List::relink(&list_cursor, &self.memory, &sorted)?;
+
+        Ok(true)
+    }
+
+    /// Find the indexes of the list items that match a predicate.
+    /// 
+    /// The predicate gets an `NP_List_Item` for every item of the list that has a value, in list order.  Values inside the item are read with `get` and a path relative to the item, without copying anything out of the buffer.  Errors returned by the predicate stop the search and are passed back.
+    /// 
+    /// Returns an empty list if there's no list at the path.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "table", "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["age",  {"type": "u8"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]}
+    /// }"#)?;
+    /// 
+    /// let new_buffer = factory.json_buffer(r#"[
+    ///     {"name": "Jeb", "age": 30, "tags": ["pilot"]},
+    ///     {"name": "Bill", "age": 25, "tags": ["engineer"]},
+    ///     {"name": "Bob", "age": 40, "tags": ["scientist", "pilot"]}
+    /// ]"#)?;
+    /// 
+    /// let older = new_buffer.list_filter(&[], |item| Ok(item.get::<u8>(&["age"])? > Some(28)))?;
+    /// assert_eq!(older, vec![0, 2]);
+    /// 
+    /// let named_b = new_buffer.list_filter(&[], |item| Ok(item.get::<&str>(&["name"])?.unwrap_or("").starts_with("B")))?;
+    /// assert_eq!(named_b, vec![1, 2]);
+    /// 
+    /// let second_tag = new_buffer.list_filter(&[], |item| Ok(item.get::<&str>(&["tags", "1"])? == Some("pilot")))?;
+    /// assert_eq!(second_tag, vec![2]);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_filter<F>(&self, path: &[&str], mut predicate: F) -> Result<Vec<usize>, NP_Error> where F: FnMut(&NP_List_Item) -> Result<bool, NP_Error> {
+
+        let list_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let list_schema = match list_cursor {
+            Some(x) => x.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(x) => x,
+                None => return Ok(Vec::new())
+            }
+        };
+
+        if let NP_Parsed_Schema::List { .. } = &self.memory.schema[list_schema] { } else {
+            return Err(NP_Error::SchemaMismatch("Attempted to filter non list item!".into()));
+        }
+
+        let mut found: Vec<usize> = Vec::new();
+
+        if let Some(list_cursor) = list_cursor {
+            for (index, item) in NP_List::items(&list_cursor, &self.memory) {
+                if item.get_value(&self.memory).get_addr_value() == 0 {
+                    continue;
+                }
+                if predicate(&NP_List_Item { index, cursor: item, buffer: self })? {
+                    found.push(index);
+                }
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Get the next page of items from a list, starting after a saved `NP_ListCursor`.
+    /// 
+    /// Up to `count` items with values come back with a cursor pointing past the last of them, or `None` once the end of the list has been reached.  The cursor can be saved and passed back in a later call to pick up where the page ended, without walking the list from the head again.  Start from `NP_ListCursor::start()`.
+    /// 
+    /// The cursor holds the index and buffer address of the last item it passed.  Items keep their address while values are set and deleted, but compacting the buffer moves them, so cursors from before a compaction should be thrown away.  If the item at the address doesn't have the cursor index, the list is walked from the head to the first item past the cursor index instead.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_ListCursor;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "u32"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// for x in 0..25u32 {
+    ///     new_buffer.list_push(&[], x * 10)?;
+    /// }
+    /// 
+    /// let (page, next) = new_buffer.list_page(&[], NP_ListCursor::start(), 10)?;
+    /// assert_eq!(page.len(), 10);
+    /// assert_eq!(page[9].get::<u32>(&[])?, Some(90));
+    /// 
+    /// // save the cursor for later
+    /// let saved: [u8; 8] = next.unwrap().to_bytes();
+    /// 
+    /// let (page, next) = new_buffer.list_page(&[], NP_ListCursor::from_bytes(&saved)?, 10)?;
+    /// assert_eq!(page[0].index, 10);
+    /// assert_eq!(page[0].get::<u32>(&[])?, Some(100));
+    /// 
+    /// let (page, next) = new_buffer.list_page(&[], next.unwrap(), 10)?;
+    /// assert_eq!(page.len(), 5);
+    /// assert!(next.is_none());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn list_page<'page>(&'page self, path: &[&str], from: NP_ListCursor, count: usize) -> Result<(Vec<NP_List_Item<'page>>, Option<NP_ListCursor>), NP_Error> {
+
+        let list_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let list_schema = match list_cursor {
+            Some(x) => x.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(x) => x,
+                None => return Ok((Vec::new(), None))
+            }
+        };
+
+        let of = match &self.memory.schema[list_schema] {
+            NP_Parsed_Schema::List { of, .. } => *of,
+            _ => return Err(NP_Error::SchemaMismatch("Attempted to page through non list item!".into()))
+        };
+
+        let list_cursor = match list_cursor {
+            Some(x) if x.get_value(&self.memory).get_addr_value() != 0 => x,
+            _ => return Ok((Vec::new(), None))
+        };
+
+        let memory: &'page NP_Memory<'page> = &self.memory;
+        let item_at = |addr: usize| NP_Cursor::new(addr, of, list_schema);
+
+        // is the item the cursor passed still where it was?
+        let item_size = memory.addr_size.bytes() * 2 + 1;
+        let resume = match from.addr {
+            0 => None,
+            addr if addr > memory.root && addr + item_size <= memory.read_bytes().len() && item_at(addr).get_value(memory).get_index() as usize == from.index => Some(addr),
+            _ => None
+        };
+
+        let mut next_addr = match resume {
+            Some(addr) => item_at(addr).get_value(memory).get_next_addr() as usize,
+            None => NP_List::get_list(list_cursor.get_value(memory).get_addr_value() as usize, memory).get_head() as usize
+        };
+
+        let mut page: Vec<NP_List_Item<'page>> = Vec::new();
+        let mut last = from;
+
+        let mut last_index: Option<usize> = resume.map(|_| from.index);
+
+        while next_addr != 0 && page.len() < count {
+            let item = item_at(next_addr);
+            let item_value = item.get_value(memory);
+            let index = item_value.get_index() as usize;
+
+            // indexes always go up along the list, stop at damaged or looping links
+            if last_index.map(|last| index <= last).unwrap_or(false) {
+                next_addr = 0;
+                break;
+            }
+            last_index = Some(index);
+            next_addr = item_value.get_next_addr() as usize;
+
+            // walking from the head, skip everything the cursor already passed
+            if resume.is_none() && from.addr != 0 && index <= from.index {
+                continue;
+            }
+
+            last = NP_ListCursor { index, addr: item.buff_addr };
+            if item_value.get_addr_value() != 0 {
+                page.push(NP_List_Item { index, cursor: item, buffer: self });
+            }
+        }
+
+        if next_addr == 0 {
+            Ok((page, None))
+        } else {
+            Ok((page, Some(last)))
+        }
+    }
+
+    /// Iterate over the items of a list from the tail to the head.
+    /// 
+    /// Lists only link forward, so the item addresses are collected in one pass from the head first, then items are handed back last to first as `NP_List_Item` handles.  Taking the first few off the iterator gets the latest entries of the list without reading the values of the others.  Items without a value are skipped.
+    /// 
+    /// The iterator is empty if there's no list at the path.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.list_push(&[], "launch")?;
+    /// new_buffer.list_push(&[], "orbit")?;
+    /// new_buffer.list_push(&[], "land")?;
+    /// 
+    /// let latest: Vec<usize> = new_buffer.iter_list_rev(&[])?.take(2).map(|item| item.index).collect();
+    /// assert_eq!(latest, vec![2, 1]);
+    /// 
+    /// let last = new_buffer.iter_list_rev(&[])?.next().unwrap();
+    /// assert_eq!(last.get::<&str>(&[])?, Some("land"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn iter_list_rev<'iter>(&'iter self, path: &[&str]) -> Result<NP_List_Rev_Iterator<'iter>, NP_Error> {
+
+        let list_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let list_schema = match list_cursor {
+            Some(x) => x.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(x) => x,
+                None => return Ok(NP_List_Rev_Iterator { items: Vec::new(), buffer: self })
+            }
+        };
+
+        if let NP_Parsed_Schema::List { .. } = &self.memory.schema[list_schema] { } else {
+            return Err(NP_Error::SchemaMismatch("Attempted to reverse iterate non list item!".into()));
+        }
+
+        let items = match list_cursor {
+            Some(x) if x.get_value(&self.memory).get_addr_value() != 0 => NP_List::items(&x, &self.memory),
+            _ => Vec::new()
+        };
+
+        Ok(NP_List_Rev_Iterator { items, buffer: self })
+    }
+
+    /// Find the keys of the map entries that match a predicate.
+    /// 
+    /// The predicate gets an `NP_Map_Item` for every entry of the map that has a value, values inside the entry are read with `get` and a path relative to the entry.  Keys are returned in the order they're stored in the map, borrowed from the buffer.  Errors returned by the predicate stop the search and are passed back.
+    /// 
+    /// Returns an empty list if there's no map at the path.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "map",
+    ///    "value": {"type": "table", "columns": [
+    ///         ["role", {"type": "string"}],
+    ///         ["missions", {"type": "u16"}]
+    ///     ]}
+    /// }"#)?;
+    /// 
+    /// let new_buffer = factory.json_buffer(r#"{
+    ///     "jeb": {"role": "pilot", "missions": 12},
+    ///     "bill": {"role": "engineer", "missions": 3},
+    ///     "val": {"role": "pilot", "missions": 9}
+    /// }"#)?;
+    /// 
+    /// let mut pilots = new_buffer.map_find(&[], |item| Ok(item.get::<&str>(&["role"])? == Some("pilot")))?;
+    /// pilots.sort();
+    /// assert_eq!(pilots, vec!["jeb", "val"]);
+    /// 
+    /// let rookies = new_buffer.map_find(&[], |item| Ok(item.get::<u16>(&["missions"])? < Some(5)))?;
+    /// assert_eq!(rookies, vec!["bill"]);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn map_find<'find, F>(&'find self, path: &[&str], mut predicate: F) -> Result<Vec<&'find str>, NP_Error> where F: FnMut(&NP_Map_Item) -> Result<bool, NP_Error> {
+
+        let map_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let map_schema = match map_cursor {
+            Some(x) => x.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(x) => x,
+                None => return Ok(Vec::new())
+            }
+        };
+
+        if let NP_Parsed_Schema::Map { .. } = &self.memory.schema[map_schema] { } else {
+            return Err(NP_Error::SchemaMismatch("Attempted to find in non map item!".into()));
+        }
+
+        let mut found: Vec<&'find str> = Vec::new();
+
+        if let Some(map_cursor) = map_cursor {
+            let memory: &'find NP_Memory<'find> = &self.memory;
+            let mut map_iter = NP_Map::new_iter(&map_cursor, memory);
+            while let Some((key, item)) = map_iter.step_iter(memory) {
+                if item.get_value(memory).get_addr_value() == 0 {
+                    continue;
+                }
+                if predicate(&NP_Map_Item { key, cursor: item, buffer: self })? {
+                    found.push(key);
+                }
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Combine a number from every item of a list into one value.
+    /// 
+    /// The list is walked once and the value at `sub_path` in each item is read straight from the buffer, use an empty `sub_path` for lists of numbers.  Integers, floats and decimals can be folded, the result is always an `f64`.  Items that don't have a value at `sub_path` are skipped, schema defaults aren't used.
+    /// 
+    /// Returns `None` if no values were found, except for `NP_Fold::Sum` which is `0` then.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Fold;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "table", "columns": [
+    ///         ["item",  {"type": "string"}],
+    ///         ["price", {"type": "dec", "exp": 2}]
+    ///     ]}
+    /// }"#)?;
+    /// 
+    /// let new_buffer = factory.json_buffer(r#"[
+    ///     {"item": "fuel", "price": 12.5},
+    ///     {"item": "snacks"},
+    ///     {"item": "boosters", "price": 250.25}
+    /// ]"#)?;
+    /// 
+    /// assert_eq!(new_buffer.fold_numeric(&[], &["price"], NP_Fold::Sum)?, Some(262.75));
+    /// assert_eq!(new_buffer.fold_numeric(&[], &["price"], NP_Fold::Max)?, Some(250.25));
+    /// assert_eq!(new_buffer.fold_numeric(&[], &["price"], NP_Fold::Avg)?, Some(131.375));
+    /// 
+    /// // only numbers can be folded
+    /// assert!(new_buffer.fold_numeric(&[], &["item"], NP_Fold::Sum).is_err());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn fold_numeric(&self, path: &[&str], sub_path: &[&str], fold: NP_Fold) -> Result<Option<f64>, NP_Error> {
+
+        let list_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let list_schema = match list_cursor {
+            Some(x) => x.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(x) => x,
+                None => return Err(NP_Error::BadPath("Attempted to fold a path that isn't in the schema!".into()))
+            }
+        };
+
+        let of = match &self.memory.schema[list_schema] {
+            NP_Parsed_Schema::List { of, .. } => *of,
+            _ => return Err(NP_Error::SchemaMismatch("Attempted to fold non list item!".into()))
+        };
+
+        match self.schema_addr_from(of, sub_path).map(|addr| &self.memory.schema[addr]) {
+            Some(NP_Parsed_Schema::Int8 { .. }) | Some(NP_Parsed_Schema::Int16 { .. }) | Some(NP_Parsed_Schema::Int32 { .. }) | Some(NP_Parsed_Schema::Int64 { .. }) |
+            Some(NP_Parsed_Schema::Uint8 { .. }) | Some(NP_Parsed_Schema::Uint16 { .. }) | Some(NP_Parsed_Schema::Uint32 { .. }) | Some(NP_Parsed_Schema::Uint64 { .. }) |
+            Some(NP_Parsed_Schema::Float { .. }) | Some(NP_Parsed_Schema::Double { .. }) | Some(NP_Parsed_Schema::Decimal { .. }) => { },
+            _ => return Err(NP_Error::SchemaMismatch("Only integers, floats and decimals can be folded!".into()))
+        }
+
+        let mut count = 0usize;
+        let mut result = 0f64;
+
+        if let Some(list_cursor) = list_cursor {
+            let mut list_iter = NP_List::new_iter(&list_cursor, &self.memory, true, 0);
+            while let Some((_index, item)) = list_iter.step_iter(&self.memory) {
+                let item = match item {
+                    Some(x) if x.get_value(&self.memory).get_addr_value() != 0 => x,
+                    _ => continue
+                };
+                let value = match self.select(item, false, sub_path)? {
+                    Some(x) => match self.numeric_at(x)? {
+                        Some(value) => value,
+                        None => continue
+                    },
+                    None => continue
+                };
+                result = match (fold, count) {
+                    (_, 0) => value,
+                    (NP_Fold::Sum, _) | (NP_Fold::Avg, _) => result + value,
+                    (NP_Fold::Min, _) => result.min(value),
+                    (NP_Fold::Max, _) => result.max(value)
+                };
+                count += 1;
+            }
+        }
+
+        Ok(match (fold, count) {
+            (NP_Fold::Sum, _) => Some(result),
+            (_, 0) => None,
+            (NP_Fold::Avg, _) => Some(result / count as f64),
+            _ => Some(result)
+        })
+    }
+
+    /// Read the number at a cursor as an `f64`
+    fn numeric_at(&self, cursor: NP_Cursor) -> Result<Option<f64>, NP_Error> {
+        let memory = &self.memory;
+        Ok(match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Int8 { .. }    => i8::into_value(&cursor, memory)?.map(|x| x as f64),
+            NP_Parsed_Schema::Int16 { .. }   => i16::into_value(&cursor, memory)?.map(|x| x as f64),
+            NP_Parsed_Schema::Int32 { .. }   => i32::into_value(&cursor, memory)?.map(|x| x as f64),
+            NP_Parsed_Schema::Int64 { .. }   => i64::into_value(&cursor, memory)?.map(|x| x as f64),
+            NP_Parsed_Schema::Uint8 { .. }   => u8::into_value(&cursor, memory)?.map(|x| x as f64),
+            NP_Parsed_Schema::Uint16 { .. }  => u16::into_value(&cursor, memory)?.map(|x| x as f64),
+            NP_Parsed_Schema::Uint32 { .. }  => u32::into_value(&cursor, memory)?.map(|x| x as f64),
+            NP_Parsed_Schema::Uint64 { .. }  => u64::into_value(&cursor, memory)?.map(|x| x as f64),
+            NP_Parsed_Schema::Float { .. }   => f32::into_value(&cursor, memory)?.map(|x| x as f64),
+            NP_Parsed_Schema::Double { .. }  => f64::into_value(&cursor, memory)?,
+            NP_Parsed_Schema::Decimal { .. } => NP_Dec::into_value(&cursor, memory)?.map(|x| x.to_float()),
+            _ => None
+        })
+    }
+
+    /// Find the items of a list with a geo value within `radius_meters` of `center`.
+    /// 
+    /// The geo value at `sub_path` in each item is read straight from the buffer, use an empty `sub_path` for lists of geo values.  Returns the indexes of the matching items in list order, distances are measured with `NP_Geo::distance_meters`.  Items that don't have a value at `sub_path` are skipped, schema defaults aren't used.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::pointer::geo::NP_Geo;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "table", "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["where", {"type": "geo8"}]
+    ///     ]}
+    /// }"#)?;
+    /// 
+    /// let new_buffer = factory.json_buffer(r#"[
+    ///     {"name": "office",  "where": {"lat": 45.5231, "lng": -122.6765}},
+    ///     {"name": "airport", "where": {"lat": 45.5898, "lng": -122.5951}},
+    ///     {"name": "cafe",    "where": {"lat": 45.5202, "lng": -122.6742}},
+    ///     {"name": "unknown"}
+    /// ]"#)?;
+    /// 
+    /// let center = NP_Geo::new(8, 45.5231, -122.6765);
+    /// assert_eq!(new_buffer.geo_within(&[], &["where"], &center, 1000.0)?, vec![0, 2]);
+    /// assert_eq!(new_buffer.geo_within(&[], &["where"], &center, 20_000.0)?, vec![0, 1, 2]);
+    /// 
+    /// // only geo values can be searched
+    /// assert!(new_buffer.geo_within(&[], &["name"], &center, 1000.0).is_err());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn geo_within(&self, path: &[&str], sub_path: &[&str], center: &NP_Geo, radius_meters: f64) -> Result<Vec<usize>, NP_Error> {
+
+        let list_cursor = self.select(self.cursor, false, path)?;
+
+        let list_schema = match list_cursor {
+            Some(x) => x.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(x) => x,
+                None => return Err(NP_Error::BadPath("Attempted to search a path that isn't in the schema!".into()))
+            }
+        };
+
+        let of = match &self.memory.schema[list_schema] {
+            NP_Parsed_Schema::List { of, .. } => *of,
+            _ => return Err(NP_Error::SchemaMismatch("Attempted to search non list item!".into()))
+        };
+
+        match self.schema_addr_from(of, sub_path).map(|addr| &self.memory.schema[addr]) {
+            Some(NP_Parsed_Schema::Geo { .. }) => { },
+            _ => return Err(NP_Error::SchemaMismatch("Only geo values can be searched by distance!".into()))
+        }
+
+        let mut found = Vec::new();
+
+        if let Some(list_cursor) = list_cursor {
+            let mut list_iter = NP_List::new_iter(&list_cursor, &self.memory, true, 0);
+            while let Some((index, item)) = list_iter.step_iter(&self.memory) {
+                let item = match item {
+                    Some(x) if x.get_value(&self.memory).get_addr_value() != 0 => x,
+                    _ => continue
+                };
+                let geo = match self.select(item, false, sub_path)? {
+                    Some(x) => match NP_Geo::into_value(&x, &self.memory)? {
+                        Some(geo) => geo,
+                        None => continue
+                    },
+                    None => continue
+                };
+                if geo.distance_meters(center) <= radius_meters {
+                    found.push(index);
+                }
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Schema address of the value at `by` inside the items of a list, if the value can be sorted
+    fn list_key_schema(&self, list_schema_addr: usize, by: &[&str]) -> Result<usize, NP_Error> {
+        let of = match &self.memory.schema[list_schema_addr] {
+            NP_Parsed_Schema::List { of, .. } => *of,
+            _ => return Err(NP_Error::SchemaMismatch("Attempted to sort non list item!".into()))
+        };
+
+        let key_schema = match self.schema_addr_from(of, by) {
+            Some(x) => x,
+            None => {
+                let mut err = "Sort path not found in list items: ".to_owned();
+                err.push_str(&by.join("."));
+                return Err(NP_Error::BadPath(err));
+            }
+        };
+
+        match &self.memory.schema[key_schema] {
+            NP_Parsed_Schema::None | NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } => {
+                Err(NP_Error::SchemaMismatch("List items can only be sorted by scalars or tuples of scalars!".into()))
+            },
+            _ => Ok(key_schema)
+        }
+    }
+
+    /// Sortable bytes of the value at `by` inside a list item
+    fn list_item_key(&self, item: NP_Cursor, by: &[&str], key_schema: usize) -> Result<Vec<u8>, NP_Error> {
+        let key_cursor = if item.get_value(&self.memory).get_addr_value() == 0 { None } else { self.select(item, false, by)? };
+        let mut key: Vec<u8> = Vec::new();
+        sortable::encode(key_cursor, key_schema, &self.memory, &mut key)?;
+        Ok(key)
+    }
+
+    /// Get length of String, Bytes, Table, Tuple, List or Map Type
+    /// 
+    /// If the type found at the path provided does not support length operations, you'll get `None`.
+    /// 
+    /// If there is no value at the path provodid, you will get `None`.
+    /// 
+    /// If an item is found and it's length is zero, you can expect `Some(0)`.
+    /// 
+    /// ## String Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set initial value
+    /// new_buffer.set(&[], "hello")?;
+    /// // get length of value at root (String)
+    /// assert_eq!(new_buffer.length(&[])?, Some(5));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Collection (List) Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set value at 9th index
+    /// new_buffer.set(&["9"], "hello")?;
+    /// // get length of value at root (List)
+    /// assert_eq!(new_buffer.length(&[])?, Some(10));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Collection (Table) Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["age", {"type": "u8"}],
+    ///         ["name", {"type": "string"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // get length of value at root (Table)
+    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Collection (Map) Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "map",
+    ///    "value": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set values
+    /// new_buffer.set(&["foo"], "bar")?;
+    /// new_buffer.set(&["foo2"], "bar2")?;
+    /// // get length of value at root (Map)
+    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    /// ## Collection (Tuple) Example
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "tuple",
+    ///    "values": [
+    ///         {"type": "string"}, 
+    ///         {"type": "string"}
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // get length of value at root (Tuple)
+    /// assert_eq!(new_buffer.length(&[])?, Some(2));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn length(&self, path: &[&str]) -> Result<Option<usize>, NP_Error> {
+        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let found_cursor = if let Some(x) = value_cursor {
+            x
+        } else {
+            return Ok(None);
+        };
+
+        let addr_value = found_cursor.get_value(&self.memory).get_addr_value();
+
+
+        match &self.memory.schema[found_cursor.schema_addr] {
+            NP_Parsed_Schema::List { of, .. } => {
+                if addr_value == 0 {
+                    return Ok(None);
+                }
+
+                let list_data = NP_List::get_list(addr_value as usize, &self.memory);
+                let tail_addr = list_data.get_tail() as usize;
+                if tail_addr == 0 {
+                    Ok(Some(0))
+                } else {
+                    let tail_cursor = NP_Cursor::new(tail_addr, *of, found_cursor.schema_addr);
+                    let cursor_data = tail_cursor.get_value(&self.memory);
+                    Ok(Some(cursor_data.get_index() as usize + 1))
+                }
+            },
+            NP_Parsed_Schema::Map { .. } => {
+                if addr_value == 0 {
+                    return Ok(None);
+                }
+                let mut count = 0usize;
+                let mut map_iter = NP_Map::new_iter(&found_cursor, &self.memory);
+
+                // key is maybe in map
+                while let Some((_ikey, _item)) = map_iter.step_iter(&self.memory) {
+                    count += 1;
+                }
+
+                Ok(Some(count))
+            },
+            NP_Parsed_Schema::Table { columns, ..} => {
+                Ok(Some(columns.len()))
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                Ok(Some(values.len()))
+            },
+            NP_Parsed_Schema::Bytes {  size, ..} => {
+                if *size > 0 {
+                    Ok(Some(*size as usize))
+                } else {
+                    let length_bytes = self.memory.get_2_bytes(addr_value as usize).unwrap_or(&[0u8; 2]);
+                    Ok(Some(u16::from_be_bytes(*length_bytes) as usize))
+                }
+            },
+            NP_Parsed_Schema::UTF8String { size, .. } => {
+                if *size > 0 {
+                    Ok(Some(*size as usize))
+                } else {
+                    let length_bytes = self.memory.get_2_bytes(addr_value as usize).unwrap_or(&[0u8; 2]);
+                    Ok(Some(u16::from_be_bytes(*length_bytes) as usize))
+                }
+            },
+            _ => {
+                Ok(None)
+            }
+        }
+  
+    }
+
+    /// Set the value at a path to null.
+    /// 
+    /// Unlike `del`, which leaves the value the same as one that was never written, a null value is kept in the buffer.  `is_null` reports it, `get` returns `None` instead of the schema default, and `json_encode` provides a `null` for it.  `json_encode_with` keeps the `null` even when `emit_defaults` is off and values that were never written are left out.  Nulls are kept through compaction and `canonicalize`.  Setting a new value at the path replaces the null.
+    /// 
+    /// Any kind of value can be set to null, including collections.  Sortable buffers always hold every value, so this returns `false` for them without changing anything.  It also returns `false` if the path doesn't exist in the schema.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::json_flex::NP_JSON_Options;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["title", {"type": "string", "default": "none"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
+    /// new_buffer.set_null(&["title"])?;
+    /// 
+    /// assert_eq!(new_buffer.is_null(&["title"])?, true);
+    /// assert_eq!(new_buffer.is_null(&["name"])?, false);
+    /// assert_eq!(new_buffer.get::<&str>(&["title"])?, None);
+    /// assert_eq!("{\"name\":\"Jeb Kermin\",\"title\":null}", new_buffer.json_encode(&[])?.stringify());
+    /// 
+    /// // without defaults the null is still there
+    /// let options = NP_JSON_Options { emit_defaults: false, ..Default::default() };
+    /// assert_eq!("{\"name\":\"Jeb Kermin\",\"title\":null}", new_buffer.json_encode_with(&[], &options)?.stringify());
+    /// 
+    /// // a value that was never written gets the default instead
+    /// new_buffer.del(&["title"])?;
+    /// assert_eq!(new_buffer.is_null(&["title"])?, false);
+    /// assert_eq!(new_buffer.get::<&str>(&["title"])?, Some("none"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn set_null(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
+
+        if self.is_watched() {
+            return self.journaled(path, |buffer| buffer.set_null(path));
+        }
+
+        self.check_writable()?;
+
+        if self.sortable {
+            return Ok(false);
+        }
+
+        match self.select(self.cursor.clone(), true, path)? {
+            Some(x) => {
+                x.get_value(&self.memory).set_addr_value(NP_NULL_ADDR);
+                Ok(true)
+            },
+            None => Ok(false)
+        }
+    }
+
+    /// Check if the value at a path was set to null with `set_null`.
+    /// 
+    /// Values that were never written, or were deleted with `del`, aren't null.
+    /// 
+    pub fn is_null(&self, path: &[&str]) -> Result<bool, NP_Error> {
+        match self.select(self.cursor.clone(), false, path)? {
+            Some(x) => Ok(x.get_value(&self.memory).is_null()),
+            None => Ok(false)
+        }
+    }
+
+    /// Choose what `del` leaves behind in this buffer.
+    /// 
+    /// With tombstones on, deleting a path leaves a marker that tells "this was deleted" apart from "this was never set", so replication layers can merge buffers from several writers without deleted values coming back.  Everything else reads tombstones like values that were never set, `get` provides the schema default for them.  Use `is_deleted` to find them.
+    /// 
+    /// `NP_Tombstones::Keep` carries tombstones through `compact` and `canonicalize`, `NP_Tombstones::UntilCompaction` drops them the next time the buffer is compacted.  Switch to either of the other settings and compact to let go of kept tombstones.  The setting isn't stored in the buffer bytes, it has to be set again when the buffer is opened.  Sortable buffers never leave tombstones.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Tombstones;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["email", {"type": "string"}],
+    ///         ["phone", {"type": "string"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set_tombstones(NP_Tombstones::Keep);
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
+    /// new_buffer.set(&["email"], "jeb@kerbal.space")?;
+    /// new_buffer.del(&["email"])?;
+    /// new_buffer.compact(None)?;
+    /// 
+    /// assert_eq!(new_buffer.get::<&str>(&["email"])?, None);
+    /// assert_eq!(new_buffer.is_deleted(&["email"])?, true);
+    /// assert_eq!(new_buffer.is_deleted(&["phone"])?, false);
+    /// 
+    /// // tombstones are let go at the next compaction
+    /// new_buffer.set_tombstones(NP_Tombstones::Off);
+    /// new_buffer.compact(None)?;
+    /// assert_eq!(new_buffer.is_deleted(&["email"])?, false);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn set_tombstones(&mut self, tombstones: NP_Tombstones) {
+        self.memory.tombstones = tombstones;
+    }
+
+    /// Check if the value at a path was deleted and left a tombstone, see `set_tombstones`.
+    /// 
+    pub fn is_deleted(&self, path: &[&str]) -> Result<bool, NP_Error> {
+        match self.select(self.cursor.clone(), false, path)? {
+            Some(x) => Ok(x.get_value(&self.memory).is_tombstone()),
+            None => Ok(false)
+        }
+    }
+
+    /// Start recording changes so they can be undone with `undo` and redone with `redo`.
+    /// 
+    /// Each call to `set`, `set_json`, `set_null`, `del`, `del_recursive`, `from_owned_tree` or one of the `list_` methods records the path it changed and a copy of the value that was there before.  Changes that make new lists, maps or items in them record the collection instead, so undo takes the new items away too.  Undoing a change puts the old value back and keeps the newer one for `redo`, making a new change throws away whatever could be redone.  Changes made through `NP_Item` aren't recorded.
+    /// 
+    /// Compaction keeps the changes made with the cursor at the root, changes made below a moved cursor are forgotten.  `clear` forgets every change.  Restored values are written like any other value, so explicit nulls and tombstones come back as unset values.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["title", {"type": "string"}],
+    ///         ["tags",  {"type": "list", "of": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.start_journal();
+    /// new_buffer.set(&["title"], "draft")?;
+    /// new_buffer.set(&["title"], "final")?;
+    /// new_buffer.list_push(&["tags"], "urgent")?;
+    /// assert_eq!(new_buffer.journal().len(), 3);
+    /// 
+    /// assert!(new_buffer.undo()?);
+    /// assert_eq!(new_buffer.get::<&str>(&["tags", "0"])?, None);
+    /// assert!(new_buffer.undo()?);
+    /// assert_eq!(new_buffer.get::<&str>(&["title"])?, Some("draft"));
+    /// 
+    /// assert!(new_buffer.redo()?);
+    /// assert_eq!(new_buffer.get::<&str>(&["title"])?, Some("final"));
+    /// 
+    /// // a new change can't be redone past
+    /// new_buffer.set(&["title"], "other")?;
+    /// assert_eq!(new_buffer.redo()?, false);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn start_journal(&mut self) {
+        if self.journal.is_none() {
+            self.journal = Some(NP_Journal::default());
+        }
+    }
+
+    /// Stop recording changes and forget the ones that were recorded.
+    /// 
+    pub fn stop_journal(&mut self) {
+        self.journal = None;
+    }
+
+    /// Changes that can be undone, oldest first.  Empty if the journal hasn't been started.
+    /// 
+    pub fn journal(&self) -> &[NP_Change] {
+        match &self.journal {
+            Some(journal) => &journal.undo,
+            None => &[]
+        }
+    }
+
+    /// Undo the newest recorded change, see `start_journal`.
+    /// 
+    /// Returns `false` if there's nothing to undo.
+    /// 
+    pub fn undo(&mut self) -> Result<bool, NP_Error> {
+        let change = match self.journal.as_mut().and_then(|journal| journal.undo.pop()) {
+            Some(change) => change,
+            None => return Ok(false)
+        };
+        let reverse = self.restore(&change)?;
+        if let Some(journal) = self.journal.as_mut() {
+            journal.redo.push(reverse);
+        }
+        Ok(true)
+    }
+
+    /// Redo the newest change that was undone, see `start_journal`.
+    /// 
+    /// Returns `false` if there's nothing to redo.
+    /// 
+    pub fn redo(&mut self) -> Result<bool, NP_Error> {
+        let change = match self.journal.as_mut().and_then(|journal| journal.redo.pop()) {
+            Some(change) => change,
+            None => return Ok(false)
+        };
+        let reverse = self.restore(&change)?;
+        if let Some(journal) = self.journal.as_mut() {
+            journal.undo.push(reverse);
+        }
+        Ok(true)
+    }
+
+    /// Call `callback` with the path, old value and new value of every change made to this buffer.
+    /// 
+    /// Every method that sets or deletes values calls the observers once per call with the path that was passed to it, changes made through a moved cursor report paths below the cursor.  Undo and redo are observed too.  Observers are called after the change is made and aren't called for changes that fail.
+    /// 
+    /// Values are copied into owned values for the callbacks, so observed buffers make changes slower by about the size of the changed values.  `clear` and compaction don't call the observers.
+    /// 
+    /// Callbacks can't borrow from their surroundings since they live as long as the buffer, share state with them through an `Arc` instead.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::owned::NP_Value_Owned;
+    /// use std::sync::{Arc, Mutex};
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["title", {"type": "string"}],
+    ///         ["tags",  {"type": "list", "of": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let dirty: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    /// let log = Arc::clone(&dirty);
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.on_change(move |path, old, new| {
+    ///     log.lock().unwrap().push(format!("{}: {:?} -> {:?}", path.join("."), old, new));
+    /// });
+    /// 
+    /// new_buffer.set(&["title"], "draft")?;
+    /// new_buffer.set(&["title"], "final")?;
+    /// new_buffer.list_push(&["tags"], "urgent")?;
+    /// new_buffer.del(&["title"])?;
+    /// 
+    /// assert_eq!(*dirty.lock().unwrap(), vec![
+    ///     r#"title: Null -> String("draft")"#,
+    ///     r#"title: String("draft") -> String("final")"#,
+    ///     r#"tags: Null -> List([String("urgent")])"#,
+    ///     r#"title: String("final") -> Null"#
+    /// ]);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn on_change<F>(&mut self, callback: F) where F: FnMut(&[&str], &NP_Value_Owned, &NP_Value_Owned) + Send + 'static {
+        self.observers.push(Box::new(callback));
+    }
+
+    /// Remove every callback registered with `on_change`.
+    /// 
+    pub fn clear_on_change(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Does the journal or an observer need to know about changes?
+    fn is_watched(&self) -> bool {
+        self.journal.is_some() || !self.observers.is_empty()
+    }
+
+    /// Owned copy of the value at `path`
+    fn owned_value(&self, path: &[&str]) -> Result<NP_Value_Owned, NP_Error> {
+        match self.select(self.cursor.clone(), false, path)? {
+            Some(x) => NP_Value_Owned::from_cursor(&x, &self.memory),
+            None => Ok(NP_Value_Owned::Null)
+        }
+    }
+
+    /// Length of the part of `path` a change there has to record so undo can take it back.
+    /// 
+    /// Setting a value can make the collections and list or map items above it, undo has to remove those too.  The path is cut at the first value that doesn't exist yet, or at the list or map holding it so the whole collection is put back.
+    fn undo_depth(&self, path: &[&str]) -> Result<usize, NP_Error> {
+        for depth in 1..=path.len() {
+            if self.select(self.cursor, false, &path[0..depth])?.is_none() {
+                let parent = self.schema_addr_at(&path[0..(depth - 1)]).map(|addr| &self.memory.schema[addr]);
+                return Ok(match parent {
+                    Some(NP_Parsed_Schema::List { .. }) | Some(NP_Parsed_Schema::Map { .. }) => depth - 1,
+                    _ => depth
+                });
+            }
+        }
+        Ok(path.len())
+    }
+
+    /// Record the value at `path` before it's changed, the old value is kept for `notify` if there are observers
+    fn record(&mut self, path: &[&str]) -> Result<Option<NP_Value_Owned>, NP_Error> {
+        if !self.is_watched() {
+            return Ok(None);
+        }
+
+        if self.journal.is_some() {
+            let change_path = &path[0..self.undo_depth(path)?];
+            let old = self.owned_value(change_path)?;
+            if let Some(journal) = self.journal.as_mut() {
+                journal.undo.push(NP_Change { path: change_path.iter().map(|x| (*x).to_owned()).collect(), old, cursor: self.cursor.clone() });
+                journal.redo.clear();
+            }
+        }
+
+        if self.observers.is_empty() { Ok(None) } else { Ok(Some(self.owned_value(path)?)) }
+    }
+
+    /// Tell the observers about a change at `path` that was recorded with `record`
+    fn notify(&mut self, path: &[&str], old: Option<NP_Value_Owned>) -> Result<(), NP_Error> {
+        if let Some(old) = old {
+            let new = self.owned_value(path)?;
+            let mut observers = core::mem::take(&mut self.observers);
+            for observer in observers.iter_mut() {
+                observer(path, &old, &new);
+            }
+            self.observers = observers;
+        }
+        Ok(())
+    }
+
+    /// Record the value at `path`, then make a change without recording or observing the smaller changes it's made of
+    fn journaled<T, F>(&mut self, path: &[&str], change: F) -> Result<T, NP_Error> where F: FnOnce(&mut Self) -> Result<T, NP_Error> {
+        let old = self.record(path)?;
+        let journal = self.journal.take();
+        let observers = core::mem::take(&mut self.observers);
+        let result = change(self);
+        self.journal = journal;
+        self.observers = observers;
+        match result {
+            Ok(value) => {
+                self.notify(path, old)?;
+                Ok(value)
+            },
+            Err(e) => Err(self.unrecord(e))
+        }
+    }
+
+    /// Forget the change `record` just recorded because making it failed, gives back the error
+    fn unrecord(&mut self, err: NP_Error) -> NP_Error {
+        if let Some(journal) = self.journal.as_mut() {
+            journal.undo.pop();
+        }
+        err
+    }
+
+    /// Put the old value of a change back, returns the change that reverses it
+    fn restore(&mut self, change: &NP_Change) -> Result<NP_Change, NP_Error> {
+        let journal = self.journal.take();
+        let observers = core::mem::take(&mut self.observers);
+        let cursor = core::mem::replace(&mut self.cursor, change.cursor.clone());
+
+        let result = (|| {
+            let path: Vec<&str> = change.path.iter().map(|x| x.as_str()).collect();
+            let current = match self.select(self.cursor.clone(), false, &path)? {
+                Some(x) => {
+                    let current = NP_Value_Owned::from_cursor(&x, &self.memory)?;
+                    // sortable buffers always hold every value, they're written over instead
+                    if !self.sortable {
+                        x.get_value(&self.memory).set_addr_value(0);
+                    }
+                    current
+                },
+                None => NP_Value_Owned::Null
+            };
+            if change.old != NP_Value_Owned::Null {
+                change.old.write_to(self, &mut change.path.clone())?;
+            }
+            Ok(NP_Change { path: change.path.clone(), old: current, cursor: change.cursor.clone() })
+        })();
+
+        self.journal = journal;
+        self.observers = observers;
+
+        // observers see undo and redo like any other change
+        if let Ok(reverse) = &result {
+            if !self.observers.is_empty() {
+                let path: Vec<&str> = change.path.iter().map(|x| x.as_str()).collect();
+                self.notify(&path, Some(reverse.old.clone()))?;
+            }
+        }
+
+        self.cursor = cursor;
+        result
+    }
+
+    /// Forget recorded changes that were made below a cursor moved away from the root, compaction moves everything but the root
+    fn forget_moved_changes(&mut self) {
+        let root = self.memory.root;
+        if let Some(journal) = self.journal.as_mut() {
+            journal.undo.retain(|change| change.cursor.buff_addr == root);
+            journal.redo.retain(|change| change.cursor.buff_addr == root);
+        }
+    }
+
+    /// Clear an inner value from the buffer.
+    /// This can also be used to clear deeply nested collection objects or scalar objects.
+    /// 
+    /// Returns `true` if it found a value to delete (and deleted it), `false` otherwise.  With tombstones on (see `set_tombstones`) a tombstone is left even if nothing was set at the path, and this returns `true` if the path exists in the schema.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///     "of": {"type": "string"}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set index 0
+    /// new_buffer.set(&["0"], "hello")?;
+    /// // del index 0
+    /// new_buffer.del(&["0"])?;
+    /// // value is gone now!
+    /// assert_eq!(None, new_buffer.get::<&str>(&["0"])?);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn del(&mut self, path: &[&str]) -> Result<bool, NP_Error> {
+
+        if self.is_watched() {
+            return self.journaled(path, |buffer| buffer.del(path));
+        }
+
+        self.check_writable()?;
+
+        let tombstone = self.memory.tombstones != NP_Tombstones::Off && !self.sortable;
+
+        let value_cursor = self.select(self.cursor.clone(), tombstone, path)?;
+        
+        match value_cursor {
+            Some(x) => {
+                if self.sortable {
+                    match &self.memory.schema[x.schema_addr] {
+                        NP_Parsed_Schema::Table { .. } => { return Ok(false) },
+                        NP_Parsed_Schema::Tuple { .. } => { return Ok(false) },
+                        NP_Parsed_Schema::List { .. } => { return Ok(false) },
+                        NP_Parsed_Schema::Map { .. } => { return Ok(false) },
+                        _ => NP_Cursor::set_default(x, &self.memory)?
+                    }
+                } else if tombstone {
+                    x.get_value(&self.memory).set_addr_value(NP_TOMBSTONE_ADDR);
+                } else {
+                    // clear value address in buffer
+                    x.get_value(&self.memory).set_addr_value(0);
+                }
+
+                Ok(true)
+            }
+            None => Ok(false)
+        }
+    }
+
+    /// Clear a value and every value nested below it.
+    ///
+    /// `del` only zeros the pointer at the provided path, this also walks the whole subtree and zeros every child pointer.  Nothing below the path can be reached after this runs, even through stale addresses.
+    ///
+    /// If the buffer cursor (or cursor backup) was pointing inside the deleted subtree it will be reset to the root.
+    ///
+    /// Returns the number of bytes that were freed (and can be reclaimed with a compaction), or `None` if no value was found at the path.
+    ///
+    /// Sortable buffers can't delete collections, like `del` scalar values will be set to their default instead.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    ///
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}],
+    ///         ["meta", {"type": "map", "value": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    ///
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["tags", "0"], "hello")?;
+    /// new_buffer.set(&["tags", "1"], "world")?;
+    /// new_buffer.set(&["meta", "key"], "value")?;
+    ///
+    /// // delete the list and everything in it
+    /// assert_eq!(new_buffer.del_recursive(&["tags"])?, Some(28));
+    /// assert_eq!(new_buffer.json_encode(&["tags"])?.stringify(), "null");
+    /// assert_eq!(new_buffer.get::<&str>(&["meta", "key"])?, Some("value"));
+    /// // the freed bytes show up as wasted space
+    /// assert_eq!(new_buffer.calc_bytes()?.wasted_bytes, 28);
+    /// // nothing to delete here
+    /// assert_eq!(new_buffer.del_recursive(&["meta", "other key"])?, None);
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    ///
+    pub fn del_recursive(&mut self, path: &[&str]) -> Result<Option<usize>, NP_Error> {
+
+        if self.is_watched() {
+            return self.journaled(path, |buffer| buffer.del_recursive(path));
+        }
+
+        self.check_writable()?;
+
+        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let found_cursor = match value_cursor {
+            Some(x) => x,
+            None => return Ok(None)
+        };
+
+        if self.sortable {
+            return if self.del(path)? { Ok(Some(0)) } else { Ok(None) };
+        }
+
+        let freed = NP_Cursor::calc_size(&found_cursor, &self.memory)? - found_cursor.get_value(&self.memory).get_size();
+
+        let mut removed: Vec<usize> = Vec::new();
+        Self::del_children(&found_cursor, &self.memory, &mut removed);
+
+        // clear value address in buffer
+        found_cursor.get_value(&self.memory).set_addr_value(0);
+
+        // cursors inside the deleted subtree are no longer valid
+        if removed.contains(&self.cursor.buff_addr) {
+            self.cursor = NP_Cursor::new(self.memory.root, 0, 0);
+        }
+        if removed.contains(&self.backup_cursor.buff_addr) {
+            self.backup_cursor = NP_Cursor::new(self.memory.root, 0, 0);
+        }
+
+        Ok(Some(freed))
+    }
+
+    fn del_children(cursor: &NP_Cursor, memory: &NP_Memory, removed: &mut Vec<usize>) {
+
+        if cursor.get_value(memory).get_addr_value() == 0 {
+            return;
+        }
+
+        let mut clear = |child: NP_Cursor| {
+            Self::del_children(&child, memory, removed);
+            child.get_value(memory).set_addr_value(0);
+            removed.push(child.buff_addr);
+        };
+
+        match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Table { .. } => {
+                let mut table_iter = NP_Table::new_iter(cursor, memory);
+                while let Some((_index, _key, item)) = table_iter.step_iter(memory) {
+                    if let Some(child) = item { clear(child); }
+                }
+            },
+            NP_Parsed_Schema::Tuple { .. } => {
+                let mut tuple_iter = NP_Tuple::new_iter(cursor, memory);
+                while let Some((_index, item)) = tuple_iter.step_iter(memory) {
+                    if let Some(child) = item { clear(child); }
+                }
+            },
+            NP_Parsed_Schema::List { .. } => {
+                let mut list_iter = NP_List::new_iter(cursor, memory, true, 0);
+                while let Some((_index, item)) = list_iter.step_iter(memory) {
+                    if let Some(child) = item { clear(child); }
+                }
+            },
+            NP_Parsed_Schema::Map { .. } => {
+                let mut map_iter = NP_Map::new_iter(cursor, memory);
+                while let Some((_key, child)) = map_iter.step_iter(memory) {
+                    clear(child);
+                }
+            },
+            _ => { }
+        }
+    }
+
+    /// Retrieve an inner value from the buffer. 
+    /// 
+    /// The type that you cast the request to will be compared to the schema, if it doesn't match the schema the request will fail.
+    /// 
+    /// Paths through list items that haven't been set return `None`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// // a list where each item is a map where each key has a value containing a list of strings
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "list",
+    ///    "of": {"type": "map", "value": {
+    ///         "type": "list", "of": {"type": "string"}
+    ///     }}
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // third item in the top level list -> key "alpha" of map at 3rd element -> 9th element of list at "alpha" key
+    /// // 
+    /// new_buffer.set(&["3", "alpha", "9"], "who would build a schema like this")?;
+    /// 
+    /// // get the same item we just set
+    /// let message = new_buffer.get::<&str>(&["3", "alpha", "9"])?;
+    /// 
+    /// assert_eq!(message, Some("who would build a schema like this"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get<'get, X: 'get>(&'get self, path: &[&str]) -> Result<Option<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
+        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+        self.get_at(value_cursor, || self.schema_addr_at(path))
+    }
+
+    /// Get the value of an `any` field, along with it's type.
+    /// 
+    /// Check out documentation [here](../pointer/any/index.html).
+    /// 
+    pub fn get_any(&self, path: &[&str]) -> Result<Option<NP_AnyValue>, NP_Error> {
+        self.get(path)
+    }
+
+    /// Retrieve an inner value from the buffer with a path compiled by `NP_Factory::compile_path`.
+    /// 
+    /// Works like `get`, but column names and indexes were already resolved against the schema.  Compiled paths always start at the root of the buffer, the buffer cursor is ignored.
+    /// 
+    /// Check out documentation [here](../path/index.html).
+    /// 
+    pub fn get_with<'get, X: 'get>(&'get self, path: &NP_Path) -> Result<Option<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar {
+        let value_cursor = self.select_path(path)?;
+        self.get_at(value_cursor, || Some(path.schema_addr))
+    }
+
+    /// Retrieve an integer from the buffer, widening any narrower integer type the schema stores at this path.
+    /// 
+    /// Code that reports on numbers doesn't have to match over every integer type in the schema.  Only conversions that can't lose information are allowed, `get_coerced::<i64>` reads `i8` to `i64` and `u8` to `u32` values but a `u64` value returns an error.  If the value isn't set the widened default from the schema is returned.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["age",   {"type": "u8"}],
+    ///         ["score", {"type": "i32", "default": -20}],
+    ///         ["views", {"type": "u64"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["age"], 200u8)?;
+    /// 
+    /// assert_eq!(new_buffer.get_coerced::<i64>(&["age"])?, Some(200i64));
+    /// assert_eq!(new_buffer.get_coerced::<i64>(&["score"])?, Some(-20i64));
+    /// assert!(new_buffer.get_coerced::<i64>(&["views"]).is_err());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get_coerced<X>(&self, path: &[&str]) -> Result<Option<X>, NP_Error> where X: NP_Coerce {
+        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let schema_addr = match value_cursor {
+            Some(cursor) => cursor.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(addr) => addr,
+                None => return Ok(None)
+            }
+        };
+
+        let type_key = self.memory.schema[schema_addr].get_type_key();
+
+        if X::can_coerce(type_key) == false {
+            let mut err = "TypeError: Attempted to coerce value for schema of type (".to_owned();
+            err.push_str(self.memory.schema[schema_addr].get_type_data().0);
+            err.push_str(") into a type that can't hold every value\n");
+            return Err(NP_Error::SchemaMismatch(err));
+        }
+
+        let addr = || Some(schema_addr);
+
+        let value = match type_key {
+            NP_TypeKeys::Int8   => self.get_at::<i8, _>(value_cursor, addr)?.map(|x| x as i128),
+            NP_TypeKeys::Int16  => self.get_at::<i16, _>(value_cursor, addr)?.map(|x| x as i128),
+            NP_TypeKeys::Int32  => self.get_at::<i32, _>(value_cursor, addr)?.map(|x| x as i128),
+            NP_TypeKeys::Int64  => self.get_at::<i64, _>(value_cursor, addr)?.map(|x| x as i128),
+            NP_TypeKeys::Uint8  => self.get_at::<u8, _>(value_cursor, addr)?.map(|x| x as i128),
+            NP_TypeKeys::Uint16 => self.get_at::<u16, _>(value_cursor, addr)?.map(|x| x as i128),
+            NP_TypeKeys::Uint32 => self.get_at::<u32, _>(value_cursor, addr)?.map(|x| x as i128),
+            NP_TypeKeys::Uint64 => self.get_at::<u64, _>(value_cursor, addr)?.map(|x| x as i128),
+            _ => None
+        };
+
+        Ok(value.map(X::from_widened))
+    }
+
+    /// Read the value at a cursor, `schema_addr` finds the schema for values that haven't been created in the buffer yet
+    fn get_at<'get, X: 'get, F>(&'get self, value_cursor: Option<NP_Cursor>, schema_addr: F) -> Result<Option<X>, NP_Error> where X: NP_Value<'get> + NP_Scalar, F: FnOnce() -> Option<usize> {
+        match value_cursor {
+            Some(x) => {
+                                
+                // type does not match schema
+                if X::type_idx().1 != *self.memory.schema[x.schema_addr].get_type_key() {
+                    let mut err = "TypeError: Attempted to get value for type (".to_owned();
+                    err.push_str(X::type_idx().0);
+                    err.push_str(") for schema of type (");
+                    err.push_str(self.memory.schema[x.schema_addr].get_type_data().0);
+                    err.push_str(")\n");
+                    return Err(NP_Error::SchemaMismatch(err));
+                }
+
+                // explicit nulls don't fall back to the schema default
+                if x.get_value(&self.memory).is_null() {
+                    return Ok(None);
+                }
+
+                match X::into_value(&x, &self.memory)? {
+                    Some(x) => {
+                        Ok(Some(x))
+                    },
+                    None => { // no value found here, return default from schema
+                        match X::schema_default(&self.memory.schema[x.schema_addr]) {
+                            Some(y) => {
+                                Ok(Some(y))
+                            },
+                            None => { // no default in schema, no value to provide
+                                Ok(None)
+                            }
+                        }                        
+                    }
+                }
+            }
+            None => { // the collections holding this value don't exist yet, use the default from the schema
+                match schema_addr().and_then(|addr| self.memory.schema.get(addr)) {
+                    Some(schema) if X::type_idx().1 == *schema.get_type_key() => Ok(X::schema_default(schema)),
+                    _ => Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Borrow a string straight out of the buffer without copying it.
+    /// 
+    /// The string bytes are checked to be valid UTF8 before they're returned, if they aren't an error is returned.  The string can be used for as long as the buffer is borrowed.  If the value isn't set the default value from the schema is returned.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["title", {"type": "string", "default": "none"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
+    /// 
+    /// assert_eq!(new_buffer.get_str(&["name"])?, Some("Jeb Kermin"));
+    /// assert_eq!(new_buffer.get_str(&["title"])?, Some("none"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get_str<'get>(&'get self, path: &[&str]) -> Result<Option<&'get str>, NP_Error> {
+        self.get::<&str>(path)
+    }
+
+    /// Borrow a byte slice straight out of the buffer without copying it.
+    /// 
+    /// The slice can be used for as long as the buffer is borrowed.  If the value isn't set the default value from the schema is returned.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["hash", {"type": "bytes", "size": 4}],
+    ///         ["blob", {"type": "bytes"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["hash"], &[1u8, 2, 3, 4] as &[u8])?;
+    /// 
+    /// assert_eq!(new_buffer.get_bytes(&["hash"])?, Some(&[1u8, 2, 3, 4][..]));
+    /// assert_eq!(new_buffer.get_bytes(&["blob"])?, None);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get_bytes<'get>(&'get self, path: &[&str]) -> Result<Option<&'get [u8]>, NP_Error> {
+        self.get::<&[u8]>(path)
+    }
+
+    /// Borrow the selected choice of an option value from the schema.
+    /// 
+    /// Unlike `get::<NP_Enum>`, the choice isn't copied into a new `String`.  If the value isn't set the default choice from the schema is returned.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::pointer::option::NP_Enum;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["color", {"type": "option", "choices": ["red", "green", "blue"]}],
+    ///         ["size",  {"type": "option", "choices": ["small", "large"], "default": "small"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["color"], NP_Enum::new("blue"))?;
+    /// 
+    /// assert_eq!(new_buffer.get_choice(&["color"])?, Some("blue"));
+    /// assert_eq!(new_buffer.get_choice(&["size"])?, Some("small"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn get_choice<'get>(&'get self, path: &[&str]) -> Result<Option<&'get str>, NP_Error> {
+        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let schema_addr = match value_cursor {
+            Some(cursor) => cursor.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(addr) => addr,
+                None => return Ok(None)
+            }
+        };
+
+        let (choices, default) = match &self.memory.schema[schema_addr] {
+            NP_Parsed_Schema::Enum { choices, default, .. } => (choices, default),
+            _ => return Err(NP_Error::SchemaMismatch("TypeError: Attempted to get choice for a value that isn't an option!".into()))
+        };
+
+        let choice = value_cursor.and_then(|cursor| {
+            let value_addr = cursor.get_value(&self.memory).get_addr_value() as usize;
+            if value_addr == 0 {
+                None
+            } else {
+                self.memory.get_1_byte(value_addr).and_then(|index| choices.get(index as usize))
+            }
+        }).or(default.as_ref());
+
+        Ok(match choice {
+            Some(NP_Enum::Some(x)) => Some(x.as_str()),
+            _ => None
+        })
+    }
+
+    /// Get the order preserving bytes of the scalar or tuple at a path.
+    /// 
+    /// The bytes sort the same way the values do, so a field can be used as a key in ordered storage without closing the whole buffer.  Tuples get the bytes of each of their values, one after another.  Values that haven't been set use the schema default.  The encoding for each type is listed [here](../sortable/index.html).
+    /// 
+    /// Fails for lists, maps, tables and paths that aren't in the schema.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["id", {"type": "i32"}],
+    ///         ["name", {"type": "string"}],
+    ///         ["place", {"type": "tuple", "values": [{"type": "string"}, {"type": "u8", "default": 3}]}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["id"], -2i32)?;
+    /// new_buffer.set(&["place", "0"], "ab")?;
+    /// 
+    /// assert_eq!(new_buffer.sortable_bytes(&["id"])?, vec![127, 255, 255, 254]);
+    /// assert_eq!(new_buffer.sortable_bytes(&["name"])?, vec![0, 0]);
+    /// assert_eq!(new_buffer.sortable_bytes(&["place"])?, vec![97, 98, 0, 0, 3]);
+    /// 
+    /// assert!(new_buffer.sortable_bytes(&["tags"]).is_err());
+    /// assert!(new_buffer.sortable_bytes(&["age"]).is_err());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn sortable_bytes(&self, path: &[&str]) -> Result<Vec<u8>, NP_Error> {
+        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let schema_addr = match value_cursor {
+            Some(cursor) => cursor.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(addr) => addr,
+                None => return Err(NP_Error::BadPath("Attempted to get sortable bytes for a path that isn't in the schema!".into()))
+            }
+        };
+
+        let mut bytes: Vec<u8> = Vec::new();
+        sortable::encode(value_cursor, schema_addr, &self.memory, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Build an index over a list of tables, keyed by one of the table columns.
+    /// 
+    /// The index finds list items by the value of their column without reading the rest of the list.  Check out documentation [here](../index/index.html).
+    /// 
+    pub fn build_index(&self, path: &[&str], column: &str) -> Result<NP_Index<'buffer>, NP_Error> {
+        let list_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let schema_addr = match list_cursor {
+            Some(cursor) => cursor.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(addr) => addr,
+                None => return Err(NP_Error::BadPath("Attempted to build index for a path that isn't in the schema!".into()))
+            }
+        };
+
+        NP_Index::build(list_cursor, schema_addr, column, &self.memory)
+    }
+
+    /// Build a bloom filter over the keys of a map or the values of a list, `bits` is rounded up to whole bytes.
+    /// 
+    /// The filter can be stored next to the buffer to test if a key might be in the buffer without opening it.  Check out documentation [here](../bloom/index.html).
+    /// 
+    pub fn bloom_filter(&self, path: &[&str], bits: usize) -> Result<NP_Bloom_Filter, NP_Error> {
+        let value_cursor = self.select(self.cursor.clone(), false, path)?;
+
+        let schema_addr = match value_cursor {
+            Some(cursor) => cursor.schema_addr,
+            None => match self.schema_addr_at(path) {
+                Some(addr) => addr,
+                None => return Err(NP_Error::BadPath("Attempted to build bloom filter for a path that isn't in the schema!".into()))
+            }
+        };
+
+        NP_Bloom_Filter::build(value_cursor, schema_addr, bits, &self.memory)
+    }
+
+    /// This performs a compaction if the closure provided as the second argument returns `true`.
+    /// Compaction is a pretty expensive operation (requires full copy of the whole buffer) so should be done sparingly.
+    /// The closure is provided an argument that contains the original size of the buffer, how many bytes could be saved by compaction, and how large the new buffer would be after compaction.  The closure should return `true` to perform compaction, `false` otherwise.
+    /// 
+    /// The first argument, new_capacity, is the capacity of the underlying Vec<u8> that we'll be copying the data into.  The default is the size of the old buffer.
+    /// 
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set initial value
+    /// new_buffer.set(&[], "hello")?;
+    /// // using 9 bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 10,
+    ///     after_compaction: 10,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// // update the value
+    /// new_buffer.set(&[], "hello, world")?;
+    /// // now using 25 bytes, with 7 bytes of wasted space
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 24,
+    ///     after_compaction: 17,
+    ///     wasted_bytes: 7
+    /// }, new_buffer.calc_bytes()?);
+    /// // compact to save space
+    /// new_buffer.maybe_compact(None, |compact_data| {
+    ///     // only compact if wasted bytes are greater than 5
+    ///     if compact_data.wasted_bytes > 5 {
+    ///         true
+    ///     } else {
+    ///         false
+    ///     }
+    /// })?;
+    /// // back down to 18 bytes with no wasted bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 17,
+    ///     after_compaction: 17,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn maybe_compact<F>(&mut self, new_capacity: Option<u32>, mut callback: F) -> Result<(), NP_Error> where F: FnMut(NP_Size_Data) -> bool {
+
+        let bytes_data = self.calc_bytes()?;
+
+        if callback(bytes_data) {
+            self.compact(new_capacity)?;
+        }
+
+        return Ok(());
+    }
+
+    /// Compacts a buffer to remove an unused bytes or free space after a mutation.
+    /// This is a pretty expensive operation (requires full copy of the whole buffer) so should be done sparingly.
+    /// 
+    /// The first argument, new_capacity, is the capacity of the underlying Vec<u8> that we'll be copying the data into.  The default is the size of the old buffer.
+    /// 
+    /// Fixed size buffers are compacted into a temporary allocation, then copied back into their borrowed bytes.
+    /// 
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// // set initial value
+    /// new_buffer.set(&[], "hello")?;
+    /// // using 11 bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 10,
+    ///     after_compaction: 10,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// // update the value
+    /// new_buffer.set(&[], "hello, world")?;
+    /// // now using 25 bytes, with 7 bytes of wasted bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 24,
+    ///     after_compaction: 17,
+    ///     wasted_bytes: 7
+    /// }, new_buffer.calc_bytes()?);
+    /// // compact to save space
+    /// new_buffer.compact(None)?;
+    /// // back down to 18 bytes with no wasted bytes
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 17,
+    ///     after_compaction: 17,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn compact<'compact>(&mut self, new_capacity: Option<u32>) -> Result<(), NP_Error> {
+
+        let capacity = match new_capacity {
+            Some(x) => { x as usize },
+            None => self.memory.read_bytes().len()
+        };
+
+        let old_root = NP_Cursor::new(self.memory.root, 0, 0);
+
+        // keep the header so compacted buffers still carry their fingerprint
+        let new_bytes = NP_Memory::new_with_header(Some(capacity), self.memory.schema, self.memory.header()).with_constraints(self.memory.constraints).with_tombstones(self.memory.tombstones);
+        let new_root  = NP_Cursor::new(new_bytes.root, 0, 0);
+
+        NP_Cursor::compact(old_root, &self.memory, new_root, &new_bytes)?;
+
+        self.cursor = NP_Cursor::new(new_bytes.root, 0, 0);
+        self.backup_cursor = NP_Cursor::new(new_bytes.root, 0, 0);
+        self.forget_moved_changes();
+
+        // fixed size buffers stay in their borrowed bytes
+        if self.memory.is_fixed() {
+            self.memory.refill(new_bytes.read_bytes())?;
+        } else {
+            self.memory = new_bytes;
+        }
+
+        Ok(())
+    }
+
+    /// Rebuild the buffer so its bytes only depend on the values inside it.
+    ///
+    /// Compaction keeps map keys in the order they were added and keeps list items, map items and collections that no longer hold any values.
+    /// Canonical buffers leave those out and write table columns and tuple values in schema order, list items by index and map items by key.
+    ///
+    /// Two buffers from the same factory holding the same values will always have identical bytes after this, which makes them safe to hash, sign or compare.
+    ///
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    ///
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["tags", {"type": "map", "value": {"type": "u8"}}]
+    ///    ]
+    /// }"#)?;
+    ///
+    /// let mut first = factory.empty_buffer(None);
+    /// first.set(&["tags", "red"], 1u8)?;
+    /// first.set(&["tags", "blue"], 2u8)?;
+    /// first.set(&["name"], "shirt")?;
+    ///
+    /// let mut second = factory.empty_buffer(None);
+    /// second.set(&["name"], "old shirt")?;
+    /// second.set(&["tags", "green"], 3u8)?;
+    /// second.set(&["tags", "blue"], 2u8)?;
+    /// second.set(&["tags", "red"], 1u8)?;
+    /// second.del(&["tags", "green"])?;
+    /// second.set(&["name"], "shirt")?;
+    ///
+    /// assert_ne!(first.read_bytes(), second.read_bytes());
+    ///
+    /// first.canonicalize()?;
+    /// second.canonicalize()?;
+    ///
+    /// assert_eq!(first.close(), second.close());
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    ///
+    pub fn canonicalize(&mut self) -> Result<(), NP_Error> {
+
+        let capacity = self.memory.read_bytes().len();
+
+        let old_root = NP_Cursor::new(self.memory.root, 0, 0);
+
+        let new_bytes = NP_Memory::new_with_header(Some(capacity), self.memory.schema, self.memory.header()).with_constraints(self.memory.constraints).with_tombstones(self.memory.tombstones);
+        let new_root  = NP_Cursor::new(new_bytes.root, 0, 0);
+
+        NP_Cursor::canonical(old_root, &self.memory, new_root, &new_bytes)?;
+
+        self.cursor = NP_Cursor::new(new_bytes.root, 0, 0);
+        self.backup_cursor = NP_Cursor::new(new_bytes.root, 0, 0);
+        self.forget_moved_changes();
+
+        // fixed size buffers stay in their borrowed bytes
+        if self.memory.is_fixed() {
+            self.memory.refill(new_bytes.read_bytes())?;
+        } else {
+            self.memory = new_bytes;
+        }
+
+        Ok(())
+    }
+
+    /// Reset the buffer to the same state as a new `empty_buffer`, but keep the underlying allocation.
+    ///
+    /// Useful in hot loops where one buffer can be reused for every message instead of allocating a new one each time.
+    ///
+    /// Read only buffers start over with new owned bytes, borrowed buffers are zeroed in place.
+    ///
+    /// **WARNING** Your cursor location and backup will be reset to the root.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    ///
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    ///
+    /// let mut new_buffer = factory.empty_buffer(None);
+    ///
+    /// for message in ["hello", "world"].iter() {
+    ///     new_buffer.clear();
+    ///     new_buffer.set(&[], *message)?;
+    ///     assert_eq!(new_buffer.get::<&str>(&[])?, Some(*message));
+    ///     assert_eq!(new_buffer.read_bytes().len(), 10);
+    /// }
+    ///
+    /// new_buffer.clear();
+    /// assert_eq!(new_buffer.read_bytes(), &[0u8; 3]);
+    /// assert!(new_buffer.close().capacity() >= 1024);
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    ///
+    pub fn clear(&mut self) {
+        if self.memory.is_read_only() {
+            let header = self.memory.header().to_vec();
+            self.memory = NP_Memory::new_with_header(None, self.memory.schema, &header).with_constraints(self.memory.constraints).with_tombstones(self.memory.tombstones);
+        }
+
+        let root = self.memory.root;
+        let root_size = self.memory.addr_size.bytes();
+        self.memory.truncate(root + root_size);
+        // the header stays in place
+        let len = self.memory.read_bytes().len();
+        if let Ok(bytes) = self.memory.get_bytes_mut(root, len.saturating_sub(root)) {
+            for x in bytes.iter_mut() {
+                *x = 0;
+            }
+        }
+
+        self.cursor = NP_Cursor::new(root, 0, 0);
+        self.backup_cursor = NP_Cursor::new(root, 0, 0);
+        if self.journal.is_some() {
+            self.journal = Some(NP_Journal::default());
+        }
+
+        // sortable buffers always have their vtables and children in place
+        if self.sortable {
+            NP_Tuple::select(NP_Cursor::new(root, 0, 0), 0, true, &self.memory).unwrap_or(None);
+        }
+    }
+
+    /// Recursively measures how many bytes each element in the buffer is using.
+    /// This will let you know how many bytes can be saved from a compaction.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::buffer::NP_Size_Data;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "string"
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&[], "hello")?;
+    /// assert_eq!(NP_Size_Data {
+    ///     current_buffer: 10,
+    ///     after_compaction: 10,
+    ///     wasted_bytes: 0
+    /// }, new_buffer.calc_bytes()?);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn calc_bytes<'bytes>(&self) -> Result<NP_Size_Data, NP_Error> {
+
+        let root = NP_Cursor::new(self.memory.root, 0, 0);
+        let real_bytes = NP_Cursor::calc_size(&root, &self.memory)? + self.memory.root;
+        let total_size = self.memory.read_bytes().len();
+        if total_size >= real_bytes {
+            return Ok(NP_Size_Data {
+                current_buffer: total_size,
+                after_compaction: real_bytes,
+                wasted_bytes: total_size - real_bytes
+            });
+        } else {
+            return Err(NP_Error::new("Error calculating bytes!"));
+        }
+    }
+
+    /// Measure how many bytes each value in the buffer is using, as a tree that follows the buffer's collections.
+    /// 
+    /// The root report counts the whole buffer including the header, so it's `bytes` match `after_compaction` from `calc_bytes`.  Use this to find out which values are making a buffer large.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["tags", {"type": "list", "of": {"type": "string"}}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["name"], "hello")?;
+    /// new_buffer.set(&["tags", "0"], "a very long tag")?;
+    /// new_buffer.set(&["name"], "hello world")?;
+    /// 
+    /// let report = new_buffer.calc_bytes_report()?;
+    /// assert_eq!(report.bytes, new_buffer.calc_bytes()?.after_compaction);
+    /// // the old name is wasted
+    /// assert_eq!(report.wasted_bytes, 7);
+    /// assert_eq!(report.find(&["name"]).unwrap().bytes, 15);
+    /// assert_eq!(report.find(&["tags"]).unwrap().children.len(), 1);
+    /// assert_eq!(report.find(&["tags", "0"]).unwrap().bytes, 22);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn calc_bytes_report(&self) -> Result<NP_Size_Report, NP_Error> {
+        let size = self.calc_bytes()?;
+        let root = NP_Cursor::new(self.memory.root, 0, 0);
+        let mut report = NP_Size_Report::walk(String::new(), &root, &self.memory)?;
+        report.bytes = size.after_compaction;
+        report.wasted_bytes = size.wasted_bytes;
+        Ok(report)
+    }
+
+    /// Find out how much of the buffer is dead bytes and how they're spread out, so you can decide when compaction is worth it.
+    /// 
+    /// `calc_bytes` estimates how many bytes compaction saves, this looks at which bytes are actually reachable from the root.  Lots of small dead regions mean the buffer is fragmented, a few large ones usually come from big values that were overwritten.  Damaged buffers return the same error `open_buffer_untrusted` would.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string"}],
+    ///         ["age",  {"type": "u32"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["name"], "hello")?;
+    /// new_buffer.set(&["age"], 10u32)?;
+    /// new_buffer.set(&["name"], "hello world")?;
+    /// 
+    /// let stats = new_buffer.frag_stats()?;
+    /// // the old name is dead
+    /// assert_eq!(stats.dead_bytes, 7);
+    /// assert_eq!(stats.dead_regions, 1);
+    /// assert_eq!(stats.largest_dead_region, 7);
+    /// assert_eq!(stats.live_bytes + stats.dead_bytes, stats.total_bytes);
+    /// 
+    /// new_buffer.compact(None)?;
+    /// assert_eq!(new_buffer.frag_stats()?.dead_bytes, 0);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn frag_stats(&self) -> Result<NP_Frag_Stats, NP_Error> {
+
+        let ranges = crate::integrity::live_ranges(&self.memory)?;
+        let total_bytes = self.memory.read_bytes().len();
+
+        let mut stats = NP_Frag_Stats { total_bytes, live_bytes: 0, dead_bytes: 0, dead_regions: 0, largest_dead_region: 0 };
+
+        let mut last_end = 0usize;
+
+        for (start, end) in ranges.iter().cloned().chain(core::iter::once((total_bytes, total_bytes))) {
+            if start > last_end {
+                let dead = start - last_end;
+                stats.dead_bytes += dead;
+                stats.dead_regions += 1;
+                stats.largest_dead_region = stats.largest_dead_region.max(dead);
+            }
+            stats.live_bytes += end - start;
+            last_end = end;
+        }
+
+        Ok(stats)
+    }
+
+    fn select(&self, cursor: NP_Cursor, make_path: bool, path: &[&str]) -> Result<Option<NP_Cursor>, NP_Error> {
+
+        if make_path {
+            self.check_writable()?;
+        }
+
+        let mut loop_cursor = cursor;
+
+        let mut path_index = 0usize;
+        
+        loop {
+            
+            if path.len() == path_index {
+                return Ok(Some(loop_cursor));
+            }
+
+            // now select into collections
+            match &self.memory.schema[loop_cursor.schema_addr] {
+                NP_Parsed_Schema::Table {  .. } => {
+                    if let Some(next) = NP_Table::select(loop_cursor, path[path_index], make_path, &self.memory)? {
+                        loop_cursor = next;
+                        path_index += 1;
+                    } else {
+                        return Ok(None);
+                    }
+                },
+                NP_Parsed_Schema::Tuple { .. } => {
+                    match path[path_index].parse::<usize>() {
+                        Ok(x) => {
+                            if let Some(next) = NP_Tuple::select(loop_cursor, x, make_path, &self.memory)? {
+                                loop_cursor = next;
+                                path_index += 1;
+                            } else {
+                                return Ok(None);
+                            }
+                        },
+                        Err(_e) => {
+                            return Err(NP_Error::BadPath("Need a number to index into tuple, string found!".into()))
+                        }
+                    }
+                },
+                NP_Parsed_Schema::List { .. } => {
+                    match path[path_index].parse::<usize>() {
                         Ok(x) => {
-                            if let Some(next) = NP_List::select(loop_cursor, x, make_path, &self.memory)? {
-                                loop_cursor = opt_err(next.1)?;
+                            if let Some((_index, Some(next))) = NP_List::select(loop_cursor, x, make_path, &self.memory)? {
+                                loop_cursor = next;
                                 path_index += 1;
                             } else {
                                 return Ok(None);
                             }
                         },
                         Err(_e) => {
-                            return Err(NP_Error::new("Need a number to index into list, string found!"))
+                            return Err(NP_Error::BadPath("Need a number to index into list, string found!".into()))
                         }
                     }
                 },
@@ -1074,199 +4287,1674 @@ impl<'buffer> NP_Buffer<'buffer> {
                         return Ok(None);
                     }
 
-                },
-                _ => { // we've reached a scalar value but not at the end of the path
-                    return Ok(None);
-                }
-            }
-        }
-    }
+                },
+                _ => { // we've reached a scalar value but not at the end of the path
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Follow a compiled path from the root of the buffer
+    fn select_path(&self, path: &NP_Path) -> Result<Option<NP_Cursor>, NP_Error> {
+
+        let mut loop_cursor = NP_Cursor::new(self.memory.root, 0, 0);
+
+        for step in path.steps.iter() {
+            let next = match (&self.memory.schema[loop_cursor.schema_addr], step) {
+                (NP_Parsed_Schema::Table { .. }, NP_Path_Step::Column(x)) => {
+                    NP_Table::select_column(loop_cursor, *x, false, &self.memory)?
+                },
+                (NP_Parsed_Schema::Tuple { .. }, NP_Path_Step::Index(x)) => {
+                    NP_Tuple::select(loop_cursor, *x, false, &self.memory)?
+                },
+                (NP_Parsed_Schema::List { .. }, NP_Path_Step::Index(x)) => {
+                    match NP_List::select(loop_cursor, *x, false, &self.memory)? {
+                        Some((_index, next)) => next,
+                        None => None
+                    }
+                },
+                (NP_Parsed_Schema::Map { .. }, NP_Path_Step::Key(x)) => {
+                    NP_Map::select(loop_cursor, x, false, &self.memory)?
+                },
+                _ => return Err(NP_Error::BadPath("Compiled path doesn't match the buffer schema!".into()))
+            };
+
+            match next {
+                Some(x) => loop_cursor = x,
+                None => return Ok(None)
+            }
+        }
+
+        Ok(Some(loop_cursor))
+    }
+
+    /// Schema address of the value at `path` (relative to the cursor), found from the schema alone.
+    pub(crate) fn schema_addr_at(&self, path: &[&str]) -> Option<usize> {
+        self.schema_addr_from(self.cursor.schema_addr, path)
+    }
+
+    /// Schema address of the value at `path` below the value at `schema_addr`
+    fn schema_addr_from(&self, mut schema_addr: usize, path: &[&str]) -> Option<usize> {
+
+        for key in path {
+            schema_addr = match &self.memory.schema[schema_addr] {
+                NP_Parsed_Schema::Table { columns, .. } => {
+                    columns.iter().find(|col| col.1 == *key)?.2
+                },
+                NP_Parsed_Schema::Tuple { values, .. } => {
+                    *values.get(key.parse::<usize>().ok()?)?
+                },
+                NP_Parsed_Schema::List { of, .. } => {
+                    key.parse::<usize>().ok()?;
+                    *of
+                },
+                NP_Parsed_Schema::Map { value, .. } => {
+                    *value
+                },
+                _ => return None
+            };
+        }
+
+        Some(schema_addr)
+    }
+
+    /// The parsed schema used by this buffer
+    pub(crate) fn parsed_schema(&self) -> &'buffer Vec<NP_Parsed_Schema> {
+        self.memory.schema
+    }
+}
+
+
+
+/// A list item given to the `list_filter` predicate or handed out by `list_page` and `iter_list_rev`
+pub struct NP_List_Item<'item> {
+    /// index of this item in the list
+    pub index: usize,
+    cursor: NP_Cursor,
+    buffer: &'item NP_Buffer<'item>
+}
+
+impl<'item> NP_List_Item<'item> {
+
+    /// Get a value inside this item, the path is relative to the item.  Works like `NP_Buffer::get`.
+    pub fn get<X: 'item>(&self, path: &[&str]) -> Result<Option<X>, NP_Error> where X: NP_Value<'item> + NP_Scalar {
+        let buffer: &'item NP_Buffer<'item> = self.buffer;
+        let value_cursor = buffer.select(self.cursor, false, path)?;
+        let item_schema = self.cursor.schema_addr;
+        buffer.get_at(value_cursor, || buffer.schema_addr_from(item_schema, path))
+    }
+
+    /// Get the JSON value of this item or a value inside it
+    pub fn json_encode(&self, path: &[&str]) -> Result<NP_JSON, NP_Error> {
+        match self.buffer.select(self.cursor, false, path)? {
+            Some(x) => Ok(NP_Value_Owned::from_cursor(&x, &self.buffer.memory)?.to_json()),
+            None => Ok(NP_JSON::Null)
+        }
+    }
+}
+
+/// Saved position in a list, used by `NP_Buffer::list_page` to page through a list over many calls
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct NP_ListCursor {
+    /// list index of the last item passed
+    index: usize,
+    /// buffer address of the last item passed, zero before the first item
+    addr: usize
+}
+
+impl NP_ListCursor {
+
+    /// Cursor in front of the first item of a list
+    pub fn start() -> Self {
+        NP_ListCursor { index: 0, addr: 0 }
+    }
+
+    /// List index of the last item this cursor passed, `None` if it's in front of the first item
+    pub fn index(&self) -> Option<usize> {
+        if self.addr == 0 { None } else { Some(self.index) }
+    }
+
+    /// Get the bytes of this cursor to save it
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let mut bytes = [0u8; 8];
+        bytes[..4].copy_from_slice(&(self.index as u32).to_be_bytes());
+        bytes[4..].copy_from_slice(&(self.addr as u32).to_be_bytes());
+        bytes
+    }
+
+    /// Open a cursor from bytes made by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NP_Error> {
+        if bytes.len() != 8 {
+            return Err(NP_Error::Damaged("Bytes are not a list cursor!".into()));
+        }
+        Ok(NP_ListCursor {
+            index: u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+            addr: u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize
+        })
+    }
+}
+
+/// Iterator over list items from the tail to the head, made by `NP_Buffer::iter_list_rev`
+pub struct NP_List_Rev_Iterator<'it> {
+    items: Vec<(usize, NP_Cursor)>,
+    buffer: &'it NP_Buffer<'it>
+}
+
+impl<'it> Iterator for NP_List_Rev_Iterator<'it> {
+    type Item = NP_List_Item<'it>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((index, cursor)) = self.items.pop() {
+            if cursor.get_value(&self.buffer.memory).get_addr_value() != 0 {
+                return Some(NP_List_Item { index, cursor, buffer: self.buffer });
+            }
+        }
+        None
+    }
+}
+
+/// A map entry given to the `map_find` predicate
+pub struct NP_Map_Item<'item> {
+    /// key of this entry
+    pub key: &'item str,
+    cursor: NP_Cursor,
+    buffer: &'item NP_Buffer<'item>
+}
+
+impl<'item> NP_Map_Item<'item> {
+
+    /// Get a value inside this entry, the path is relative to the entry.  Works like `NP_Buffer::get`.
+    pub fn get<X: 'item>(&self, path: &[&str]) -> Result<Option<X>, NP_Error> where X: NP_Value<'item> + NP_Scalar {
+        let buffer: &'item NP_Buffer<'item> = self.buffer;
+        let value_cursor = buffer.select(self.cursor, false, path)?;
+        let item_schema = self.cursor.schema_addr;
+        buffer.get_at(value_cursor, || buffer.schema_addr_from(item_schema, path))
+    }
+
+    /// Get the JSON value of this entry or a value inside it
+    pub fn json_encode(&self, path: &[&str]) -> Result<NP_JSON, NP_Error> {
+        match self.buffer.select(self.cursor, false, path)? {
+            Some(x) => Ok(NP_Value_Owned::from_cursor(&x, &self.buffer.memory)?.to_json()),
+            None => Ok(NP_JSON::Null)
+        }
+    }
+}
+
+/// NP Item
+pub struct NP_Item<'item> {
+    /// index of this value
+    pub index: usize,
+    /// Key at this index
+    pub key: &'item str,
+    /// Column at this index
+    pub col: &'item str,
+    /// Cursor value
+    cursor: Option<NP_Cursor>,
+    parent: NP_Cursor,
+    memory: &'item NP_Memory<'item>
+}
+
+impl<'item> NP_Item<'item> {
+
+    /// If this item has a value
+    pub fn has_value(&self) -> bool {
+        if let Some(x) = self.cursor {
+            let value = x.get_value(self.memory);
+            value.get_addr_value() != 0
+        } else {
+            false
+        }
+    }
+    /// Get value at this pointer
+    pub fn get<X>(&'item self) -> Result<Option<X>, NP_Error> where X: NP_Value<'item> + NP_Scalar {
+        if let Some(cursor) = self.cursor {
+            match X::into_value(&cursor, &self.memory)? {
+                Some(x) => {
+                    Ok(Some(x))
+                },
+                None => {
+                    match X::schema_default(&self.memory.schema[cursor.schema_addr]) {
+                        Some(y) => {
+                            Ok(Some(y))
+                        },
+                        None => {
+                            Ok(None)
+                        }
+                    }
+                }
+            }
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Set value at this pointer
+    pub fn set<X>(&'item mut self, value: X) -> Result<(), NP_Error> where X: NP_Value<'item> + NP_Scalar {
+        if self.memory.is_read_only() {
+            return Err(NP_Error::ReadOnly("Read only buffers can't be changed!".into()));
+        }
+        if let Some(cursor) = self.cursor {
+            X::set_value(cursor.clone(), self.memory, value)?;
+        } else {
+            match self.memory.schema[self.parent.schema_addr] {
+                NP_Parsed_Schema::List { .. } => {
+                    let item = opt_err(opt_err(NP_List::select(self.parent.clone(), self.index, true, self.memory)?)?.1)?;
+                    X::set_value(item, self.memory, value)?;
+                }
+                NP_Parsed_Schema::Table { .. } => {
+                    let item = opt_err(NP_Table::select(self.parent.clone(), self.key, true, self.memory)?)?;
+                    X::set_value(item, self.memory, value)?;
+                },
+                NP_Parsed_Schema::Tuple { .. } => {
+                    let item = opt_err(NP_Tuple::select(self.parent.clone(), self.index, true, self.memory)?)?;
+                    X::set_value(item, self.memory, value)?;
+                }
+                _ => { }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Clear the value at this pointer
+    pub fn del(&'item mut self) -> bool {
+        if self.memory.is_read_only() {
+            return false;
+        }
+        if let Some(cursor) = self.cursor {
+            let value = cursor.get_value(self.memory);
+            value.set_addr_value(0);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Iterator Enum
+#[derive(Debug)]
+#[doc(hidden)]
+pub enum NP_Iterator_Collection<'col> {
+    /// None
+    None,
+    /// Map
+    Map(NP_Map<'col>),
+    /// List
+    List(NP_List),
+    /// Table
+    Table(NP_Table<'col>),
+    /// Tuple
+    Tuple(NP_Tuple<'col>)
+}
+
+#[allow(missing_docs)]
+impl<'col> NP_Iterator_Collection<'col> {
+    pub fn new(cursor: NP_Cursor, memory: &'col NP_Memory) -> Result<Self, NP_Error> {
+        match memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Table { .. } => {
+                let table = NP_Table::new_iter(&cursor, memory);
+                Ok(NP_Iterator_Collection::Table(table))
+            },
+            NP_Parsed_Schema::List { .. } => {
+                let list = NP_List::new_iter(&cursor, memory, false, 0);
+                Ok(NP_Iterator_Collection::List(list))
+            },
+            NP_Parsed_Schema::Tuple { .. } => {
+                let tuple = NP_Tuple::new_iter(&cursor, memory);
+                Ok(NP_Iterator_Collection::Tuple(tuple))
+            },
+            NP_Parsed_Schema::Map { .. } => {
+                let map = NP_Map::new_iter(&cursor, memory);
+                Ok(NP_Iterator_Collection::Map(map))
+            },
+            _ => Err(NP_Error::SchemaMismatch("Tried to create iterator on non collection item!".into()))
+        }
+    }
+}
+
+#[allow(missing_docs)]
+pub struct NP_Generic_Iterator<'it> {
+    root: NP_Cursor,
+    value: NP_Iterator_Collection<'it>,
+    memory: &'it NP_Memory<'it>,
+    index: usize
+}
+
+#[allow(missing_docs)]
+impl<'it> NP_Generic_Iterator<'it> {
+    pub fn new(cursor: NP_Cursor, memory: &'it NP_Memory) -> Result<Self, NP_Error> {
+        Ok(Self { 
+            root: cursor.clone(),
+            value: NP_Iterator_Collection::new(cursor.clone(), memory)?,
+            memory: memory,
+            index: 0
+        })
+    }
+}
+
+
+impl<'it> Iterator for NP_Generic_Iterator<'it> {
+    type Item = NP_Item<'it>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.value {
+            NP_Iterator_Collection::Map(x) => {
+                if let Some(next_item) = x.step_iter(&self.memory) {
+                    self.index += 1;
+                    Some(NP_Item { memory: self.memory, key: next_item.0, col: next_item.0, index: self.index - 1, cursor: Some(next_item.1), parent: self.root.clone() })
+                } else {
+                    None
+                }
+            },
+            NP_Iterator_Collection::List(x) => {
+                if let Some(next_item) = x.step_iter(&self.memory) {
+                    Some(NP_Item { memory: self.memory, key: "", col: "", index: next_item.0, cursor: next_item.1, parent: self.root.clone() })
+                } else {
+                    None
+                }
+            },
+            NP_Iterator_Collection::Table(x) => {
+                if let Some(next_item) = x.step_iter(&self.memory) {
+                    Some(NP_Item { memory: self.memory, key: next_item.1, col: next_item.1, index: next_item.0, cursor: next_item.2, parent: self.root.clone() })
+                } else {
+                    None
+                }
+            },
+            NP_Iterator_Collection::Tuple(x) => {
+                if let Some(next_item) = x.step_iter(&self.memory) {
+                    Some(NP_Item { memory: self.memory, key: "", col: "", index: next_item.0, cursor: next_item.1, parent: self.root.clone() })
+                } else {
+                    None
+                }
+            },
+            _ => { None }
+        }
+    }
+}
+#[cfg(test)]
+pub(crate) mod alloc_counter {
+    extern crate std;
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::cell::Cell;
+
+    std::thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    struct NP_Counting_Alloc;
+
+    unsafe impl GlobalAlloc for NP_Counting_Alloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            std::alloc::System.alloc(layout)
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            std::alloc::System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: NP_Counting_Alloc = NP_Counting_Alloc;
+
+    /// How many allocations the current thread made while running the callback
+    pub fn count<F: FnOnce()>(callback: F) -> usize {
+        let start = ALLOCATIONS.with(|count| count.get());
+        callback();
+        ALLOCATIONS.with(|count| count.get()) - start
+    }
+}
+
+#[test]
+fn reads_dont_allocate() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["name", {"type": "string"}],
+            ["blob", {"type": "bytes"}],
+            ["age", {"type": "u8", "default": 20}],
+            ["score", {"type": "dec", "exp": 2}],
+            ["place", {"type": "geo4"}],
+            ["when", {"type": "date"}],
+            ["id", {"type": "uuid"}],
+            ["color", {"type": "option", "choices": ["red", "green"], "default": "green"}],
+            ["point", {"type": "tuple", "values": [{"type": "i16"}, {"type": "bool"}]}],
+            ["tags", {"type": "list", "of": {"type": "string"}}],
+            ["meta", {"type": "map", "value": {"type": "u32"}}]
+        ]
+    }"#)?;
+    let tag_path = factory.compile_path(&["tags", "1"])?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["name"], "Jeb")?;
+    buffer.set(&["blob"], &[1u8, 2, 3] as &[u8])?;
+    buffer.set(&["score"], NP_Dec::new(520, 2))?;
+    buffer.set(&["place"], NP_Geo::new(4, 45.0, 45.0))?;
+    buffer.set(&["when"], NP_Date::new(1604965249484))?;
+    buffer.set(&["id"], &NP_UUID::generate(22))?;
+    buffer.set(&["point", "0"], -20i16)?;
+    buffer.set(&["tags", "1"], "hello")?;
+    buffer.set(&["meta", "size"], 5u32)?;
+    let bytes = buffer.close();
+
+    let buffer = factory.open_buffer_read_only(&bytes);
+    let mut sum = 0usize;
+    let allocations = alloc_counter::count(|| {
+        sum += buffer.get_str(&["name"]).unwrap().unwrap().len();
+        sum += buffer.get_bytes(&["blob"]).unwrap().unwrap().len();
+        sum += buffer.get::<u8>(&["age"]).unwrap().unwrap() as usize;
+        sum += buffer.get::<NP_Dec>(&["score"]).unwrap().unwrap().num as usize;
+        sum += buffer.get::<NP_Geo>(&["place"]).unwrap().unwrap().lat as usize;
+        sum += buffer.get::<NP_Date>(&["when"]).unwrap().unwrap().value as usize;
+        sum += buffer.get::<&NP_UUID>(&["id"]).unwrap().unwrap().value[0] as usize;
+        sum += buffer.get_choice(&["color"]).unwrap().unwrap().len();
+        sum += buffer.get::<i16>(&["point", "0"]).unwrap().unwrap().unsigned_abs() as usize;
+        sum += buffer.get_with::<&str>(&tag_path).unwrap().unwrap().len();
+        sum += buffer.get::<u32>(&["meta", "size"]).unwrap().unwrap() as usize;
+        sum += buffer.length(&["tags"]).unwrap().unwrap();
+        for item in buffer.get_iter(&["tags"]).unwrap().unwrap() {
+            sum += item.get::<&str>().unwrap().map(|tag| tag.len()).unwrap_or(0);
+        }
+        for item in buffer.get_iter(&["meta"]).unwrap().unwrap() {
+            sum += item.key.len();
+        }
+        for item in buffer.get_iter(&[]).unwrap().unwrap() {
+            sum += item.col.len();
+        }
+    });
+    assert_eq!(allocations, 0);
+    assert!(sum > 0);
+
+    // owned enums copy the choice
+    assert_eq!(alloc_counter::count(|| { buffer.get::<NP_Enum>(&["color"]).unwrap(); }), 1);
+
+    Ok(())
+}
+
+#[test]
+fn deep_eq_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "table", "columns": [
+        ["id", {"type": "u16"}],
+        ["tags", {"type": "list", "of": {"type": "string"}}]
+    ]}}"#)?;
+
+    let mut first = factory.empty_buffer(None);
+    first.set(&["2", "id"], 5u16)?;
+    first.set(&["2", "tags", "1"], "moon")?;
+    first.set(&["0", "tags", "0"], "mun")?;
+    first.del(&["0", "tags", "0"])?;
+
+    let mut second = factory.empty_buffer(None);
+    second.set(&["2", "tags", "1"], "moon")?;
+    second.set(&["2", "id"], 5u16)?;
+    assert!(first.deep_eq(&second)?);
+    assert!(second.deep_eq(&first)?);
+
+    first.compact(None)?;
+    assert!(first.deep_eq(&second)?);
+
+    // cursors pick the values to compare
+    first.move_cursor(&["2", "tags"])?;
+    assert!(!first.deep_eq(&second)?);
+    second.move_cursor(&["2", "tags"])?;
+    assert!(first.deep_eq(&second)?);
+
+    second.set(&["0"], "mars")?;
+    assert!(!first.deep_eq(&second)?);
+
+    Ok(())
+}
+
+#[test]
+fn canonicalize_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["list", {"type": "list", "of": {"type": "string"}}],
+        ["pair", {"type": "tuple", "values": [{"type": "u8"}, {"type": "map", "value": {"type": "string"}}]}],
+        ["extra", {"type": "map", "value": {"type": "list", "of": {"type": "u8"}}}]
+    ]}"#)?;
+
+    let mut first = factory.empty_buffer(None);
+    first.set(&["list", "3"], "c")?;
+    first.set(&["list", "1"], "a")?;
+    first.set(&["pair", "0"], 2u8)?;
+    first.set(&["pair", "1", "b"], "bee")?;
+    first.set(&["pair", "1", "a"], "ay")?;
+
+    let mut second = factory.empty_buffer(None);
+    second.set(&["extra", "empty", "0"], 9u8)?;
+    second.set(&["pair", "1", "a"], "ay")?;
+    second.set(&["list", "1"], "a")?;
+    second.set(&["list", "2"], "b")?;
+    second.set(&["list", "3"], "c")?;
+    second.set(&["pair", "1", "b"], "bee")?;
+    second.set(&["pair", "0"], 2u8)?;
+    second.del(&["list", "2"])?;
+    second.del(&["extra", "empty", "0"])?;
+
+    first.canonicalize()?;
+    second.canonicalize()?;
+    assert_eq!(first.read_bytes(), second.read_bytes());
+    assert_eq!(second.get::<&str>(&["list", "3"])?, Some("c"));
+    assert_eq!(second.get::<&str>(&["pair", "1", "a"])?, Some("ay"));
+    assert_eq!(second.calc_bytes()?.wasted_bytes, 0);
+
+    // canonicalizing again changes nothing
+    let bytes = first.read_bytes().to_vec();
+    first.canonicalize()?;
+    assert_eq!(first.read_bytes(), &bytes[..]);
+
+    // a buffer with no values left is the same as a new one
+    let mut third = factory.empty_buffer(None);
+    third.set(&["extra", "x", "1"], 1u8)?;
+    third.del(&["extra", "x", "1"])?;
+    third.canonicalize()?;
+    assert_eq!(third.close(), factory.empty_buffer(None).close());
+
+    Ok(())
+}
+
+#[test]
+fn null_values_work() -> Result<(), NP_Error> {
+    use crate::{NP_Factory, NP_Address_Size};
+
+    for size in [NP_Address_Size::U8, NP_Address_Size::U16, NP_Address_Size::U32].iter() {
+        let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+            ["age",   {"type": "u8", "default": 20, "inline": true}],
+            ["tags",  {"type": "list", "of": {"type": "string"}}],
+            ["attrs", {"type": "map", "value": {"type": "i32"}}],
+            ["pair",  {"type": "tuple", "values": [{"type": "string"}, {"type": "bool"}]}]
+        ]}"#)?.with_address_size(size.clone());
+
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set_null(&["age"])?;
+        buffer.set(&["tags", "0"], "a")?;
+        buffer.set_null(&["tags", "2"])?;
+        buffer.set_null(&["attrs", "x"])?;
+        buffer.set_null(&["pair"])?;
+
+        let check = |buffer: &NP_Buffer| -> Result<(), NP_Error> {
+            assert!(buffer.is_null(&["age"])?);
+            assert!(buffer.is_null(&["tags", "2"])?);
+            assert!(buffer.is_null(&["attrs", "x"])?);
+            assert!(buffer.is_null(&["pair"])?);
+            assert!(!buffer.is_null(&["tags", "0"])?);
+            assert!(!buffer.is_null(&["tags", "1"])?);
+            assert_eq!(buffer.get::<u8>(&["age"])?, None);
+            assert_eq!(buffer.get::<bool>(&["pair", "1"])?, None);
+            assert_eq!(buffer.json_encode(&[])?.stringify(), r#"{"age":null,"tags":["a",null,null],"attrs":{"x":null},"pair":null}"#);
+            assert!(buffer.verify()?.is_ok());
+            Ok(())
+        };
+
+        check(&buffer)?;
+        buffer.compact(None)?;
+        check(&buffer)?;
+        buffer.canonicalize()?;
+        check(&buffer)?;
+
+        // new values replace nulls
+        buffer.set(&["age"], 5u8)?;
+        buffer.set(&["pair", "1"], true)?;
+        assert!(!buffer.is_null(&["age"])?);
+        assert!(!buffer.is_null(&["pair"])?);
+        assert_eq!(buffer.get::<u8>(&["age"])?, Some(5));
+        assert_eq!(buffer.get::<bool>(&["pair", "1"])?, Some(true));
+        buffer.del(&["tags", "2"])?;
+        assert!(!buffer.is_null(&["tags", "2"])?);
+        buffer.canonicalize()?;
+        assert_eq!(buffer.json_encode(&[])?.stringify(), r#"{"age":5,"tags":["a"],"attrs":{"x":null},"pair":[null,true]}"#);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn tombstones_work() -> Result<(), NP_Error> {
+    use crate::{NP_Factory, NP_Address_Size};
+
+    for size in [NP_Address_Size::U8, NP_Address_Size::U16, NP_Address_Size::U32].iter() {
+        let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+            ["age",   {"type": "u8", "default": 20}],
+            ["tags",  {"type": "list", "of": {"type": "string"}}],
+            ["attrs", {"type": "map", "value": {"type": "i32"}}]
+        ]}"#)?.with_address_size(size.clone());
+
+        // deleted values are cleared without tombstones
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&["age"], 5u8)?;
+        buffer.del(&["age"])?;
+        assert!(!buffer.is_deleted(&["age"])?);
+
+        buffer.set_tombstones(NP_Tombstones::Keep);
+        buffer.set(&["age"], 5u8)?;
+        buffer.set(&["tags", "0"], "a")?;
+        buffer.set(&["tags", "1"], "b")?;
+        buffer.set(&["attrs", "x"], 1i32)?;
+        assert!(buffer.del(&["age"])?);
+        assert!(buffer.del(&["tags", "1"])?);
+        assert!(buffer.del(&["attrs", "x"])?);
+        // paths that were never set can be deleted too
+        assert!(buffer.del(&["attrs", "y"])?);
+        assert!(!buffer.del(&["missing"])?);
+
+        let check = |buffer: &NP_Buffer| -> Result<(), NP_Error> {
+            assert!(buffer.is_deleted(&["age"])?);
+            assert!(buffer.is_deleted(&["tags", "1"])?);
+            assert!(buffer.is_deleted(&["attrs", "x"])?);
+            assert!(buffer.is_deleted(&["attrs", "y"])?);
+            assert!(!buffer.is_deleted(&["tags", "0"])?);
+            assert!(!buffer.is_deleted(&["tags", "2"])?);
+            assert_eq!(buffer.get::<u8>(&["age"])?, Some(20));
+            assert_eq!(buffer.get::<&str>(&["tags", "1"])?, None);
+            assert_eq!(buffer.get::<i32>(&["attrs", "x"])?, None);
+            assert!(buffer.verify()?.is_ok());
+            Ok(())
+        };
+
+        check(&buffer)?;
+        buffer.compact(None)?;
+        check(&buffer)?;
+        buffer.canonicalize()?;
+        check(&buffer)?;
+
+        // new values replace tombstones
+        buffer.set(&["attrs", "y"], 2i32)?;
+        assert!(!buffer.is_deleted(&["attrs", "y"])?);
+        assert_eq!(buffer.get::<i32>(&["attrs", "y"])?, Some(2));
+
+        // the next compaction lets go of them
+        buffer.set_tombstones(NP_Tombstones::UntilCompaction);
+        buffer.del(&["tags", "0"])?;
+        assert!(buffer.is_deleted(&["tags", "0"])?);
+        buffer.canonicalize()?;
+        assert!(!buffer.is_deleted(&["tags", "0"])?);
+        assert!(!buffer.is_deleted(&["age"])?);
+        assert_eq!(buffer.json_encode(&[])?.stringify(), r#"{"age":20,"tags":null,"attrs":{"y":2}}"#);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn journal_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use crate::json_flex::json_decode;
+    use alloc::vec;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["title", {"type": "string"}],
+        ["count", {"type": "u32", "default": 1}],
+        ["tags",  {"type": "list", "of": {"type": "string"}}],
+        ["attrs", {"type": "map", "value": {"type": "string"}}]
+    ]}"#)?;
+
+    // map items come back in a different order, the keys are sorted to compare
+    let canonical = NP_JSON_Options::canonical();
+    let mut buffer = factory.empty_buffer(None);
+
+    // nothing is recorded until the journal is started
+    buffer.set(&["title"], "first")?;
+    assert_eq!(buffer.journal().len(), 0);
+    assert_eq!(buffer.undo()?, false);
+
+    buffer.start_journal();
+    buffer.set(&["count"], 5u32)?;
+    buffer.set_json(&["attrs"], &*json_decode(String::from(r#"{"a": "x", "b": "y"}"#))?)?;
+    buffer.list_push(&["tags"], "one")?;
+    buffer.list_push(&["tags"], "two")?;
+    buffer.del(&["title"])?;
+    buffer.list_sort(&["tags"], &[])?;
+    // failed changes aren't recorded
+    assert!(buffer.set(&["count"], "wrong type").is_err());
+    assert_eq!(buffer.journal().len(), 6);
+    assert_eq!(buffer.journal()[4].path, vec!["title"]);
+    assert_eq!(buffer.journal()[4].old, NP_Value_Owned::String("first".into()));
+
+    let end = buffer.json_encode(&[])?.stringify_with(&canonical);
+    buffer.compact(None)?;
+
+    let mut states = Vec::new();
+    loop {
+        states.push(buffer.json_encode(&[])?.stringify_with(&canonical));
+        if !buffer.undo()? { break; }
+    }
+    assert_eq!(states.len(), 7);
+    assert_eq!(states[6], r#"{"attrs":null,"count":1,"tags":null,"title":"first"}"#);
+    assert_eq!(states[2], r#"{"attrs":{"a":"x","b":"y"},"count":5,"tags":["one","two"],"title":"first"}"#);
+
+    // redo goes back through the same states
+    for x in (0..6).rev() {
+        assert!(buffer.redo()?);
+        assert_eq!(buffer.json_encode(&[])?.stringify_with(&canonical), states[x]);
+    }
+    assert_eq!(buffer.redo()?, false);
+    assert_eq!(buffer.json_encode(&[])?.stringify_with(&canonical), end);
+
+    // changes below a moved cursor use the cursor, compaction forgets them
+    buffer.move_cursor(&["attrs"])?;
+    buffer.set(&["a"], "z")?;
+    buffer.cursor_to_root();
+    assert!(buffer.undo()?);
+    assert_eq!(buffer.get::<&str>(&["attrs", "a"])?, Some("x"));
+    assert!(buffer.redo()?);
+    buffer.compact(None)?;
+    assert_eq!(buffer.journal().len(), 6);
+
+    buffer.stop_journal();
+    assert_eq!(buffer.journal().len(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn snapshots_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["title", {"type": "string"}],
+        ["tags",  {"type": "list", "of": {"type": "string"}}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["title"], "first")?;
+    buffer.list_push(&["tags"], "one")?;
+    let before = buffer.read_bytes().to_vec();
+
+    // snapshots share the bytes of the buffer
+    let snapshot = buffer.snapshot();
+    let again = snapshot.snapshot();
+    assert_eq!(snapshot.read_bytes().as_ptr(), buffer.read_bytes().as_ptr());
+    assert_eq!(again.read_bytes().as_ptr(), buffer.read_bytes().as_ptr());
+
+    // changing the buffer leaves the snapshots alone
+    buffer.set(&["title"], "second")?;
+    buffer.list_push(&["tags"], "two")?;
+    buffer.del(&["tags", "0"])?;
+    assert_ne!(snapshot.read_bytes().as_ptr(), buffer.read_bytes().as_ptr());
+    assert_eq!(snapshot.read_bytes(), &before[..]);
+    assert_eq!(again.read_bytes(), &before[..]);
+    assert_eq!(snapshot.get::<&str>(&["title"])?, Some("first"));
+    assert_eq!(snapshot.length(&["tags"])?, Some(1));
+    assert_eq!(buffer.get::<&str>(&["title"])?, Some("second"));
+
+    // snapshots can't be changed
+    let mut snapshot = snapshot;
+    assert!(snapshot.is_read_only());
+    assert!(snapshot.set(&["title"], "third").is_err());
+    assert!(snapshot.list_push(&["tags"], "three").is_err());
+    assert_eq!(snapshot.read_bytes(), &before[..]);
+
+    // closing drops the shared bytes
+    assert_eq!(again.close(), before);
+
+    // compacted buffers still have their snapshots
+    let snapshot = buffer.snapshot();
+    buffer.compact(None)?;
+    assert_eq!(snapshot.get::<&str>(&["title"])?, Some("second"));
+    assert_eq!(snapshot.close().len() > buffer.read_bytes().len(), true);
+
+    // borrowed bytes are copied right away
+    let mut bytes = buffer.close();
+    let mut borrowed = factory.open_buffer_borrowed(&mut bytes[..]);
+    let snapshot = borrowed.snapshot();
+    borrowed.set(&["title"], "thirds")?;
+    assert_eq!(snapshot.get::<&str>(&["title"])?, Some("second"));
+
+    Ok(())
+}
+
+#[test]
+fn observers_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use crate::json_flex::json_decode;
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    extern crate std;
+    use std::sync::Mutex;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["title", {"type": "string"}],
+        ["count", {"type": "u8"}],
+        ["attrs", {"type": "map", "value": {"type": "string"}}]
+    ]}"#)?;
+
+    let changes: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&changes);
+    let seen: Arc<Mutex<Vec<(String, NP_Value_Owned, NP_Value_Owned)>>> = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&seen);
+
+    {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.on_change(move |_, _, _| { counter.fetch_add(1, Ordering::Relaxed); });
+        buffer.on_change(move |path, old, new| log.lock().unwrap().push((path.join("."), old.clone(), new.clone())));
+        buffer.start_journal();
+
+        // collections set from JSON are one change
+        buffer.set_json(&["attrs"], &*json_decode(String::from(r#"{"a": "x", "b": "y"}"#))?)?;
+        buffer.increment(&["count"], 3)?;
+        // failed changes aren't observed
+        assert!(buffer.increment(&["count"], -10).is_err());
+        assert!(buffer.set(&["count"], "wrong").is_err());
+
+        // paths are relative to the cursor
+        buffer.move_cursor(&["attrs"])?;
+        buffer.del(&["a"])?;
+        buffer.cursor_to_root();
+
+        // undo and redo are observed too
+        buffer.undo()?;
+        buffer.undo()?;
+        buffer.redo()?;
+    }
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(changes.load(Ordering::Relaxed), 6);
+    assert_eq!(seen.len(), 6);
+    assert_eq!(seen[0].0, "attrs");
+    assert_eq!(seen[0].1, NP_Value_Owned::Null);
+    assert_eq!(seen[1], (String::from("count"), NP_Value_Owned::Null, NP_Value_Owned::Uint8(3)));
+    assert_eq!(seen[2], (String::from("a"), NP_Value_Owned::String(String::from("x")), NP_Value_Owned::Null));
+    assert_eq!(seen[3], (String::from("a"), NP_Value_Owned::Null, NP_Value_Owned::String(String::from("x"))));
+    assert_eq!(seen[4], (String::from("count"), NP_Value_Owned::Uint8(3), NP_Value_Owned::Null));
+    assert_eq!(seen[5], (String::from("count"), NP_Value_Owned::Null, NP_Value_Owned::Uint8(3)));
+
+    // removed observers aren't called again
+    let mut buffer = factory.empty_buffer(None);
+    let counter = Arc::clone(&changes);
+    buffer.on_change(move |_, _, _| { counter.fetch_add(1, Ordering::Relaxed); });
+    buffer.set(&["title"], "once")?;
+    buffer.clear_on_change();
+    buffer.set(&["title"], "twice")?;
+    assert_eq!(changes.load(Ordering::Relaxed), 7);
+
+    Ok(())
+}
+
+#[test]
+fn del_recursive_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name",  {"type": "string"}],
+        ["items", {"type": "list", "of": {"type": "table", "columns": [
+            ["tags", {"type": "map", "value": {"type": "string"}}],
+            ["pair", {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}]
+        ]}}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["name"], "kept")?;
+    buffer.set(&["items", "0", "tags", "a"], "one")?;
+    buffer.set(&["items", "2", "pair", "0"], 3u8)?;
+    buffer.set(&["items", "2", "pair", "1"], "two")?;
+    let pair = buffer.select(buffer.cursor, false, &["items", "2", "pair"])?.unwrap();
+    let tag = buffer.select(buffer.cursor, false, &["items", "0", "tags", "a"])?.unwrap();
+
+    // the backup cursor points inside the deleted values
+    buffer.move_cursor(&["items", "2", "pair"])?;
+    buffer.backup_cursor();
+    buffer.cursor_to_root();
+
+    let wasted = buffer.calc_bytes()?.wasted_bytes;
+    let freed = buffer.del_recursive(&["items"])?.unwrap();
+    assert!(freed > 0);
+    assert_eq!(buffer.calc_bytes()?.wasted_bytes, wasted + freed);
+
+    // stale addresses below the path don't lead anywhere
+    assert_eq!(pair.get_value(&buffer.memory).get_addr_value(), 0);
+    assert_eq!(tag.get_value(&buffer.memory).get_addr_value(), 0);
+    assert_eq!(buffer.json_encode(&["items"])?.stringify(), "null");
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("kept"));
+
+    // and cursors that were inside them go back to the root
+    buffer.restore_cursor();
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("kept"));
+
+    // scalars are deleted like `del`
+    assert_eq!(buffer.del_recursive(&["name"])?, Some(6));
+    assert_eq!(buffer.get::<&str>(&["name"])?, None);
+
+    // read only buffers can't delete anything
+    let bytes = buffer.close();
+    let mut read_only = factory.open_buffer_read_only(&bytes);
+    assert!(read_only.del_recursive(&["items"]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn clear_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name", {"type": "string"}],
+        ["tags", {"type": "list", "of": {"type": "string"}}]
+    ]}"#)?.with_fingerprint();
+
+    let mut buffer = factory.empty_buffer(Some(512));
+    let empty = buffer.read_bytes().to_vec();
+    buffer.start_journal();
+    buffer.set(&["name"], "first")?;
+    buffer.set(&["tags", "3"], "tag")?;
+    buffer.move_cursor(&["tags"])?;
+    buffer.backup_cursor();
+
+    // back to the empty buffer with the same allocation and header
+    let start = buffer.read_bytes().as_ptr();
+    buffer.clear();
+    assert_eq!(buffer.read_bytes(), &empty[..]);
+    assert_eq!(buffer.read_bytes().as_ptr(), start);
+    assert_eq!(buffer.journal().len(), 0);
+    assert_eq!(buffer.undo()?, false);
+
+    // cursors are back at the root
+    buffer.restore_cursor();
+    buffer.set(&["name"], "second")?;
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("second"));
+    assert_eq!(buffer.get::<&str>(&["tags", "3"])?, None);
+    assert_eq!(factory.open_buffer_checked(buffer.close())?.get::<&str>(&["name"])?, Some("second"));
+
+    // sortable buffers get their values back in place
+    let sortable = NP_Factory::new(r#"{"type": "tuple", "sorted": true, "values": [{"type": "u8"}, {"type": "string", "size": 4}]}"#)?;
+    let mut buffer = sortable.empty_buffer(None);
+    buffer.set(&["0"], 9u8)?;
+    let empty = sortable.empty_buffer(None).close_sortable()?;
+    buffer.clear();
+    assert_eq!(buffer.get::<u8>(&["0"])?, Some(0));
+    assert_eq!(buffer.close_sortable()?, empty);
+
+    // read only buffers start over with their own bytes
+    let bytes = factory.empty_buffer(None).close();
+    let mut read_only = factory.open_buffer_read_only(&bytes);
+    read_only.clear();
+    read_only.set(&["name"], "owned")?;
+    assert_eq!(read_only.get::<&str>(&["name"])?, Some("owned"));
+
+    Ok(())
+}
+
+#[test]
+fn missing_list_items_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use alloc::string::{String, ToString};
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["tags", {"type": "list", "of": {"type": "string"}}],
+        ["rows", {"type": "list", "of": {"type": "list", "of": {"type": "u8"}}}]
+    ]}"#)?;
+
+    // no list at all
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<&str>(&["tags", "3"])?, None);
+    assert_eq!(buffer.get::<u8>(&["rows", "2", "0"])?, None);
+
+    // list without that item
+    buffer.set(&["tags", "1"], "one")?;
+    buffer.set(&["rows", "0", "0"], 4u8)?;
+    assert_eq!(buffer.get::<&str>(&["tags", "3"])?, None);
+    assert_eq!(buffer.get::<u8>(&["rows", "2", "0"])?, None);
+    assert_eq!(buffer.get::<&str>(&["tags", "1"])?, Some("one"));
+    assert_eq!(buffer.get::<u8>(&["rows", "0", "0"])?, Some(4));
+
+    // values only need to outlive the borrow of the buffer
+    let pushed = {
+        let short = String::from("short lived");
+        buffer.set(&["tags", "0"], short.as_str())?;
+        buffer.list_push(&["tags"], short.as_str())?
+    };
+    assert_eq!(buffer.get::<&str>(&["tags", "0"])?, Some("short lived"));
+    assert_eq!(buffer.get::<&str>(&["tags", &pushed.unwrap_or(0).to_string()])?, Some("short lived"));
+
+    Ok(())
+}
+
+#[test]
+fn get_str_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name", {"type": "string"}],
+        ["code", {"type": "string", "size": 4}],
+        ["title", {"type": "string", "default": "none"}],
+        ["age", {"type": "u8"}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get_str(&["name"])?, None);
+    assert_eq!(buffer.get_str(&["title"])?, Some("none"));
+    buffer.set(&["name"], "hello")?;
+    buffer.set(&["code"], "ab")?;
+    assert_eq!(buffer.get_str(&["code"])?, Some("ab  "));
+
+    // the string is borrowed from the buffer bytes
+    let bytes = buffer.read_bytes();
+    let name = buffer.get_str(&["name"])?.unwrap_or_default();
+    assert_eq!(name, "hello");
+    assert!(bytes.as_ptr_range().contains(&name.as_ptr()));
+
+    // wrong type
+    assert!(buffer.get_str(&["age"]).is_err());
+
+    // invalid utf8 errors instead of being returned
+    let mut bytes = buffer.close();
+    let at = bytes.windows(5).position(|w| w == b"hello").unwrap_or_default();
+    bytes[at] = 0xff;
+    let buffer = factory.open_buffer(bytes);
+    assert!(matches!(buffer.get_str(&["name"]), Err(NP_Error::Utf8(_))));
+
+    Ok(())
+}
+
+#[test]
+fn get_bytes_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["hash", {"type": "bytes", "size": 4}],
+        ["blob", {"type": "bytes"}],
+        ["salt", {"type": "bytes", "default": [1, 2]}],
+        ["name", {"type": "string"}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get_bytes(&["blob"])?, None);
+    assert_eq!(buffer.get_bytes(&["salt"])?, Some(&[1u8, 2][..]));
+    buffer.set(&["hash"], &[9u8, 8][..])?;
+    buffer.set(&["blob"], &[1u8, 2, 3, 4, 5][..])?;
+    assert_eq!(buffer.get_bytes(&["hash"])?, Some(&[9u8, 8, 0, 0][..]));
+
+    // the slice is borrowed from the buffer bytes
+    let bytes = buffer.read_bytes();
+    let blob = buffer.get_bytes(&["blob"])?.unwrap_or_default();
+    assert_eq!(blob, &[1, 2, 3, 4, 5]);
+    assert!(bytes.as_ptr_range().contains(&blob.as_ptr()));
+
+    // wrong type
+    assert!(buffer.get_bytes(&["name"]).is_err());
+
+    Ok(())
 }
 
+#[test]
+fn json_buffer_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use crate::pointer::date::NP_Date;
 
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name", {"type": "string"}],
+        ["age", {"type": "u8"}],
+        ["score", {"type": "i32"}],
+        ["ratio", {"type": "float"}],
+        ["created", {"type": "date"}],
+        ["id", {"type": "uuid"}],
+        ["tags", {"type": "list", "of": {"type": "string"}}],
+        ["meta", {"type": "map", "value": {"type": "u16"}}]
+    ]}"#)?;
 
-/// NP Item
-pub struct NP_Item<'item> {
-    /// index of this value
-    pub index: usize,
-    /// Key at this index
-    pub key: &'item str,
-    /// Column at this index
-    pub col: &'item str,
-    /// Cursor value
-    cursor: Option<NP_Cursor>,
-    parent: NP_Cursor,
-    memory: &'item NP_Memory<'item>
+    // numbers and dates coerced from strings and whole floats
+    let buffer = factory.json_buffer(r#"{
+        "name": "Jeb",
+        "age": "30",
+        "score": -4.0,
+        "ratio": "0.5",
+        "created": "1605138980392",
+        "id": "48e6aab07df5409f4d574d969fa065ee",
+        "tags": ["a", null, "c"],
+        "meta": {"x": 1, "y": "2"}
+    }"#)?;
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("Jeb"));
+    assert_eq!(buffer.get::<u8>(&["age"])?, Some(30));
+    assert_eq!(buffer.get::<i32>(&["score"])?, Some(-4));
+    assert_eq!(buffer.get::<f32>(&["ratio"])?, Some(0.5));
+    assert_eq!(buffer.get::<NP_Date>(&["created"])?, Some(NP_Date::new(1605138980392)));
+    assert_eq!(buffer.json_encode(&["id"])?.stringify(), "\"48E6AAB0-7DF5-409F-4D57-4D969FA065EE\"");
+    assert_eq!(buffer.json_encode(&["tags"])?.stringify(), "[\"a\",null,\"c\"]");
+    assert_eq!(buffer.get::<u16>(&["meta", "y"])?, Some(2));
+
+    // round trips through json_encode, map keys can come back in another order
+    let json = buffer.json_encode(&[])?.stringify();
+    assert!(factory.json_buffer(&json)?.deep_eq(&buffer)?);
+
+    // values that don't fit the schema
+    assert!(factory.json_buffer(r#"{"age": 1.5}"#).is_err());
+    assert!(factory.json_buffer(r#"{"age": 300}"#).is_err());
+    assert!(factory.json_buffer(r#"{"age": "old"}"#).is_err());
+    assert!(factory.json_buffer(r#"{"id": "not a uuid"}"#).is_err());
+    assert!(factory.json_buffer(r#"{"tags": "a"}"#).is_err());
+    assert!(factory.json_buffer(r#"["Jeb", 30]"#).is_err());
+
+    Ok(())
 }
 
-impl<'item> NP_Item<'item> {
+#[cfg(feature = "std")]
+#[test]
+fn json_lines_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use alloc::vec;
+    extern crate std;
 
-    /// If this item has a value
-    pub fn has_value(&self) -> bool {
-        if let Some(x) = self.cursor {
-            let value = x.get_value(self.memory);
-            value.get_addr_value() != 0
-        } else {
-            false
-        }
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["rows", {"type": "list", "of": {"type": "table", "columns": [["id", {"type": "u32"}]]}}],
+        ["name", {"type": "string"}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    let mut output: Vec<u8> = Vec::new();
+
+    // unset lists write nothing
+    assert_eq!(buffer.json_lines(&["rows"], &mut output)?, 0);
+    assert_eq!(buffer.json_lines(&["missing"], &mut output)?, 0);
+    assert!(output.is_empty());
+
+    // gaps become null lines
+    buffer.set(&["rows", "1", "id"], 1u32)?;
+    buffer.set(&["rows", "3", "id"], 3u32)?;
+    assert_eq!(buffer.json_lines(&["rows"], &mut output)?, 4);
+    assert_eq!(output, b"null\n{\"id\":1}\nnull\n{\"id\":3}\n".to_vec());
+
+    // every line is the json of that list item
+    let lines: Vec<&[u8]> = output.split(|b| *b == b'\n').filter(|l| !l.is_empty()).collect();
+    for (index, line) in lines.into_iter().enumerate() {
+        let index = index.to_string();
+        assert_eq!(line, buffer.json_encode(&["rows", &index])?.stringify().as_bytes());
     }
-    /// Get value at this pointer
-    pub fn get<X>(&'item self) -> Result<Option<X>, NP_Error> where X: NP_Value<'item> + NP_Scalar {
-        if let Some(cursor) = self.cursor {
-            match X::into_value(&cursor, &self.memory)? {
-                Some(x) => {
-                    Ok(Some(x))
-                },
-                None => {
-                    match X::schema_default(&self.memory.schema[cursor.schema_addr]) {
-                        Some(y) => {
-                            Ok(Some(y))
-                        },
-                        None => {
-                            Ok(None)
-                        }
-                    }
-                }
-            }
-        } else {
-            Ok(None)
-        }
+
+    // the cursor path is followed
+    buffer.move_cursor(&["rows"])?;
+    let mut from_cursor: Vec<u8> = Vec::new();
+    assert_eq!(buffer.json_lines(&[], &mut from_cursor)?, 4);
+    assert_eq!(from_cursor, output);
+    buffer.cursor_to_root();
+
+    // only lists can be exported
+    assert!(buffer.json_lines(&["name"], &mut vec![]).is_err());
+
+    // write errors are returned
+    let mut full = [0u8; 4];
+    assert!(matches!(buffer.json_lines(&["rows"], &mut &mut full[..]), Err(NP_Error::Io(_))));
+
+    Ok(())
+}
+
+#[test]
+fn fingerprints_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let schema = r#"{"type": "table", "columns": [["name", {"type": "string"}], ["age", {"type": "u8"}]]}"#;
+    let plain = NP_Factory::new(schema)?;
+    let stamped = NP_Factory::new(schema)?.with_fingerprint();
+    let other = NP_Factory::new(r#"{"type": "table", "columns": [["name", {"type": "string"}], ["age", {"type": "u16"}]]}"#)?.with_fingerprint();
+
+    // the hash of the compiled schema doesn't change between releases or schema sources
+    let fingerprint = plain.schema.fingerprint();
+    assert_eq!(fingerprint, [146, 77, 232, 196, 5, 78, 148, 84]);
+    assert_eq!(NP_Factory::new_compiled(plain.compile_schema()).schema.fingerprint(), fingerprint);
+    assert_ne!(other.schema.fingerprint(), fingerprint);
+
+    // stamped buffers carry it in an 8 byte larger header
+    let mut buffer = stamped.empty_buffer(None);
+    assert_eq!(buffer.fingerprint(), Some(fingerprint));
+    assert_eq!(buffer.read_bytes().len(), plain.empty_buffer(None).read_bytes().len() + 8);
+    assert_eq!(plain.empty_buffer(None).fingerprint(), None);
+    buffer.set(&["name"], "hello")?;
+    buffer.set(&["name"], "world")?;
+    buffer.compact(None)?;
+    assert_eq!(buffer.fingerprint(), Some(fingerprint));
+    let bytes = buffer.close();
+
+    // checked opens compare fingerprints
+    assert_eq!(stamped.open_buffer_checked(bytes.clone())?.get::<&str>(&["name"])?, Some("world"));
+    assert_eq!(plain.open_buffer_checked(bytes.clone())?.get::<&str>(&["name"])?, Some("world"));
+    assert!(matches!(other.open_buffer_checked(bytes.clone()), Err(NP_Error::SchemaMismatch(_))));
+    let unstamped = plain.empty_buffer(None).close();
+    assert!(plain.open_buffer_checked(unstamped.clone()).is_ok());
+    assert!(matches!(stamped.open_buffer_checked(unstamped), Err(NP_Error::SchemaMismatch(_))));
+
+    // unchecked opens read the bytes either way
+    assert_eq!(plain.open_buffer(bytes).get::<&str>(&["name"])?, Some("world"));
+
+    Ok(())
+}
+
+#[test]
+fn calc_bytes_report_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name", {"type": "string"}],
+        ["age", {"type": "u8"}],
+        ["tags", {"type": "list", "of": {"type": "string"}}],
+        ["meta", {"type": "map", "value": {"type": "string"}}],
+        ["pair", {"type": "tuple", "values": [{"type": "u32"}, {"type": "string"}]}]
+    ]}"#)?;
+
+    // empty buffers only have the root
+    let mut buffer = factory.empty_buffer(None);
+    let report = buffer.calc_bytes_report()?;
+    assert_eq!((report.key.as_str(), report.bytes, report.wasted_bytes), ("", buffer.read_bytes().len(), 0));
+    assert!(report.children.is_empty());
+
+    buffer.set(&["name"], "hello")?;
+    buffer.set(&["tags", "4"], "four")?;
+    buffer.set(&["tags", "1"], "one")?;
+    buffer.set(&["meta", "color"], "blue")?;
+    buffer.set(&["pair", "1"], "second")?;
+    buffer.set(&["name"], "replaced")?;
+
+    let report = buffer.calc_bytes_report()?;
+    let size = buffer.calc_bytes()?;
+    assert_eq!((report.bytes, report.wasted_bytes), (size.after_compaction, size.wasted_bytes));
+    assert!(report.wasted_bytes > 0);
+
+    // only set values are reported, keyed like paths
+    let keys: Vec<&str> = report.children.iter().map(|x| x.key.as_str()).collect();
+    assert_eq!(keys, alloc::vec!["name", "tags", "meta", "pair"]);
+    let tags: Vec<&str> = report.find(&["tags"]).map(|x| x.children.iter().map(|x| x.key.as_str()).collect()).unwrap_or_default();
+    assert_eq!(tags, alloc::vec!["1", "4"]);
+    assert!(report.find(&["meta", "color"]).is_some());
+    assert!(report.find(&["pair", "1"]).is_some());
+    assert!(report.find(&["pair", "0"]).is_none());
+    assert!(report.find(&["age"]).is_none());
+    assert!(report.find(&["tags", "0"]).is_none());
+
+    // collections count their children
+    let fits = |report: &NP_Size_Report| report.children.iter().map(|x| x.bytes).sum::<usize>() <= report.bytes;
+    assert!(fits(&report));
+    for child in &report.children {
+        assert!(fits(child));
     }
 
-    /// Set value at this pointer
-    pub fn set<X>(&'item mut self, value: X) -> Result<(), NP_Error> where X: NP_Value<'item> + NP_Scalar {
-        if let Some(cursor) = self.cursor {
-            X::set_value(cursor.clone(), self.memory, value)?;
-        } else {
-            match self.memory.schema[self.parent.schema_addr] {
-                NP_Parsed_Schema::List { .. } => {
-                    let item = opt_err(opt_err(NP_List::select(self.parent.clone(), self.index, true, self.memory)?)?.1)?;
-                    X::set_value(item, self.memory, value)?;
-                }
-                NP_Parsed_Schema::Table { .. } => {
-                    let item = opt_err(NP_Table::select(self.parent.clone(), self.key, true, self.memory)?)?;
-                    X::set_value(item, self.memory, value)?;
-                },
-                NP_Parsed_Schema::Tuple { .. } => {
-                    let item = opt_err(NP_Tuple::select(self.parent.clone(), self.index, true, self.memory)?)?;
-                    X::set_value(item, self.memory, value)?;
-                }
-                _ => { }
-            }
-        }
+    // compaction drops the wasted bytes but no value changes size
+    let mut compacted = factory.open_buffer(buffer.read_bytes().to_vec());
+    compacted.compact(None)?;
+    let after = compacted.calc_bytes_report()?;
+    assert_eq!(after.wasted_bytes, 0);
+    assert_eq!(after.bytes, compacted.read_bytes().len());
+    assert_eq!(after.children, report.children);
 
-        Ok(())
+    Ok(())
+}
+
+#[test]
+fn cmp_sortable_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let schema = r#"{"type": "tuple", "sorted": true, "values": [{"type": "string", "size": 4}, {"type": "i32"}, {"type": "bool"}]}"#;
+    let factory = NP_Factory::new(schema)?;
+    let stamped = NP_Factory::new(schema)?.with_fingerprint().with_version(9);
+
+    let rows: [(&str, i32, bool); 6] = [("b", -200, true), ("a", 5, false), ("b", 7, false), ("a", -1, true), ("ab", 0, false), ("b", -200, false)];
+    let mut buffers = Vec::new();
+    for (x, (name, num, flag)) in rows.iter().enumerate() {
+        // every other buffer has a larger header
+        let mut buffer = if x % 2 == 0 { factory.empty_buffer(None) } else { stamped.empty_buffer(None) };
+        buffer.set(&["0"], *name)?;
+        buffer.set(&["1"], *num)?;
+        buffer.set(&["2"], *flag)?;
+        buffers.push(buffer);
     }
 
-    /// Clear the value at this pointer
-    pub fn del(&'item mut self) -> bool {
-        if let Some(cursor) = self.cursor {
-            let value = cursor.get_value(self.memory);
-            value.set_addr_value(0);
-            true
-        } else {
-            false
+    // the order matches the values and the closed bytes, not the raw buffer bytes
+    for a in 0..rows.len() {
+        for b in 0..rows.len() {
+            let expected = (rows[a].0, rows[a].1, rows[a].2).cmp(&(rows[b].0, rows[b].1, rows[b].2));
+            assert_eq!(buffers[a].cmp_sortable(&buffers[b])?, expected);
+            let closed_a = factory.open_buffer(buffers[a].read_bytes().to_vec()).close_sortable()?;
+            let closed_b = factory.open_buffer(buffers[b].read_bytes().to_vec()).close_sortable()?;
+            assert_eq!(closed_a.cmp(&closed_b), expected);
         }
     }
+
+    // unset values compare as their defaults
+    let empty = factory.empty_buffer(None);
+    let mut defaults = stamped.empty_buffer(None);
+    defaults.set(&["1"], 0i32)?;
+    assert_eq!(empty.cmp_sortable(&defaults)?, core::cmp::Ordering::Equal);
+
+    // only sortable buffers can be compared
+    let unsorted = NP_Factory::new(r#"{"type": "tuple", "values": [{"type": "i32"}]}"#)?;
+    assert!(unsorted.empty_buffer(None).cmp_sortable(&empty).is_err());
+    assert!(empty.cmp_sortable(&unsorted.empty_buffer(None)).is_err());
+
+    Ok(())
 }
 
-/// Iterator Enum
-#[derive(Debug)]
-#[doc(hidden)]
-pub enum NP_Iterator_Collection<'col> {
-    /// None
-    None,
-    /// Map
-    Map(NP_Map<'col>),
-    /// List
-    List(NP_List),
-    /// Table
-    Table(NP_Table<'col>),
-    /// Tuple
-    Tuple(NP_Tuple<'col>)
+#[test]
+fn sortable_bytes_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["count", {"type": "i64"}],
+        ["score", {"type": "double"}],
+        ["name", {"type": "string"}],
+        ["rows", {"type": "list", "of": {"type": "tuple", "values": [{"type": "u16"}, {"type": "bool", "default": true}]}}],
+        ["meta", {"type": "map", "value": {"type": "u8"}}]
+    ]}"#)?;
+
+    // the bytes sort in the same order as the values
+    let mut buffer = factory.empty_buffer(None);
+    let counts = [-300i64, -1, 0, 2, 70_000];
+    let scores = [-2.5f64, -0.5, 0.0, 0.25, 1e10];
+    let names = ["", "a", "a\u{0}", "ab", "b"];
+    let mut keys: [Vec<Vec<u8>>; 3] = [Vec::new(), Vec::new(), Vec::new()];
+    for x in 0..5 {
+        buffer.set(&["count"], counts[x])?;
+        buffer.set(&["score"], scores[x])?;
+        buffer.set(&["name"], names[x])?;
+        keys[0].push(buffer.sortable_bytes(&["count"])?);
+        keys[1].push(buffer.sortable_bytes(&["score"])?);
+        keys[2].push(buffer.sortable_bytes(&["name"])?);
+    }
+    for key in keys.iter() {
+        assert!(key.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    // tuples inside collections, relative to the cursor and with defaults
+    buffer.set(&["rows", "2", "0"], 258u16)?;
+    assert_eq!(buffer.sortable_bytes(&["rows", "2"])?, alloc::vec![1, 2, 1]);
+    assert_eq!(buffer.sortable_bytes(&["rows", "2", "1"])?, alloc::vec![1]);
+    assert_eq!(buffer.sortable_bytes(&["rows", "7"])?, alloc::vec![0, 0, 1]);
+    buffer.move_cursor(&["rows"])?;
+    assert_eq!(buffer.sortable_bytes(&["2"])?, alloc::vec![1, 2, 1]);
+    buffer.cursor_to_root();
+    buffer.set(&["meta", "a"], 4u8)?;
+    assert_eq!(buffer.sortable_bytes(&["meta", "a"])?, alloc::vec![4]);
+    assert_eq!(buffer.sortable_bytes(&["meta", "missing"])?, alloc::vec![0]);
+
+    // root sortable tuples give the same bytes as close_sortable
+    let sorted = NP_Factory::new(r#"{"type": "tuple", "sorted": true, "values": [{"type": "i8"}, {"type": "string", "size": 2}]}"#)?;
+    let mut key = sorted.empty_buffer(None);
+    key.set(&["0"], -3i8)?;
+    key.set(&["1"], "z")?;
+    assert_eq!(key.sortable_bytes(&[])?, key.close_sortable()?);
+
+    // collections without a fixed order and paths outside the schema
+    assert!(buffer.sortable_bytes(&["rows"]).is_err());
+    assert!(buffer.sortable_bytes(&["meta"]).is_err());
+    assert!(buffer.sortable_bytes(&[]).is_err());
+    assert!(buffer.sortable_bytes(&["missing"]).is_err());
+    assert!(buffer.sortable_bytes(&["rows", "x"]).is_err());
+
+    Ok(())
 }
 
-#[allow(missing_docs)]
-impl<'col> NP_Iterator_Collection<'col> {
-    pub fn new(cursor: NP_Cursor, memory: &'col NP_Memory) -> Result<Self, NP_Error> {
-        match memory.schema[cursor.schema_addr] {
-            NP_Parsed_Schema::Table { .. } => {
-                let table = NP_Table::new_iter(&cursor, memory);
-                Ok(NP_Iterator_Collection::Table(table))
-            },
-            NP_Parsed_Schema::List { .. } => {
-                let list = NP_List::new_iter(&cursor, memory, false, 0);
-                Ok(NP_Iterator_Collection::List(list))
-            },
-            NP_Parsed_Schema::Tuple { .. } => {
-                let tuple = NP_Tuple::new_iter(&cursor, memory);
-                Ok(NP_Iterator_Collection::Tuple(tuple))
-            },
-            NP_Parsed_Schema::Map { .. } => {
-                let map = NP_Map::new_iter(&cursor, memory);
-                Ok(NP_Iterator_Collection::Map(map))
-            },
-            _ => Err(NP_Error::new("Tried to create iterator on non collection item!"))
-        }
+#[test]
+fn values_after_growth_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use crate::pointer::ulid::NP_ULID;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["bool", {"type": "bool"}],
+        ["bytes", {"type": "bytes"}],
+        ["fixed bytes", {"type": "bytes", "size": 4}],
+        ["string", {"type": "string"}],
+        ["fixed string", {"type": "string", "size": 4}],
+        ["enum", {"type": "option", "choices": ["red", "blue"]}],
+        ["date", {"type": "date"}],
+        ["dec", {"type": "decimal", "exp": 2}],
+        ["geo", {"type": "geo4"}],
+        ["u32", {"type": "u32"}],
+        ["ulid", {"type": "ulid"}],
+        ["uuid", {"type": "uuid"}],
+        ["map", {"type": "map", "value": {"type": "u8"}}],
+        ["tuple", {"type": "tuple", "values": [{"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}]}],
+        ["sorted", {"type": "tuple", "sorted": true, "values": [{"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}, {"type": "u8"}]}]
+    ]}"#)?;
+
+    let ulid = NP_ULID::generate(1_604_965_249_484, 212);
+    let uuid = NP_UUID::generate(212);
+
+    // every set mallocs into bytes without spare capacity, so the buffer moves under the value being written
+    let mut loose = factory.empty_buffer(None);
+    let mut tight = {
+        let mut bytes = factory.empty_buffer(None).close();
+        bytes.shrink_to_fit();
+        bytes
+    };
+    macro_rules! set_both {
+        ($path: expr, $value: expr) => {{
+            loose.set($path, $value)?;
+            let mut buffer = factory.open_buffer(tight);
+            buffer.set($path, $value)?;
+            tight = buffer.close();
+            tight.shrink_to_fit();
+        }}
     }
+
+    set_both!(&["bool"], true);
+    set_both!(&["bytes"], &[1u8, 2, 3][..]);
+    set_both!(&["fixed bytes"], &[4u8, 5][..]);
+    set_both!(&["string"], "kerbin");
+    set_both!(&["fixed string"], "mun");
+    set_both!(&["enum"], NP_Enum::new("blue"));
+    set_both!(&["date"], NP_Date::new(1_604_965_249_484));
+    set_both!(&["dec"], NP_Dec::new(-1205, 2));
+    set_both!(&["geo"], NP_Geo::new(4, 45.5, -122.25));
+    set_both!(&["u32"], 70_000u32);
+    set_both!(&["ulid"], &ulid);
+    set_both!(&["uuid"], &uuid);
+    set_both!(&["map", "first"], 1u8);
+    set_both!(&["map", "second"], 2u8);
+    set_both!(&["tuple", "5"], 5u8);
+    set_both!(&["tuple", "0"], 9u8);
+    set_both!(&["sorted", "5"], 5u8);
+
+    let mut buffer = factory.open_buffer(tight);
+    buffer.verify()?;
+    assert!(buffer.deep_eq(&loose)?);
+    assert_eq!(buffer.get::<&str>(&["string"])?, Some("kerbin"));
+    assert_eq!(buffer.get::<u8>(&["tuple", "5"])?, Some(5));
+    assert_eq!(buffer.get::<u8>(&["sorted", "5"])?, Some(5));
+
+    // compaction copies tables and tuples with more than one vtable into a new buffer
+    buffer.compact(None)?;
+    assert!(buffer.deep_eq(&loose)?);
+
+    Ok(())
 }
 
-#[allow(missing_docs)]
-pub struct NP_Generic_Iterator<'it> {
-    root: NP_Cursor,
-    value: NP_Iterator_Collection<'it>,
-    memory: &'it NP_Memory<'it>,
-    index: usize
+#[test]
+fn journal_structure_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["l", {"type": "list", "of": {"type": "string"}}],
+        ["m", {"type": "map", "value": {"type": "list", "of": {"type": "u8"}}}],
+        ["t", {"type": "table", "columns": [["a", {"type": "u8"}], ["b", {"type": "u8"}]]}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.start_journal();
+
+    // items made in front of a new value go away with it
+    buffer.set(&["l", "3"], "kerbin")?;
+    assert_eq!(buffer.json_encode(&["l"])?.stringify(), r#"[null,null,null,"kerbin"]"#);
+    assert!(buffer.undo()?);
+    assert_eq!(buffer.json_encode(&["l"])?.stringify(), "null");
+    assert!(buffer.redo()?);
+    assert_eq!(buffer.json_encode(&["l"])?.stringify(), r#"[null,null,null,"kerbin"]"#);
+
+    // reading items past the end doesn't make them
+    assert_eq!(buffer.get::<&str>(&["l", "5"])?, None);
+    assert_eq!(buffer.length(&["l"])?, Some(4));
+
+    // the same for lists that already have items
+    buffer.set(&["l", "5"], "duna")?;
+    assert!(buffer.undo()?);
+    assert_eq!(buffer.json_encode(&["l"])?.stringify(), r#"[null,null,null,"kerbin"]"#);
+    assert_eq!(buffer.length(&["l"])?, Some(4));
+
+    // and for map keys with lists in them
+    buffer.set(&["m", "a", "0"], 1u8)?;
+    buffer.set(&["m", "b", "2"], 2u8)?;
+    assert!(buffer.undo()?);
+    assert_eq!(buffer.json_encode(&["m"])?.stringify(), r#"{"a":[1]}"#);
+    assert!(buffer.undo()?);
+    assert_eq!(buffer.json_encode(&["m"])?.stringify(), "null");
+
+    // values that already exist only record themselves
+    buffer.set(&["t", "a"], 1u8)?;
+    buffer.set(&["t", "b"], 2u8)?;
+    assert_eq!(buffer.journal().last().map(|change| change.path.len()), Some(2));
+    assert!(buffer.undo()?);
+    assert_eq!(buffer.json_encode(&["t"])?.stringify(), r#"{"a":1,"b":null}"#);
+
+    Ok(())
 }
 
-#[allow(missing_docs)]
-impl<'it> NP_Generic_Iterator<'it> {
-    pub fn new(cursor: NP_Cursor, memory: &'it NP_Memory) -> Result<Self, NP_Error> {
-        Ok(Self { 
-            root: cursor.clone(),
-            value: NP_Iterator_Collection::new(cursor.clone(), memory)?,
-            memory: memory,
-            index: 0
-        })
+#[cfg(feature = "std")]
+#[test]
+fn open_from_reader_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    extern crate std;
+    use std::io::{Read, ErrorKind};
+
+    // a reader that gives one byte per read and is interrupted every other read
+    struct Trickle<'bytes> { bytes: &'bytes [u8], interrupt: bool }
+
+    impl<'bytes> Read for Trickle<'bytes> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(ErrorKind::Interrupted.into());
+            }
+            if self.bytes.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.bytes[0];
+            self.bytes = &self.bytes[1..];
+            Ok(1)
+        }
+    }
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+
+    let mut stream: Vec<u8> = Vec::new();
+    for item in ["one", "two"].iter() {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.list_push(&[], *item)?;
+        let bytes = buffer.close();
+        stream.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+        stream.extend_from_slice(&bytes);
     }
+
+    let mut reader = Trickle { bytes: &stream, interrupt: false };
+    assert_eq!(factory.open_from_reader(&mut reader, 1024)?.unwrap().get::<&str>(&["0"])?, Some("one"));
+    assert_eq!(factory.open_from_reader(&mut reader, 1024)?.unwrap().get::<&str>(&["0"])?, Some("two"));
+    assert!(factory.open_from_reader(&mut reader, 1024)?.is_none());
+
+    // buffers past the max size fail before reading the body
+    let mut reader = &stream[..];
+    assert!(matches!(factory.open_from_reader(&mut reader, 4), Err(NP_Error::OutOfBounds(_))));
+    assert_eq!(reader.len(), stream.len() - 4);
+
+    // a damaged length can't allocate more than the reader has
+    let mut reader = &[0xffu8, 0xff, 0xff, 0xff, 1, 2, 3][..];
+    assert!(matches!(factory.open_from_reader(&mut reader, usize::MAX), Err(NP_Error::Io(_))));
+
+    // readers that end part way through a buffer
+    assert!(matches!(factory.open_from_reader(&mut &stream[0..2], 1024), Err(NP_Error::Io(_))));
+    assert!(matches!(factory.open_from_reader(&mut &stream[0..8], 1024), Err(NP_Error::Io(_))));
+
+    Ok(())
 }
 
+#[cfg(feature = "std")]
+#[test]
+fn write_to_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    extern crate std;
+    use std::io::{Write, ErrorKind};
 
-impl<'it> Iterator for NP_Generic_Iterator<'it> {
-    type Item = NP_Item<'it>;
+    // a writer that keeps the size of every write and fails once it's full
+    struct Chunks { bytes: Vec<u8>, writes: Vec<usize>, limit: usize }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match &mut self.value {
-            NP_Iterator_Collection::Map(x) => {
-                if let Some(next_item) = x.step_iter(&self.memory) {
-                    self.index += 1;
-                    Some(NP_Item { memory: self.memory, key: next_item.0, col: next_item.0, index: self.index - 1, cursor: Some(next_item.1), parent: self.root.clone() })
-                } else {
-                    None
-                }
-            },
-            NP_Iterator_Collection::List(x) => {
-                if let Some(next_item) = x.step_iter(&self.memory) {
-                    Some(NP_Item { memory: self.memory, key: "", col: "", index: next_item.0, cursor: next_item.1, parent: self.root.clone() })
-                } else {
-                    None
-                }
-            },
-            NP_Iterator_Collection::Table(x) => {
-                if let Some(next_item) = x.step_iter(&self.memory) {
-                    Some(NP_Item { memory: self.memory, key: next_item.1, col: next_item.1, index: next_item.0, cursor: next_item.2, parent: self.root.clone() })
-                } else {
-                    None
-                }
-            },
-            NP_Iterator_Collection::Tuple(x) => {
-                if let Some(next_item) = x.step_iter(&self.memory) {
-                    Some(NP_Item { memory: self.memory, key: "", col: "", index: next_item.0, cursor: next_item.1, parent: self.root.clone() })
-                } else {
-                    None
-                }
-            },
-            _ => { None }
+    impl Write for Chunks {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.bytes.len() + buf.len() > self.limit {
+                return Err(ErrorKind::WriteZero.into());
+            }
+            self.bytes.extend_from_slice(buf);
+            self.writes.push(buf.len());
+            Ok(buf.len())
         }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    let mut buffer = factory.empty_buffer(Some(20_000));
+    let text: String = core::iter::repeat('x').take(200).collect();
+    for _ in 0..100 {
+        buffer.list_push(&[], text.as_str())?;
     }
-}
\ No newline at end of file
+    buffer.del(&["0"])?;
+
+    // large buffers are written in chunks
+    let mut writer = Chunks { bytes: Vec::new(), writes: Vec::new(), limit: usize::MAX };
+    assert_eq!(buffer.write_to(&mut writer, false)?, buffer.read_bytes().len());
+    assert_eq!(&writer.bytes[..], buffer.read_bytes());
+    assert!(writer.writes.len() > 1 && writer.writes.iter().all(|len| *len <= WRITE_CHUNK_SIZE));
+
+    // compacted writes match a compacted copy and leave the buffer alone
+    let len = buffer.read_bytes().len();
+    let mut compacted = Chunks { bytes: Vec::new(), writes: Vec::new(), limit: usize::MAX };
+    let written = buffer.write_to(&mut compacted, true)?;
+    assert_eq!(buffer.read_bytes().len(), len);
+    let mut copy = factory.open_buffer(buffer.read_bytes().to_vec());
+    copy.compact(None)?;
+    assert_eq!(&compacted.bytes[..], copy.read_bytes());
+    assert_eq!(written, copy.read_bytes().len());
+    assert!(written < len);
+
+    // writer errors come back as io errors
+    let mut full = Chunks { bytes: Vec::new(), writes: Vec::new(), limit: 100 };
+    assert!(matches!(buffer.write_to(&mut full, false), Err(NP_Error::Io(_))));
+
+    Ok(())
+}
+
+#[test]
+fn set_null_json_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["name", {"type": "string", "default": "none"}],
+        ["title", {"type": "string", "default": "none"}],
+        ["tags", {"type": "list", "of": {"type": "string"}}],
+        ["attrs", {"type": "map", "value": {"type": "u8"}}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set_null(&["title"])?;
+    buffer.set(&["tags", "0"], "pilot")?;
+    buffer.set_null(&["tags", "2"])?;
+    buffer.set_null(&["attrs", "x"])?;
+
+    // explicit nulls and values that were never written look the same with defaults
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"{"name":"none","title":null,"tags":["pilot",null,null],"attrs":{"x":null}}"#);
+
+    // without defaults only the explicit nulls are left
+    let options = NP_JSON_Options { emit_defaults: false, ..Default::default() };
+    assert_eq!(buffer.json_encode_with(&[], &options)?.stringify(), r#"{"title":null,"tags":["pilot",null,null],"attrs":{"x":null}}"#);
+    assert_eq!(buffer.json_encode_with(&["title"], &options)?.stringify(), "null");
+
+    // deleting a null leaves nothing behind
+    buffer.del(&["title"])?;
+    buffer.del(&["tags", "2"])?;
+    buffer.del(&["attrs", "x"])?;
+    assert_eq!(buffer.json_encode_with(&[], &options)?.stringify(), r#"{"tags":["pilot"],"attrs":{}}"#);
+
+    Ok(())
+}
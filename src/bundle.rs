@@ -0,0 +1,265 @@
+//! Many buffers in one file
+//!
+//! `NP_Bundle` packs closed buffers into one block of bytes with an index, so documents can be archived together and read back by position without scanning the whole file.  Buffers in a bundle can use different schemas, buffers from factories made with `with_fingerprint` carry their schema fingerprint so readers can tell them apart, see `NP_Factory_Registry::factory_for`.
+//!
+//! Bundle bytes look like this:
+//!
+//! | Bytes       | Value |
+//! |-------------|-------|
+//! | 4           | Magic bytes `NPBD` |
+//! | 1           | Bundle version, always `1` for now |
+//! | ...         | Each buffer as a 4 byte big endian length and the buffer bytes |
+//! | 8 per entry | Index of where each buffer starts, big endian |
+//! | 4           | Number of buffers, big endian |
+//! | 8           | Where the index starts, big endian |
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::bundle::NP_Bundle;
+//!
+//! let users = NP_Factory::new(r#"{"type": "table", "columns": [["name", {"type": "string"}]]}"#)?.with_fingerprint();
+//! let scores = NP_Factory::new(r#"{"type": "list", "of": {"type": "u32"}}"#)?.with_fingerprint();
+//!
+//! let mut bundle = NP_Bundle::new();
+//!
+//! let mut user = users.empty_buffer(None);
+//! user.set(&["name"], "Jeb")?;
+//! assert_eq!(bundle.push_buffer(&user), 0);
+//!
+//! let mut score = scores.empty_buffer(None);
+//! score.list_push(&[], 20u32)?;
+//! assert_eq!(bundle.push_buffer(&score), 1);
+//!
+//! // save the file somewhere
+//! let file: Vec<u8> = bundle.into_bytes();
+//!
+//! // then open it again and add more
+//! let mut bundle = NP_Bundle::from_bytes(file)?;
+//! bundle.push_buffer(&user);
+//! assert_eq!(bundle.len(), 3);
+//!
+//! assert_eq!(bundle.fingerprint(1), Some(scores.schema.fingerprint()));
+//! let score = bundle.open(1, &scores)?;
+//! assert_eq!(score.get::<u32>(&["0"])?, Some(20));
+//!
+//! // buffers only open with the factory they were made with
+//! assert!(bundle.open(1, &users).is_err());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::memory::NP_HEADER_FINGERPRINT;
+use alloc::vec::Vec;
+
+/// Magic bytes at the start of every bundle
+pub const NP_BUNDLE_MAGIC: [u8; 4] = *b"NPBD";
+
+/// Bundle version written by `into_bytes`
+pub const NP_BUNDLE_VERSION: u8 = 1;
+
+/// Magic bytes and version
+const BUNDLE_HEADER_SIZE: usize = 5;
+/// Number of buffers and index start
+const BUNDLE_TRAILER_SIZE: usize = 12;
+
+/// Closed buffers packed together with an index
+///
+/// Check out documentation [here](../bundle/index.html).
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NP_Bundle {
+    /// Header and length prefixed buffers, the index is only written by `into_bytes`
+    bytes: Vec<u8>,
+    /// Where each buffer's length prefix starts
+    offsets: Vec<usize>
+}
+
+impl Default for NP_Bundle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NP_Bundle {
+
+    /// Create an empty bundle
+    pub fn new() -> Self {
+        let mut bytes = Vec::with_capacity(BUNDLE_HEADER_SIZE);
+        bytes.extend_from_slice(&NP_BUNDLE_MAGIC);
+        bytes.push(NP_BUNDLE_VERSION);
+        NP_Bundle { bytes, offsets: Vec::new() }
+    }
+
+    /// Open bundle bytes made by `into_bytes`.  Fails if the bytes or the index are damaged.
+    pub fn from_bytes(mut bytes: Vec<u8>) -> Result<Self, NP_Error> {
+        let damaged = || NP_Error::Damaged("Bundle bytes are damaged!".into());
+
+        if bytes.len() < BUNDLE_HEADER_SIZE + BUNDLE_TRAILER_SIZE || bytes[0..4] != NP_BUNDLE_MAGIC {
+            return Err(NP_Error::Damaged("Bytes aren't a bundle!".into()));
+        }
+        if bytes[4] != NP_BUNDLE_VERSION {
+            return Err(NP_Error::Damaged("Bundle version isn't supported!".into()));
+        }
+
+        let trailer = bytes.len() - BUNDLE_TRAILER_SIZE;
+        let count = bundle_u32(&bytes[trailer..]) as usize;
+        let index = bundle_u64(&bytes[(trailer + 4)..]);
+        if index < BUNDLE_HEADER_SIZE as u64 || index > trailer as u64 || (trailer as u64 - index) != count as u64 * 8 {
+            return Err(damaged());
+        }
+        let index = index as usize;
+
+        // every buffer has to fit between the header and the index
+        let mut offsets = Vec::with_capacity(count);
+        for entry in bytes[index..trailer].chunks(8) {
+            let offset = bundle_u64(entry);
+            if offset < BUNDLE_HEADER_SIZE as u64 || offset + 4 > index as u64 {
+                return Err(damaged());
+            }
+            let offset = offset as usize;
+            if offset + 4 + bundle_u32(&bytes[offset..]) as usize > index {
+                return Err(damaged());
+            }
+            offsets.push(offset);
+        }
+
+        bytes.truncate(index);
+        Ok(NP_Bundle { bytes, offsets })
+    }
+
+    /// Add the bytes of a closed buffer to the end of the bundle, returns it's position
+    pub fn push(&mut self, buffer_bytes: &[u8]) -> usize {
+        self.offsets.push(self.bytes.len());
+        self.bytes.extend_from_slice(&(buffer_bytes.len() as u32).to_be_bytes());
+        self.bytes.extend_from_slice(buffer_bytes);
+        self.offsets.len() - 1
+    }
+
+    /// Add the bytes of a buffer to the end of the bundle, returns it's position
+    pub fn push_buffer(&mut self, buffer: &NP_Buffer) -> usize {
+        self.push(buffer.read_bytes())
+    }
+
+    /// Get the bytes of the buffer at a position
+    pub fn get(&self, position: usize) -> Option<&[u8]> {
+        let offset = *self.offsets.get(position)?;
+        let len = bundle_u32(&self.bytes[offset..]) as usize;
+        Some(&self.bytes[(offset + 4)..(offset + 4 + len)])
+    }
+
+    /// Get the schema fingerprint of the buffer at a position, if it was made by a factory using `with_fingerprint`
+    pub fn fingerprint(&self, position: usize) -> Option<[u8; 8]> {
+        let bytes = self.get(position)?;
+        if bytes.len() < 9 || bytes[0] & NP_HEADER_FINGERPRINT == 0 {
+            return None;
+        }
+        let mut fingerprint = [0u8; 8];
+        fingerprint.copy_from_slice(&bytes[1..9]);
+        Some(fingerprint)
+    }
+
+    /// Open the buffer at a position as a read only buffer, without copying it.
+    ///
+    /// Fails if there's no buffer at the position, or if the buffer has a schema fingerprint that doesn't match the factory.
+    ///
+    pub fn open<'bundle>(&'bundle self, position: usize, factory: &'bundle NP_Factory) -> Result<NP_Buffer<'bundle>, NP_Error> {
+        let bytes = match self.get(position) {
+            Some(bytes) => bytes,
+            None => return Err(NP_Error::OutOfBounds("There's no buffer at this position in the bundle!".into()))
+        };
+        match self.fingerprint(position) {
+            Some(fingerprint) if fingerprint != factory.schema.fingerprint() => {
+                Err(NP_Error::SchemaMismatch("Buffer in the bundle doesn't use the schema of this factory!".into()))
+            },
+            _ => Ok(factory.open_buffer_read_only(bytes))
+        }
+    }
+
+    /// Iterate over the bytes of every buffer in order
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        (0..self.len()).filter_map(move |position| self.get(position))
+    }
+
+    /// How many buffers are in the bundle
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Is the bundle empty?
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Get the bundle bytes with the index, open them again with `from_bytes`
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        let index = self.bytes.len() as u64;
+        self.bytes.reserve(self.offsets.len() * 8 + BUNDLE_TRAILER_SIZE);
+        for offset in self.offsets.iter() {
+            self.bytes.extend_from_slice(&(*offset as u64).to_be_bytes());
+        }
+        self.bytes.extend_from_slice(&(self.offsets.len() as u32).to_be_bytes());
+        self.bytes.extend_from_slice(&index.to_be_bytes());
+        self.bytes
+    }
+}
+
+fn bundle_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn bundle_u64(bytes: &[u8]) -> u64 {
+    let mut word = [0u8; 8];
+    word.copy_from_slice(&bytes[0..8]);
+    u64::from_be_bytes(word)
+}
+
+#[test]
+fn bundles_work() -> Result<(), NP_Error> {
+    let factory = NP_Factory::new(r#"{"type": "string"}"#)?;
+    let stamped = NP_Factory::new(r#"{"type": "string"}"#)?.with_fingerprint();
+
+    let empty = NP_Bundle::new();
+    assert!(empty.is_empty());
+    assert!(NP_Bundle::from_bytes(empty.into_bytes())?.is_empty());
+
+    let mut bundle = NP_Bundle::default();
+    for (index, value) in ["one", "two", "three"].iter().enumerate() {
+        let mut buffer = if index == 1 { stamped.empty_buffer(None) } else { factory.empty_buffer(None) };
+        buffer.set(&[], *value)?;
+        assert_eq!(bundle.push_buffer(&buffer), index);
+    }
+    assert_eq!(bundle.fingerprint(0), None);
+    assert_eq!(bundle.fingerprint(1), Some(stamped.schema.fingerprint()));
+    assert!(bundle.get(3).is_none());
+    assert!(bundle.open(3, &factory).is_err());
+
+    let bytes = bundle.clone().into_bytes();
+    let opened = NP_Bundle::from_bytes(bytes.clone())?;
+    assert_eq!(opened, bundle);
+    for (position, value) in ["one", "two", "three"].iter().enumerate() {
+        assert_eq!(opened.open(position, &stamped)?.get::<&str>(&[])?, Some(*value));
+    }
+    assert_eq!(opened.iter().count(), 3);
+    assert!(opened.open(0, &factory)?.is_read_only());
+
+    // damaged bundles fail to open
+    assert!(NP_Bundle::from_bytes(bytes[1..].to_vec()).is_err());
+    assert!(NP_Bundle::from_bytes(bytes[..(bytes.len() - 1)].to_vec()).is_err());
+    let mut damaged = bytes.clone();
+    let index = damaged.len() - 12 - 8;
+    damaged[index] = 0xFF;
+    assert!(NP_Bundle::from_bytes(damaged).is_err());
+    let mut damaged = bytes.clone();
+    damaged[5] = 0xFF;
+    assert!(NP_Bundle::from_bytes(damaged).is_err());
+    let mut future = bytes;
+    future[4] = 2;
+    assert!(NP_Bundle::from_bytes(future).is_err());
+
+    Ok(())
+}
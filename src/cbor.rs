@@ -0,0 +1,572 @@
+//! CBOR import and export
+//!
+//! With the `cbor` feature enabled, buffers can be converted to and from [CBOR](https://www.rfc-editor.org/rfc/rfc8949.html) using the schema to pick the right types.
+//!
+//! Tables and maps become CBOR maps keyed by column name or map key, lists and tuples become arrays and empty values become `null`.  Only values that have been set in the buffer are exported, default values from the schema are left out.
+//!
+//! Types that CBOR doesn't have built in are written with their registered tags:
+//!
+//! | NoProto type | CBOR type                                                        |
+//! |--------------|------------------------------------------------------------------|
+//! | `uuid`       | tag 37 wrapping a 16 byte string                                 |
+//! | `date`       | tag 1 wrapping the epoch seconds, a float if there are milliseconds |
+//! | `decimal`    | tag 4 decimal fraction `[-exp, num]`                             |
+//! | `geo`        | tag 103 wrapping `[lat, lng]`                                    |
+//! | `ulid`       | 16 byte string                                                   |
+//! | `option`     | text string                                                      |
+//!
+//! When importing, untagged values are also accepted: uuids as text, dates as unsigned milliseconds, decimals as plain numbers and geo as a `lat`/`lng` map.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::dec::NP_Dec;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",  {"type": "string"}],
+//!         ["price", {"type": "dec", "exp": 2}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//! new_buffer.set(&["price"], NP_Dec::new(2050, 2))?;
+//!
+//! let bytes: Vec<u8> = new_buffer.cbor_encode()?;
+//! assert_eq!(bytes, vec![
+//!     0xa2,
+//!     0x64, b'n', b'a', b'm', b'e', 0x63, b'J', b'e', b'b',
+//!     0x65, b'p', b'r', b'i', b'c', b'e', 0xc4, 0x82, 0x21, 0x19, 0x08, 0x02
+//! ]);
+//!
+//! let copy_buffer = factory.from_cbor(&bytes)?;
+//! assert_eq!(copy_buffer.get::<NP_Dec>(&["price"])?, Some(NP_Dec::new(2050, 2)));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::owned::NP_Value_Owned;
+use crate::schema::NP_Parsed_Schema;
+use crate::pointer::dec::NP_Dec;
+use crate::pointer::geo::NP_Geo;
+use crate::pointer::date::NP_Date;
+use crate::pointer::uuid::NP_UUID;
+use crate::pointer::ulid::NP_ULID;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::boxed::Box;
+use core::convert::TryFrom;
+
+const TAG_EPOCH: u64 = 1;
+const TAG_DECIMAL: u64 = 4;
+const TAG_UUID: u64 = 37;
+const TAG_GEO: u64 = 103;
+
+/// A CBOR value before it's been matched to the schema
+enum CB_Value {
+    Null,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<CB_Value>),
+    Map(Vec<(CB_Value, CB_Value)>),
+    Tag(u64, Box<CB_Value>)
+}
+
+fn write_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if value <= u8::MAX as u64 {
+        out.push(major | 24);
+        out.push(value as u8);
+    } else if value <= u16::MAX as u64 {
+        out.push(major | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        out.push(major | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_int(out: &mut Vec<u8>, value: i64) {
+    if value >= 0 {
+        write_head(out, 0, value as u64);
+    } else {
+        write_head(out, 1, (-1 - value) as u64);
+    }
+}
+
+fn write_text(out: &mut Vec<u8>, value: &str) {
+    write_head(out, 3, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_bytes(out: &mut Vec<u8>, value: &[u8]) {
+    write_head(out, 2, value.len() as u64);
+    out.extend_from_slice(value);
+}
+
+fn write_f64(out: &mut Vec<u8>, value: f64) {
+    out.push(0xfb);
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn write_entries(out: &mut Vec<u8>, entries: &Vec<(String, NP_Value_Owned)>) {
+    write_head(out, 5, entries.len() as u64);
+    for (key, value) in entries {
+        write_text(out, key);
+        encode(value, out);
+    }
+}
+
+/// Write an owned value tree as CBOR
+pub(crate) fn encode(value: &NP_Value_Owned, out: &mut Vec<u8>) {
+    match value {
+        NP_Value_Owned::Null => out.push(0xf6),
+        NP_Value_Owned::String(x) => write_text(out, x),
+        NP_Value_Owned::Enum(x) => write_text(out, x),
+        NP_Value_Owned::Bytes(x) => write_bytes(out, x),
+        NP_Value_Owned::Int8(x) => write_int(out, *x as i64),
+        NP_Value_Owned::Int16(x) => write_int(out, *x as i64),
+        NP_Value_Owned::Int32(x) => write_int(out, *x as i64),
+        NP_Value_Owned::Int64(x) => write_int(out, *x),
+        NP_Value_Owned::Uint8(x) => write_head(out, 0, *x as u64),
+        NP_Value_Owned::Uint16(x) => write_head(out, 0, *x as u64),
+        NP_Value_Owned::Uint32(x) => write_head(out, 0, *x as u64),
+        NP_Value_Owned::Uint64(x) => write_head(out, 0, *x),
+        NP_Value_Owned::Float(x) => {
+            out.push(0xfa);
+            out.extend_from_slice(&x.to_be_bytes());
+        },
+        NP_Value_Owned::Double(x) => write_f64(out, *x),
+        NP_Value_Owned::Dec(x) => {
+            write_head(out, 6, TAG_DECIMAL);
+            write_head(out, 4, 2);
+            write_int(out, -(x.exp as i64));
+            write_int(out, x.num);
+        },
+        NP_Value_Owned::Bool(x) => out.push(if *x { 0xf5 } else { 0xf4 }),
+        NP_Value_Owned::Geo(x) => {
+            write_head(out, 6, TAG_GEO);
+            write_head(out, 4, 2);
+            write_f64(out, x.lat);
+            write_f64(out, x.lng);
+        },
+        NP_Value_Owned::Uuid(x) => {
+            write_head(out, 6, TAG_UUID);
+            write_bytes(out, &x.value);
+        },
+        NP_Value_Owned::Ulid(x) => write_bytes(out, x.as_bytes()),
+        NP_Value_Owned::Date(x) => {
+            write_head(out, 6, TAG_EPOCH);
+            if x.value % 1000 == 0 {
+                write_head(out, 0, x.value / 1000);
+            } else {
+                write_f64(out, x.value as f64 / 1000f64);
+            }
+        },
+        NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) => write_entries(out, entries),
+        NP_Value_Owned::List(items) | NP_Value_Owned::Tuple(items) => {
+            write_head(out, 4, items.len() as u64);
+            for item in items {
+                encode(item, out);
+            }
+        }
+    }
+}
+
+/// Convert the bits of a half precision float
+fn f16_to_f64(bits: u16) -> f64 {
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let fraction = (bits & 0x3ff) as f64;
+    let magnitude = match exponent {
+        0 => fraction * (1f64 / (1u64 << 24) as f64),
+        31 => if fraction == 0f64 { f64::INFINITY } else { f64::NAN },
+        _ => {
+            let scale = if exponent >= 25 { (1u64 << (exponent - 25)) as f64 } else { 1f64 / (1u64 << (25 - exponent)) as f64 };
+            (fraction + 1024f64) * scale
+        }
+    };
+    if bits & 0x8000 != 0 { -magnitude } else { magnitude }
+}
+
+struct CB_Reader<'bytes> {
+    bytes: &'bytes [u8],
+    index: usize
+}
+
+impl<'bytes> CB_Reader<'bytes> {
+
+    fn take(&mut self, len: usize) -> Result<&'bytes [u8], NP_Error> {
+        if self.index + len > self.bytes.len() {
+            return Err(NP_Error::Parse("CBOR data ended unexpectedly!".into()));
+        }
+        let slice = &self.bytes[self.index..(self.index + len)];
+        self.index += len;
+        Ok(slice)
+    }
+
+    fn peek_break(&mut self) -> Result<bool, NP_Error> {
+        if self.index >= self.bytes.len() {
+            return Err(NP_Error::Parse("CBOR data ended unexpectedly!".into()));
+        }
+        if self.bytes[self.index] == 0xff {
+            self.index += 1;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Read the argument of a head, `None` means indefinite length
+    fn read_argument(&mut self, info: u8) -> Result<Option<u64>, NP_Error> {
+        Ok(Some(match info {
+            0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => { let mut b = [0u8; 2]; b.copy_from_slice(self.take(2)?); u16::from_be_bytes(b) as u64 },
+            26 => { let mut b = [0u8; 4]; b.copy_from_slice(self.take(4)?); u32::from_be_bytes(b) as u64 },
+            27 => { let mut b = [0u8; 8]; b.copy_from_slice(self.take(8)?); u64::from_be_bytes(b) },
+            31 => return Ok(None),
+            _ => return Err(NP_Error::Parse("Invalid CBOR data!".into()))
+        }))
+    }
+
+    fn read_length(&mut self, info: u8) -> Result<Option<usize>, NP_Error> {
+        match self.read_argument(info)? {
+            Some(len) => usize::try_from(len).map(Some).map_err(|_| NP_Error::Parse("CBOR length is too large!".into())),
+            None => Ok(None)
+        }
+    }
+
+    /// Read a byte or text string, joining the chunks of indefinite length strings
+    fn read_string(&mut self, major: u8, info: u8) -> Result<Vec<u8>, NP_Error> {
+        match self.read_length(info)? {
+            Some(len) => Ok(self.take(len)?.to_vec()),
+            None => {
+                let mut bytes = Vec::new();
+                while !self.peek_break()? {
+                    let head = self.take(1)?[0];
+                    if head >> 5 != major || head & 0x1f == 31 {
+                        return Err(NP_Error::Parse("Invalid CBOR string chunk!".into()));
+                    }
+                    bytes.extend(self.read_string(major, head & 0x1f)?);
+                }
+                Ok(bytes)
+            }
+        }
+    }
+
+    fn read_value(&mut self) -> Result<CB_Value, NP_Error> {
+        let head = self.take(1)?[0];
+        let major = head >> 5;
+        let info = head & 0x1f;
+        match major {
+            0 => Ok(CB_Value::Int(self.read_argument(info)?.ok_or_else(|| NP_Error::Parse("Invalid CBOR data!".into()))? as i128)),
+            1 => Ok(CB_Value::Int(-1 - self.read_argument(info)?.ok_or_else(|| NP_Error::Parse("Invalid CBOR data!".into()))? as i128)),
+            2 => Ok(CB_Value::Bytes(self.read_string(2, info)?)),
+            3 => Ok(CB_Value::Text(String::from_utf8(self.read_string(3, info)?)?)),
+            4 => {
+                let mut items = Vec::new();
+                match self.read_length(info)? {
+                    Some(len) => for _x in 0..len { items.push(self.read_value()?); },
+                    None => while !self.peek_break()? { items.push(self.read_value()?); }
+                }
+                Ok(CB_Value::Array(items))
+            },
+            5 => {
+                let mut entries = Vec::new();
+                match self.read_length(info)? {
+                    Some(len) => for _x in 0..len {
+                        let key = self.read_value()?;
+                        entries.push((key, self.read_value()?));
+                    },
+                    None => while !self.peek_break()? {
+                        let key = self.read_value()?;
+                        entries.push((key, self.read_value()?));
+                    }
+                }
+                Ok(CB_Value::Map(entries))
+            },
+            6 => {
+                let tag = self.read_argument(info)?.ok_or_else(|| NP_Error::Parse("Invalid CBOR data!".into()))?;
+                Ok(CB_Value::Tag(tag, Box::new(self.read_value()?)))
+            },
+            _ => match info {
+                20 => Ok(CB_Value::Bool(false)),
+                21 => Ok(CB_Value::Bool(true)),
+                22 | 23 => Ok(CB_Value::Null),
+                25 => { let mut b = [0u8; 2]; b.copy_from_slice(self.take(2)?); Ok(CB_Value::Float(f16_to_f64(u16::from_be_bytes(b)))) },
+                26 => { let mut b = [0u8; 4]; b.copy_from_slice(self.take(4)?); Ok(CB_Value::Float(f32::from_be_bytes(b) as f64)) },
+                27 => { let mut b = [0u8; 8]; b.copy_from_slice(self.take(8)?); Ok(CB_Value::Float(f64::from_be_bytes(b))) },
+                _ => Err(NP_Error::Parse("Unsupported CBOR simple value!".into()))
+            }
+        }
+    }
+}
+
+fn type_error(schema: &NP_Parsed_Schema) -> NP_Error {
+    let mut err = String::from("TypeError: CBOR value doesn't match schema of type (");
+    err.push_str(schema.get_type_data().0);
+    err.push_str(")");
+    NP_Error::SchemaMismatch(err)
+}
+
+fn number(value: &CB_Value) -> Option<f64> {
+    match value {
+        CB_Value::Int(x) => Some(*x as f64),
+        CB_Value::Float(x) => Some(*x),
+        _ => None
+    }
+}
+
+fn geo_coords(value: &CB_Value) -> Option<(f64, f64)> {
+    match value {
+        CB_Value::Tag(TAG_GEO, inner) => geo_coords(inner),
+        CB_Value::Array(items) if items.len() >= 2 => Some((number(&items[0])?, number(&items[1])?)),
+        CB_Value::Map(entries) => {
+            let (mut lat, mut lng) = (None, None);
+            for (key, value) in entries {
+                match key {
+                    CB_Value::Text(k) if k == "lat" => lat = number(value),
+                    CB_Value::Text(k) if k == "lng" => lng = number(value),
+                    _ => { }
+                }
+            }
+            Some((lat?, lng?))
+        },
+        _ => None
+    }
+}
+
+fn date_ms(value: &CB_Value) -> Option<u64> {
+    match value {
+        CB_Value::Tag(TAG_EPOCH, inner) => match **inner {
+            CB_Value::Int(x) => u64::try_from(x).ok()?.checked_mul(1000),
+            CB_Value::Float(x) if x >= 0f64 && x < (u64::MAX / 1000) as f64 => Some((x * 1000f64 + 0.5f64) as u64),
+            _ => None
+        },
+        CB_Value::Int(x) => u64::try_from(*x).ok(),
+        _ => None
+    }
+}
+
+fn decimal(value: &CB_Value, exp: u8) -> Option<NP_Dec> {
+    let mut dec: NP_Dec = match value {
+        CB_Value::Tag(TAG_DECIMAL, inner) => match &**inner {
+            CB_Value::Array(items) if items.len() == 2 => match (&items[0], &items[1]) {
+                (CB_Value::Int(e), CB_Value::Int(m)) => {
+                    let mut num = i64::try_from(*m).ok()?;
+                    let mut e = *e;
+                    // positive exponents are folded into the number
+                    while e > 0 {
+                        num = num.checked_mul(10)?;
+                        e -= 1;
+                    }
+                    NP_Dec::new(num, u8::try_from(-e).ok()?)
+                },
+                _ => return None
+            },
+            _ => return None
+        },
+        CB_Value::Int(x) => NP_Dec::new(i64::try_from(*x).ok()?, 0),
+        CB_Value::Float(x) => (*x).into(),
+        _ => return None
+    };
+    dec.shift_exp(exp);
+    Some(dec)
+}
+
+/// Convert a CBOR value into an owned value tree using the schema at `address`
+fn to_owned(value: CB_Value, schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<NP_Value_Owned, NP_Error> {
+
+    if let CB_Value::Null = value {
+        return Ok(NP_Value_Owned::Null);
+    }
+
+    let this_schema = &schema[address];
+
+    macro_rules! int {
+        ($t: ty, $variant: ident) => {
+            match value {
+                CB_Value::Int(x) => <$t>::try_from(x).ok(),
+                _ => None
+            }.map(NP_Value_Owned::$variant).ok_or_else(|| type_error(this_schema))
+        }
+    }
+
+    let text_key = |key: CB_Value| match key {
+        CB_Value::Text(x) => Ok(x),
+        _ => Err(NP_Error::Parse("CBOR map keys must be text strings!".into()))
+    };
+
+    match this_schema {
+        NP_Parsed_Schema::UTF8String { .. } => match value {
+            CB_Value::Text(x) => Ok(NP_Value_Owned::String(x)),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Bytes { .. } => match value {
+            CB_Value::Bytes(x) => Ok(NP_Value_Owned::Bytes(x)),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Int8 { .. } => int!(i8, Int8),
+        NP_Parsed_Schema::Int16 { .. } => int!(i16, Int16),
+        NP_Parsed_Schema::Int32 { .. } => int!(i32, Int32),
+        NP_Parsed_Schema::Int64 { .. } => int!(i64, Int64),
+        NP_Parsed_Schema::Uint8 { .. } => int!(u8, Uint8),
+        NP_Parsed_Schema::Uint16 { .. } => int!(u16, Uint16),
+        NP_Parsed_Schema::Uint32 { .. } => int!(u32, Uint32),
+        NP_Parsed_Schema::Uint64 { .. } => int!(u64, Uint64),
+        NP_Parsed_Schema::Float { .. } => number(&value).map(|x| NP_Value_Owned::Float(x as f32)).ok_or_else(|| type_error(this_schema)),
+        NP_Parsed_Schema::Double { .. } => number(&value).map(NP_Value_Owned::Double).ok_or_else(|| type_error(this_schema)),
+        NP_Parsed_Schema::Decimal { exp, .. } => decimal(&value, *exp).map(NP_Value_Owned::Dec).ok_or_else(|| type_error(this_schema)),
+        NP_Parsed_Schema::Boolean { .. } => match value {
+            CB_Value::Bool(x) => Ok(NP_Value_Owned::Bool(x)),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Geo { size, .. } => match geo_coords(&value) {
+            Some((lat, lng)) => Ok(NP_Value_Owned::Geo(NP_Geo::new(*size, lat, lng))),
+            None => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Uuid { .. } => {
+            let value = match value {
+                CB_Value::Tag(TAG_UUID, inner) => *inner,
+                x => x
+            };
+            match value {
+                CB_Value::Bytes(x) if x.len() == 16 => {
+                    let mut uuid = NP_UUID { value: [0; 16] };
+                    uuid.value.copy_from_slice(&x);
+                    Ok(NP_Value_Owned::Uuid(uuid))
+                },
+                CB_Value::Text(x) => Ok(NP_Value_Owned::Uuid(NP_UUID::from_string(&x)?)),
+                _ => Err(type_error(this_schema))
+            }
+        },
+        NP_Parsed_Schema::Ulid { .. } => match value {
+            CB_Value::Bytes(x) if x.len() == 16 => {
+                let mut bytes = [0u8; 16];
+                bytes.copy_from_slice(&x);
+                Ok(NP_Value_Owned::Ulid(NP_ULID::from_bytes(bytes)))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Date { .. } => date_ms(&value).map(|ms| NP_Value_Owned::Date(NP_Date::new(ms))).ok_or_else(|| type_error(this_schema)),
+        NP_Parsed_Schema::Enum { .. } => match value {
+            CB_Value::Text(x) => Ok(NP_Value_Owned::Enum(x)),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Table { columns, .. } => match value {
+            CB_Value::Map(entries) => {
+                let mut values = Vec::new();
+                for (key, value) in entries {
+                    let key = text_key(key)?;
+                    let column = match columns.iter().find(|(_index, name, _addr)| *name == key) {
+                        Some((_index, _name, addr)) => *addr,
+                        None => {
+                            let mut err = String::from("CBOR key not found in table schema: ");
+                            err.push_str(&key);
+                            return Err(NP_Error::Parse(err));
+                        }
+                    };
+                    values.push((key, to_owned(value, schema, column)?));
+                }
+                Ok(NP_Value_Owned::Table(values))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Map { value: of, .. } => match value {
+            CB_Value::Map(entries) => {
+                let mut values = Vec::new();
+                for (key, value) in entries {
+                    values.push((text_key(key)?, to_owned(value, schema, *of)?));
+                }
+                Ok(NP_Value_Owned::Map(values))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::List { of, .. } => match value {
+            CB_Value::Array(items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(to_owned(item, schema, *of)?);
+                }
+                Ok(NP_Value_Owned::List(values))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Tuple { values: addrs, .. } => match value {
+            CB_Value::Array(items) if items.len() <= addrs.len() => {
+                let mut values = Vec::new();
+                for (item, addr) in items.into_iter().zip(addrs.iter()) {
+                    values.push(to_owned(item, schema, *addr)?);
+                }
+                Ok(NP_Value_Owned::Tuple(values))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::None => Err(type_error(this_schema))
+    }
+}
+
+/// Read CBOR bytes into an owned value tree using the schema at `address`
+pub(crate) fn decode(bytes: &[u8], schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<NP_Value_Owned, NP_Error> {
+    let mut reader = CB_Reader { bytes, index: 0 };
+    let value = reader.read_value()?;
+    if reader.index != bytes.len() {
+        return Err(NP_Error::Parse("Extra bytes found after CBOR value!".into()));
+    }
+    to_owned(value, schema, address)
+}
+
+#[test]
+fn cbor_round_trip_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id",     {"type": "uuid"}],
+            ["when",   {"type": "date"}],
+            ["where",  {"type": "geo8"}],
+            ["price",  {"type": "dec", "exp": 2}],
+            ["small",  {"type": "i64"}],
+            ["big",    {"type": "u64"}],
+            ["pair",   {"type": "tuple", "values": [{"type": "string"}, {"type": "bool"}]}],
+            ["scores", {"type": "list", "of": {"type": "float"}}],
+            ["meta",   {"type": "map", "value": {"type": "bytes"}}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["id"], &NP_UUID::generate(22))?;
+    buffer.set(&["when"], NP_Date::new(1605138980392))?;
+    buffer.set(&["where"], NP_Geo::new(8, 45.5, -120.25))?;
+    buffer.set(&["price"], NP_Dec::new(-2050, 2))?;
+    buffer.set(&["small"], i64::MIN)?;
+    buffer.set(&["big"], u64::MAX)?;
+    buffer.set(&["pair", "1"], true)?;
+    buffer.set(&["scores", "2"], 2.5f32)?;
+    buffer.set(&["meta", "key"], &[1u8, 2, 3] as &[u8])?;
+
+    let bytes = buffer.cbor_encode()?;
+    let copy = factory.from_cbor(&bytes)?;
+    assert_eq!(copy.to_owned_tree()?, buffer.to_owned_tree()?);
+
+    // indefinite lengths, half floats and untagged values are understood
+    let copy = factory.from_cbor(&[0xbf, 0x66, b's', b'c', b'o', b'r', b'e', b's', 0x9f, 0xf9, 0x3e, 0x00, 0xff, 0x64, b'w', b'h', b'e', b'n', 0x19, 0x03, 0xe8, 0xff])?;
+    assert_eq!(copy.get::<f32>(&["scores", "0"])?, Some(1.5));
+    assert_eq!(copy.get::<NP_Date>(&["when"])?, Some(NP_Date::new(1000)));
+
+    // values that don't fit the schema are rejected
+    assert!(factory.from_cbor(&[0xa1, 0x64, b'p', b'a', b'i', b'r', 0x83, 0xf6, 0xf6, 0xf6]).is_err());
+    assert!(factory.from_cbor(&[0xa1, 0x63, b'b', b'i', b'g', 0x20]).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,315 @@
+//! Generate typed Rust wrappers from a schema
+//!
+//! Paths like `&["users", "3", "name"]` are only checked when the code runs, a typo or a schema change turns into a runtime error.  `generate_rust` writes Rust source with a wrapper struct around `NP_Buffer` that has a `get_*` and `set_*` method for every scalar in the schema, so field access is checked by the compiler instead.  This module requires the `std` feature.
+//!
+//! The generated source has two structs:
+//!
+//! - `RootPaths` holds an `NP_Path` for every scalar that isn't inside a list or map, compile it once per factory with `RootPaths::new`.
+//! - `Root` wraps a buffer and the paths.  Getters read with `get_with` and the compiled paths, setters write to the same paths as string slices.  Values inside lists and maps take the list index or map key of each level as arguments.
+//!
+//! Method names are the path steps joined with `_`.  The struct names are always the same, so `include!` the generated file into it's own module for each schema, usually from a build script.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::codegen::generate_rust;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",  {"type": "string"}],
+//!         ["users", {"type": "list", "of": {"type": "table", "columns": [
+//!             ["age", {"type": "u8"}]
+//!         ]}}]
+//!     ]
+//! }"#)?;
+//!
+//! let source = generate_rust(&factory.schema);
+//! assert!(source.contains("pub fn get_name(&self) -> Result<Option<&str>, NP_Error> {"));
+//! assert!(source.contains("pub fn set_users_age(&mut self, users_index: usize, value: u8) -> Result<bool, NP_Error> {"));
+//!
+//! // std::fs::write(out_dir.join("user.rs"), source)?;
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+//! The generated code is then used like this:
+//!
+//! ```text
+//! mod user { include!(concat!(env!("OUT_DIR"), "/user.rs")); }
+//!
+//! let paths = user::RootPaths::new(&factory)?;
+//! let mut buffer = user::Root::new(factory.empty_buffer(None), &paths);
+//! buffer.set_name("Jeb")?;
+//! buffer.set_users_age(2, 30)?;
+//! assert_eq!(buffer.get_name()?, Some("Jeb"));
+//! ```
+//!
+
+use crate::schema::{NP_Schema, NP_Schema_Node, NP_TypeKeys};
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::borrow::ToOwned;
+use core::fmt::Write;
+
+/// One step of a generated accessor path
+enum Code_Step {
+    /// Fixed column name or tuple index
+    Fixed(String),
+    /// List index argument
+    Index(String),
+    /// Map key argument
+    Key(String)
+}
+
+/// A scalar in the schema and how to reach it
+struct Code_Field {
+    name: String,
+    steps: Vec<Code_Step>,
+    rust_type: &'static str
+}
+
+/// Rust type used to get and set a scalar, `None` for collections
+fn rust_type(type_key: NP_TypeKeys) -> Option<&'static str> {
+    Some(match type_key {
+        NP_TypeKeys::UTF8String => "&str",
+        NP_TypeKeys::Bytes      => "&[u8]",
+        NP_TypeKeys::Int8       => "i8",
+        NP_TypeKeys::Int16      => "i16",
+        NP_TypeKeys::Int32      => "i32",
+        NP_TypeKeys::Int64      => "i64",
+        NP_TypeKeys::Uint8      => "u8",
+        NP_TypeKeys::Uint16     => "u16",
+        NP_TypeKeys::Uint32     => "u32",
+        NP_TypeKeys::Uint64     => "u64",
+        NP_TypeKeys::Float      => "f32",
+        NP_TypeKeys::Double     => "f64",
+        NP_TypeKeys::Boolean    => "bool",
+        NP_TypeKeys::Decimal    => "NP_Dec",
+        NP_TypeKeys::Geo        => "NP_Geo",
+        NP_TypeKeys::Date       => "NP_Date",
+        NP_TypeKeys::Uuid       => "&NP_UUID",
+        NP_TypeKeys::Ulid       => "&NP_ULID",
+        NP_TypeKeys::Enum       => "NP_Enum",
+        NP_TypeKeys::Any        => "NP_AnyValue",
+        _ => return None
+    })
+}
+
+/// Turn a column name into part of a snake case identifier
+fn identifier(step: &str) -> String {
+    let mut name = String::with_capacity(step.len());
+    for c in step.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_matches('_');
+    if name.is_empty() { "field".to_owned() } else { name.to_owned() }
+}
+
+/// Add a number to the end of a name until it isn't in `used`
+fn unique(name: String, used: &mut Vec<String>) -> String {
+    let mut result = name.clone();
+    let mut count = 2;
+    while used.contains(&result) {
+        result = alloc::format!("{}_{}", name, count);
+        count += 1;
+    }
+    used.push(result.clone());
+    result
+}
+
+/// Find every scalar in the schema
+fn fields(schema: &NP_Schema) -> Vec<Code_Field> {
+    let mut fields: Vec<Code_Field> = Vec::new();
+    let mut used: Vec<String> = Vec::new();
+
+    for entry in schema.paths() {
+        let rust_type = match rust_type(entry.node.type_key()) {
+            Some(x) => x,
+            None => continue
+        };
+
+        let mut node: NP_Schema_Node = schema.root();
+        let mut steps: Vec<Code_Step> = Vec::new();
+        let mut words: Vec<String> = Vec::new();
+        let mut arguments: Vec<String> = Vec::new();
+
+        for step in entry.path.iter() {
+            if step == "*" {
+                let last = words.last().cloned();
+                let (suffix, is_list) = if node.type_key() == NP_TypeKeys::List { ("index", true) } else { ("key", false) };
+                let argument = match last {
+                    // identifiers can't start with a number
+                    Some(word) if word.starts_with(|c: char| c.is_ascii_digit()) => alloc::format!("_{}_{}", word, suffix),
+                    Some(word) => alloc::format!("{}_{}", word, suffix),
+                    None => suffix.to_owned()
+                };
+                let argument = unique(argument, &mut arguments);
+                steps.push(if is_list { Code_Step::Index(argument) } else { Code_Step::Key(argument) });
+                node = node.children()[0].node;
+            } else {
+                words.push(identifier(step));
+                steps.push(Code_Step::Fixed(step.clone()));
+                node = match node.child(step) {
+                    Some(x) => x,
+                    None => break
+                };
+            }
+        }
+
+        let name = unique(words.join("_"), &mut used);
+        fields.push(Code_Field { name, steps, rust_type });
+    }
+
+    fields
+}
+
+impl Code_Field {
+
+    /// Is this field at a fixed path that can be compiled?
+    fn is_fixed(&self) -> bool {
+        self.steps.iter().all(|step| matches!(step, Code_Step::Fixed(_)))
+    }
+
+    /// Method name with `prefix`, like `get_name`
+    fn method(&self, prefix: &str) -> String {
+        if self.name.is_empty() { prefix.to_owned() } else { alloc::format!("{}_{}", prefix, self.name) }
+    }
+
+    /// Arguments for list indexes and map keys, each with a leading comma
+    fn arguments(&self) -> String {
+        let mut arguments = String::new();
+        for step in self.steps.iter() {
+            match step {
+                Code_Step::Index(x) => { let _ = write!(arguments, ", {}: usize", x); },
+                Code_Step::Key(x) => { let _ = write!(arguments, ", {}: &str", x); },
+                Code_Step::Fixed(_) => { }
+            }
+        }
+        arguments
+    }
+
+    /// Lines that turn list indexes into strings
+    fn index_strings(&self, code: &mut String) {
+        for step in self.steps.iter() {
+            if let Code_Step::Index(x) = step {
+                let _ = writeln!(code, "        let {} = {}.to_string();", x, x);
+            }
+        }
+    }
+
+    /// The path as a slice of string slices
+    fn path(&self) -> String {
+        let steps: Vec<String> = self.steps.iter().map(|step| match step {
+            Code_Step::Fixed(x) => alloc::format!("{:?}", x),
+            Code_Step::Index(x) => alloc::format!("&{}", x),
+            Code_Step::Key(x) => x.clone()
+        }).collect();
+        alloc::format!("&[{}]", steps.join(", "))
+    }
+}
+
+/// Generate Rust source for typed wrappers around buffers of a schema
+///
+/// Check out documentation [here](../codegen/index.html).
+///
+pub fn generate_rust(schema: &NP_Schema) -> String {
+
+    let fields = fields(schema);
+    let mut code = String::new();
+
+    code.push_str("// Generated by no_proto::codegen from a schema, changes will be lost when it's generated again.\n\n");
+    code.push_str("use no_proto::NP_Factory;\nuse no_proto::buffer::NP_Buffer;\nuse no_proto::error::NP_Error;\nuse no_proto::path::NP_Path;\n");
+    for (rust_type, import) in [
+        ("NP_Dec", "no_proto::pointer::dec::NP_Dec"),
+        ("NP_Geo", "no_proto::pointer::geo::NP_Geo"),
+        ("NP_Date", "no_proto::pointer::date::NP_Date"),
+        ("&NP_UUID", "no_proto::pointer::uuid::NP_UUID"),
+        ("&NP_ULID", "no_proto::pointer::ulid::NP_ULID"),
+        ("NP_Enum", "no_proto::pointer::option::NP_Enum"),
+        ("NP_AnyValue", "no_proto::pointer::any::NP_AnyValue")
+    ].iter() {
+        if fields.iter().any(|field| field.rust_type == *rust_type) {
+            let _ = writeln!(code, "use {};", import);
+        }
+    }
+
+    code.push_str("\n/// Paths of every value outside of lists and maps, compiled once for a factory\n");
+    code.push_str("#[allow(dead_code)]\n#[derive(Debug, Clone)]\npub struct RootPaths {\n");
+    for field in fields.iter().filter(|x| x.is_fixed()) {
+        let _ = writeln!(code, "    {}: NP_Path,", field.method("path"));
+    }
+    code.push_str("}\n\n#[allow(dead_code)]\nimpl RootPaths {\n");
+    code.push_str("    /// Compile the paths for a factory made from the same schema\n");
+    code.push_str("    pub fn new(factory: &NP_Factory) -> Result<Self, NP_Error> {\n        Ok(RootPaths {\n");
+    for field in fields.iter().filter(|x| x.is_fixed()) {
+        let _ = writeln!(code, "            {}: factory.compile_path({})?,", field.method("path"), field.path());
+    }
+    code.push_str("        })\n    }\n}\n\n");
+
+    code.push_str("/// Typed access to a buffer\n#[allow(dead_code)]\npub struct Root<'buffer, 'paths> {\n    buffer: NP_Buffer<'buffer>,\n    paths: &'paths RootPaths\n}\n\n");
+    code.push_str("#[allow(dead_code)]\nimpl<'buffer, 'paths> Root<'buffer, 'paths> {\n\n");
+    code.push_str("    /// Wrap a buffer, the paths have to be compiled for the factory of the buffer\n");
+    code.push_str("    pub fn new(buffer: NP_Buffer<'buffer>, paths: &'paths RootPaths) -> Self {\n        Root { buffer, paths }\n    }\n\n");
+    code.push_str("    /// The wrapped buffer\n    pub fn buffer(&self) -> &NP_Buffer<'buffer> {\n        &self.buffer\n    }\n\n");
+    code.push_str("    /// Get the wrapped buffer back\n    pub fn into_buffer(self) -> NP_Buffer<'buffer> {\n        self.buffer\n    }\n");
+
+    for field in fields.iter() {
+        let arguments = field.arguments();
+
+        let _ = writeln!(code, "\n    pub fn {}(&self{}) -> Result<Option<{}>, NP_Error> {{", field.method("get"), arguments, field.rust_type);
+        if field.is_fixed() {
+            let _ = writeln!(code, "        self.buffer.get_with::<{}>(&self.paths.{})", field.rust_type, field.method("path"));
+        } else {
+            field.index_strings(&mut code);
+            let _ = writeln!(code, "        self.buffer.get::<{}>({})", field.rust_type, field.path());
+        }
+        code.push_str("    }\n");
+
+        let _ = writeln!(code, "\n    pub fn {}(&mut self{}, value: {}) -> Result<bool, NP_Error> {{", field.method("set"), arguments, field.rust_type);
+        field.index_strings(&mut code);
+        let _ = writeln!(code, "        self.buffer.set({}, value)", field.path());
+        code.push_str("    }\n");
+    }
+
+    code.push_str("}\n");
+    code
+}
+
+#[test]
+fn generate_rust_works() -> Result<(), crate::error::NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["Full Name", {"type": "string"}],
+        ["full_name", {"type": "uuid"}],
+        ["point",     {"type": "tuple", "values": [{"type": "i8"}, {"type": "dec", "exp": 2}]}],
+        ["scores",    {"type": "map", "value": {"type": "list", "of": {"type": "float"}}}]
+    ]}"#)?;
+    let source = generate_rust(&factory.schema);
+
+    // names that would clash get a number
+    assert!(source.contains("    pub fn get_full_name(&self) -> Result<Option<&str>, NP_Error> {\n        self.buffer.get_with::<&str>(&self.paths.path_full_name)\n"));
+    assert!(source.contains("    pub fn set_full_name_2(&mut self, value: &NP_UUID) -> Result<bool, NP_Error> {\n        self.buffer.set(&[\"full_name\"], value)\n"));
+    assert!(source.contains("            path_full_name: factory.compile_path(&[\"Full Name\"])?,\n"));
+
+    // only the types that are used get imported
+    assert!(source.contains("use no_proto::pointer::dec::NP_Dec;\nuse no_proto::pointer::uuid::NP_UUID;\n\n"));
+    assert!(source.contains("pub fn get_point_1(&self) -> Result<Option<NP_Dec>, NP_Error> {"));
+
+    // values in maps and lists take their keys and indexes
+    assert!(source.contains(concat!(
+        "    pub fn get_scores(&self, scores_key: &str, scores_index: usize) -> Result<Option<f32>, NP_Error> {\n",
+        "        let scores_index = scores_index.to_string();\n",
+        "        self.buffer.get::<f32>(&[\"scores\", scores_key, &scores_index])\n"
+    )));
+    assert!(!source.contains("path_scores"));
+
+    // scalar roots get plain get and set methods
+    let source = generate_rust(&crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "list", "of": {"type": "bool"}}}"#)?.schema);
+    assert!(source.contains("pub fn set(&mut self, index: usize, index_2: usize, value: bool) -> Result<bool, NP_Error> {"));
+    assert!(source.contains("pub struct RootPaths {\n}"));
+
+    Ok(())
+}
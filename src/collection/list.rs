@@ -1,11 +1,9 @@
-use crate::utils::opt_err;
-use crate::{pointer::{NP_List_Bytes}};
+use crate::pointer::{NP_List_Ref, list_bytes};
 use crate::{error::NP_Error, json_flex::{JSMAP, NP_JSON}, memory::{NP_Memory}, pointer::{NP_Value}, pointer::{NP_Cursor}, schema::NP_Parsed_Schema, schema::{NP_Schema, NP_TypeKeys}};
 
 use alloc::borrow::ToOwned;
 use alloc::boxed::Box;
 use alloc::{vec::*};
-use core::{hint::unreachable_unchecked};
 use alloc::string::ToString;
 
 #[doc(hidden)]
@@ -54,16 +52,18 @@ impl NP_List {
             }
         }
 
-        let list_data = Self::get_list(list_value.get_addr_value() as usize, memory);
+        let list_data = Self::get_list(list_cursor.get_value(memory).get_addr_value() as usize, memory);
 
         // empty list
         if list_data.get_head() == 0 {
-            let new_cursor_addr = memory.malloc_borrow(&[0u8; 5])?; // malloc list item
+            if !make_path { return Ok(Some((index, None))) }
+            let new_cursor_addr = memory.malloc_pointers(2, 1)?; // malloc list item
+            let list_data = Self::get_list(list_cursor.get_value(memory).get_addr_value() as usize, memory);
             let new_cursor = NP_Cursor::new(new_cursor_addr, schema_of, list_cursor.schema_addr);
             let new_cursor_value = new_cursor.get_value(memory);
             new_cursor_value.set_index(index as u8);
-            list_data.set_head(new_cursor_addr as u16);
-            list_data.set_tail(new_cursor_addr as u16);
+            list_data.set_head(new_cursor_addr as u32);
+            list_data.set_tail(new_cursor_addr as u32);
             return Ok(Some((index, Some(new_cursor))))
         }
 
@@ -73,12 +73,14 @@ impl NP_List {
         let head_index = head.get_value(memory).get_index() as usize;
 
         if head_index > index { // index is in front of head
-            let new_cursor_addr = memory.malloc_borrow(&[0u8; 5])?; // malloc list item
+            if !make_path { return Ok(Some((index, None))) }
+            let new_cursor_addr = memory.malloc_pointers(2, 1)?; // malloc list item
+            let list_data = Self::get_list(list_cursor.get_value(memory).get_addr_value() as usize, memory);
             let new_cursor = NP_Cursor::new(new_cursor_addr, schema_of, list_cursor.schema_addr);
             let new_cursor_value = new_cursor.get_value(memory);
             new_cursor_value.set_index(index as u8);
-            new_cursor_value.set_next_addr(head.buff_addr as u16);
-            list_data.set_head(new_cursor_addr as u16);
+            new_cursor_value.set_next_addr(head.buff_addr as u32);
+            list_data.set_head(new_cursor_addr as u32);
             return Ok(Some((index, Some(new_cursor))))
         } else if head_index == index { // index is equal to head
             return Ok(Some((index, Some(head))))
@@ -91,12 +93,15 @@ impl NP_List {
         let tail_index = tail_value.get_index() as usize;
 
         if tail_index < index { // index is in front of head
-            let new_cursor_addr = memory.malloc_borrow(&[0u8; 5])?; // malloc list item
+            if !make_path { return Ok(Some((index, None))) }
+            let new_cursor_addr = memory.malloc_pointers(2, 1)?; // malloc list item
+            let list_data = Self::get_list(list_cursor.get_value(memory).get_addr_value() as usize, memory);
+            let tail_value = tail.get_value(memory);
             let new_cursor = NP_Cursor::new(new_cursor_addr, schema_of, list_cursor.schema_addr);
             let new_cursor_value = new_cursor.get_value(memory);
             new_cursor_value.set_index(index as u8);
-            tail_value.set_next_addr(new_cursor_addr as u16);
-            list_data.set_tail(new_cursor_addr as u16);
+            tail_value.set_next_addr(new_cursor_addr as u32);
+            list_data.set_tail(new_cursor_addr as u32);
             return Ok(Some((index, Some(new_cursor))))
         } else if tail_index == index { // index is equal to head
             return Ok(Some((index, Some(tail))))
@@ -110,8 +115,10 @@ impl NP_List {
             if index == idx {
                 if let Some(found_cursor) = item { // found cursor here
                     return Ok(Some((index, Some(found_cursor))))
-                } else { // found index but no cursor
-                    return Ok(Some((index, Some(list_iter.make_item_in_loop(memory)?))))
+                } else if make_path { // found index but no cursor
+                    return Ok(Some((index, Some(list_iter.make_item_in_loop(index, memory)?))))
+                } else {
+                    return Ok(Some((index, None)))
                 }
             }
         }
@@ -122,15 +129,15 @@ impl NP_List {
     }
 
     #[inline(always)]
-    pub fn make_item_in_loop(self, memory: &NP_Memory) -> Result<NP_Cursor, NP_Error> {
+    pub fn make_item_in_loop(self, index: usize, memory: &NP_Memory) -> Result<NP_Cursor, NP_Error> {
         
+        let new_cursor_addr = memory.malloc_pointers(2, 1)?; // malloc list item
+
         let list_value = self.list.get_value(memory);
         let list_data = Self::get_list(list_value.get_addr_value() as usize, memory);
-
-        let new_cursor_addr = memory.malloc_borrow(&[0u8; 5])?; // malloc list item
         let new_cursor = NP_Cursor::new(new_cursor_addr, self.schema_of, self.list.schema_addr);
         let new_cursor_value = new_cursor.get_value(memory);
-        new_cursor_value.set_index(self.index as u8);
+        new_cursor_value.set_index(index as u8);
 
 
         if let Some(current) = self.current {
@@ -139,13 +146,13 @@ impl NP_List {
                 // set NEXT of previous to new cursor
                 let prev_cursor = NP_Cursor::new(prev.buff_addr, self.schema_of, self.list.schema_addr);
                 let prev_cursor_value = prev_cursor.get_value(memory);
-                prev_cursor_value.set_next_addr(new_cursor_addr as u16);
+                prev_cursor_value.set_next_addr(new_cursor_addr as u32);
 
                 // set NEXT of this cursor to CURRENT
-                new_cursor_value.set_next_addr(current.buff_addr as u16);
+                new_cursor_value.set_next_addr(current.buff_addr as u32);
             } else { // replacing head
                 new_cursor_value.set_next_addr(list_data.get_head());
-                list_data.set_head(new_cursor_addr as u16);
+                list_data.set_head(new_cursor_addr as u32);
             }
 
             Ok(new_cursor)
@@ -156,19 +163,20 @@ impl NP_List {
 
     #[inline(always)]
     pub fn make_list<'make>(list_cursor: &NP_Cursor, memory: &'make NP_Memory) -> Result<(), NP_Error> {
-        let list_addr = memory.malloc_borrow(&[0u8; 4])?; // head & tail
+        let list_addr = memory.malloc_pointers(2, 0)?; // head & tail
         let value = list_cursor.get_value(memory);
-        value.set_addr_value(list_addr as u16);
+        value.set_addr_value(list_addr as u32);
         Ok(())
     }
 
     #[inline(always)]
-    pub fn get_list<'list>(list_cursor_value_addr: usize, memory: &'list NP_Memory<'list>) -> &'list mut NP_List_Bytes {
-        if list_cursor_value_addr > memory.read_bytes().len() { // attack
-            unsafe { &mut *(memory.write_bytes().as_ptr() as *mut NP_List_Bytes) }
+    pub fn get_list<'list>(list_cursor_value_addr: usize, memory: &'list NP_Memory<'list>) -> NP_List_Ref<'list> {
+        let address = if list_cursor_value_addr > memory.read_bytes().len() { // attack
+            0
         } else { // normal operation
-            unsafe { &mut *(memory.write_bytes().as_ptr().add(list_cursor_value_addr as usize) as *mut NP_List_Bytes) }
-        }
+            list_cursor_value_addr
+        };
+        list_bytes(memory, address)
     }
 
     #[inline(always)]
@@ -183,11 +191,9 @@ impl NP_List {
             _ => 0
         };
 
-        let memory_bytes = memory.write_bytes();
-
-        if list_addr > 0 && list_addr < (memory_bytes.len() + 4) {
+        if list_addr > 0 && list_addr < (memory.read_bytes().len() + 4) {
 
-            let bytes = unsafe { &mut *(memory_bytes.as_ptr().add(list_addr) as *mut NP_List_Bytes) };
+            let bytes = Self::get_list(list_addr, memory);
 
             let tail_addr = bytes.get_tail() as usize;
 
@@ -224,6 +230,10 @@ impl NP_List {
     #[inline(always)]
     pub fn step_iter(&mut self, memory: &NP_Memory) -> Option<(usize, Option<NP_Cursor>)> {
 
+        if self.only_real == false {
+            return self.step_virtual_iter(memory);
+        }
+
         match self.head {
             Some(head) => {
 
@@ -237,38 +247,23 @@ impl NP_List {
                             return None;
                         }
 
-                        if self.only_real {    
-                            let next_cursor = NP_Cursor::new(next_addr, self.schema_of, self.list.schema_addr);
-                            let next_index = next_cursor.get_value(memory).get_index();
-                            self.index = next_index as usize;
-                            self.previous = self.current.clone();
-                            self.current = Some(List_Item { buff_addr: next_addr, index: next_index as usize});
-                            Some((next_index as usize, Some(next_cursor)))
-                        } else {
-                            if current.index > self.index {
-                                self.index += 1;
-                                Some((self.index - 1, None))
-                            } else if current.index == self.index {
-                                let next_cursor = NP_Cursor::new(next_addr, self.schema_of, self.list.schema_addr);
-                                let next_index = next_cursor.get_value(memory).get_index();
-                                self.index += 1;
-                                self.previous = self.current.clone();
-                                self.current = Some(List_Item { buff_addr: next_addr, index: next_index as usize});
-                                Some((self.index - 1, Some(NP_Cursor::new(current.buff_addr, self.schema_of, self.list.schema_addr))))
-                            } else {
-                                None
-                            }
+                        let next_cursor = NP_Cursor::new(next_addr, self.schema_of, self.list.schema_addr);
+                        let next_index = next_cursor.get_value(memory).get_index();
+
+                        // indexes always go up along the list, anything else is a damaged or looping link
+                        if next_index as usize <= current.index {
+                            return None;
                         }
+
+                        self.index = next_index as usize;
+                        self.previous = self.current.clone();
+                        self.current = Some(List_Item { buff_addr: next_addr, index: next_index as usize});
+                        Some((next_index as usize, Some(next_cursor)))
                     },
                     None => { // first iteration
                         self.current = Some(head);
-                        if self.only_real || head.index == 0 {
-                            self.index = head.index;
-                            Some((head.index, Some(NP_Cursor::new(head.buff_addr, self.schema_of, self.list.schema_addr))))
-                        } else {
-                            self.index = 0;
-                            Some((0, None))
-                        }
+                        self.index = head.index;
+                        Some((head.index, Some(NP_Cursor::new(head.buff_addr, self.schema_of, self.list.schema_addr))))
                     }
                 }
             },
@@ -276,6 +271,54 @@ impl NP_List {
         }
     }
 
+    // step through every index up to the tail, including indexes without list items.
+    // `current` is the next real item and `previous` is the real item before it.
+    #[inline(always)]
+    fn step_virtual_iter(&mut self, memory: &NP_Memory) -> Option<(usize, Option<NP_Cursor>)> {
+
+        let head = self.head?;
+
+        if self.current.is_none() && self.previous.is_none() { // first iteration
+            self.current = Some(head);
+        }
+
+        loop {
+            let current = self.current?;
+
+            if current.index < self.index { // item is before starting index
+                self.step_real_item(memory);
+                continue;
+            }
+
+            let this_index = self.index;
+            self.index += 1;
+
+            if current.index > this_index {
+                return Some((this_index, None));
+            }
+
+            self.step_real_item(memory);
+            return Some((this_index, Some(NP_Cursor::new(current.buff_addr, self.schema_of, self.list.schema_addr))));
+        }
+    }
+
+    #[inline(always)]
+    fn step_real_item(&mut self, memory: &NP_Memory) {
+        if let Some(current) = self.current {
+            let current_cursor = NP_Cursor::new(current.buff_addr, self.schema_of, self.list.schema_addr);
+            let next_addr = current_cursor.get_value(memory).get_next_addr() as usize;
+            self.previous = Some(current);
+            self.current = if next_addr == 0 {
+                None
+            } else {
+                let next_cursor = NP_Cursor::new(next_addr, self.schema_of, self.list.schema_addr);
+                let next_index = next_cursor.get_value(memory).get_index() as usize;
+                // indexes always go up along the list, anything else is a damaged or looping link
+                if next_index <= current.index { None } else { Some(List_Item { buff_addr: next_addr, index: next_index }) }
+            };
+        }
+    }
+
     #[inline(always)]
     pub fn push<'push>(list_cursor: &NP_Cursor, memory: &NP_Memory, index: Option<usize>) -> Result<Option<(u16, NP_Cursor)>, NP_Error> {
 
@@ -290,8 +333,10 @@ impl NP_List {
 
                 let mut new_index: usize = index.unwrap_or(0);
 
-                let new_item_addr = memory.malloc_borrow(&[0u8; 5])?; // list item
+                let new_item_addr = memory.malloc_pointers(2, 1)?; // list item
 
+                // the malloc can move the buffer, get the list again
+                let list_value = list_cursor.get_value(memory);
                 let list_data = Self::get_list(list_value.get_addr_value() as usize, memory);
 
                 let new_cursor = NP_Cursor::new(new_item_addr, of, list_cursor.schema_addr);
@@ -299,26 +344,26 @@ impl NP_List {
                 
 
                 if list_data.get_head() == 0 { // empty list
-                    list_data.set_head(new_item_addr as u16);
-                    list_data.set_tail(new_item_addr as u16);
+                    list_data.set_head(new_item_addr as u32);
+                    list_data.set_tail(new_item_addr as u32);
                     if new_index > 255 {
-                        return Err(NP_Error::new("Index cannot be greater than 255!"))
+                        return Err(NP_Error::OutOfBounds("Index cannot be greater than 255!".into()))
                     }
                     new_cursor_value.set_index(new_index as u8)
                 } else { // list has items
                     let old_tail = NP_Cursor::new(list_data.get_tail() as usize, of, list_cursor.schema_addr);
                     let old_tail_value = old_tail.get_value(memory);
-                    old_tail_value.set_next_addr(new_item_addr as u16);
                     new_index = if let Some(idx) = index {
                         idx as usize
                     } else {
-                        (old_tail_value.get_index() + 1) as usize
+                        old_tail_value.get_index() as usize + 1
                     };
                     if new_index > 255 {
-                        return Err(NP_Error::new("Index cannot be greater than 255!"))
+                        return Err(NP_Error::OutOfBounds("Index cannot be greater than 255!".into()))
                     }
+                    old_tail_value.set_next_addr(new_item_addr as u32);
                     new_cursor_value.set_index(new_index as u8);
-                    list_data.set_tail(new_item_addr as u16);
+                    list_data.set_tail(new_item_addr as u32);
                 }
 
 
@@ -328,6 +373,47 @@ impl NP_List {
             _ => Ok(None)
         }
     }
+
+    /// Every item in the list with it's index, in list order
+    pub fn items(list_cursor: &NP_Cursor, memory: &NP_Memory) -> Vec<(usize, NP_Cursor)> {
+        let mut items: Vec<(usize, NP_Cursor)> = Vec::new();
+        let mut list_iter = Self::new_iter(list_cursor, memory, true, 0);
+        while let Some((index, item)) = list_iter.step_iter(memory) {
+            if let Some(item) = item {
+                items.push((index, item));
+            }
+        }
+        items
+    }
+
+    /// Rebuild the list from items in the order given, each item gets the index next to it.  Indexes should go up in order.
+    pub fn relink(list_cursor: &NP_Cursor, memory: &NP_Memory, items: &[(usize, NP_Cursor)]) -> Result<(), NP_Error> {
+        let list_value = list_cursor.get_value(memory);
+        if list_value.get_addr_value() == 0 {
+            if items.len() == 0 {
+                return Ok(());
+            }
+            Self::make_list(list_cursor, memory)?;
+        }
+
+        let list_data = Self::get_list(list_cursor.get_value(memory).get_addr_value() as usize, memory);
+
+        for (x, (index, item)) in items.iter().enumerate() {
+            if *index > 255 {
+                return Err(NP_Error::OutOfBounds("Index cannot be greater than 255!".into()))
+            }
+            let item_value = item.get_value(memory);
+            item_value.set_index(*index as u8);
+            item_value.set_next_addr(match items.get(x + 1) {
+                Some((_, next)) => next.buff_addr as u32,
+                None => 0
+            });
+        }
+
+        list_data.set_head(items.first().map(|(_, item)| item.buff_addr as u32).unwrap_or(0));
+        list_data.set_tail(items.last().map(|(_, item)| item.buff_addr as u32).unwrap_or(0));
+        Ok(())
+    }
 }
 
 impl<'value> NP_Value<'value> for NP_List {
@@ -358,20 +444,8 @@ impl<'value> NP_Value<'value> for NP_List {
             return Ok(0) 
         }
 
-        // head + tail
-        let base_size = 4usize;
-
-        let mut acc_size = 0usize;
-
-        let mut list_iter = Self::new_iter(&cursor, memory, true, 0);
-
-        while let Some((_index, item)) = Self::step_iter(&mut list_iter, memory) {
-            if let Some(item_cursor) = &item {
-                acc_size += NP_Cursor::calc_size(item_cursor, memory)?;
-            }
-        }
-
-        Ok(acc_size + base_size)
+        // head + tail, items are counted by NP_Cursor::calc_size
+        Ok(2 * memory.addr_size.bytes())
     }
     
     fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
@@ -382,19 +456,8 @@ impl<'value> NP_Value<'value> for NP_List {
             return NP_JSON::Null
         }
 
-        let mut json_list = Vec::new();
-
-        let mut list_iter = Self::new_iter(&cursor, memory, false, 0);
-
-        while let Some((_index, item)) = Self::step_iter(&mut list_iter, memory) {
-             if let Some(item_cursor) = &item {
-                json_list.push(NP_Cursor::json_encode(item_cursor, memory));   
-            } else {
-                json_list.push(NP_JSON::Null);   
-            }    
-        }
-
-        NP_JSON::Array(json_list)
+        // items are added by NP_Cursor::json_encode
+        NP_JSON::Array(Vec::new())
     }
 
     fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
@@ -405,17 +468,9 @@ impl<'value> NP_Value<'value> for NP_List {
             return Ok(to_cursor) 
         }
 
+        // items are copied by NP_Cursor::compact
         Self::make_list(&to_cursor, to_memory)?;
 
-        let mut list_iter = Self::new_iter(&from_cursor, from_memory, true, 0);
-
-        while let Some((index, item)) = Self::step_iter(&mut list_iter, from_memory) {
-            if let Some(old_item) = &item {
-                let (_new_index, new_item) = opt_err(NP_List::push(&to_cursor, to_memory, Some(index))?)?;
-                NP_Cursor::compact(old_item.clone(), from_memory, new_item, to_memory)?;
-            }       
-        }
-
         Ok(to_cursor)
     }
 
@@ -433,7 +488,7 @@ impl<'value> NP_Value<'value> for NP_List {
 
         match json_schema["of"] {
             NP_JSON::Null => {
-                return Err(NP_Error::new("Lists require an 'of' property that is a schema type!"))
+                return Err(NP_Error::Schema("Lists require an 'of' property that is a schema type!".into()))
             },
             _ => { }
         }
@@ -523,4 +578,376 @@ fn parseing_works() -> Result<(), NP_Error> {
     assert_eq!(new_buffer.get::<&str>(&["10"])?.unwrap(), "world");
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn sparse_list_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"list\",\"of\":{\"type\":\"string\"}}";
+    let factory = crate::NP_Factory::new(schema)?;
+
+    // fill gaps in the middle of the list
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0"], "a")?;
+    buffer.set(&["4"], "e")?;
+    buffer.set(&["2"], "c")?;
+    buffer.set(&["1"], "b")?;
+    assert_eq!(buffer.get::<&str>(&["1"])?, Some("b"));
+    assert_eq!(buffer.get::<&str>(&["2"])?, Some("c"));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[\"a\",\"b\",\"c\",null,\"e\"]");
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["2"], "c")?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[null,null,\"c\"]");
+
+    Ok(())
+}
+
+#[test]
+fn sorted_insert_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "i32"}}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    for x in [5i32, -2, 9, 5, 0].iter() {
+        buffer.list_insert_sorted(&[], *x)?;
+    }
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[-2,0,5,5,9]");
+
+    // gaps and items that aren't set move up with the rest of the list
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0"], 1i32)?;
+    buffer.set(&["3"], 8i32)?;
+    buffer.set(&["4"], 20i32)?;
+    buffer.del(&["3"])?;
+    assert_eq!(buffer.list_insert_sorted(&[], 10i32)?, Some(4));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[1,null,null,null,10,20]");
+    assert_eq!(buffer.list_insert_sorted(&[], -1i32)?, Some(0));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[-1,1,null,null,null,10,20]");
+    buffer.compact(None)?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[-1,1,null,null,null,10,20]");
+    assert_eq!(buffer.list_push(&[], 99i32)?, Some(7));
+
+    // the list can't grow past the last index
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["255"], 3i32)?;
+    assert!(buffer.list_insert_sorted(&[], 1i32).is_err());
+    assert!(buffer.list_insert_sorted(&[], 1u8).is_err());
+
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "table", "columns": [
+        ["name", {"type": "string"}],
+        ["rank", {"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}]
+    ]}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.list_insert_sorted_by(&[], &["rank", "0"], 3u8)?;
+    buffer.list_insert_sorted_by(&[], &["rank", "0"], 1u8)?;
+    buffer.list_insert_sorted_by(&[], &["name"], "jeb")?;
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"[{"name":null,"rank":[1,null]},{"name":null,"rank":[3,null]},{"name":"jeb","rank":null}]"#);
+    assert!(buffer.list_insert_sorted(&[], "jeb").is_err());
+    assert!(buffer.list_insert_sorted_by(&[], &["age"], 3u8).is_err());
+    assert_eq!(buffer.length(&[])?, Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn list_sort_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["words", {"type": "list", "of": {"type": "string"}}],
+        ["points", {"type": "list", "of": {"type": "tuple", "values": [{"type": "float"}, {"type": "u8"}]}}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.list_sort(&["words"], &[])?, false);
+
+    // gaps stay put and items without values sort first
+    buffer.set(&["words", "0"], "mun")?;
+    buffer.set(&["words", "1"], "eve")?;
+    buffer.set(&["words", "4"], "duna")?;
+    buffer.set(&["words", "5"], "kerbin")?;
+    buffer.del(&["words", "5"])?;
+    assert!(buffer.list_sort(&["words"], &[])?);
+    assert_eq!(buffer.json_encode(&["words"])?.stringify(), r#"[null,"duna",null,null,"eve","mun"]"#);
+    assert_eq!(buffer.list_push(&["words"], "moho")?, Some(6));
+    buffer.compact(None)?;
+    assert_eq!(buffer.json_encode(&["words"])?.stringify(), r#"[null,"duna",null,null,"eve","mun","moho"]"#);
+
+    // stable over equal keys
+    buffer.set(&["points", "0", "0"], 2.5f32)?;
+    buffer.set(&["points", "0", "1"], 1u8)?;
+    buffer.set(&["points", "1", "0"], -1.0f32)?;
+    buffer.set(&["points", "2", "0"], 2.5f32)?;
+    buffer.set(&["points", "2", "1"], 0u8)?;
+    buffer.list_sort(&["points"], &["0"])?;
+    assert_eq!(buffer.json_encode(&["points"])?.stringify(), "[[-1,null],[2.5,1],[2.5,0]]");
+    buffer.list_sort(&["points"], &[])?;
+    assert_eq!(buffer.json_encode(&["points"])?.stringify(), "[[-1,null],[2.5,0],[2.5,1]]");
+
+    assert!(buffer.list_sort(&[], &[]).is_err());
+    assert!(buffer.list_sort(&["points"], &["3"]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn list_filter_works() -> Result<(), NP_Error> {
+    use alloc::vec;
+
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["ids", {"type": "list", "of": {"type": "u16", "default": 7}}],
+        ["name", {"type": "string"}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.list_filter(&["ids"], |_item| Ok(true))?, Vec::<usize>::new());
+
+    buffer.set(&["ids", "1"], 40u16)?;
+    buffer.set(&["ids", "3"], 2u16)?;
+    buffer.set(&["ids", "4"], 41u16)?;
+    buffer.set(&["ids", "6"], 9u16)?;
+    buffer.del(&["ids", "4"])?;
+
+    // holes and deleted items are skipped
+    assert_eq!(buffer.list_filter(&["ids"], |_item| Ok(true))?, vec![1, 3, 6]);
+    assert_eq!(buffer.list_filter(&["ids"], |item| Ok(item.get::<u16>(&[])? > Some(5)))?, vec![1, 6]);
+    assert_eq!(buffer.list_filter(&["ids"], |item| Ok(item.json_encode(&[])?.stringify() == "2"))?, vec![3]);
+
+    // errors stop the search
+    let mut seen = 0;
+    assert!(buffer.list_filter(&["ids"], |item| { seen += 1; Ok(item.get::<u8>(&[])?.is_some()) }).is_err());
+    assert_eq!(seen, 1);
+
+    assert!(buffer.list_filter(&["name"], |_item| Ok(true)).is_err());
+    assert_eq!(buffer.list_filter(&["nothing"], |_item| Ok(true))?, Vec::<usize>::new());
+
+    Ok(())
+}
+
+#[test]
+fn fold_numeric_works() -> Result<(), NP_Error> {
+    use crate::buffer::NP_Fold;
+
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["temps", {"type": "list", "of": {"type": "i8", "default": 100}}],
+        ["names", {"type": "list", "of": {"type": "string"}}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.fold_numeric(&["temps"], &[], NP_Fold::Sum)?, Some(0.0));
+    assert_eq!(buffer.fold_numeric(&["temps"], &[], NP_Fold::Min)?, None);
+    assert_eq!(buffer.fold_numeric(&["temps"], &[], NP_Fold::Avg)?, None);
+
+    buffer.set(&["temps", "0"], -20i8)?;
+    buffer.set(&["temps", "2"], 35i8)?;
+    buffer.set(&["temps", "3"], 5i8)?;
+    buffer.set(&["temps", "5"], 120i8)?;
+    buffer.del(&["temps", "5"])?;
+
+    assert_eq!(buffer.fold_numeric(&["temps"], &[], NP_Fold::Sum)?, Some(20.0));
+    assert_eq!(buffer.fold_numeric(&["temps"], &[], NP_Fold::Min)?, Some(-20.0));
+    assert_eq!(buffer.fold_numeric(&["temps"], &[], NP_Fold::Max)?, Some(35.0));
+    assert_eq!(buffer.fold_numeric(&["temps"], &[], NP_Fold::Avg)?, Some(20.0 / 3.0));
+
+    assert!(buffer.fold_numeric(&["names"], &[], NP_Fold::Sum).is_err());
+    assert!(buffer.fold_numeric(&[], &[], NP_Fold::Sum).is_err());
+    assert!(buffer.fold_numeric(&["temps"], &["0"], NP_Fold::Sum).is_err());
+    assert!(buffer.fold_numeric(&["nothing"], &[], NP_Fold::Sum).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn list_page_works() -> Result<(), NP_Error> {
+    use crate::buffer::NP_ListCursor;
+
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["ids", {"type": "list", "of": {"type": "u16"}}],
+        ["name", {"type": "string"}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    let (page, next) = buffer.list_page(&["ids"], NP_ListCursor::start(), 10)?;
+    assert_eq!((page.len(), next), (0, None));
+
+    for x in 0..200u16 {
+        buffer.list_push(&["ids"], x)?;
+    }
+    buffer.del(&["ids", "3"])?;
+
+    // every item with a value comes back once
+    let mut seen: Vec<u16> = Vec::new();
+    let mut cursor = Some(NP_ListCursor::start());
+    while let Some(from) = cursor {
+        let (page, next) = buffer.list_page(&["ids"], NP_ListCursor::from_bytes(&from.to_bytes())?, 7)?;
+        for item in page.iter() {
+            seen.push(item.get::<u16>(&[])?.unwrap_or(0));
+        }
+        cursor = next;
+    }
+    assert_eq!(seen.len(), 199);
+    assert!(seen.iter().zip(seen.iter().skip(1)).all(|(a, b)| a < b));
+    assert!(!seen.contains(&3));
+
+    // deleted items don't count towards the page
+    let (page, next) = buffer.list_page(&["ids"], NP_ListCursor::start(), 3)?;
+    assert_eq!(next.unwrap().index(), Some(2));
+    assert_eq!(page.len(), 3);
+    let (page, next) = buffer.list_page(&["ids"], next.unwrap(), 1)?;
+    assert_eq!(page[0].index, 4);
+    assert_eq!(next.unwrap().index(), Some(4));
+
+    // cursors that don't point at an item walk from the head
+    let (page, _next) = buffer.list_page(&["ids"], NP_ListCursor::from_bytes(&[0, 0, 0, 50, 0, 0, 255, 255])?, 1)?;
+    assert_eq!(page[0].get::<u16>(&[])?, Some(51));
+    let (_page, next) = buffer.list_page(&["ids"], next.unwrap(), 100)?;
+    let mut moved = next.unwrap().to_bytes();
+    moved[3] = 120;
+    let (page, _next) = buffer.list_page(&["ids"], NP_ListCursor::from_bytes(&moved)?, 1)?;
+    assert_eq!(page[0].get::<u16>(&[])?, Some(121));
+
+    assert!(NP_ListCursor::from_bytes(&[1, 2]).is_err());
+    assert!(buffer.list_page(&["name"], NP_ListCursor::start(), 1).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn list_slices_work() -> Result<(), NP_Error> {
+    use crate::json_flex::NP_JSON;
+
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["ids", {"type": "list", "of": {"type": "u8"}}],
+        ["name", {"type": "string"}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.list_truncate(&["ids"], 2)?, false);
+    assert_eq!(buffer.list_remove_range(&["ids"], 0..2)?, false);
+    assert_eq!(buffer.list_splice(&["nothing"], 0, &[NP_JSON::Integer(1)])?, false);
+
+    // splicing into a list that doesn't exist yet makes it
+    assert!(buffer.list_splice(&["ids"], 0, &[NP_JSON::Integer(1), NP_JSON::Integer(2)])?);
+    assert_eq!(buffer.json_encode(&["ids"])?.stringify(), "[1,2]");
+
+    for x in 3..10u8 {
+        buffer.list_push(&["ids"], x)?;
+    }
+    buffer.set(&["ids", "12"], 13u8)?;
+
+    assert!(buffer.list_remove_range(&["ids"], 2..5)?);
+    assert_eq!(buffer.json_encode(&["ids"])?.stringify(), "[1,2,6,7,8,9,null,null,null,13]");
+    assert!(buffer.list_remove_range(&["ids"], 5..7)?);
+    assert_eq!(buffer.json_encode(&["ids"])?.stringify(), "[1,2,6,7,8,null,null,13]");
+
+    assert!(buffer.list_splice(&["ids"], 1, &[NP_JSON::Integer(40), NP_JSON::Null, NP_JSON::Integer(41)])?);
+    assert_eq!(buffer.json_encode(&["ids"])?.stringify(), "[1,40,null,41,2,6,7,8,null,null,13]");
+    assert!(buffer.list_splice(&["ids"], 12, &[NP_JSON::Integer(50)])?);
+    assert_eq!(buffer.length(&["ids"])?, Some(13));
+
+    assert!(buffer.list_truncate(&["ids"], 4)?);
+    assert_eq!(buffer.json_encode(&["ids"])?.stringify(), "[1,40,null,41]");
+    assert_eq!(buffer.list_push(&["ids"], 3u8)?, Some(4));
+    assert!(buffer.list_truncate(&["ids"], 0)?);
+    assert_eq!(buffer.length(&["ids"])?, Some(0));
+    assert_eq!(buffer.list_push(&["ids"], 3u8)?, Some(0));
+
+    // bad values and lists that get too long are errors
+    assert!(buffer.list_splice(&["ids"], 0, &[NP_JSON::Integer(300)]).is_err());
+    assert!(buffer.list_splice(&["ids"], 255, &[NP_JSON::Integer(1), NP_JSON::Integer(2)]).is_err());
+    assert!(buffer.list_truncate(&["name"], 0).is_err());
+
+    buffer.compact(None)?;
+    assert_eq!(buffer.json_encode(&["ids"])?.stringify(), "[null,3]");
+
+    Ok(())
+}
+
+#[test]
+fn list_rev_iter_works() -> Result<(), NP_Error> {
+    use alloc::vec;
+
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["ids", {"type": "list", "of": {"type": "u16"}}],
+        ["name", {"type": "string"}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.iter_list_rev(&["ids"])?.count(), 0);
+
+    buffer.set(&["ids", "9"], 90u16)?;
+    buffer.set(&["ids", "2"], 20u16)?;
+    for x in 0..5u16 {
+        buffer.list_push(&["ids"], 100 + x)?;
+    }
+    buffer.del(&["ids", "12"])?;
+
+    let indexes: Vec<usize> = buffer.iter_list_rev(&["ids"])?.map(|item| item.index).collect();
+    assert_eq!(indexes, vec![14, 13, 11, 10, 9, 2]);
+
+    let latest: Vec<Option<u16>> = buffer.iter_list_rev(&["ids"])?.take(3).map(|item| item.get::<u16>(&[]).unwrap()).collect();
+    assert_eq!(latest, vec![Some(104), Some(103), Some(101)]);
+
+    assert!(buffer.iter_list_rev(&["name"]).is_err());
+    assert_eq!(buffer.iter_list_rev(&["missing"])?.count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn sparse_list_iter_works() -> Result<(), NP_Error> {
+    use alloc::vec;
+    use alloc::string::String;
+
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+
+    // gaps before the head, between items and none after the tail
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["3"], "d")?;
+    buffer.set(&["1"], "b")?;
+    buffer.set(&["5"], "f")?;
+
+    let items: Vec<(usize, Option<String>)> = buffer.get_iter(&[])?.unwrap_or_else(|| panic!()).map(|item| (item.index, item.get::<&str>().unwrap().map(String::from))).collect();
+    assert_eq!(items, vec![(0, None), (1, Some("b".into())), (2, None), (3, Some("d".into())), (4, None), (5, Some("f".into()))]);
+
+    // items made in a gap get the index of that gap
+    buffer.set(&["2"], "c")?;
+    buffer.set(&["0"], "a")?;
+    let items: Vec<(usize, Option<String>)> = buffer.get_iter(&[])?.unwrap_or_else(|| panic!()).map(|item| (item.index, item.get::<&str>().unwrap().map(String::from))).collect();
+    assert_eq!(items, vec![(0, Some("a".into())), (1, Some("b".into())), (2, Some("c".into())), (3, Some("d".into())), (4, None), (5, Some("f".into()))]);
+    assert_eq!(buffer.get::<&str>(&["4"])?, None);
+    assert_eq!(buffer.get::<&str>(&["5"])?, Some("f"));
+
+    Ok(())
+}
+
+#[test]
+fn list_inserts_after_growth_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?;
+
+    // every insert below mallocs into bytes without spare capacity, so the buffer moves while the list is being linked
+    let reopen = |buffer: crate::buffer::NP_Buffer| -> Result<Vec<u8>, NP_Error> {
+        let mut bytes = buffer.close();
+        bytes.shrink_to_fit();
+        Ok(bytes)
+    };
+
+    let mut bytes = reopen(factory.empty_buffer(None))?;
+    for (index, value) in [(4usize, 4u8), (1, 1), (8, 8), (6, 6), (0, 0)].iter() {
+        let mut buffer = factory.open_buffer(bytes);
+        let index = index.to_string();
+        buffer.set(&[index.as_str()], *value)?;
+        bytes = reopen(buffer)?;
+    }
+
+    let mut buffer = factory.open_buffer(bytes);
+    assert_eq!(buffer.list_push(&[], 9u8)?, Some(9));
+    bytes = reopen(buffer)?;
+
+    let buffer = factory.open_buffer(bytes);
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[0,1,null,null,4,null,6,null,8,9]");
+    buffer.verify()?;
+
+    // pushing into a list that doesn't exist yet
+    let mut buffer = factory.open_buffer(reopen(factory.empty_buffer(None))?);
+    assert_eq!(buffer.list_push(&[], 3u8)?, Some(0));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "[3]");
+
+    Ok(())
+}
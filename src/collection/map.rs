@@ -1,5 +1,4 @@
-use alloc::string::String;
-use crate::{pointer::NP_Map_Bytes, utils::opt_err};
+use crate::pointer::{NP_Map_Ref, map_bytes};
 use crate::pointer::NP_Cursor;
 use crate::{json_flex::JSMAP};
 use crate::pointer::{NP_Value};
@@ -9,7 +8,6 @@ use alloc::string::ToString;
 use alloc::vec::Vec;
 use alloc::boxed::Box;
 use alloc::borrow::ToOwned;
-use core::{hint::unreachable_unchecked};
 
 #[doc(hidden)]
 #[derive(Debug, Clone, Copy)]
@@ -53,12 +51,13 @@ impl<'map> NP_Map<'map> {
     }
 
     #[inline(always)]
-    pub fn get_map<'get>(map_buff_addr: usize, memory: &'get NP_Memory<'get>) -> &'get mut NP_Map_Bytes {
-        if map_buff_addr > memory.read_bytes().len() { // attack
-            unsafe { &mut *(memory.write_bytes().as_ptr() as *mut NP_Map_Bytes) }
+    pub fn get_map<'get>(map_buff_addr: usize, memory: &'get NP_Memory<'get>) -> NP_Map_Ref<'get> {
+        let address = if map_buff_addr > memory.read_bytes().len() { // attack
+            0
         } else { // normal operation
-            unsafe { &mut *(memory.write_bytes().as_ptr().add(map_buff_addr as usize) as *mut NP_Map_Bytes) }
-        }
+            map_buff_addr
+        };
+        map_bytes(memory, address)
     }
 
     #[inline(always)]
@@ -105,7 +104,8 @@ impl<'map> NP_Map<'map> {
                         let current_item = NP_Cursor::new(current.buff_addr, self.value_of, self.map.schema_addr);
                         let current_value = current_item.get_value(memory);
                         let next_value = current_value.get_next_addr() as usize;
-                        if next_value == 0 { //nothing left to step
+                        // new items are put in front of older ones, so links always point back in the buffer
+                        if next_value == 0 || next_value >= current.buff_addr { //nothing left to step
                             return None;
                         } else {
                             let next_value_cursor = NP_Cursor::new(next_value, self.value_of, self.map.schema_addr);
@@ -136,27 +136,28 @@ impl<'map> NP_Map<'map> {
         };
 
         if key.len() >= 255 {
-            return Err(NP_Error::new("Key length cannot be larger than 255 charecters!"));
+            return Err(NP_Error::InvalidValue("Key length cannot be larger than 255 charecters!".into()));
         }
 
-        let map_value = map_cursor.get_value(memory);
-
-        let new_cursor_addr = memory.malloc_borrow(&[0u8; 6])?;
+        let new_cursor_addr = memory.malloc_pointers(3, 0)?;
         let new_cursor = NP_Cursor::new(new_cursor_addr, value_of, map_cursor.schema_addr);
-        let new_cursor_value = new_cursor.get_value(memory);
 
         // set key
         let key_item_addr = memory.malloc_borrow(&[key.len() as u8])?;
         memory.malloc_borrow(key.as_bytes())?;
-        new_cursor_value.set_key_addr(key_item_addr as u16);
+
+        // the mallocs can move the buffer, get the pointers after them
+        let map_value = map_cursor.get_value(memory);
+        let new_cursor_value = new_cursor.get_value(memory);
+        new_cursor_value.set_key_addr(key_item_addr as u32);
 
         let head = map_value.get_addr_value() as usize;
 
         // Set head of map to new cursor
-        map_value.set_addr_value(new_cursor_addr as u16);
+        map_value.set_addr_value(new_cursor_addr as u32);
 
         if head != 0 { // set new cursors NEXT to old HEAD
-            new_cursor_value.set_next_addr(head as u16);
+            new_cursor_value.set_next_addr(head as u32);
         }
 
         Ok(new_cursor)
@@ -183,28 +184,9 @@ impl<'value> NP_Value<'value> for NP_Map<'value> {
         Ok(NP_JSON::Dictionary(schema_json))
     }
 
-    fn get_size(cursor: &NP_Cursor, memory: &'value NP_Memory<'value>) -> Result<usize, NP_Error> {
-
-        let c_value = cursor.get_value(memory);
-
-        if c_value.get_addr_value() == 0 {
-            return Ok(0) 
-        }
-
-        let mut acc_size = 0usize;
-
-        let mut map_iter = Self::new_iter(&cursor, memory);
-
-        while let Some((_index, item)) = Self::step_iter(&mut map_iter, memory) {
-            let key_size = item.get_value(memory).get_key_size(memory);
-            acc_size += 1; // length byte
-            acc_size += key_size;
-            acc_size += NP_Cursor::calc_size(&item, memory)?;
-        }
-
-
-        Ok(acc_size)
-   
+    fn get_size(_cursor: &NP_Cursor, _memory: &'value NP_Memory<'value>) -> Result<usize, NP_Error> {
+        // the map is only it's items, they're counted with their keys by NP_Cursor::calc_size
+        Ok(0)
     }
 
     fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
@@ -215,34 +197,12 @@ impl<'value> NP_Value<'value> for NP_Map<'value> {
             return NP_JSON::Null
         }
 
-        let mut json_map = JSMAP::new();
-
-        let mut map_iter = Self::new_iter(&cursor, memory);
-
-        while let Some((key, item)) = Self::step_iter(&mut map_iter, memory) {
-            json_map.insert(String::from(key), NP_Cursor::json_encode(&item, memory));     
-        }
-
-        NP_JSON::Dictionary(json_map)
-   
+        // items are added by NP_Cursor::json_encode
+        NP_JSON::Dictionary(JSMAP::new())
     }
 
-    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
-
-        let from_value = from_cursor.get_value(from_memory);
-
-        if from_value.get_addr_value() == 0 {
-            return Ok(to_cursor) 
-        }
-
-        let mut map_iter = Self::new_iter(&from_cursor, from_memory);
-
-        while let Some((key, item)) = Self::step_iter(&mut map_iter, from_memory) {
-            let new_item = Self::insert(&to_cursor, to_memory, key)?;
-            NP_Cursor::compact(item.clone(), from_memory, new_item, to_memory)?;    
-        }
-
-
+    fn do_compact(_from_cursor: NP_Cursor, _from_memory: &'value NP_Memory, to_cursor: NP_Cursor, _to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+        // items are inserted by NP_Cursor::compact
         Ok(to_cursor)
     }
 
@@ -260,7 +220,7 @@ impl<'value> NP_Value<'value> for NP_Map<'value> {
 
         match json_schema["value"] {
             NP_JSON::Null => {
-                return Err(NP_Error::new("Maps require a 'value' property that is a schema type!"))
+                return Err(NP_Error::Schema("Maps require a 'value' property that is a schema type!".into()))
             },
             _ => { }
         }
@@ -328,4 +288,31 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 55usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn map_find_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["scores", {"type": "map", "value": {"type": "u32"}}],
+        ["name", {"type": "string"}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.map_find(&["scores"], |_item| Ok(true))?.len(), 0);
+
+    buffer.set(&["scores", "jeb"], 20u32)?;
+    buffer.set(&["scores", "bill"], 50u32)?;
+    buffer.set(&["scores", "bob"], 70u32)?;
+    buffer.set(&["scores", "val"], 90u32)?;
+    buffer.del(&["scores", "val"])?;
+
+    let mut high = buffer.map_find(&["scores"], |item| Ok(item.get::<u32>(&[])? > Some(40)))?;
+    high.sort();
+    assert_eq!(high, ["bill", "bob"]);
+    assert_eq!(buffer.map_find(&["scores"], |item| Ok(item.key.starts_with("j")))?, ["jeb"]);
+    assert_eq!(buffer.map_find(&["scores"], |item| Ok(item.json_encode(&[])?.stringify() == "70"))?, ["bob"]);
+
+    assert!(buffer.map_find(&["scores"], |item| Ok(item.get::<u8>(&[])?.is_some())).is_err());
+    assert!(buffer.map_find(&["name"], |_item| Ok(true)).is_err());
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
-use crate::{hashmap::NP_HashMap};
+use crate::{hashmap::{NP_HashMap, NP_Key_Hash}};
 use alloc::string::String;
-use crate::pointer::{NP_Vtable};
+use crate::pointer::{NP_Vtable_Ref, vtable_bytes};
 use crate::{pointer::{NP_Cursor}, schema::{NP_Parsed_Schema, NP_Schema_Addr}};
 use crate::{memory::{NP_Memory}, pointer::{NP_Value}, error::NP_Error, schema::{NP_Schema, NP_TypeKeys}, json_flex::{JSMAP, NP_JSON}};
 
@@ -8,7 +8,7 @@ use alloc::string::ToString;
 use alloc::vec::Vec;
 use alloc::boxed::Box;
 use alloc::borrow::ToOwned;
-use core::{result::Result, hint::unreachable_unchecked};
+use core::result::Result;
 
 /// The data type for tables in NoProto buffers.
 /// 
@@ -16,7 +16,7 @@ use core::{result::Result, hint::unreachable_unchecked};
 #[derive(Debug)]
 pub struct NP_Table<'table> {
     index: usize,
-    v_table: Option<&'table mut NP_Vtable>,
+    v_table: Option<NP_Vtable_Ref<'table>>,
     v_table_addr: usize,
     v_table_index: usize,
     table: NP_Cursor
@@ -26,49 +26,63 @@ pub struct NP_Table<'table> {
 impl<'table> NP_Table<'table> {
 
     #[inline(always)]
-    pub fn select(mut table_cursor: NP_Cursor, key: &str, make_path: bool, memory: &NP_Memory) -> Result<Option<NP_Cursor>, NP_Error> {
+    pub fn select(table_cursor: NP_Cursor, key: &str, make_path: bool, memory: &NP_Memory) -> Result<Option<NP_Cursor>, NP_Error> {
         match &memory.schema[table_cursor.schema_addr] {
-            NP_Parsed_Schema::Table { columns, columns_mapped, .. } => {
+            NP_Parsed_Schema::Table { columns_mapped, .. } => {
                 match columns_mapped.get(key) {
-                    Some(x) => {
+                    Some(x) => Self::select_column(table_cursor, *x, make_path, memory),
+                    None => Ok(None)
+                }
+            },
+            _ => Err(NP_Error::new("unreachable"))
+        }
+    }
 
-                        let v_table =  *x / 4; // which vtable
-                        let v_table_idx = *x % 4; // which index on the selected vtable
+    /// Select a column by it's index in the table schema
+    pub fn select_column(mut table_cursor: NP_Cursor, column: usize, make_path: bool, memory: &NP_Memory) -> Result<Option<NP_Cursor>, NP_Error> {
+        match &memory.schema[table_cursor.schema_addr] {
+            NP_Parsed_Schema::Table { columns, .. } => {
 
-                        let mut table_value = table_cursor.get_value(memory);
+                if column >= columns.len() {
+                    return Ok(None);
+                }
 
-                        if table_value.get_addr_value() == 0 {
-                            if make_path {
-                                table_cursor = Self::make_first_vtable(table_cursor, memory)?;
-                            } else {
-                                return Ok(None);
-                            }
-                        }
+                let v_table =  column / 4; // which vtable
+                let v_table_idx = column % 4; // which index on the selected vtable
 
-                        table_value = table_cursor.get_value(memory);
+                let mut table_value = table_cursor.get_value(memory);
 
-                        let mut seek_vtable = 0usize;
-                        let mut vtable_address = table_value.get_addr_value() as usize;
- 
-                        while seek_vtable < v_table {
-                            let this_vtable = Self::get_vtable(vtable_address, memory);
-                            let next_vtable = this_vtable.get_next();
+                if table_value.get_addr_value() == 0 {
+                    if make_path {
+                        table_cursor = Self::make_first_vtable(table_cursor, memory)?;
+                    } else {
+                        return Ok(None);
+                    }
+                }
 
-                            if next_vtable == 0 {
-                                vtable_address = Self::make_next_vtable(this_vtable, memory)?;
-                            } else {
-                                vtable_address = next_vtable as usize;
-                            }
+                table_value = table_cursor.get_value(memory);
 
-                            seek_vtable += 1;
-                        }
+                let mut seek_vtable = 0usize;
+                let mut vtable_address = table_value.get_addr_value() as usize;
 
-                        let item_address = vtable_address + (v_table_idx * 2);
+                while seek_vtable < v_table {
+                    let this_vtable = Self::get_vtable(vtable_address, memory);
+                    let next_vtable = this_vtable.get_next();
 
-                        Ok(Some(NP_Cursor::new(item_address, columns[*x].2, table_cursor.schema_addr)))
-                    },
-                    None => Ok(None)
+                    if next_vtable != 0 {
+                        vtable_address = next_vtable as usize;
+                    } else if make_path {
+                        vtable_address = Self::make_next_vtable(vtable_address, memory)?;
+                    } else {
+                        return Ok(None);
+                    }
+
+                    seek_vtable += 1;
                 }
+
+                let item_address = vtable_address + (v_table_idx * memory.addr_size.bytes());
+
+                Ok(Some(NP_Cursor::new(item_address, columns[column].2, table_cursor.schema_addr)))
             },
             _ => Err(NP_Error::new("unreachable"))
         }
@@ -77,20 +91,21 @@ impl<'table> NP_Table<'table> {
     #[inline(always)]
     pub fn make_first_vtable<'make>(table_cursor: NP_Cursor, memory: &'make NP_Memory) -> Result<NP_Cursor, NP_Error> {
 
-        let first_vtable_addr = memory.malloc_borrow(&[0u8; 10])?;
+        let first_vtable_addr = memory.malloc_pointers(5, 0)?;
         
         let table_value = table_cursor.get_value(memory);
-        table_value.set_addr_value(first_vtable_addr as u16);
+        table_value.set_addr_value(first_vtable_addr as u32);
 
         Ok(table_cursor)
     }
 
     #[inline(always)]
-    pub fn make_next_vtable<'make>(prev_vtable: &'make mut NP_Vtable, memory: &'make NP_Memory) -> Result<usize, NP_Error> {
+    pub fn make_next_vtable<'make>(prev_vtable_addr: usize, memory: &'make NP_Memory) -> Result<usize, NP_Error> {
 
-        let vtable_addr = memory.malloc_borrow(&[0u8; 10])?;
+        let vtable_addr = memory.malloc_pointers(5, 0)?;
         
-        prev_vtable.set_next(vtable_addr as u16);
+        // the malloc can move the buffer, get the previous vtable after it
+        Self::get_vtable(prev_vtable_addr, memory).set_next(vtable_addr as u32);
 
         Ok(vtable_addr)
     }
@@ -116,12 +131,13 @@ impl<'table> NP_Table<'table> {
     }
 
     #[inline(always)]
-    pub fn get_vtable<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory) -> &'vtable mut NP_Vtable {
-        if v_table_addr > memory.read_bytes().len() { // attack
-            unsafe { &mut *(memory.write_bytes().as_ptr() as *mut NP_Vtable) }
+    pub fn get_vtable<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory<'vtable>) -> NP_Vtable_Ref<'vtable> {
+        let address = if v_table_addr > memory.read_bytes().len() { // attack
+            0
         } else { // normal operation
-            unsafe { &mut *(memory.write_bytes().as_ptr().add(v_table_addr) as *mut NP_Vtable) }
-        }
+            v_table_addr
+        };
+        vtable_bytes(memory, address)
     }
 
     #[inline(always)]
@@ -137,7 +153,7 @@ impl<'table> NP_Table<'table> {
                 let v_table =  self.index / 4; // which vtable
                 let v_table_idx = self.index % 4; // which index on the selected vtable
 
-                if self.v_table_index > v_table {
+                if v_table > self.v_table_index {
                     self.v_table_index = v_table;
                     match &self.v_table {
                         Some(vtable) => {
@@ -158,7 +174,7 @@ impl<'table> NP_Table<'table> {
                 self.index += 1;
 
                 if self.v_table_addr != 0 {
-                    let item_address = self.v_table_addr + (v_table_idx * 2);
+                    let item_address = self.v_table_addr + (v_table_idx * memory.addr_size.bytes());
                     Some((this_index, columns[this_index].1.as_str(), Some(NP_Cursor::new(item_address, columns[this_index].2, self.table.schema_addr))))
                 } else {
                     Some((this_index, columns[this_index].1.as_str(), None))
@@ -175,6 +191,14 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
     fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("table", NP_TypeKeys::Table) }
 
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+
+        // tables always have columns, a zero column count means the key hash comes next
+        let (key_hash, address) = if bytes[address + 1] == 0 {
+            (NP_Key_Hash::from_byte(bytes[address + 2]), address + 2)
+        } else {
+            (NP_Key_Hash::Murmur3, address)
+        };
+
         let column_len = bytes[address + 1];
 
         let mut parsed_columns: Vec<(u8, String,  NP_Schema_Addr)> = Vec::new();
@@ -192,7 +216,7 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
 
         let mut offset = address + 2;
 
-        let mut hash_map = NP_HashMap::new();
+        let mut hash_map = NP_HashMap::with_hash(key_hash);
 
         for x in 0..column_len as usize {
             let col_name_len = bytes[offset] as usize;
@@ -242,6 +266,12 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
 
         schema_json.insert("columns".to_owned(), NP_JSON::Array(columns));
 
+        if let NP_Parsed_Schema::Table { columns_mapped, .. } = &schema[address] {
+            if columns_mapped.key_hash() != NP_Key_Hash::Murmur3 {
+                schema_json.insert("key_hash".to_owned(), NP_JSON::String(columns_mapped.key_hash().name().to_owned()));
+            }
+        }
+
         Ok(NP_JSON::Dictionary(schema_json))
     }
  
@@ -258,23 +288,16 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
         let mut nex_vtable = c_value.get_addr_value() as usize;
 
         while nex_vtable > 0 {
-            acc_size += 10;
+            acc_size += 5 * memory.addr_size.bytes();
             let vtable = Self::get_vtable(nex_vtable, memory);
-            nex_vtable = vtable.get_next() as usize;
+            // vtables are always added after the one before them, links pointing back are damaged
+            nex_vtable = match vtable.get_next() as usize {
+                next if next > nex_vtable => next,
+                _ => 0
+            };
         }
 
-        let mut table = Self::new_iter(&cursor, memory);
-
-        while let Some((_index, _key, item)) = table.step_iter(memory) {
-            if let Some(real) = item {
-                let add_size = NP_Cursor::calc_size(&real, memory)?;
-                if add_size > 2 {
-                    // scalar cursor is part of vtable
-                    acc_size += add_size - 2;             
-                }
-            }         
-        }
-   
+        // values are counted by NP_Cursor::calc_size
         Ok(acc_size)
     }
 
@@ -284,22 +307,11 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
 
         if c_value.get_addr_value() == 0 { return NP_JSON::Null };
 
-        let mut json_map = JSMAP::new();
-
-        let mut table = Self::new_iter(&cursor, memory);
-
-        while let Some((_index, key, item)) = table.step_iter(memory) {
-            if let Some(real) = item {
-                json_map.insert(String::from(key), NP_Cursor::json_encode(&real, memory));  
-            } else {
-                json_map.insert(String::from(key), NP_JSON::Null);  
-            }            
-        }
-
-        NP_JSON::Dictionary(json_map)
+        // values are added by NP_Cursor::json_encode
+        NP_JSON::Dictionary(JSMAP::new())
     }
 
-    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, mut to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
 
         let from_value = from_cursor.get_value(from_memory);
 
@@ -307,39 +319,8 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
             return Ok(to_cursor) 
         }
 
-        to_cursor = Self::make_first_vtable(to_cursor, to_memory)?;
-        let to_cursor_value = to_cursor.get_value(to_memory);
-        let mut last_real_vtable = to_cursor_value.get_addr_value() as usize;
-        let mut last_vtable_idx = 0usize;
-
-        let c: Vec<(u8, String, usize)>;
-        let col_schemas = match &from_memory.schema[from_cursor.schema_addr] {
-            NP_Parsed_Schema::Table { columns, .. } => {
-                columns
-            },
-            _ => { c = Vec::new(); &c }
-        };
-
-        let mut table = Self::new_iter(&from_cursor, from_memory);
-
-        while let Some((idx, _key, item)) = table.step_iter(from_memory) {
-           if let Some(real) = item {
-
-                let v_table =  idx / 4; // which vtable
-                let v_table_idx = idx % 4; // which index on the selected vtable
-                
-                if last_vtable_idx < v_table {
-                    let vtable_data = Self::get_vtable(last_real_vtable, to_memory);
-                    last_real_vtable = Self::make_next_vtable(vtable_data, to_memory)?;
-                    last_vtable_idx += 1;
-                }
-
-                let item_addr = last_real_vtable + (v_table_idx * 2);
-                NP_Cursor::compact(real.clone(), from_memory, NP_Cursor::new(item_addr, col_schemas[idx].2, to_cursor.schema_addr), to_memory)?;
-            }         
-        }
-
-        Ok(to_cursor)
+        // values are copied by NP_Cursor::compact
+        Self::make_first_vtable(to_cursor, to_memory)
     }
 
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
@@ -355,7 +336,13 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
             columns_mapped: NP_HashMap::new()
         });
 
-        let mut columns_mapped = NP_HashMap::new();
+        let key_hash = match &json_schema["key_hash"] {
+            NP_JSON::String(x) => NP_Key_Hash::from_name(x)?,
+            NP_JSON::Null => NP_Key_Hash::Murmur3,
+            _ => return Err(NP_Error::Schema("Table key_hash property must be a string!".into()))
+        };
+
+        let mut columns_mapped = NP_HashMap::with_hash(key_hash);
 
         let mut columns: Vec<(u8, String, NP_Schema_Addr)> = Vec::new();
 
@@ -372,7 +359,7 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
                         _ => "".to_owned()
                     };
                     if column_name.len() > 255 {
-                        return Err(NP_Error::new("Table column names cannot be longer than 255 characters!"))
+                        return Err(NP_Error::Schema("Table column names cannot be longer than 255 characters!".into()))
                     }
 
                     let column_schema_addr = schema_parsed.len();
@@ -385,7 +372,7 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
                 }
             },
             _ => { 
-                return Err(NP_Error::new("Tables require a 'columns' property that is an array of schemas!"))
+                return Err(NP_Error::Schema("Tables require a 'columns' property that is an array of schemas!".into()))
             }
         }
 
@@ -397,11 +384,17 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
         };
 
         if column_data.len() > 255 {
-            return Err(NP_Error::new("Tables cannot have more than 255 columns!"))
+            return Err(NP_Error::Schema("Tables cannot have more than 255 columns!".into()))
         }
 
         if column_data.len() == 0 {
-            return Err(NP_Error::new("Tables must have at least one column!"))
+            return Err(NP_Error::Schema("Tables must have at least one column!".into()))
+        }
+
+        // the default hash isn't recorded so existing schemas keep their bytes
+        if key_hash != NP_Key_Hash::Murmur3 {
+            schema_bytes.push(0);
+            schema_bytes.push(key_hash.to_byte());
         }
 
         // number of columns
@@ -414,7 +407,7 @@ impl<'value> NP_Value<'value> for NP_Table<'value> {
             schema_bytes.extend(bytes);
 
             if col.1.len() > u16::max as usize {
-                return Err(NP_Error::new("Schema overflow error!"))
+                return Err(NP_Error::Schema("Schema overflow error!".into()))
             }
             
             // column type
@@ -442,6 +435,32 @@ fn schema_parsing_works() -> Result<(), NP_Error> {
     Ok(())
 }
 
+#[test]
+fn key_hash_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"table\",\"columns\":[[\"age\",{\"type\":\"uint8\"}],[\"name\",{\"type\":\"string\"}]],\"key_hash\":\"siphash\"}";
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    assert_eq!(&factory.compile_schema()[0..4], &[NP_TypeKeys::Table as u8, 0, NP_Key_Hash::SipHash.to_byte(), 2]);
+
+    // the hash is recorded in compiled schemas
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, compiled.schema.to_json()?.stringify());
+
+    let mut buffer = compiled.empty_buffer(None);
+    buffer.set(&["name"], "hello")?;
+    buffer.set(&["age"], 20u8)?;
+    let buffer = factory.open_buffer(buffer.close());
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("hello"));
+    assert_eq!(buffer.get::<u8>(&["age"])?, Some(20));
+
+    // the default hash doesn't change schema bytes
+    let factory = crate::NP_Factory::new("{\"type\":\"table\",\"key_hash\":\"murmur3\",\"columns\":[[\"age\",{\"type\":\"uint8\"}]]}")?;
+    assert_eq!(factory.compile_schema()[1], 1);
+    assert!(crate::NP_Factory::new("{\"type\":\"table\",\"key_hash\":\"md5\",\"columns\":[[\"age\",{\"type\":\"uint8\"}]]}").is_err());
+
+    Ok(())
+}
+
 #[test]
 fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     let schema = "{\"type\":\"table\",\"columns\":[[\"age\",{\"type\":\"uint8\"}],[\"name\",{\"type\":\"string\"}]]}";
@@ -1,7 +1,5 @@
-use alloc::string::String;
 use crate::utils::opt_err;
-use crate::{ pointer::NP_Vtable};
-use core::hint::unreachable_unchecked;
+use crate::pointer::{NP_Vtable_Ref, vtable_bytes};
 
 use crate::{json_flex::JSMAP, pointer::{NP_Cursor}};
 use crate::pointer::{NP_Value};
@@ -18,7 +16,7 @@ use alloc::string::ToString;
 #[derive(Debug)]
 pub struct NP_Tuple<'tuple> {
     index: usize,
-    v_table: Option<&'tuple mut NP_Vtable>,
+    v_table: Option<NP_Vtable_Ref<'tuple>>,
     v_table_addr: usize,
     v_table_index: usize,
     table: NP_Cursor
@@ -60,7 +58,7 @@ impl<'tuple> NP_Tuple<'tuple> {
                     let next_vtable = this_vtable.get_next();
 
                     if next_vtable == 0 {
-                        vtable_address = Self::make_next_vtable(this_vtable, memory)?;
+                        vtable_address = Self::make_next_vtable(vtable_address, memory)?;
                     } else {
                         vtable_address = next_vtable as usize;
                     }
@@ -68,7 +66,7 @@ impl<'tuple> NP_Tuple<'tuple> {
                     seek_vtable += 1;
                 }
 
-                let item_address = vtable_address + (v_table_idx * 2);
+                let item_address = vtable_address + (v_table_idx * memory.addr_size.bytes());
 
                 Ok(Some(NP_Cursor::new(item_address, column_schema_data, tuple_cursor.schema_addr)))
              
@@ -79,10 +77,10 @@ impl<'tuple> NP_Tuple<'tuple> {
 
     pub fn make_first_vtable<'make>(table_cursor: NP_Cursor, memory: &'make NP_Memory) -> Result<NP_Cursor, NP_Error> {
 
-        let first_vtable_addr = memory.malloc_borrow(&[0u8; 10])?;
+        let first_vtable_addr = memory.malloc_pointers(5, 0)?;
         
         let table_value = table_cursor.get_value(memory);
-        table_value.set_addr_value(first_vtable_addr as u16);
+        table_value.set_addr_value(first_vtable_addr as u32);
 
 
         match &memory.schema[table_cursor.schema_addr] {
@@ -90,10 +88,9 @@ impl<'tuple> NP_Tuple<'tuple> {
                 if *sortable {
                     // make all the vtables we'll need forever
                     let mut v_table_capacity = 4usize;
-                    let mut vtable = Self::get_vtable(first_vtable_addr, memory);
+                    let mut vtable_addr = first_vtable_addr;
                     while v_table_capacity < values.len() {
-                        let next_addr = Self::make_next_vtable(vtable, memory)?;
-                        vtable = Self::get_vtable(next_addr, memory);
+                        vtable_addr = Self::make_next_vtable(vtable_addr, memory)?;
                         v_table_capacity += 4;
                     }
 
@@ -111,11 +108,12 @@ impl<'tuple> NP_Tuple<'tuple> {
         Ok(table_cursor)
     }
 
-    pub fn make_next_vtable<'make>(prev_vtable: &'make mut NP_Vtable, memory: &'make NP_Memory) -> Result<usize, NP_Error> {
+    pub fn make_next_vtable<'make>(prev_vtable_addr: usize, memory: &'make NP_Memory) -> Result<usize, NP_Error> {
 
-        let vtable_addr = memory.malloc_borrow(&[0u8; 10])?;
+        let vtable_addr = memory.malloc_pointers(5, 0)?;
         
-        prev_vtable.set_next(vtable_addr as u16);
+        // the malloc can move the buffer, get the previous vtable after it
+        Self::get_vtable(prev_vtable_addr, memory).set_next(vtable_addr as u32);
 
         Ok(vtable_addr)
     }
@@ -140,12 +138,13 @@ impl<'tuple> NP_Tuple<'tuple> {
     }
 
     #[inline(always)]
-    pub fn get_vtable<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory) -> &'vtable mut NP_Vtable {
-        if v_table_addr > memory.read_bytes().len() { // attack
-            unsafe { &mut *(memory.write_bytes().as_ptr() as *mut NP_Vtable) }
+    pub fn get_vtable<'vtable>(v_table_addr: usize, memory: &'vtable NP_Memory<'vtable>) -> NP_Vtable_Ref<'vtable> {
+        let address = if v_table_addr > memory.read_bytes().len() { // attack
+            0
         } else { // normal operation
-            unsafe { &mut *(memory.write_bytes().as_ptr().add(v_table_addr) as *mut NP_Vtable) }
-        }
+            v_table_addr
+        };
+        vtable_bytes(memory, address)
     }
 
     pub fn step_iter(&mut self, memory: &'tuple NP_Memory) -> Option<(usize, Option<NP_Cursor>)> {
@@ -160,7 +159,7 @@ impl<'tuple> NP_Tuple<'tuple> {
                 let v_table =  self.index / 4; // which vtable
                 let v_table_idx = self.index % 4; // which index on the selected vtable
 
-                if self.v_table_index > v_table {
+                if v_table > self.v_table_index {
                     self.v_table_index = v_table;
                     match &self.v_table {
                         Some(vtable) => {
@@ -181,7 +180,7 @@ impl<'tuple> NP_Tuple<'tuple> {
                 self.index += 1;
 
                 if self.v_table_addr != 0 {
-                    let item_address = self.v_table_addr + (v_table_idx * 2);
+                    let item_address = self.v_table_addr + (v_table_idx * memory.addr_size.bytes());
                     Some((this_index, Some(NP_Cursor::new(item_address, values[this_index], self.table.schema_addr))))
                 } else {
                     Some((this_index, None))
@@ -235,23 +234,16 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
         let mut nex_vtable = c_value.get_addr_value() as usize;
 
         while nex_vtable > 0 {
-            acc_size += 10;
+            acc_size += 5 * memory.addr_size.bytes();
             let vtable = Self::get_vtable(nex_vtable, memory);
-            nex_vtable = vtable.get_next() as usize;
+            // vtables are always added after the one before them, links pointing back are damaged
+            nex_vtable = match vtable.get_next() as usize {
+                next if next > nex_vtable => next,
+                _ => 0
+            };
         }
 
-        let mut table = Self::new_iter(&cursor, memory);
-
-        while let Some((_index, item)) = table.step_iter(memory) {
-            if let Some(real) = item {
-                let add_size = NP_Cursor::calc_size(&real, memory)?;
-                if add_size > 2 {
-                    // scalar cursor is part of vtable
-                    acc_size += add_size - 2;             
-                }
-            }            
-        }
-   
+        // values are counted by NP_Cursor::calc_size
         Ok(acc_size)
     }
 
@@ -261,23 +253,11 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
 
         if c_value.get_addr_value() == 0 { return NP_JSON::Null };
 
-        let mut json_list = Vec::new();
-
-        let mut table = Self::new_iter(&cursor, memory);
-
-        while let Some((_idx, item)) = table.step_iter(memory) {
-            if let Some(real) = item {
-                json_list.push(NP_Cursor::json_encode(&real, memory));  
-            } else {
-                json_list.push(NP_JSON::Null);  
-            }
-        }
-
-
-        NP_JSON::Array(json_list)
+        // values are added by NP_Cursor::json_encode
+        NP_JSON::Array(Vec::new())
     }
 
-    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, mut to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
+    fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
 
         let from_value = from_cursor.get_value(from_memory);
 
@@ -285,42 +265,10 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
             return Ok(to_cursor) 
         }
 
-        to_cursor = Self::make_first_vtable(to_cursor, to_memory)?;
-        let to_cursor_value = to_cursor.get_value(to_memory);
-        let mut last_real_vtable = to_cursor_value.get_addr_value() as usize;
-        let mut last_vtable_idx = 0usize;
-
-        let c: Vec<(u8, String, usize)>;
-        let col_schemas = match &from_memory.schema[from_cursor.schema_addr] {
-            NP_Parsed_Schema::Table { columns, .. } => {
-                columns
-            },
-            _ => { c = Vec::new(); &c }
-        };
-
-        let mut table = Self::new_iter(&from_cursor, from_memory);
-
-        while let Some((idx, item)) = table.step_iter(from_memory) {
-            if let Some(real) = item {
-
-                let v_table =  idx / 4; // which vtable
-                let v_table_idx = idx % 4; // which index on the selected vtable
-                
-                if last_vtable_idx < v_table {
-                    let vtable_data = Self::get_vtable(last_real_vtable, to_memory);
-                    last_real_vtable = Self::make_next_vtable(vtable_data, to_memory)?;
-                    last_vtable_idx += 1;
-                }
-
-                let item_addr = last_real_vtable + (v_table_idx * 2);
-                NP_Cursor::compact(real.clone(), from_memory, NP_Cursor::new(item_addr, col_schemas[idx].2, to_cursor.schema_addr), to_memory)?;
-            }            
-        }
-
-        Ok(to_cursor)
+        // values are copied by NP_Cursor::compact
+        Self::make_first_vtable(to_cursor, to_memory)
     }
 
-
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
 
     
@@ -358,13 +306,13 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
                     let (is_sortable, schema_bytes, _schema ) = NP_Schema::from_json(working_schema, &Box::new(col.clone()))?;
                     working_schema = _schema;
                     if sorted && is_sortable == false {
-                        return Err(NP_Error::new("All children of a sorted tuple must be sortable items!"))
+                        return Err(NP_Error::Schema("All children of a sorted tuple must be sortable items!".into()))
                     }
                     column_schemas.push(schema_bytes);
                 }
             },
             _ => { 
-                return Err(NP_Error::new("Tuples require a 'values' property that is an array of schemas!"))
+                return Err(NP_Error::Schema("Tuples require a 'values' property that is an array of schemas!".into()))
             }
         }
         
@@ -375,7 +323,7 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
         };
 
         if column_schemas.len() > 255 {
-            return Err(NP_Error::new("Tuples cannot have more than 255 values!"))
+            return Err(NP_Error::Schema("Tuples cannot have more than 255 values!".into()))
         }
 
         // number of schema values
@@ -384,7 +332,7 @@ impl<'value> NP_Value<'value> for NP_Tuple<'value> {
         for col in column_schemas {
 
             if col.len() > u16::max as usize {
-                return Err(NP_Error::new("Schema overflow error!"))
+                return Err(NP_Error::Schema("Schema overflow error!".into()))
             }
             
             // column type
@@ -0,0 +1,178 @@
+//! Compress closed buffers with zlib or zstd
+//!
+//! NoProto buffers leave room for values to be changed in place, so closed buffers usually shrink a lot when they're compressed before being stored or sent.  With the `zlib` or `zstd` feature enabled, `NP_Buffer::close_compressed` compresses the closed buffer and `NP_Factory::open_compressed` opens it again.
+//!
+//! Compressed bytes start with a 3 byte header, the letters `NP` and the codec id (`1` for zlib, `2` for zstd), followed by a standard [zlib](https://tools.ietf.org/html/rfc1950) stream or [zstd](https://tools.ietf.org/html/rfc8878) frame.  `open_compressed` reads the header to pick the codec, so readers don't need to know how the buffer was compressed.  The `zlib` feature uses the [miniz_oxide](https://crates.io/crates/miniz_oxide) crate and the `zstd` feature uses the [ruzstd](https://crates.io/crates/ruzstd) crate, both are pure Rust and work without `std`.
+//!
+//! The zstd encoder works at the speed and ratio of zstd level 1.  Any zstd frame without a dictionary can be opened.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! # #[cfg(feature = "zlib")] {
+//! use no_proto::compression::NP_Compression;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Bill Kerman")?;
+//! for x in 0..20 {
+//!     new_buffer.set(&["tags", x.to_string().as_str()], "pilot")?;
+//! }
+//! let closed_size = new_buffer.calc_bytes()?.current_buffer;
+//!
+//! let compressed: Vec<u8> = new_buffer.close_compressed(NP_Compression::Zlib);
+//! assert!(compressed.len() < closed_size);
+//!
+//! let user = factory.open_compressed(&compressed)?;
+//! assert_eq!(user.get(&["name"])?, Some("Bill Kerman"));
+//! assert_eq!(user.get(&["tags", "19"])?, Some("pilot"));
+//! # }
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+#[cfg(feature = "zlib")]
+mod zlib;
+#[cfg(feature = "zstd")]
+mod zstd;
+
+use crate::error::NP_Error;
+use alloc::vec::Vec;
+
+/// Every compressed buffer starts with these bytes, followed by the codec id
+const HEADER: [u8; 2] = *b"NP";
+
+const ZLIB_ID: u8 = 1;
+const ZSTD_ID: u8 = 2;
+
+/// Codecs for `NP_Buffer::close_compressed`
+///
+/// Check out documentation [here](../compression/index.html).
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_Compression {
+    /// zlib stream, needs the `zlib` feature
+    #[cfg(feature = "zlib")]
+    Zlib,
+    /// zstd frame, needs the `zstd` feature
+    #[cfg(feature = "zstd")]
+    Zstd
+}
+
+/// Compress closed buffer bytes and put the codec header in front
+pub(crate) fn compress(bytes: &[u8], codec: NP_Compression) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len() / 2 + 16);
+    out.extend_from_slice(&HEADER);
+    match codec {
+        #[cfg(feature = "zlib")]
+        NP_Compression::Zlib => {
+            out.push(ZLIB_ID);
+            zlib::compress(bytes, &mut out);
+        },
+        #[cfg(feature = "zstd")]
+        NP_Compression::Zstd => {
+            out.push(ZSTD_ID);
+            zstd::compress(bytes, &mut out);
+        }
+    }
+    out
+}
+
+/// Read the codec header and decompress the rest
+pub(crate) fn decompress(bytes: &[u8]) -> Result<Vec<u8>, NP_Error> {
+    if bytes.len() < 3 || bytes[0..2] != HEADER {
+        return Err(NP_Error::Damaged("Bytes are not a compressed buffer!".into()));
+    }
+    match bytes[2] {
+        #[cfg(feature = "zlib")]
+        ZLIB_ID => zlib::decompress(&bytes[3..]),
+        #[cfg(not(feature = "zlib"))]
+        ZLIB_ID => Err(NP_Error::new("Buffer was compressed with zlib, enable the zlib feature to open it!")),
+        #[cfg(feature = "zstd")]
+        ZSTD_ID => zstd::decompress(&bytes[3..]),
+        #[cfg(not(feature = "zstd"))]
+        ZSTD_ID => Err(NP_Error::new("Buffer was compressed with zstd, enable the zstd feature to open it!")),
+        _ => Err(NP_Error::Damaged("Unknown compression codec!".into()))
+    }
+}
+
+#[test]
+fn compression_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use alloc::string::ToString;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id", {"type": "u32"}],
+            ["names", {"type": "list", "of": {"type": "string"}}],
+            ["blob", {"type": "bytes"}]
+        ]
+    }"#)?;
+
+    let mut codecs: Vec<NP_Compression> = Vec::new();
+    #[cfg(feature = "zlib")]
+    codecs.push(NP_Compression::Zlib);
+    #[cfg(feature = "zstd")]
+    codecs.push(NP_Compression::Zstd);
+
+    // pseudo random bytes that don't compress
+    let mut seed = 17u32;
+    let noise: Vec<u8> = (0..70000).map(|_| { seed = seed.wrapping_mul(1103515245).wrapping_add(12345); (seed >> 16) as u8 }).collect();
+
+    for codec in codecs {
+        let empty = factory.empty_buffer(None);
+        let closed = empty.close();
+        let compressed = factory.empty_buffer(None).close_compressed(codec);
+        assert_eq!(decompress(&compressed)?, closed);
+
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&["id"], 12345u32)?;
+        for x in 0..200 {
+            buffer.set(&["names", x.to_string().as_str()], "the same few words over and over")?;
+        }
+        buffer.set(&["blob"], &noise[..20000])?;
+        let closed = buffer.close();
+
+        let compressed = compress(&closed, codec);
+        assert!(compressed.len() < closed.len());
+        assert_eq!(decompress(&compressed)?, closed);
+
+        let buffer = factory.open_compressed(&compressed)?;
+        assert_eq!(buffer.get(&["id"])?, Some(12345u32));
+        assert_eq!(buffer.get(&["names", "199"])?, Some("the same few words over and over"));
+        assert_eq!(buffer.get::<&[u8]>(&["blob"])?, Some(&noise[..20000]));
+
+        // long runs, repeats across the window and bytes that don't compress at all
+        let mut data: Vec<u8> = alloc::vec![7u8; 300000];
+        data.extend_from_slice(&noise);
+        data.extend_from_slice(&noise[..40000]);
+        data.extend((0..200000u32).map(|x| (x % 251) as u8));
+        assert_eq!(decompress(&compress(&data, codec))?, data);
+        assert_eq!(decompress(&compress(&noise, codec))?, noise);
+        assert_eq!(decompress(&compress(&[], codec))?, Vec::<u8>::new());
+        assert_eq!(decompress(&compress(&[1, 2], codec))?, alloc::vec![1, 2]);
+
+        // damaged bytes fail instead of opening something else
+        let mut damaged = compressed.clone();
+        let len = damaged.len();
+        damaged[len - 2] ^= 0x10;
+        assert!(decompress(&damaged).is_err());
+        assert!(decompress(&compressed[..(compressed.len() / 2)]).is_err());
+    }
+
+    assert!(decompress(&[]).is_err());
+    assert!(decompress(b"NP").is_err());
+    assert!(decompress(b"NP\x09").is_err());
+    assert!(factory.open_compressed(&factory.empty_buffer(None).close()).is_err());
+
+    Ok(())
+}
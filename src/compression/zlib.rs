@@ -0,0 +1,21 @@
+//! zlib streams (RFC 1950) holding deflate data (RFC 1951), through the `miniz_oxide` crate
+
+use crate::error::NP_Error;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// miniz compression level, the same default the zlib library uses
+const LEVEL: u8 = 6;
+
+pub(crate) fn compress(data: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(&miniz_oxide::deflate::compress_to_vec_zlib(data, LEVEL));
+}
+
+/// Decompress a whole zlib stream, the checksum at the end is checked too
+pub(crate) fn decompress(bytes: &[u8]) -> Result<Vec<u8>, NP_Error> {
+    miniz_oxide::inflate::decompress_to_vec_zlib(bytes).map_err(|err| {
+        let mut message = String::from("zlib stream is damaged: ");
+        message.push_str(&err.to_string());
+        NP_Error::Damaged(message)
+    })
+}
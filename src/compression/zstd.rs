@@ -0,0 +1,56 @@
+//! zstd frames (RFC 8878), through the `ruzstd` crate
+
+use crate::error::NP_Error;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Display;
+use ruzstd::decoding::{BlockDecodingStrategy, FrameDecoder};
+use ruzstd::decoding::errors::{FrameDecoderError, ReadFrameHeaderError};
+use ruzstd::encoding::CompressionLevel;
+
+fn damaged(err: impl Display) -> NP_Error {
+    let mut message = String::from("zstd frame is damaged: ");
+    message.push_str(&err.to_string());
+    NP_Error::Damaged(message)
+}
+
+/// Compress into one frame with a content checksum
+pub(crate) fn compress(data: &[u8], out: &mut Vec<u8>) {
+    ruzstd::encoding::compress(data, out, CompressionLevel::Fastest);
+}
+
+/// Decompress every frame in the bytes, skippable frames are ignored
+pub(crate) fn decompress(bytes: &[u8]) -> Result<Vec<u8>, NP_Error> {
+    if bytes.is_empty() {
+        return Err(NP_Error::Damaged("Bytes are not a zstd frame!".into()));
+    }
+
+    let mut source = bytes;
+    let mut decoder = FrameDecoder::new();
+    let mut out: Vec<u8> = Vec::with_capacity(bytes.len() * 4);
+
+    while !source.is_empty() {
+        match decoder.reset(&mut source) {
+            Ok(()) => { },
+            Err(FrameDecoderError::ReadFrameHeaderError(ReadFrameHeaderError::SkipFrame { length, .. })) => {
+                source = source.get((length as usize)..).ok_or_else(|| damaged("skippable frame is cut off"))?;
+                continue;
+            },
+            Err(err) => return Err(damaged(err))
+        }
+
+        decoder.decode_blocks(&mut source, BlockDecodingStrategy::All).map_err(damaged)?;
+        if !decoder.is_finished() {
+            return Err(damaged("frame is cut off"));
+        }
+        decoder.collect_to_writer(&mut out).map_err(damaged)?;
+
+        if let Some(checksum) = decoder.get_checksum_from_data() {
+            if decoder.get_calculated_checksum() != Some(checksum) {
+                return Err(damaged("checksum doesn't match"));
+            }
+        }
+    }
+
+    Ok(out)
+}
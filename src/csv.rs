@@ -0,0 +1,146 @@
+//! CSV helpers for `NP_Buffer::to_csv`
+
+use crate::error::NP_Error;
+use crate::owned::NP_Value_Owned;
+use crate::pointer::date::NP_Date;
+use crate::pointer::dec::NP_Dec;
+use crate::schema::NP_Parsed_Schema;
+use alloc::string::String;
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// A single CSV column, `path` selects the value inside each row
+pub(crate) struct CSV_Column {
+    pub name: String,
+    path: Vec<String>,
+    part: CSV_Part
+}
+
+enum CSV_Part {
+    Whole,
+    Lat,
+    Lng
+}
+
+/// Find the CSV columns for the table schema at `address`, nested tables are flattened into `parent.child` columns
+pub(crate) fn columns(schema: &Vec<NP_Parsed_Schema>, address: usize, prefix: &str, path: &mut Vec<String>, result: &mut Vec<CSV_Column>) -> Result<(), NP_Error> {
+
+    let columns = match &schema[address] {
+        NP_Parsed_Schema::Table { columns, .. } => columns,
+        _ => return Err(NP_Error::SchemaMismatch("CSV export needs a list of tables!".into()))
+    };
+
+    for (_index, column, addr) in columns {
+        let mut name = prefix.to_owned();
+        name.push_str(column);
+        path.push(column.clone());
+        match &schema[*addr] {
+            NP_Parsed_Schema::Table { .. } => {
+                name.push('.');
+                self::columns(schema, *addr, &name, path, result)?;
+            },
+            NP_Parsed_Schema::Geo { .. } => {
+                let mut lat = name.clone();
+                lat.push_str(".lat");
+                result.push(CSV_Column { name: lat, path: path.clone(), part: CSV_Part::Lat });
+                name.push_str(".lng");
+                result.push(CSV_Column { name, path: path.clone(), part: CSV_Part::Lng });
+            },
+            _ => result.push(CSV_Column { name, path: path.clone(), part: CSV_Part::Whole })
+        }
+        path.pop();
+    }
+
+    Ok(())
+}
+
+/// Quote a cell if it contains a delimiter, quote or line break
+pub(crate) fn escape(cell: &str, out: &mut String) {
+    if cell.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        out.push('"');
+        out.push_str(&cell.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(cell);
+    }
+}
+
+fn find<'row>(row: &'row NP_Value_Owned, path: &[String]) -> Option<&'row NP_Value_Owned> {
+    match path.first() {
+        None => Some(row),
+        Some(key) => match row {
+            NP_Value_Owned::Table(entries) => entries.iter().find(|(name, _value)| name == key).and_then(|(_name, value)| find(value, &path[1..])),
+            _ => None
+        }
+    }
+}
+
+/// Write a decimal like `1.50` using the exponent from the value
+fn format_dec(num: i64, exp: u8, out: &mut String) {
+    write!(out, "{}", NP_Dec::new(num, exp)).unwrap_or(())
+}
+
+/// Write milliseconds since the unix epoch as an ISO 8601 UTC timestamp
+fn format_date(ms: u64, out: &mut String) {
+    write!(out, "{}", NP_Date::new(ms)).unwrap_or(())
+}
+
+/// Write one row of cells followed by a newline
+pub(crate) fn write_row(row: Option<&NP_Value_Owned>, columns: &Vec<CSV_Column>, out: &mut String) {
+
+    for (index, column) in columns.iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+
+        let value = match row.and_then(|row| find(row, &column.path)) {
+            Some(x) => x,
+            None => continue
+        };
+
+        match (value, &column.part) {
+            (NP_Value_Owned::Geo(x), CSV_Part::Lat) => write!(out, "{}", x.lat).unwrap_or(()),
+            (NP_Value_Owned::Geo(x), CSV_Part::Lng) => write!(out, "{}", x.lng).unwrap_or(()),
+            (NP_Value_Owned::Null, _) => { },
+            (NP_Value_Owned::String(x), _) | (NP_Value_Owned::Enum(x), _) => escape(x, out),
+            (NP_Value_Owned::Bytes(x), _) => for byte in x { write!(out, "{:02x}", byte).unwrap_or(()) },
+            (NP_Value_Owned::Int8(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Int16(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Int32(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Int64(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Uint8(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Uint16(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Uint32(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Uint64(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Float(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Double(x), _) => write!(out, "{}", x).unwrap_or(()),
+            (NP_Value_Owned::Bool(x), _) => out.push_str(if *x { "true" } else { "false" }),
+            (NP_Value_Owned::Dec(x), _) => format_dec(x.num, x.exp, out),
+            (NP_Value_Owned::Date(x), _) => format_date(x.value, out),
+            (NP_Value_Owned::Uuid(x), _) => out.push_str(&x.to_string()),
+            (NP_Value_Owned::Ulid(x), _) => out.push_str(&x.to_string()),
+            // collections don't fit in a single cell, write them as JSON
+            (other, _) => escape(&other.to_json().stringify(), out)
+        }
+    }
+
+    out.push('\n');
+}
+
+#[test]
+fn csv_formatting_works() {
+    let mut out = String::new();
+    format_dec(-5, 3, &mut out);
+    out.push(' ');
+    format_dec(12345, 2, &mut out);
+    out.push(' ');
+    format_dec(7, 0, &mut out);
+    out.push(' ');
+    format_date(1_604_965_249_484, &mut out);
+    out.push(' ');
+    format_date(0, &mut out);
+    out.push(' ');
+    escape("say \"hi\", then\nleave", &mut out);
+    assert_eq!(out, "-0.005 123.45 7 2020-11-09T23:40:49.484Z 1970-01-01T00:00:00.000Z \"say \"\"hi\"\", then\nleave\"");
+}
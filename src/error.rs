@@ -1,4 +1,32 @@
 //! Primary error type used by the library
+//!
+//! Every error has a kind that can be matched on and a message for people to read.  The message is what `Display` prints, the kind is stable across releases and also has a numeric `code` for logs and other languages.  With the `std` feature `NP_Error` is also a `std::error::Error`, so `?` can turn it into a `Box<dyn Error>`.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//!
+//! match new_buffer.set(&["name"], 20u32) {
+//!     Err(NP_Error::SchemaMismatch(message)) => assert!(message.starts_with("TypeError")),
+//!     _ => panic!()
+//! }
+//!
+//! let err = new_buffer.list_push(&["name"], "Jeb").unwrap_err();
+//! assert_eq!(err.code(), 2);
+//! assert_eq!(err.to_string(), "Trying to push onto non list item!");
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
 
 use alloc::string::FromUtf8Error;
 use alloc::string::String;
@@ -6,16 +34,41 @@ use alloc::borrow::ToOwned;
 use alloc::string::ToString;
 
 /// The error type used for errors in this library
-#[derive(Debug)]
-pub struct NP_Error {
-    /// The message of this error
-    pub message: String
+///
+/// Each kind holds the message of the error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NP_Error {
+    /// Errors without a more specific kind
+    Other(String),
+    /// The schema couldn't be parsed
+    Schema(String),
+    /// The value or operation doesn't match the type in the schema
+    SchemaMismatch(String),
+    /// The path isn't in the schema or can't be followed
+    BadPath(String),
+    /// The buffer is read only or borrowed
+    ReadOnly(String),
+    /// There isn't any room left in the buffer
+    BufferFull(String),
+    /// An address, index or length is past what the buffer can hold
+    OutOfBounds(String),
+    /// Bytes aren't valid UTF-8
+    Utf8(String),
+    /// Text or bytes in another format couldn't be parsed
+    Parse(String),
+    /// The value isn't allowed, like a string that's too long or an unknown option
+    InvalidValue(String),
+    /// Bytes were damaged, changed or made by something else
+    Damaged(String),
+    /// Reading or writing failed
+    Io(String)
 }
 
 impl NP_Error {
-    /// Generate a new error with a specific message
+    /// Generate a new error with a specific message, the kind is `Other`
     pub fn new<S: AsRef<str>>(message: S) -> Self {
-        NP_Error { message: message.as_ref().to_owned() }
+        NP_Error::Other(message.as_ref().to_owned())
     }
     /// Convert an option to an error type
     pub fn unwrap<T>(value: Option<T>) -> Result<T, NP_Error> {
@@ -24,22 +77,128 @@ impl NP_Error {
             None => Err(NP_Error::new("Missing Value in option!"))
         }
     }
+    /// The message of this error
+    pub fn message(&self) -> &str {
+        match self {
+            NP_Error::Other(x) | NP_Error::Schema(x) | NP_Error::SchemaMismatch(x) | NP_Error::BadPath(x) | NP_Error::ReadOnly(x) | NP_Error::BufferFull(x) | NP_Error::OutOfBounds(x) | NP_Error::Utf8(x) | NP_Error::Parse(x) | NP_Error::InvalidValue(x) | NP_Error::Damaged(x) | NP_Error::Io(x) => x
+        }
+    }
+    /// Take the message out of this error
+    pub fn into_message(self) -> String {
+        match self {
+            NP_Error::Other(x) | NP_Error::Schema(x) | NP_Error::SchemaMismatch(x) | NP_Error::BadPath(x) | NP_Error::ReadOnly(x) | NP_Error::BufferFull(x) | NP_Error::OutOfBounds(x) | NP_Error::Utf8(x) | NP_Error::Parse(x) | NP_Error::InvalidValue(x) | NP_Error::Damaged(x) | NP_Error::Io(x) => x
+        }
+    }
+    /// Number for the kind of this error, these never change between releases
+    pub fn code(&self) -> u16 {
+        match self {
+            NP_Error::Other(_)          => 0,
+            NP_Error::Schema(_)         => 1,
+            NP_Error::SchemaMismatch(_) => 2,
+            NP_Error::BadPath(_)        => 3,
+            NP_Error::ReadOnly(_)       => 4,
+            NP_Error::BufferFull(_)     => 5,
+            NP_Error::OutOfBounds(_)    => 6,
+            NP_Error::Utf8(_)           => 7,
+            NP_Error::Parse(_)          => 8,
+            NP_Error::InvalidValue(_)   => 9,
+            NP_Error::Damaged(_)        => 10,
+            NP_Error::Io(_)             => 11
+        }
+    }
+    /// Name of the kind of this error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NP_Error::Other(_)          => "Other",
+            NP_Error::Schema(_)         => "Schema",
+            NP_Error::SchemaMismatch(_) => "SchemaMismatch",
+            NP_Error::BadPath(_)        => "BadPath",
+            NP_Error::ReadOnly(_)       => "ReadOnly",
+            NP_Error::BufferFull(_)     => "BufferFull",
+            NP_Error::OutOfBounds(_)    => "OutOfBounds",
+            NP_Error::Utf8(_)           => "Utf8",
+            NP_Error::Parse(_)          => "Parse",
+            NP_Error::InvalidValue(_)   => "InvalidValue",
+            NP_Error::Damaged(_)        => "Damaged",
+            NP_Error::Io(_)             => "Io"
+        }
+    }
+}
+
+impl core::fmt::Display for NP_Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.message())
+    }
 }
 
 impl From<FromUtf8Error> for NP_Error {
     fn from(err: FromUtf8Error) -> NP_Error {
-        NP_Error::new(err.to_string().as_str())
+        NP_Error::Utf8(err.to_string())
     }
 }
 
 impl From<core::num::ParseFloatError> for NP_Error {
     fn from(err: core::num::ParseFloatError) -> NP_Error {
-        NP_Error::new(err.to_string().as_str())
+        NP_Error::Parse(err.to_string())
     }
 }
 
 impl From<core::num::ParseIntError> for NP_Error {
     fn from(err: core::num::ParseIntError) -> NP_Error {
-        NP_Error::new(err.to_string().as_str())
+        NP_Error::Parse(err.to_string())
+    }
+}
+
+impl From<core::str::Utf8Error> for NP_Error {
+    fn from(err: core::str::Utf8Error) -> NP_Error {
+        NP_Error::Utf8(err.to_string())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NP_Error {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for NP_Error {
+    fn from(err: std::io::Error) -> NP_Error {
+        NP_Error::Io(err.to_string())
+    }
+}
+
+#[test]
+fn error_kinds_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string", "size": 4}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0"], "hello")?;
+
+    assert_eq!(buffer.set(&["name"], "hello").unwrap_err().kind(), "BadPath");
+    assert_eq!(buffer.list_splice(&[], 300, &[crate::json_flex::NP_JSON::Null]).unwrap_err().code(), 6);
+    assert!(matches!(buffer.set(&["1"], 20u32), Err(NP_Error::SchemaMismatch(_))));
+
+    let closed = buffer.close();
+    let mut read_only = factory.open_buffer_read_only(&closed);
+    assert!(matches!(read_only.set(&["1"], "hi"), Err(NP_Error::ReadOnly(_))));
+
+    let not_utf8 = alloc::vec![0xffu8];
+    assert!(matches!(NP_Error::from(core::str::from_utf8(&not_utf8).unwrap_err()), NP_Error::Utf8(_)));
+    assert_eq!(NP_Error::new("plain").to_string(), "plain");
+    assert_eq!(NP_Error::new("plain").kind(), "Other");
+
+    #[cfg(feature = "std")]
+    {
+        use std::boxed::Box;
+        use std::error::Error;
+
+        let parse = |schema: &str| -> Result<(), Box<dyn Error>> {
+            crate::NP_Factory::new(schema)?;
+            Ok(())
+        };
+        let err = parse(r#"{"type": "nothing"}"#).unwrap_err();
+        assert_eq!(err.to_string(), err.downcast_ref::<NP_Error>().map(|x| x.to_string()).unwrap_or_default());
+        assert!(err.source().is_none());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,358 @@
+//! Extend schemas at runtime
+//!
+//! Plugin systems often need to add to a record after the schema has shipped.  `NP_Schema::evolve` starts an `NP_Schema_Evolution` with steps that keep old buffers readable, `build` applies the steps and returns a new factory for the evolved schema.
+//!
+//! - `add_column` adds a column to the end of a table, old buffers read the column's default value.
+//! - `add_choice` adds a choice to the end of an enum, the existing choices keep their index.
+//! - `widen` changes an integer into a larger integer type that can hold every old value.
+//!
+//! Evolved factories stamp their schema fingerprint into new buffers.  `open_buffer_checked` and `open_buffer_untrusted` of the evolved factory open buffers of the old schema too: added columns and choices don't change the layout, so those buffers are opened as they are, while buffers with widened integers are copied into a new buffer of the evolved schema.  Plain `open_buffer` doesn't check the schema, so with `widen` it can only be used on buffers of the evolved factory.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::option::NP_Enum;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",  {"type": "string"}],
+//!         ["age",   {"type": "u8"}],
+//!         ["color", {"type": "option", "choices": ["red", "green"]}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut old_buffer = factory.empty_buffer(None);
+//! old_buffer.set(&["name"], "Jeb")?;
+//! old_buffer.set(&["age"], 40u8)?;
+//! old_buffer.set(&["color"], NP_Enum::new("green"))?;
+//! let old_bytes = old_buffer.close();
+//!
+//! let evolved = factory.schema.evolve()?
+//!     .add_column(&[], "plugin", r#"{"type": "string", "default": "none"}"#)
+//!     .add_choice(&["color"], "blue")
+//!     .widen(&["age"], "u32")
+//!     .build()?;
+//!
+//! let mut buffer = evolved.open_buffer_checked(old_bytes)?;
+//! assert_eq!(buffer.get::<&str>(&["name"])?, Some("Jeb"));
+//! assert_eq!(buffer.get::<u32>(&["age"])?, Some(40));
+//! assert_eq!(buffer.get::<&str>(&["plugin"])?, Some("none"));
+//!
+//! buffer.set(&["age"], 70000u32)?;
+//! buffer.set(&["color"], NP_Enum::new("blue"))?;
+//! let buffer = evolved.open_buffer_checked(buffer.close())?;
+//! assert_eq!(buffer.get::<u32>(&["age"])?, Some(70000));
+//! assert_eq!(buffer.get::<NP_Enum>(&["color"])?, Some(NP_Enum::new("blue")));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::json_flex::{json_decode, JSMAP, NP_JSON};
+use crate::owned::NP_Value_Owned;
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+enum NP_Evolution_Step {
+    Column { path: Vec<String>, name: String, schema: String },
+    Choice { path: Vec<String>, choice: String },
+    Widen { path: Vec<String>, to: String }
+}
+
+/// The factory an evolved factory was made from
+#[derive(Debug)]
+pub(crate) struct NP_Evolved_From {
+    factory: NP_Factory,
+    /// paths and types of widened integers, buffers of the old factory are copied into the evolved schema if there are any
+    widened: Vec<(Vec<String>, (bool, u8))>
+}
+
+impl NP_Evolved_From {
+
+    /// Open bytes that weren't made by the evolved factory
+    pub(crate) fn open<'buffer>(&self, evolved: &'buffer NP_Factory, bytes: Vec<u8>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let old_buffer = self.factory.open_buffer_checked(bytes)?;
+        if !self.widened.is_empty() {
+            let mut tree = old_buffer.root_owned_tree()?;
+            for (path, to) in &self.widened {
+                widen_tree(&mut tree, path, *to);
+            }
+            let mut new_buffer = evolved.empty_buffer(None);
+            new_buffer.from_owned_tree(&tree)?;
+            Ok(new_buffer)
+        } else {
+            Ok(evolved.open_buffer(old_buffer.close()))
+        }
+    }
+}
+
+/// Steps that extend a schema without breaking old buffers
+///
+/// Check out documentation [here](../evolution/index.html).
+///
+pub struct NP_Schema_Evolution {
+    factory: NP_Factory,
+    steps: Vec<NP_Evolution_Step>
+}
+
+fn to_path(path: &[&str]) -> Vec<String> {
+    path.iter().map(|x| (*x).to_owned()).collect()
+}
+
+/// Signed and bit size of integer types
+fn int_type(name: &str) -> Option<(bool, u8)> {
+    match name {
+        "i8"  | "int8"   => Some((true, 8)),
+        "i16" | "int16"  => Some((true, 16)),
+        "i32" | "int32"  => Some((true, 32)),
+        "i64" | "int64"  => Some((true, 64)),
+        "u8"  | "uint8"  => Some((false, 8)),
+        "u16" | "uint16" => Some((false, 16)),
+        "u32" | "uint32" => Some((false, 32)),
+        "u64" | "uint64" => Some((false, 64)),
+        _ => None
+    }
+}
+
+/// Convert every integer at `path` into the integer type `to`, list items and map values all share a schema so they're always converted
+fn widen_tree(value: &mut NP_Value_Owned, path: &[String], to: (bool, u8)) {
+
+    if path.is_empty() {
+        let x: i128 = match value {
+            NP_Value_Owned::Int8(x) => *x as i128,
+            NP_Value_Owned::Int16(x) => *x as i128,
+            NP_Value_Owned::Int32(x) => *x as i128,
+            NP_Value_Owned::Int64(x) => *x as i128,
+            NP_Value_Owned::Uint8(x) => *x as i128,
+            NP_Value_Owned::Uint16(x) => *x as i128,
+            NP_Value_Owned::Uint32(x) => *x as i128,
+            NP_Value_Owned::Uint64(x) => *x as i128,
+            _ => return
+        };
+        *value = match to {
+            (true, 16) => NP_Value_Owned::Int16(x as i16),
+            (true, 32) => NP_Value_Owned::Int32(x as i32),
+            (true, _) => NP_Value_Owned::Int64(x as i64),
+            (false, 16) => NP_Value_Owned::Uint16(x as u16),
+            (false, 32) => NP_Value_Owned::Uint32(x as u32),
+            (false, _) => NP_Value_Owned::Uint64(x as u64)
+        };
+        return;
+    }
+
+    match value {
+        NP_Value_Owned::Table(entries) => {
+            for (_, child) in entries.iter_mut().filter(|(name, _)| *name == path[0]) {
+                widen_tree(child, &path[1..], to);
+            }
+        },
+        NP_Value_Owned::Map(entries) => {
+            for (_, child) in entries.iter_mut() {
+                widen_tree(child, &path[1..], to);
+            }
+        },
+        NP_Value_Owned::List(items) => {
+            for child in items.iter_mut() {
+                widen_tree(child, &path[1..], to);
+            }
+        },
+        NP_Value_Owned::Tuple(items) => {
+            if let Some(child) = path[0].parse::<usize>().ok().and_then(|index| items.get_mut(index)) {
+                widen_tree(child, &path[1..], to);
+            }
+        },
+        _ => { }
+    }
+}
+
+/// Type name of a schema
+fn type_of(schema: &JSMAP) -> Option<&str> {
+    schema.get("type").and_then(|x| x.into_string()).map(|x| x.as_str())
+}
+
+/// Follow a path through the JSON of a schema
+fn find<'json>(schema: &'json mut NP_JSON, path: &[String]) -> Result<&'json mut NP_JSON, NP_Error> {
+
+    if path.is_empty() {
+        return Ok(schema);
+    }
+
+    let kind = schema["type"].into_string().cloned().unwrap_or_default();
+
+    let map = match schema {
+        NP_JSON::Dictionary(map) => map,
+        _ => return Err(NP_Error::Schema("Schemas must be objects!".into()))
+    };
+
+    let child = match kind.as_str() {
+        "table" => match map.get_mut("columns") {
+            Some(NP_JSON::Array(columns)) => columns.iter_mut().find(|column| column[0].into_string() == Some(&path[0])).and_then(|column| match column {
+                NP_JSON::Array(column) => column.get_mut(1),
+                _ => None
+            }),
+            _ => None
+        },
+        "list" => map.get_mut("of"),
+        "map" => map.get_mut("value"),
+        "tuple" => match (map.get_mut("values"), path[0].parse::<usize>()) {
+            (Some(NP_JSON::Array(values)), Ok(index)) => values.get_mut(index),
+            _ => None
+        },
+        _ => None
+    };
+
+    match child {
+        Some(child) => find(child, &path[1..]),
+        None => Err(NP_Error::BadPath("Evolution path isn't in the schema: ".to_owned() + &path.join(".")))
+    }
+}
+
+impl NP_Schema_Evolution {
+
+    /// Start evolving the schema of a factory.  The evolved factory keeps the version and address size of this factory, and opens every buffer this factory can open.
+    pub fn new(factory: &NP_Factory) -> Self {
+        NP_Schema_Evolution { factory: factory.clone(), steps: Vec::new() }
+    }
+
+    /// Add a column to the end of the table at `path`.  Give the column a default in it's schema so old buffers read something other than `None`.
+    pub fn add_column(mut self, path: &[&str], name: &str, schema: &str) -> Self {
+        self.steps.push(NP_Evolution_Step::Column { path: to_path(path), name: name.to_owned(), schema: schema.to_owned() });
+        self
+    }
+
+    /// Add a choice to the end of the enum at `path`
+    pub fn add_choice(mut self, path: &[&str], choice: &str) -> Self {
+        self.steps.push(NP_Evolution_Step::Choice { path: to_path(path), choice: choice.to_owned() });
+        self
+    }
+
+    /// Change the integer at `path` into the integer type `to`.  Unsigned integers can widen into larger signed integers, signed integers can only widen into larger signed integers.
+    pub fn widen(mut self, path: &[&str], to: &str) -> Self {
+        self.steps.push(NP_Evolution_Step::Widen { path: to_path(path), to: to.to_owned() });
+        self
+    }
+
+    /// Apply every step in the order they were added and create the factory of the evolved schema
+    pub fn build(&self) -> Result<NP_Factory, NP_Error> {
+
+        let mut schema = self.factory.export_schema()?;
+        let mut widened = Vec::new();
+
+        for step in &self.steps {
+            match step {
+                NP_Evolution_Step::Column { path, name, schema: column } => {
+                    let column = *json_decode(column.clone())?;
+                    let columns = match find(&mut schema, path)? {
+                        NP_JSON::Dictionary(map) if type_of(map) == Some("table") => map.get_mut("columns"),
+                        _ => None
+                    };
+                    match columns {
+                        Some(NP_JSON::Array(columns)) => {
+                            if columns.iter().any(|x| x[0].into_string() == Some(name)) {
+                                return Err(NP_Error::Schema("Table already has a column named ".to_owned() + name));
+                            }
+                            columns.push(NP_JSON::Array(alloc::vec![NP_JSON::String(name.clone()), column]));
+                        },
+                        _ => return Err(NP_Error::SchemaMismatch("Columns can only be added to tables!".into()))
+                    }
+                },
+                NP_Evolution_Step::Choice { path, choice } => {
+                    let choices = match find(&mut schema, path)? {
+                        NP_JSON::Dictionary(map) if type_of(map) == Some("option") => map.get_mut("choices"),
+                        _ => None
+                    };
+                    match choices {
+                        Some(NP_JSON::Array(choices)) => {
+                            if choices.iter().any(|x| x.into_string() == Some(choice)) {
+                                return Err(NP_Error::Schema("Enum already has the choice ".to_owned() + choice));
+                            }
+                            choices.push(NP_JSON::String(choice.clone()));
+                        },
+                        _ => return Err(NP_Error::SchemaMismatch("Choices can only be added to enums!".into()))
+                    }
+                },
+                NP_Evolution_Step::Widen { path, to } => {
+                    let map = match find(&mut schema, path)? {
+                        NP_JSON::Dictionary(map) => map,
+                        _ => return Err(NP_Error::Schema("Schemas must be objects!".into()))
+                    };
+                    let from = type_of(map).and_then(int_type);
+                    let to_type = match (from, int_type(to)) {
+                        (Some((from_signed, from_bits)), Some((to_signed, to_bits))) if to_bits > from_bits && (to_signed || !from_signed) => (to_signed, to_bits),
+                        _ => return Err(NP_Error::SchemaMismatch("Only integers can be widened, into a larger integer type that holds every old value!".into()))
+                    };
+                    map.insert("type".to_owned(), NP_JSON::String(to.clone()));
+                    if to_type.1 > 16 {
+                        map.values.retain(|(key, _)| key != "inline");
+                    }
+                    widened.push((path.clone(), to_type));
+                }
+            }
+        }
+
+        let mut evolved = NP_Factory::new(&schema.stringify())?.with_fingerprint().with_address_size(self.factory.address_size);
+        evolved.version = self.factory.version;
+        evolved.evolved_from = Some(Arc::new(NP_Evolved_From { factory: self.factory.clone(), widened }));
+        Ok(evolved)
+    }
+}
+
+#[test]
+fn schema_evolution_works() -> Result<(), NP_Error> {
+    use crate::pointer::option::NP_Enum;
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "table", "columns": [
+        ["level", {"type": "i8", "inline": true}],
+        ["kind",  {"type": "option", "choices": ["a"]}]
+    ]}}"#)?.with_version(3);
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["0", "level"], -5i8)?;
+    buffer.set(&["0", "kind"], NP_Enum::new("a"))?;
+    let old_bytes = buffer.close();
+
+    // layout compatible changes open the old bytes as they are
+    let added = NP_Schema_Evolution::new(&factory)
+        .add_column(&["*"], "score", r#"{"type": "u16", "default": 9}"#)
+        .add_choice(&["*", "kind"], "b")
+        .build()?;
+    assert_eq!(added.version(), Some(3));
+    let buffer = added.open_buffer_checked(old_bytes.clone())?;
+    assert_eq!(buffer.read_bytes(), &old_bytes[..]);
+    assert_eq!(buffer.get::<u16>(&["0", "score"])?, Some(9));
+    assert_eq!(buffer.get::<NP_Enum>(&["0", "kind"])?, Some(NP_Enum::new("a")));
+
+    // evolutions can be evolved again, buffers of every step still open
+    let widened = NP_Schema_Evolution::new(&added).widen(&["*", "level"], "int64").build()?;
+    let mut buffer = widened.open_buffer_checked(old_bytes)?;
+    assert_eq!(buffer.get::<i64>(&["0", "level"])?, Some(-5));
+    buffer.set(&["1", "level"], i64::MIN)?;
+    let buffer = widened.open_buffer_checked(buffer.close())?;
+    assert_eq!(buffer.get::<i64>(&["1", "level"])?, Some(i64::MIN));
+    let mut added_buffer = added.empty_buffer(None);
+    added_buffer.set(&["0", "kind"], NP_Enum::new("b"))?;
+    assert_eq!(widened.open_buffer_checked(added_buffer.close())?.get::<NP_Enum>(&["0", "kind"])?, Some(NP_Enum::new("b")));
+
+    // steps that would break old buffers fail
+    let evolve = || NP_Schema_Evolution::new(&factory);
+    assert!(evolve().add_column(&["*"], "kind", r#"{"type": "string"}"#).build().is_err());
+    assert!(evolve().add_column(&["*", "level"], "x", r#"{"type": "string"}"#).build().is_err());
+    assert!(evolve().add_column(&["*", "nope"], "x", r#"{"type": "string"}"#).build().is_err());
+    assert!(evolve().add_choice(&["*", "kind"], "a").build().is_err());
+    assert!(evolve().add_choice(&["*", "level"], "a").build().is_err());
+    assert!(evolve().widen(&["*", "level"], "u64").build().is_err());
+    assert!(evolve().widen(&["*", "level"], "i8").build().is_err());
+    assert!(evolve().widen(&["*", "kind"], "i64").build().is_err());
+
+    // buffers of other schemas don't open
+    let other = NP_Factory::new(r#"{"type": "string"}"#)?.with_fingerprint();
+    assert!(added.open_buffer_checked(other.empty_buffer(None).close()).is_err());
+
+    Ok(())
+}
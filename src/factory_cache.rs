@@ -0,0 +1,127 @@
+//! Parse each schema once
+//!
+//! Multi-tenant servers often load the same schema text for many tenants.  An `NP_Factory_Cache` remembers the text of every schema it has parsed, so identical text is only parsed the first time, and keeps one factory per schema fingerprint, so schemas that only differ in formatting or key order share a factory too.  Factories are handed out as `Arc` handles that all point to the same factory.
+//!
+//! The cache needs `&mut self` to add schemas, put it behind a `Mutex` to fill it from many threads.  Use an `NP_Factory_Registry` to look factories up by the fingerprint in buffer headers instead.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::factory_cache::NP_Factory_Cache;
+//! use std::sync::Arc;
+//!
+//! let mut cache = NP_Factory_Cache::new();
+//!
+//! let tenant_a = cache.get_or_parse(r#"{"type": "table", "columns": [["name", {"type": "string"}]]}"#)?;
+//! let tenant_b = cache.get_or_parse(r#"{"type": "table", "columns": [["name", {"type": "string"}]]}"#)?;
+//! let tenant_c = cache.get_or_parse(r#"{ "columns": [["name", {"type": "string"}]], "type": "table" }"#)?;
+//!
+//! assert!(Arc::ptr_eq(&tenant_a, &tenant_b));
+//! assert!(Arc::ptr_eq(&tenant_a, &tenant_c));
+//! assert_eq!(cache.len(), 1);
+//!
+//! let mut new_buffer = tenant_c.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//! assert_eq!(new_buffer.get::<&str>(&["name"])?, Some("Jeb"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::error::NP_Error;
+use crate::hashmap::murmurhash3_x86_32;
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Shared factories keyed by schema text and fingerprint
+///
+/// Check out documentation [here](../factory_cache/index.html).
+///
+#[derive(Debug, Default)]
+pub struct NP_Factory_Cache {
+    /// hash of the text, the text and the index of it's factory
+    texts: Vec<(u32, String, usize)>,
+    factories: Vec<Arc<NP_Factory>>
+}
+
+impl NP_Factory_Cache {
+
+    /// Create an empty cache
+    pub fn new() -> Self {
+        NP_Factory_Cache::default()
+    }
+
+    /// Get the factory for a JSON schema, the schema is only parsed if this exact text hasn't been seen before
+    pub fn get_or_parse(&mut self, json_schema: &str) -> Result<Arc<NP_Factory>, NP_Error> {
+        let hash = murmurhash3_x86_32(json_schema.as_bytes(), 0);
+        if let Some((_, _, index)) = self.texts.iter().find(|(x, text, _)| *x == hash && text == json_schema) {
+            return Ok(Arc::clone(&self.factories[*index]));
+        }
+        let index = self.index_of(NP_Factory::new(json_schema)?);
+        self.texts.push((hash, json_schema.to_owned(), index));
+        Ok(Arc::clone(&self.factories[index]))
+    }
+
+    /// Add a factory to the cache and get a handle to the cached one back.  If there's already a factory for the same schema fingerprint, that factory is returned instead.
+    pub fn insert(&mut self, factory: NP_Factory) -> Arc<NP_Factory> {
+        let index = self.index_of(factory);
+        Arc::clone(&self.factories[index])
+    }
+
+    fn index_of(&mut self, factory: NP_Factory) -> usize {
+        let fingerprint = factory.schema.fingerprint();
+        match self.factories.iter().position(|x| x.schema.fingerprint() == fingerprint) {
+            Some(index) => index,
+            None => {
+                self.factories.push(Arc::new(factory));
+                self.factories.len() - 1
+            }
+        }
+    }
+
+    /// Get the factory for a schema fingerprint
+    pub fn get(&self, fingerprint: [u8; 8]) -> Option<Arc<NP_Factory>> {
+        self.factories.iter().find(|x| x.schema.fingerprint() == fingerprint).cloned()
+    }
+
+    /// How many distinct factories are in the cache
+    pub fn len(&self) -> usize {
+        self.factories.len()
+    }
+
+    /// Is the cache empty?
+    pub fn is_empty(&self) -> bool {
+        self.factories.is_empty()
+    }
+}
+
+#[test]
+fn factory_cache_works() -> Result<(), NP_Error> {
+    let schema = r#"{"type": "list", "of": {"type": "u8"}}"#;
+
+    let mut cache = NP_Factory_Cache::new();
+    assert!(cache.is_empty());
+    let first = cache.get_or_parse(schema)?;
+    let parsed = cache.insert(NP_Factory::new(schema)?);
+    assert!(Arc::ptr_eq(&first, &parsed));
+    assert!(Arc::ptr_eq(&first, &cache.get(first.schema.fingerprint()).unwrap()));
+
+    // every text is only kept once
+    for _x in 0..10 {
+        cache.get_or_parse(schema)?;
+    }
+    assert_eq!(cache.texts.len(), 1);
+
+    let other = cache.get_or_parse(r#"{"type": "string"}"#)?;
+    assert!(!Arc::ptr_eq(&first, &other));
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get([0; 8]).is_none());
+
+    // bad schemas aren't cached
+    assert!(cache.get_or_parse(r#"{"type": "nope"}"#).is_err());
+    assert_eq!((cache.len(), cache.texts.len()), (2, 2));
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Many message types over one stream
+//!
+//! A socket that carries more than one kind of message needs to say which schema each buffer uses.  `NP_Factory_Set` holds a factory for each named type, closing a buffer through the set puts the type name in front of the bytes and `open` reads it back to pick the right factory.
+//!
+//! The tag is one byte for the length of the type name followed by the name itself.  Names are used instead of numbers so both ends can add types in any order.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::factory_set::NP_Factory_Set;
+//!
+//! let types = NP_Factory_Set::new(&[
+//!     ("login", r#"{"type": "table", "columns": [["user", {"type": "string"}]]}"#),
+//!     ("ping",  r#"{"type": "u64"}"#)
+//! ])?;
+//!
+//! let mut login = types.empty_buffer("login")?;
+//! login.set(&["user"], "jeb")?;
+//! let bytes = types.close("login", login)?;
+//!
+//! let (type_name, buffer) = types.open(bytes)?;
+//! assert_eq!(type_name, "login");
+//! assert_eq!(buffer.get::<&str>(&["user"])?, Some("jeb"));
+//!
+//! // buffers can only be closed as the type they were made with
+//! let ping = types.empty_buffer("ping")?;
+//! assert!(types.close("login", ping).is_err());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Factories for named message types
+///
+/// Check out documentation [here](../factory_set/index.html).
+///
+#[derive(Debug, Clone, Default)]
+pub struct NP_Factory_Set {
+    factories: Vec<(String, NP_Factory)>
+}
+
+impl NP_Factory_Set {
+
+    /// Create a set from type names and their JSON schemas
+    pub fn new(schemas: &[(&str, &str)]) -> Result<Self, NP_Error> {
+        let mut set = NP_Factory_Set::default();
+        for (name, schema) in schemas {
+            set.add(name, NP_Factory::new(schema)?)?;
+        }
+        Ok(set)
+    }
+
+    /// Add a type to the set.  Names must be 1 to 255 bytes and can only be used once.
+    pub fn add(&mut self, name: &str, factory: NP_Factory) -> Result<(), NP_Error> {
+        if name.is_empty() || name.len() > 255 {
+            return Err(NP_Error::Schema("Type names must be 1 to 255 bytes!".into()));
+        }
+        if self.factory(name).is_some() {
+            return Err(NP_Error::Schema("Type is already in the set: ".to_owned() + name));
+        }
+        self.factories.push((name.to_owned(), factory));
+        Ok(())
+    }
+
+    /// Get the factory of a type
+    pub fn factory(&self, name: &str) -> Option<&NP_Factory> {
+        self.factories.iter().find(|(x, _)| x == name).map(|(_, factory)| factory)
+    }
+
+    /// Names of every type in the order they were added
+    pub fn names(&self) -> Vec<&str> {
+        self.factories.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    fn find(&self, name: &str) -> Result<&NP_Factory, NP_Error> {
+        match self.factory(name) {
+            Some(factory) => Ok(factory),
+            None => Err(NP_Error::SchemaMismatch("Type isn't in the set: ".to_owned() + name))
+        }
+    }
+
+    /// Create a new empty buffer of a type
+    pub fn empty_buffer(&self, name: &str) -> Result<NP_Buffer<'_>, NP_Error> {
+        Ok(self.find(name)?.empty_buffer(None))
+    }
+
+    /// Close a buffer with it's type name in front of the bytes.  Fails if the buffer wasn't made by the factory of that type.
+    pub fn close(&self, name: &str, buffer: NP_Buffer) -> Result<Vec<u8>, NP_Error> {
+        let factory = self.find(name)?;
+        if !core::ptr::eq(buffer.parsed_schema(), &factory.schema.parsed) {
+            return Err(NP_Error::SchemaMismatch("Buffer wasn't made by the factory of this type!".into()));
+        }
+        let bytes = buffer.close();
+        let mut tagged = Vec::with_capacity(1 + name.len() + bytes.len());
+        tagged.push(name.len() as u8);
+        tagged.extend_from_slice(name.as_bytes());
+        tagged.extend_from_slice(&bytes);
+        Ok(tagged)
+    }
+
+    /// Open bytes made by `close`, returns the type name and the buffer.
+    pub fn open(&self, mut bytes: Vec<u8>) -> Result<(&str, NP_Buffer<'_>), NP_Error> {
+        let len = match bytes.first() {
+            Some(len) if bytes.len() > *len as usize => *len as usize,
+            _ => return Err(NP_Error::Damaged("Bytes don't start with a type name!".into()))
+        };
+        let (name, factory) = match self.factories.iter().find(|(x, _)| x.as_bytes() == &bytes[1..(1 + len)]) {
+            Some((name, factory)) => (name.as_str(), factory),
+            None => return Err(NP_Error::SchemaMismatch("Bytes are for a type that isn't in the set!".into()))
+        };
+        bytes.drain(0..(1 + len));
+        Ok((name, factory.open_buffer(bytes)))
+    }
+}
+
+#[test]
+fn factory_sets_work() -> Result<(), NP_Error> {
+    let mut types = NP_Factory_Set::new(&[("a", r#"{"type": "string"}"#), ("b", r#"{"type": "u8"}"#)])?;
+    assert!(types.add("a", NP_Factory::new(r#"{"type": "bool"}"#)?).is_err());
+    assert!(types.add("", NP_Factory::new(r#"{"type": "bool"}"#)?).is_err());
+    types.add("c", NP_Factory::new(r#"{"type": "bool"}"#)?)?;
+    assert_eq!(types.names(), alloc::vec!["a", "b", "c"]);
+    assert!(NP_Factory_Set::new(&[("a", "{")]).is_err());
+
+    let mut buffer = types.empty_buffer("b")?;
+    buffer.set(&[], 7u8)?;
+    let bytes = types.close("b", buffer)?;
+    assert_eq!(&bytes[0..2], &[1, b'b']);
+
+    // the other end can add types in a different order
+    let other = NP_Factory_Set::new(&[("c", r#"{"type": "bool"}"#), ("b", r#"{"type": "u8"}"#)])?;
+    let (name, buffer) = other.open(bytes.clone())?;
+    assert_eq!((name, buffer.get::<u8>(&[])?), ("b", Some(7)));
+
+    // unknown types and damaged tags fail
+    assert!(NP_Factory_Set::default().open(bytes).is_err());
+    assert!(types.open(alloc::vec![]).is_err());
+    assert!(types.open(alloc::vec![5, b'a']).is_err());
+    assert!(types.empty_buffer("d").is_err());
+    let outside = NP_Factory::new(r#"{"type": "string"}"#)?;
+    assert!(types.close("a", outside.empty_buffer(None)).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! Buffers that are done changing
+//!
+//! `NP_Buffer::freeze` turns a buffer into an `NP_Finished_Buffer`.  Finished buffers dereference into a read only `NP_Buffer`, so every read method is available but nothing can change the bytes anymore.  Pipelines can take an `NP_Finished_Buffer` to make sure the next stage gets a complete document.
+//!
+//! Freezing can compact the buffer first and keep a checksum of the finished bytes to check them again later.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::finished::{NP_Finished_Buffer, NP_Freeze_Options};
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! fn next_stage(buffer: NP_Finished_Buffer) -> Result<Option<u8>, NP_Error> {
+//!     assert!(buffer.verify_checksum());
+//!     buffer.get::<u8>(&["age"])
+//! }
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//! new_buffer.set(&["name"], "Bill")?;
+//! new_buffer.set(&["age"], 30u8)?;
+//!
+//! let finished = new_buffer.freeze_with(&NP_Freeze_Options { compact: true, checksum: true })?;
+//! assert!(finished.is_read_only());
+//! // compaction dropped the old name
+//! assert_eq!(finished.calc_bytes()?.wasted_bytes, 0);
+//!
+//! assert_eq!(next_stage(finished)?, Some(30));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::NP_Buffer;
+use crate::hashmap::murmurhash3_x86_32;
+use crate::sync::NP_Buffer_Sync;
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+/// Options used by `NP_Buffer::freeze_with`
+#[derive(Debug, Clone, Default)]
+pub struct NP_Freeze_Options {
+    /// Compact the buffer before it's finished
+    pub compact: bool,
+    /// Keep a checksum of the finished bytes, see `NP_Finished_Buffer::verify_checksum`
+    pub checksum: bool
+}
+
+/// A buffer that can't be changed anymore
+///
+/// Check out documentation [here](../finished/index.html).
+///
+pub struct NP_Finished_Buffer<'buffer> {
+    buffer: NP_Buffer<'buffer>,
+    checksum: Option<u32>
+}
+
+impl<'buffer> NP_Finished_Buffer<'buffer> {
+
+    pub(crate) fn new(buffer: NP_Buffer<'buffer>, checksum: bool) -> Self {
+        let checksum = if checksum { Some(murmurhash3_x86_32(buffer.read_bytes(), 0)) } else { None };
+        NP_Finished_Buffer { buffer, checksum }
+    }
+
+    /// Checksum of the bytes when the buffer was finished, if it was frozen with one
+    pub fn checksum(&self) -> Option<u32> {
+        self.checksum
+    }
+
+    /// Do the bytes still match the checksum?  Buffers frozen without a checksum always pass.
+    pub fn verify_checksum(&self) -> bool {
+        match self.checksum {
+            Some(checksum) => murmurhash3_x86_32(self.buffer.read_bytes(), 0) == checksum,
+            None => true
+        }
+    }
+
+    /// Share the finished buffer between threads
+    pub fn into_sync(self) -> NP_Buffer_Sync<'buffer> {
+        self.buffer.into_sync()
+    }
+
+    /// Close the buffer and get the finished bytes
+    pub fn close(self) -> Vec<u8> {
+        self.buffer.close()
+    }
+}
+
+impl<'buffer> Deref for NP_Finished_Buffer<'buffer> {
+    type Target = NP_Buffer<'buffer>;
+
+    fn deref(&self) -> &NP_Buffer<'buffer> {
+        &self.buffer
+    }
+}
+
+#[test]
+fn finished_buffers_work() -> Result<(), crate::error::NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["title", {"type": "string"}],
+        ["tags",  {"type": "list", "of": {"type": "string"}}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["title"], "first")?;
+    buffer.set(&["title"], "second")?;
+    buffer.list_push(&["tags"], "one")?;
+    let bytes = buffer.read_bytes().to_vec();
+
+    // freezing keeps the bytes as they are
+    let finished = buffer.freeze();
+    assert!(finished.is_read_only());
+    assert_eq!(finished.checksum(), None);
+    assert!(finished.verify_checksum());
+    assert_eq!(finished.read_bytes(), &bytes[..]);
+    assert_eq!(finished.get::<&str>(&["title"])?, Some("second"));
+
+    // snapshots don't make reads copy the bytes
+    let snapshot = finished.snapshot();
+    assert_eq!(finished.get::<&str>(&["tags", "0"])?, Some("one"));
+    assert_eq!(snapshot.read_bytes().as_ptr(), finished.read_bytes().as_ptr());
+    assert_eq!(finished.close(), bytes);
+
+    // or compacts them first with a checksum
+    let buffer = factory.open_buffer(bytes.clone());
+    let finished = buffer.freeze_with(&NP_Freeze_Options { compact: true, checksum: true })?;
+    assert!(finished.read_bytes().len() < bytes.len());
+    assert_eq!(finished.checksum(), Some(murmurhash3_x86_32(finished.read_bytes(), 0)));
+    assert!(finished.verify_checksum());
+    assert_eq!(finished.json_encode(&[])?.stringify(), r#"{"title":"second","tags":["one"]}"#);
+
+    let shared = finished.into_sync();
+    assert_eq!(shared.buffer().get::<&str>(&["title"])?, Some("second"));
+
+    Ok(())
+}
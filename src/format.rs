@@ -9,16 +9,27 @@
 //! 
 //! Pointers contain one or more addresses depending on the pointer type.  The addresses will point to data or other pointers.
 //! 
-//! There is only one address size, u16.  Addresses are always stored in big endian format and addresses are always zero based from the beginning of the buffer.  In other words, address `23` always means 23 bytes from the beginning of the buffer.
+//! Addresses are u16 by default, factories created with `with_address_size` can use u8 or u32 addresses instead.  Addresses are always stored in big endian format and addresses are always zero based from the beginning of the buffer.  In other words, address `23` always means 23 bytes from the beginning of the buffer.
 //! 
-//! | Pointer Kind | u16 size (bytes) |
-//! |--------------|------------------|
-//! | Standard     | 2                | 
-//! | Map Item     | 6                | 
-//! | List Item    | 5                |
+//! | Pointer Kind | u8 size (bytes) | u16 size (bytes) | u32 size (bytes) |
+//! |--------------|-----------------|------------------|------------------|
+//! | Standard     | 1               | 2                | 4                |
+//! | Map Item     | 3               | 6                | 12               |
+//! | List Item    | 3               | 5                | 9                |
+//! 
+//! The rest of this document describes buffers with u16 addresses, buffers with other address sizes have the same layout with every address resized.
 //!  
 //! 
-//! The first byte of every buffer is a version/size number to be used by later version of NoProto.  The next 2 bytes of every buffer is the root pointer, these bytes should contain the address of the root object in the buffer.
+//! The first byte of every buffer holds header flags.  The header is usually just this byte, otherwise each flag adds bytes to the header in this order:
+//! 
+//! | Flag   | Header bytes                                        |
+//! |--------|-----------------------------------------------------|
+//! | `0x01` | 8 byte schema fingerprint from `NP_Schema::fingerprint` |
+//! | `0x02` | 4 byte big endian schema version, then 1 byte of user flags |
+//! | `0x04` | No header bytes, the buffer uses u32 addresses      |
+//! | `0x08` | No header bytes, the buffer uses u8 addresses       |
+//! 
+//! The next address (2 bytes with u16 addresses) of every buffer after the header is the root pointer, these bytes should contain the address of the root object in the buffer.
 //! 
 //! Most of the time these bytes will point to the data immediately following them, but it's possible to clear the root object causing these bytes to be zero, or to update the root data which would cause this address to update to something else.
 //! 
@@ -168,7 +179,7 @@
 //! 
 //! Vtables are normally created as needed,  For example if there are 100 values in the schema but the client only ever sets the first 4 values there will only ever be 1 vtable in the buffer.
 //! 
-//! If the tuple is set to be sorted, all vtables needed by the schema are created at once in a continuous chain.  Following the continuos chain of vtables, default zero bytes are set for all children of the tuple.  This gaurantees all sorted tuples of the same schema have identical leading bytes followed by sortable bytes determined by the value of the data.
+//! If the tuple is set to be sorted, all vtables needed by the schema are created at once in a continuous chain.  Following the continuos chain of vtables, default zero bytes are set for all children of the tuple.  This gaurantees all sorted tuples of the same schema have identical leading bytes followed by sortable bytes determined by the value of the data.  `close_sortable` drops the leading bytes and writes each value of the tuple in it's [sortable encoding](../sortable/index.html).
 //! 
 //! ```
 //! use no_proto::error::NP_Error;
@@ -377,6 +388,8 @@
 //! <br/>
 //! geo16: ....
 //! 
+//! Values of `"size": "auto"` schemas start with one byte holding the size picked for that value, the coordinate bytes follow in the format of that size.
+//! 
 //! ```
 //! use no_proto::error::NP_Error;
 //! use no_proto::NP_Factory;
@@ -647,8 +660,8 @@
 //! ### geo4, geo8, geo16 (Scalar)
 //! 
 //! Geo stores the size of the data type in the second byte.
-//! The third byte is 0 if there is no default, and 1 if there is a default.
-//! The remaining bytes are the default value (if there is one) parsed in the specific size designated in the second byte.
+//! The first bit of the third byte is 0 if there is no default, and 1 if there is a default.  The second bit is 1 if the schema has `"z_order": true`.
+//! The remaining bytes are the default value (if there is one) parsed in the specific size designated in the second byte.  A size of 0 means `"size": "auto"`, the default then starts with it's own size.
 //! 
 //! ```
 //! use no_proto::error::NP_Error;
@@ -745,7 +758,7 @@
 //! 
 //! ### date (Scalar)
 //! 
-//! The second byte is a 1 if there is a default value, 0 otherwise.
+//! The second byte is a 1 if there is a default value, 2 if the default is `"now"` and 0 otherwise.
 //! 
 //! If there is a default value it follows the second byte.
 //! 
@@ -772,6 +785,13 @@
 //! 
 //! // [       19,            1, 0, 0, 0, 0, 95, 168, 65, 44]
 //! // [data type, default flag,        default value       ]
+//! 
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "date",
+//!    "default": "now"
+//! }"#)?;
+//!
+//! assert_eq!(vec![19, 2], factory.compile_schema());
 //!
 //! # Ok::<(), NP_Error>(()) 
 //! ```
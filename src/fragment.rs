@@ -0,0 +1,166 @@
+//! Split buffers into small frames for links with packet limits
+//!
+//! UDP datagrams and BLE packets are often smaller than a buffer.  `NP_Buffer::fragment` splits the buffer bytes into frames no larger than a given size, and `NP_Factory::reassemble` puts the frames back together into a buffer.  Frames can arrive in any order.
+//!
+//! Each frame starts with an 8 byte header:
+//!
+//! | Bytes | Value |
+//! |-------|-------|
+//! | 2     | Sequence number of this frame, starting at zero, big endian |
+//! | 2     | Total number of frames, big endian |
+//! | 4     | Checksum of the frame, big endian |
+//!
+//! The checksum covers the sequence number, the total and the frame bytes, so damaged or mixed up frames fail to reassemble instead of making a damaged buffer.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["bio",  {"type": "string"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//! new_buffer.set(&["bio"], "Pilot of the first crewed flight to the mun and back.")?;
+//!
+//! let mut frames = new_buffer.fragment(20)?;
+//! assert!(frames.len() > 1);
+//! assert!(frames.iter().all(|frame| frame.len() <= 20));
+//!
+//! // frames can arrive out of order
+//! frames.reverse();
+//!
+//! let buffer = factory.reassemble(&frames)?;
+//! assert_eq!(buffer.get::<&str>(&["name"])?, Some("Jeb"));
+//!
+//! // but all of them need to arrive
+//! assert!(factory.reassemble(&frames[1..]).is_err());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::hashmap::murmurhash3_x86_32;
+use alloc::vec::Vec;
+
+/// Length of the header in front of each frame
+pub const NP_FRAGMENT_HEADER_SIZE: usize = 8;
+
+/// Checksum of a frame, seeded with it's place so frames can't be swapped
+fn frame_checksum(sequence: u16, total: u16, bytes: &[u8]) -> u32 {
+    murmurhash3_x86_32(bytes, ((sequence as u32) << 16) | total as u32)
+}
+
+/// Split bytes into frames of at most `max_frame_size` bytes, headers included
+pub(crate) fn fragment(bytes: &[u8], max_frame_size: usize) -> Result<Vec<Vec<u8>>, NP_Error> {
+    if max_frame_size <= NP_FRAGMENT_HEADER_SIZE {
+        return Err(NP_Error::OutOfBounds("Frames need room for more than the fragment header!".into()));
+    }
+
+    let chunk_size = max_frame_size - NP_FRAGMENT_HEADER_SIZE;
+    let total = bytes.len().div_ceil(chunk_size);
+    if total > u16::MAX as usize {
+        return Err(NP_Error::OutOfBounds("Buffer needs more than 65535 frames at this frame size!".into()));
+    }
+
+    Ok(bytes.chunks(chunk_size).enumerate().map(|(sequence, chunk)| {
+        let (sequence, total) = (sequence as u16, total as u16);
+        let mut frame = Vec::with_capacity(NP_FRAGMENT_HEADER_SIZE + chunk.len());
+        frame.extend_from_slice(&sequence.to_be_bytes());
+        frame.extend_from_slice(&total.to_be_bytes());
+        frame.extend_from_slice(&frame_checksum(sequence, total, chunk).to_be_bytes());
+        frame.extend_from_slice(chunk);
+        frame
+    }).collect())
+}
+
+/// Put frames made by `fragment` back together, in any order
+pub(crate) fn reassemble<F: AsRef<[u8]>>(frames: &[F]) -> Result<Vec<u8>, NP_Error> {
+    let mut parts: Vec<(u16, &[u8])> = Vec::with_capacity(frames.len());
+    let mut total: Option<u16> = None;
+
+    for frame in frames {
+        let frame = frame.as_ref();
+        if frame.len() < NP_FRAGMENT_HEADER_SIZE {
+            return Err(NP_Error::Damaged("Frame is shorter than the fragment header!".into()));
+        }
+        let sequence = u16::from_be_bytes([frame[0], frame[1]]);
+        let frame_total = u16::from_be_bytes([frame[2], frame[3]]);
+        let checksum = u32::from_be_bytes([frame[4], frame[5], frame[6], frame[7]]);
+        let bytes = &frame[NP_FRAGMENT_HEADER_SIZE..];
+
+        if frame_checksum(sequence, frame_total, bytes) != checksum {
+            return Err(NP_Error::Damaged("Frame checksum doesn't match, the frame was damaged!".into()));
+        }
+        if *total.get_or_insert(frame_total) != frame_total {
+            return Err(NP_Error::Damaged("Frames are from different buffers!".into()));
+        }
+        parts.push((sequence, bytes));
+    }
+
+    parts.sort_by_key(|(sequence, _)| *sequence);
+    let complete = !parts.is_empty() && parts.len() == total.unwrap_or(0) as usize && parts.iter().enumerate().all(|(index, (sequence, _))| index == *sequence as usize);
+    if !complete {
+        return Err(NP_Error::Damaged("Frames are missing or repeated!".into()));
+    }
+
+    let mut bytes = Vec::with_capacity(parts.iter().map(|(_, x)| x.len()).sum());
+    for (_, part) in parts {
+        bytes.extend_from_slice(part);
+    }
+    Ok(bytes)
+}
+
+#[test]
+fn fragments_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    for x in 0..20 {
+        buffer.list_push(&[], alloc::format!("item {}", x).as_str())?;
+    }
+    let bytes = buffer.read_bytes().to_vec();
+
+    // frame sizes are a limit, headers included
+    assert!(buffer.fragment(NP_FRAGMENT_HEADER_SIZE).is_err());
+    let frames = buffer.fragment(NP_FRAGMENT_HEADER_SIZE + 1)?;
+    assert_eq!(frames.len(), bytes.len());
+    let frames = buffer.fragment(64)?;
+    assert_eq!(frames.len(), bytes.len().div_ceil(56));
+    assert!(frames.iter().all(|frame| frame.len() <= 64));
+
+    // big frames hold the whole buffer
+    assert_eq!(buffer.fragment(4096)?.len(), 1);
+    assert_eq!(factory.reassemble(&buffer.fragment(4096)?)?.read_bytes(), &bytes[..]);
+
+    // any order works
+    let mut shuffled = frames.clone();
+    shuffled.rotate_left(2);
+    shuffled.swap(0, 3);
+    let reassembled = factory.reassemble(&shuffled)?;
+    assert_eq!(reassembled.read_bytes(), &bytes[..]);
+    assert_eq!(reassembled.get::<&str>(&["19"])?, Some("item 19"));
+
+    // damaged, repeated, missing and mixed frames fail
+    let mut damaged = frames.clone();
+    damaged[1][NP_FRAGMENT_HEADER_SIZE] ^= 1;
+    assert!(factory.reassemble(&damaged).is_err());
+    let mut repeated = frames.clone();
+    repeated[1] = repeated[0].clone();
+    assert!(factory.reassemble(&repeated).is_err());
+    assert!(factory.reassemble(&frames[1..]).is_err());
+    let mut mixed = frames.clone();
+    mixed[0] = buffer.fragment(4096)?.remove(0);
+    assert!(factory.reassemble(&mixed).is_err());
+    assert!(factory.reassemble::<Vec<u8>>(&[]).is_err());
+    assert!(factory.reassemble(&[[0u8; 4]]).is_err());
+
+    Ok(())
+}
@@ -1,14 +1,107 @@
 use alloc::vec::Vec;
+use alloc::string::{String, ToString};
 
 use crate::error::NP_Error;
 
 
 pub static SEED: u32 = 2181155409;
 
+/// Hash function used to look up table columns by name
+/// 
+/// The hash function is set with the `key_hash` property of a table schema and recorded in the compiled schema.  Only the column names of that table are hashed, when the schema is parsed and when a path step is looked up in the table.  The choice doesn't change how map keys or anything else in a buffer are stored.  Keys are always compared after their hashes match, so collisions never select the wrong column.
+/// 
+/// Every function uses the same fixed public seed, so none of them keep someone who can pick the column names from making them collide.  Column names come from the schema, so this only matters for schemas you didn't write.
+/// 
+/// | Hash       | Schema name  | Notes                                           |
+/// |------------|--------------|-------------------------------------------------|
+/// | Murmur3    | `"murmur3"`  | default                                         |
+/// | Fnv1a      | `"fnv1a"`    | fastest for short keys                          |
+/// | XxHash32   | `"xxhash32"` | fast for long keys                              |
+/// | SipHash    | `"siphash"`  | SipHash-2-4 with a fixed key                    |
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_Key_Hash {
+    /// MurmurHash3 x86 32 bit
+    Murmur3,
+    /// FNV-1a 32 bit
+    Fnv1a,
+    /// xxHash 32 bit
+    XxHash32,
+    /// SipHash-2-4
+    SipHash
+}
+
+impl Default for NP_Key_Hash {
+    fn default() -> Self {
+        NP_Key_Hash::Murmur3
+    }
+}
+
+impl NP_Key_Hash {
+    /// Hash a key with this function
+    pub fn hash(&self, key: &[u8]) -> u32 {
+        match self {
+            NP_Key_Hash::Murmur3  => murmurhash3_x86_32(key, SEED),
+            NP_Key_Hash::Fnv1a    => fnv1a_32(key),
+            NP_Key_Hash::XxHash32 => xxhash_32(key, SEED),
+            NP_Key_Hash::SipHash  => {
+                let hash = siphash_2_4(key, SEED as u64, (SEED as u64) << 32 | 0x9747_b28c);
+                (hash ^ (hash >> 32)) as u32
+            }
+        }
+    }
+
+    /// Name of this hash function in JSON schemas
+    pub fn name(&self) -> &'static str {
+        match self {
+            NP_Key_Hash::Murmur3  => "murmur3",
+            NP_Key_Hash::Fnv1a    => "fnv1a",
+            NP_Key_Hash::XxHash32 => "xxhash32",
+            NP_Key_Hash::SipHash  => "siphash"
+        }
+    }
+
+    /// Find a hash function from it's name in JSON schemas
+    pub fn from_name(name: &str) -> Result<Self, NP_Error> {
+        match name {
+            "murmur3"  => Ok(NP_Key_Hash::Murmur3),
+            "fnv1a"    => Ok(NP_Key_Hash::Fnv1a),
+            "xxhash32" => Ok(NP_Key_Hash::XxHash32),
+            "siphash"  => Ok(NP_Key_Hash::SipHash),
+            _ => {
+                let mut err = String::from("Unknown key hash: ");
+                err.push_str(name);
+                Err(NP_Error::Schema(err))
+            }
+        }
+    }
+
+    /// The byte recorded in compiled schemas
+    pub fn to_byte(&self) -> u8 {
+        match self {
+            NP_Key_Hash::Murmur3  => 0,
+            NP_Key_Hash::Fnv1a    => 1,
+            NP_Key_Hash::XxHash32 => 2,
+            NP_Key_Hash::SipHash  => 3
+        }
+    }
+
+    /// Read the byte recorded in compiled schemas
+    pub fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => NP_Key_Hash::Fnv1a,
+            2 => NP_Key_Hash::XxHash32,
+            3 => NP_Key_Hash::SipHash,
+            _ => NP_Key_Hash::Murmur3
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct NP_HashMap {
-    data: Vec<Vec<(u32, usize)>>,
-    size: u16
+    data: Vec<Vec<(u32, String, usize)>>,
+    size: u16,
+    hash: NP_Key_Hash
 }
 
 const MAP_SIZE: usize = 1024;
@@ -16,60 +109,53 @@ const MAP_SIZE: usize = 1024;
 impl NP_HashMap {
 
     pub fn new() -> Self {
-        let mut vector = Vec::with_capacity(1024);
+        Self::with_hash(NP_Key_Hash::default())
+    }
+
+    pub fn with_hash(hash: NP_Key_Hash) -> Self {
+        let mut vector = Vec::with_capacity(MAP_SIZE);
         vector.extend((0..MAP_SIZE).map(|_| Vec::new()));
-        Self { data: vector, size: 0 }
+        Self { data: vector, size: 0, hash }
     }
 
-    pub fn do_hash(key: &str) -> u32 {
-        murmurhash3_x86_32(key.as_bytes(), SEED)
+    /// The hash function used for keys
+    pub fn key_hash(&self) -> NP_Key_Hash {
+        self.hash
     }
 
-    pub fn insert_hash(&mut self, hash: u32, value: usize) -> Result<(), NP_Error> {
-        if self.size + 1 > 255 {
-            return Err(NP_Error::new("Too many records in hash map!"));
-        }
+    pub fn do_hash(&self, key: &str) -> u32 {
+        self.hash.hash(key.as_bytes())
+    }
 
-        self.size += 1;
+    pub fn insert(&mut self, key: &str, value: usize) -> Result<u32, NP_Error> {
 
+        let hash = self.do_hash(key);
         let bucket = hash as usize % MAP_SIZE;
 
-        if self.data[bucket].len() == 0 {
-            self.data[bucket].push((hash, value));
-        } else {
-            for (k, v) in self.data[bucket].iter_mut() {
-                if *k == hash {
-                    *v = value;
-                    return Ok(())
-                }
+        for (k, existing_key, v) in self.data[bucket].iter_mut() {
+            if *k == hash && existing_key == key {
+                *v = value;
+                return Ok(hash)
             }
-            self.data[bucket].push((hash, value));
         }
 
-        Ok(())
-    }
-
-    pub fn insert(&mut self, key: &str, value: usize) -> Result<u32, NP_Error> {
-        
         if self.size + 1 > 255 {
-            return Err(NP_Error::new("Too many records in hash map!"));
+            return Err(NP_Error::InvalidValue("Too many records in hash map!".into()));
         }
 
         self.size += 1;
-
-        let hash = murmurhash3_x86_32(key.as_bytes(), SEED);
-    
-        self.insert_hash(hash, value)?;
+        self.data[bucket].push((hash, key.to_string(), value));
 
         Ok(hash)
     }
 
-    pub fn get_hash(&self, key: u32) -> Option<&usize> {
-        let bucket = key as usize % MAP_SIZE;
+    pub fn get(&self, key: &str) -> Option<&usize> {
+        let hash = self.do_hash(key);
+        let bucket = hash as usize % MAP_SIZE;
         match self.data.get(bucket) {
             Some(x) => {
-                for (k, v) in x.iter() {
-                    if *k == key {
+                for (k, existing_key, v) in x.iter() {
+                    if *k == hash && existing_key == key {
                         return Some(v)
                     }
                 }
@@ -79,18 +165,14 @@ impl NP_HashMap {
         }
     }
 
-    pub fn get(&self, key: &str) -> Option<&usize> {
-        let hash = murmurhash3_x86_32(key.as_bytes(), SEED);
-        self.get_hash(hash)
-    }
-
     pub fn delete(&mut self, key: &str) {
-        self.size -= 1;
-        let hash = murmurhash3_x86_32(key.as_bytes(), SEED);
+        let hash = self.do_hash(key);
         let bucket = hash as usize % MAP_SIZE;
         match self.data.get_mut(bucket) {
             Some(bucket) => {
-                bucket.retain(|(k, _v)| *k != hash);
+                let before = bucket.len();
+                bucket.retain(|(k, existing_key, _v)| *k != hash || existing_key != key);
+                self.size -= (before - bucket.len()) as u16;
             },
             _ => { }
         }
@@ -133,9 +215,8 @@ fn fmix32(mut h: u32) -> u32 {
 }
 
 fn get_32_block(bytes: &[u8], index: usize) -> u32 {
-    let b32: &[u32] = unsafe { core::mem::transmute(bytes) };
-
-    return b32[index];
+    let start = index * 4;
+    return u32::from_le_bytes([bytes[start], bytes[start + 1], bytes[start + 2], bytes[start + 3]]);
 }
 
 pub fn murmurhash3_x86_32(bytes: &[u8], seed: u32) -> u32 {
@@ -174,4 +255,142 @@ pub fn murmurhash3_x86_32(bytes: &[u8], seed: u32) -> u32 {
     h1 = fmix32(h1);
 
     return h1;
-}
\ No newline at end of file
+}
+
+/// FNV-1a 32 bit, see http://www.isthe.com/chongo/tech/comp/fnv/
+pub fn fnv1a_32(bytes: &[u8]) -> u32 {
+    let mut hash = 0x811c_9dc5u32;
+    for byte in bytes {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+const XXH_PRIME_1: u32 = 0x9E37_79B1;
+const XXH_PRIME_2: u32 = 0x85EB_CA77;
+const XXH_PRIME_3: u32 = 0xC2B2_AE3D;
+const XXH_PRIME_4: u32 = 0x27D4_EB2F;
+const XXH_PRIME_5: u32 = 0x1656_67B1;
+
+fn xxh_round(acc: u32, input: u32) -> u32 {
+    acc.wrapping_add(input.wrapping_mul(XXH_PRIME_2)).rotate_left(13).wrapping_mul(XXH_PRIME_1)
+}
+
+/// xxHash 32 bit, see https://github.com/Cyan4973/xxHash/blob/dev/doc/xxhash_spec.md
+pub fn xxhash_32(bytes: &[u8], seed: u32) -> u32 {
+    let len = bytes.len();
+    let mut index = 0usize;
+
+    let mut hash = if len >= 16 {
+        let mut v1 = seed.wrapping_add(XXH_PRIME_1).wrapping_add(XXH_PRIME_2);
+        let mut v2 = seed.wrapping_add(XXH_PRIME_2);
+        let mut v3 = seed;
+        let mut v4 = seed.wrapping_sub(XXH_PRIME_1);
+
+        while index + 16 <= len {
+            v1 = xxh_round(v1, get_32_block(bytes, index / 4));
+            v2 = xxh_round(v2, get_32_block(bytes, index / 4 + 1));
+            v3 = xxh_round(v3, get_32_block(bytes, index / 4 + 2));
+            v4 = xxh_round(v4, get_32_block(bytes, index / 4 + 3));
+            index += 16;
+        }
+
+        v1.rotate_left(1).wrapping_add(v2.rotate_left(7)).wrapping_add(v3.rotate_left(12)).wrapping_add(v4.rotate_left(18))
+    } else {
+        seed.wrapping_add(XXH_PRIME_5)
+    };
+
+    hash = hash.wrapping_add(len as u32);
+
+    while index + 4 <= len {
+        hash = hash.wrapping_add(get_32_block(bytes, index / 4).wrapping_mul(XXH_PRIME_3));
+        hash = hash.rotate_left(17).wrapping_mul(XXH_PRIME_4);
+        index += 4;
+    }
+
+    while index < len {
+        hash = hash.wrapping_add((bytes[index] as u32).wrapping_mul(XXH_PRIME_5));
+        hash = hash.rotate_left(11).wrapping_mul(XXH_PRIME_1);
+        index += 1;
+    }
+
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(XXH_PRIME_2);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(XXH_PRIME_3);
+    hash ^= hash >> 16;
+
+    hash
+}
+
+fn sip_round(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]); v[1] = v[1].rotate_left(13); v[1] ^= v[0]; v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]); v[3] = v[3].rotate_left(16); v[3] ^= v[2];
+    v[0] = v[0].wrapping_add(v[3]); v[3] = v[3].rotate_left(21); v[3] ^= v[0];
+    v[2] = v[2].wrapping_add(v[1]); v[1] = v[1].rotate_left(17); v[1] ^= v[2]; v[2] = v[2].rotate_left(32);
+}
+
+/// SipHash-2-4, see https://www.aumasson.jp/siphash/siphash.pdf
+pub fn siphash_2_4(bytes: &[u8], k0: u64, k1: u64) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573
+    ];
+
+    let mut chunks = bytes.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        let m = u64::from_le_bytes(word);
+        v[3] ^= m;
+        sip_round(&mut v);
+        sip_round(&mut v);
+        v[0] ^= m;
+    }
+
+    // last block holds the remaining bytes and the length
+    let mut last = [0u8; 8];
+    let rest = chunks.remainder();
+    last[..rest.len()].copy_from_slice(rest);
+    last[7] = bytes.len() as u8;
+    let m = u64::from_le_bytes(last);
+    v[3] ^= m;
+    sip_round(&mut v);
+    sip_round(&mut v);
+    v[0] ^= m;
+
+    v[2] ^= 0xff;
+    for _x in 0..4 {
+        sip_round(&mut v);
+    }
+
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[test]
+fn key_hashes_work() {
+    assert_eq!(fnv1a_32(b""), 0x811c_9dc5);
+    assert_eq!(fnv1a_32(b"a"), 0xe40c_292c);
+    assert_eq!(xxhash_32(b"", 0), 0x02cc_5d05);
+    assert_eq!(xxhash_32(b"abc", 0), 0x32d1_53ff);
+    let key: Vec<u8> = (0u8..16).collect();
+    let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+    let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+    assert_eq!(siphash_2_4(b"", k0, k1), 0x726f_db47_dd0e_0e31);
+    assert_eq!(siphash_2_4(&key[0..15], k0, k1), 0xa129_ca61_49be_45e5);
+
+    // colliding hashes never select the wrong key
+    let mut map = NP_HashMap::with_hash(NP_Key_Hash::Fnv1a);
+    map.insert("first", 0).unwrap();
+    map.insert("second", 1).unwrap();
+    map.insert("first", 2).unwrap();
+    assert_eq!(map.get("first"), Some(&2));
+    assert_eq!(map.get("second"), Some(&1));
+    assert_eq!(map.get("third"), None);
+    map.delete("first");
+    assert_eq!(map.get("first"), None);
+    assert_eq!(map.size, 1);
+}
@@ -0,0 +1,103 @@
+//! Detect changes to buffers with an HMAC-SHA256 tag
+//!
+//! Buffers that pass through caches, queues or clients you don't control can be changed on the way.  With the `hmac` feature enabled, `NP_Buffer::close_signed` appends a 32 byte [HMAC-SHA256](https://tools.ietf.org/html/rfc2104) tag over the closed buffer bytes, computed with the RustCrypto `hmac` and `sha2` crates, and `NP_Factory::open_verified` only opens bytes whose tag matches the key.
+//!
+//! The tag proves the bytes were signed by someone with the key, it doesn't hide the contents of the buffer.  Keys of any length work, 32 random bytes is a good choice.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "uint8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let key = b"server secret";
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Bill Kerman")?;
+//! new_buffer.set(&["age"], 32u8)?;
+//! let signed: Vec<u8> = new_buffer.close_signed(key);
+//!
+//! let user = factory.open_verified(signed.clone(), key)?;
+//! assert_eq!(user.get(&["name"])?, Some("Bill Kerman"));
+//!
+//! // changed bytes or the wrong key fail to open
+//! let mut changed = signed.clone();
+//! changed[10] ^= 1;
+//! assert!(factory.open_verified(changed, key).is_err());
+//! assert!(factory.open_verified(signed, b"other secret").is_err());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use alloc::vec::Vec;
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+/// Length of the tag appended by `close_signed`
+pub const HMAC_TAG_LEN: usize = 32;
+
+fn new_mac(key: &[u8]) -> Hmac<Sha256> {
+    // keys longer than the SHA-256 block are hashed first, so every length is accepted
+    Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length")
+}
+
+/// Append the tag for `bytes` to the end of them
+pub(crate) fn sign(mut bytes: Vec<u8>, key: &[u8]) -> Vec<u8> {
+    let mut mac = new_mac(key);
+    mac.update(&bytes);
+    bytes.extend_from_slice(&mac.finalize().into_bytes());
+    bytes
+}
+
+/// Check and strip the tag from the end of signed bytes, `None` if it doesn't match
+pub(crate) fn verify(mut bytes: Vec<u8>, key: &[u8]) -> Option<Vec<u8>> {
+    if bytes.len() < HMAC_TAG_LEN {
+        return None;
+    }
+
+    let data_len = bytes.len() - HMAC_TAG_LEN;
+    let mut mac = new_mac(key);
+    mac.update(&bytes[..data_len]);
+
+    // constant time compare, the time taken doesn't leak how much of the tag matched
+    mac.verify_slice(&bytes[data_len..]).ok()?;
+
+    bytes.truncate(data_len);
+    Some(bytes)
+}
+
+#[test]
+fn hmac_works() -> Result<(), crate::error::NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "u32"}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["3"], 42u32)?;
+    let closed = buffer.close();
+
+    let mut buffer = factory.open_buffer(closed.clone());
+    let signed = buffer.close_signed(b"key");
+    assert_eq!(signed.len(), closed.len() + HMAC_TAG_LEN);
+    assert_eq!(verify(signed.clone(), b"key"), Some(closed.clone()));
+
+    buffer = factory.open_verified(signed.clone(), b"key")?;
+    assert_eq!(buffer.get(&["3"])?, Some(42u32));
+
+    // every changed byte is caught, including the tag
+    for x in 0..signed.len() {
+        let mut changed = signed.clone();
+        changed[x] ^= 0x80;
+        assert!(factory.open_verified(changed, b"key").is_err());
+    }
+    assert!(factory.open_verified(signed[..(signed.len() - 1)].to_vec(), b"key").is_err());
+    assert!(factory.open_verified(alloc::vec![0u8; 4], b"key").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,279 @@
+//! Look up list items by the value of a column
+//!
+//! Finding the item in a list of tables that has a given id means reading the id of every item until it turns up.  `NP_Buffer::build_index` reads a column out of every table in a list once and keeps the values sorted, later lookups are a binary search that returns the index of the matching list item.
+//!
+//! Keys are kept in their [sortable encoding](../sortable/index.html), so any scalar or tuple column can be used.  Items that don't have a value for the column are left out of the index.
+//!
+//! Indexes can be saved with `to_bytes` and opened again with `NP_Factory::open_index`.  An index is a snapshot, it doesn't change when the buffer does.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["users", {"type": "list", "of": {"type": "table", "columns": [
+//!             ["email", {"type": "string"}],
+//!             ["age", {"type": "u8"}]
+//!         ]}}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["users", "0", "email"], "jeb@kerbal.space")?;
+//! new_buffer.set(&["users", "1", "email"], "bill@kerbal.space")?;
+//! new_buffer.set(&["users", "2", "age"], 30u8)?;
+//! new_buffer.set(&["users", "3", "email"], "bob@kerbal.space")?;
+//!
+//! let index = new_buffer.build_index(&["users"], "email")?;
+//! assert_eq!(index.len(), 3);
+//! assert_eq!(index.find("bob@kerbal.space")?, Some(3));
+//! assert_eq!(index.find("val@kerbal.space")?, None);
+//!
+//! // keys must match the type of the column
+//! assert!(index.find(30u8).is_err());
+//!
+//! // save the index next to the buffer
+//! let index_bytes = index.to_bytes();
+//! let index = factory.open_index(&index_bytes)?;
+//! assert_eq!(index.find("bill@kerbal.space")?, Some(1));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::collection::list::NP_List;
+use crate::collection::table::NP_Table;
+use crate::error::NP_Error;
+use crate::memory::NP_Memory;
+use crate::pointer::{NP_Cursor, NP_Scalar, NP_Value};
+use crate::schema::NP_Parsed_Schema;
+use crate::sortable;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::borrow::ToOwned;
+
+/// Sorted column values of a list of tables, pointing back to their list index
+///
+/// Check out documentation [here](../index/index.html).
+///
+#[derive(Debug, Clone)]
+pub struct NP_Index<'index> {
+    schema: &'index Vec<NP_Parsed_Schema>,
+    /// schema address of the indexed column
+    schema_addr: usize,
+    /// sortable bytes of each key with the list index it came from, sorted
+    entries: Vec<(Vec<u8>, usize)>
+}
+
+impl<'index> NP_Index<'index> {
+
+    /// Read the column out of every table in the list at `list_cursor`
+    pub(crate) fn build(list_cursor: Option<NP_Cursor>, list_schema_addr: usize, column: &str, memory: &NP_Memory<'index>) -> Result<Self, NP_Error> {
+        let schema_addr = match &memory.schema[list_schema_addr] {
+            NP_Parsed_Schema::List { of, .. } => match &memory.schema[*of] {
+                NP_Parsed_Schema::Table { columns, columns_mapped, .. } => match columns_mapped.get(column) {
+                    Some(x) => columns[*x].2,
+                    None => {
+                        let mut err = String::from("Column not found in table: ");
+                        err.push_str(column);
+                        return Err(NP_Error::BadPath(err));
+                    }
+                },
+                _ => return Err(NP_Error::SchemaMismatch("Indexes can only be built over lists of tables!".into()))
+            },
+            _ => return Err(NP_Error::SchemaMismatch("Indexes can only be built over lists of tables!".into()))
+        };
+        Self::check_key_schema(memory.schema, schema_addr)?;
+
+        let mut entries: Vec<(Vec<u8>, usize)> = Vec::new();
+
+        if let Some(list_cursor) = list_cursor {
+            let mut list_iter = NP_List::new_iter(&list_cursor, memory, true, 0);
+            while let Some((index, item)) = list_iter.step_iter(memory) {
+                let item = match item {
+                    Some(x) if x.get_value(memory).get_addr_value() != 0 => x,
+                    _ => continue
+                };
+                let value = match NP_Table::select(item, column, false, memory)? {
+                    Some(x) if x.get_value(memory).get_addr_value() != 0 => x,
+                    _ => continue
+                };
+                let mut key: Vec<u8> = Vec::new();
+                sortable::encode(Some(value), schema_addr, memory, &mut key)?;
+                entries.push((key, index));
+            }
+        }
+
+        entries.sort();
+
+        Ok(NP_Index { schema: memory.schema, schema_addr, entries })
+    }
+
+    fn check_key_schema(schema: &Vec<NP_Parsed_Schema>, schema_addr: usize) -> Result<(), NP_Error> {
+        match &schema[schema_addr] {
+            NP_Parsed_Schema::None | NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } => {
+                Err(NP_Error::SchemaMismatch("Index columns must be scalars or tuples of scalars!".into()))
+            },
+            _ => Ok(())
+        }
+    }
+
+    /// Open an index saved with `to_bytes`
+    pub(crate) fn from_bytes(schema: &'index Vec<NP_Parsed_Schema>, bytes: &[u8]) -> Result<Self, NP_Error> {
+        let bad_bytes = || NP_Error::Damaged("Index bytes are not valid for this schema!".into());
+
+        let read_u32 = |offset: usize| -> Result<usize, NP_Error> {
+            match bytes.get(offset..(offset + 4)) {
+                Some(x) => Ok(u32::from_be_bytes([x[0], x[1], x[2], x[3]]) as usize),
+                None => Err(bad_bytes())
+            }
+        };
+
+        let schema_addr = read_u32(0)?;
+        if schema_addr >= schema.len() {
+            return Err(bad_bytes());
+        }
+        Self::check_key_schema(schema, schema_addr)?;
+
+        let count = read_u32(4)?;
+        let mut offset = 8usize;
+        let mut entries: Vec<(Vec<u8>, usize)> = Vec::new();
+
+        for _x in 0..count {
+            let index = read_u32(offset)?;
+            let key_len = read_u32(offset + 4)?;
+            offset += 8;
+            match bytes.get(offset..(offset + key_len)) {
+                Some(key) => entries.push((key.to_vec(), index)),
+                None => return Err(bad_bytes())
+            }
+            offset += key_len;
+        }
+
+        if offset != bytes.len() || entries.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(bad_bytes());
+        }
+
+        Ok(NP_Index { schema, schema_addr, entries })
+    }
+
+    /// Save the index so it can be opened again with `NP_Factory::open_index`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(&(self.schema_addr as u32).to_be_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for (key, index) in self.entries.iter() {
+            bytes.extend_from_slice(&(*index as u32).to_be_bytes());
+            bytes.extend_from_slice(&(key.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(key);
+        }
+        bytes
+    }
+
+    /// Encode a key the same way the column values were encoded
+    fn key_bytes<'key, X: 'key>(&self, key: X) -> Result<Vec<u8>, NP_Error> where X: NP_Value<'key> + NP_Scalar {
+        if X::type_idx().1 != *self.schema[self.schema_addr].get_type_key() {
+            let mut err = "TypeError: Attempted to find key of type (".to_owned();
+            err.push_str(X::type_idx().0);
+            err.push_str(") in index of type (");
+            err.push_str(self.schema[self.schema_addr].get_type_data().0);
+            err.push_str(")\n");
+            return Err(NP_Error::SchemaMismatch(err));
+        }
+
+        let memory = NP_Memory::new(None, self.schema);
+        let cursor = NP_Cursor::new(memory.root, self.schema_addr, 0);
+        X::set_value(cursor, &memory, key)?;
+
+        let mut bytes: Vec<u8> = Vec::new();
+        sortable::encode(Some(cursor), self.schema_addr, &memory, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Find the lowest list index with this key
+    pub fn find<'key, X: 'key>(&self, key: X) -> Result<Option<usize>, NP_Error> where X: NP_Value<'key> + NP_Scalar {
+        Ok(self.find_bytes(&self.key_bytes(key)?))
+    }
+
+    /// Find every list index with this key, lowest first
+    pub fn find_all<'key, X: 'key>(&self, key: X) -> Result<Vec<usize>, NP_Error> where X: NP_Value<'key> + NP_Scalar {
+        let key = self.key_bytes(key)?;
+        Ok(self.matching(&key).iter().map(|(_key, index)| *index).collect())
+    }
+
+    /// Find the lowest list index for a key in it's sortable encoding, like the bytes from `NP_Buffer::sortable_bytes`
+    pub fn find_bytes(&self, key: &[u8]) -> Option<usize> {
+        self.matching(key).first().map(|(_key, index)| *index)
+    }
+
+    fn matching(&self, key: &[u8]) -> &[(Vec<u8>, usize)] {
+        let start = self.entries.partition_point(|(x, _index)| x.as_slice() < key);
+        let end = start + self.entries[start..].partition_point(|(x, _index)| x.as_slice() == key);
+        &self.entries[start..end]
+    }
+
+    /// Every key in the index with it's list index, in key order
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], usize)> {
+        self.entries.iter().map(|(key, index)| (key.as_slice(), *index))
+    }
+
+    /// How many list items are in the index
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Is the index empty?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[test]
+fn indexes_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use alloc::vec;
+    use alloc::string::ToString;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "list",
+        "of": {"type": "table", "columns": [
+            ["id", {"type": "i32"}],
+            ["place", {"type": "tuple", "values": [{"type": "string"}, {"type": "u8"}]}],
+            ["tags", {"type": "list", "of": {"type": "string"}}]
+        ]}
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.build_index(&[], "id")?.len(), 0);
+
+    for (index, id) in [7i32, -3, 7, 12].iter().enumerate() {
+        buffer.set(&[index.to_string().as_str(), "id"], *id)?;
+    }
+    buffer.set(&["5", "place", "0"], "moon")?;
+
+    let index = buffer.build_index(&[], "id")?;
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.find(7i32)?, Some(0));
+    assert_eq!(index.find_all(7i32)?, vec![0, 2]);
+    assert_eq!(index.find(-3i32)?, Some(1));
+    assert_eq!(index.find(8i32)?, None);
+    assert_eq!(index.find_bytes(&buffer.sortable_bytes(&["3", "id"])?), Some(3));
+    assert_eq!(index.iter().map(|(_key, index)| index).collect::<Vec<usize>>(), vec![1, 0, 2, 3]);
+
+    let places = buffer.build_index(&[], "place")?;
+    assert_eq!(places.find_bytes(&buffer.sortable_bytes(&["5", "place"])?), Some(5));
+
+    assert!(buffer.build_index(&[], "tags").is_err());
+    assert!(buffer.build_index(&[], "name").is_err());
+    assert!(buffer.build_index(&["0"], "id").is_err());
+
+    // saved indexes are checked when they're opened
+    let bytes = index.to_bytes();
+    assert_eq!(factory.open_index(&bytes)?.find_all(7i32)?, vec![0, 2]);
+    assert!(factory.open_index(&bytes[..(bytes.len() - 1)]).is_err());
+    assert!(factory.open_index(&[0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+
+    Ok(())
+}
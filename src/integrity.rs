@@ -0,0 +1,767 @@
+//! Check the structure of buffers before trusting them
+//!
+//! Reads and writes follow the addresses stored in a buffer, so bytes from the public internet or any other source you don't control could point anywhere.  Reads of damaged buffers don't panic, but they can return garbage, loop over the same items or hand out cursors to bytes that belong to other values.
+//!
+//! `NP_Factory::open_buffer_untrusted` walks the whole buffer before it's opened and checks that:
+//! - Every pointer, vtable, list item, map item and value is inside the buffer, including the bytes after length prefixes.
+//! - Vtable, list and map links go the direction this library writes them, so following them always ends.
+//! - List indexes go up along the list and the tail of each list is it's last item.
+//! - Map keys are inside the buffer and valid UTF-8.
+//! - No value, vtable or item is pointed to more than once, and no two of them share any bytes.
+//!
+//! The first problem found is returned as a `Damaged` error with the path to it.  The walk visits every byte at most once, so checking a hostile buffer takes about as long as checking a good one.
+//!
+//! `NP_Buffer::verify` runs the same checks on a buffer that's already open and returns an `NP_Integrity_Report` with every problem found, which is handy as a sanity check for buffers read back from disk after a crash.  `NP_Factory::salvage` goes one step further and copies everything that passed the checks into a new buffer, and `NP_Buffer::frag_stats` uses the same walk to find the bytes nothing points to anymore.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Bill Kerman")?;
+//! new_buffer.list_push(&["tags"], "pilot")?;
+//! let bytes: Vec<u8> = new_buffer.close();
+//!
+//! let user = factory.open_buffer_untrusted(bytes.clone())?;
+//! assert_eq!(user.get(&["name"])?, Some("Bill Kerman"));
+//!
+//! // cut off before the last value
+//! match factory.open_buffer_untrusted(bytes[..(bytes.len() - 3)].to_vec()) {
+//!     Err(NP_Error::Damaged(message)) => assert!(message.starts_with("Buffer is damaged at 'tags.0'")),
+//!     _ => panic!()
+//! }
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::collection::{list::NP_List, map::NP_Map, table::NP_Table, tuple::NP_Tuple};
+use crate::utils::opt_err;
+use crate::memory::NP_Memory;
+use crate::pointer::{NP_Cursor, NP_Overlay_Kind, inline_size, pointer_bytes};
+use crate::schema::NP_Parsed_Schema;
+use crate::error::NP_Error;
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::format;
+
+/// What kind of problem was found in a buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_Damage_Kind {
+    /// An address points past the end of the buffer or into the header
+    Dangling,
+    /// Two values, items or vtables use the same bytes
+    Overlap,
+    /// A link goes back the wrong direction, following it could loop forever
+    Cycle,
+    /// A value's length prefix or size runs past the end of the buffer
+    Length,
+    /// Anything else, like a list tail that isn't the last item or a map key that isn't valid UTF-8
+    Invalid
+}
+
+/// One problem found in the structure of a buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct NP_Damage {
+    /// What kind of problem this is
+    pub kind: NP_Damage_Kind,
+    /// Path to the value the problem was found in, empty for the root
+    pub path: Vec<String>,
+    /// Address in the buffer the problem was found at
+    pub address: usize,
+    /// Description of the problem
+    pub message: String
+}
+
+/// Every problem found in the structure of a buffer, returned by `NP_Buffer::verify`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NP_Integrity_Report {
+    /// Problems in the order they were found
+    pub problems: Vec<NP_Damage>
+}
+
+impl NP_Integrity_Report {
+    /// True if no problems were found
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Get a `Damaged` error describing the first problem, or `Ok` if there aren't any
+    pub fn into_result(self) -> Result<(), NP_Error> {
+        match self.problems.first() {
+            None => Ok(()),
+            Some(damage) => {
+                let mut message = String::from("Buffer is damaged");
+                if !damage.path.is_empty() {
+                    message.push_str(" at '");
+                    message.push_str(&damage.path.join("."));
+                    message.push('\'');
+                }
+                message.push_str(": ");
+                message.push_str(&damage.message);
+                Err(NP_Error::Damaged(message))
+            }
+        }
+    }
+}
+
+struct NP_Integrity<'i> {
+    memory: &'i NP_Memory<'i>,
+    path: Vec<String>,
+    seen: BTreeSet<usize>,
+    // start, end and path of every allocation that was reached
+    ranges: Vec<(usize, usize, Vec<String>)>,
+    damage: Vec<NP_Damage>
+}
+
+impl<'i> NP_Integrity<'i> {
+
+    fn damaged(&mut self, kind: NP_Damage_Kind, address: usize, message: String) {
+        self.damage.push(NP_Damage { kind, path: self.path.clone(), address, message });
+    }
+
+    /// Are the `len` bytes at `address` inside the buffer?
+    fn fits(&self, address: usize, len: usize) -> bool {
+        match address.checked_add(len) {
+            Some(end) => end <= self.memory.read_bytes().len(),
+            None => false
+        }
+    }
+
+    /// Record that something lives at `address`, false if something else already pointed there
+    fn claim(&mut self, address: usize, what: &str) -> bool {
+        if self.seen.insert(address) {
+            true
+        } else {
+            self.damaged(NP_Damage_Kind::Overlap, address, format!("{} at {} is pointed to more than once", what, address));
+            false
+        }
+    }
+
+    /// Record the bytes used by something that was reached
+    fn allocated(&mut self, address: usize, len: usize) {
+        if len > 0 {
+            self.ranges.push((address, address + len, self.path.clone()));
+        }
+    }
+
+    /// Check the pointer at the cursor and everything it points to
+    fn walk(&mut self, cursor: &NP_Cursor) {
+
+        let memory = self.memory;
+
+        let pointer = cursor.get_value(memory);
+
+        if !self.fits(cursor.buff_addr, pointer.get_size()) {
+            self.damaged(NP_Damage_Kind::Dangling, cursor.buff_addr, format!("Pointer at {} is past the end of the buffer", cursor.buff_addr));
+            return;
+        }
+
+        let value_addr = pointer.get_addr_value() as usize;
+
+        // inline values are stored in the pointer, not at an address
+        if value_addr == 0 || inline_size(&memory.schema[cursor.schema_addr], memory).is_some() {
+            return;
+        }
+
+        if value_addr < memory.root + memory.addr_size.bytes() {
+            self.damaged(NP_Damage_Kind::Dangling, value_addr, format!("Value address {} points into the buffer header", value_addr));
+            return;
+        }
+
+        // the address of a map is it's newest item
+        if let NP_Parsed_Schema::Map { value, .. } = &memory.schema[cursor.schema_addr] {
+            self.map(cursor, value_addr, *value);
+            return;
+        }
+
+        if !self.claim(value_addr, "Value") {
+            return;
+        }
+
+        match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Table { columns, .. } => {
+                let children: Vec<(String, usize)> = columns.iter().map(|column| (column.1.clone(), column.2)).collect();
+                self.vtables(cursor, value_addr, &children);
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                let children: Vec<(String, usize)> = values.iter().enumerate().map(|(index, schema)| (index.to_string(), *schema)).collect();
+                self.vtables(cursor, value_addr, &children);
+            },
+            NP_Parsed_Schema::List { of, .. } => self.list(cursor, value_addr, *of),
+            NP_Parsed_Schema::None | NP_Parsed_Schema::Any { .. } => { },
+            _ => {
+                // scalar sizes include their length prefix, which is read with bounds checks
+                match NP_Cursor::calc_size(cursor, memory) {
+                    Ok(size) => {
+                        let value_size = size.saturating_sub(pointer.get_size());
+                        if self.fits(value_addr, value_size) {
+                            self.allocated(value_addr, value_size);
+                        } else if self.fits(value_addr, 1) {
+                            self.damaged(NP_Damage_Kind::Length, value_addr, format!("Value at {} with {} bytes is past the end of the buffer", value_addr, value_size));
+                        } else {
+                            self.damaged(NP_Damage_Kind::Dangling, value_addr, format!("Value at {} is past the end of the buffer", value_addr));
+                        }
+                    },
+                    Err(e) => self.damaged(NP_Damage_Kind::Length, value_addr, e.into_message())
+                }
+            }
+        }
+    }
+
+    /// Check the vtables of a table or tuple, `children` has the path key and schema of each slot
+    fn vtables(&mut self, cursor: &NP_Cursor, first_vtable: usize, children: &[(String, usize)]) {
+
+        let memory = self.memory;
+        let addr_size = memory.addr_size.bytes();
+
+        let mut vtable_addr = first_vtable;
+        let mut vtable_index = 0usize;
+
+        loop {
+            if !self.fits(vtable_addr, addr_size * 5) {
+                self.damaged(NP_Damage_Kind::Dangling, vtable_addr, format!("Vtable at {} is past the end of the buffer", vtable_addr));
+                return;
+            }
+
+            self.allocated(vtable_addr, addr_size * 5);
+
+            for slot in 0..4 {
+                let index = vtable_index * 4 + slot;
+                if index >= children.len() {
+                    break;
+                }
+                let child = NP_Cursor::new(vtable_addr + slot * addr_size, children[index].1, cursor.schema_addr);
+                self.path.push(children[index].0.clone());
+                self.walk(&child);
+                self.path.pop();
+            }
+
+            let next_vtable = NP_Table::get_vtable(vtable_addr, memory).get_next() as usize;
+
+            if next_vtable == 0 {
+                return;
+            }
+
+            // vtables are always added after the one before them
+            if next_vtable <= vtable_addr {
+                self.damaged(NP_Damage_Kind::Cycle, vtable_addr, format!("Vtable at {} links back to {}", vtable_addr, next_vtable));
+                return;
+            }
+
+            vtable_index += 1;
+
+            if vtable_index * 4 >= children.len() {
+                self.damaged(NP_Damage_Kind::Invalid, vtable_addr, format!("Vtable at {} links to more vtables than the schema has room for", vtable_addr));
+                return;
+            }
+
+            if !self.claim(next_vtable, "Vtable") {
+                return;
+            }
+
+            vtable_addr = next_vtable;
+        }
+    }
+
+    fn list(&mut self, cursor: &NP_Cursor, list_addr: usize, of: usize) {
+
+        let memory = self.memory;
+        let addr_size = memory.addr_size.bytes();
+
+        if !self.fits(list_addr, addr_size * 2) {
+            self.damaged(NP_Damage_Kind::Dangling, list_addr, format!("List at {} is past the end of the buffer", list_addr));
+            return;
+        }
+
+        self.allocated(list_addr, addr_size * 2);
+
+        let list_data = NP_List::get_list(list_addr, memory);
+        let head = list_data.get_head() as usize;
+        let tail = list_data.get_tail() as usize;
+
+        if head == 0 {
+            if tail != 0 {
+                self.damaged(NP_Damage_Kind::Invalid, list_addr, format!("List at {} has a tail without a head", list_addr));
+            }
+            return;
+        }
+
+        let mut item_addr = head;
+        let mut last_index: Option<u8> = None;
+
+        loop {
+            if !self.fits(item_addr, addr_size * 2 + 1) {
+                self.damaged(NP_Damage_Kind::Dangling, item_addr, format!("List item at {} is past the end of the buffer", item_addr));
+                return;
+            }
+
+            let item = pointer_bytes(memory, item_addr, NP_Overlay_Kind::List_Item);
+            let index = item.get_index();
+
+            // indexes only go up, so following the links always ends
+            if let Some(last) = last_index {
+                if index <= last {
+                    self.damaged(NP_Damage_Kind::Cycle, item_addr, format!("List item at {} has index {} after index {}", item_addr, index, last));
+                    return;
+                }
+            }
+            last_index = Some(index);
+
+            if !self.claim(item_addr, "List item") {
+                return;
+            }
+
+            self.path.push(index.to_string());
+            self.allocated(item_addr, addr_size * 2 + 1);
+            self.walk(&NP_Cursor::new(item_addr, of, cursor.schema_addr));
+            self.path.pop();
+
+            let next_addr = pointer_bytes(memory, item_addr, NP_Overlay_Kind::List_Item).get_next_addr() as usize;
+
+            if next_addr == 0 {
+                if item_addr != tail {
+                    self.damaged(NP_Damage_Kind::Invalid, list_addr, format!("List at {} has tail {} but the last item is at {}", list_addr, tail, item_addr));
+                }
+                return;
+            }
+
+            item_addr = next_addr;
+        }
+    }
+
+    fn map(&mut self, cursor: &NP_Cursor, head: usize, value: usize) {
+
+        let memory = self.memory;
+        let addr_size = memory.addr_size.bytes();
+
+        let mut item_addr = head;
+
+        while item_addr != 0 {
+            if !self.fits(item_addr, addr_size * 3) {
+                self.damaged(NP_Damage_Kind::Dangling, item_addr, format!("Map item at {} is past the end of the buffer", item_addr));
+                return;
+            }
+
+            if !self.claim(item_addr, "Map item") {
+                return;
+            }
+
+            let item = pointer_bytes(memory, item_addr, NP_Overlay_Kind::Map_Item);
+            let key_addr = item.get_key_addr() as usize;
+
+            let key = if key_addr == 0 || !self.fits(key_addr, 1) {
+                None
+            } else {
+                let key_len = memory.read_bytes()[key_addr] as usize;
+                match memory.get_bytes(key_addr + 1, key_len) {
+                    Ok(key_bytes) => core::str::from_utf8(key_bytes).ok(),
+                    Err(_) => None
+                }
+            };
+
+            match key {
+                Some(key) => {
+                    self.path.push(key.to_string());
+                    self.allocated(item_addr, addr_size * 3);
+                    self.allocated(key_addr, key.len() + 1);
+                    self.walk(&NP_Cursor::new(item_addr, value, cursor.schema_addr));
+                    self.path.pop();
+                },
+                None => {
+                    self.damaged(NP_Damage_Kind::Invalid, item_addr, format!("Map item at {} has a key that's past the end of the buffer or isn't valid UTF-8", item_addr));
+                    return;
+                }
+            }
+
+            let next_addr = pointer_bytes(memory, item_addr, NP_Overlay_Kind::Map_Item).get_next_addr() as usize;
+
+            // new items go in front of older ones, so links always point back
+            if next_addr >= item_addr {
+                self.damaged(NP_Damage_Kind::Cycle, item_addr, format!("Map item at {} links forward to {}", item_addr, next_addr));
+                return;
+            }
+
+            item_addr = next_addr;
+        }
+    }
+
+    /// Find allocations that share bytes without starting at the same address
+    fn overlaps(&mut self) {
+
+        let mut ranges = core::mem::take(&mut self.ranges);
+        ranges.sort_by_key(|range| range.0);
+
+        let mut last_end = 0usize;
+
+        for (start, end, path) in ranges {
+            if start < last_end {
+                self.damage.push(NP_Damage {
+                    kind: NP_Damage_Kind::Overlap,
+                    path,
+                    address: start,
+                    message: format!("Bytes at {} are used by more than one value", start)
+                });
+            }
+            last_end = last_end.max(end);
+        }
+    }
+}
+
+/// Walk everything below the root of the memory and report every problem found
+pub(crate) fn report(memory: &NP_Memory) -> NP_Integrity_Report {
+
+    let mut integrity = NP_Integrity { memory, path: Vec::new(), seen: BTreeSet::new(), ranges: Vec::new(), damage: Vec::new() };
+
+    if integrity.fits(memory.root, memory.addr_size.bytes()) {
+        integrity.walk(&NP_Cursor::new(memory.root, 0, 0));
+        integrity.overlaps();
+    } else {
+        integrity.damaged(NP_Damage_Kind::Dangling, memory.root, "Buffer is too short to hold a root pointer".to_string());
+    }
+
+    NP_Integrity_Report { problems: integrity.damage }
+}
+
+/// Get the byte ranges used by everything below the root of the memory, sorted and merged so none of them touch
+pub(crate) fn live_ranges(memory: &NP_Memory) -> Result<Vec<(usize, usize)>, NP_Error> {
+
+    let mut integrity = NP_Integrity { memory, path: Vec::new(), seen: BTreeSet::new(), ranges: Vec::new(), damage: Vec::new() };
+
+    if integrity.fits(memory.root, memory.addr_size.bytes()) {
+        integrity.walk(&NP_Cursor::new(memory.root, 0, 0));
+    } else {
+        integrity.damaged(NP_Damage_Kind::Dangling, memory.root, "Buffer is too short to hold a root pointer".to_string());
+    }
+
+    NP_Integrity_Report { problems: integrity.damage }.into_result()?;
+
+    let mut ranges: Vec<(usize, usize)> = Vec::with_capacity(integrity.ranges.len() + 1);
+    // header and root pointer
+    integrity.ranges.push((0, memory.root + memory.addr_size.bytes(), Vec::new()));
+    integrity.ranges.sort_by_key(|range| range.0);
+
+    for (start, end, _path) in integrity.ranges {
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => ranges.push((start, end))
+        }
+    }
+
+    Ok(ranges)
+}
+
+/// Check the structure of everything below the root of the memory, the error describes the first problem found
+pub(crate) fn check(memory: &NP_Memory) -> Result<(), NP_Error> {
+    report(memory).into_result()
+}
+
+/// Copy everything from the damaged memory into the new memory except the subtrees with problems in the report, values that can't be copied are added to the report
+pub(crate) fn salvage(from_memory: &NP_Memory, to_memory: &NP_Memory, report: &mut NP_Integrity_Report) -> Result<(), NP_Error> {
+
+    let lost: Vec<Vec<String>> = report.problems.iter().map(|damage| damage.path.clone()).collect();
+
+    let mut path: Vec<String> = Vec::new();
+    salvage_cursor(NP_Cursor::new(from_memory.root, 0, 0), from_memory, NP_Cursor::new(to_memory.root, 0, 0), to_memory, &mut path, &lost, report)
+}
+
+fn salvage_cursor(from_cursor: NP_Cursor, from_memory: &NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory, path: &mut Vec<String>, lost: &[Vec<String>], report: &mut NP_Integrity_Report) -> Result<(), NP_Error> {
+
+    if lost.iter().any(|damaged| damaged == path) {
+        return Ok(());
+    }
+
+    match &from_memory.schema[from_cursor.schema_addr] {
+        NP_Parsed_Schema::Table { .. } => {
+            let mut table = NP_Table::new_iter(&from_cursor, from_memory);
+            while let Some((idx, key, item)) = table.step_iter(from_memory) {
+                if let Some(real) = item {
+                    path.push(key.to_string());
+                    let new_item = opt_err(NP_Table::select_column(to_cursor, idx, true, to_memory)?)?;
+                    salvage_cursor(real, from_memory, new_item, to_memory, path, lost, report)?;
+                    path.pop();
+                }
+            }
+        },
+        NP_Parsed_Schema::Tuple { .. } => {
+            let mut tuple = NP_Tuple::new_iter(&from_cursor, from_memory);
+            while let Some((idx, item)) = tuple.step_iter(from_memory) {
+                if let Some(real) = item {
+                    path.push(idx.to_string());
+                    let new_item = opt_err(NP_Tuple::select(to_cursor, idx, true, to_memory)?)?;
+                    salvage_cursor(real, from_memory, new_item, to_memory, path, lost, report)?;
+                    path.pop();
+                }
+            }
+        },
+        NP_Parsed_Schema::List { .. } => {
+            NP_List::make_list(&to_cursor, to_memory)?;
+            let mut list = NP_List::new_iter(&from_cursor, from_memory, true, 0);
+            while let Some((index, item)) = list.step_iter(from_memory) {
+                if let Some(real) = item {
+                    path.push(index.to_string());
+                    let (_new_index, new_item) = opt_err(NP_List::push(&to_cursor, to_memory, Some(index))?)?;
+                    salvage_cursor(real, from_memory, new_item, to_memory, path, lost, report)?;
+                    path.pop();
+                }
+            }
+        },
+        NP_Parsed_Schema::Map { .. } => {
+            // insert the oldest items first so the salvaged map keeps it's order
+            let mut items: Vec<(&str, NP_Cursor)> = Vec::new();
+            let mut map = NP_Map::new_iter(&from_cursor, from_memory);
+            while let Some((key, item)) = map.step_iter(from_memory) {
+                items.push((key, item));
+            }
+            for (key, item) in items.into_iter().rev() {
+                path.push(key.to_string());
+                let new_item = NP_Map::insert(&to_cursor, to_memory, key)?;
+                salvage_cursor(item, from_memory, new_item, to_memory, path, lost, report)?;
+                path.pop();
+            }
+        },
+        _ => {
+            // the structure is fine but the value itself might not be, like strings that aren't UTF-8
+            if let Err(e) = NP_Cursor::compact(from_cursor, from_memory, to_cursor, to_memory) {
+                report.problems.push(NP_Damage { kind: NP_Damage_Kind::Invalid, path: path.clone(), address: from_cursor.get_value(from_memory).get_addr_value() as usize, message: e.into_message() });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn untrusted_buffers_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["name", {"type": "string"}],
+            ["tags", {"type": "list", "of": {"type": "string"}}],
+            ["meta", {"type": "map", "value": {"type": "u32"}}],
+            ["pair", {"type": "tuple", "values": [{"type": "i64"}, {"type": "bytes"}]}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["name"], "hello")?;
+    buffer.list_push(&["tags"], "one")?;
+    buffer.list_push(&["tags"], "two")?;
+    buffer.set(&["meta", "jeb"], 5u32)?;
+    buffer.set(&["meta", "bill"], 7u32)?;
+    buffer.set(&["pair", "1"], &[1u8, 2, 3][..])?;
+    let bytes = buffer.close();
+
+    let buffer = factory.open_buffer_untrusted(bytes.clone())?;
+    assert_eq!(buffer.get::<&str>(&["tags", "1"])?, Some("two"));
+
+    let column = |memory: &NP_Memory, index: usize| -> Result<usize, NP_Error> {
+        let cursor = NP_Table::select_column(NP_Cursor::new(memory.root, 0, 0), index, false, memory)?.unwrap();
+        Ok(cursor.get_value(memory).get_addr_value() as usize)
+    };
+
+    // list that loops back to it's head
+    let memory = NP_Memory::existing(bytes.clone(), &factory.schema.parsed);
+    let list = NP_List::get_list(column(&memory, 1)?, &memory);
+    pointer_bytes(&memory, list.get_tail() as usize, NP_Overlay_Kind::List_Item).set_next_addr(list.get_head());
+    assert!(check(&memory).unwrap_err().message().starts_with("Buffer is damaged at 'tags': List item"));
+
+    // two map items with the same value
+    let memory = NP_Memory::existing(bytes.clone(), &factory.schema.parsed);
+    let head = pointer_bytes(&memory, column(&memory, 2)?, NP_Overlay_Kind::Map_Item);
+    let next = pointer_bytes(&memory, head.get_next_addr() as usize, NP_Overlay_Kind::Map_Item);
+    head.set_addr_value(next.get_addr_value());
+    assert!(check(&memory).unwrap_err().message().ends_with("is pointed to more than once"));
+
+    // a vtable linking back to itself
+    let memory = NP_Memory::existing(bytes.clone(), &factory.schema.parsed);
+    let vtable = column(&memory, 3)?;
+    NP_Table::get_vtable(vtable, &memory).set_next(vtable as u32);
+    assert!(check(&memory).unwrap_err().message().starts_with("Buffer is damaged at 'pair': Vtable"));
+
+    // nothing gets past the end, however the buffer is cut
+    for len in 0..bytes.len() {
+        assert!(factory.open_buffer_untrusted(bytes[..len].to_vec()).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn integrity_reports_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use alloc::vec;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["name", {"type": "string"}],
+            ["tags", {"type": "list", "of": {"type": "string"}}],
+            ["meta", {"type": "map", "value": {"type": "u32"}}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["name"], "hello world")?;
+    buffer.list_push(&["tags"], "one")?;
+    buffer.list_push(&["tags"], "two")?;
+    buffer.set(&["meta", "jeb"], 5u32)?;
+    assert!(buffer.verify()?.is_ok());
+    let bytes = buffer.close();
+
+    let column = |memory: &NP_Memory, index: usize| -> Result<usize, NP_Error> {
+        let cursor = NP_Table::select_column(NP_Cursor::new(memory.root, 0, 0), index, false, memory)?.unwrap();
+        Ok(cursor.get_value(memory).get_addr_value() as usize)
+    };
+
+    let memory = NP_Memory::existing(bytes.clone(), &factory.schema.parsed);
+
+    // map value pointing into the middle of the name
+    let name = column(&memory, 0)?;
+    pointer_bytes(&memory, column(&memory, 2)?, NP_Overlay_Kind::Map_Item).set_addr_value(name as u32 + 3);
+
+    // list looping back to it's head
+    let list = NP_List::get_list(column(&memory, 1)?, &memory);
+    pointer_bytes(&memory, list.get_tail() as usize, NP_Overlay_Kind::List_Item).set_next_addr(list.get_head());
+
+    let found = report(&memory);
+    let kinds: Vec<NP_Damage_Kind> = found.problems.iter().map(|damage| damage.kind).collect();
+    assert_eq!(kinds, vec![NP_Damage_Kind::Cycle, NP_Damage_Kind::Overlap]);
+    assert_eq!(found.problems[0].path, vec!["tags"]);
+    assert_eq!(found.problems[1].address, name + 3);
+    assert!(found.into_result().is_err());
+
+    // dangling addresses
+    let memory = NP_Memory::existing(bytes.clone(), &factory.schema.parsed);
+    let tags = NP_Table::select_column(NP_Cursor::new(memory.root, 0, 0), 1, false, &memory)?.unwrap();
+    tags.get_value(&memory).set_addr_value(bytes.len() as u32 + 10);
+    let found = report(&memory);
+    assert_eq!(found.problems.len(), 1);
+    assert_eq!(found.problems[0].kind, NP_Damage_Kind::Dangling);
+
+    Ok(())
+}
+
+#[test]
+fn salvage_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use alloc::vec;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["name", {"type": "string"}],
+            ["tags", {"type": "list", "of": {"type": "string"}}],
+            ["meta", {"type": "map", "value": {"type": "string"}}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["name"], "hello")?;
+    buffer.list_push(&["tags"], "one")?;
+    buffer.list_push(&["tags"], "two")?;
+    buffer.list_push(&["tags"], "three")?;
+    buffer.set(&["meta", "jeb"], "pilot")?;
+    buffer.set(&["meta", "bill"], "engineer")?;
+    let bytes = buffer.close();
+
+    // nothing is lost from a good buffer
+    let (recovered, lost) = factory.salvage(bytes.clone())?;
+    assert!(lost.is_ok());
+    assert!(recovered.deep_eq(&factory.open_buffer(bytes.clone()))?);
+
+    // the middle list item and one map value point past the end
+    let memory = NP_Memory::existing(bytes.clone(), &factory.schema.parsed);
+    let root = NP_Cursor::new(memory.root, 0, 0);
+    let item = NP_List::select(NP_Table::select_column(root, 1, false, &memory)?.unwrap(), 1, false, &memory)?.unwrap().1.unwrap();
+    item.get_value(&memory).set_addr_value(bytes.len() as u32 + 10);
+    let value = NP_Map::select(NP_Table::select_column(root, 2, false, &memory)?.unwrap(), "bill", false, &memory)?.unwrap();
+    value.get_value(&memory).set_addr_value(bytes.len() as u32 - 1);
+
+    let (recovered, lost) = factory.salvage(memory.dump())?;
+    let paths: Vec<Vec<String>> = lost.problems.iter().map(|damage| damage.path.clone()).collect();
+    assert_eq!(paths, vec![vec!["tags", "1"], vec!["meta", "bill"]]);
+    assert_eq!(recovered.get::<&str>(&["name"])?, Some("hello"));
+    assert_eq!(recovered.get::<&str>(&["tags", "0"])?, Some("one"));
+    assert_eq!(recovered.get::<&str>(&["tags", "1"])?, None);
+    assert_eq!(recovered.get::<&str>(&["tags", "2"])?, Some("three"));
+    assert_eq!(recovered.get::<&str>(&["meta", "jeb"])?, Some("pilot"));
+    assert_eq!(recovered.get::<&str>(&["meta", "bill"])?, None);
+    assert!(recovered.verify()?.is_ok());
+
+    // a looping list is lost entirely
+    let memory = NP_Memory::existing(bytes.clone(), &factory.schema.parsed);
+    let list = NP_List::get_list(NP_Table::select_column(NP_Cursor::new(memory.root, 0, 0), 1, false, &memory)?.unwrap().get_value(&memory).get_addr_value() as usize, &memory);
+    pointer_bytes(&memory, list.get_tail() as usize, NP_Overlay_Kind::List_Item).set_next_addr(list.get_head());
+
+    let (recovered, lost) = factory.salvage(memory.dump())?;
+    assert_eq!(lost.problems.len(), 1);
+    assert_eq!(recovered.length(&["tags"])?, None);
+    assert_eq!(recovered.get::<&str>(&["meta", "bill"])?, Some("engineer"));
+
+    // so is everything in a buffer without a root pointer
+    let (recovered, lost) = factory.salvage(vec![1])?;
+    assert_eq!(lost.problems[0].kind, NP_Damage_Kind::Dangling);
+    assert_eq!(recovered.get::<&str>(&["name"])?, None);
+
+    Ok(())
+}
+
+#[test]
+fn frag_stats_work() -> Result<(), NP_Error> {
+    use crate::{NP_Factory, NP_Address_Size};
+
+    for size in [NP_Address_Size::U8, NP_Address_Size::U16, NP_Address_Size::U32].iter() {
+        let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+            ["name",  {"type": "string"}],
+            ["tags",  {"type": "list", "of": {"type": "string"}}],
+            ["attrs", {"type": "map", "value": {"type": "u16"}}],
+            ["pair",  {"type": "tuple", "values": [{"type": "u8"}, {"type": "bool"}]}]
+        ]}"#)?.with_address_size(size.clone());
+
+        let mut buffer = factory.empty_buffer(None);
+        let stats = buffer.frag_stats()?;
+        assert_eq!((stats.live_bytes, stats.dead_bytes, stats.dead_regions), (buffer.read_bytes().len(), 0, 0));
+
+        buffer.set(&["name"], "first")?;
+        buffer.set(&["tags", "0"], "a")?;
+        buffer.set(&["attrs", "x"], 1u16)?;
+        buffer.set(&["pair", "0"], 2u8)?;
+        buffer.set(&["name"], "second name")?;
+        buffer.set(&["tags", "0"], "bb")?;
+        buffer.set(&["attrs", "y"], 3u16)?;
+        buffer.del(&["attrs", "x"])?;
+
+        let stats = buffer.frag_stats()?;
+        assert_eq!(stats.total_bytes, buffer.read_bytes().len());
+        assert_eq!(stats.live_bytes + stats.dead_bytes, stats.total_bytes);
+        assert_eq!(stats.dead_bytes, buffer.calc_bytes()?.wasted_bytes);
+        // the old name, the old tag and the deleted map item
+        assert_eq!(stats.dead_regions, 3);
+        assert!(stats.largest_dead_region >= 7 && stats.largest_dead_region < stats.dead_bytes);
+
+        buffer.compact(None)?;
+        assert_eq!(buffer.frag_stats()?.dead_bytes, 0);
+    }
+
+    // damaged buffers can't be measured
+    let factory = NP_Factory::new(r#"{"type": "string"}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&[], "hello")?;
+    let mut bytes = buffer.close();
+    bytes.truncate(bytes.len() - 2);
+    assert!(matches!(factory.open_buffer(bytes).frag_stats(), Err(NP_Error::Damaged(_))));
+
+    Ok(())
+}
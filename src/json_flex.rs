@@ -37,10 +37,12 @@
 use alloc::vec::Vec;
 use alloc::string::String;
 use alloc::boxed::Box;
+use alloc::format;
 use alloc::borrow::ToOwned;
 use alloc::string::ToString;
 use core::str::FromStr;
 use core::ops::Index;
+use core::fmt::Write;
 use crate::{error::NP_Error};
 
 /// The JSON representation of a JS Map
@@ -125,6 +127,95 @@ pub enum NP_JSON {
 }
 
 
+/// Options used to control how JSON is generated.
+/// 
+/// `indent`, `omit_null` and `canonical` are used by `NP_JSON::stringify_with`, `emit_defaults` is used by `NP_Buffer::json_encode_with`.
+/// 
+#[derive(Debug, Clone)]
+pub struct NP_JSON_Options {
+    /// How many spaces to indent each level of objects and arrays.  When this is zero (the default) everything is put on a single line.
+    pub indent: usize,
+    /// Leave out object keys that have `null` values.  Array items are always kept so the indexes don't change.
+    pub omit_null: bool,
+    /// Provide default values from the schema where a value hasn't been set (the default).  When this is `false`, unset values are left out of the JSON.
+    pub emit_defaults: bool,
+    /// Generate deterministic output: object keys are sorted, floats always have a decimal point (non finite floats become `null`) and control characters in strings are escaped.
+    /// Two JSON values with the same content always stringify to the same string in this mode, so the output can be hashed or signed.
+    pub canonical: bool
+}
+
+impl Default for NP_JSON_Options {
+    fn default() -> Self {
+        NP_JSON_Options { indent: 0, omit_null: false, emit_defaults: true, canonical: false }
+    }
+}
+
+impl NP_JSON_Options {
+    /// Options for canonical output, everything on a single line with sorted keys.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::json_flex::NP_JSON_Options;
+    ///
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "map",
+    ///    "value": {"type": "double"}
+    /// }"#)?;
+    ///
+    /// let mut buffer_a = factory.empty_buffer(None);
+    /// buffer_a.set(&["b"], 2f64)?;
+    /// buffer_a.set(&["a"], 1.5f64)?;
+    ///
+    /// let mut buffer_b = factory.empty_buffer(None);
+    /// buffer_b.set(&["a"], 1.5f64)?;
+    /// buffer_b.set(&["b"], 2f64)?;
+    ///
+    /// let options = NP_JSON_Options::canonical();
+    ///
+    /// let json_a = buffer_a.json_encode(&[])?.stringify_with(&options);
+    /// let json_b = buffer_b.json_encode(&[])?.stringify_with(&options);
+    ///
+    /// assert_eq!(json_a, "{\"a\":1.5,\"b\":2.0}");
+    /// assert_eq!(json_a, json_b);
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    ///
+    pub fn canonical() -> Self {
+        NP_JSON_Options { canonical: true, ..Default::default() }
+    }
+}
+
+fn canonical_string(value: &str, string: &mut String) {
+    string.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            '\r' => string.push_str("\\r"),
+            '\t' => string.push_str("\\t"),
+            '\u{08}' => string.push_str("\\b"),
+            '\u{0c}' => string.push_str("\\f"),
+            c if (c as u32) < 0x20 => { write!(string, "\\u{:04x}", c as u32).unwrap_or(()); },
+            c => string.push(c)
+        }
+    }
+    string.push('"');
+}
+
+fn canonical_float(value: f64, string: &mut String) {
+    if value.is_finite() == false {
+        string.push_str("null");
+        return;
+    }
+    let number = value.to_string();
+    string.push_str(number.as_str());
+    if number.contains('.') == false {
+        string.push_str(".0");
+    }
+}
 
 impl NP_JSON {
 
@@ -351,6 +442,91 @@ impl NP_JSON {
             &NP_JSON::True => "true".to_owned(),
         }
     }
+
+    /// Stringify this JSON object and it's children with the provided options
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::json_flex::{json_decode, NP_JSON_Options};
+    /// 
+    /// let json = json_decode(String::from(r#"{"name": "Jeb", "age": null, "tags": [1, 2]}"#))?;
+    /// 
+    /// let options = NP_JSON_Options { indent: 2, omit_null: true, ..Default::default() };
+    /// 
+    /// assert_eq!(json.stringify_with(&options), "{\n  \"name\": \"Jeb\",\n  \"tags\": [\n    1,\n    2\n  ]\n}");
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn stringify_with(&self, options: &NP_JSON_Options) -> String {
+        let mut string = String::new();
+        self.stringify_into(options, 0, &mut string);
+        string
+    }
+
+    fn stringify_into(&self, options: &NP_JSON_Options, depth: usize, string: &mut String) {
+
+        let new_line = |string: &mut String, depth: usize| {
+            if options.indent > 0 {
+                string.push('\n');
+                for _x in 0..(options.indent * depth) {
+                    string.push(' ');
+                }
+            }
+        };
+
+        match self {
+            NP_JSON::Dictionary(map) => {
+                let mut is_first = true;
+                let mut values: Vec<&(String, NP_JSON)> = map.values.iter().collect();
+                if options.canonical {
+                    values.sort_by(|a, b| a.0.cmp(&b.0));
+                }
+                string.push('{');
+                for (k, v) in values {
+                    if options.omit_null && v.is_null() {
+                        continue;
+                    }
+                    if is_first {
+                        is_first = false;
+                    } else {
+                        string.push(',');
+                    }
+                    new_line(string, depth + 1);
+                    if options.canonical {
+                        canonical_string(k, string);
+                    } else {
+                        string.push('"');
+                        string.push_str(k.replace("\"", "\\\"").as_str());
+                        string.push('"');
+                    }
+                    string.push_str(if options.indent > 0 { ": " } else { ":" });
+                    v.stringify_into(options, depth + 1, string);
+                }
+                if is_first == false {
+                    new_line(string, depth);
+                }
+                string.push('}');
+            },
+            NP_JSON::Array(items) => {
+                string.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        string.push(',');
+                    }
+                    new_line(string, depth + 1);
+                    item.stringify_into(options, depth + 1, string);
+                }
+                if items.len() > 0 {
+                    new_line(string, depth);
+                }
+                string.push(']');
+            },
+            NP_JSON::String(value) if options.canonical => canonical_string(value, string),
+            NP_JSON::Float(value) if options.canonical => canonical_float(*value, string),
+            _ => string.push_str(&self.stringify())
+        }
+    }
 }
 
 impl Index<usize> for NP_JSON {
@@ -493,6 +669,16 @@ fn recursive(v: &mut NP_JSON,
     Ok(is_find)
 }
 
+/// Parsing recurses once for every array or object the value is nested in, so deeper JSON is rejected before it can overflow the stack
+pub const NP_MAX_JSON_DEPTH: usize = 512;
+
+fn check_depth(chain: &[char]) -> Result<(), NP_Error> {
+    if chain.iter().filter(|kind| **kind == 'a' || **kind == 'd').count() >= NP_MAX_JSON_DEPTH {
+        return Err(NP_Error::Parse(format!("JSON is nested more than {} arrays or objects deep!", NP_MAX_JSON_DEPTH)));
+    }
+    Ok(())
+}
+
 /// Parse a JSON string into a JSON object in memory
 pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
 
@@ -554,6 +740,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                     _ => {
 
                         let a = 'a';
+                        check_depth(&chain)?;
                         chain.push(a);
                         last_chain = a;
                         a_chain.push(0);
@@ -617,7 +804,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(s_true.pop())?;
                         s_true = s_true.trim().to_string();
                         if s_true != "true" {
-                            return Err(NP_Error::new("JSON Parse Error"));
+                            return Err(NP_Error::Parse("JSON Parse Error".into()));
                         }
 
                         let a_nest = 0i64;
@@ -661,7 +848,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(s_false.pop())?;
                         s_false = s_false.trim().to_string();
                         if s_false != "false" {
-                            return Err(NP_Error::new("JSON Parse Error"));
+                            return Err(NP_Error::Parse("JSON Parse Error".into()));
                         }
 
                         let a_nest = 0i64;
@@ -706,7 +893,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(s_null.pop())?;
                         s_null = s_null.trim().to_string();
                         if s_null != "null" {
-                            return Err(NP_Error::new("JSON Parse Error"));
+                            return Err(NP_Error::Parse("JSON Parse Error".into()));
                         }
 
                         let a_nest = 0i64;
@@ -836,7 +1023,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(a_chain.pop())?;
                     }
 
-                    _ => return Err(NP_Error::new("JSON Parse Error: Unknown chain from Array")),
+                    _ => return Err(NP_Error::Parse("JSON Parse Error: Unknown chain from Array".into())),
                 }
 
                 last_active_char = c.clone();
@@ -853,6 +1040,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                     'v' => {
 
                         let a = 'd';
+                        check_depth(&chain)?;
                         chain.push(a);
                         last_chain = a;
 
@@ -895,6 +1083,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                     _ => {
 
                         let a = 'd';
+                        check_depth(&chain)?;
                         chain.push(a);
                         last_chain = a;
 
@@ -959,7 +1148,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(s_true.pop())?;
                         s_true = s_true.trim().to_string();
                         if s_true != "true" {
-                            return Err(NP_Error::new("JSON Parse Error"));
+                            return Err(NP_Error::Parse("JSON Parse Error".into()));
                         }
 
                         NP_Error::unwrap(chain.pop())?;
@@ -1010,7 +1199,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(s_false.pop())?;
                         s_false = s_false.trim().to_string();
                         if s_false != "false" {
-                            return Err(NP_Error::new("JSON Parse Error"));
+                            return Err(NP_Error::Parse("JSON Parse Error".into()));
                         }
 
                         NP_Error::unwrap(chain.pop())?;
@@ -1062,7 +1251,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(s_null.pop())?;
                         s_null = s_null.trim().to_string();
                         if s_null != "null" {
-                            return Err(NP_Error::new("JSON Parse Error"));
+                            return Err(NP_Error::Parse("JSON Parse Error".into()));
                         }
 
                         NP_Error::unwrap(chain.pop())?;
@@ -1214,7 +1403,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(s_true.pop())?;
                         s_true = s_true.trim().to_string();
                         if s_true != "true" {
-                            return Err(NP_Error::new("JSON Parse Error"));
+                            return Err(NP_Error::Parse("JSON Parse Error".into()));
                         }
 
                         if last_chain == 't' {
@@ -1277,7 +1466,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(s_false.pop())?;
                         s_false = s_false.trim().to_string();
                         if s_false != "false" {
-                            return Err(NP_Error::new("JSON Parse Error"));
+                            return Err(NP_Error::Parse("JSON Parse Error".into()));
                         }
 
                         if last_chain == 'f' {
@@ -1339,7 +1528,7 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
                         NP_Error::unwrap(s_null.pop())?;
                         s_null = s_null.trim().to_string();
                         if s_null != "null" {
-                            return Err(NP_Error::new("JSON Parse Error"));
+                            return Err(NP_Error::Parse("JSON Parse Error".into()));
                         }
 
                         if last_chain == '0' {
@@ -1816,4 +2005,17 @@ pub fn json_decode<'json>(text: String) -> Result<Box<NP_JSON>, NP_Error> {
 
 
     Ok(ret)
+}
+
+/// Parse JSON text of any value, `json_decode` only takes objects and arrays at the top level
+#[cfg(any(feature = "wasm", feature = "pyo3"))]
+pub(crate) fn json_decode_value(text: &str) -> Result<NP_JSON, NP_Error> {
+    let mut wrapped = String::with_capacity(text.len() + 2);
+    wrapped.push('[');
+    wrapped.push_str(text);
+    wrapped.push(']');
+    match *json_decode(wrapped)? {
+        NP_JSON::Array(mut values) if values.len() == 1 => Ok(values.remove(0)),
+        _ => Err(NP_Error::Parse("Value isn't a single JSON value!".into()))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,359 @@
+//! JSON-RPC 2.0 envelopes for buffers
+//!
+//! Services built on NoProto often still need to talk to [JSON-RPC 2.0](https://www.jsonrpc.org/specification) clients.  `NP_Json_Rpc_Request` and `NP_Json_Rpc_Response` wrap the JSON export of a buffer in a request or response envelope, and open the `params` or `result` of incoming envelopes straight into a buffer through the schema.
+//!
+//! Parsing a request returns an `NP_Json_Rpc_Error` with the error code the spec asks for, so a server can send it right back to the client.  Everything else returns `NP_Error` like the rest of the library.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::json_rpc::{NP_Json_Rpc_Request, NP_Json_Rpc_Response, NP_Json_Rpc_Id};
+//!
+//! let user = NP_Factory::new(r#"{"type": "table", "columns": [["name", {"type": "string"}], ["age", {"type": "u8"}]]}"#)?;
+//!
+//! // client
+//! let mut params = user.empty_buffer(None);
+//! params.set(&["name"], "Jeb")?;
+//! let request = NP_Json_Rpc_Request::new("users.add", Some(NP_Json_Rpc_Id::Number(1)), &params)?;
+//! assert_eq!(request.stringify(), r#"{"jsonrpc":"2.0","method":"users.add","params":{"name":"Jeb","age":null},"id":1}"#);
+//!
+//! // server
+//! let request = NP_Json_Rpc_Request::parse(r#"{"jsonrpc": "2.0", "method": "users.add", "params": {"name": "Bill", "age": 40}, "id": 2}"#)?;
+//! let params = request.open_params(&user)?;
+//! assert_eq!(params.get::<u8>(&["age"])?, Some(40));
+//!
+//! // params that don't fit the schema get the invalid params error code
+//! let bad = NP_Json_Rpc_Request::parse(r#"{"jsonrpc": "2.0", "method": "users.add", "params": {"age": "old"}, "id": 3}"#)?;
+//! assert_eq!(bad.open_params(&user).err().map(|err| err.code), Some(-32602));
+//!
+//! let response = NP_Json_Rpc_Response::new(request.id.unwrap_or(NP_Json_Rpc_Id::Null), &params)?;
+//! let response_json = response.stringify();
+//!
+//! // client
+//! let response = NP_Json_Rpc_Response::parse(&response_json)?;
+//! assert_eq!(response.id, NP_Json_Rpc_Id::Number(2));
+//! assert_eq!(response.open_result(&user)?.get::<&str>(&["name"])?, Some("Bill"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::json_flex::{json_decode, JSMAP, NP_JSON};
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+
+/// Invalid JSON was received
+pub const NP_JSON_RPC_PARSE_ERROR: i64 = -32700;
+/// The JSON isn't a valid request object
+pub const NP_JSON_RPC_INVALID_REQUEST: i64 = -32600;
+/// The method doesn't exist
+pub const NP_JSON_RPC_METHOD_NOT_FOUND: i64 = -32601;
+/// The params don't fit the schema of the method
+pub const NP_JSON_RPC_INVALID_PARAMS: i64 = -32602;
+/// Internal error of the server
+pub const NP_JSON_RPC_INTERNAL_ERROR: i64 = -32603;
+
+/// Id of a request, responses have the id of the request they answer
+#[derive(Debug, Clone, PartialEq)]
+pub enum NP_Json_Rpc_Id {
+    /// Number id
+    Number(i64),
+    /// String id
+    String(String),
+    /// Null id, used in responses to requests that couldn't be parsed
+    Null
+}
+
+impl NP_Json_Rpc_Id {
+
+    fn from_json(json: &NP_JSON) -> Option<Self> {
+        match json {
+            NP_JSON::Integer(x) => Some(NP_Json_Rpc_Id::Number(*x)),
+            NP_JSON::String(x) => Some(NP_Json_Rpc_Id::String(x.clone())),
+            NP_JSON::Null => Some(NP_Json_Rpc_Id::Null),
+            _ => None
+        }
+    }
+
+    fn to_json(&self) -> NP_JSON {
+        match self {
+            NP_Json_Rpc_Id::Number(x) => NP_JSON::Integer(*x),
+            NP_Json_Rpc_Id::String(x) => NP_JSON::String(x.clone()),
+            NP_Json_Rpc_Id::Null => NP_JSON::Null
+        }
+    }
+}
+
+/// The error object of a JSON-RPC response
+#[derive(Debug, Clone, PartialEq)]
+pub struct NP_Json_Rpc_Error {
+    /// Error code, the spec reserves -32768 to -32000
+    pub code: i64,
+    /// Short description of the error
+    pub message: String
+}
+
+impl NP_Json_Rpc_Error {
+
+    /// Create an error object
+    pub fn new(code: i64, message: &str) -> Self {
+        NP_Json_Rpc_Error { code, message: message.to_owned() }
+    }
+
+    fn invalid_request(message: &str) -> Self {
+        Self::new(NP_JSON_RPC_INVALID_REQUEST, message)
+    }
+
+    fn to_json(&self) -> NP_JSON {
+        let mut error = JSMAP::new();
+        error.insert("code".to_owned(), NP_JSON::Integer(self.code));
+        error.insert("message".to_owned(), NP_JSON::String(self.message.clone()));
+        NP_JSON::Dictionary(error)
+    }
+}
+
+impl From<NP_Json_Rpc_Error> for NP_Error {
+    fn from(err: NP_Json_Rpc_Error) -> NP_Error {
+        NP_Error::Parse(err.message)
+    }
+}
+
+/// Start an envelope object with the version member
+fn envelope() -> JSMAP {
+    let mut json = JSMAP::new();
+    json.insert("jsonrpc".to_owned(), NP_JSON::String("2.0".to_owned()));
+    json
+}
+
+/// Parse JSON text into an envelope object, checking the version member
+fn parse_envelope(json: &str) -> Result<JSMAP, NP_Json_Rpc_Error> {
+    let parse_error = NP_Json_Rpc_Error::new(NP_JSON_RPC_PARSE_ERROR, "Parse error");
+    // the decoder only reads objects and arrays, anything else comes back as null
+    if json.trim().is_empty() {
+        return Err(parse_error);
+    }
+    let json = match json_decode(json.to_owned()) {
+        Ok(x) => *x,
+        Err(_) => return Err(parse_error)
+    };
+    match json {
+        NP_JSON::Null => Err(parse_error),
+        NP_JSON::Dictionary(map) => match map.get("jsonrpc") {
+            Some(NP_JSON::String(version)) if version == "2.0" => Ok(map),
+            _ => Err(NP_Json_Rpc_Error::invalid_request("Envelope isn't JSON-RPC 2.0"))
+        },
+        _ => Err(NP_Json_Rpc_Error::invalid_request("Envelope isn't an object"))
+    }
+}
+
+/// Export a buffer for `params` or `result`
+fn export(buffer: &NP_Buffer) -> Result<NP_JSON, NP_Error> {
+    let json = buffer.json_encode(&[])?;
+    match json {
+        NP_JSON::Dictionary(_) | NP_JSON::Array(_) | NP_JSON::Null => Ok(json),
+        _ => Err(NP_Error::SchemaMismatch("JSON-RPC params and results need a table, tuple, list or map schema!".into()))
+    }
+}
+
+/// Import `params` or `result` into a new buffer
+fn import<'buffer>(factory: &'buffer NP_Factory, json: &NP_JSON) -> Result<NP_Buffer<'buffer>, NP_Error> {
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set_json(&[], json)?;
+    Ok(buffer)
+}
+
+/// A JSON-RPC request or notification
+///
+/// Check out documentation [here](../json_rpc/index.html).
+///
+#[derive(Debug)]
+pub struct NP_Json_Rpc_Request {
+    /// Name of the method
+    pub method: String,
+    /// Id of the request, `None` for notifications
+    pub id: Option<NP_Json_Rpc_Id>,
+    /// The params, `Null` if there weren't any
+    pub params: NP_JSON
+}
+
+impl NP_Json_Rpc_Request {
+
+    /// Create a request with the contents of a buffer as the params, leave out the id for a notification.
+    ///
+    /// The buffer schema has to be a collection, JSON-RPC params are always an object or array.  Empty buffers leave out the params.
+    ///
+    pub fn new(method: &str, id: Option<NP_Json_Rpc_Id>, params: &NP_Buffer) -> Result<Self, NP_Error> {
+        Ok(NP_Json_Rpc_Request { method: method.to_owned(), id, params: export(params)? })
+    }
+
+    /// Parse JSON text of a request, errors have the code and message the spec asks for
+    pub fn parse(json: &str) -> Result<Self, NP_Json_Rpc_Error> {
+        let map = parse_envelope(json)?;
+
+        let method = match map.get("method") {
+            Some(NP_JSON::String(x)) => x.clone(),
+            _ => return Err(NP_Json_Rpc_Error::invalid_request("Request method must be a string"))
+        };
+
+        let id = match map.get("id") {
+            Some(x) => Some(NP_Json_Rpc_Id::from_json(x).ok_or_else(|| NP_Json_Rpc_Error::invalid_request("Request id must be a string, integer or null"))?),
+            None => None
+        };
+
+        let params = match map.get("params") {
+            Some(x @ NP_JSON::Dictionary(_)) | Some(x @ NP_JSON::Array(_)) => x.clone(),
+            None => NP_JSON::Null,
+            Some(_) => return Err(NP_Json_Rpc_Error::invalid_request("Request params must be an object or array"))
+        };
+
+        Ok(NP_Json_Rpc_Request { method, id, params })
+    }
+
+    /// Is this a notification?  Servers don't respond to notifications.
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+
+    /// Open the params into a new buffer, params that don't fit the schema fail with the invalid params error code
+    pub fn open_params<'buffer>(&self, factory: &'buffer NP_Factory) -> Result<NP_Buffer<'buffer>, NP_Json_Rpc_Error> {
+        import(factory, &self.params).map_err(|err| NP_Json_Rpc_Error::new(NP_JSON_RPC_INVALID_PARAMS, err.message()))
+    }
+
+    /// Get the request envelope as JSON
+    pub fn to_json(&self) -> NP_JSON {
+        let mut json = envelope();
+        json.insert("method".to_owned(), NP_JSON::String(self.method.clone()));
+        if let NP_JSON::Null = self.params { } else {
+            json.insert("params".to_owned(), self.params.clone());
+        }
+        if let Some(id) = &self.id {
+            json.insert("id".to_owned(), id.to_json());
+        }
+        NP_JSON::Dictionary(json)
+    }
+
+    /// Get the request envelope as JSON text
+    pub fn stringify(&self) -> String {
+        self.to_json().stringify()
+    }
+}
+
+/// A JSON-RPC response with a result or an error
+///
+/// Check out documentation [here](../json_rpc/index.html).
+///
+#[derive(Debug)]
+pub struct NP_Json_Rpc_Response {
+    /// Id of the request this answers
+    pub id: NP_Json_Rpc_Id,
+    /// The result, or the error of the request
+    pub result: Result<NP_JSON, NP_Json_Rpc_Error>
+}
+
+impl NP_Json_Rpc_Response {
+
+    /// Create a response with the contents of a buffer as the result.  The buffer schema has to be a collection.
+    pub fn new(id: NP_Json_Rpc_Id, result: &NP_Buffer) -> Result<Self, NP_Error> {
+        Ok(NP_Json_Rpc_Response { id, result: Ok(export(result)?) })
+    }
+
+    /// Create an error response
+    pub fn error(id: NP_Json_Rpc_Id, error: NP_Json_Rpc_Error) -> Self {
+        NP_Json_Rpc_Response { id, result: Err(error) }
+    }
+
+    /// Parse JSON text of a response
+    pub fn parse(json: &str) -> Result<Self, NP_Error> {
+        let map = parse_envelope(json)?;
+
+        let id = match map.get("id").and_then(NP_Json_Rpc_Id::from_json) {
+            Some(x) => x,
+            None => return Err(NP_Error::Parse("Response id must be a string, integer or null!".into()))
+        };
+
+        let result = match (map.get("result"), map.get("error")) {
+            (Some(result), None) => Ok(result.clone()),
+            (None, Some(error)) => match (&error["code"], &error["message"]) {
+                (NP_JSON::Integer(code), NP_JSON::String(message)) => Err(NP_Json_Rpc_Error { code: *code, message: message.clone() }),
+                _ => return Err(NP_Error::Parse("Response error must have an integer code and a string message!".into()))
+            },
+            _ => return Err(NP_Error::Parse("Response must have either a result or an error!".into()))
+        };
+
+        Ok(NP_Json_Rpc_Response { id, result })
+    }
+
+    /// Open the result into a new buffer.  Error responses fail with the message of the error.
+    pub fn open_result<'buffer>(&self, factory: &'buffer NP_Factory) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        match &self.result {
+            Ok(result) => import(factory, result),
+            Err(error) => Err(NP_Error::Other(error.message.clone()))
+        }
+    }
+
+    /// Get the response envelope as JSON
+    pub fn to_json(&self) -> NP_JSON {
+        let mut json = envelope();
+        match &self.result {
+            Ok(result) => json.insert("result".to_owned(), result.clone()),
+            Err(error) => json.insert("error".to_owned(), error.to_json())
+        };
+        json.insert("id".to_owned(), self.id.to_json());
+        NP_JSON::Dictionary(json)
+    }
+
+    /// Get the response envelope as JSON text
+    pub fn stringify(&self) -> String {
+        self.to_json().stringify()
+    }
+}
+
+#[test]
+fn json_rpc_works() -> Result<(), NP_Error> {
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "u32"}}"#)?;
+
+    // notifications have no id and empty buffers have no params
+    let notification = NP_Json_Rpc_Request::new("ping", None, &factory.empty_buffer(None))?;
+    assert_eq!(notification.stringify(), r#"{"jsonrpc":"2.0","method":"ping"}"#);
+    let parsed = NP_Json_Rpc_Request::parse(&notification.stringify())?;
+    assert!(parsed.is_notification());
+    assert_eq!(parsed.open_params(&factory)?.json_encode(&[])?.stringify(), "null");
+
+    let scalar = NP_Factory::new(r#"{"type": "u32"}"#)?;
+    let mut buffer = scalar.empty_buffer(None);
+    buffer.set(&[], 2u32)?;
+    assert!(NP_Json_Rpc_Request::new("ping", None, &buffer).is_err());
+
+    // bad envelopes get the error codes from the spec
+    let code = |json: &str| NP_Json_Rpc_Request::parse(json).unwrap_err().code;
+    assert_eq!(code("nope"), NP_JSON_RPC_PARSE_ERROR);
+    assert_eq!(code(""), NP_JSON_RPC_PARSE_ERROR);
+    assert_eq!(code(r#"[1]"#), NP_JSON_RPC_INVALID_REQUEST);
+    assert_eq!(code(r#"{"jsonrpc": "1.0", "method": "a"}"#), NP_JSON_RPC_INVALID_REQUEST);
+    assert_eq!(code(r#"{"jsonrpc": "2.0", "method": 1}"#), NP_JSON_RPC_INVALID_REQUEST);
+    assert_eq!(code(r#"{"jsonrpc": "2.0", "method": "a", "id": [1]}"#), NP_JSON_RPC_INVALID_REQUEST);
+    assert_eq!(code(r#"{"jsonrpc": "2.0", "method": "a", "params": 1}"#), NP_JSON_RPC_INVALID_REQUEST);
+    let request = NP_Json_Rpc_Request::parse(r#"{"jsonrpc": "2.0", "method": "a", "params": [1, -2], "id": "x"}"#)?;
+    assert_eq!(request.id, Some(NP_Json_Rpc_Id::String("x".to_owned())));
+    assert_eq!(request.open_params(&factory).err().map(|err| err.code), Some(NP_JSON_RPC_INVALID_PARAMS));
+
+    // error responses go both ways
+    let error = NP_Json_Rpc_Response::error(NP_Json_Rpc_Id::Null, NP_Json_Rpc_Error::new(NP_JSON_RPC_METHOD_NOT_FOUND, "Method not found"));
+    assert_eq!(error.stringify(), r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":null}"#);
+    let parsed = NP_Json_Rpc_Response::parse(&error.stringify())?;
+    assert_eq!(parsed.result.as_ref().unwrap_err().code, NP_JSON_RPC_METHOD_NOT_FOUND);
+    assert!(parsed.open_result(&factory).is_err());
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.list_push(&[], 7u32)?;
+    let response = NP_Json_Rpc_Response::parse(&NP_Json_Rpc_Response::new(NP_Json_Rpc_Id::Number(9), &buffer)?.stringify())?;
+    assert_eq!(response.open_result(&factory)?.get::<u32>(&["0"])?, Some(7));
+    assert!(NP_Json_Rpc_Response::parse(r#"{"jsonrpc": "2.0", "id": 1}"#).is_err());
+    assert!(NP_Json_Rpc_Response::parse(r#"{"jsonrpc": "2.0", "id": 1, "result": 1, "error": {"code": 1, "message": "a"}}"#).is_err());
+    assert!(NP_Json_Rpc_Response::parse(r#"{"jsonrpc": "2.0", "id": 1, "error": {"code": "a"}}"#).is_err());
+
+    Ok(())
+}
@@ -8,9 +8,23 @@
 //! [Github](https://github.com/ClickSimply/NoProto) | [Crates.io](https://crates.io/crates/no_proto) | [Documentation](https://docs.rs/no_proto)
 //! 
 //! ### Features  
-//! - Zero dependencies
+//! - One required dependency, `spin` for the `no_std` lock around factory pools and the factory registry, these optional features add more:
+//!     - `derive`: `#[derive(NP_Record)]` from the `no_proto_derive` crate
+//!     - `serde`: serde `Serializer` and `Deserializer` for buffers
+//!     - `chrono`: conversions between `NP_Date` and `chrono` dates
+//!     - `rust_decimal`: conversions between `NP_Dec` and `rust_decimal` decimals
+//!     - `zlib`: zlib buffer compression from the `miniz_oxide` crate
+//!     - `zstd`: zstd buffer compression from the `ruzstd` crate
+//!     - `arrow`: `RecordBatch` export for lists of tables from the `arrow-array` and `arrow-schema` crates
+//!     - `hmac`: signed buffers with HMAC-SHA256 tags from the `hmac` and `sha2` crates
+//!     - `futures-io`: async buffer streams over `futures::io` readers and writers
+//!     - `tokio`: async buffer streams over `tokio::io` readers and writers
+//!     - `tokio-util`: `Encoder` and `Decoder` for `NP_Codec` from `tokio_util::codec`
+//!     - `wasm`: JavaScript classes for factories and buffers from the `wasm-bindgen` and `js-sys` crates
+//!     - `pyo3`: Python classes for factories and buffers from the `pyo3` crate
 //! - Zero copy deserialization
 //! - `no_std` support, WASM ready
+//! - Reading scalars and iterating collections never allocates (use `get_choice` instead of `NP_Enum` for options)
 //! - Native byte-wise sorting
 //! - Extensive Documentation & Testing
 //! - Easily mutate, add or delete values in existing buffers
@@ -158,7 +172,7 @@
 //! I also think there's a strong argument here against using data without a schema.  The cost of an entirely flexible formats like JSON or BSON is crazy.  Putting schemas on your data not only increases your data hygiene but makes the storage of the data far more comapct while increasing the deserialization and serialization perfomrance substantially.
 //! 
 //! #### Limitations
-//! - Buffers cannot be larger than 2^16 bytes (~16kb).
+//! - Buffers cannot be larger than 2^16 bytes (~64kb), unless the factory uses `NP_Address_Size::U32` addresses.
 //! - Collections (Lists, Maps, Tuples & Tables) cannot have more than 255 immediate child items.
 //! - Enum/Option types are limited to 255 choices and choice strings cannot be larger than 255 bytes.
 //! - Tables are limited to 255 columns and column names cannot be larger than 255 bytes.
@@ -167,6 +181,8 @@
 //! #### Non Goals / Known Tradeoffs 
 //! If every CPU cycle counts, you don't mind compiling fixed schemas and you don't plan to mutate your buffers/objects, FlatBuffers/CapnProto is probably the way to go.  It's impossible to make a flexible format like NoProto as fast as formats that compile your schemas ahead of time and store data immutably.
 //! 
+//! Pointers and vtables are read by casting the buffer bytes into structs.  The `safe` feature replaces those casts with bounds checked reads and writes of the bytes, it's a bit slower but doesn't use `unsafe` to get at pointers.
+//! 
 //! ----------------------
 //! 
 //! MIT License
@@ -200,27 +216,100 @@ pub mod json_flex;
 pub mod format;
 pub mod memory;
 pub mod rpc;
+pub mod json_rpc;
+pub mod record;
+pub mod owned;
+pub mod schema_idl;
+pub mod migration;
+pub mod evolution;
+pub mod projection;
+pub mod versioned;
+pub mod validation;
+pub mod integrity;
+#[cfg(target_has_atomic = "ptr")]
+pub mod pool;
+pub mod path;
+pub mod sortable;
+pub mod index;
+pub mod bloom;
+pub mod sync;
+#[cfg(target_has_atomic = "ptr")]
+pub mod registry;
+pub mod factory_cache;
+pub mod roots;
+pub mod factory_set;
+pub mod finished;
+pub mod message;
+pub mod fragment;
+pub mod bundle;
+#[cfg(feature = "std")]
+pub mod codegen;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "avro")]
+pub mod avro;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "hmac")]
+pub mod hmac;
+#[cfg(any(feature = "zlib", feature = "zstd"))]
+pub mod compression;
+#[cfg(feature = "async")]
+pub mod async_io;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "pyo3")]
+pub mod python;
 mod hashmap;
 mod utils;
+#[cfg(feature = "std")]
+mod csv;
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 use crate::json_flex::NP_JSON;
-use crate::schema::NP_Schema;
+use crate::schema::{NP_Schema, NP_MAX_DEPTH};
 use crate::json_flex::json_decode;
 use crate::error::NP_Error;
-use crate::memory::NP_Memory;
-use buffer::{NP_Buffer, ROOT_PTR_ADDR};
+use crate::validation::NP_Constraints;
+use crate::integrity::NP_Integrity_Report;
+#[cfg(target_has_atomic = "ptr")]
+use crate::pool::{NP_Pool, NP_Pooled_Buffer};
+use crate::pointer::date::{NP_Clock, NP_Clock_Ref};
+use crate::path::NP_Path;
+use crate::index::NP_Index;
+use crate::evolution::NP_Evolved_From;
+use crate::roots::NP_ROOTS_MARKER;
+use crate::memory::{NP_Memory, NP_Address_Size, NP_HEADER_FINGERPRINT, NP_HEADER_VERSION};
+use buffer::NP_Buffer;
 use alloc::vec::Vec;
 use alloc::{borrow::ToOwned};
+use alloc::string::{String, ToString};
+use alloc::format;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
 use schema::NP_Parsed_Schema;
 
+pub use crate::record::NP_Record;
+#[cfg(feature = "derive")]
+pub use no_proto_derive::NP_Record;
+
 /// Factories are created from schemas.  Once you have a factory you can use it to create new buffers or open existing ones.
 /// 
 /// The easiest way to create a factory is to pass a JSON string schema into the static `new` method.  [Learn about schemas here.](./schema/index.html)
 /// 
 /// You can also create a factory with a compiled byte schema using the static `new_compiled` method.
 /// 
+/// Factories are cheap to clone, clones share the parsed schema.  Check out `NP_Factory_Registry` to share factories between connections or threads.
+/// 
 /// # Example
 /// ```
 /// use no_proto::error::NP_Error;
@@ -283,8 +372,34 @@ use schema::NP_Parsed_Schema;
 #[derive(Debug)]
 pub struct NP_Factory {
     /// schema data used by this factory
-    pub schema: NP_Schema,
-    schema_bytes: Vec<u8>
+    pub schema: Arc<NP_Schema>,
+    schema_bytes: Arc<Vec<u8>>,
+    stamp_fingerprint: bool,
+    version: Option<u32>,
+    address_size: NP_Address_Size,
+    evolved_from: Option<Arc<NP_Evolved_From>>,
+    roots: Vec<(String, Arc<NP_Schema>)>,
+    #[cfg(target_has_atomic = "ptr")]
+    pool: NP_Pool,
+    clock: Option<NP_Clock_Ref>
+}
+
+impl Clone for NP_Factory {
+    /// Clones share the schema of this factory, each clone starts with an empty allocation pool
+    fn clone(&self) -> Self {
+        NP_Factory {
+            schema: Arc::clone(&self.schema),
+            schema_bytes: Arc::clone(&self.schema_bytes),
+            stamp_fingerprint: self.stamp_fingerprint,
+            version: self.version,
+            address_size: self.address_size,
+            evolved_from: self.evolved_from.clone(),
+            roots: self.roots.clone(),
+            #[cfg(target_has_atomic = "ptr")]
+            pool: NP_Pool::new(),
+            clock: self.clock.clone()
+        }
+    }
 }
 
 impl NP_Factory {
@@ -297,52 +412,206 @@ impl NP_Factory {
 
         let parsed_value = json_decode(json_schema.to_owned())?;
 
+        if parsed_value["type"].is_null() && !parsed_value["roots"].is_null() {
+            let (roots, schema_bytes) = roots::from_json(&parsed_value["roots"])?;
+            return Ok(Self::with_roots(roots, schema_bytes));
+        }
+
         let (is_sortable, schema_bytes, schema) = NP_Schema::from_json(Vec::new(), &parsed_value)?;
 
-        Ok(Self {
-            schema_bytes: schema_bytes,
-            schema:  NP_Schema {
+        let constraints = NP_Constraints::from_json(&schema, &parsed_value);
+
+        Self {
+            schema:  Arc::new(NP_Schema {
                 is_sortable: is_sortable,
-                parsed: schema
-            }
-        })      
+                parsed: schema,
+                fingerprint: NP_Schema::fingerprint_bytes(&schema_bytes),
+                constraints: constraints
+            }),
+            schema_bytes: Arc::new(schema_bytes),
+            stamp_fingerprint: false,
+            version: None,
+            address_size: NP_Address_Size::U16,
+            evolved_from: None,
+            roots: Vec::new(),
+            #[cfg(target_has_atomic = "ptr")]
+            pool: NP_Pool::new(),
+            clock: NP_Clock_Ref::default_clock()
+        }.with_max_depth(NP_MAX_DEPTH)      
         
     }
 
+    /// Generate a new factory from a [JSON Schema](https://json-schema.org/) string.
+    /// 
+    /// Only a subset of JSON Schema is supported: `type` (optionally combined with `null`), `properties`, `additionalProperties`, `items`, `enum`, `minimum`, `maximum`, `maxLength`, `maxItems` and `default`.  
+    /// Objects with `properties` become tables, objects with only an `additionalProperties` schema become maps, arrays become lists (tuples if `items` is an array), string enums become enums and integers get the smallest type that holds their `minimum`/`maximum` range.
+    /// 
+    /// Keywords like `$ref` or `oneOf` that can't be represented produce an error.  Schemas generated by `NP_Schema::to_json_schema` convert back into the same types, except for the ones JSON Schema can't tell apart: `float` comes back as `double`, `date` as `uint64`, `ulid` as `string` and geo types always come back as `geo16`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::from_json_schema(r#"{
+    ///     "type": "object",
+    ///     "properties": {
+    ///         "name": {"type": "string"},
+    ///         "age":  {"type": "integer", "minimum": 0, "maximum": 150, "default": 20},
+    ///         "tags": {"type": "array", "items": {"type": "string"}}
+    ///     }
+    /// }"#)?;
+    /// 
+    /// assert_eq!(factory.export_schema()?.stringify(), concat!(
+    ///     r#"{"type":"table","columns":["#,
+    ///     r#"["name",{"type":"string"}],"#,
+    ///     r#"["age",{"type":"uint8","default":20}],"#,
+    ///     r#"["tags",{"type":"list","of":{"type":"string"}}]"#,
+    ///     r#"]}"#
+    /// ));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn from_json_schema(json_schema: &str) -> Result<NP_Factory, NP_Error> {
+
+        let parsed_value = json_decode(json_schema.to_owned())?;
+
+        let np_schema = Box::new(NP_Schema::_from_json_schema(&parsed_value)?);
+
+        let (is_sortable, schema_bytes, schema) = NP_Schema::from_json(Vec::new(), &np_schema)?;
+
+        let constraints = NP_Constraints::from_json(&schema, &np_schema);
+
+        Self {
+            schema:  Arc::new(NP_Schema {
+                is_sortable: is_sortable,
+                parsed: schema,
+                fingerprint: NP_Schema::fingerprint_bytes(&schema_bytes),
+                constraints: constraints
+            }),
+            schema_bytes: Arc::new(schema_bytes),
+            stamp_fingerprint: false,
+            version: None,
+            address_size: NP_Address_Size::U16,
+            evolved_from: None,
+            roots: Vec::new(),
+            #[cfg(target_has_atomic = "ptr")]
+            pool: NP_Pool::new(),
+            clock: NP_Clock_Ref::default_clock()
+        }.with_max_depth(NP_MAX_DEPTH)
+    }
+
+    /// Generate a new factory from a schema written in the compact schema IDL.
+    /// 
+    /// The IDL is converted into a JSON schema first, so `NP_Factory::new` and this method produce identical factories for equivalent schemas.
+    /// 
+    /// Check out documentation [here](./schema_idl/index.html).
+    /// 
+    pub fn from_idl(idl_schema: &str) -> Result<NP_Factory, NP_Error> {
+
+        let parsed_value = Box::new(schema_idl::idl_to_json(idl_schema)?);
+
+        let (is_sortable, schema_bytes, schema) = NP_Schema::from_json(Vec::new(), &parsed_value)?;
+
+        let constraints = NP_Constraints::from_json(&schema, &parsed_value);
+
+        Self {
+            schema:  Arc::new(NP_Schema {
+                is_sortable: is_sortable,
+                parsed: schema,
+                fingerprint: NP_Schema::fingerprint_bytes(&schema_bytes),
+                constraints: constraints
+            }),
+            schema_bytes: Arc::new(schema_bytes),
+            stamp_fingerprint: false,
+            version: None,
+            address_size: NP_Address_Size::U16,
+            evolved_from: None,
+            roots: Vec::new(),
+            #[cfg(target_has_atomic = "ptr")]
+            pool: NP_Pool::new(),
+            clock: NP_Clock_Ref::default_clock()
+        }.with_max_depth(NP_MAX_DEPTH)
+    }
+
     /// Create a new factory from a compiled schema byte array.
     /// The byte schemas are at least an order of magnitude faster to parse than JSON schemas.
     /// 
     pub fn new_compiled(schema_bytes: Vec<u8>) -> Self {
         
+        if schema_bytes.first() == Some(&NP_ROOTS_MARKER) {
+            return Self::with_roots(roots::from_bytes(&schema_bytes), schema_bytes);
+        }
+
         let (is_sortable, schema) = NP_Schema::from_bytes(Vec::new(), 0, &schema_bytes);
 
         Self {
-            schema_bytes: schema_bytes,
-            schema:  NP_Schema { 
+            schema:  Arc::new(NP_Schema {
                 is_sortable: is_sortable,
-                parsed: schema
-            }
+                parsed: schema,
+                fingerprint: NP_Schema::fingerprint_bytes(&schema_bytes),
+                constraints: Vec::new()
+            }),
+            schema_bytes: Arc::new(schema_bytes),
+            stamp_fingerprint: false,
+            version: None,
+            address_size: NP_Address_Size::U16,
+            evolved_from: None,
+            roots: Vec::new(),
+            #[cfg(target_has_atomic = "ptr")]
+            pool: NP_Pool::new(),
+            clock: NP_Clock_Ref::default_clock()
+        }
+    }
+
+    /// Factory for named roots, the first root is the default schema
+    fn with_roots(roots: Vec<(String, Arc<NP_Schema>)>, schema_bytes: Vec<u8>) -> Self {
+        Self {
+            schema: Arc::clone(&roots[0].1),
+            schema_bytes: Arc::new(schema_bytes),
+            stamp_fingerprint: false,
+            version: None,
+            address_size: NP_Address_Size::U16,
+            evolved_from: None,
+            roots,
+            #[cfg(target_has_atomic = "ptr")]
+            pool: NP_Pool::new(),
+            clock: NP_Clock_Ref::default_clock()
         }
     }
 
     /// Get a copy of the compiled schema byte array
     /// 
     pub fn compile_schema(&self) -> Vec<u8> {
-        self.schema_bytes.clone()
+        self.schema_bytes.to_vec()
     }
 
 
     /// Exports this factorie's schema to JSON.  This works regardless of wether the factory was created with `NP_Factory::new` or `NP_Factory::new_compiled`.
     /// 
     pub fn export_schema(&self) -> Result<NP_JSON, NP_Error> {
+        if !self.roots.is_empty() {
+            return roots::to_json(&self.roots);
+        }
         self.schema.to_json()
     }
 
+    /// Exports this factorie's schema to the compact schema IDL.
+    /// 
+    /// Check out documentation [here](./schema_idl/index.html).
+    /// 
+    pub fn export_idl(&self) -> Result<String, NP_Error> {
+        if !self.roots.is_empty() {
+            return Err(NP_Error::Schema("The schema IDL doesn't support named roots!".into()));
+        }
+        schema_idl::json_to_idl(&self.schema.to_json()?)
+    }
+
     /// Open existing Vec<u8> sortable buffer that was closed with `.close_sortable()` 
     /// 
     /// There is typically 10 bytes or more in front of every sortable buffer that is identical between all sortable buffers for a given schema.
     /// 
-    /// This method is used to open buffers that have had the leading identical bytes trimmed from them using `.close_sortale()`.
+    /// This method is used to open buffers that have had the leading identical bytes trimmed from them using `.close_sortale()`.  Fails if the bytes don't hold exactly one value for each item in the tuple.
     /// 
     /// This operation fails if the buffer is not sortable.
     /// 
@@ -385,35 +654,492 @@ impl NP_Factory {
     pub fn open_sortable_buffer<'buffer>(&'buffer self, bytes: Vec<u8>) -> Result<NP_Buffer<'buffer>, NP_Error> {
         
         match &self.schema.parsed[0] {
-            NP_Parsed_Schema::Tuple { values, sortable,  ..} => {
+            NP_Parsed_Schema::Tuple { sortable,  ..} => {
                 if *sortable == false {
-                    Err(NP_Error::new("Attempted to open sorted buffer when root wasn't sortable!"))
+                    Err(NP_Error::SchemaMismatch("Attempted to open sorted buffer when root wasn't sortable!".into()))
                 } else {
-                    let mut vtables = 1usize;
-                    let mut length = values.len();
-                    while length > 4 {
-                        vtables +=1;
-                        length -= 4;
-                    }
-                    // how many leading bytes are identical across all buffers with this schema
-                    let root_offset = ROOT_PTR_ADDR + 2 + (vtables * 10);
-
-                    let default_buffer = NP_Buffer::_new(NP_Memory::new(Some(root_offset + bytes.len()), &self.schema.parsed));
-                    let mut use_bytes = default_buffer.close()[0..root_offset].to_vec();
-                    use_bytes.extend_from_slice(&bytes[..]);
-
-                    Ok(NP_Buffer::_new(NP_Memory::existing(use_bytes, &self.schema.parsed)))
+                    let mut buffer = self.empty_buffer(None);
+                    buffer.set_sortable(&bytes)?;
+                    Ok(buffer)
                 }
             },
-            _ => return Err(NP_Error::new("Attempted to open sorted buffer when root wasn't tuple!"))
+            _ => return Err(NP_Error::SchemaMismatch("Attempted to open sorted buffer when root wasn't tuple!".into()))
         }
     }
 
+    /// Get the bytes on either side of a range of sortable buffers, for key range queries in sorted stores like RocksDB or sled.
+    /// 
+    /// The first bytes are the inclusive start and the second the exclusive end of the range, compare them against bytes from `close_sortable`.  Values are JSON in the same format `set_json` takes them, one for each value of the tuple in order.
+    /// 
+    /// Fewer values than the tuple has match on the leading values only: every buffer that starts with `start_values` is in the range, and every buffer that starts with `end_values` is past it.  `null` values are the same as values that haven't been set, sorted tuples hold the default of each type for those (not the schema default).
+    /// 
+    /// This operation fails if the root isn't a sortable tuple, or if a value doesn't fit it's schema.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::json_flex::NP_JSON;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "tuple",
+    ///    "sorted": true,
+    ///    "values": [
+    ///         {"type": "u32"},
+    ///         {"type": "string"}
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut keys: Vec<Vec<u8>> = Vec::new();
+    /// for (user, item) in [(1u32, "boots"), (2, "helmet"), (2, "jetpack"), (3, "boots")].iter() {
+    ///     let mut buffer = factory.empty_buffer(None);
+    ///     buffer.set(&["0"], *user)?;
+    ///     buffer.set(&["1"], *item)?;
+    ///     keys.push(buffer.close_sortable()?);
+    /// }
+    /// 
+    /// // every item of user 2
+    /// let (start, end) = factory.sortable_range(&[NP_JSON::Integer(2)], &[NP_JSON::Integer(3)])?;
+    /// let in_range: Vec<&Vec<u8>> = keys.iter().filter(|key| **key >= start && **key < end).collect();
+    /// assert_eq!(in_range, vec![&keys[1], &keys[2]]);
+    /// 
+    /// // user 2 from "i" onwards
+    /// let (start, end) = factory.sortable_range(&[NP_JSON::Integer(2), NP_JSON::String("i".to_owned())], &[NP_JSON::Integer(3)])?;
+    /// let in_range: Vec<&Vec<u8>> = keys.iter().filter(|key| **key >= start && **key < end).collect();
+    /// assert_eq!(in_range, vec![&keys[2]]);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn sortable_range(&self, start_values: &[NP_JSON], end_values: &[NP_JSON]) -> Result<(Vec<u8>, Vec<u8>), NP_Error> {
+
+        let range_bound = |values: &[NP_JSON]| -> Result<Vec<u8>, NP_Error> {
+            let mut buffer = self.empty_buffer(None);
+            for (index, value) in values.iter().enumerate() {
+                let index = index.to_string();
+                buffer.set_json(&[index.as_str()], value)?;
+            }
+            buffer.sortable_prefix(values.len())
+        };
+
+        Ok((range_bound(start_values)?, range_bound(end_values)?))
+    }
+
 
     /// Open existing Vec<u8> as buffer for this factory.  
     /// 
     pub fn open_buffer<'buffer>(&'buffer self, bytes: Vec<u8>) -> NP_Buffer<'buffer> {
-        NP_Buffer::_new(NP_Memory::existing(bytes, &self.schema.parsed))
+        NP_Buffer::_new(NP_Memory::existing(bytes, &self.schema.parsed).with_constraints(&self.schema.constraints))
+    }
+
+    /// Open bytes made by `NP_Buffer::close_signed` after checking their HMAC-SHA256 tag.
+    /// 
+    /// Fails if the bytes or the tag were changed, or if they were signed with a different key.  The tag is removed before the buffer is opened.  This method requires the `hmac` feature.
+    /// 
+    /// Check out documentation [here](./hmac/index.html).
+    /// 
+    #[cfg(feature = "hmac")]
+    pub fn open_verified<'buffer>(&'buffer self, bytes: Vec<u8>, key: &[u8]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        match crate::hmac::verify(bytes, key) {
+            Some(bytes) => Ok(self.open_buffer(bytes)),
+            None => Err(NP_Error::Damaged("Buffer signature doesn't match, the bytes or key have changed!".into()))
+        }
+    }
+
+    /// Open bytes made by `NP_Buffer::close_compressed`.
+    /// 
+    /// The codec is read from the header in front of the compressed bytes, opening fails if the bytes are damaged or the feature for that codec isn't enabled.  This method requires the `zlib` or `zstd` feature.
+    /// 
+    /// Check out documentation [here](./compression/index.html).
+    /// 
+    #[cfg(any(feature = "zlib", feature = "zstd"))]
+    pub fn open_compressed<'buffer>(&'buffer self, bytes: &[u8]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        Ok(self.open_buffer(crate::compression::decompress(bytes)?))
+    }
+
+    /// Open a buffer from the frames made by `NP_Buffer::fragment`, the frames can be in any order.
+    /// 
+    /// Fails if any frame is damaged, missing or repeated, or if the frames came from different buffers.
+    /// 
+    /// Check out documentation [here](./fragment/index.html).
+    /// 
+    pub fn reassemble<'buffer, F: AsRef<[u8]>>(&'buffer self, frames: &[F]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        Ok(self.open_buffer(crate::fragment::reassemble(frames)?))
+    }
+
+    /// Read the next length prefixed buffer from a reader, like a file or socket.
+    ///
+    /// The prefix is a 4 byte big endian length in front of the buffer bytes, the same one `async_io::write_buffer` writes.  Buffers longer than `max_size` bytes fail before any room is allocated for them, and the bytes are read in chunks so a damaged length can't allocate more than the reader actually has.
+    ///
+    /// Returns `None` if the reader ended before another buffer started, fails if it ends part way through one.  This method requires the `std` feature.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use std::io::Write;
+    ///
+    /// let factory = NP_Factory::new(r#"{"type": "string"}"#)?;
+    ///
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&[], "hello")?;
+    /// let bytes = new_buffer.close();
+    ///
+    /// let mut file: Vec<u8> = Vec::new();
+    /// file.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    /// file.write_all(&bytes)?;
+    ///
+    /// let mut reader = &file[..];
+    /// let buffer = factory.open_from_reader(&mut reader, 1024)?.unwrap();
+    /// assert_eq!(buffer.get::<&str>(&[])?, Some("hello"));
+    /// assert!(factory.open_from_reader(&mut reader, 1024)?.is_none());
+    ///
+    /// // too large
+    /// assert!(factory.open_from_reader(&mut &file[..], 4).is_err());
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    ///
+    #[cfg(feature = "std")]
+    pub fn open_from_reader<'buffer, R: std::io::Read>(&'buffer self, reader: &mut R, max_size: usize) -> Result<Option<NP_Buffer<'buffer>>, NP_Error> {
+        use std::io::Read;
+
+        let mut prefix = [0u8; 4];
+        let mut read = 0usize;
+        while read < prefix.len() {
+            match reader.read(&mut prefix[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(NP_Error::Io("Reader ended inside a buffer length!".into())),
+                Ok(len) => read += len,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => { },
+                Err(e) => return Err(e.into())
+            }
+        }
+
+        let len = u32::from_be_bytes(prefix) as usize;
+        if len > max_size {
+            return Err(NP_Error::OutOfBounds("Buffer from reader is larger than the max size!".into()));
+        }
+
+        // grow as bytes arrive instead of trusting the length up front
+        let mut bytes: Vec<u8> = Vec::with_capacity(core::cmp::min(len, 4096));
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(NP_Error::Io("Reader ended before the end of the buffer!".into()));
+        }
+
+        Ok(Some(self.open_buffer(bytes)))
+    }
+
+    /// Open borrowed bytes as buffer for this factory without copying them.
+    /// 
+    /// Useful for buffers that arrive inside network frames or arena allocations.  Values that already exist can be changed in place, but the buffer can't grow so setting new values fails.  Use `open_buffer_read_only` if the bytes can't be changed at all, or `compact` to copy the buffer into owned bytes that can grow.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["id",   {"type": "u32"}],
+    ///         ["name", {"type": "string"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["id"], 20u32)?;
+    /// let mut frame = new_buffer.close();
+    /// 
+    /// // change existing values in place
+    /// let mut borrowed = factory.open_buffer_borrowed(&mut frame[..]);
+    /// borrowed.set(&["id"], 21u32)?;
+    /// // but there's no room for new ones
+    /// assert!(borrowed.set(&["name"], "Jeb").is_err());
+    /// 
+    /// // read only buffers can't be changed at all
+    /// let read_only = factory.open_buffer_read_only(&frame[..]);
+    /// assert_eq!(read_only.get::<u32>(&["id"])?, Some(21));
+    /// assert!(read_only.is_read_only());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn open_buffer_borrowed<'buffer>(&'buffer self, bytes: &'buffer mut [u8]) -> NP_Buffer<'buffer> {
+        NP_Buffer::_new(NP_Memory::existing_borrowed(bytes, &self.schema.parsed).with_constraints(&self.schema.constraints))
+    }
+
+    /// Open borrowed bytes as a read only buffer for this factory without copying them.
+    /// 
+    /// Every method that changes the buffer returns an error.  Check out `open_buffer_borrowed` for an example.
+    /// 
+    pub fn open_buffer_read_only<'buffer>(&'buffer self, bytes: &'buffer [u8]) -> NP_Buffer<'buffer> {
+        NP_Buffer::_new(NP_Memory::existing_read_only(bytes, &self.schema.parsed).with_constraints(&self.schema.constraints))
+    }
+
+    /// Generate a new empty buffer inside borrowed bytes with a fixed capacity.
+    /// 
+    /// New values are written into the free space of the borrowed bytes, once there's no room left setting new values returns an error.  Nothing is allocated while values are set, so buffers can be built on targets without a heap.  The part of the bytes in use is always available from `read_bytes`.
+    /// 
+    /// Fails if the bytes are too small to even hold an empty buffer.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["id",   {"type": "u32"}],
+    ///         ["name", {"type": "string"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let mut storage = [0u8; 24];
+    /// 
+    /// let mut new_buffer = factory.empty_buffer_fixed(&mut storage)?;
+    /// new_buffer.set(&["id"], 20u32)?;
+    /// assert_eq!(new_buffer.read_bytes().len(), 17);
+    /// // not enough room left for the name
+    /// assert!(new_buffer.set(&["name"], "Jeb Kermin").is_err());
+    /// let len = new_buffer.read_bytes().len();
+    /// 
+    /// // pick up where the buffer left off
+    /// let new_buffer = factory.open_buffer_fixed(&mut storage, len)?;
+    /// assert_eq!(new_buffer.get::<u32>(&["id"])?, Some(20));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn empty_buffer_fixed<'buffer>(&'buffer self, bytes: &'buffer mut [u8]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let (header, len) = self.header_bytes();
+        let mut buffer = NP_Buffer::_new(NP_Memory::new_fixed(bytes, &self.schema.parsed, &header[0..len])?.with_constraints(&self.schema.constraints));
+        self.stamp_now(&mut buffer, &self.schema)?;
+        Ok(buffer)
+    }
+
+    /// Open a buffer inside borrowed bytes with a fixed capacity, the buffer uses the first `len` bytes.
+    /// 
+    /// Check out `empty_buffer_fixed` for an example.
+    /// 
+    pub fn open_buffer_fixed<'buffer>(&'buffer self, bytes: &'buffer mut [u8], len: usize) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        Ok(NP_Buffer::_new(NP_Memory::existing_fixed(bytes, len, &self.schema.parsed)?.with_constraints(&self.schema.constraints)))
+    }
+
+    /// Stamp the schema fingerprint into the header of every new buffer from this factory.
+    /// 
+    /// Stamped buffers are 8 bytes larger.  Use `open_buffer_checked` to open them, it fails if the buffer was written with a different schema or doesn't have a fingerprint at all.  Buffers with a fingerprint can still be opened with `open_buffer` by any factory.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory = NP_Factory::new(r#"{"type": "string"}"#)?.with_fingerprint();
+    /// let other = NP_Factory::new(r#"{"type": "u32"}"#)?.with_fingerprint();
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&[], "hello")?;
+    /// assert_eq!(new_buffer.fingerprint(), Some(factory.schema.fingerprint()));
+    /// let bytes = new_buffer.close();
+    /// 
+    /// // wrong schema fails loudly
+    /// assert!(other.open_buffer_checked(bytes.clone()).is_err());
+    /// 
+    /// let new_buffer = factory.open_buffer_checked(bytes)?;
+    /// assert_eq!(new_buffer.get::<&str>(&[])?, Some("hello"));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn with_fingerprint(mut self) -> Self {
+        self.stamp_fingerprint = true;
+        self
+    }
+
+    /// Stamp a schema version number into the header of every new buffer from this factory.
+    /// 
+    /// Stamped buffers are 5 bytes larger, the version can be read back with `NP_Buffer::get_version`.  Use `NP_Versioned_Factory` to open buffers with the factory matching their version.
+    /// 
+    /// Check out documentation [here](./versioned/index.html).
+    /// 
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Get the schema version this factory stamps into new buffers, if there is one.
+    /// 
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+
+    /// Set the size of the addresses in new buffers from this factory.
+    /// 
+    /// Buffers use 2 byte addresses by default, which limits them to 64 KB.  Buffers with `NP_Address_Size::U32` addresses can grow up to 4 GB, but every pointer in them takes twice the space.
+    /// Going the other way, `NP_Address_Size::U8` addresses halve the pointer overhead of tiny buffers like sensor packets, which can't grow past 255 bytes.
+    /// 
+    /// The address size is recorded in the buffer header, so `open_buffer` reads buffers of any address size.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::memory::NP_Address_Size;
+    /// 
+    /// let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    /// let large = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?.with_address_size(NP_Address_Size::U32);
+    /// 
+    /// let text = "x".repeat(1000);
+    /// 
+    /// // 2 byte addresses run out of space
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// let all_fit = (0..100).all(|x| new_buffer.set(&[&x.to_string()], text.as_str()).is_ok());
+    /// assert_eq!(all_fit, false);
+    /// 
+    /// let mut new_buffer = large.empty_buffer(None);
+    /// for x in 0..100 {
+    ///     new_buffer.set(&[&x.to_string()], text.as_str())?;
+    /// }
+    /// assert!(new_buffer.read_bytes().len() > 100_000);
+    /// 
+    /// let new_buffer = factory.open_buffer(new_buffer.close());
+    /// assert_eq!(new_buffer.get::<&str>(&["99"])?, Some(text.as_str()));
+    /// 
+    /// // tiny buffers with 1 byte addresses
+    /// let tiny = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?.with_address_size(NP_Address_Size::U8);
+    /// let mut small_buffer = factory.empty_buffer(None);
+    /// let mut tiny_buffer = tiny.empty_buffer(None);
+    /// for x in 0..3 {
+    ///     small_buffer.set(&[&x.to_string()], "hi")?;
+    ///     tiny_buffer.set(&[&x.to_string()], "hi")?;
+    /// }
+    /// assert_eq!(small_buffer.read_bytes().len(), 34);
+    /// assert_eq!(tiny_buffer.read_bytes().len(), 25);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn with_address_size(mut self, address_size: NP_Address_Size) -> Self {
+        self.address_size = address_size;
+        self
+    }
+
+    /// Limit how deep the schema of this factory can nest collections.
+    ///
+    /// Exporting to JSON, measuring and compacting buffers walk their collections with a stack on the heap, but selecting paths and checking buffers still recurse once for every level of the schema, and buffers can't nest deeper than their schema.  Schemas are limited to `NP_MAX_DEPTH` levels when they're parsed, use this to set a lower limit for threads with small stacks.  Fails if the schema is already deeper than `max_depth`.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    ///
+    /// let schema = r#"{"type": "list", "of": {"type": "list", "of": {"type": "u8"}}}"#;
+    ///
+    /// assert!(NP_Factory::new(schema)?.with_max_depth(3).is_ok());
+    /// assert!(NP_Factory::new(schema)?.with_max_depth(2).is_err());
+    ///
+    /// // way too deep for any factory
+    /// let too_deep = r#"{"type": "list", "of": "#.repeat(200) + r#"{"type": "u8"}"# + &"}".repeat(200);
+    /// assert!(NP_Factory::new(&too_deep).is_err());
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    ///
+    pub fn with_max_depth(self, max_depth: usize) -> Result<Self, NP_Error> {
+        let depth = self.schema.depth();
+        if depth > max_depth {
+            return Err(NP_Error::Schema(format!("Schema is {} levels deep, more than the max depth of {}!", depth, max_depth)));
+        }
+        Ok(self)
+    }
+
+    /// Open existing Vec<u8> as buffer for this factory, checking the schema fingerprint in the buffer header first.
+    /// 
+    /// Fails if the buffer fingerprint doesn't match this factory's schema.  Buffers without a fingerprint are only accepted if this factory doesn't stamp fingerprints with `with_fingerprint`.
+    /// 
+    /// Factories made by `NP_Schema_Evolution::build` also open buffers of the schema they evolved from, check out documentation [here](./evolution/index.html).
+    /// 
+    pub fn open_buffer_checked<'buffer>(&'buffer self, bytes: Vec<u8>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+
+        let buffer = self.open_buffer(bytes);
+
+        // buffers of the schema this one evolved from
+        if let Some(evolved_from) = &self.evolved_from {
+            if buffer.fingerprint() != Some(self.schema.fingerprint()) {
+                return evolved_from.open(self, buffer.close());
+            }
+        }
+
+        match buffer.fingerprint() {
+            Some(fingerprint) => {
+                if fingerprint != self.schema.fingerprint() {
+                    return Err(NP_Error::SchemaMismatch("Buffer fingerprint doesn't match the factory schema!".into()));
+                }
+            },
+            None => {
+                if self.stamp_fingerprint {
+                    return Err(NP_Error::SchemaMismatch("Buffer doesn't have a schema fingerprint!".into()));
+                }
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    /// Open bytes from a source you don't trust, checking every address in the buffer first.
+    /// 
+    /// Every pointer, vtable link, list link and length prefix has to be inside the buffer, links have to go the direction this library writes them so they can't loop, and nothing can be pointed to twice.  Fails with a `Damaged` error describing the first problem and the path to it.  The schema fingerprint is checked like `open_buffer_checked`.
+    /// 
+    /// This only checks the structure of the buffer, use `NP_Buffer::validate` to check the values against the schema.
+    /// 
+    /// Check out documentation [here](./integrity/index.html).
+    /// 
+    pub fn open_buffer_untrusted<'buffer>(&'buffer self, bytes: Vec<u8>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let buffer = self.open_buffer_checked(bytes)?;
+        buffer.check_integrity()?;
+        Ok(buffer)
+    }
+
+    /// Recover what can still be read from a damaged buffer, like a record from a partially written flash page or a truncated file.
+    ///
+    /// The damaged bytes are checked like `open_buffer_untrusted`, then everything that passed is copied into a new buffer from this factory.  Values and collections with a problem are left out along with everything below them, the returned report lists the path and problem for each subtree that was lost.  A broken link inside a list or map loses that whole collection, and values that can't be read, like strings that aren't valid UTF-8, are lost too.
+    ///
+    /// The schema fingerprint isn't checked, the bytes are read with this factory's schema either way.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    ///
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["id", {"type": "u32"}],
+    ///         ["name", {"type": "string"}]
+    ///     ]
+    /// }"#)?;
+    ///
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// new_buffer.set(&["id"], 20u32)?;
+    /// new_buffer.set(&["name"], "Bill Kerman")?;
+    ///
+    /// // the end of the buffer never made it to disk
+    /// let mut bytes = new_buffer.close();
+    /// bytes.truncate(bytes.len() - 4);
+    ///
+    /// let (recovered, lost) = factory.salvage(bytes)?;
+    /// assert_eq!(recovered.get::<u32>(&["id"])?, Some(20));
+    /// assert_eq!(recovered.get::<&str>(&["name"])?, None);
+    /// assert_eq!(lost.problems.len(), 1);
+    /// assert_eq!(lost.problems[0].path, vec!["name"]);
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    ///
+    pub fn salvage<'buffer>(&'buffer self, bytes: Vec<u8>) -> Result<(NP_Buffer<'buffer>, NP_Integrity_Report), NP_Error> {
+        let damaged = NP_Memory::existing(bytes, &self.schema.parsed);
+        let mut report = crate::integrity::report(&damaged);
+
+        let (header, len) = self.header_bytes();
+        let memory = NP_Memory::new_with_header(Some(damaged.read_bytes().len()), &self.schema.parsed, &header[0..len]).with_constraints(&self.schema.constraints);
+        crate::integrity::salvage(&damaged, &memory, &mut report)?;
+
+        Ok((NP_Buffer::_new(memory), report))
     }
 
     /// Generate a new empty buffer from this factory.
@@ -424,6 +1150,276 @@ impl NP_Factory {
     /// You can change the address size through compaction after the buffer is created, so it's fine to start with a smaller address space and convert it to a larger one later as needed.  It's also possible to go the other way, you can convert larger address space down to a smaller one durring compaction.
     /// 
     pub fn empty_buffer<'buffer>(&'buffer self, capacity: Option<usize>) -> NP_Buffer<'buffer> {
-        NP_Buffer::_new(NP_Memory::new(capacity, &self.schema.parsed))
+        let (header, len) = self.header_bytes();
+        let mut buffer = NP_Buffer::_new(NP_Memory::new_with_header(capacity, &self.schema.parsed, &header[0..len]).with_constraints(&self.schema.constraints));
+        self.stamp_now(&mut buffer, &self.schema).unwrap_or(());
+        buffer
+    }
+
+    /// Set the clock that fills in date values with a `"default": "now"` schema.
+    /// 
+    /// Every new empty buffer from this factory gets the current time of the clock in those dates, as long as they're inside tables and tuples from the root.  Dates inside lists and maps are left unset, since there's no telling which items will exist.  The time is read once per buffer, so all the dates in a buffer get the same value.
+    /// 
+    /// Factories use the system time with the `std` feature, without it there's no clock until one is set here.  Any closure returning an `NP_Date` works as a clock.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::pointer::date::NP_Date;
+    /// 
+    /// let factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",    {"type": "string"}],
+    ///         ["created", {"type": "date", "default": "now"}]
+    ///     ]
+    /// }"#)?.with_clock(|| NP_Date::new(1604965249484));
+    /// 
+    /// let mut new_buffer = factory.empty_buffer(None);
+    /// assert_eq!(new_buffer.get::<NP_Date>(&["created"])?, Some(NP_Date::new(1604965249484)));
+    /// 
+    /// // the time is saved in the buffer, opening it again doesn't change it
+    /// new_buffer.set(&["name"], "Jeb Kermin")?;
+    /// let later = factory.clone().with_clock(|| NP_Date::new(1700000000000));
+    /// let new_buffer = later.open_buffer(new_buffer.close());
+    /// assert_eq!(new_buffer.get::<NP_Date>(&["created"])?, Some(NP_Date::new(1604965249484)));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn with_clock<C: NP_Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(NP_Clock_Ref(Arc::new(clock)));
+        self
+    }
+
+    /// Write the clock time into the `"default": "now"` dates of a new buffer
+    fn stamp_now<'buffer>(&self, buffer: &mut NP_Buffer<'buffer>, schema: &NP_Schema) -> Result<(), NP_Error> {
+        let clock = match &self.clock {
+            Some(clock) => clock,
+            None => return Ok(())
+        };
+
+        let paths = schema.now_dates();
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let now = clock.0.now();
+        for path in paths.iter() {
+            let path: Vec<&str> = path.iter().map(|x| x.as_str()).collect();
+            buffer.set(&path, now)?;
+        }
+
+        Ok(())
+    }
+
+    /// Names of the roots of this factory in the order they're in the schema, empty unless the schema has named roots.
+    /// 
+    /// Check out documentation [here](./roots/index.html).
+    /// 
+    pub fn root_names(&self) -> Vec<&str> {
+        self.roots.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Get the schema of a named root
+    /// 
+    pub fn root_schema(&self, name: &str) -> Option<&NP_Schema> {
+        self.roots.iter().find(|(x, _)| x == name).map(|(_, schema)| &**schema)
+    }
+
+    fn find_root(&self, name: &str) -> Result<&NP_Schema, NP_Error> {
+        match self.root_schema(name) {
+            Some(schema) => Ok(schema),
+            None => Err(NP_Error::SchemaMismatch("Schema doesn't have a root named ".to_owned() + name))
+        }
+    }
+
+    /// Generate a new empty buffer of a named root.
+    /// 
+    /// Check out documentation [here](./roots/index.html).
+    /// 
+    pub fn empty_buffer_for<'buffer>(&'buffer self, name: &str) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let schema = self.find_root(name)?;
+        let (header, len) = self.header_bytes_for(schema);
+        let mut buffer = NP_Buffer::_new(NP_Memory::new_with_header(None, &schema.parsed, &header[0..len]).with_constraints(&schema.constraints));
+        self.stamp_now(&mut buffer, schema)?;
+        Ok(buffer)
+    }
+
+    /// Open existing Vec<u8> as buffer of a named root, the schema fingerprint is checked like `open_buffer_checked`.
+    /// 
+    /// Check out documentation [here](./roots/index.html).
+    /// 
+    pub fn open_buffer_for<'buffer>(&'buffer self, name: &str, bytes: Vec<u8>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let schema = self.find_root(name)?;
+        let buffer = NP_Buffer::_new(NP_Memory::existing(bytes, &schema.parsed).with_constraints(&schema.constraints));
+        match buffer.fingerprint() {
+            Some(fingerprint) if fingerprint != schema.fingerprint() => Err(NP_Error::SchemaMismatch("Buffer fingerprint doesn't match the root schema!".into())),
+            None if self.stamp_fingerprint => Err(NP_Error::SchemaMismatch("Buffer doesn't have a schema fingerprint!".into())),
+            _ => Ok(buffer)
+        }
+    }
+
+    /// Resolve a path against the schema of this factory once, so it can be used with `NP_Buffer::get_with` without walking the schema again.
+    /// 
+    /// Fails if the path doesn't exist in the schema.
+    /// 
+    /// Check out documentation [here](./path/index.html).
+    /// 
+    pub fn compile_path(&self, path: &[&str]) -> Result<NP_Path, NP_Error> {
+        NP_Path::compile(&self.schema.parsed, path)
+    }
+
+    /// Open an index that was saved with `NP_Index::to_bytes`.
+    /// 
+    /// The index must have been built from a buffer of this factory's schema.  Check out documentation [here](./index/index.html).
+    /// 
+    pub fn open_index<'index>(&'index self, bytes: &[u8]) -> Result<NP_Index<'index>, NP_Error> {
+        NP_Index::from_bytes(&self.schema.parsed, bytes)
+    }
+
+    /// Generate a new empty buffer that reuses allocations from this factory.
+    /// 
+    /// Pooled buffers work like any other buffer, but when they're dropped or `finish`ed their allocation goes back into a pool in the factory.  The next pooled buffer picks it up instead of allocating a new one.
+    /// 
+    /// Check out documentation [here](./pool/index.html).
+    /// 
+    #[cfg(target_has_atomic = "ptr")]
+    pub fn pooled_buffer<'buffer>(&'buffer self) -> NP_Pooled_Buffer<'buffer> {
+        let memory = match self.pool.take() {
+            Some(mut bytes) => {
+                bytes.clear();
+                self.write_header(&mut bytes);
+                NP_Memory::from_header(bytes, &self.schema.parsed)
+            },
+            None => NP_Memory::new_with_header(None, &self.schema.parsed, &self.buffer_header())
+        };
+        let mut buffer = NP_Pooled_Buffer::new(NP_Buffer::_new(memory.with_constraints(&self.schema.constraints)), &self.pool);
+        self.stamp_now(&mut buffer, &self.schema).unwrap_or(());
+        buffer
+    }
+
+    /// How many allocations are waiting in the pool for the next `pooled_buffer`
+    #[cfg(target_has_atomic = "ptr")]
+    pub fn pooled_allocations(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// The header bytes in front of every new buffer from this factory
+    fn buffer_header(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        self.write_header(&mut header);
+        header
+    }
+
+    fn write_header(&self, bytes: &mut Vec<u8>) {
+        let (header, len) = self.header_bytes();
+        bytes.extend_from_slice(&header[0..len]);
+    }
+
+    /// Header for new buffers without allocating, headers are never longer than 14 bytes
+    fn header_bytes(&self) -> ([u8; 14], usize) {
+        self.header_bytes_for(&self.schema)
+    }
+
+    fn header_bytes_for(&self, schema: &NP_Schema) -> ([u8; 14], usize) {
+        let mut header = [0u8; 14];
+        let mut len = 1;
+        header[0] = self.address_size.header_flags();
+        if self.stamp_fingerprint {
+            header[0] |= NP_HEADER_FINGERPRINT;
+            header[1..9].copy_from_slice(&schema.fingerprint());
+            len += 8;
+        }
+        if let Some(version) = self.version {
+            header[0] |= NP_HEADER_VERSION;
+            header[len..(len + 4)].copy_from_slice(&version.to_be_bytes());
+            // user flags stay zero
+            len += 5;
+        }
+        (header, len)
+    }
+
+    /// Generate a new buffer from a JSON document.
+    /// 
+    /// The document is parsed and set into an empty buffer with `set_json`, so every value is checked against the schema.  Numbers and dates can be provided as strings and uuids are parsed from their string form.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",    {"type": "string"}],
+    ///         ["age",     {"type": "uint8"}],
+    ///         ["created", {"type": "date"}],
+    ///         ["id",      {"type": "uuid"}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// let new_buffer = factory.json_buffer(r#"{
+    ///     "name": "Jeb Kermin",
+    ///     "age": "30",
+    ///     "created": 1605138980392,
+    ///     "id": "48E6AAB0-7DF5-409F-4D57-4D969FA065EE"
+    /// }"#)?;
+    /// 
+    /// assert_eq!(new_buffer.get::<u8>(&["age"])?, Some(30));
+    /// assert_eq!(new_buffer.json_encode(&["id"])?.stringify(), "\"48E6AAB0-7DF5-409F-4D57-4D969FA065EE\"");
+    /// 
+    /// // documents must match the schema
+    /// assert!(factory.json_buffer(r#"{"age": "old"}"#).is_err());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn json_buffer<'buffer>(&'buffer self, json: &str) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let parsed = json_decode(json.to_owned())?;
+        let mut buffer = self.empty_buffer(None);
+        buffer.set_json(&[], &parsed)?;
+        Ok(buffer)
+    }
+
+    /// Generate a new buffer from MessagePack bytes.
+    /// 
+    /// The msgpack value is checked against the schema as it's copied into the buffer.  This method requires the `msgpack` feature.
+    /// 
+    /// Check out documentation [here](./msgpack/index.html).
+    /// 
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack<'buffer>(&'buffer self, bytes: &[u8]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let tree = crate::msgpack::decode(bytes, &self.schema.parsed, 0)?;
+        let mut buffer = self.empty_buffer(None);
+        buffer.from_owned_tree(&tree)?;
+        Ok(buffer)
+    }
+
+    /// Generate a new buffer from CBOR bytes.
+    /// 
+    /// The CBOR value is checked against the schema as it's copied into the buffer.  This method requires the `cbor` feature.
+    /// 
+    /// Check out documentation [here](./cbor/index.html).
+    /// 
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor<'buffer>(&'buffer self, bytes: &[u8]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let tree = crate::cbor::decode(bytes, &self.schema.parsed, 0)?;
+        let mut buffer = self.empty_buffer(None);
+        buffer.from_owned_tree(&tree)?;
+        Ok(buffer)
+    }
+
+    /// Generate a new buffer from a protobuf message.  The schema must be a table.
+    /// 
+    /// The message is checked against the schema as it's copied into the buffer, unknown fields are skipped.  This method requires the `protobuf` feature.
+    /// 
+    /// Check out documentation [here](./protobuf/index.html).
+    /// 
+    #[cfg(feature = "protobuf")]
+    pub fn from_protobuf<'buffer>(&'buffer self, bytes: &[u8]) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let tree = crate::protobuf::decode_message(bytes, &self.schema.parsed, 0)?;
+        let mut buffer = self.empty_buffer(None);
+        buffer.from_owned_tree(&tree)?;
+        Ok(buffer)
     }
 }
\ No newline at end of file
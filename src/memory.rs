@@ -2,76 +2,437 @@
 
 use crate::{schema::NP_Parsed_Schema};
 use crate::{error::NP_Error};
+use core::convert::TryInto;
 use core::cell::UnsafeCell;
+use core::marker::PhantomData;
 use alloc::vec::Vec;
-
+use alloc::sync::Arc;
+use crate::buffer::{ROOT_PTR_ADDR, NP_Tombstones};
+use crate::validation::NP_Constraints;
+
+
+/// Storage behind buffer memory
+/// 
+/// Borrowed bytes are kept as raw parts so the memory stays covariant over it's lifetime, `NP_Memory` holds the borrow.
+enum NP_Memory_Bytes {
+    /// bytes owned by the buffer, new values are appended
+    Owned(Vec<u8>),
+    /// bytes borrowed from the caller, values can be changed in place but the buffer can't grow
+    Borrowed(*mut u8, usize),
+    /// bytes borrowed from the caller that can't be changed
+    Read_Only(*const u8, usize),
+    /// bytes borrowed from the caller with a fixed capacity, new values are appended until it's full
+    Fixed(*mut u8, usize, usize),
+    /// owned bytes shared with snapshots, `Arc::make_mut` copies them the first time they're changed while a snapshot is still alive
+    Shared(Arc<Vec<u8>>),
+    /// bytes of a snapshot that can't be changed
+    Snapshot(Arc<Vec<u8>>)
+}
 
 #[doc(hidden)]
 pub struct NP_Memory<'memory> {
-    bytes: UnsafeCell<Vec<u8>>,
-    pub schema: &'memory Vec<NP_Parsed_Schema>
+    bytes: UnsafeCell<NP_Memory_Bytes>,
+    borrowed: PhantomData<&'memory mut [u8]>,
+    pub schema: &'memory Vec<NP_Parsed_Schema>,
+    /// address of the root pointer, right after the header
+    pub root: usize,
+    /// size of the addresses stored in the buffer
+    pub addr_size: NP_Address_Size,
+    /// optional schema constraints checked by `NP_Buffer::validate`
+    pub(crate) constraints: &'memory [NP_Constraints],
+    /// what deleting values leaves behind, set by `NP_Buffer::set_tombstones`
+    pub(crate) tombstones: NP_Tombstones
+}
+
+/// Get the size of the header described by the first byte of a buffer, including the first byte
+pub fn header_size(flags: u8) -> usize {
+    let mut size = 1;
+    if flags & NP_HEADER_FINGERPRINT != 0 {
+        size += 8;
+    }
+    if flags & NP_HEADER_VERSION != 0 {
+        size += 5;
+    }
+    size
+}
+
+/// Read the schema version and user flags from the header of buffer bytes
+pub fn header_version(bytes: &[u8]) -> Option<(u32, u8)> {
+    let flags = *bytes.first()?;
+    if flags & NP_HEADER_VERSION == 0 {
+        return None;
+    }
+    let start = if flags & NP_HEADER_FINGERPRINT != 0 { 9 } else { 1 };
+    if bytes.len() < start + 5 {
+        return None;
+    }
+    let mut version = [0u8; 4];
+    version.copy_from_slice(&bytes[start..(start + 4)]);
+    Some((u32::from_be_bytes(version), bytes[start + 4]))
 }
 
+/// Header flag for buffers that start with the fingerprint of their schema
+pub const NP_HEADER_FINGERPRINT: u8 = 0b0000_0001;
+/// Header flag for buffers with a schema version number and user flags
+pub const NP_HEADER_VERSION: u8 = 0b0000_0010;
+/// Header flag for buffers that use 4 byte addresses
+pub const NP_HEADER_ADDR_U32: u8 = 0b0000_0100;
+/// Header flag for buffers that use 1 byte addresses
+pub const NP_HEADER_ADDR_U8: u8 = 0b0000_1000;
+
+/// Size of the addresses stored in a buffer
+/// 
+/// Every pointer in a buffer is an address, so the address size limits how large a buffer can get.  
+/// The address size is recorded in the buffer header, buffers without a header use 2 byte addresses.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_Address_Size {
+    /// 1 byte addresses, buffers can be up to 255 bytes
+    U8,
+    /// 2 byte addresses, buffers can be up to 64 KB (default)
+    U16,
+    /// 4 byte addresses, buffers can be up to 4 GB
+    U32
+}
 
+impl Default for NP_Address_Size {
+    fn default() -> Self {
+        NP_Address_Size::U16
+    }
+}
+
+impl NP_Address_Size {
+    /// Get the address size recorded in buffer header flags
+    pub fn from_flags(flags: u8) -> Self {
+        if flags & NP_HEADER_ADDR_U32 != 0 {
+            NP_Address_Size::U32
+        } else if flags & NP_HEADER_ADDR_U8 != 0 {
+            NP_Address_Size::U8
+        } else {
+            NP_Address_Size::U16
+        }
+    }
+
+    /// Header flag bits for this address size
+    pub fn header_flags(&self) -> u8 {
+        match self {
+            NP_Address_Size::U8  => NP_HEADER_ADDR_U8,
+            NP_Address_Size::U16 => 0,
+            NP_Address_Size::U32 => NP_HEADER_ADDR_U32
+        }
+    }
+
+    /// How many bytes each address takes
+    pub fn bytes(&self) -> usize {
+        match self {
+            NP_Address_Size::U8  => 1,
+            NP_Address_Size::U16 => 2,
+            NP_Address_Size::U32 => 4
+        }
+    }
+
+    /// Largest buffer that can be addressed
+    pub fn max_buffer_size(&self) -> usize {
+        match self {
+            NP_Address_Size::U8  => core::u8::MAX as usize,
+            NP_Address_Size::U16 => core::u16::MAX as usize,
+            NP_Address_Size::U32 => core::u32::MAX as usize
+        }
+    }
+}
+
+// SAFETY: the raw pointers are the only fields that aren't `Send` on their own, each storage variant is safe to move to another thread:
+// - `Owned` is a `Vec<u8>`.
+// - `Borrowed` and `Fixed` are the parts of a `&'memory mut [u8]`, which is `Send`.  `borrowed` holds that exclusive borrow, so nothing else can touch the bytes while the memory is on another thread.
+// - `Read_Only` is the parts of a `&'memory [u8]`, which is `Send` because `u8` is `Sync`.  The bytes are only ever read through shared slices, `write_ptr` refuses them.
+// - `Shared` and `Snapshot` are an `Arc<Vec<u8>>`.  Other clones of the `Arc` can read the same bytes from other threads, so snapshots are only read through shared slices and `Shared` bytes are only written through `Arc::make_mut`, which copies them if a snapshot still has them.
+// The memory itself isn't `Sync`, it changes it's storage through `&self`.
+unsafe impl<'memory> Send for NP_Memory<'memory> {}
 
 #[doc(hidden)]
 impl<'memory> NP_Memory<'memory> {
 
 
     pub fn existing(bytes: Vec<u8>, schema: &'memory Vec<NP_Parsed_Schema>) -> Self {
+        Self::from_storage(NP_Memory_Bytes::Owned(bytes), schema)
+    }
 
-        NP_Memory {
-            bytes: UnsafeCell::new(bytes),
-            schema: schema
+    /// Use borrowed bytes in place, values can be changed but nothing can be added
+    pub fn existing_borrowed(bytes: &'memory mut [u8], schema: &'memory Vec<NP_Parsed_Schema>) -> Self {
+        Self::from_storage(NP_Memory_Bytes::Borrowed(bytes.as_mut_ptr(), bytes.len()), schema)
+    }
+
+    /// Use borrowed bytes in place without changing them
+    pub fn existing_read_only(bytes: &'memory [u8], schema: &'memory Vec<NP_Parsed_Schema>) -> Self {
+        Self::from_storage(NP_Memory_Bytes::Read_Only(bytes.as_ptr(), bytes.len()), schema)
+    }
+
+    /// Use the first `len` bytes of a borrowed slice, new values are appended into the rest of the slice
+    pub fn existing_fixed(bytes: &'memory mut [u8], len: usize, schema: &'memory Vec<NP_Parsed_Schema>) -> Result<Self, NP_Error> {
+        if len > bytes.len() {
+            return Err(NP_Error::BufferFull("Buffer length is larger than the fixed size bytes!".into()));
+        }
+        Ok(Self::from_storage(NP_Memory_Bytes::Fixed(bytes.as_mut_ptr(), len, bytes.len()), schema))
+    }
+
+    /// Create empty memory inside a borrowed slice starting with the provided header
+    pub fn new_fixed(bytes: &'memory mut [u8], schema: &'memory Vec<NP_Parsed_Schema>, header: &[u8]) -> Result<Self, NP_Error> {
+        let addr_size = NP_Address_Size::from_flags(*header.first().unwrap_or(&0));
+        let root = header.len();
+
+        if root + addr_size.bytes() > bytes.len() {
+            return Err(NP_Error::BufferFull("Fixed size bytes are too small to hold an empty buffer!".into()));
         }
+
+        bytes[0..root].copy_from_slice(header);
+        // root pointer
+        for x in bytes[root..(root + addr_size.bytes())].iter_mut() {
+            *x = 0;
+        }
+
+        Ok(NP_Memory {
+            bytes: UnsafeCell::new(NP_Memory_Bytes::Fixed(bytes.as_mut_ptr(), root + addr_size.bytes(), bytes.len())),
+            borrowed: PhantomData,
+            schema: schema,
+            root: root,
+            addr_size: addr_size,
+            constraints: &[],
+            tombstones: NP_Tombstones::Off
+        })
+    }
+
+    fn from_storage(storage: NP_Memory_Bytes, schema: &'memory Vec<NP_Parsed_Schema>) -> Self {
+
+        let memory = NP_Memory {
+            bytes: UnsafeCell::new(storage),
+            borrowed: PhantomData,
+            schema: schema,
+            root: ROOT_PTR_ADDR,
+            addr_size: NP_Address_Size::U16,
+            constraints: &[],
+            tombstones: NP_Tombstones::Off
+        };
+
+        let (addr_size, root) = {
+            let bytes = memory.read_bytes();
+
+            let addr_size = NP_Address_Size::from_flags(*bytes.first().unwrap_or(&0));
+
+            let root = match bytes.first() {
+                Some(flags) if header_size(*flags) + addr_size.bytes() <= bytes.len() => header_size(*flags),
+                _ => ROOT_PTR_ADDR
+            };
+
+            (addr_size, root)
+        };
+
+        NP_Memory { root, addr_size, ..memory }
     }
 
 
     pub fn new(capacity: Option<usize>, schema: &'memory Vec<NP_Parsed_Schema>) -> Self {
+        Self::new_with_header(capacity, schema, &[0u8])
+    }
+
+    /// Create empty memory starting with the provided header, the first header byte holds the header flags and address size
+    pub fn new_with_header(capacity: Option<usize>, schema: &'memory Vec<NP_Parsed_Schema>, header: &[u8]) -> Self {
         let use_size = match capacity {
             Some(x) => x,
             None => 1024
         };
 
         let mut new_bytes = Vec::with_capacity(use_size);
+        new_bytes.extend(header);
+
+        Self::from_header(new_bytes, schema)
+    }
+
+    /// Create empty memory from bytes that only hold a header, the allocation is used as is
+    pub fn from_header(mut bytes: Vec<u8>, schema: &'memory Vec<NP_Parsed_Schema>) -> Self {
+
+        let addr_size = NP_Address_Size::from_flags(*bytes.first().unwrap_or(&0));
+        let root = bytes.len();
 
-        // size, root pointer
-        new_bytes.extend(&[0u8; 3]);
+        // root pointer
+        bytes.resize(root + addr_size.bytes(), 0);
 
         NP_Memory {
-            bytes: UnsafeCell::new(new_bytes),
+            bytes: UnsafeCell::new(NP_Memory_Bytes::Owned(bytes)),
+            borrowed: PhantomData,
             schema: schema,
+            root: root,
+            addr_size: addr_size,
+            constraints: &[],
+            tombstones: NP_Tombstones::Off
         }
     }
 
-    pub fn malloc_borrow(&self, bytes: &[u8])  -> Result<usize, NP_Error> {
-        let self_bytes = unsafe { &mut *self.bytes.get() };
+    /// Use the schema constraints for validation
+    pub(crate) fn with_constraints(mut self, constraints: &'memory [NP_Constraints]) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Use the tombstone setting of another buffer
+    pub(crate) fn with_tombstones(mut self, tombstones: NP_Tombstones) -> Self {
+        self.tombstones = tombstones;
+        self
+    }
+
+    /// The header bytes in front of the root pointer
+    pub fn header(&self) -> &[u8] {
+        let bytes = self.read_bytes();
+        &bytes[0..self.root.min(bytes.len())]
+    }
+
+    /// Is this memory borrowed without permission to change it?
+    pub fn is_read_only(&self) -> bool {
+        match unsafe { &*self.bytes.get() } {
+            NP_Memory_Bytes::Read_Only(..) | NP_Memory_Bytes::Snapshot(..) => true,
+            _ => false
+        }
+    }
+
+    /// Is this memory borrowed with a fixed capacity?
+    pub fn is_fixed(&self) -> bool {
+        match unsafe { &*self.bytes.get() } {
+            NP_Memory_Bytes::Fixed(..) => true,
+            _ => false
+        }
+    }
 
-        let location = self_bytes.len();
+    /// Read only memory that shares the current bytes, the bytes are only copied if this memory is changed while the snapshot is still alive
+    ///
+    /// The copy is of every byte in the memory, not just the part being changed.  After that first change the memory has it's own bytes again and later changes don't copy anything.
+    pub fn snapshot(&self) -> Self {
+        let storage = unsafe { &mut *self.bytes.get() };
+        let shared = match storage {
+            NP_Memory_Bytes::Owned(x) => {
+                let shared = Arc::new(core::mem::replace(x, Vec::new()));
+                *storage = NP_Memory_Bytes::Shared(Arc::clone(&shared));
+                shared
+            },
+            NP_Memory_Bytes::Shared(x) | NP_Memory_Bytes::Snapshot(x) => Arc::clone(x),
+            // borrowed bytes can change under the snapshot, so those are copied right away
+            _ => Arc::new(self.read_bytes().to_vec())
+        };
+
+        NP_Memory {
+            bytes: UnsafeCell::new(NP_Memory_Bytes::Snapshot(shared)),
+            borrowed: PhantomData,
+            schema: self.schema,
+            root: self.root,
+            addr_size: self.addr_size,
+            constraints: self.constraints,
+            tombstones: self.tombstones
+        }
+    }
+
+    /// Add `bytes` to the end of the buffer, or `size` zeros if there are no bytes
+    fn grow(&self, size: usize, bytes: Option<&[u8]>) -> Result<usize, NP_Error> {
+
+        let location = self.read_bytes().len();
 
         // not enough space left?
-        if location + bytes.len() >= core::u16::MAX as usize {
-            return Err(NP_Error::new("Not enough space available in buffer!"))
+        if location + size >= self.addr_size.max_buffer_size() {
+            return Err(NP_Error::BufferFull("Not enough space available in buffer!".into()))
+        }
+
+        let owned = match unsafe { &mut *self.bytes.get() } {
+            NP_Memory_Bytes::Owned(x) => x,
+            NP_Memory_Bytes::Shared(x) => Arc::make_mut(x),
+            NP_Memory_Bytes::Fixed(ptr, len, capacity) => {
+                if location + size > *capacity {
+                    return Err(NP_Error::BufferFull("Fixed size buffer is full!".into()))
+                }
+                let new_bytes = unsafe { core::slice::from_raw_parts_mut(ptr.add(location), size) };
+                match bytes {
+                    Some(bytes) => new_bytes.copy_from_slice(bytes),
+                    None => for x in new_bytes.iter_mut() { *x = 0 }
+                }
+                *len = location + size;
+                return Ok(location);
+            },
+            NP_Memory_Bytes::Borrowed(..) => return Err(NP_Error::ReadOnly("Borrowed buffers can't grow, only existing values can be changed!  Use open_buffer to add new values.".into())),
+            NP_Memory_Bytes::Read_Only(..) | NP_Memory_Bytes::Snapshot(..) => return Err(NP_Error::ReadOnly("Read only buffers can't be changed!".into()))
+        };
+
+        match bytes {
+            Some(bytes) => owned.extend_from_slice(bytes),
+            None => owned.resize(location + size, 0)
         }
 
-        self_bytes.extend(bytes);
         Ok(location)
     }
 
+    /// Make room for at least `additional` more bytes so the next allocations don't move the buffer
+    pub fn reserve(&self, additional: usize) {
+        match unsafe { &mut *self.bytes.get() } {
+            NP_Memory_Bytes::Owned(x) => x.reserve(additional),
+            NP_Memory_Bytes::Shared(x) => Arc::make_mut(x).reserve(additional),
+            _ => { }
+        }
+    }
+
+    /// Shorten the buffer to `len` bytes, borrowed bytes keep their length
+    pub fn truncate(&self, len: usize) {
+        match unsafe { &mut *self.bytes.get() } {
+            NP_Memory_Bytes::Owned(x) => x.truncate(len),
+            NP_Memory_Bytes::Shared(x) => Arc::make_mut(x).truncate(len),
+            NP_Memory_Bytes::Fixed(_, x, _) => *x = len.min(*x),
+            _ => { }
+        }
+    }
+
+    /// Replace the contents of fixed size memory, the header has to be the same
+    pub fn refill(&self, bytes: &[u8]) -> Result<(), NP_Error> {
+        match unsafe { &mut *self.bytes.get() } {
+            NP_Memory_Bytes::Fixed(ptr, len, capacity) if bytes.len() <= *capacity => {
+                unsafe { core::slice::from_raw_parts_mut(*ptr, bytes.len()) }.copy_from_slice(bytes);
+                *len = bytes.len();
+                Ok(())
+            },
+            _ => Err(NP_Error::BufferFull("Only fixed size memory with enough space can be refilled!".into()))
+        }
+    }
+
+    pub fn malloc_borrow(&self, bytes: &[u8])  -> Result<usize, NP_Error> {
+        self.grow(bytes.len(), Some(bytes))
+    }
+
     pub fn malloc(&self, bytes: Vec<u8>) -> Result<usize, NP_Error> {
         self.malloc_borrow(&bytes)
     }
 
+    /// Allocate zeroed space for `addresses` pointer addresses plus `extra` bytes
+    pub fn malloc_pointers(&self, addresses: usize, extra: usize) -> Result<usize, NP_Error> {
+        self.grow((addresses * self.addr_size.bytes()) + extra, None)
+    }
+
     #[inline(always)]
-    pub fn read_bytes(&self) -> &Vec<u8> {
-        let self_bytes = unsafe { &*self.bytes.get() };
-        self_bytes
+    pub fn read_bytes(&self) -> &[u8] {
+        match unsafe { &*self.bytes.get() } {
+            NP_Memory_Bytes::Owned(x) => x,
+            NP_Memory_Bytes::Borrowed(ptr, len) => unsafe { core::slice::from_raw_parts(*ptr, *len) },
+            NP_Memory_Bytes::Read_Only(ptr, len) => unsafe { core::slice::from_raw_parts(*ptr, *len) },
+            NP_Memory_Bytes::Fixed(ptr, len, _) => unsafe { core::slice::from_raw_parts(*ptr, *len) },
+            NP_Memory_Bytes::Shared(x) | NP_Memory_Bytes::Snapshot(x) => x
+        }
     }   
 
+    /// Pointer to the start of bytes that can be changed, `None` for read only and snapshot memory so there's never a `&mut` over bytes the buffer doesn't own.
+    ///
+    /// Changes are made through slices of only the bytes being changed, a `&mut` over the whole buffer would invalidate every pointer struct overlayed on it.
     #[inline(always)]
-    pub fn write_bytes(&self) -> &mut Vec<u8> {
-        let self_bytes = unsafe { &mut *self.bytes.get() };
-        self_bytes
+    pub(crate) fn write_ptr(&self) -> Option<*mut u8> {
+        match unsafe { &mut *self.bytes.get() } {
+            // `as_mut_ptr` doesn't make a reference to the bytes, so pointers handed out before stay valid
+            NP_Memory_Bytes::Owned(x) => Some(x.as_mut_ptr()),
+            NP_Memory_Bytes::Shared(x) => Some(Arc::make_mut(x).as_mut_ptr()),
+            NP_Memory_Bytes::Borrowed(ptr, _) => Some(*ptr),
+            NP_Memory_Bytes::Fixed(ptr, _, _) => Some(*ptr),
+            NP_Memory_Bytes::Read_Only(..) | NP_Memory_Bytes::Snapshot(..) => None
+        }
     }
 
     #[inline(always)]
@@ -82,9 +443,31 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
- 
-        Some(self_bytes[address])
+        self.read_bytes().get(address).copied()
+    }
+
+    /// Get `len` bytes starting at `address` to change them, errors if they go past the end of the buffer
+    #[inline(always)]
+    pub fn get_bytes_mut(&self, address: usize, len: usize) -> Result<&mut [u8], NP_Error> {
+        match address.checked_add(len) {
+            Some(end) if end <= self.read_bytes().len() => { },
+            _ => return Err(NP_Error::OutOfBounds("Value is past the end of the buffer!".into()))
+        }
+        match self.write_ptr() {
+            // SAFETY: `address..address + len` is inside the bytes and they can be changed
+            Some(ptr) => Ok(unsafe { core::slice::from_raw_parts_mut(ptr.add(address), len) }),
+            None => Err(NP_Error::ReadOnly("Read only buffers can't be changed!".into()))
+        }
+    }
+
+    /// Get `len` bytes starting at `address`, errors if they go past the end of the buffer
+    #[inline(always)]
+    pub fn get_bytes(&self, address: usize, len: usize) -> Result<&[u8], NP_Error> {
+        let bytes = self.read_bytes();
+        match address.checked_add(len) {
+            Some(end) if end <= bytes.len() => Ok(&bytes[address..end]),
+            _ => Err(NP_Error::OutOfBounds("Value is past the end of the buffer!".into()))
+        }
     }
 
     #[inline(always)]
@@ -95,7 +478,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.read_bytes();
 
         if self_bytes.len() < address + 2 {
             return None;
@@ -103,7 +486,7 @@ impl<'memory> NP_Memory<'memory> {
 
         let slice = &self_bytes[address..(address + 2)];
 
-        Some(unsafe { &*(slice as *const [u8] as *const [u8; 2]) })
+        slice.try_into().ok()
     }
 
     #[inline(always)]
@@ -114,7 +497,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.read_bytes();
 
         if self_bytes.len() < address + 4 {
             return None;
@@ -122,7 +505,7 @@ impl<'memory> NP_Memory<'memory> {
 
         let slice = &self_bytes[address..(address + 4)];
 
-        Some(unsafe { &*(slice as *const [u8] as *const [u8; 4]) })
+        slice.try_into().ok()
     }
 
     #[inline(always)]
@@ -133,7 +516,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.read_bytes();
 
         if self_bytes.len() < address + 8 {
             return None;
@@ -141,7 +524,7 @@ impl<'memory> NP_Memory<'memory> {
 
         let slice = &self_bytes[address..(address + 8)];
 
-        Some(unsafe { &*(slice as *const [u8] as *const [u8; 8]) })
+        slice.try_into().ok()
     }
 
     #[inline(always)]
@@ -152,7 +535,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.read_bytes();
 
         if self_bytes.len() < address + 16 {
             return None;
@@ -160,7 +543,7 @@ impl<'memory> NP_Memory<'memory> {
 
         let slice = &self_bytes[address..(address + 16)];
 
-        Some(unsafe { &*(slice as *const [u8] as *const [u8; 16]) })
+        slice.try_into().ok()
     }
 
     #[inline(always)]
@@ -171,7 +554,7 @@ impl<'memory> NP_Memory<'memory> {
             return None;
         }
 
-        let self_bytes = unsafe { &*self.bytes.get() };
+        let self_bytes = self.read_bytes();
 
         if self_bytes.len() < address + 32 {
             return None;
@@ -179,10 +562,315 @@ impl<'memory> NP_Memory<'memory> {
 
         let slice = &self_bytes[address..(address + 32)];
 
-        Some(unsafe { &*(slice as *const [u8] as *const [u8; 32]) })
+        slice.try_into().ok()
     }
 
     pub fn dump(self) -> Vec<u8> {
-        self.bytes.into_inner()
+        match self.bytes.into_inner() {
+            NP_Memory_Bytes::Owned(x) => x,
+            NP_Memory_Bytes::Borrowed(ptr, len) => unsafe { core::slice::from_raw_parts(ptr, len) }.to_vec(),
+            NP_Memory_Bytes::Read_Only(ptr, len) => unsafe { core::slice::from_raw_parts(ptr, len) }.to_vec(),
+            NP_Memory_Bytes::Fixed(ptr, len, _) => unsafe { core::slice::from_raw_parts(ptr, len) }.to_vec(),
+            NP_Memory_Bytes::Shared(x) | NP_Memory_Bytes::Snapshot(x) => Arc::try_unwrap(x).unwrap_or_else(|x| Vec::clone(&x))
+        }
+    }
+}
+#[test]
+fn address_sizes_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let schema = r#"{
+        "type": "table",
+        "columns": [
+            ["id",    {"type": "u32"}],
+            ["name",  {"type": "string"}],
+            ["tags",  {"type": "list", "of": {"type": "string"}}],
+            ["meta",  {"type": "map", "value": {"type": "u8"}}],
+            ["point", {"type": "tuple", "values": [{"type": "i16"}, {"type": "i16"}]}],
+            ["score", {"type": "float"}]
+        ]
+    }"#;
+
+    let tiny = NP_Factory::new(schema)?.with_address_size(NP_Address_Size::U8);
+    let small = NP_Factory::new(schema)?;
+    let large = NP_Factory::new(schema)?.with_address_size(NP_Address_Size::U32);
+
+    let mut encoded = Vec::new();
+
+    for factory in [&tiny, &small, &large].iter() {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&["id"], 20u32)?;
+        buffer.set(&["name"], "hello")?;
+        buffer.set(&["tags", "2"], "blue")?;
+        buffer.set(&["tags", "0"], "red")?;
+        buffer.set(&["meta", "a"], 1u8)?;
+        buffer.set(&["meta", "b"], 2u8)?;
+        buffer.set(&["point", "1"], -5i16)?;
+        buffer.set(&["score"], 2.5f32)?;
+        buffer.set(&["name"], "hello world")?;
+        buffer.compact(None)?;
+        assert_eq!(buffer.calc_bytes()?.after_compaction, buffer.calc_bytes()?.current_buffer);
+
+        let buffer = small.open_buffer(buffer.close());
+        encoded.push(buffer.json_encode(&[])?.stringify());
+    }
+
+    assert_eq!(encoded[0], encoded[1]);
+    assert_eq!(encoded[1], encoded[2]);
+    assert_eq!(tiny.empty_buffer(None).read_bytes(), &alloc::vec![NP_HEADER_ADDR_U8, 0]);
+    assert_eq!(large.empty_buffer(None).read_bytes(), &alloc::vec![NP_HEADER_ADDR_U32, 0, 0, 0, 0]);
+
+    // tiny buffers run out of space quickly
+    let mut buffer = tiny.empty_buffer(None);
+    assert!(buffer.set(&["name"], "x".repeat(300).as_str()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn tiny_addresses_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use alloc::string::ToString;
+
+    let schema = r#"{"type": "list", "of": {"type": "table", "columns": [["id", {"type": "u8"}], ["tag", {"type": "string"}]]}}"#;
+    let tiny = NP_Factory::new(schema)?.with_address_size(NP_Address_Size::U8);
+    let small = NP_Factory::new(schema)?;
+
+    // every pointer is smaller than with 2 byte addresses
+    let mut tiny_buffer = tiny.empty_buffer(None);
+    let mut small_buffer = small.empty_buffer(None);
+    for x in 0..4u8 {
+        tiny_buffer.set(&[&x.to_string(), "id"], x)?;
+        small_buffer.set(&[&x.to_string(), "id"], x)?;
+    }
+    assert!(tiny_buffer.read_bytes().len() < small_buffer.read_bytes().len());
+    assert!(tiny_buffer.deep_eq(&small_buffer)?);
+
+    // fill the buffer until the addresses run out
+    let mut x = 4usize;
+    let error = loop {
+        match tiny_buffer.set(&[&x.to_string(), "tag"], "tag") {
+            Ok(_) => x += 1,
+            Err(e) => break e
+        }
+    };
+    assert!(matches!(error, NP_Error::BufferFull(_)));
+    assert!(x > 10);
+    assert!(tiny_buffer.read_bytes().len() < NP_Address_Size::U8.max_buffer_size());
+    let bytes = tiny_buffer.close();
+    assert!(tiny.open_buffer(bytes.clone()).verify()?.is_ok());
+
+    // everything before the full buffer is still readable, from any factory
+    let buffer = small.open_buffer(bytes);
+    assert_eq!(buffer.get::<u8>(&["3", "id"])?, Some(3));
+    assert_eq!(buffer.get::<&str>(&[&(x - 1).to_string(), "tag"])?, Some("tag"));
+
+    // headers go in front of tiny addresses too
+    let stamped = NP_Factory::new(schema)?.with_address_size(NP_Address_Size::U8).with_fingerprint().with_version(2);
+    let mut buffer = stamped.empty_buffer(None);
+    assert_eq!(buffer.read_bytes().len(), 1 + 8 + 5 + 1);
+    buffer.set(&["0", "tag"], "hi")?;
+    let buffer = stamped.open_buffer_checked(buffer.close())?;
+    assert_eq!((buffer.get_version(), buffer.get::<&str>(&["0", "tag"])?), (Some(2), Some("hi")));
+
+    Ok(())
+}
+
+#[test]
+fn borrowed_memory_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id",   {"type": "u32"}],
+            ["tags", {"type": "list", "of": {"type": "string"}}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["id"], 20u32)?;
+    buffer.set(&["tags", "0"], "red")?;
+    let mut frame = buffer.close();
+
+    {
+        let mut borrowed = factory.open_buffer_borrowed(&mut frame[..]);
+        borrowed.set(&["id"], 25u32)?;
+        assert!(borrowed.set(&["tags", "1"], "blue").is_err());
+        assert!(borrowed.del(&["tags"])?);
+        // compacting copies into bytes that can grow
+        borrowed.compact(None)?;
+        borrowed.set(&["tags", "1"], "blue")?;
+        assert_eq!(borrowed.get::<&str>(&["tags", "1"])?, Some("blue"));
     }
-}
\ No newline at end of file
+
+    let mut read_only = factory.open_buffer_read_only(&frame[..]);
+    assert_eq!(read_only.get::<u32>(&["id"])?, Some(25));
+    assert_eq!(read_only.get::<&str>(&["tags", "0"])?, None);
+    assert_eq!(read_only.move_cursor(&["tags", "0"])?, false);
+    assert!(read_only.set(&["id"], 1u32).is_err());
+    assert!(read_only.del(&["id"]).is_err());
+    assert_eq!(read_only.close(), frame);
+
+    // clearing a read only buffer starts over with owned bytes
+    let mut read_only = factory.open_buffer_read_only(&frame[..]);
+    read_only.clear();
+    read_only.set(&["id"], 1u32)?;
+    assert_eq!(read_only.get::<u32>(&["id"])?, Some(1));
+    assert_eq!(frame[1..3], [0, 3]);
+
+    Ok(())
+}
+
+#[test]
+fn fixed_memory_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use crate::buffer::alloc_counter;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id",   {"type": "u32"}],
+            ["tags", {"type": "list", "of": {"type": "string"}}],
+            ["meta", {"type": "map", "value": {"type": "u8"}}]
+        ]
+    }"#)?.with_version(3);
+
+    let mut storage = [0u8; 96];
+    assert!(factory.empty_buffer_fixed(&mut storage[0..4]).is_err());
+
+    let mut buffer = factory.empty_buffer_fixed(&mut storage)?;
+    let mut result = Ok(());
+    let allocations = alloc_counter::count(|| {
+        result = buffer.set(&["id"], 5u32).and(buffer.set(&["tags", "0"], "hello")).and(buffer.set(&["meta", "a"], 1u8)).map(|_| ());
+    });
+    result?;
+    assert_eq!(allocations, 0);
+    assert_eq!(buffer.get_version(), Some(3));
+
+    // updates fill the free space until it runs out
+    for x in 0..10 {
+        if buffer.set(&["tags", "0"], "a much longer tag").is_err() {
+            assert!(x > 0);
+            break;
+        }
+    }
+    assert_eq!(buffer.get::<&str>(&["tags", "0"])?, Some("a much longer tag"));
+    assert!(buffer.validate().is_ok());
+
+    // compaction stays inside the borrowed bytes
+    let used = buffer.read_bytes().len();
+    buffer.compact(None)?;
+    assert!(buffer.read_bytes().len() < used);
+    buffer.set(&["meta", "b"], 2u8)?;
+    let len = buffer.read_bytes().len();
+    let bytes = buffer.read_bytes().to_vec();
+
+    let buffer = factory.open_buffer_fixed(&mut storage, len)?;
+    assert_eq!(buffer.read_bytes(), &bytes[..]);
+    assert_eq!(buffer.get::<u8>(&["meta", "b"])?, Some(2));
+    assert_eq!(buffer.get::<u32>(&["id"])?, Some(5));
+    assert!(factory.open_buffer_fixed(&mut storage, 97).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn damaged_buffers_dont_panic() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["name",  {"type": "string"}],
+            ["code",  {"type": "string", "size": 4}],
+            ["tags",  {"type": "list", "of": {"type": "string"}}],
+            ["meta",  {"type": "map", "value": {"type": "u32"}}],
+            ["pair",  {"type": "tuple", "values": [{"type": "i64"}, {"type": "bytes"}]}],
+            ["where", {"type": "geo4"}],
+            ["kind",  {"type": "option", "choices": ["a", "b"]}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["name"], "hello")?;
+    buffer.set(&["code"], "ab")?;
+    buffer.list_push(&["tags"], "one")?;
+    buffer.list_push(&["tags"], "two")?;
+    buffer.set(&["meta", "jeb"], 5u32)?;
+    buffer.set(&["meta", "bill"], 7u32)?;
+    buffer.set(&["pair", "0"], -4i64)?;
+    buffer.set(&["pair", "1"], &[1u8, 2, 3][..])?;
+    buffer.set(&["where"], crate::pointer::geo::NP_Geo::new(4, 1.5, 2.5))?;
+    buffer.set(&["kind"], crate::pointer::option::NP_Enum::new("b"))?;
+    let bytes = buffer.close();
+
+    let mut damaged: Vec<Vec<u8>> = Vec::new();
+    for len in 0..bytes.len() {
+        damaged.push(bytes[..len].to_vec());
+    }
+    for x in 0..bytes.len() {
+        for flip in [0x01u8, 0x10, 0xFF].iter() {
+            let mut changed = bytes.clone();
+            changed[x] ^= flip;
+            damaged.push(changed);
+        }
+    }
+
+    // addresses past the end, looping links and bad strings give errors or empty values
+    for bytes in damaged {
+        let buffer = factory.open_buffer(bytes.clone());
+        let _ = buffer.json_encode(&[]);
+        let _ = buffer.calc_bytes();
+        let _ = buffer.get::<&str>(&["meta", "jeb"]);
+        let _ = buffer.iter_list_rev(&["tags"]).map(|items| items.count());
+
+        let mut buffer = factory.open_buffer(bytes);
+        let _ = buffer.set(&["code"], "xyz");
+        let _ = buffer.set(&["pair", "0"], 1i64);
+        let _ = buffer.list_push(&["tags"], "three");
+        let _ = buffer.set(&["meta", "val"], 1u32);
+        let _ = buffer.compact(None);
+        let _ = buffer.json_encode(&[]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn snapshot_write_cost_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use crate::buffer::alloc_counter;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [["id", {"type": "u32"}], ["name", {"type": "string"}]]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["id"], 1u32)?;
+    buffer.set(&["name"], "a name that takes up some space")?;
+    let before = buffer.read_bytes().to_vec();
+
+    // allocations made by changing the id
+    fn set_id(buffer: &mut crate::buffer::NP_Buffer, id: u32) -> Result<usize, NP_Error> {
+        let mut result = Ok(false);
+        let allocations = alloc_counter::count(|| { result = buffer.set(&["id"], id); });
+        result.map(|_| allocations)
+    }
+
+    // without a snapshot alive changes are made in place
+    drop(buffer.snapshot());
+    assert_eq!(set_id(&mut buffer, 2)?, 0);
+
+    // the first change with a snapshot alive copies every byte into a new `Arc`, later ones don't copy again
+    let snapshot = buffer.snapshot();
+    let shared = snapshot.read_bytes().as_ptr();
+    assert_eq!(set_id(&mut buffer, 3)?, 2);
+    assert_ne!(buffer.read_bytes().as_ptr(), shared);
+    assert_eq!(buffer.read_bytes().len(), before.len());
+    let copied = buffer.read_bytes().as_ptr();
+    assert_eq!(set_id(&mut buffer, 4)?, 0);
+    assert_eq!(buffer.read_bytes().as_ptr(), copied);
+
+    assert_eq!(snapshot.get::<u32>(&["id"])?, Some(2));
+    assert_eq!(buffer.get::<u32>(&["id"])?, Some(4));
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//! Frame buffers for streams and sockets
+//!
+//! Buffers don't say how long they are or which schema they use, so sending them over TCP means putting a header in front of each one.  `NP_Message` is that header: the schema fingerprint of the body, a message id and an optional correlation id that points replies back at the message they answer.
+//!
+//! `encode_frame` writes a message as one frame, `decode_frame` reads the first frame from the front of a stream.  Frames look like this:
+//!
+//! | Bytes | Value |
+//! |-------|-------|
+//! | 4     | Magic bytes `NPMF` |
+//! | 1     | Frame version, always `1` for now |
+//! | 1     | Flags, the lowest bit is set if there's a correlation id |
+//! | 8     | Schema fingerprint of the body |
+//! | 8     | Message id, big endian |
+//! | 8     | Correlation id, big endian, zero without one |
+//! | 4     | Body length, big endian |
+//! | ...   | Body buffer bytes |
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::message::{NP_Message, encode_frame, decode_frame};
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//!
+//! let mut stream: Vec<u8> = Vec::new();
+//! stream.extend(encode_frame(&NP_Message::new(&factory, 1, new_buffer)));
+//!
+//! // the other end reads frames as they arrive
+//! let (message, used) = decode_frame(&stream)?.unwrap();
+//! assert_eq!(used, stream.len());
+//! assert_eq!(message.id, 1);
+//!
+//! // and replies to them
+//! let mut reply_buffer = factory.empty_buffer(None);
+//! reply_buffer.set(&["age"], 30u8)?;
+//! let reply = message.reply(&factory, 2, reply_buffer);
+//! assert_eq!(reply.correlation_id, Some(1));
+//!
+//! let buffer = message.open(&factory)?;
+//! assert_eq!(buffer.get::<&str>(&["name"])?, Some("Jeb"));
+//!
+//! // incomplete frames wait for more bytes
+//! let frame = encode_frame(&reply);
+//! assert!(decode_frame(&frame[0..10])?.is_none());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use alloc::vec::Vec;
+
+/// Magic bytes at the start of every frame
+pub const NP_MESSAGE_MAGIC: [u8; 4] = *b"NPMF";
+
+/// Frame version written by `encode_frame`
+pub const NP_MESSAGE_VERSION: u8 = 1;
+
+/// Length of the frame header in front of the body bytes
+pub const NP_MESSAGE_HEADER_SIZE: usize = 34;
+
+/// Flag set when the frame has a correlation id
+const MESSAGE_CORRELATION: u8 = 1;
+
+/// A buffer with the header needed to send it over a stream
+///
+/// Check out documentation [here](../message/index.html).
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NP_Message {
+    /// Schema fingerprint of the body, see `NP_Schema::fingerprint`
+    pub fingerprint: [u8; 8],
+    /// Id of this message
+    pub id: u64,
+    /// Id of the message this one answers
+    pub correlation_id: Option<u64>,
+    /// Buffer bytes
+    pub body: Vec<u8>
+}
+
+impl NP_Message {
+
+    /// Create a message from a buffer of the factory
+    pub fn new(factory: &NP_Factory, id: u64, body: NP_Buffer) -> Self {
+        NP_Message { fingerprint: factory.schema.fingerprint(), id, correlation_id: None, body: body.close() }
+    }
+
+    /// Create a message that answers this one
+    pub fn reply(&self, factory: &NP_Factory, id: u64, body: NP_Buffer) -> Self {
+        NP_Message { correlation_id: Some(self.id), ..NP_Message::new(factory, id, body) }
+    }
+
+    /// Open the body with the factory it was made with.  Fails if the factory has a different schema fingerprint.
+    pub fn open(self, factory: &NP_Factory) -> Result<NP_Buffer<'_>, NP_Error> {
+        if factory.schema.fingerprint() != self.fingerprint {
+            return Err(NP_Error::SchemaMismatch("Message body doesn't use the schema of this factory!".into()));
+        }
+        Ok(factory.open_buffer(self.body))
+    }
+}
+
+/// Write a message as a frame
+pub fn encode_frame(message: &NP_Message) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(NP_MESSAGE_HEADER_SIZE + message.body.len());
+    frame.extend_from_slice(&NP_MESSAGE_MAGIC);
+    frame.push(NP_MESSAGE_VERSION);
+    frame.push(if message.correlation_id.is_some() { MESSAGE_CORRELATION } else { 0 });
+    frame.extend_from_slice(&message.fingerprint);
+    frame.extend_from_slice(&message.id.to_be_bytes());
+    frame.extend_from_slice(&message.correlation_id.unwrap_or(0).to_be_bytes());
+    frame.extend_from_slice(&(message.body.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&message.body);
+    frame
+}
+
+/// Read the frame at the start of `bytes`.
+///
+/// Returns the message and how many bytes the frame used, or `None` if `bytes` doesn't hold the whole frame yet.  Fails if the bytes don't start with a frame.
+///
+pub fn decode_frame(bytes: &[u8]) -> Result<Option<(NP_Message, usize)>, NP_Error> {
+    // check as much of the header as we have so garbage fails early
+    let magic = core::cmp::min(bytes.len(), NP_MESSAGE_MAGIC.len());
+    if bytes[0..magic] != NP_MESSAGE_MAGIC[0..magic] {
+        return Err(NP_Error::Damaged("Bytes don't start with a message frame!".into()));
+    }
+    if bytes.len() > 4 && bytes[4] != NP_MESSAGE_VERSION {
+        return Err(NP_Error::Damaged("Message frame version isn't supported!".into()));
+    }
+    if bytes.len() < NP_MESSAGE_HEADER_SIZE {
+        return Ok(None);
+    }
+
+    let mut word = [0u8; 8];
+    let mut fingerprint = [0u8; 8];
+    fingerprint.copy_from_slice(&bytes[6..14]);
+    word.copy_from_slice(&bytes[14..22]);
+    let id = u64::from_be_bytes(word);
+    word.copy_from_slice(&bytes[22..30]);
+    let correlation_id = if bytes[5] & MESSAGE_CORRELATION != 0 { Some(u64::from_be_bytes(word)) } else { None };
+    let body_len = u32::from_be_bytes([bytes[30], bytes[31], bytes[32], bytes[33]]) as usize;
+
+    let used = NP_MESSAGE_HEADER_SIZE + body_len;
+    if bytes.len() < used {
+        return Ok(None);
+    }
+
+    Ok(Some((NP_Message { fingerprint, id, correlation_id, body: bytes[NP_MESSAGE_HEADER_SIZE..used].to_vec() }, used)))
+}
+
+#[test]
+fn messages_work() -> Result<(), NP_Error> {
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    let other = NP_Factory::new(r#"{"type": "string"}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.list_push(&[], "first")?;
+    let first = NP_Message::new(&factory, 7, buffer);
+    let second = first.reply(&other, 8, other.empty_buffer(None));
+
+    // frames can follow each other in one stream
+    let mut stream = encode_frame(&first);
+    assert_eq!(stream.len(), NP_MESSAGE_HEADER_SIZE + first.body.len());
+    stream.extend(encode_frame(&second));
+
+    let (decoded, used) = decode_frame(&stream)?.unwrap();
+    assert_eq!(decoded, first);
+    let (reply, rest) = decode_frame(&stream[used..])?.unwrap();
+    assert_eq!(used + rest, stream.len());
+    assert_eq!((reply.id, reply.correlation_id), (8, Some(7)));
+    assert_eq!(reply.fingerprint, other.schema.fingerprint());
+
+    // every partial frame waits for more bytes
+    for len in 0..used {
+        assert!(decode_frame(&stream[0..len])?.is_none());
+    }
+
+    // bodies only open with their own schema
+    assert!(decoded.clone().open(&other).is_err());
+    assert_eq!(decoded.open(&factory)?.get::<&str>(&["0"])?, Some("first"));
+
+    // garbage and unknown versions fail
+    assert!(decode_frame(b"NPX").is_err());
+    let mut future = encode_frame(&reply);
+    future[4] = 2;
+    assert!(decode_frame(&future).is_err());
+
+    Ok(())
+}
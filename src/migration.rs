@@ -0,0 +1,313 @@
+//! Migrate buffers between schema versions
+//!
+//! An `NP_Migration` holds a list of steps that upgrade buffers from an old schema into a new one.  Every value is copied from the old buffer into a new buffer created by the new schema's factory, the steps change the values along the way.
+//!
+//! Steps run in this order, no matter the order they were declared in:
+//! 1. `remove` and `rename` use paths in the **old** schema.
+//! 2. `convert` and `set_default` use paths in the **new** schema, after columns have been renamed.
+//!
+//! A `*` in a path matches every item of a list or every value of a map, so `&["users", "*", "name"]` selects the name of every user.  Values that don't fit the new schema after all steps have run make `migrate` return an error, so type changes need a `convert` step.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::migration::NP_Migration;
+//! use no_proto::owned::NP_Value_Owned;
+//!
+//! let v1 = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["fname",  {"type": "string"}],
+//!         ["age",    {"type": "u8"}],
+//!         ["legacy", {"type": "bool"}]
+//!     ]
+//! }"#)?;
+//!
+//! let v2 = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["first_name", {"type": "string"}],
+//!         ["age",        {"type": "u32"}],
+//!         ["active",     {"type": "bool"}]
+//!     ]
+//! }"#)?;
+//!
+//! let migration = NP_Migration::new(&v2)
+//!     .remove(&["legacy"])
+//!     .rename(&["fname"], "first_name")
+//!     .convert(&["age"], |value| match value {
+//!         NP_Value_Owned::Uint8(x) => Ok(NP_Value_Owned::Uint32(x as u32)),
+//!         other => Ok(other)
+//!     })
+//!     .set_default(&["active"], NP_Value_Owned::Bool(true));
+//!
+//! let mut old_buffer = v1.empty_buffer(None);
+//! old_buffer.set(&["fname"], "Jeb")?;
+//! old_buffer.set(&["age"], 30u8)?;
+//! old_buffer.set(&["legacy"], false)?;
+//!
+//! let new_buffer = migration.migrate(&old_buffer)?;
+//! assert_eq!(new_buffer.get::<&str>(&["first_name"])?, Some("Jeb"));
+//! assert_eq!(new_buffer.get::<u32>(&["age"])?, Some(30));
+//! assert_eq!(new_buffer.get::<bool>(&["active"])?, Some(true));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::owned::NP_Value_Owned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::borrow::ToOwned;
+
+type NP_Converter = Box<dyn Fn(NP_Value_Owned) -> Result<NP_Value_Owned, NP_Error>>;
+
+enum NP_Migration_Step {
+    Remove { path: Vec<String> },
+    Rename { path: Vec<String>, name: String },
+    Convert { path: Vec<String>, converter: NP_Converter },
+    Default { path: Vec<String>, value: NP_Value_Owned }
+}
+
+impl NP_Migration_Step {
+    fn order(&self) -> u8 {
+        match self {
+            NP_Migration_Step::Remove { .. } | NP_Migration_Step::Rename { .. } => 0,
+            NP_Migration_Step::Convert { .. } => 1,
+            NP_Migration_Step::Default { .. } => 2
+        }
+    }
+}
+
+/// Upgrades buffers into a new schema
+///
+/// Check out documentation [here](../migration/index.html).
+///
+pub struct NP_Migration<'factory> {
+    factory: &'factory NP_Factory,
+    steps: Vec<NP_Migration_Step>
+}
+
+fn to_path(path: &[&str]) -> Vec<String> {
+    path.iter().map(|x| (*x).to_owned()).collect()
+}
+
+/// Call `func` on every value matching `path`, the last path segment is passed in with the collection that holds it
+fn visit<F>(value: &mut NP_Value_Owned, path: &[String], func: &mut F) -> Result<(), NP_Error> where F: FnMut(&mut NP_Value_Owned, &str) -> Result<(), NP_Error> {
+
+    if path.len() == 1 {
+        return func(value, &path[0]);
+    }
+
+    let key = &path[0];
+
+    match value {
+        NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) => {
+            for (name, child) in entries.iter_mut() {
+                if key == "*" || name == key {
+                    visit(child, &path[1..], func)?;
+                }
+            }
+        },
+        NP_Value_Owned::List(items) | NP_Value_Owned::Tuple(items) => {
+            for (index, child) in items.iter_mut().enumerate() {
+                if key == "*" || key.parse::<usize>().ok() == Some(index) {
+                    visit(child, &path[1..], func)?;
+                }
+            }
+        },
+        _ => { }
+    }
+
+    Ok(())
+}
+
+/// Get the entries of a table or map, empty tables are created at `Null` values
+fn entries(value: &mut NP_Value_Owned) -> Option<&mut Vec<(String, NP_Value_Owned)>> {
+    if *value == NP_Value_Owned::Null {
+        *value = NP_Value_Owned::Table(Vec::new());
+    }
+    match value {
+        NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) => Some(entries),
+        _ => None
+    }
+}
+
+impl<'factory> NP_Migration<'factory> {
+
+    /// Start a new migration into the schema of the provided factory
+    pub fn new(factory: &'factory NP_Factory) -> Self {
+        NP_Migration { factory, steps: Vec::new() }
+    }
+
+    /// Drop the value at `path` in the old schema, use this for columns that aren't in the new schema
+    pub fn remove(mut self, path: &[&str]) -> Self {
+        self.steps.push(NP_Migration_Step::Remove { path: to_path(path) });
+        self
+    }
+
+    /// Rename the column at `path` in the old schema to `name`
+    pub fn rename(mut self, path: &[&str], name: &str) -> Self {
+        self.steps.push(NP_Migration_Step::Rename { path: to_path(path), name: name.to_owned() });
+        self
+    }
+
+    /// Replace every value that's been set at `path` in the new schema with the value returned by `converter`
+    pub fn convert<F>(mut self, path: &[&str], converter: F) -> Self where F: Fn(NP_Value_Owned) -> Result<NP_Value_Owned, NP_Error> + 'static {
+        self.steps.push(NP_Migration_Step::Convert { path: to_path(path), converter: Box::new(converter) });
+        self
+    }
+
+    /// Set `value` at `path` in the new schema if nothing has been set there
+    pub fn set_default(mut self, path: &[&str], value: NP_Value_Owned) -> Self {
+        self.steps.push(NP_Migration_Step::Default { path: to_path(path), value });
+        self
+    }
+
+    /// Apply every step to the old buffer and write the result into a new buffer
+    pub fn migrate(&self, old_buffer: &NP_Buffer) -> Result<NP_Buffer<'factory>, NP_Error> {
+
+        let mut tree = old_buffer.root_owned_tree()?;
+
+        let mut steps: Vec<&NP_Migration_Step> = self.steps.iter().collect();
+        steps.sort_by_key(|step| step.order());
+
+        for step in steps {
+            match step {
+                NP_Migration_Step::Remove { path } | NP_Migration_Step::Rename { path, .. } | NP_Migration_Step::Convert { path, .. } | NP_Migration_Step::Default { path, .. } if path.is_empty() => {
+                    return Err(NP_Error::Schema("Migration steps need a path!".into()));
+                },
+                NP_Migration_Step::Remove { path } => {
+                    visit(&mut tree, path, &mut |parent, key| {
+                        if let NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) = parent {
+                            entries.retain(|(name, _value)| name != key);
+                        }
+                        Ok(())
+                    })?;
+                },
+                NP_Migration_Step::Rename { path, name } => {
+                    visit(&mut tree, path, &mut |parent, key| {
+                        if let NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) = parent {
+                            for entry in entries.iter_mut() {
+                                if entry.0 == key {
+                                    entry.0 = name.clone();
+                                }
+                            }
+                        }
+                        Ok(())
+                    })?;
+                },
+                NP_Migration_Step::Convert { path, converter } => {
+                    visit(&mut tree, path, &mut |parent, key| {
+                        let convert = |value: &mut NP_Value_Owned| -> Result<(), NP_Error> {
+                            if *value != NP_Value_Owned::Null {
+                                let old = core::mem::replace(value, NP_Value_Owned::Null);
+                                *value = converter(old)?;
+                            }
+                            Ok(())
+                        };
+                        match parent {
+                            NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) => {
+                                for (name, value) in entries.iter_mut() {
+                                    if key == "*" || name == key {
+                                        convert(value)?;
+                                    }
+                                }
+                            },
+                            NP_Value_Owned::List(items) | NP_Value_Owned::Tuple(items) => {
+                                for (index, value) in items.iter_mut().enumerate() {
+                                    if key == "*" || key.parse::<usize>().ok() == Some(index) {
+                                        convert(value)?;
+                                    }
+                                }
+                            },
+                            _ => { }
+                        }
+                        Ok(())
+                    })?;
+                },
+                NP_Migration_Step::Default { path, value } => {
+                    if path.iter().any(|key| key == "*") {
+                        // only existing items get defaults
+                        visit(&mut tree, path, &mut |parent, key| {
+                            if let NP_Value_Owned::Table(entries) = parent {
+                                if !entries.iter().any(|(name, current)| name == key && *current != NP_Value_Owned::Null) {
+                                    entries.retain(|(name, _current)| name != key);
+                                    entries.push((key.to_owned(), value.clone()));
+                                }
+                            }
+                            Ok(())
+                        })?;
+                    } else {
+                        // create the parent tables on the way down
+                        let mut parent = &mut tree;
+                        for key in &path[..(path.len() - 1)] {
+                            let entries = match entries(parent) {
+                                Some(x) => x,
+                                None => return Err(NP_Error::BadPath("Migration default path goes through a value that isn't a table!".into()))
+                            };
+                            let index = match entries.iter().position(|(name, _value)| name == key) {
+                                Some(x) => x,
+                                None => {
+                                    entries.push((key.clone(), NP_Value_Owned::Null));
+                                    entries.len() - 1
+                                }
+                            };
+                            parent = &mut entries[index].1;
+                        }
+                        let key = &path[path.len() - 1];
+                        let entries = match entries(parent) {
+                            Some(x) => x,
+                            None => return Err(NP_Error::BadPath("Migration default path goes through a value that isn't a table!".into()))
+                        };
+                        if !entries.iter().any(|(name, current)| name == key && *current != NP_Value_Owned::Null) {
+                            entries.retain(|(name, _current)| name != key);
+                            entries.push((key.clone(), value.clone()));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut new_buffer = self.factory.empty_buffer(None);
+        new_buffer.from_owned_tree(&tree)?;
+        Ok(new_buffer)
+    }
+}
+
+#[test]
+fn migration_lists_work() -> Result<(), NP_Error> {
+    let v1 = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["users", {"type": "list", "of": {"type": "table", "columns": [["name", {"type": "string"}], ["score", {"type": "u8"}]]}}]
+    ]}"#)?;
+    let v2 = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["users", {"type": "list", "of": {"type": "table", "columns": [["nick", {"type": "string"}], ["score", {"type": "i64"}], ["level", {"type": "u8"}]]}}],
+        ["meta", {"type": "table", "columns": [["version", {"type": "u8"}]]}]
+    ]}"#)?;
+
+    let migration = NP_Migration::new(&v2)
+        .rename(&["users", "*", "name"], "nick")
+        .convert(&["users", "*", "score"], |value| match value {
+            NP_Value_Owned::Uint8(x) => Ok(NP_Value_Owned::Int64(x as i64 * 10)),
+            _ => Err(NP_Error::InvalidValue("bad score".into()))
+        })
+        .set_default(&["users", "*", "level"], NP_Value_Owned::Uint8(1))
+        .set_default(&["meta", "version"], NP_Value_Owned::Uint8(2));
+
+    let mut old = v1.empty_buffer(None);
+    old.set(&["users", "0", "name"], "a")?;
+    old.set(&["users", "0", "score"], 5u8)?;
+    old.set(&["users", "2", "name"], "c")?;
+
+    let new = migration.migrate(&old)?;
+    assert_eq!(new.json_encode(&[])?.stringify(), r#"{"users":[{"nick":"a","score":50,"level":1},null,{"nick":"c","score":null,"level":1}],"meta":{"version":2}}"#);
+
+    // values that don't fit the new schema fail
+    assert!(NP_Migration::new(&v2).migrate(&old).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,567 @@
+//! MessagePack import and export
+//!
+//! With the `msgpack` feature enabled, buffers can be converted to and from [MessagePack](https://msgpack.org/) using the schema to pick the right types.
+//!
+//! Tables and maps become msgpack maps keyed by column name or map key, lists and tuples become arrays and empty values become `nil`.  Only values that have been set in the buffer are exported, default values from the schema are left out.
+//!
+//! Types that msgpack doesn't have are converted like this:
+//!
+//! | NoProto type | MessagePack type                                             |
+//! |--------------|--------------------------------------------------------------|
+//! | `decimal`    | float64 (integers and floats are accepted when importing)   |
+//! | `geo`        | map with `lat` and `lng` float64 values                     |
+//! | `uuid`       | 16 byte bin (strings are accepted when importing)           |
+//! | `ulid`       | 16 byte bin                                                  |
+//! | `date`       | timestamp extension (unsigned milliseconds are accepted when importing) |
+//! | `option`     | string                                                       |
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "uint8"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//! new_buffer.set(&["age"], 30u8)?;
+//!
+//! let bytes: Vec<u8> = new_buffer.msgpack_encode()?;
+//! assert_eq!(bytes, vec![0x82, 0xa4, b'n', b'a', b'm', b'e', 0xa3, b'J', b'e', b'b', 0xa3, b'a', b'g', b'e', 30]);
+//!
+//! let copy_buffer = factory.from_msgpack(&bytes)?;
+//! assert_eq!(copy_buffer.get::<&str>(&["name"])?, Some("Jeb"));
+//! assert_eq!(copy_buffer.get::<u8>(&["age"])?, Some(30));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::owned::NP_Value_Owned;
+use crate::schema::NP_Parsed_Schema;
+use crate::pointer::dec::NP_Dec;
+use crate::pointer::geo::NP_Geo;
+use crate::pointer::date::NP_Date;
+use crate::pointer::uuid::NP_UUID;
+use crate::pointer::ulid::NP_ULID;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// msgpack extension type for timestamps
+const TIMESTAMP_EXT: i8 = -1;
+
+/// A msgpack value before it's been matched to the schema
+enum MP_Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    Str(String),
+    Bin(Vec<u8>),
+    Array(Vec<MP_Value>),
+    Map(Vec<(MP_Value, MP_Value)>),
+    Ext(i8, Vec<u8>)
+}
+
+fn write_len(out: &mut Vec<u8>, len: usize, fix: Option<(u8, usize)>, codes: [u8; 3]) {
+    match fix {
+        Some((code, max)) if len < max => out.push(code | len as u8),
+        _ => {
+            if len <= u8::MAX as usize && codes[0] != 0 {
+                out.push(codes[0]);
+                out.push(len as u8);
+            } else if len <= u16::MAX as usize {
+                out.push(codes[1]);
+                out.extend_from_slice(&(len as u16).to_be_bytes());
+            } else {
+                out.push(codes[2]);
+                out.extend_from_slice(&(len as u32).to_be_bytes());
+            }
+        }
+    }
+}
+
+fn write_uint(out: &mut Vec<u8>, value: u64) {
+    if value < 128 {
+        out.push(value as u8);
+    } else if value <= u8::MAX as u64 {
+        out.push(0xcc);
+        out.push(value as u8);
+    } else if value <= u16::MAX as u64 {
+        out.push(0xcd);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        out.push(0xce);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(0xcf);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_int(out: &mut Vec<u8>, value: i64) {
+    if value >= 0 {
+        write_uint(out, value as u64);
+    } else if value >= -32 {
+        out.push(value as i8 as u8);
+    } else if value >= i8::MIN as i64 {
+        out.push(0xd0);
+        out.push(value as i8 as u8);
+    } else if value >= i16::MIN as i64 {
+        out.push(0xd1);
+        out.extend_from_slice(&(value as i16).to_be_bytes());
+    } else if value >= i32::MIN as i64 {
+        out.push(0xd2);
+        out.extend_from_slice(&(value as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_str(out: &mut Vec<u8>, value: &str) {
+    write_len(out, value.len(), Some((0xa0, 32)), [0xd9, 0xda, 0xdb]);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_bin(out: &mut Vec<u8>, value: &[u8]) {
+    write_len(out, value.len(), None, [0xc4, 0xc5, 0xc6]);
+    out.extend_from_slice(value);
+}
+
+fn write_f64(out: &mut Vec<u8>, value: f64) {
+    out.push(0xcb);
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn write_date(out: &mut Vec<u8>, date: &NP_Date) {
+    let seconds = date.value / 1000;
+    let nanoseconds = (date.value % 1000) * 1_000_000;
+    if nanoseconds == 0 && seconds <= u32::MAX as u64 {
+        out.push(0xd6);
+        out.push(TIMESTAMP_EXT as u8);
+        out.extend_from_slice(&(seconds as u32).to_be_bytes());
+    } else if seconds < (1u64 << 34) {
+        out.push(0xd7);
+        out.push(TIMESTAMP_EXT as u8);
+        out.extend_from_slice(&((nanoseconds << 34) | seconds).to_be_bytes());
+    } else {
+        out.push(0xc7);
+        out.push(12);
+        out.push(TIMESTAMP_EXT as u8);
+        out.extend_from_slice(&(nanoseconds as u32).to_be_bytes());
+        out.extend_from_slice(&(seconds as i64).to_be_bytes());
+    }
+}
+
+fn write_entries(out: &mut Vec<u8>, entries: &Vec<(String, NP_Value_Owned)>) {
+    write_len(out, entries.len(), Some((0x80, 16)), [0, 0xde, 0xdf]);
+    for (key, value) in entries {
+        write_str(out, key);
+        encode(value, out);
+    }
+}
+
+/// Write an owned value tree as msgpack
+pub(crate) fn encode(value: &NP_Value_Owned, out: &mut Vec<u8>) {
+    match value {
+        NP_Value_Owned::Null => out.push(0xc0),
+        NP_Value_Owned::String(x) => write_str(out, x),
+        NP_Value_Owned::Enum(x) => write_str(out, x),
+        NP_Value_Owned::Bytes(x) => write_bin(out, x),
+        NP_Value_Owned::Int8(x) => write_int(out, *x as i64),
+        NP_Value_Owned::Int16(x) => write_int(out, *x as i64),
+        NP_Value_Owned::Int32(x) => write_int(out, *x as i64),
+        NP_Value_Owned::Int64(x) => write_int(out, *x),
+        NP_Value_Owned::Uint8(x) => write_uint(out, *x as u64),
+        NP_Value_Owned::Uint16(x) => write_uint(out, *x as u64),
+        NP_Value_Owned::Uint32(x) => write_uint(out, *x as u64),
+        NP_Value_Owned::Uint64(x) => write_uint(out, *x),
+        NP_Value_Owned::Float(x) => {
+            out.push(0xca);
+            out.extend_from_slice(&x.to_be_bytes());
+        },
+        NP_Value_Owned::Double(x) => write_f64(out, *x),
+        NP_Value_Owned::Dec(x) => write_f64(out, x.to_float()),
+        NP_Value_Owned::Bool(x) => out.push(if *x { 0xc3 } else { 0xc2 }),
+        NP_Value_Owned::Geo(x) => {
+            out.push(0x82);
+            write_str(out, "lat");
+            write_f64(out, x.lat);
+            write_str(out, "lng");
+            write_f64(out, x.lng);
+        },
+        NP_Value_Owned::Uuid(x) => write_bin(out, &x.value),
+        NP_Value_Owned::Ulid(x) => write_bin(out, x.as_bytes()),
+        NP_Value_Owned::Date(x) => write_date(out, x),
+        NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) => write_entries(out, entries),
+        NP_Value_Owned::List(items) | NP_Value_Owned::Tuple(items) => {
+            write_len(out, items.len(), Some((0x90, 16)), [0, 0xdc, 0xdd]);
+            for item in items {
+                encode(item, out);
+            }
+        }
+    }
+}
+
+struct MP_Reader<'bytes> {
+    bytes: &'bytes [u8],
+    index: usize
+}
+
+impl<'bytes> MP_Reader<'bytes> {
+
+    fn take(&mut self, len: usize) -> Result<&'bytes [u8], NP_Error> {
+        if self.index + len > self.bytes.len() {
+            return Err(NP_Error::Parse("MessagePack data ended unexpectedly!".into()));
+        }
+        let slice = &self.bytes[self.index..(self.index + len)];
+        self.index += len;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, NP_Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, NP_Error> {
+        let mut bytes = [0u8; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(u16::from_be_bytes(bytes))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, NP_Error> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, NP_Error> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn read_str(&mut self, len: usize) -> Result<MP_Value, NP_Error> {
+        Ok(MP_Value::Str(String::from(core::str::from_utf8(self.take(len)?)?)))
+    }
+
+    fn read_array(&mut self, len: usize) -> Result<MP_Value, NP_Error> {
+        let mut items = Vec::new();
+        for _x in 0..len {
+            items.push(self.read_value()?);
+        }
+        Ok(MP_Value::Array(items))
+    }
+
+    fn read_map(&mut self, len: usize) -> Result<MP_Value, NP_Error> {
+        let mut entries = Vec::new();
+        for _x in 0..len {
+            let key = self.read_value()?;
+            entries.push((key, self.read_value()?));
+        }
+        Ok(MP_Value::Map(entries))
+    }
+
+    fn read_ext(&mut self, len: usize) -> Result<MP_Value, NP_Error> {
+        let ext_type = self.read_u8()? as i8;
+        Ok(MP_Value::Ext(ext_type, self.take(len)?.to_vec()))
+    }
+
+    fn read_value(&mut self) -> Result<MP_Value, NP_Error> {
+        let code = self.read_u8()?;
+        match code {
+            0x00..=0x7f => Ok(MP_Value::Uint(code as u64)),
+            0x80..=0x8f => self.read_map((code & 0x0f) as usize),
+            0x90..=0x9f => self.read_array((code & 0x0f) as usize),
+            0xa0..=0xbf => self.read_str((code & 0x1f) as usize),
+            0xc0 => Ok(MP_Value::Nil),
+            0xc2 => Ok(MP_Value::Bool(false)),
+            0xc3 => Ok(MP_Value::Bool(true)),
+            0xc4 => { let len = self.read_u8()? as usize; Ok(MP_Value::Bin(self.take(len)?.to_vec())) },
+            0xc5 => { let len = self.read_u16()? as usize; Ok(MP_Value::Bin(self.take(len)?.to_vec())) },
+            0xc6 => { let len = self.read_u32()? as usize; Ok(MP_Value::Bin(self.take(len)?.to_vec())) },
+            0xc7 => { let len = self.read_u8()? as usize; self.read_ext(len) },
+            0xc8 => { let len = self.read_u16()? as usize; self.read_ext(len) },
+            0xc9 => { let len = self.read_u32()? as usize; self.read_ext(len) },
+            0xca => Ok(MP_Value::Float(f32::from_bits(self.read_u32()?) as f64)),
+            0xcb => Ok(MP_Value::Float(f64::from_bits(self.read_u64()?))),
+            0xcc => Ok(MP_Value::Uint(self.read_u8()? as u64)),
+            0xcd => Ok(MP_Value::Uint(self.read_u16()? as u64)),
+            0xce => Ok(MP_Value::Uint(self.read_u32()? as u64)),
+            0xcf => Ok(MP_Value::Uint(self.read_u64()?)),
+            0xd0 => Ok(MP_Value::Int(self.read_u8()? as i8 as i64)),
+            0xd1 => Ok(MP_Value::Int(self.read_u16()? as i16 as i64)),
+            0xd2 => Ok(MP_Value::Int(self.read_u32()? as i32 as i64)),
+            0xd3 => Ok(MP_Value::Int(self.read_u64()? as i64)),
+            0xd4 => self.read_ext(1),
+            0xd5 => self.read_ext(2),
+            0xd6 => self.read_ext(4),
+            0xd7 => self.read_ext(8),
+            0xd8 => self.read_ext(16),
+            0xd9 => { let len = self.read_u8()? as usize; self.read_str(len) },
+            0xda => { let len = self.read_u16()? as usize; self.read_str(len) },
+            0xdb => { let len = self.read_u32()? as usize; self.read_str(len) },
+            0xdc => { let len = self.read_u16()? as usize; self.read_array(len) },
+            0xdd => { let len = self.read_u32()? as usize; self.read_array(len) },
+            0xde => { let len = self.read_u16()? as usize; self.read_map(len) },
+            0xdf => { let len = self.read_u32()? as usize; self.read_map(len) },
+            0xe0..=0xff => Ok(MP_Value::Int(code as i8 as i64)),
+            _ => Err(NP_Error::Parse("Invalid MessagePack data!".into()))
+        }
+    }
+}
+
+fn timestamp_ms(data: &[u8]) -> Option<u64> {
+    let (seconds, nanoseconds) = match data.len() {
+        4 => (u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as i64, 0u64),
+        8 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(data);
+            let value = u64::from_be_bytes(bytes);
+            ((value & ((1u64 << 34) - 1)) as i64, value >> 34)
+        },
+        12 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&data[4..]);
+            (i64::from_be_bytes(bytes), u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as u64)
+        },
+        _ => return None
+    };
+    if seconds < 0 {
+        return None;
+    }
+    (seconds as u64).checked_mul(1000).and_then(|ms| ms.checked_add(nanoseconds / 1_000_000))
+}
+
+fn type_error(schema: &NP_Parsed_Schema) -> NP_Error {
+    let mut err = String::from("TypeError: MessagePack value doesn't match schema of type (");
+    err.push_str(schema.get_type_data().0);
+    err.push_str(")");
+    NP_Error::SchemaMismatch(err)
+}
+
+/// Convert a msgpack value into an owned value tree using the schema at `address`
+fn to_owned(value: MP_Value, schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<NP_Value_Owned, NP_Error> {
+
+    if let MP_Value::Nil = value {
+        return Ok(NP_Value_Owned::Null);
+    }
+
+    let this_schema = &schema[address];
+
+    macro_rules! int {
+        ($t: ty, $variant: ident) => {
+            match value {
+                MP_Value::Int(x) => <$t>::try_from(x).ok(),
+                MP_Value::Uint(x) => <$t>::try_from(x).ok(),
+                _ => None
+            }.map(NP_Value_Owned::$variant).ok_or_else(|| type_error(this_schema))
+        }
+    }
+
+    let number = |value: &MP_Value| match value {
+        MP_Value::Int(x) => Some(*x as f64),
+        MP_Value::Uint(x) => Some(*x as f64),
+        MP_Value::Float(x) => Some(*x),
+        _ => None
+    };
+
+    let string_key = |key: MP_Value| match key {
+        MP_Value::Str(x) => Ok(x),
+        _ => Err(NP_Error::Parse("MessagePack map keys must be strings!".into()))
+    };
+
+    match this_schema {
+        NP_Parsed_Schema::UTF8String { .. } => match value {
+            MP_Value::Str(x) => Ok(NP_Value_Owned::String(x)),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Bytes { .. } => match value {
+            MP_Value::Bin(x) => Ok(NP_Value_Owned::Bytes(x)),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Int8 { .. } => int!(i8, Int8),
+        NP_Parsed_Schema::Int16 { .. } => int!(i16, Int16),
+        NP_Parsed_Schema::Int32 { .. } => int!(i32, Int32),
+        NP_Parsed_Schema::Int64 { .. } => int!(i64, Int64),
+        NP_Parsed_Schema::Uint8 { .. } => int!(u8, Uint8),
+        NP_Parsed_Schema::Uint16 { .. } => int!(u16, Uint16),
+        NP_Parsed_Schema::Uint32 { .. } => int!(u32, Uint32),
+        NP_Parsed_Schema::Uint64 { .. } => int!(u64, Uint64),
+        NP_Parsed_Schema::Float { .. } => number(&value).map(|x| NP_Value_Owned::Float(x as f32)).ok_or_else(|| type_error(this_schema)),
+        NP_Parsed_Schema::Double { .. } => number(&value).map(NP_Value_Owned::Double).ok_or_else(|| type_error(this_schema)),
+        NP_Parsed_Schema::Decimal { exp, .. } => {
+            let mut dec: NP_Dec = match value {
+                MP_Value::Int(x) => NP_Dec::new(x, 0),
+                MP_Value::Uint(x) => NP_Dec::new(i64::try_from(x).map_err(|_| type_error(this_schema))?, 0),
+                MP_Value::Float(x) => x.into(),
+                _ => return Err(type_error(this_schema))
+            };
+            dec.shift_exp(*exp);
+            Ok(NP_Value_Owned::Dec(dec))
+        },
+        NP_Parsed_Schema::Boolean { .. } => match value {
+            MP_Value::Bool(x) => Ok(NP_Value_Owned::Bool(x)),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Geo { size, .. } => {
+            let (mut lat, mut lng) = (None, None);
+            match value {
+                MP_Value::Map(entries) => {
+                    for (key, value) in entries {
+                        match key {
+                            MP_Value::Str(ref k) if k == "lat" => lat = number(&value),
+                            MP_Value::Str(ref k) if k == "lng" => lng = number(&value),
+                            _ => { }
+                        }
+                    }
+                },
+                MP_Value::Array(items) if items.len() == 2 => {
+                    lat = number(&items[0]);
+                    lng = number(&items[1]);
+                },
+                _ => { }
+            }
+            match (lat, lng) {
+                (Some(lat), Some(lng)) => Ok(NP_Value_Owned::Geo(NP_Geo::new(*size, lat, lng))),
+                _ => Err(type_error(this_schema))
+            }
+        },
+        NP_Parsed_Schema::Uuid { .. } => match value {
+            MP_Value::Bin(x) if x.len() == 16 => {
+                let mut uuid = NP_UUID { value: [0; 16] };
+                uuid.value.copy_from_slice(&x);
+                Ok(NP_Value_Owned::Uuid(uuid))
+            },
+            MP_Value::Str(x) => Ok(NP_Value_Owned::Uuid(NP_UUID::from_string(&x)?)),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Ulid { .. } => match value {
+            MP_Value::Bin(x) if x.len() == 16 => {
+                let mut bytes = [0u8; 16];
+                bytes.copy_from_slice(&x);
+                Ok(NP_Value_Owned::Ulid(NP_ULID::from_bytes(bytes)))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Date { .. } => match value {
+            MP_Value::Ext(TIMESTAMP_EXT, data) => timestamp_ms(&data).map(|ms| NP_Value_Owned::Date(NP_Date::new(ms))).ok_or_else(|| type_error(this_schema)),
+            MP_Value::Uint(x) => Ok(NP_Value_Owned::Date(NP_Date::new(x))),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Enum { .. } => match value {
+            MP_Value::Str(x) => Ok(NP_Value_Owned::Enum(x)),
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Table { columns, .. } => match value {
+            MP_Value::Map(entries) => {
+                let mut values = Vec::new();
+                for (key, value) in entries {
+                    let key = string_key(key)?;
+                    let column = match columns.iter().find(|(_index, name, _addr)| *name == key) {
+                        Some((_index, _name, addr)) => *addr,
+                        None => {
+                            let mut err = String::from("MessagePack key not found in table schema: ");
+                            err.push_str(&key);
+                            return Err(NP_Error::Parse(err));
+                        }
+                    };
+                    values.push((key, to_owned(value, schema, column)?));
+                }
+                Ok(NP_Value_Owned::Table(values))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Map { value: of, .. } => match value {
+            MP_Value::Map(entries) => {
+                let mut values = Vec::new();
+                for (key, value) in entries {
+                    values.push((string_key(key)?, to_owned(value, schema, *of)?));
+                }
+                Ok(NP_Value_Owned::Map(values))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::List { of, .. } => match value {
+            MP_Value::Array(items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(to_owned(item, schema, *of)?);
+                }
+                Ok(NP_Value_Owned::List(values))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Tuple { values: addrs, .. } => match value {
+            MP_Value::Array(items) if items.len() <= addrs.len() => {
+                let mut values = Vec::new();
+                for (item, addr) in items.into_iter().zip(addrs.iter()) {
+                    values.push(to_owned(item, schema, *addr)?);
+                }
+                Ok(NP_Value_Owned::Tuple(values))
+            },
+            _ => Err(type_error(this_schema))
+        },
+        NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::None => Err(type_error(this_schema))
+    }
+}
+
+/// Read msgpack bytes into an owned value tree using the schema at `address`
+pub(crate) fn decode(bytes: &[u8], schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<NP_Value_Owned, NP_Error> {
+    let mut reader = MP_Reader { bytes, index: 0 };
+    let value = reader.read_value()?;
+    if reader.index != bytes.len() {
+        return Err(NP_Error::Parse("Extra bytes found after MessagePack value!".into()));
+    }
+    to_owned(value, schema, address)
+}
+
+#[test]
+fn msgpack_round_trip_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id",     {"type": "uuid"}],
+            ["when",   {"type": "date"}],
+            ["where",  {"type": "geo8"}],
+            ["price",  {"type": "dec", "exp": 2}],
+            ["small",  {"type": "i8"}],
+            ["big",    {"type": "u64"}],
+            ["pair",   {"type": "tuple", "values": [{"type": "string"}, {"type": "bool"}]}],
+            ["scores", {"type": "list", "of": {"type": "float"}}],
+            ["meta",   {"type": "map", "value": {"type": "bytes"}}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["id"], &NP_UUID::generate(22))?;
+    buffer.set(&["when"], NP_Date::new(1605138980392))?;
+    buffer.set(&["where"], NP_Geo::new(8, 45.5, -120.25))?;
+    buffer.set(&["price"], NP_Dec::new(2050, 2))?;
+    buffer.set(&["small"], -100i8)?;
+    buffer.set(&["big"], u64::MAX)?;
+    buffer.set(&["pair", "1"], true)?;
+    buffer.set(&["scores", "2"], 2.5f32)?;
+    buffer.set(&["meta", "key"], &[1u8, 2, 3] as &[u8])?;
+
+    let bytes = buffer.msgpack_encode()?;
+    let copy = factory.from_msgpack(&bytes)?;
+    assert_eq!(copy.to_owned_tree()?, buffer.to_owned_tree()?);
+
+    // values that don't fit the schema are rejected
+    assert!(factory.from_msgpack(&[0x81, 0xa5, b's', b'm', b'a', b'l', b'l', 0xcc, 200]).is_err());
+    assert!(factory.from_msgpack(&[0x81, 0xa3, b'b', b'a', b'd', 0x01]).is_err());
+    assert!(factory.from_msgpack(&[0x81, 0xa5, b's', b'm', b'a', b'l', b'l']).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,394 @@
+//! Owned copies of buffer values
+//!
+//! `NP_Value_Owned` holds a copy of a buffer value and all of it's children in plain Rust data structures.  Unlike `NP_JSON`, every NoProto type keeps it's own variant, so uuids, decimals, geo coordinates and dates go through without being converted to strings or floats.
+//!
+//! Use `to_owned_tree` to copy a buffer into a tree and `from_owned_tree` to write a tree back into a buffer.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::owned::NP_Value_Owned;
+//! use no_proto::pointer::dec::NP_Dec;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",  {"type": "string"}],
+//!         ["price", {"type": "dec", "exp": 2}],
+//!         ["tags",  {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "hello")?;
+//! new_buffer.set(&["price"], NP_Dec::new(2050, 2))?;
+//! new_buffer.set(&["tags", "1"], "world")?;
+//!
+//! let tree = new_buffer.to_owned_tree()?;
+//! assert_eq!(tree, NP_Value_Owned::Table(vec![
+//!     (String::from("name"), NP_Value_Owned::String(String::from("hello"))),
+//!     (String::from("price"), NP_Value_Owned::Dec(NP_Dec::new(2050, 2))),
+//!     (String::from("tags"), NP_Value_Owned::List(vec![
+//!         NP_Value_Owned::Null,
+//!         NP_Value_Owned::String(String::from("world"))
+//!     ]))
+//! ]));
+//!
+//! let mut copy_buffer = factory.empty_buffer(None);
+//! copy_buffer.from_owned_tree(&tree)?;
+//! assert_eq!(copy_buffer.get::<&str>(&["tags", "1"])?, Some("world"));
+//! assert_eq!(copy_buffer.to_owned_tree()?, tree);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::NP_Buffer;
+use crate::collection::{table::NP_Table, tuple::NP_Tuple, list::NP_List, map::NP_Map};
+use crate::error::NP_Error;
+use crate::memory::NP_Memory;
+use crate::pointer::{NP_Cursor, NP_Value};
+use crate::pointer::dec::NP_Dec;
+use crate::pointer::geo::NP_Geo;
+use crate::pointer::date::NP_Date;
+use crate::pointer::option::NP_Enum;
+use crate::pointer::uuid::NP_UUID;
+use crate::pointer::ulid::NP_ULID;
+use crate::schema::NP_Parsed_Schema;
+use crate::json_flex::{JSMAP, NP_JSON};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::borrow::ToOwned;
+
+/// An owned copy of a buffer value
+///
+/// Check out documentation [here](../owned/index.html).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum NP_Value_Owned {
+    /// No value
+    Null,
+    /// string type
+    String(String),
+    /// bytes type
+    Bytes(Vec<u8>),
+    /// int8 type
+    Int8(i8),
+    /// int16 type
+    Int16(i16),
+    /// int32 type
+    Int32(i32),
+    /// int64 type
+    Int64(i64),
+    /// uint8 type
+    Uint8(u8),
+    /// uint16 type
+    Uint16(u16),
+    /// uint32 type
+    Uint32(u32),
+    /// uint64 type
+    Uint64(u64),
+    /// float type
+    Float(f32),
+    /// double type
+    Double(f64),
+    /// decimal type
+    Dec(NP_Dec),
+    /// bool type
+    Bool(bool),
+    /// geo type
+    Geo(NP_Geo),
+    /// uuid type
+    Uuid(NP_UUID),
+    /// ulid type
+    Ulid(NP_ULID),
+    /// date type
+    Date(NP_Date),
+    /// option type
+    Enum(String),
+    /// table type, only columns with values are included
+    Table(Vec<(String, NP_Value_Owned)>),
+    /// map type
+    Map(Vec<(String, NP_Value_Owned)>),
+    /// list type, empty list items are `Null`
+    List(Vec<NP_Value_Owned>),
+    /// tuple type, empty tuple values are `Null`
+    Tuple(Vec<NP_Value_Owned>)
+}
+
+impl NP_Value_Owned {
+
+    /// Copy the value at this cursor and all of it's children
+    pub(crate) fn from_cursor(cursor: &NP_Cursor, memory: &NP_Memory) -> Result<Self, NP_Error> {
+        Self::copy(cursor, memory, false)
+    }
+
+    /// Copy the value at this cursor and all of it's children, collections keep values set with `set_null` as `Null` entries
+    pub(crate) fn from_cursor_with_nulls(cursor: &NP_Cursor, memory: &NP_Memory) -> Result<Self, NP_Error> {
+        Self::copy(cursor, memory, true)
+    }
+
+    fn copy(cursor: &NP_Cursor, memory: &NP_Memory, nulls: bool) -> Result<Self, NP_Error> {
+
+        if cursor.get_value(memory).get_addr_value() == 0 {
+            return Ok(NP_Value_Owned::Null);
+        }
+
+        macro_rules! scalar {
+            ($t: ty, $variant: ident) => {
+                match <$t>::into_value(cursor, memory)? {
+                    Some(x) => NP_Value_Owned::$variant(x),
+                    None => NP_Value_Owned::Null
+                }
+            }
+        }
+
+        Ok(match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Table { .. } => {
+                let mut columns: Vec<(String, NP_Value_Owned)> = Vec::new();
+                let mut table_iter = NP_Table::new_iter(cursor, memory);
+                while let Some((_index, key, item)) = table_iter.step_iter(memory) {
+                    if let Some(child) = item {
+                        let value = Self::copy(&child, memory, nulls)?;
+                        if value != NP_Value_Owned::Null || Self::kept_null(&child, memory, nulls) {
+                            columns.push((key.to_owned(), value));
+                        }
+                    }
+                }
+                NP_Value_Owned::Table(columns)
+            },
+            NP_Parsed_Schema::Tuple { .. } => {
+                let mut values: Vec<NP_Value_Owned> = Vec::new();
+                let mut tuple_iter = NP_Tuple::new_iter(cursor, memory);
+                while let Some((_index, item)) = tuple_iter.step_iter(memory) {
+                    values.push(match item {
+                        Some(child) => Self::copy(&child, memory, nulls)?,
+                        None => NP_Value_Owned::Null
+                    });
+                }
+                NP_Value_Owned::Tuple(values)
+            },
+            NP_Parsed_Schema::List { .. } => {
+                let mut items: Vec<NP_Value_Owned> = Vec::new();
+                let mut list_iter = NP_List::new_iter(cursor, memory, true, 0);
+                while let Some((index, item)) = list_iter.step_iter(memory) {
+                    if let Some(child) = item {
+                        let value = Self::copy(&child, memory, nulls)?;
+                        if value != NP_Value_Owned::Null || Self::kept_null(&child, memory, nulls) {
+                            items.resize(index, NP_Value_Owned::Null);
+                            items.push(value);
+                        }
+                    }
+                }
+                NP_Value_Owned::List(items)
+            },
+            NP_Parsed_Schema::Map { .. } => {
+                let mut entries: Vec<(String, NP_Value_Owned)> = Vec::new();
+                let mut map_iter = NP_Map::new_iter(cursor, memory);
+                while let Some((key, child)) = map_iter.step_iter(memory) {
+                    let value = Self::copy(&child, memory, nulls)?;
+                    if value != NP_Value_Owned::Null || Self::kept_null(&child, memory, nulls) {
+                        entries.push((key.to_owned(), value));
+                    }
+                }
+                NP_Value_Owned::Map(entries)
+            },
+            NP_Parsed_Schema::UTF8String { .. } => {
+                match <&str>::into_value(cursor, memory)? {
+                    Some(x) => NP_Value_Owned::String(x.to_owned()),
+                    None => NP_Value_Owned::Null
+                }
+            },
+            NP_Parsed_Schema::Bytes { .. } => {
+                match <&[u8]>::into_value(cursor, memory)? {
+                    Some(x) => NP_Value_Owned::Bytes(x.to_vec()),
+                    None => NP_Value_Owned::Null
+                }
+            },
+            NP_Parsed_Schema::Int8 { .. } => scalar!(i8, Int8),
+            NP_Parsed_Schema::Int16 { .. } => scalar!(i16, Int16),
+            NP_Parsed_Schema::Int32 { .. } => scalar!(i32, Int32),
+            NP_Parsed_Schema::Int64 { .. } => scalar!(i64, Int64),
+            NP_Parsed_Schema::Uint8 { .. } => scalar!(u8, Uint8),
+            NP_Parsed_Schema::Uint16 { .. } => scalar!(u16, Uint16),
+            NP_Parsed_Schema::Uint32 { .. } => scalar!(u32, Uint32),
+            NP_Parsed_Schema::Uint64 { .. } => scalar!(u64, Uint64),
+            NP_Parsed_Schema::Float { .. } => scalar!(f32, Float),
+            NP_Parsed_Schema::Double { .. } => scalar!(f64, Double),
+            NP_Parsed_Schema::Decimal { .. } => scalar!(NP_Dec, Dec),
+            NP_Parsed_Schema::Boolean { .. } => scalar!(bool, Bool),
+            NP_Parsed_Schema::Geo { .. } => scalar!(NP_Geo, Geo),
+            NP_Parsed_Schema::Date { .. } => scalar!(NP_Date, Date),
+            NP_Parsed_Schema::Uuid { .. } => {
+                match <&NP_UUID>::into_value(cursor, memory)? {
+                    Some(x) => NP_Value_Owned::Uuid(x.clone()),
+                    None => NP_Value_Owned::Null
+                }
+            },
+            NP_Parsed_Schema::Ulid { .. } => {
+                match <&NP_ULID>::into_value(cursor, memory)? {
+                    Some(x) => NP_Value_Owned::Ulid(x.clone()),
+                    None => NP_Value_Owned::Null
+                }
+            },
+            NP_Parsed_Schema::Enum { .. } => {
+                match NP_Enum::into_value(cursor, memory)? {
+                    Some(NP_Enum::Some(x)) => NP_Value_Owned::Enum(x),
+                    _ => NP_Value_Owned::Null
+                }
+            },
+            NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::None => NP_Value_Owned::Null
+        })
+    }
+
+    fn kept_null(cursor: &NP_Cursor, memory: &NP_Memory, nulls: bool) -> bool {
+        nulls && cursor.get_value(memory).is_null()
+    }
+
+    /// Convert this value and all of it's children into JSON, using the same format as `json_encode`.
+    pub fn to_json(&self) -> NP_JSON {
+
+        let entries_json = |entries: &Vec<(String, NP_Value_Owned)>| {
+            let mut object = JSMAP::new();
+            for (key, value) in entries {
+                object.insert(key.clone(), value.to_json());
+            }
+            NP_JSON::Dictionary(object)
+        };
+
+        match self {
+            NP_Value_Owned::Null => NP_JSON::Null,
+            NP_Value_Owned::String(x) => NP_JSON::String(x.clone()),
+            NP_Value_Owned::Bytes(x) => NP_JSON::Array(x.iter().map(|b| NP_JSON::Integer(*b as i64)).collect()),
+            NP_Value_Owned::Int8(x) => NP_JSON::Integer(*x as i64),
+            NP_Value_Owned::Int16(x) => NP_JSON::Integer(*x as i64),
+            NP_Value_Owned::Int32(x) => NP_JSON::Integer(*x as i64),
+            NP_Value_Owned::Int64(x) => NP_JSON::Integer(*x),
+            NP_Value_Owned::Uint8(x) => NP_JSON::Integer(*x as i64),
+            NP_Value_Owned::Uint16(x) => NP_JSON::Integer(*x as i64),
+            NP_Value_Owned::Uint32(x) => NP_JSON::Integer(*x as i64),
+            NP_Value_Owned::Uint64(x) => NP_JSON::Integer(*x as i64),
+            NP_Value_Owned::Float(x) => NP_JSON::Float(*x as f64),
+            NP_Value_Owned::Double(x) => NP_JSON::Float(*x),
+            NP_Value_Owned::Dec(x) => {
+                let mut object = JSMAP::new();
+                object.insert("num".to_owned(), NP_JSON::Integer(x.num));
+                object.insert("exp".to_owned(), NP_JSON::Integer(x.exp as i64));
+                NP_JSON::Dictionary(object)
+            },
+            NP_Value_Owned::Bool(x) => if *x { NP_JSON::True } else { NP_JSON::False },
+            NP_Value_Owned::Geo(x) => x.into_json(),
+            NP_Value_Owned::Uuid(x) => NP_JSON::String(x.to_string()),
+            NP_Value_Owned::Ulid(x) => NP_JSON::String(x.to_string()),
+            NP_Value_Owned::Date(x) => NP_JSON::String(x.to_string()),
+            NP_Value_Owned::Enum(x) => NP_JSON::String(x.clone()),
+            NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) => entries_json(entries),
+            NP_Value_Owned::List(items) | NP_Value_Owned::Tuple(items) => NP_JSON::Array(items.iter().map(|x| x.to_json()).collect())
+        }
+    }
+
+    /// Write this value and all of it's children into the buffer at `path`
+    pub(crate) fn write_to(&self, buffer: &mut NP_Buffer, path: &mut Vec<String>) -> Result<(), NP_Error> {
+
+        macro_rules! set {
+            ($value: expr) => {{
+                let path_refs: Vec<&str> = path.iter().map(|x| x.as_str()).collect();
+                if !buffer.set(&path_refs, $value)? {
+                    let mut err = "Error writing owned value, path not found: ".to_owned();
+                    err.push_str(&path.join("."));
+                    return Err(NP_Error::BadPath(err));
+                }
+            }}
+        }
+
+        match self {
+            NP_Value_Owned::Null => {
+                let path_refs: Vec<&str> = path.iter().map(|x| x.as_str()).collect();
+                buffer.del(&path_refs)?;
+            },
+            NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) => {
+                for (key, value) in entries {
+                    path.push(key.clone());
+                    value.write_to(buffer, path)?;
+                    path.pop();
+                }
+            },
+            NP_Value_Owned::List(items) | NP_Value_Owned::Tuple(items) => {
+                for (index, value) in items.iter().enumerate() {
+                    if *value == NP_Value_Owned::Null {
+                        continue;
+                    }
+                    path.push(index.to_string());
+                    value.write_to(buffer, path)?;
+                    path.pop();
+                }
+            },
+            NP_Value_Owned::String(x) => set!(x.as_str()),
+            NP_Value_Owned::Bytes(x) => set!(x.as_slice()),
+            NP_Value_Owned::Int8(x) => set!(*x),
+            NP_Value_Owned::Int16(x) => set!(*x),
+            NP_Value_Owned::Int32(x) => set!(*x),
+            NP_Value_Owned::Int64(x) => set!(*x),
+            NP_Value_Owned::Uint8(x) => set!(*x),
+            NP_Value_Owned::Uint16(x) => set!(*x),
+            NP_Value_Owned::Uint32(x) => set!(*x),
+            NP_Value_Owned::Uint64(x) => set!(*x),
+            NP_Value_Owned::Float(x) => set!(*x),
+            NP_Value_Owned::Double(x) => set!(*x),
+            NP_Value_Owned::Dec(x) => set!(*x),
+            NP_Value_Owned::Bool(x) => set!(*x),
+            NP_Value_Owned::Geo(x) => set!(x.clone()),
+            NP_Value_Owned::Uuid(x) => set!(x),
+            NP_Value_Owned::Ulid(x) => set!(x),
+            NP_Value_Owned::Date(x) => set!(*x),
+            NP_Value_Owned::Enum(x) => set!(NP_Enum::new(x.as_str()))
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn owned_tree_round_trip() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id",    {"type": "uuid"}],
+            ["when",  {"type": "date"}],
+            ["where", {"type": "geo4"}],
+            ["color", {"type": "option", "choices": ["red", "blue"]}],
+            ["pair",  {"type": "tuple", "values": [{"type": "i16"}, {"type": "bool"}]}],
+            ["meta",  {"type": "map", "value": {"type": "bytes"}}],
+            ["age",   {"type": "u8", "default": 20}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["id"], &NP_UUID::generate(22))?;
+    buffer.set(&["when"], NP_Date::new(1605138980392))?;
+    buffer.set(&["where"], NP_Geo::new(4, 45.5, -120.25))?;
+    buffer.set(&["color"], NP_Enum::new("blue"))?;
+    buffer.set(&["pair", "1"], true)?;
+    buffer.set(&["meta", "key"], &[1u8, 2, 3] as &[u8])?;
+
+    let tree = buffer.to_owned_tree()?;
+
+    // default values aren't copied into the tree
+    if let NP_Value_Owned::Table(columns) = &tree {
+        assert_eq!(columns.len(), 6);
+        assert_eq!(columns[4].1, NP_Value_Owned::Tuple(alloc::vec![NP_Value_Owned::Null, NP_Value_Owned::Bool(true)]));
+    } else {
+        panic!()
+    }
+
+    let mut copy = factory.empty_buffer(None);
+    copy.from_owned_tree(&tree)?;
+    assert_eq!(copy.to_owned_tree()?, tree);
+    assert_eq!(copy.get::<u8>(&["age"])?, Some(20));
+
+    // values must match the schema
+    let bad = NP_Value_Owned::Table(alloc::vec![(String::from("age"), NP_Value_Owned::Int64(2))]);
+    assert!(copy.from_owned_tree(&bad).is_err());
+
+    Ok(())
+}
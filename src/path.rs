@@ -0,0 +1,169 @@
+//! Paths resolved against the schema ahead of time
+//!
+//! Every call to `get` with a string path looks up table columns by name and parses list and tuple indexes, for each level of the path.  In tight loops that read the same path out of many buffers that work can be done once with `NP_Factory::compile_path`, the resulting `NP_Path` is then used with `NP_Buffer::get_with`.
+//!
+//! Compiled paths always start at the root of the buffer, not at the buffer cursor.  They should only be used with buffers from the factory that compiled them.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["users", {"type": "list", "of": {"type": "table", "columns": [
+//!             ["name", {"type": "string"}],
+//!             ["tags", {"type": "map", "value": {"type": "u8"}}]
+//!         ]}}]
+//!     ]
+//! }"#)?;
+//!
+//! let name_path = factory.compile_path(&["users", "3", "name"])?;
+//! let tag_path = factory.compile_path(&["users", "3", "tags", "level"])?;
+//!
+//! // paths are checked against the schema
+//! assert!(factory.compile_path(&["users", "3", "age"]).is_err());
+//! assert!(factory.compile_path(&["users", "three"]).is_err());
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["users", "3", "name"], "Jeb")?;
+//! new_buffer.set(&["users", "3", "tags", "level"], 5u8)?;
+//!
+//! for _x in 0..10 {
+//!     assert_eq!(new_buffer.get_with::<&str>(&name_path)?, Some("Jeb"));
+//!     assert_eq!(new_buffer.get_with::<u8>(&tag_path)?, Some(5));
+//! }
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::LIST_MAX_SIZE;
+use crate::error::NP_Error;
+use crate::schema::NP_Parsed_Schema;
+use alloc::vec::Vec;
+use alloc::string::{String, ToString};
+
+/// A single step into a collection
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum NP_Path_Step {
+    /// index of a table column
+    Column(usize),
+    /// index of a tuple value or list item
+    Index(usize),
+    /// key of a map item
+    Key(String)
+}
+
+/// A path into buffers that was checked against the schema when it was compiled
+///
+/// Check out documentation [here](../path/index.html).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct NP_Path {
+    pub(crate) steps: Vec<NP_Path_Step>,
+    /// schema address of the value at the end of the path
+    pub(crate) schema_addr: usize
+}
+
+impl NP_Path {
+
+    /// Resolve a path against the schema, starting at the root
+    pub(crate) fn compile(schema: &Vec<NP_Parsed_Schema>, path: &[&str]) -> Result<Self, NP_Error> {
+
+        let mut steps: Vec<NP_Path_Step> = Vec::with_capacity(path.len());
+        let mut schema_addr = 0usize;
+
+        for key in path {
+            let (step, next) = match &schema[schema_addr] {
+                NP_Parsed_Schema::Table { columns, columns_mapped, .. } => {
+                    match columns_mapped.get(key) {
+                        Some(x) => (NP_Path_Step::Column(*x), columns[*x].2),
+                        None => return Err(Self::error("Column not found in table: ", key))
+                    }
+                },
+                NP_Parsed_Schema::Tuple { values, .. } => {
+                    match key.parse::<usize>() {
+                        Ok(x) if x < values.len() => (NP_Path_Step::Index(x), values[x]),
+                        Ok(_x) => return Err(Self::error("Index is outside of tuple: ", key)),
+                        Err(_e) => return Err(NP_Error::BadPath("Need a number to index into tuple, string found!".into()))
+                    }
+                },
+                NP_Parsed_Schema::List { of, .. } => {
+                    match key.parse::<usize>() {
+                        Ok(x) if x < LIST_MAX_SIZE => (NP_Path_Step::Index(x), *of),
+                        Ok(_x) => return Err(Self::error("Index is outside of list: ", key)),
+                        Err(_e) => return Err(NP_Error::BadPath("Need a number to index into list, string found!".into()))
+                    }
+                },
+                NP_Parsed_Schema::Map { value, .. } => {
+                    (NP_Path_Step::Key(key.to_string()), *value)
+                },
+                _ => return Err(Self::error("Path continues past a scalar value: ", key))
+            };
+            steps.push(step);
+            schema_addr = next;
+        }
+
+        Ok(NP_Path { steps, schema_addr })
+    }
+
+    fn error(message: &str, key: &str) -> NP_Error {
+        let mut err = String::from(message);
+        err.push_str(key);
+        NP_Error::BadPath(err)
+    }
+
+    /// How many steps are in this path
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Is this the path to the root value?
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+#[test]
+fn compiled_paths_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["a", {"type": "u8"}],
+            ["b", {"type": "u8"}],
+            ["c", {"type": "u8"}],
+            ["d", {"type": "u8"}],
+            ["e", {"type": "u32", "default": 7}],
+            ["point", {"type": "tuple", "values": [{"type": "i16"}, {"type": "string"}]}]
+        ]
+    }"#)?;
+    let other = NP_Factory::new(r#"{"type": "list", "of": {"type": "u32"}}"#)?;
+
+    let e_path = factory.compile_path(&["e"])?;
+    let point_path = factory.compile_path(&["point", "1"])?;
+    assert_eq!(e_path.steps, alloc::vec![NP_Path_Step::Column(4)]);
+    assert_eq!(point_path.len(), 2);
+    assert!(factory.compile_path(&[])?.is_empty());
+    assert!(factory.compile_path(&["point", "2"]).is_err());
+    assert!(factory.compile_path(&["a", "0"]).is_err());
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["a"], 1u8)?;
+    let bytes = buffer.close();
+
+    // reading a column on a missing vtable doesn't change the buffer
+    let buffer = factory.open_buffer_read_only(&bytes);
+    assert_eq!(buffer.get_with::<u32>(&e_path)?, Some(7));
+    assert_eq!(buffer.get::<u32>(&["e"])?, Some(7));
+    assert_eq!(buffer.get_with::<&str>(&point_path)?, None);
+    assert_eq!(buffer.read_bytes(), &bytes[..]);
+
+    // paths from other schemas fail without panicking
+    let buffer = other.empty_buffer(None);
+    assert!(buffer.get_with::<u32>(&point_path).is_err());
+
+    Ok(())
+}
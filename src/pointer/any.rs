@@ -1,5 +1,47 @@
+//! Dynamic values for fields that can hold any scalar
+//!
+//! `any` fields don't describe their type in the schema, instead each value is stored with a one byte type tag in front of it.  Values are written and read as [`NP_AnyValue`](enum.NP_AnyValue.html), `set_any` and `get_any` on the buffer are shortcuts for `set` and `get` with this type.
+//!
+//! Each value is stored as a type tag byte, a 4 byte big endian payload length and the payload.
+//!
+//! | Type     | Tag | Payload |
+//! |----------|-----|---------|
+//! | `Bool`   | 1   | 1 byte, `0` or `1` |
+//! | `Int`    | 2   | 8 bytes, big endian |
+//! | `Uint`   | 3   | 8 bytes, big endian |
+//! | `Float`  | 4   | 8 bytes, big endian |
+//! | `String` | 5   | UTF8 bytes |
+//! | `Bytes`  | 6   | bytes |
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::any::NP_AnyValue;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "map",
+//!    "value": {"type": "any"}
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set_any(&["name"], NP_AnyValue::String("Jeb".into()))?;
+//! new_buffer.set_any(&["age"], NP_AnyValue::Uint(32))?;
+//!
+//! assert_eq!(new_buffer.get_any(&["age"])?, Some(NP_AnyValue::Uint(32)));
+//!
+//! // values can change type at any time
+//! new_buffer.set_any(&["age"], NP_AnyValue::Float(32.5))?;
+//! new_buffer.compact(None)?;
+//!
+//! assert_eq!(new_buffer.get_any(&["age"])?, Some(NP_AnyValue::Float(32.5)));
+//! assert_eq!(new_buffer.json_encode(&[])?.stringify(), r#"{"name":"Jeb","age":32.5}"#);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+
 use crate::{json_flex::{JSMAP}, schema::{NP_Parsed_Schema}};
 use alloc::vec::Vec;
+use alloc::string::String;
 use crate::error::NP_Error;
 use crate::{schema::{NP_TypeKeys}, pointer::NP_Value, json_flex::NP_JSON};
 
@@ -27,19 +69,19 @@ impl<'value> NP_Value<'value> for NP_Any {
     }
 
     fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
-        Err(NP_Error::new("Can't use .set() with (Any), must cast first with NP_Any::cast<T>(pointer)."))
+        Err(NP_Error::SchemaMismatch("Can't use .set() with (NP_Any), use NP_AnyValue instead.".into()))
     }
     fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
-        Err(NP_Error::new("Type (Any) doesn't support .into()!"))
+        Err(NP_Error::SchemaMismatch("Can't use .get() with (NP_Any), use NP_AnyValue instead.".into()))
     }
     fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
-        NP_JSON::Null
+        NP_AnyValue::to_json(cursor, memory)
     }
-    fn get_size(_cursor: &NP_Cursor, _memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
-        Ok(0)
+    fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
+        NP_AnyValue::get_size(cursor, memory)
     }
     fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
-        Err(NP_Error::new("Cannot compact an ANY field!"))
+        NP_AnyValue::do_compact(from_cursor, from_memory, to_cursor, to_memory)
     }
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, _json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
 
@@ -67,17 +109,254 @@ impl<'value> NP_Value<'value> for NP_Any {
 }
 
 impl<'value> Default for NP_Any {
-    fn default() -> Self { 
+    fn default() -> Self {
         NP_Any {}
     }
 }
 
+/// A value of an `any` field, along with it's type
+///
+/// Check out documentation [here](../any/index.html).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum NP_AnyValue {
+    /// Boolean value
+    Bool(bool),
+    /// Signed integer value
+    Int(i64),
+    /// Unsigned integer value
+    Uint(u64),
+    /// Floating point value
+    Float(f64),
+    /// UTF8 string value
+    String(String),
+    /// Bytes value
+    Bytes(Vec<u8>)
+}
+
+/// Type tag and payload length in front of every any value
+const ANY_HEADER_SIZE: usize = 5;
+
+impl NP_AnyValue {
+
+    /// The type tag stored in front of this value
+    pub fn type_tag(&self) -> u8 {
+        match self {
+            NP_AnyValue::Bool(_)   => 1,
+            NP_AnyValue::Int(_)    => 2,
+            NP_AnyValue::Uint(_)   => 3,
+            NP_AnyValue::Float(_)  => 4,
+            NP_AnyValue::String(_) => 5,
+            NP_AnyValue::Bytes(_)  => 6
+        }
+    }
+
+    /// Convert a JSON scalar into a value, `None` for null, arrays and objects
+    pub fn from_json(json: &NP_JSON) -> Option<Self> {
+        match json {
+            NP_JSON::True       => Some(NP_AnyValue::Bool(true)),
+            NP_JSON::False      => Some(NP_AnyValue::Bool(false)),
+            NP_JSON::Integer(x) => Some(NP_AnyValue::Int(*x)),
+            NP_JSON::Float(x)   => Some(NP_AnyValue::Float(*x)),
+            NP_JSON::String(x)  => Some(NP_AnyValue::String(x.clone())),
+            _ => None
+        }
+    }
+
+    /// Tag, length and payload of this value
+    fn encode(&self) -> Result<Vec<u8>, NP_Error> {
+        let payload: &[u8] = match self {
+            NP_AnyValue::Bool(x)   => if *x { &[1] } else { &[0] },
+            NP_AnyValue::String(x) => x.as_bytes(),
+            NP_AnyValue::Bytes(x)  => x.as_slice(),
+            _ => &[]
+        };
+        let fixed = match self {
+            NP_AnyValue::Int(x)   => Some(x.to_be_bytes()),
+            NP_AnyValue::Uint(x)  => Some(x.to_be_bytes()),
+            NP_AnyValue::Float(x) => Some(x.to_be_bytes()),
+            _ => None
+        };
+        let payload = match &fixed {
+            Some(x) => &x[..],
+            None => payload
+        };
+
+        if payload.len() > u32::MAX as usize {
+            return Err(NP_Error::InvalidValue("Any value too large!".into()));
+        }
+
+        let mut bytes = Vec::with_capacity(ANY_HEADER_SIZE + payload.len());
+        bytes.push(self.type_tag());
+        bytes.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(payload);
+        Ok(bytes)
+    }
+}
+
+impl super::NP_Scalar for NP_AnyValue {}
+
+impl<'value> NP_Value<'value> for NP_AnyValue {
+
+    fn type_idx() -> (&'value str, NP_TypeKeys) { ("any", NP_TypeKeys::Any) }
+    fn self_type_idx(&self) -> (&'value str, NP_TypeKeys) { ("any", NP_TypeKeys::Any) }
+
+    fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> {
+        NP_Any::schema_to_json(schema, address)
+    }
+
+    fn schema_default(_schema: &NP_Parsed_Schema) -> Option<Self> {
+        None
+    }
+
+    fn set_value<'set>(cursor: NP_Cursor, memory: &'set NP_Memory, value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
+
+        let bytes = value.encode()?;
+        let c_value = cursor.get_value(memory);
+        let addr_value = c_value.get_addr_value() as usize;
+
+        if addr_value != 0 {
+            // previous value is the same size, use existing memory
+            let prev_size = match memory.get_4_bytes(addr_value + 1) {
+                Some(x) => u32::from_be_bytes(*x) as usize,
+                None => 0
+            };
+            if prev_size + ANY_HEADER_SIZE == bytes.len() {
+                memory.get_bytes_mut(addr_value, bytes.len())?.copy_from_slice(&bytes);
+                return Ok(cursor);
+            }
+        }
+
+        let new_addr = memory.malloc(bytes)?;
+        cursor.get_value(memory).set_addr_value(new_addr as u32);
+
+        Ok(cursor)
+    }
+
+    fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
+
+        let value_addr = cursor.get_value(memory).get_addr_value() as usize;
+
+        // empty value
+        if value_addr == 0 {
+            return Ok(None);
+        }
+
+        let header = memory.get_bytes(value_addr, ANY_HEADER_SIZE)?;
+        let size = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let tag = header[0];
+        let payload = memory.get_bytes(value_addr + ANY_HEADER_SIZE, size)?;
+
+        let damaged = || NP_Error::Damaged("Any value has a bad type tag or length!".into());
+
+        let fixed = || -> Result<[u8; 8], NP_Error> {
+            let mut bytes = [0u8; 8];
+            if payload.len() != 8 {
+                return Err(damaged());
+            }
+            bytes.copy_from_slice(payload);
+            Ok(bytes)
+        };
+
+        Ok(Some(match tag {
+            1 if size == 1 => NP_AnyValue::Bool(payload[0] == 1),
+            2 => NP_AnyValue::Int(i64::from_be_bytes(fixed()?)),
+            3 => NP_AnyValue::Uint(u64::from_be_bytes(fixed()?)),
+            4 => NP_AnyValue::Float(f64::from_be_bytes(fixed()?)),
+            5 => match core::str::from_utf8(payload) {
+                Ok(x) => NP_AnyValue::String(x.to_owned()),
+                Err(_) => return Err(damaged())
+            },
+            6 => NP_AnyValue::Bytes(payload.to_vec()),
+            _ => return Err(damaged())
+        }))
+    }
+
+    fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
+        match Self::into_value(cursor, memory) {
+            Ok(Some(value)) => match value {
+                NP_AnyValue::Bool(x)   => if x { NP_JSON::True } else { NP_JSON::False },
+                NP_AnyValue::Int(x)    => NP_JSON::Integer(x),
+                NP_AnyValue::Uint(x)   => NP_JSON::Integer(x as i64),
+                NP_AnyValue::Float(x)  => NP_JSON::Float(x),
+                NP_AnyValue::String(x) => NP_JSON::String(x),
+                NP_AnyValue::Bytes(x)  => NP_JSON::Array(x.into_iter().map(|b| NP_JSON::Integer(b as i64)).collect())
+            },
+            _ => NP_JSON::Null
+        }
+    }
+
+    fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
+        let value_addr = cursor.get_value(memory).get_addr_value() as usize;
+        if value_addr == 0 {
+            return Ok(0);
+        }
+        match memory.get_4_bytes(value_addr + 1) {
+            Some(x) => Ok(ANY_HEADER_SIZE + u32::from_be_bytes(*x) as usize),
+            None => Err(NP_Error::Damaged("Any value is outside the buffer!".into()))
+        }
+    }
+
+    fn from_json_to_schema(schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
+        NP_Any::from_json_to_schema(schema, json_schema)
+    }
+
+    fn from_bytes_to_schema(schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+        NP_Any::from_bytes_to_schema(schema, address, bytes)
+    }
+}
+
 
 #[test]
 fn schema_parsing_works() -> Result<(), NP_Error> {
     let schema = "{\"type\":\"any\"}";
     let factory = crate::NP_Factory::new(schema)?;
     assert_eq!(schema, factory.schema.to_json()?.stringify());
-    
+
+    Ok(())
+}
+
+#[test]
+fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "any"}}"#)?;
+    let values = alloc::vec![
+        NP_AnyValue::Bool(true),
+        NP_AnyValue::Int(-20),
+        NP_AnyValue::Uint(u64::MAX),
+        NP_AnyValue::Float(2.5),
+        NP_AnyValue::String("hello".to_owned()),
+        NP_AnyValue::Bytes(alloc::vec![1, 2, 3])
+    ];
+
+    let mut buffer = factory.empty_buffer(None);
+    for value in values.iter() {
+        buffer.list_push(&[], value.clone())?;
+    }
+    for (index, value) in values.iter().enumerate() {
+        assert_eq!(buffer.get_any(&[&alloc::format!("{}", index)])?.as_ref(), Some(value));
+    }
+
+    // same size values are replaced in place, other sizes move
+    let size = buffer.calc_bytes()?.current_buffer;
+    buffer.set_any(&["1"], NP_AnyValue::Uint(7))?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size);
+    buffer.set_any(&["1"], NP_AnyValue::String("a longer string".to_owned()))?;
+    assert!(buffer.del(&["0"])?);
+    assert_eq!(buffer.get_any(&["0"])?, None);
+
+    buffer.compact(None)?;
+    assert_eq!(buffer.calc_bytes()?.after_compaction, buffer.calc_bytes()?.current_buffer);
+    assert_eq!(buffer.get_any(&["1"])?, Some(NP_AnyValue::String("a longer string".to_owned())));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), r#"[null,"a longer string",-1,2.5,"hello",[1,2,3]]"#);
+
+    // JSON scalars go in with a type picked from the JSON value
+    let mut imported = factory.empty_buffer(None);
+    imported.set_json(&[], &*crate::json_flex::json_decode(r#"[true, -1, 2.5, "hello"]"#.to_owned())?)?;
+    assert_eq!(imported.get_any(&["1"])?, Some(NP_AnyValue::Int(-1)));
+    assert!(imported.set_json(&["0"], &NP_JSON::Array(Vec::new())).is_err());
+
+    // other types can't be set into an any field
+    assert!(buffer.set(&["0"], 5u8).is_err());
+
     Ok(())
 }
@@ -27,7 +27,7 @@ use alloc::{borrow::ToOwned};
 use crate::NP_Memory;
 use alloc::string::ToString;
 
-use super::NP_Cursor;
+use super::{NP_Cursor, inline_size, inline_encode, inline_decode};
 
 impl super::NP_Scalar for bool {}
 
@@ -41,13 +41,16 @@ impl<'value> NP_Value<'value> for bool {
         schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
 
         match &schema[address] {
-            NP_Parsed_Schema::Boolean { i: _, sortable: _, default} => {
+            NP_Parsed_Schema::Boolean { default, inline, .. } => {
                 if let Some(d) = default {
                     schema_json.insert("default".to_owned(), match *d {
                         true => NP_JSON::True,
                         false => NP_JSON::False
                     });
                 }
+                if *inline {
+                    schema_json.insert("inline".to_owned(), NP_JSON::True);
+                }
             },
             _ =>  { }
         }
@@ -73,10 +76,15 @@ impl<'value> NP_Value<'value> for bool {
         let c_value = cursor.get_value(memory);
         let mut value_address = c_value.get_addr_value();  
 
+        if inline_size(&memory.schema[cursor.schema_addr], memory).is_some() {
+            c_value.set_addr_value(inline_encode(&[if value == true { 1 } else { 0 }]));
+            return Ok(cursor);
+        }
+
         if value_address != 0 { // existing value, replace
 
             // overwrite existing values in buffer
-            memory.write_bytes()[value_address as usize] = if value == true {
+            memory.get_bytes_mut(value_address as usize, 1)?[0] = if value == true {
                 1
             } else {
                 0
@@ -92,8 +100,8 @@ impl<'value> NP_Value<'value> for bool {
                 [0] as [u8; 1]
             };
 
-            value_address = memory.malloc_borrow(&bytes)? as u16;
-            c_value.set_addr_value(value_address as u16);
+            value_address = memory.malloc_borrow(&bytes)? as u32;
+            cursor.get_value(memory).set_addr_value(value_address as u32);
 
             return Ok(cursor);
 
@@ -112,6 +120,12 @@ impl<'value> NP_Value<'value> for bool {
             return Ok(None);
         }
 
+        if inline_size(&memory.schema[cursor.schema_addr], memory).is_some() {
+            let mut bytes = [0u8; 1];
+            inline_decode(value_addr as u32, &mut bytes);
+            return Ok(Some(bytes[0] == 1));
+        }
+
         Ok(match memory.get_1_byte(value_addr) {
             Some(x) => {
                 Some(if x == 1 { true } else { false })
@@ -136,7 +150,7 @@ impl<'value> NP_Value<'value> for bool {
                     },
                     None => {                        
                         match memory.schema[cursor.schema_addr] {
-                            NP_Parsed_Schema::Boolean { i: _, sortable: _, default} => {
+                            NP_Parsed_Schema::Boolean { default, .. } => {
                                 if let Some(d) = default {
                                     if d == true {
                                         NP_JSON::True
@@ -160,7 +174,7 @@ impl<'value> NP_Value<'value> for bool {
 
     fn get_size(cursor: &NP_Cursor, memory: &NP_Memory<'value>) -> Result<usize, NP_Error> {
         let c_value = cursor.get_value(memory);
-        if c_value.get_addr_value() == 0 {
+        if c_value.get_addr_value() == 0 || inline_size(&memory.schema[cursor.schema_addr], memory).is_some() {
             Ok(0) 
         } else {
             Ok(core::mem::size_of::<u8>())
@@ -172,17 +186,26 @@ impl<'value> NP_Value<'value> for bool {
         let mut schema_data: Vec<u8> = Vec::new();
         schema_data.push(NP_TypeKeys::Boolean as u8);
 
+        let inline = match json_schema["inline"] {
+            NP_JSON::True => true,
+            NP_JSON::False | NP_JSON::Null => false,
+            _ => return Err(NP_Error::Schema("The inline property must be a boolean!".into()))
+        };
+
+        // first two bits for default value, third bit for inline values
+        let inline_flag = if inline { 4 } else { 0 };
+
         let default = match json_schema["default"] {
             NP_JSON::False => {
-                schema_data.push(2);
+                schema_data.push(2 | inline_flag);
                 Some(false)
             },
             NP_JSON::True => {
-                schema_data.push(1);
+                schema_data.push(1 | inline_flag);
                 Some(true)
             },
             _ => {
-                schema_data.push(0);
+                schema_data.push(inline_flag);
                 None
             }
         };
@@ -190,22 +213,24 @@ impl<'value> NP_Value<'value> for bool {
         schema.push(NP_Parsed_Schema::Boolean {
             i: NP_TypeKeys::Boolean,
             default: default,
-            sortable: true
+            sortable: true,
+            inline
         });
 
         return Ok((true, schema_data, schema));
   
     }
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+        let inline = bytes[address + 1] & 4 != 0;
         schema.push(NP_Parsed_Schema::Boolean {
             i: NP_TypeKeys::Boolean,
             sortable: true,
-            default: match bytes[address] {
-                0 => None,
+            default: match bytes[address + 1] & 3 {
                 1 => Some(true),
                 2 => Some(false),
-                _ => unreachable!()
-            }
+                _ => None
+            },
+            inline
         });
         (true, schema)
      }
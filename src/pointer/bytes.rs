@@ -21,7 +21,6 @@
 use crate::{json_flex::JSMAP, schema::{NP_Parsed_Schema}};
 use crate::error::NP_Error;
 use crate::{schema::{NP_TypeKeys}, pointer::NP_Value, json_flex::NP_JSON};
-use core::hint::unreachable_unchecked;
 
 use alloc::vec::Vec;
 use alloc::boxed::Box;
@@ -89,8 +88,6 @@ impl<'value> NP_Value<'value> for &'value [u8] {
     
         let str_size = bytes.len() as usize;
     
-        let write_bytes = memory.write_bytes();
-    
         let size = match memory.schema[cursor.schema_addr] {
             NP_Parsed_Schema::Bytes { size, .. } => size,
             _ => 0
@@ -100,26 +97,21 @@ impl<'value> NP_Value<'value> for &'value [u8] {
             // fixed size bytes
     
             if c_value.get_addr_value() == 0 {
-                // malloc new bytes
-    
-                let mut empty_bytes: Vec<u8> = Vec::with_capacity(size as usize);
-                for _x in 0..size {
-                    empty_bytes.push(0);
-                }
-    
-                let new_addr = memory.malloc(empty_bytes)? as usize;
-                c_value.set_addr_value(new_addr as u16);
+                // malloc new bytes, they're filled in below
+                let new_addr = memory.malloc_pointers(0, size as usize)?;
+                cursor.get_value(memory).set_addr_value(new_addr as u32);
             }
 
-            let addr = c_value.get_addr_value() as usize;
+            let addr = cursor.get_value(memory).get_addr_value() as usize;
+            let write_bytes = memory.get_bytes_mut(addr, size as usize)?;
     
             for x in 0..(size as usize) {
                 if x < bytes.len() {
                     // assign values of bytes
-                    write_bytes[(addr + x)] = bytes[x];
+                    write_bytes[x] = bytes[x];
                 } else {
                     // rest is zeros
-                    write_bytes[(addr + x)] = 0;
+                    write_bytes[x] = 0;
                 }
             }
     
@@ -141,19 +133,20 @@ impl<'value> NP_Value<'value> for &'value [u8] {
     
             // update string length in buffer
             if str_size > core::u16::MAX as usize {
-                return Err(NP_Error::new("String too large!"));
+                return Err(NP_Error::InvalidValue("String too large!".into()));
             }
             let size_bytes = (str_size as u16).to_be_bytes();
+            let write_bytes = memory.get_bytes_mut(addr_value, 2 + str_size)?;
             // set string size
             for x in 0..size_bytes.len() {
-                write_bytes[(addr_value + x)] = size_bytes[x];
+                write_bytes[x] = size_bytes[x];
             }
     
             let offset = 2;
     
             // set bytes
             for x in 0..bytes.len() {
-                write_bytes[(addr_value + x + offset) as usize] = bytes[x];
+                write_bytes[x + offset] = bytes[x];
             }
     
             return Ok(cursor);
@@ -163,13 +156,13 @@ impl<'value> NP_Value<'value> for &'value [u8] {
             // first bytes are string length
             let new_addr = {
                 if str_size > core::u16::MAX as usize {
-                    return Err(NP_Error::new("String too large!"));
+                    return Err(NP_Error::InvalidValue("String too large!".into()));
                 }
                 let size_bytes = (str_size as u16).to_be_bytes();
                 memory.malloc_borrow(&size_bytes)?
             };
     
-            c_value.set_addr_value(new_addr as u16);
+            cursor.get_value(memory).set_addr_value(new_addr as u32);
     
             memory.malloc_borrow(bytes)?;
     
@@ -199,7 +192,7 @@ impl<'value> NP_Value<'value> for &'value [u8] {
                     // fixed size
 
                     // get bytes
-                    let bytes = &memory.read_bytes()[(value_addr)..(value_addr + (size as usize))];
+                    let bytes = memory.get_bytes(value_addr, size as usize)?;
 
                     return Ok(Some(bytes));
                 } else {
@@ -209,7 +202,7 @@ impl<'value> NP_Value<'value> for &'value [u8] {
                     let bytes_size: usize = u16::from_be_bytes(*memory.get_2_bytes(value_addr).unwrap_or(&[0; 2])) as usize;
 
                     // get bytes
-                    let bytes = &memory.read_bytes()[(value_addr + 2)..(value_addr + 2 + bytes_size)];
+                    let bytes = memory.get_bytes(value_addr + 2, bytes_size)?;
 
                     return Ok(Some(bytes));
                 }
@@ -285,29 +278,26 @@ impl<'value> NP_Value<'value> for &'value [u8] {
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
 
 
-        let mut has_fixed_size = false;
         let mut schema_data: Vec<u8> = Vec::new();
         schema_data.push(NP_TypeKeys::Bytes as u8);
 
         let size = match json_schema["size"] {
             NP_JSON::Integer(x) => {
-                has_fixed_size = true;
                 if x < 1 {
-                    return Err(NP_Error::new("Fixed size for bytes must be larger than 1!"));
+                    return Err(NP_Error::Schema("Fixed size for bytes must be larger than 1!".into()));
                 }
                 if x > u16::MAX.into() {
-                    return Err(NP_Error::new("Fixed size for bytes cannot be larger than 2^16!"));
+                    return Err(NP_Error::Schema("Fixed size for bytes cannot be larger than 2^16!".into()));
                 }
                 schema_data.extend((x as u16).to_be_bytes().to_vec());
                 x as u16
             },
             NP_JSON::Float(x) => {
-                has_fixed_size = true;
                 if x < 1.0 {
-                    return Err(NP_Error::new("Fixed size for bytes must be larger than 1!"));
+                    return Err(NP_Error::Schema("Fixed size for bytes must be larger than 1!".into()));
                 }
                 if x > u16::MAX.into() {
-                    return Err(NP_Error::new("Fixed size for bytes cannot be larger than 2^16!"));
+                    return Err(NP_Error::Schema("Fixed size for bytes cannot be larger than 2^16!".into()));
                 }
 
                 schema_data.extend((x as u16).to_be_bytes().to_vec());
@@ -344,10 +334,10 @@ impl<'value> NP_Value<'value> for &'value [u8] {
             i: NP_TypeKeys::Bytes,
             size: size,
             default: default,
-            sortable: has_fixed_size
+            sortable: true
         });
 
-        return Ok((has_fixed_size, schema_data, schema));
+        return Ok((true, schema_data, schema));
     }
 
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
@@ -367,7 +357,7 @@ impl<'value> NP_Value<'value> for &'value [u8] {
             schema.push(NP_Parsed_Schema::Bytes {
                 i: NP_TypeKeys::Bytes,
                 default: None,
-                sortable: fixed_size > 0,
+                sortable: true,
                 size: fixed_size
             });
         } else {
@@ -377,11 +367,11 @@ impl<'value> NP_Value<'value> for &'value [u8] {
                 i: NP_TypeKeys::Bytes,
                 default: Some(default_bytes.to_vec()),
                 size: fixed_size,
-                sortable: fixed_size > 0
+                sortable: true
             });    
         }
 
-        (fixed_size > 0, schema)
+        (true, schema)
 
     }
 }
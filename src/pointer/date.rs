@@ -19,19 +19,44 @@
 //! # Ok::<(), NP_Error>(()) 
 //! ```
 //! 
+//! Dates are written to JSON as RFC3339 strings in UTC with milliseconds, like `"2020-11-09T23:40:49.484Z"`.  `set_json` and schema defaults take the same strings or the milliseconds as a number.  Parsed strings can have any offset from UTC, a date without a time is midnight UTC and digits past milliseconds are cut off.
+//! 
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::date::NP_Date;
+//! 
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "date",
+//!    "default": "2020-11-09"
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! assert_eq!(new_buffer.json_encode(&[])?.stringify(), "\"2020-11-09T00:00:00.000Z\"");
+//! 
+//! new_buffer.set(&[], NP_Date::from_rfc3339("2020-11-09T15:40:49.484-08:00")?)?;
+//! assert_eq!(new_buffer.get::<NP_Date>(&[])?, Some(NP_Date::new(1604965249484)));
+//! assert_eq!(new_buffer.get::<NP_Date>(&[])?.unwrap().to_string(), "2020-11-09T23:40:49.484Z");
+//!
+//! # Ok::<(), NP_Error>(()) 
+//! ```
+//! 
+//! With the `chrono` feature dates convert to and from `chrono::DateTime<Utc>`, converting a date from before the unix epoch fails.
+//! 
 
 use crate::schema::{NP_Parsed_Schema};
 use alloc::vec::Vec;
 use crate::json_flex::{JSMAP, NP_JSON};
 use crate::schema::{NP_TypeKeys};
 use crate::{pointer::NP_Value, error::NP_Error};
-use core::{fmt::{Debug, Formatter}, hint::unreachable_unchecked};
+use core::fmt::{Debug, Formatter};
 
 use alloc::boxed::Box;
 use alloc::borrow::ToOwned;
 use super::{NP_Cursor};
 use crate::NP_Memory;
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
 
 
 /// Holds Date data.
@@ -54,6 +79,230 @@ impl NP_Date {
     }
 }
 
+/// Days since the unix epoch of a date, see http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Year, month and day of a day since the unix epoch
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + if month <= 2 { 1 } else { 0 }, month, day)
+}
+
+/// Read a number of `len` digits and move `at` past it
+fn take_digits(bytes: &[u8], at: &mut usize, len: usize) -> Option<i64> {
+    let digits = bytes.get(*at..(*at + len))?;
+    let mut value = 0i64;
+    for digit in digits {
+        if !digit.is_ascii_digit() {
+            return None;
+        }
+        value = value * 10 + (digit - b'0') as i64;
+    }
+    *at += len;
+    Some(value)
+}
+
+/// Move `at` past the next byte if it's one of `chars`
+fn take_char(bytes: &[u8], at: &mut usize, chars: &[u8]) -> Option<u8> {
+    let next = *bytes.get(*at)?;
+    if chars.contains(&next) {
+        *at += 1;
+        Some(next)
+    } else {
+        None
+    }
+}
+
+/// Milliseconds since the unix epoch of an RFC3339 string or an ISO-8601 date
+fn parse_rfc3339(value: &str) -> Option<i64> {
+    let bytes = value.as_bytes();
+    let mut at = 0;
+
+    let year = take_digits(bytes, &mut at, 4)?;
+    take_char(bytes, &mut at, b"-")?;
+    let month = take_digits(bytes, &mut at, 2)?;
+    take_char(bytes, &mut at, b"-")?;
+    let day = take_digits(bytes, &mut at, 2)?;
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = [31, if leap { 29 } else { 28 }, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    if !(1..=12).contains(&month) || day < 1 || day > month_days[month as usize - 1] {
+        return None;
+    }
+
+    let mut ms = days_from_civil(year, month, day) * 86_400_000;
+
+    // dates without a time are midnight
+    if at == bytes.len() {
+        return Some(ms);
+    }
+
+    take_char(bytes, &mut at, b"Tt ")?;
+    let hour = take_digits(bytes, &mut at, 2)?;
+    take_char(bytes, &mut at, b":")?;
+    let minute = take_digits(bytes, &mut at, 2)?;
+    take_char(bytes, &mut at, b":")?;
+    let second = take_digits(bytes, &mut at, 2)?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    ms += ((hour * 60 + minute) * 60 + second) * 1000;
+
+    if take_char(bytes, &mut at, b".").is_some() {
+        let start = at;
+        while at < bytes.len() && bytes[at].is_ascii_digit() {
+            at += 1;
+        }
+        if at == start {
+            return None;
+        }
+        // only milliseconds are kept
+        for place in 0..3 {
+            ms += if start + place < at { (bytes[start + place] - b'0') as i64 * [100, 10, 1][place] } else { 0 };
+        }
+    }
+
+    match take_char(bytes, &mut at, b"Zz+-")? {
+        b'Z' | b'z' => { },
+        sign => {
+            let hours = take_digits(bytes, &mut at, 2)?;
+            take_char(bytes, &mut at, b":")?;
+            let minutes = take_digits(bytes, &mut at, 2)?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = (hours * 60 + minutes) * 60_000;
+            ms += if sign == b'+' { -offset } else { offset };
+        }
+    }
+
+    if at == bytes.len() { Some(ms) } else { None }
+}
+
+impl NP_Date {
+    /// Parse an RFC3339 string like `"2020-11-09T23:40:49.484Z"` or an ISO-8601 date like `"2020-11-09"`.
+    /// 
+    /// ```
+    /// use no_proto::pointer::date::NP_Date;
+    /// 
+    /// assert_eq!(NP_Date::from_rfc3339("1970-01-01T00:00:01.5+00:00")?, NP_Date::new(1500));
+    /// assert_eq!(NP_Date::from_rfc3339("1970-01-02")?, NP_Date::new(86_400_000));
+    /// assert!(NP_Date::from_rfc3339("1970-02-30").is_err());
+    /// assert!(NP_Date::from_rfc3339("1969-12-31T23:59:59Z").is_err());
+    /// # Ok::<(), no_proto::error::NP_Error>(()) 
+    /// ```
+    pub fn from_rfc3339(value: &str) -> Result<Self, NP_Error> {
+        match parse_rfc3339(value) {
+            Some(ms) if ms >= 0 => Ok(NP_Date { value: ms as u64 }),
+            Some(_) => Err(NP_Error::InvalidValue("Dates before the unix epoch can't be saved: ".to_owned() + value)),
+            None => Err(NP_Error::Parse("Date isn't an RFC3339 string: ".to_owned() + value))
+        }
+    }
+
+    /// The date as an RFC3339 string in UTC with milliseconds, same as `to_string`
+    pub fn to_rfc3339(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl core::fmt::Display for NP_Date {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let secs = (self.value / 1000) as i64;
+        let (year, month, day) = civil_from_days(secs / 86_400);
+        let rem = secs % 86_400;
+        write!(f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, rem / 3600, (rem % 3600) / 60, rem % 60, self.value % 1000)
+    }
+}
+
+/// Dates past the range of chrono become `DateTime::MAX_UTC`
+#[cfg(feature = "chrono")]
+impl From<NP_Date> for chrono::DateTime<chrono::Utc> {
+    fn from(date: NP_Date) -> Self {
+        let secs = (date.value / 1000) as i64;
+        let nanos = (date.value % 1000) as u32 * 1_000_000;
+        chrono::DateTime::from_timestamp(secs, nanos).unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl core::convert::TryFrom<chrono::DateTime<chrono::Utc>> for NP_Date {
+    type Error = NP_Error;
+
+    fn try_from(date: chrono::DateTime<chrono::Utc>) -> Result<Self, NP_Error> {
+        match date.timestamp_millis() {
+            ms if ms >= 0 => Ok(NP_Date { value: ms as u64 }),
+            _ => Err(NP_Error::InvalidValue("Dates before the unix epoch can't be saved: ".to_owned() + &date.to_rfc3339()))
+        }
+    }
+}
+
+/// Source of the current time for date fields with a `"default": "now"` schema, set with `NP_Factory::with_clock`.
+/// 
+/// Closures returning an `NP_Date` are clocks too, so `no_std` users can read the time from whatever hardware they have.
+pub trait NP_Clock: Send + Sync {
+    /// The current time
+    fn now(&self) -> NP_Date;
+}
+
+impl<F: Fn() -> NP_Date + Send + Sync> NP_Clock for F {
+    fn now(&self) -> NP_Date {
+        self()
+    }
+}
+
+/// Clock reading the system time, the default clock of factories with the `std` feature
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NP_System_Clock;
+
+#[cfg(feature = "std")]
+impl NP_Clock for NP_System_Clock {
+    fn now(&self) -> NP_Date {
+        match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            Ok(x) => NP_Date::new(x.as_millis() as u64),
+            Err(_) => NP_Date::new(0)
+        }
+    }
+}
+
+/// Shared clock of a factory
+#[derive(Clone)]
+pub(crate) struct NP_Clock_Ref(pub Arc<dyn NP_Clock>);
+
+impl NP_Clock_Ref {
+    /// Clock of new factories, the system time with the `std` feature
+    #[cfg(feature = "std")]
+    pub fn default_clock() -> Option<Self> {
+        Some(NP_Clock_Ref(Arc::new(NP_System_Clock)))
+    }
+
+    /// Without the `std` feature new factories don't have a clock
+    #[cfg(not(feature = "std"))]
+    pub fn default_clock() -> Option<Self> {
+        None
+    }
+}
+
+impl Debug for NP_Clock_Ref {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "NP_Clock")
+    }
+}
+
 impl Default for NP_Date {
     fn default() -> Self { 
         NP_Date { value: 0 }
@@ -76,9 +325,11 @@ impl<'value> NP_Value<'value> for NP_Date {
         schema_json.insert("type".to_owned(), NP_JSON::String(Self::type_idx().0.to_string()));
 
         match &schema[address] {
-            NP_Parsed_Schema::Date { i: _, default, sortable: _} => {
-                if let Some(d) = default {
-                    schema_json.insert("default".to_owned(), NP_JSON::Integer(d.value as i64));
+            NP_Parsed_Schema::Date { i: _, default, sortable: _, now } => {
+                if *now {
+                    schema_json.insert("default".to_owned(), NP_JSON::String("now".to_owned()));
+                } else if let Some(d) = default {
+                    schema_json.insert("default".to_owned(), NP_JSON::String(d.to_string()));
                 }
             },
             _ => { }
@@ -109,18 +360,14 @@ impl<'value> NP_Value<'value> for NP_Date {
         if value_address != 0 { // existing value, replace
             let bytes = value.value.to_be_bytes();
 
-            let write_bytes = memory.write_bytes();
-
             // overwrite existing values in buffer
-            for x in 0..bytes.len() {
-                write_bytes[value_address + x] = bytes[x];
-            }
+            memory.get_bytes_mut(value_address, bytes.len())?.copy_from_slice(&bytes);
 
         } else { // new value
 
             let bytes = value.value.to_be_bytes();
             value_address = memory.malloc_borrow(&bytes)?;
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value(memory).set_addr_value(value_address as u32);
         }                    
 
         Ok(cursor)
@@ -151,13 +398,13 @@ impl<'value> NP_Value<'value> for NP_Date {
             Ok(x) => {
                 match x {
                     Some(y) => {
-                        NP_JSON::Integer(y.value as i64)
+                        NP_JSON::String(y.to_string())
                     },
                     None => {
                         match memory.schema[cursor.schema_addr] {
-                            NP_Parsed_Schema::Date { i: _, default, sortable: _} => {
+                            NP_Parsed_Schema::Date { default, .. } => {
                                 if let Some(d) = default {
-                                    NP_JSON::Integer(d.value.clone() as i64)
+                                    NP_JSON::String(d.to_string())
                                 } else {
                                     NP_JSON::Null
                                 }
@@ -189,11 +436,24 @@ impl<'value> NP_Value<'value> for NP_Date {
         let mut schema_data: Vec<u8> = Vec::new();
         schema_data.push(NP_TypeKeys::Date as u8);
 
-        let default = match json_schema["default"] {
+        let mut now = false;
+
+        let default = match &json_schema["default"] {
+            NP_JSON::String(x) if x == "now" => {
+                schema_data.push(2);
+                now = true;
+                None
+            },
             NP_JSON::Integer(x) => {
                 schema_data.push(1);
-                schema_data.extend((x as u64).to_be_bytes().to_vec());
-                Some(NP_Date { value: x as u64})
+                schema_data.extend((*x as u64).to_be_bytes().to_vec());
+                Some(NP_Date { value: *x as u64})
+            },
+            NP_JSON::String(x) => {
+                let date = NP_Date::from_rfc3339(x)?;
+                schema_data.push(1);
+                schema_data.extend(date.value.to_be_bytes().to_vec());
+                Some(date)
             },
             _ => {
                 schema_data.push(0);
@@ -204,7 +464,8 @@ impl<'value> NP_Value<'value> for NP_Date {
         schema.push(NP_Parsed_Schema::Date {
             i: NP_TypeKeys::Date,
             default: default,
-            sortable: true
+            sortable: true,
+            now
         });
 
         return Ok((true, schema_data, schema));
@@ -214,7 +475,7 @@ impl<'value> NP_Value<'value> for NP_Date {
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
         let has_default = bytes[address + 1];
 
-        let default = if has_default == 0 {
+        let default = if has_default != 1 {
             None
         } else {
             let bytes_slice = &bytes[(address + 2)..(address + 10)];
@@ -227,7 +488,8 @@ impl<'value> NP_Value<'value> for NP_Date {
         schema.push(NP_Parsed_Schema::Date {
             i: NP_TypeKeys::Date,
             sortable: true,
-            default: default
+            default: default,
+            now: has_default == 2
         });
         (true, schema)
     }
@@ -266,4 +528,64 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn rfc3339_works() -> Result<(), NP_Error> {
+    assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+    assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+    assert_eq!(NP_Date::from_rfc3339("2020-11-09T23:40:49.484Z")?, NP_Date::new(1604965249484));
+    assert_eq!(NP_Date::from_rfc3339("2020-11-10t05:10:49.4849+05:30")?, NP_Date::new(1604965249484));
+    assert_eq!(NP_Date::from_rfc3339("2000-02-29 00:00:00z")?.to_string(), "2000-02-29T00:00:00.000Z");
+    assert_eq!(NP_Date::new(0).to_rfc3339(), "1970-01-01T00:00:00.000Z");
+    for bad in ["", "2020-11-09T23:40:49", "2020-11-09T24:00:00Z", "1900-02-29", "2020-11-09T23:40:49.Z", "2020-11-09Z", "2020-11-09T23:40:49+0100"].iter() {
+        assert!(NP_Date::from_rfc3339(bad).is_err());
+    }
+
+    // strings work in defaults and JSON
+    let factory = crate::NP_Factory::new(r#"{"type":"date","default":"2020-11-09T23:40:49.484Z"}"#)?;
+    assert_eq!(factory.export_schema()?.stringify(), r#"{"type":"date","default":"2020-11-09T23:40:49.484Z"}"#);
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<NP_Date>(&[])?, Some(NP_Date::new(1604965249484)));
+    buffer.set_json(&[], &NP_JSON::String("1970-01-01T00:00:01Z".into()))?;
+    assert_eq!(buffer.get::<NP_Date>(&[])?, Some(NP_Date::new(1000)));
+    assert!(buffer.set_json(&[], &NP_JSON::String("yesterday".into())).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type":"date","default":"1969-01-01"}"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn now_default_works() -> Result<(), NP_Error> {
+    let schema = r#"{"type":"table","columns":[["created",{"type":"date","default":"now"}],["pair",{"type":"tuple","values":[{"type":"uint8"},{"type":"date","default":"now"}]}],["log",{"type":"list","of":{"type":"date","default":"now"}}]]}"#;
+    let factory = crate::NP_Factory::new(schema)?.with_clock(|| NP_Date::new(1604965249484));
+    assert_eq!(factory.export_schema()?.stringify(), schema);
+    assert_eq!(factory.schema.now_dates().len(), 2);
+
+    let buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.get::<NP_Date>(&["created"])?, Some(NP_Date::new(1604965249484)));
+    assert_eq!(buffer.get::<NP_Date>(&["pair", "1"])?, Some(NP_Date::new(1604965249484)));
+    assert_eq!(buffer.get::<NP_Date>(&["log", "0"])?, None);
+
+    // compiled schemas keep the now default
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema()).with_clock(|| NP_Date::new(20));
+    assert_eq!(compiled.export_schema()?.stringify(), schema);
+    assert_eq!(compiled.empty_buffer(None).get::<NP_Date>(&["created"])?, Some(NP_Date::new(20)));
+
+    Ok(())
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_works() -> Result<(), NP_Error> {
+    use core::convert::TryFrom;
+
+    let date = NP_Date::new(1604965249484);
+    let chrono_date: chrono::DateTime<chrono::Utc> = date.into();
+    assert_eq!(chrono_date.to_rfc3339_opts(chrono::SecondsFormat::Millis, true), date.to_rfc3339());
+    assert_eq!(NP_Date::try_from(chrono_date)?, date);
+
+    assert_eq!(chrono::DateTime::<chrono::Utc>::from(NP_Date::new(u64::MAX)), chrono::DateTime::<chrono::Utc>::MAX_UTC);
+    assert!(NP_Date::try_from(chrono::DateTime::<chrono::Utc>::from_timestamp(-1, 0).unwrap()).is_err());
+
+    Ok(())
+}
@@ -21,6 +21,12 @@
 //! 
 //! `NP_Dec` values can safely be multiplied, added, devided, subtracted or compared with eachother.  It's a good idea to manually shift the `exp` values of two `NP_Dec` to match before performing any operation between them, otherwise the operation might not do what you expect.
 //! 
+//! The `checked_add`, `checked_sub`, `checked_mul` and `checked_div` methods do the exponent matching for you.  They compute the exact result, round it to the `exp` of the left value with an `NP_Rounding` mode and return `None` instead of overflowing or dividing by zero.
+//! 
+//! Floats can't hold most decimal values exactly, so strings are the safer way to move decimals around.  `NP_Dec::from_str` parses strings like `"123.45"` at a given `exp` and `to_string` writes them back out.  Schema defaults and values imported with `set_json` can be strings too, like `{"type": "decimal", "exp": 2, "default": "19.99"}`.
+//! 
+//! With the `rust_decimal` feature `NP_Dec` converts into `rust_decimal::Decimal` with `From` and back with `TryFrom`, since a `Decimal` can hold numbers too large for an `NP_Dec`.
+//! 
 //! When `NP_Dec` values are pulled out of a buffer, the `num` property is pulled from the buffer contents and the `exp` property comes from the schema.
 //! 
 //! ```
@@ -45,6 +51,19 @@
 //! ```
 //! 
 //! ```
+//! use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+//! 
+//! let price = NP_Dec::from_cents(1999); // 19.99
+//! 
+//! // 19.99 * 3 * 0.0825 tax, rounded to cents
+//! let subtotal = price.checked_mul(NP_Dec::new(3, 0), NP_Rounding::HalfUp).unwrap();
+//! let tax = subtotal.checked_mul(NP_Dec::new(825, 4), NP_Rounding::HalfUp).unwrap();
+//! let total = subtotal.checked_add(tax, NP_Rounding::HalfUp).unwrap();
+//! 
+//! assert_eq!(total.to_cents(NP_Rounding::HalfUp), Some(6492));
+//! ```
+//! 
+//! ```
 //! use no_proto::error::NP_Error;
 //! use no_proto::NP_Factory;
 //! use no_proto::pointer::dec::NP_Dec;
@@ -63,7 +82,7 @@
 //! ```
 //!
 
-use alloc::prelude::v1::Box;
+use alloc::boxed::Box;
 use crate::utils::to_signed;
 use crate::schema::{NP_Parsed_Schema};
 use alloc::vec::Vec;
@@ -71,7 +90,7 @@ use crate::utils::to_unsigned;
 use crate::json_flex::{JSMAP, NP_JSON};
 use crate::schema::{NP_TypeKeys};
 use crate::{pointer::NP_Value, error::NP_Error};
-use core::{fmt::{Debug}, hint::unreachable_unchecked};
+use core::fmt::Debug;
 
 use alloc::borrow::ToOwned;
 use super::{NP_Cursor};
@@ -92,6 +111,80 @@ pub struct NP_Dec {
 
 impl super::NP_Scalar for NP_Dec {}
 
+/// How checked `NP_Dec` operations round away digits that don't fit in the result's `exp`
+/// 
+/// Check out documentation [here](../dec/index.html).
+/// 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NP_Rounding {
+    /// Round to the nearest number, halfway values round away from zero (2.5 becomes 3, -2.5 becomes -3).  This is the default.
+    #[default]
+    HalfUp,
+    /// Round to the nearest number, halfway values round to the even neighbor (2.5 becomes 2, 3.5 becomes 4)
+    HalfEven,
+    /// Round toward zero, dropping the extra digits (2.9 becomes 2, -2.9 becomes -2)
+    Down,
+    /// Round away from zero (2.1 becomes 3, -2.1 becomes -3)
+    Up,
+    /// Round toward negative infinity (2.9 becomes 2, -2.1 becomes -3)
+    Floor,
+    /// Round toward positive infinity (2.1 becomes 3, -2.9 becomes -2)
+    Ceiling
+}
+
+/// 10 to the power of `exp` if it fits in an i128
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+/// Pick between the truncated quotient `q` and the next number away from zero
+fn round_quotient(q: i128, negative: bool, half: core::cmp::Ordering, rounding: NP_Rounding) -> i128 {
+    let away = if negative { q - 1 } else { q + 1 };
+    let go_away = match rounding {
+        NP_Rounding::Down => false,
+        NP_Rounding::Up => true,
+        NP_Rounding::Floor => negative,
+        NP_Rounding::Ceiling => !negative,
+        NP_Rounding::HalfUp => half != core::cmp::Ordering::Less,
+        NP_Rounding::HalfEven => half == core::cmp::Ordering::Greater || (half == core::cmp::Ordering::Equal && q % 2 != 0)
+    };
+    if go_away { away } else { q }
+}
+
+/// Divide `n` by `d` with the given rounding, `d` can't be zero
+fn div_round(n: i128, d: i128, rounding: NP_Rounding) -> i128 {
+    let q = n / d;
+    let r = (n % d).abs();
+    if r == 0 {
+        return q;
+    }
+    // compare the remainder to half of the divisor without overflowing
+    let half = r.cmp(&(d.abs() - r));
+    round_quotient(q, (n < 0) != (d < 0), half, rounding)
+}
+
+/// Move `num` from one `exp` to another, rounding if digits are removed
+fn rescale_num(num: i128, from_exp: u8, to_exp: u8, rounding: NP_Rounding) -> Option<i128> {
+    if to_exp >= from_exp {
+        return num.checked_mul(pow10((to_exp - from_exp) as u32)?);
+    }
+    match pow10((from_exp - to_exp) as u32) {
+        Some(d) => Some(div_round(num, d, rounding)),
+        None => { // the divisor is larger than any i128, so only the sign of `num` is left
+            let half = if num == 0 { core::cmp::Ordering::Equal } else { core::cmp::Ordering::Less };
+            if num == 0 { Some(0) } else { Some(round_quotient(0, num < 0, half, rounding)) }
+        }
+    }
+}
+
+/// Fit an i128 result back into an NP_Dec
+fn to_dec(num: i128, exp: u8) -> Option<NP_Dec> {
+    if num > i64::MAX as i128 || num < i64::MIN as i128 {
+        return None;
+    }
+    Some(NP_Dec::new(num as i64, exp))
+}
+
 impl NP_Dec {
     /// Convert an NP_Dec into a native floating point value.
     /// 
@@ -226,6 +319,283 @@ impl NP_Dec {
     pub fn export(&self) -> (i64, u8) {
         (self.num, self.exp)
     }
+
+    /// Get a copy of this NP_Dec with a different `exp`, removed digits are rounded with `rounding`.
+    /// 
+    /// Returns `None` if the value doesn't fit in an i64 at the new `exp`.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+    /// 
+    /// let my_num = NP_Dec::new(2205, 3); // value is 2.205
+    /// 
+    /// assert_eq!(my_num.rescale(2, NP_Rounding::HalfUp), Some(NP_Dec::new(221, 2)));
+    /// assert_eq!(my_num.rescale(2, NP_Rounding::HalfEven), Some(NP_Dec::new(220, 2)));
+    /// assert_eq!(my_num.rescale(2, NP_Rounding::Down), Some(NP_Dec::new(220, 2)));
+    /// assert_eq!(my_num.rescale(5, NP_Rounding::Down), Some(NP_Dec::new(220500, 5)));
+    /// 
+    /// // too large for an i64
+    /// assert_eq!(my_num.rescale(19, NP_Rounding::Down), None);
+    /// ```
+    pub fn rescale(&self, exp: u8, rounding: NP_Rounding) -> Option<NP_Dec> {
+        to_dec(rescale_num(self.num as i128, self.exp, exp, rounding)?, exp)
+    }
+
+    /// Add another NP_Dec to this one.  The result keeps the `exp` of `self`, so values pulled from a buffer stay at the `exp` of their schema.
+    /// 
+    /// Digits of `other` past the `exp` of `self` are rounded with `rounding`.  Returns `None` if the result overflows.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+    /// 
+    /// let price = NP_Dec::new(1999, 2); // 19.99
+    /// let fee = NP_Dec::new(5, 1); // 0.5
+    /// 
+    /// assert_eq!(price.checked_add(fee, NP_Rounding::HalfUp), Some(NP_Dec::new(2049, 2)));
+    /// assert_eq!(price.checked_add(NP_Dec::new(1005, 3), NP_Rounding::Down), Some(NP_Dec::new(2099, 2)));
+    /// assert_eq!(NP_Dec::new(i64::MAX, 0).checked_add(NP_Dec::new(1, 0), NP_Rounding::HalfUp), None);
+    /// ```
+    pub fn checked_add(&self, other: NP_Dec, rounding: NP_Rounding) -> Option<NP_Dec> {
+        let exp = u8::max(self.exp, other.exp);
+        let a = rescale_num(self.num as i128, self.exp, exp, rounding)?;
+        let b = rescale_num(other.num as i128, other.exp, exp, rounding)?;
+        to_dec(rescale_num(a.checked_add(b)?, exp, self.exp, rounding)?, self.exp)
+    }
+
+    /// Subtract another NP_Dec from this one.  The result keeps the `exp` of `self`.
+    /// 
+    /// Digits of `other` past the `exp` of `self` are rounded with `rounding`.  Returns `None` if the result overflows.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+    /// 
+    /// let balance = NP_Dec::new(1000, 2); // 10.00
+    /// 
+    /// assert_eq!(balance.checked_sub(NP_Dec::new(2505, 3), NP_Rounding::Floor), Some(NP_Dec::new(749, 2)));
+    /// assert_eq!(balance.checked_sub(NP_Dec::new(2505, 3), NP_Rounding::Ceiling), Some(NP_Dec::new(750, 2)));
+    /// ```
+    pub fn checked_sub(&self, other: NP_Dec, rounding: NP_Rounding) -> Option<NP_Dec> {
+        let other = NP_Dec::new(other.num.checked_neg()?, other.exp);
+        self.checked_add(other, rounding)
+    }
+
+    /// Multiply this NP_Dec with another one.  The exact product is rounded to the `exp` of `self` with `rounding`.
+    /// 
+    /// Returns `None` if the result overflows.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+    /// 
+    /// let price = NP_Dec::new(1999, 2); // 19.99
+    /// let tax_rate = NP_Dec::new(825, 4); // 0.0825
+    /// 
+    /// // 1.649175 rounds to 1.65
+    /// assert_eq!(price.checked_mul(tax_rate, NP_Rounding::HalfUp), Some(NP_Dec::new(165, 2)));
+    /// assert_eq!(price.checked_mul(tax_rate, NP_Rounding::Down), Some(NP_Dec::new(164, 2)));
+    /// ```
+    pub fn checked_mul(&self, other: NP_Dec, rounding: NP_Rounding) -> Option<NP_Dec> {
+        let product = (self.num as i128).checked_mul(other.num as i128)?;
+        let exp = self.exp.checked_add(other.exp);
+        match exp {
+            Some(exp) => to_dec(rescale_num(product, exp, self.exp, rounding)?, self.exp),
+            None => { // the product has more than 255 decimal places, drop `other`'s first
+                let product = rescale_num(product, other.exp, 0, rounding)?;
+                to_dec(product, self.exp)
+            }
+        }
+    }
+
+    /// Divide this NP_Dec by another one.  The quotient is rounded to the `exp` of `self` with `rounding`.
+    /// 
+    /// Returns `None` if `other` is zero or the result overflows.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+    /// 
+    /// let total = NP_Dec::new(1000, 2); // 10.00
+    /// 
+    /// // split three ways, 3.333... rounds to 3.33
+    /// assert_eq!(total.checked_div(NP_Dec::new(3, 0), NP_Rounding::HalfUp), Some(NP_Dec::new(333, 2)));
+    /// assert_eq!(total.checked_div(NP_Dec::new(3, 0), NP_Rounding::Up), Some(NP_Dec::new(334, 2)));
+    /// assert_eq!(total.checked_div(NP_Dec::new(25, 1), NP_Rounding::HalfUp), Some(NP_Dec::new(400, 2)));
+    /// assert_eq!(total.checked_div(NP_Dec::new(0, 2), NP_Rounding::HalfUp), None);
+    /// ```
+    pub fn checked_div(&self, other: NP_Dec, rounding: NP_Rounding) -> Option<NP_Dec> {
+        if other.num == 0 {
+            return None;
+        }
+        // (a / 10^self.exp) / (b / 10^other.exp) at self.exp is (a * 10^other.exp) / b
+        let num = (self.num as i128).checked_mul(pow10(other.exp as u32)?)?;
+        to_dec(div_round(num, other.num as i128, rounding), self.exp)
+    }
+
+    /// Convert a floating point value into an NP_Dec with the given `exp`, extra digits are rounded with `rounding`.
+    /// 
+    /// The float is first read as the shortest decimal with up to 14 places that matches it, so `2.675` is handled as 2.675 even though the closest float is a bit smaller.  Floats without a short decimal like `0.1 + 0.2` are scaled directly.  Returns `None` for NaN, infinite values and values that don't fit at `exp`.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+    /// 
+    /// assert_eq!(NP_Dec::from_f64(2.675, 2, NP_Rounding::HalfUp), Some(NP_Dec::new(268, 2)));
+    /// assert_eq!(NP_Dec::from_f64(-2.675, 2, NP_Rounding::Down), Some(NP_Dec::new(-267, 2)));
+    /// assert_eq!(NP_Dec::from_f64(core::f64::NAN, 2, NP_Rounding::HalfUp), None);
+    /// ```
+    pub fn from_f64(value: f64, exp: u8, rounding: NP_Rounding) -> Option<NP_Dec> {
+        if value.is_nan() || value.is_infinite() || value >= i64::MAX as f64 || value <= i64::MIN as f64 {
+            return None;
+        }
+        if precision(value).is_some() {
+            let dec: NP_Dec = value.into();
+            return dec.rescale(exp, rounding);
+        }
+        // no short decimal matches the float, scale it directly
+        let mut scaled = value;
+        for _x in 0..exp {
+            scaled *= 10f64;
+        }
+        if scaled.is_infinite() || scaled >= i64::MAX as f64 || scaled <= i64::MIN as f64 {
+            return None;
+        }
+        let q = scaled as i128;
+        let fraction = (scaled - q as f64).abs();
+        if fraction == 0.0 {
+            return to_dec(q, exp);
+        }
+        let half = fraction.partial_cmp(&0.5).unwrap_or(core::cmp::Ordering::Less);
+        to_dec(round_quotient(q, scaled < 0.0, half, rounding), exp)
+    }
+
+    /// Create an NP_Dec from an integer amount of cents, the NP_Dec has an `exp` of 2.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::NP_Dec;
+    /// 
+    /// assert_eq!(NP_Dec::from_cents(1999).to_float(), 19.99f64);
+    /// ```
+    pub fn from_cents(cents: i64) -> NP_Dec {
+        NP_Dec::new(cents, 2)
+    }
+
+    /// Get this NP_Dec as an integer amount of cents, extra digits are rounded with `rounding`.
+    /// 
+    /// Returns `None` if the value doesn't fit in an i64 as cents.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+    /// 
+    /// assert_eq!(NP_Dec::new(19995, 3).to_cents(NP_Rounding::HalfUp), Some(2000));
+    /// assert_eq!(NP_Dec::new(19, 0).to_cents(NP_Rounding::HalfUp), Some(1900));
+    /// ```
+    pub fn to_cents(&self, rounding: NP_Rounding) -> Option<i64> {
+        self.rescale(2, rounding).map(|x| x.num)
+    }
+
+    /// Parse a decimal string like `"123.45"` or `"-0.5"` into an NP_Dec with the given `exp`.
+    /// 
+    /// Digits past `exp` are rounded half up, use `from_str_rounded` to pick another rounding mode.  The string is read digit by digit so no precision is lost on the way, unlike going through a float.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::NP_Dec;
+    /// 
+    /// assert_eq!(NP_Dec::from_str("123.45", 2)?, NP_Dec::new(12345, 2));
+    /// assert_eq!(NP_Dec::from_str("123.45", 4)?.export(), (1234500, 4));
+    /// assert_eq!(NP_Dec::from_str("-0.125", 2)?.export(), (-13, 2));
+    /// assert_eq!(NP_Dec::from_str("7", 2)?.to_string(), "7.00");
+    /// 
+    /// assert!(NP_Dec::from_str("12.3.4", 2).is_err());
+    /// assert!(NP_Dec::from_str("99999999999999999999", 0).is_err());
+    /// # Ok::<(), no_proto::error::NP_Error>(()) 
+    /// ```
+    pub fn from_str(value: &str, exp: u8) -> Result<NP_Dec, NP_Error> {
+        NP_Dec::from_str_rounded(value, exp, NP_Rounding::default())
+    }
+
+    /// Parse a decimal string into an NP_Dec with the given `exp`, digits past `exp` are rounded with `rounding`.
+    /// 
+    /// ```
+    /// use no_proto::pointer::dec::{NP_Dec, NP_Rounding};
+    /// 
+    /// assert_eq!(NP_Dec::from_str_rounded("2.345", 2, NP_Rounding::HalfEven)?.export(), (234, 2));
+    /// assert_eq!(NP_Dec::from_str_rounded("-2.341", 2, NP_Rounding::Floor)?.export(), (-235, 2));
+    /// # Ok::<(), no_proto::error::NP_Error>(()) 
+    /// ```
+    pub fn from_str_rounded(value: &str, exp: u8, rounding: NP_Rounding) -> Result<NP_Dec, NP_Error> {
+        let parse_error = || NP_Error::Parse("Decimal strings must look like '123.45', found: ".to_owned() + value);
+
+        let (negative, unsigned) = match value.as_bytes().first() {
+            Some(b'-') => (true, &value[1..]),
+            Some(b'+') => (false, &value[1..]),
+            _ => (false, value)
+        };
+        let (whole, fraction) = match unsigned.find('.') {
+            Some(x) => (&unsigned[..x], &unsigned[(x + 1)..]),
+            None => (unsigned, "")
+        };
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(parse_error());
+        }
+        if !whole.bytes().chain(fraction.bytes()).all(|x| x.is_ascii_digit()) {
+            return Err(parse_error());
+        }
+
+        let kept = usize::min(fraction.len(), exp as usize);
+        let mut num: i128 = 0;
+        for digit in whole.bytes().chain(fraction[..kept].bytes()) {
+            num = num.checked_mul(10).and_then(|x| x.checked_add((digit - b'0') as i128)).ok_or_else(parse_error)?;
+        }
+
+        let num = if negative { -num } else { num };
+
+        // fill missing decimal places, round away the extra ones
+        let dropped = &fraction.as_bytes()[kept..];
+        let num = if kept < exp as usize {
+            rescale_num(num, kept as u8, exp, rounding)
+        } else if dropped.iter().all(|x| *x == b'0') {
+            Some(num)
+        } else {
+            let half = match dropped[0] {
+                x if x > b'5' => core::cmp::Ordering::Greater,
+                x if x < b'5' => core::cmp::Ordering::Less,
+                _ => if dropped[1..].iter().any(|x| *x != b'0') { core::cmp::Ordering::Greater } else { core::cmp::Ordering::Equal }
+            };
+            Some(round_quotient(num, negative, half, rounding))
+        };
+
+        num.and_then(|x| to_dec(x, exp)).ok_or_else(|| NP_Error::InvalidValue("Decimal string doesn't fit in an i64 at exp ".to_owned() + &exp.to_string() + ": " + value))
+    }
+}
+
+/// Write an NP_Dec as a decimal string with all of it's `exp` places, `to_string` uses this.
+/// 
+/// ```
+/// use no_proto::pointer::dec::NP_Dec;
+/// 
+/// assert_eq!(NP_Dec::new(12345, 2).to_string(), "123.45");
+/// assert_eq!(NP_Dec::new(-5, 3).to_string(), "-0.005");
+/// assert_eq!(NP_Dec::new(150, 2).to_string(), "1.50");
+/// assert_eq!(NP_Dec::new(42, 0).to_string(), "42");
+/// ```
+impl core::fmt::Display for NP_Dec {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let digits = (self.num as i128).abs().to_string();
+        if self.num < 0 {
+            f.write_str("-")?;
+        }
+        let exp = self.exp as usize;
+        if exp == 0 {
+            f.write_str(&digits)
+        } else if digits.len() > exp {
+            f.write_str(&digits[..(digits.len() - exp)])?;
+            f.write_str(".")?;
+            f.write_str(&digits[(digits.len() - exp)..])
+        } else {
+            f.write_str("0.")?;
+            for _x in digits.len()..exp {
+                f.write_str("0")?;
+            }
+            f.write_str(&digits)
+        }
+    }
 }
 
 /// Check if two NP_Dec are equal or not equal
@@ -363,10 +733,10 @@ impl core::cmp::PartialOrd for NP_Dec {
 /// 
 /// assert_eq!(y, 101i32);
 /// ```
-impl Into<i32> for NP_Dec {
-    fn into(self) -> i32 { 
-        let mut change_value = self.num;
-        let mut loop_val = self.exp;
+impl From<NP_Dec> for i32 {
+    fn from(value: NP_Dec) -> i32 { 
+        let mut change_value = value.num;
+        let mut loop_val = value.exp;
         while loop_val > 0 {
             change_value /= 10;
             loop_val -= 1;
@@ -384,9 +754,9 @@ impl Into<i32> for NP_Dec {
 /// 
 /// assert_eq!(y.num as i32, x);
 /// ```
-impl Into<NP_Dec> for i32 {
-    fn into(self) -> NP_Dec { 
-        NP_Dec::new(self as i64, 0)
+impl From<i32> for NP_Dec {
+    fn from(value: i32) -> NP_Dec { 
+        NP_Dec::new(value as i64, 0)
     }
 }
 
@@ -400,10 +770,10 @@ impl Into<NP_Dec> for i32 {
 /// 
 /// assert_eq!(y, 101i64);
 /// ```
-impl Into<i64> for NP_Dec {
-    fn into(self) -> i64 { 
-        let mut change_value = self.num;
-        let mut loop_val = self.exp;
+impl From<NP_Dec> for i64 {
+    fn from(value: NP_Dec) -> i64 { 
+        let mut change_value = value.num;
+        let mut loop_val = value.exp;
         while loop_val > 0 {
             change_value /= 10;
             loop_val -= 1;
@@ -421,9 +791,9 @@ impl Into<i64> for NP_Dec {
 /// 
 /// assert_eq!(y.num, x);
 /// ```
-impl Into<NP_Dec> for i64 {
-    fn into(self) -> NP_Dec { 
-        NP_Dec::new(self, 0)
+impl From<i64> for NP_Dec {
+    fn from(value: i64) -> NP_Dec { 
+        NP_Dec::new(value, 0)
     }
 }
 
@@ -446,7 +816,7 @@ fn round_f32(n: f32) -> f32 {
 }
 
 fn round(n: f64, precision: u32) -> f64 {
-    round_f64(n * 10_u32.pow(precision) as f64) / 10_i32.pow(precision) as f64
+    round_f64(n * 10_u64.pow(precision) as f64) / 10_i64.pow(precision) as f64
 }
 
 fn precision(x: f64) -> Option<u32> {
@@ -459,7 +829,7 @@ fn precision(x: f64) -> Option<u32> {
 }
 
 fn round32(n: f32, precision: u32) -> f32 {
-    round_f32(n * 10_u32.pow(precision) as f32) / 10_i32.pow(precision) as f32
+    round_f32(n * 10_u64.pow(precision) as f32) / 10_i64.pow(precision) as f32
 }
 
 fn precision32(x: f32) -> Option<u32> {
@@ -480,9 +850,9 @@ fn precision32(x: f32) -> Option<u32> {
 /// 
 /// assert_eq!(y, x.to_float());
 /// ```
-impl Into<f64> for NP_Dec {
-    fn into(self) -> f64 { 
-        self.to_float()
+impl From<NP_Dec> for f64 {
+    fn from(value: NP_Dec) -> f64 { 
+        value.to_float()
     }
 }
 
@@ -495,12 +865,12 @@ impl Into<f64> for NP_Dec {
 /// 
 /// assert_eq!(y.to_float(), x);
 /// ```
-impl Into<NP_Dec> for f64 {
-    fn into(self) -> NP_Dec { 
-        match precision(self) {
+impl From<f64> for NP_Dec {
+    fn from(value: f64) -> NP_Dec { 
+        match precision(value) {
             Some(x) => {
                 let max_decimal_places = u32::min(x, 18);
-                let mut new_self = self.clone();
+                let mut new_self = value.clone();
                 let mut loop_exp = max_decimal_places;
                 while loop_exp > 0 {
                     new_self *= 10f64;
@@ -510,7 +880,7 @@ impl Into<NP_Dec> for f64 {
                 return NP_Dec::new(value, max_decimal_places as u8);
             },
             None => { // this should be impossible, but just incase
-                let value = round_f64(self) as i64;
+                let value = round_f64(value) as i64;
                 return NP_Dec::new(value, 0);
             }
         }
@@ -526,9 +896,9 @@ impl Into<NP_Dec> for f64 {
 /// 
 /// assert_eq!(y, x.to_float() as f32);
 /// ```
-impl Into<f32> for NP_Dec {
-    fn into(self) -> f32 { 
-        self.to_float() as f32
+impl From<NP_Dec> for f32 {
+    fn from(value: NP_Dec) -> f32 { 
+        value.to_float() as f32
     }
 }
 
@@ -541,12 +911,12 @@ impl Into<f32> for NP_Dec {
 /// 
 /// assert_eq!(y.to_float() as f32, x);
 /// ```
-impl Into<NP_Dec> for f32 {
-    fn into(self) -> NP_Dec { 
-        match precision32(self) {
+impl From<f32> for NP_Dec {
+    fn from(value: f32) -> NP_Dec { 
+        match precision32(value) {
             Some(x) => {
                 let max_decimal_places = u32::min(x, 18);
-                let mut new_self = self.clone();
+                let mut new_self = value.clone();
                 let mut loop_exp = max_decimal_places;
                 while loop_exp > 0 {
                     new_self *= 10f32;
@@ -556,13 +926,37 @@ impl Into<NP_Dec> for f32 {
                 return NP_Dec::new(value, max_decimal_places as u8);
             },
             None => { // this should be impossible, but just incase
-                let value = round_f32(self) as i64;
+                let value = round_f32(value) as i64;
                 return NP_Dec::new(value, 0);
             }
         }
     }
 }
 
+/// Exps past the 28 decimal places `Decimal` holds are rounded to 28 places with `NP_Rounding::HalfUp`
+#[cfg(feature = "rust_decimal")]
+impl From<NP_Dec> for rust_decimal::Decimal {
+    fn from(value: NP_Dec) -> Self {
+        let max_exp = rust_decimal::Decimal::MAX_SCALE as u8;
+        if value.exp > max_exp {
+            let num = rescale_num(value.num as i128, value.exp, max_exp, NP_Rounding::HalfUp).unwrap_or(0);
+            return rust_decimal::Decimal::from_i128_with_scale(num, max_exp as u32);
+        }
+        rust_decimal::Decimal::from_i128_with_scale(value.num as i128, value.exp as u32)
+    }
+}
+
+/// Trailing zeros are dropped if that's the only way the number fits in an i64
+#[cfg(feature = "rust_decimal")]
+impl core::convert::TryFrom<rust_decimal::Decimal> for NP_Dec {
+    type Error = NP_Error;
+
+    fn try_from(value: rust_decimal::Decimal) -> Result<Self, NP_Error> {
+        let fit = |x: rust_decimal::Decimal| to_dec(x.mantissa(), x.scale() as u8);
+        fit(value).or_else(|| fit(value.normalize())).ok_or_else(|| NP_Error::OutOfBounds("Decimal is too large for NP_Dec: ".to_owned() + &value.to_string()))
+    }
+}
+
 impl core::ops::DivAssign for NP_Dec { // a /= b
     fn div_assign(&mut self, other: NP_Dec) { 
         if self.exp != other.exp {
@@ -682,7 +1076,12 @@ impl<'value> NP_Value<'value> for NP_Dec {
     
                 if let Some(d) = default {
                     let value = NP_Dec::new(d.num.clone(), exp.clone());
-                    schema_json.insert("default".to_owned(), NP_JSON::Float(value.into()));
+                    // defaults that don't survive a trip through a float are exported as strings
+                    if NP_Dec::from_f64(value.into(), *exp, NP_Rounding::HalfUp).map(|x| x.num) == Some(value.num) {
+                        schema_json.insert("default".to_owned(), NP_JSON::Float(value.into()));
+                    } else {
+                        schema_json.insert("default".to_owned(), NP_JSON::String(value.to_string()));
+                    }
                 }
         
                 Ok(NP_JSON::Dictionary(schema_json))
@@ -731,12 +1130,8 @@ impl<'value> NP_Value<'value> for NP_Dec {
             // convert to unsigned
             bytes[0] = to_unsigned(bytes[0]);
 
-            let write_bytes = memory.write_bytes();
-
             // overwrite existing values in buffer
-            for x in 0..bytes.len() {
-                write_bytes[value_address + x] = bytes[x];
-            }
+            memory.get_bytes_mut(value_address, bytes.len())?.copy_from_slice(&bytes);
 
         } else { // new value
 
@@ -746,7 +1141,7 @@ impl<'value> NP_Value<'value> for NP_Dec {
             be_bytes[0] = to_unsigned(be_bytes[0]);
 
             value_address = memory.malloc_borrow(&be_bytes)?;
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value(memory).set_addr_value(value_address as u32);
 
         }
 
@@ -849,30 +1244,36 @@ impl<'value> NP_Value<'value> for NP_Dec {
         match json_schema["exp"] {
             NP_JSON::Integer(x) => {
                 if x > 255 || x < 0 {
-                    return Err(NP_Error::new("Decimal 'exp' property must be between 0 and 255!"))
+                    return Err(NP_Error::Schema("Decimal 'exp' property must be between 0 and 255!".into()))
                 }
                 exp = x as u8;
                 schema_data.push(x as u8);
             },
             _ => {
-                return Err(NP_Error::new("Decimal type requires 'exp' property!"))
+                return Err(NP_Error::Schema("Decimal type requires 'exp' property!".into()))
             }
         }
 
-        let mult = 10i64.pow(exp as u32);
+        let default_error = || NP_Error::Schema("Decimal 'default' property doesn't fit at the 'exp' of the schema!".into());
 
-        let default = match json_schema["default"] {
+        let default = match &json_schema["default"] {
             NP_JSON::Float(x) => {
+                let value = NP_Dec::from_f64(*x, exp, NP_Rounding::HalfUp).ok_or_else(default_error)?;
                 schema_data.push(1);
-                let value = x * (mult as f64);
-                schema_data.extend((value as i64).to_be_bytes().to_vec());
-                Some(NP_Dec::new(value as i64, exp))
+                schema_data.extend(value.num.to_be_bytes().to_vec());
+                Some(value)
             },
             NP_JSON::Integer(x) => {
+                let value = NP_Dec::new(*x, 0).rescale(exp, NP_Rounding::HalfUp).ok_or_else(default_error)?;
                 schema_data.push(1);
-                let value = x * (mult as i64);
-                schema_data.extend((value as i64).to_be_bytes().to_vec());
-                Some(NP_Dec::new(value as i64, exp))
+                schema_data.extend(value.num.to_be_bytes().to_vec());
+                Some(value)
+            },
+            NP_JSON::String(x) => {
+                let value = NP_Dec::from_str(x, exp)?;
+                schema_data.push(1);
+                schema_data.extend(value.num.to_be_bytes().to_vec());
+                Some(value)
             },
             _ => {
                 schema_data.push(0);
@@ -953,4 +1354,123 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn checked_arithmetic_works() -> Result<(), NP_Error> {
+    let schema = "{\"type\":\"decimal\",\"exp\": 2}";
+    let factory = crate::NP_Factory::new(schema)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&[], NP_Dec::new(1050, 2))?;
+
+    // results stay at the exp of the schema
+    let value = buffer.get::<NP_Dec>(&[])?.unwrap();
+    let value = value.checked_mul(NP_Dec::new(15, 1), NP_Rounding::HalfUp).unwrap();
+    assert_eq!(value.export(), (1575, 2));
+    let value = value.checked_div(NP_Dec::new(4, 0), NP_Rounding::HalfEven).unwrap();
+    assert_eq!(value.export(), (394, 2));
+    let value = value.checked_sub(NP_Dec::new(4005, 3), NP_Rounding::HalfEven).unwrap();
+    assert_eq!(value.export(), (-6, 2));
+
+    // every rounding mode on both signs
+    let modes = [NP_Rounding::HalfUp, NP_Rounding::HalfEven, NP_Rounding::Down, NP_Rounding::Up, NP_Rounding::Floor, NP_Rounding::Ceiling];
+    let expected: [(i64, [i64; 6]); 6] = [
+        ( 25, [ 3,  2,  2,  3,  2,  3]),
+        (-25, [-3, -2, -2, -3, -3, -2]),
+        ( 35, [ 4,  4,  3,  4,  3,  4]),
+        ( 21, [ 2,  2,  2,  3,  2,  3]),
+        (-29, [-3, -3, -2, -3, -3, -2]),
+        ( 20, [ 2,  2,  2,  2,  2,  2])
+    ];
+    for (num, results) in expected.iter() {
+        for (mode, result) in modes.iter().zip(results.iter()) {
+            assert_eq!(NP_Dec::new(*num, 1).rescale(0, *mode).map(|x| x.num), Some(*result));
+        }
+    }
+
+    // huge exps and overflows don't panic
+    assert_eq!(NP_Dec::new(5, 255).rescale(0, NP_Rounding::Up).map(|x| x.num), Some(1));
+    assert_eq!(NP_Dec::new(-5, 255).rescale(0, NP_Rounding::HalfUp).map(|x| x.num), Some(0));
+    assert_eq!(NP_Dec::new(1, 200).checked_mul(NP_Dec::new(1, 200), NP_Rounding::Up).map(|x| x.export()), Some((1, 200)));
+    assert_eq!(NP_Dec::new(1, 0).checked_div(NP_Dec::new(1, 100), NP_Rounding::Up), None);
+    assert_eq!(NP_Dec::new(i64::MIN, 0).checked_sub(NP_Dec::new(1, 0), NP_Rounding::Up), None);
+    assert_eq!(NP_Dec::new(1, 0).checked_sub(NP_Dec::new(i64::MIN, 0), NP_Rounding::Up), None);
+
+    // conversions
+    assert_eq!(NP_Dec::from_f64(0.1 + 0.2, 2, NP_Rounding::HalfUp), Some(NP_Dec::new(30, 2)));
+    assert_eq!(NP_Dec::from_f64(1e300, 0, NP_Rounding::HalfUp), None);
+    assert_eq!(NP_Dec::new(-1999, 2).to_cents(NP_Rounding::Down), Some(-1999));
+    let whole: i64 = NP_Dec::new(1999, 2).into();
+    assert_eq!(whole, 19);
+
+    Ok(())
+}
+
+#[test]
+fn string_values_work() -> Result<(), NP_Error> {
+    // float defaults are rounded, not truncated
+    let factory = crate::NP_Factory::new(r#"{"type":"decimal","exp":2,"default":0.29}"#)?;
+    assert_eq!(factory.empty_buffer(None).get::<NP_Dec>(&[])?.unwrap().export(), (29, 2));
+
+    // string defaults keep every digit and export as strings when a float can't hold them
+    let schema = r#"{"type":"decimal","exp":4,"default":"922337203685477.5807"}"#;
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(factory.empty_buffer(None).get::<NP_Dec>(&[])?.unwrap().export(), (i64::MAX, 4));
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    let factory = crate::NP_Factory::new(r#"{"type":"decimal","exp":2,"default":"19.99"}"#)?;
+    assert_eq!(r#"{"type":"decimal","exp":2,"default":19.99}"#, factory.schema.to_json()?.stringify());
+    assert!(crate::NP_Factory::new(r#"{"type":"decimal","exp":2,"default":"19,99"}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type":"decimal","exp":18,"default":100}"#).is_err());
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set_json(&[], &NP_JSON::String("0.005".into()))?;
+    assert_eq!(buffer.get::<NP_Dec>(&[])?.unwrap().to_string(), "0.01");
+    assert!(buffer.set_json(&[], &NP_JSON::String("abc".into())).is_err());
+
+    // parsing edge cases
+    assert_eq!(NP_Dec::from_str(".5", 1)?.export(), (5, 1));
+    assert_eq!(NP_Dec::from_str("5.", 1)?.export(), (50, 1));
+    assert_eq!(NP_Dec::from_str("+1.2000000000000000000000000000000000000000001", 1)?.export(), (12, 1));
+    assert_eq!(NP_Dec::from_str_rounded("-0.001", 2, NP_Rounding::Floor)?.export(), (-1, 2));
+    assert_eq!(NP_Dec::from_str_rounded("0.0050", 2, NP_Rounding::HalfEven)?.export(), (0, 2));
+    assert_eq!(NP_Dec::from_str_rounded("0.0051", 2, NP_Rounding::HalfEven)?.export(), (1, 2));
+    assert_eq!(NP_Dec::from_str("-9223372036854775808", 0)?.num, i64::MIN);
+    for bad in ["", "-", ".", "1e5", " 1", "1-", "--1"].iter() {
+        assert!(NP_Dec::from_str(bad, 2).is_err());
+    }
+    assert_eq!(NP_Dec::new(i64::MIN, 3).to_string(), "-9223372036854775.808");
+
+    Ok(())
+}
+
+#[cfg(feature = "rust_decimal")]
+#[test]
+fn rust_decimal_works() -> Result<(), NP_Error> {
+    use core::convert::TryFrom;
+    use core::str::FromStr;
+    use rust_decimal::Decimal;
+
+    // values round trip with their exp
+    for (num, exp) in [(50283, 2), (-1, 0), (0, 4), (i64::MAX, 4), (i64::MIN, 18), (12, 28)].iter() {
+        let dec = NP_Dec::new(*num, *exp);
+        let decimal = Decimal::from(dec);
+        assert_eq!(decimal.to_string(), dec.to_string());
+        assert_eq!(NP_Dec::try_from(decimal)?.export(), (*num, *exp));
+    }
+
+    // values pulled from a buffer convert at the exp of the schema
+    let factory = crate::NP_Factory::new(r#"{"type":"decimal","exp":2}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&[], NP_Dec::try_from(Decimal::from_str("19.99").unwrap())?)?;
+    assert_eq!(Decimal::from(buffer.get::<NP_Dec>(&[])?.unwrap()), Decimal::from_str("19.99").unwrap());
+
+    // exps past 28 places are rounded
+    assert_eq!(Decimal::from(NP_Dec::new(15, 29)), Decimal::from_str("0.0000000000000000000000000002").unwrap());
+    assert_eq!(Decimal::from(NP_Dec::new(-5, 200)), Decimal::ZERO);
+
+    // trailing zeros are dropped to fit, other decimals too large fail
+    assert_eq!(NP_Dec::try_from(Decimal::from_str("1000000000000000000.000").unwrap())?.export(), (1_000_000_000_000_000_000, 0));
+    assert!(matches!(NP_Dec::try_from(Decimal::MAX), Err(NP_Error::OutOfBounds(_))));
+
+    Ok(())
+}
@@ -19,6 +19,33 @@
 //! # Ok::<(), NP_Error>(()) 
 //! ```
 //! 
+//! Geo schemas with `"z_order": true` interleave the bits of the latitude and longitude in sortable buffers, so nearby coordinates sort near each other and an `NP_GeoBox` can be looked up as a range of keys.  Without it coordinates sort by latitude then longitude.
+//! 
+//! Schemas with `"type": "geo"` and `"size": "auto"` save each value in the smallest size that holds it's coordinates, rounded to the nearest step of that size.  `NP_Geo::size` of the values you get back is the size that was picked.
+//! 
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::pointer::geo::NP_Geo;
+//! 
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "geo",
+//!    "size": "auto"
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&[], NP_Geo::new(0, 45.5, -122.71))?;
+//! assert_eq!(new_buffer.get::<NP_Geo>(&[])?.unwrap().size, 4);
+//! 
+//! new_buffer.set(&[], NP_Geo::new(0, 45.509616, -122.714625))?;
+//! assert_eq!(new_buffer.get::<NP_Geo>(&[])?.unwrap().size, 8);
+//! assert_eq!("{\"lat\":45.509616,\"lng\":-122.714625}", new_buffer.get::<NP_Geo>(&[])?.unwrap().into_json().stringify());
+//!
+//! # Ok::<(), NP_Error>(()) 
+//! ```
+//! 
+//! `NP_Geo::distance_meters` gives the distance between two coordinates and `NP_Buffer::geo_within` finds the items of a list that are close to a point without copying the list out of the buffer.
+//! 
 
 use crate::schema::{NP_Parsed_Schema};
 use alloc::vec::Vec;
@@ -27,7 +54,7 @@ use crate::utils::to_unsigned;
 use crate::json_flex::{JSMAP, NP_JSON};
 use crate::schema::{NP_Schema, NP_TypeKeys};
 use crate::{pointer::NP_Value, error::NP_Error};
-use core::{fmt::{Debug}, hint::unreachable_unchecked};
+use core::fmt::Debug;
 use core::convert::TryInto;
 
 use alloc::boxed::Box;
@@ -122,7 +149,7 @@ impl<'value> NP_Value<'value> for NP_Geo_Bytes {
     fn schema_to_json(schema: &Vec<NP_Parsed_Schema>, address: usize)-> Result<NP_JSON, NP_Error> { NP_Geo::schema_to_json(schema, address)}
 
     fn set_value<'set>(_cursor: NP_Cursor, _memory: &'set NP_Memory, _value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
-        Err(NP_Error::new("Can't set value with NP_Geo_Bytes, use NP_Geo instead!"))
+        Err(NP_Error::SchemaMismatch("Can't set value with NP_Geo_Bytes, use NP_Geo instead!".into()))
     }
     fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
         NP_Geo::to_json(cursor, memory)
@@ -140,7 +167,7 @@ impl<'value> NP_Value<'value> for NP_Geo_Bytes {
                 },
                 _ => 0
             };
-            Ok(size as usize)
+            Ok(value_size(c_value.get_addr_value() as usize, size, memory))
         }
     }
 
@@ -162,6 +189,13 @@ impl<'value> NP_Value<'value> for NP_Geo_Bytes {
             _ => 0
         };
 
+        // auto sized values start with their size
+        let (size, value_addr) = if size == 0 {
+            (memory.get_1_byte(value_addr).unwrap_or(0), value_addr + 1)
+        } else {
+            (size, value_addr)
+        };
+
         Ok(Some(match size {
             16 => {
                 let bytes_lat: [u8; 8] = *memory.get_8_bytes(value_addr).unwrap_or(&[0; 8]);
@@ -182,7 +216,7 @@ impl<'value> NP_Value<'value> for NP_Geo_Bytes {
                 NP_Geo_Bytes { lat: bytes_lat.to_vec(), lng: bytes_lon.to_vec(), size: 4 }
             },
             _ => {
-                unreachable!();
+                return Err(NP_Error::SchemaMismatch("Geo values are 4, 8 or 16 bytes!".into()));
             }
         }))
     }
@@ -202,7 +236,7 @@ impl<'value> NP_Value<'value> for NP_Geo_Bytes {
 /// 
 /// Check out documentation [here](../geo/index.html).
 /// 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct NP_Geo {
     /// The size of this geographic coordinate.  4, 8 or 16
     pub size: u8,
@@ -240,6 +274,60 @@ impl NP_Geo {
         NP_JSON::Dictionary(result_json)
      }
 
+     /// Great circle distance to another coordinate in meters, using the haversine formula on a sphere with the mean radius of the earth.
+     /// 
+     /// The earth isn't quite a sphere so results can be off by up to about 0.5%, that's plenty for finding things nearby.
+     /// 
+     /// ```
+     /// use no_proto::pointer::geo::NP_Geo;
+     /// 
+     /// let portland = NP_Geo::new(16, 45.5152, -122.6784);
+     /// let seattle = NP_Geo::new(16, 47.6062, -122.3321);
+     /// 
+     /// let km = portland.distance_meters(&seattle) / 1000.0;
+     /// assert!(km > 233.0 && km < 235.0);
+     /// assert_eq!(portland.distance_meters(&portland), 0.0);
+     /// ```
+     pub fn distance_meters(&self, other: &NP_Geo) -> f64 {
+        let lat1 = self.lat * DEGREES_TO_RADIANS;
+        let lat2 = other.lat * DEGREES_TO_RADIANS;
+        let half_lat = (lat2 - lat1) / 2.0;
+        let half_lng = (other.lng - self.lng) * DEGREES_TO_RADIANS / 2.0;
+
+        let a = sin(half_lat) * sin(half_lat) + cos(lat1) * cos(lat2) * sin(half_lng) * sin(half_lng);
+        let a = a.clamp(0.0, 1.0);
+
+        2.0 * EARTH_RADIUS_METERS * atan2(sqrt(a), sqrt(1.0 - a))
+     }
+
+     /// Get the Z-order key of this coordinate, the bits of the latitude and longitude bytes interleaved into one value.
+     /// 
+     /// Keys of nearby coordinates tend to be close to each other, geo values with `"z_order": true` in the schema use this encoding in sortable buffers.  The key has the same size as the coordinate.
+     /// 
+     /// ```
+     /// use no_proto::pointer::geo::NP_Geo;
+     /// 
+     /// let point = NP_Geo::new(8, 45.5231, -122.6765);
+     /// let key = point.z_order().unwrap();
+     /// 
+     /// assert_eq!(key.len(), 8);
+     /// assert_eq!(NP_Geo::from_z_order(&key), Some(NP_Geo::new(8, 45.5231, -122.6765)));
+     /// ```
+     pub fn z_order(&self) -> Option<Vec<u8>> {
+        self.get_bytes().map(|bytes| z_interleave(&bytes.lat, &bytes.lng))
+     }
+
+     /// Read a coordinate back out of a key made by `z_order`, the size of the key is the size of the coordinate
+     pub fn from_z_order(key: &[u8]) -> Option<NP_Geo> {
+        match key.len() {
+            4 | 8 | 16 => {
+                let (lat, lng) = z_deinterleave(key);
+                Some(NP_Geo_Bytes { size: key.len() as u8, lat, lng }.into_geo())
+            },
+            _ => None
+        }
+     }
+
      /// Get the bytes that represent this geographic coordinate
      pub fn get_bytes(&self) -> Option<NP_Geo_Bytes> {
         if self.size == 0 {
@@ -289,6 +377,282 @@ impl NP_Geo {
      }
 }
 
+/// Round to the nearest whole number
+fn round_to_int(x: f64) -> i64 {
+    if x < 0.0 { (x - 0.5) as i64 } else { (x + 0.5) as i64 }
+}
+
+/// The smallest geo size that holds both coordinates without losing digits
+pub(crate) fn auto_size(lat: f64, lng: f64) -> u8 {
+    for size in [4u8, 8].iter() {
+        let dev = NP_Geo::get_deviser(*size as i64);
+        // a millionth of a step covers float error in the scaling
+        let fits = |x: f64| {
+            let scaled = x * dev;
+            (scaled - round_to_int(scaled) as f64).abs() < 0.000001
+        };
+        if fits(lat) && fits(lng) {
+            return *size;
+        }
+    }
+    16
+}
+
+/// Bytes of a coordinate at a size, rounded to the nearest step instead of cut off like `get_bytes`
+pub(crate) fn rounded_bytes(size: u8, lat: f64, lng: f64) -> NP_Geo_Bytes {
+    let dev = NP_Geo::get_deviser(size as i64);
+    let (lat, lng) = (round_to_int(lat * dev), round_to_int(lng * dev));
+    let (mut lat, mut lng) = match size {
+        16 => (lat.to_be_bytes().to_vec(), lng.to_be_bytes().to_vec()),
+        8 => ((lat as i32).to_be_bytes().to_vec(), (lng as i32).to_be_bytes().to_vec()),
+        _ => ((lat as i16).to_be_bytes().to_vec(), (lng as i16).to_be_bytes().to_vec())
+    };
+    // convert to unsigned bytes
+    lat[0] = to_unsigned(lat[0]);
+    lng[0] = to_unsigned(lng[0]);
+    NP_Geo_Bytes { size, lat, lng }
+}
+
+/// Bytes used by a geo value, auto sized values have their size in front
+fn value_size(value_addr: usize, schema_size: u8, memory: &NP_Memory) -> usize {
+    if schema_size == 0 {
+        1 + memory.get_1_byte(value_addr).unwrap_or(0) as usize
+    } else {
+        schema_size as usize
+    }
+}
+
+/// Save a value of an auto sized schema as it's size followed by the coordinate bytes
+fn set_auto_value(cursor: NP_Cursor, memory: &NP_Memory, value: &NP_Geo) -> Result<NP_Cursor, NP_Error> {
+    let size = auto_size(value.lat, value.lng);
+    let bytes = rounded_bytes(size, value.lat, value.lng);
+    let mut value_bytes = alloc::vec![size];
+    value_bytes.extend_from_slice(&bytes.lat);
+    value_bytes.extend_from_slice(&bytes.lng);
+
+    let c_value = cursor.get_value(memory);
+    let value_address = c_value.get_addr_value() as usize;
+
+    if value_address != 0 && memory.get_1_byte(value_address) == Some(size) { // same size, replace in place
+        memory.get_bytes_mut(value_address, value_bytes.len())?.copy_from_slice(&value_bytes);
+    } else { // new value or a different size, the old bytes are removed by compaction
+        let value_address = memory.malloc(value_bytes)?;
+        cursor.get_value(memory).set_addr_value(value_address as u32);
+    }
+
+    Ok(cursor)
+}
+
+/// Interleave the bits of two equal length values, starting with the first bit of `lat`
+pub(crate) fn z_interleave(lat: &[u8], lng: &[u8]) -> Vec<u8> {
+    let mut key = alloc::vec![0u8; lat.len() + lng.len()];
+    for bit in 0..(lat.len() * 8) {
+        let lat_bit = (lat[bit / 8] >> (7 - bit % 8)) & 1;
+        let lng_bit = (lng[bit / 8] >> (7 - bit % 8)) & 1;
+        key[(bit * 2) / 8] |= lat_bit << (7 - (bit * 2) % 8);
+        key[(bit * 2 + 1) / 8] |= lng_bit << (7 - (bit * 2 + 1) % 8);
+    }
+    key
+}
+
+/// Split bits interleaved by `z_interleave` back into the two values
+pub(crate) fn z_deinterleave(key: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let half = key.len() / 2;
+    let mut lat = alloc::vec![0u8; half];
+    let mut lng = alloc::vec![0u8; half];
+    for bit in 0..(half * 8) {
+        let lat_bit = (key[(bit * 2) / 8] >> (7 - (bit * 2) % 8)) & 1;
+        let lng_bit = (key[(bit * 2 + 1) / 8] >> (7 - (bit * 2 + 1) % 8)) & 1;
+        lat[bit / 8] |= lat_bit << (7 - bit % 8);
+        lng[bit / 8] |= lng_bit << (7 - bit % 8);
+    }
+    (lat, lng)
+}
+
+/// A latitude / longitude rectangle between two corners
+/// 
+/// The box covers every coordinate with a latitude and longitude between the ones of it's corners, edges included.  Boxes don't wrap around the 180th meridian, split boxes that cross it into two.
+/// 
+/// ```
+/// use no_proto::pointer::geo::{NP_Geo, NP_GeoBox};
+/// 
+/// // corners can be given in any order
+/// let portland = NP_GeoBox::new(NP_Geo::new(8, 45.65, -122.47), NP_Geo::new(8, 45.43, -122.84));
+/// let downtown = NP_GeoBox::new(NP_Geo::new(8, 45.50, -122.69), NP_Geo::new(8, 45.53, -122.66));
+/// 
+/// assert!(portland.contains(&NP_Geo::new(8, 45.5231, -122.6765)));
+/// assert!(!portland.contains(&NP_Geo::new(8, 47.6062, -122.3321)));
+/// assert!(portland.contains_box(&downtown));
+/// assert_eq!(portland.intersection(&downtown), Some(downtown.clone()));
+/// 
+/// let east = NP_GeoBox::new(NP_Geo::new(8, 45.0, -122.5), NP_Geo::new(8, 46.0, -122.0));
+/// assert!(portland.intersects(&east));
+/// assert_eq!(portland.intersection(&east), Some(NP_GeoBox::new(NP_Geo::new(8, 45.43, -122.5), NP_Geo::new(8, 45.65, -122.47))));
+/// assert_eq!(downtown.intersection(&east), None);
+/// ```
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub struct NP_GeoBox {
+    /// The corner with the lowest latitude and longitude
+    pub south_west: NP_Geo,
+    /// The corner with the highest latitude and longitude
+    pub north_east: NP_Geo
+}
+
+impl NP_GeoBox {
+
+    /// Create a box from two opposite corners, the size of the first corner is used for both
+    pub fn new(corner: NP_Geo, other_corner: NP_Geo) -> Self {
+        NP_GeoBox {
+            south_west: NP_Geo::new(corner.size, corner.lat.min(other_corner.lat), corner.lng.min(other_corner.lng)),
+            north_east: NP_Geo::new(corner.size, corner.lat.max(other_corner.lat), corner.lng.max(other_corner.lng))
+        }
+    }
+
+    /// Is the coordinate inside this box or on it's edge?
+    pub fn contains(&self, point: &NP_Geo) -> bool {
+        point.lat >= self.south_west.lat && point.lat <= self.north_east.lat &&
+        point.lng >= self.south_west.lng && point.lng <= self.north_east.lng
+    }
+
+    /// Is the other box completely inside this one?
+    pub fn contains_box(&self, other: &NP_GeoBox) -> bool {
+        self.contains(&other.south_west) && self.contains(&other.north_east)
+    }
+
+    /// Do the two boxes overlap?  Boxes that only share an edge overlap too.
+    pub fn intersects(&self, other: &NP_GeoBox) -> bool {
+        self.south_west.lat <= other.north_east.lat && other.south_west.lat <= self.north_east.lat &&
+        self.south_west.lng <= other.north_east.lng && other.south_west.lng <= self.north_east.lng
+    }
+
+    /// The area both boxes cover, if they overlap
+    pub fn intersection(&self, other: &NP_GeoBox) -> Option<NP_GeoBox> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let size = self.south_west.size;
+        Some(NP_GeoBox {
+            south_west: NP_Geo::new(size, self.south_west.lat.max(other.south_west.lat), self.south_west.lng.max(other.south_west.lng)),
+            north_east: NP_Geo::new(size, self.north_east.lat.min(other.north_east.lat), self.north_east.lng.min(other.north_east.lng))
+        })
+    }
+
+    /// The first and last Z-order keys of coordinates in this box, both included.
+    /// 
+    /// Every coordinate in the box has a key in this range, so the range can be scanned over keys sorted by `NP_Geo::z_order` or over sortable buffers with a `"z_order": true` geo value.  Some coordinates outside of the box have keys in the range too, check the matches with `contains`.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::pointer::geo::{NP_Geo, NP_GeoBox};
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "tuple",
+    ///    "sorted": true,
+    ///    "values": [{"type": "geo8", "z_order": true}, {"type": "string"}]
+    /// }"#)?;
+    /// 
+    /// let mut keys: Vec<Vec<u8>> = Vec::new();
+    /// for (lat, lng, name) in [(45.52, -122.67, "office"), (47.60, -122.33, "seattle"), (45.51, -122.68, "cafe")].iter() {
+    ///     let mut buffer = factory.empty_buffer(None);
+    ///     buffer.set(&["0"], NP_Geo::new(8, *lat, *lng))?;
+    ///     buffer.set(&["1"], *name)?;
+    ///     keys.push(buffer.close_sortable()?);
+    /// }
+    /// keys.sort();
+    /// 
+    /// let area = NP_GeoBox::new(NP_Geo::new(8, 45.4, -122.8), NP_Geo::new(8, 45.6, -122.5));
+    /// let (start, end) = area.z_range().unwrap();
+    /// 
+    /// let mut found: Vec<String> = Vec::new();
+    /// for key in keys.iter().filter(|key| key[..8] >= start[..] && key[..8] <= end[..]) {
+    ///     let buffer = factory.open_sortable_buffer(key.clone())?;
+    ///     if area.contains(&buffer.get::<NP_Geo>(&["0"])?.unwrap()) {
+    ///         found.push(buffer.get::<&str>(&["1"])?.unwrap().to_string());
+    ///     }
+    /// }
+    /// assert_eq!(found, vec!["cafe", "office"]);
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    pub fn z_range(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        Some((self.south_west.z_order()?, self.north_east.z_order()?))
+    }
+}
+
+/// Mean radius of the earth
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+const DEGREES_TO_RADIANS: f64 = core::f64::consts::PI / 180.0;
+
+// core doesn't have float math without std, these are accurate to about 1e-15 over the ranges used here
+
+fn sqrt(x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    // halving the exponent bits is a close first guess
+    let mut guess = f64::from_bits((x.to_bits() + (1023u64 << 52)) >> 1);
+    for _x in 0..6 {
+        guess = (guess + x / guess) / 2.0;
+    }
+    guess
+}
+
+fn sin(x: f64) -> f64 {
+    let pi = core::f64::consts::PI;
+    // move x into -pi to pi so the series converges quickly
+    let mut x = x % (2.0 * pi);
+    if x > pi {
+        x -= 2.0 * pi;
+    } else if x < -pi {
+        x += 2.0 * pi;
+    }
+    let mut term = x;
+    let mut sum = x;
+    for n in 1..20 {
+        term *= -x * x / ((2 * n) as f64 * (2 * n + 1) as f64);
+        sum += term;
+    }
+    sum
+}
+
+fn cos(x: f64) -> f64 {
+    sin(x + core::f64::consts::FRAC_PI_2)
+}
+
+/// Arc tangent of `y / x` for `x` and `y` that aren't negative
+fn atan2(y: f64, x: f64) -> f64 {
+    if y == 0.0 {
+        return 0.0;
+    }
+    if x == 0.0 {
+        return core::f64::consts::FRAC_PI_2;
+    }
+    let t = y / x;
+    if t > 1.0 {
+        return core::f64::consts::FRAC_PI_2 - atan(1.0 / t);
+    }
+    atan(t)
+}
+
+/// Arc tangent of `0 <= t <= 1`
+fn atan(t: f64) -> f64 {
+    // atan(t) = 2 * atan(t / (1 + sqrt(1 + t^2))), twice brings t under 0.2
+    let mut t = t;
+    for _x in 0..2 {
+        t /= 1.0 + sqrt(1.0 + t * t);
+    }
+    let mut term = t;
+    let mut sum = t;
+    for n in 1..20 {
+        term *= -t * t;
+        sum += term / (2 * n + 1) as f64;
+    }
+    sum * 4.0
+}
+
 impl Default for NP_Geo {
     fn default() -> Self { 
         NP_Geo { lat: 0.0, lng: 0.0, size: 0 }
@@ -312,7 +676,7 @@ fn geo_default_value(size: u8, json: &NP_JSON) -> Result<Option<NP_Geo_Bytes>, N
                     }
                 },
                 None => {
-                    return Err(NP_Error::new("Default values for NP_Geo should have lat key!"))
+                    return Err(NP_Error::Schema("Default values for NP_Geo should have lat key!".into()))
                 }
             };
             let mut lng = 0f64;
@@ -329,10 +693,14 @@ fn geo_default_value(size: u8, json: &NP_JSON) -> Result<Option<NP_Geo_Bytes>, N
                     }
                 },
                 None => {
-                    return Err(NP_Error::new("Default values for NP_Geo should have lng key!"))
+                    return Err(NP_Error::Schema("Default values for NP_Geo should have lng key!".into()))
                 }
             };
 
+            if size == 0 {
+                return Ok(Some(rounded_bytes(auto_size(lat, lng), lat, lng)));
+            }
+
             match NP_Geo::new(size, lat, lng).get_bytes() {
                 Some(b) => return Ok(Some(b)),
                 None => return Ok(None)
@@ -346,7 +714,7 @@ impl<'value> NP_Value<'value> for NP_Geo {
 
     fn schema_default(schema: &NP_Parsed_Schema) -> Option<Self> {
         match schema {
-            NP_Parsed_Schema::Geo { i: _, sortable: _, default, size: _} => {
+            NP_Parsed_Schema::Geo { default, .. } => {
                 if let Some(d) = default {
                     Some(d.clone())
                 } else {
@@ -364,10 +732,19 @@ impl<'value> NP_Value<'value> for NP_Geo {
         let mut schema_json = JSMAP::new();
 
         match &schema[address] {
-            NP_Parsed_Schema::Geo { i: _, sortable: _, default, size} => {
+            NP_Parsed_Schema::Geo { default, size, z_order, .. } => {
                 let mut type_str = Self::type_idx().0.to_string();
-                type_str.push_str(size.to_string().as_str());
-                schema_json.insert("type".to_owned(), NP_JSON::String(type_str));
+                if *size == 0 {
+                    schema_json.insert("type".to_owned(), NP_JSON::String(type_str));
+                    schema_json.insert("size".to_owned(), NP_JSON::String("auto".to_owned()));
+                } else {
+                    type_str.push_str(size.to_string().as_str());
+                    schema_json.insert("type".to_owned(), NP_JSON::String(type_str));
+                }
+
+                if *z_order {
+                    schema_json.insert("z_order".to_owned(), NP_JSON::True);
+                }
             
                 if let Some(d) = default {
                     let mut default_map = JSMAP::new();
@@ -395,13 +772,11 @@ impl<'value> NP_Value<'value> for NP_Geo {
             _ => 0
         };
 
-        let value_bytes_size = size as usize;
-
-        if value_bytes_size == 0 {
-            unreachable!();
+        if size == 0 {
+            return set_auto_value(cursor, memory, &value);
         }
 
-        let write_bytes = memory.write_bytes();
+        let value_bytes_size = size as usize;
 
         let half_value_bytes = value_bytes_size / 2;
 
@@ -477,11 +852,7 @@ impl<'value> NP_Value<'value> for NP_Geo {
         if value_address != 0 { // existing value, replace
 
             // overwrite existing values in buffer
-            for x in 0..value_bytes.len() {
-                if x < value_bytes_size {
-                    write_bytes[value_address + x] = value_bytes[x];
-                }
-            }
+            memory.get_bytes_mut(value_address, value_bytes_size)?.copy_from_slice(&value_bytes[..value_bytes_size]);
 
 
         } else { // new value
@@ -494,13 +865,9 @@ impl<'value> NP_Value<'value> for NP_Geo {
             };
 
             // set values in buffer
-            for x in 0..value_bytes.len() {
-                if x < value_bytes_size {
-                    write_bytes[value_address + x] = value_bytes[x];
-                }
-            }
+            memory.get_bytes_mut(value_address, value_bytes_size)?.copy_from_slice(&value_bytes[..value_bytes_size]);
 
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value(memory).set_addr_value(value_address as u32);
 
         }
 
@@ -525,6 +892,13 @@ impl<'value> NP_Value<'value> for NP_Geo {
             _ => 0
         };
 
+        // auto sized values start with their size
+        let (size, value_addr) = if size == 0 {
+            (memory.get_1_byte(value_addr).unwrap_or(0), value_addr + 1)
+        } else {
+            (size, value_addr)
+        };
+
         Ok(Some(match size {
             16 => {
          
@@ -573,7 +947,7 @@ impl<'value> NP_Value<'value> for NP_Geo {
                 NP_Geo { lat: lat / dev, lng: lon / dev, size: 4}
             },
             _ => {
-                unreachable!();
+                return Err(NP_Error::SchemaMismatch("Geo values are 4, 8 or 16 bytes!".into()));
             }
         }))
     }
@@ -594,7 +968,7 @@ impl<'value> NP_Value<'value> for NP_Geo {
                     None => {
 
                         match &memory.schema[cursor.schema_addr] {
-                            NP_Parsed_Schema::Geo { i: _, sortable: _, default, size: _} => {
+                            NP_Parsed_Schema::Geo { default, .. } => {
                                 if let Some(d) = default {
                                     let mut object = JSMAP::new();
 
@@ -627,157 +1001,92 @@ impl<'value> NP_Value<'value> for NP_Geo {
             return Ok(0) 
         } else {
             let size = match memory.schema[cursor.schema_addr] {
-                NP_Parsed_Schema::Geo { i: _, sortable: _, default: _, size} => {
+                NP_Parsed_Schema::Geo { size, .. } => {
                     size
                 },
                 _ => 0
             };
-            Ok(size as usize)
+            Ok(value_size(c_value.get_addr_value() as usize, size, memory))
         }
     }
 
     fn from_json_to_schema(mut schema: Vec<NP_Parsed_Schema>, json_schema: &Box<NP_JSON>) -> Result<(bool, Vec<u8>, Vec<NP_Parsed_Schema>), NP_Error> {
 
-        let type_str = NP_Schema::_get_type(&json_schema)?;
-
-        match type_str.as_str() {
-            "geo4" => {
-                let mut schema_data: Vec<u8> = Vec::new();
-                schema_data.push(NP_TypeKeys::Geo as u8);
-                schema_data.push(4);
-                let default = match geo_default_value(4, &json_schema)? {
-                    Some(x) => {
-                        schema_data.push(1);
-                        schema_data.extend(x.lat.clone());
-                        schema_data.extend(x.lng.clone());
-                        let g = x.into_geo();
-                        Some(NP_Geo::new(4, g.lat, g.lng))
-                    },
-                    None => {
-                        schema_data.push(0);
-                        None
-                    }
-                };
-                schema.push(NP_Parsed_Schema::Geo {
-                    i: NP_TypeKeys::Geo,
-                    size: 4,
-                    default: default,
-                    sortable: false
-                });
-                Ok((false, schema_data, schema))
-            },
-            "geo8" => {
-                let mut schema_data: Vec<u8> = Vec::new();
-                schema_data.push(NP_TypeKeys::Geo as u8);
-                schema_data.push(8);
-                let default = match geo_default_value(8, &json_schema)? {
-                    Some(x) => {
-                        schema_data.push(1);
-                        schema_data.extend(x.lat.clone());
-                        schema_data.extend(x.lng.clone());
-                        let g = x.into_geo();
-                        Some(NP_Geo::new(8, g.lat, g.lng))
-                    },
-                    None => {
-                        schema_data.push(0);
-                        None
-                    }
-                };
-                schema.push(NP_Parsed_Schema::Geo {
-                    i: NP_TypeKeys::Geo,
-                    size: 8,
-                    default: default,
-                    sortable: false
-                });
-                Ok((false, schema_data, schema))
-            },
-            "geo16" => {
-                let mut schema_data: Vec<u8> = Vec::new();
-                schema_data.push(NP_TypeKeys::Geo as u8);
-                schema_data.push(16);
-                let default = match geo_default_value(16, &json_schema)? {
-                    Some(x) => {
-                        schema_data.push(1);
-                        schema_data.extend(x.lat.clone());
-                        schema_data.extend(x.lng.clone());
-                        let g = x.into_geo();
-                        Some(NP_Geo::new(16, g.lat, g.lng))
-                    },
-                    None => {
-                        schema_data.push(0);
-                        None
-                    }
-                };
-                schema.push(NP_Parsed_Schema::Geo {
-                    i: NP_TypeKeys::Geo,
-                    size: 16,
-                    default: default,
-                    sortable: false
-                });
-                Ok((false, schema_data, schema))
+        let type_str = NP_Schema::_get_type(json_schema)?;
+
+        // a size of 0 is "auto", each value picks it's own size
+        let size: u8 = match (type_str.as_str(), &json_schema["size"]) {
+            ("geo4", _) => 4,
+            ("geo8", _) => 8,
+            ("geo16", _) => 16,
+            ("geo", NP_JSON::String(x)) if x == "auto" => 0,
+            ("geo", NP_JSON::Integer(x)) if *x == 4 || *x == 8 || *x == 16 => *x as u8,
+            ("geo", _) => return Err(NP_Error::Schema("Geo 'size' property must be 4, 8, 16 or \"auto\"!".into())),
+            _ => return Ok((false, Vec::new(), Vec::new()))
+        };
+
+        let z_order = match json_schema["z_order"] {
+            NP_JSON::True => true,
+            NP_JSON::False | NP_JSON::Null => false,
+            _ => return Err(NP_Error::Schema("The z_order property must be a boolean!".into()))
+        };
+
+        // first bit for default value, second bit for z-order sorting
+        let flags = if z_order { 2 } else { 0 };
+
+        let mut schema_data: Vec<u8> = Vec::new();
+        schema_data.push(NP_TypeKeys::Geo as u8);
+        schema_data.push(size);
+        let default = match geo_default_value(size, json_schema)? {
+            Some(x) => {
+                schema_data.push(flags | 1);
+                if size == 0 {
+                    schema_data.push(x.size);
+                }
+                schema_data.extend(x.lat.clone());
+                schema_data.extend(x.lng.clone());
+                Some(x.into_geo())
             },
-            _ => {
-                Ok((false, Vec::new(), Vec::new()))
+            None => {
+                schema_data.push(flags);
+                None
             }
-        }
+        };
+        schema.push(NP_Parsed_Schema::Geo {
+            i: NP_TypeKeys::Geo,
+            size,
+            default,
+            sortable: size != 0,
+            z_order
+        });
+        // auto sized values change size so they can't be in sorted tuples
+        Ok((size != 0, schema_data, schema))
     }
 
     fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
         let size = bytes[address + 1];
+        let flags = bytes[address + 2];
 
-        // no default
-        if bytes[address + 2] == 0 {
-            schema.push(NP_Parsed_Schema::Geo {
-                i: NP_TypeKeys::Geo,
-                sortable: false,
-                size: size,
-                default: None
-            });
-            return (false, schema) 
-        }
+        let default = if flags & 1 == 0 {
+            None
+        } else {
+            // auto sized defaults have their size in front
+            let (value_size, start) = if size == 0 { (bytes[address + 3], address + 4) } else { (size, address + 3) };
+            let half = value_size as usize / 2;
+            let lat = &bytes[start..(start + half)];
+            let lng = &bytes[(start + half)..(start + value_size as usize)];
+            Some(NP_Geo_Bytes { size: value_size, lat: lat.to_vec(), lng: lng.to_vec() }.into_geo())
+        };
 
-        // has default
-        match size {
-            4 => {
-                let lat = &bytes[(address + 3)..(address + 5)];
-                let lng = &bytes[(address + 6)..(address + 8)];
-                let default_value = NP_Geo_Bytes { size: size, lat: lat.to_vec(), lng: lng.to_vec()};
-                schema.push(NP_Parsed_Schema::Geo {
-                    i: NP_TypeKeys::Geo,
-                    size: size,
-                    sortable: false,
-                    default: Some(default_value.into_geo())
-                });
-                (false, schema)
-            },
-            8 => {
-                let lat = &bytes[(address + 3)..(address + 7)];
-                let lng = &bytes[(address + 7)..(address + 11)];
-                let default_value = NP_Geo_Bytes { size: size, lat: lat.to_vec(), lng: lng.to_vec()};
-                schema.push(NP_Parsed_Schema::Geo {
-                    i: NP_TypeKeys::Geo,
-                    size: size,
-                    sortable: false,
-                    default: Some(default_value.into_geo())
-                });
-                (false, schema)
-            },
-            16 => {
-                let lat = &bytes[(address + 3)..(address + 11)];
-                let lng = &bytes[(address + 12)..(address + 20)];
-                let default_value = NP_Geo_Bytes { size: size, lat: lat.to_vec(), lng: lng.to_vec()};
-                schema.push(NP_Parsed_Schema::Geo {
-                    i: NP_TypeKeys::Geo,
-                    size: size,
-                    sortable: false,
-                    default: Some(default_value.into_geo())
-                });
-                (false, schema)
-            },
-            _ => {
-                unreachable!();
-            }
-        }
+        schema.push(NP_Parsed_Schema::Geo {
+            i: NP_TypeKeys::Geo,
+            size,
+            sortable: size != 0,
+            default,
+            z_order: flags & 2 != 0
+        });
+
+        (size != 0, schema)
     }
 }
 
@@ -850,4 +1159,113 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn distance_works() -> Result<(), NP_Error> {
+    let close = |a: f64, b: f64| (a - b).abs() < 0.01;
+
+    // one degree along the equator and across the poles
+    assert!(close(NP_Geo::new(16, 0.0, 0.0).distance_meters(&NP_Geo::new(16, 0.0, 1.0)), 111_195.08));
+    assert!(close(NP_Geo::new(16, 89.5, 0.0).distance_meters(&NP_Geo::new(16, 89.5, 180.0)), 111_195.08));
+    assert!(close(NP_Geo::new(16, 0.0, -179.5).distance_meters(&NP_Geo::new(16, 0.0, 179.5)), 111_195.08));
+    // opposite sides of the earth
+    assert!(close(NP_Geo::new(16, 10.0, 20.0).distance_meters(&NP_Geo::new(16, -10.0, -160.0)), EARTH_RADIUS_METERS * core::f64::consts::PI));
+
+    let factory = crate::NP_Factory::new(r#"{"type": "list", "of": {"type": "geo4"}}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    assert_eq!(buffer.geo_within(&[], &[], &NP_Geo::new(4, 0.0, 0.0), 1.0)?, Vec::<usize>::new());
+    buffer.set(&["0"], NP_Geo::new(4, 1.0, 1.0))?;
+    buffer.set(&["3"], NP_Geo::new(4, 1.01, 1.0))?;
+    buffer.set(&["5"], NP_Geo::new(4, 50.0, 1.0))?;
+    assert_eq!(buffer.geo_within(&[], &[], &NP_Geo::new(4, 1.0, 1.0), 1200.0)?, alloc::vec![0, 3]);
+    assert_eq!(buffer.geo_within(&[], &[], &NP_Geo::new(4, 1.0, 1.0), 1000.0)?, alloc::vec![0]);
+    assert!(buffer.geo_within(&["0"], &[], &NP_Geo::new(4, 1.0, 1.0), 1000.0).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn geo_box_and_z_order_works() -> Result<(), NP_Error> {
+    // interleaving round trips and orders by both coordinates
+    assert_eq!(z_interleave(&[0b1111_0000], &[0b0000_1111]), alloc::vec![0b1010_1010, 0b0101_0101]);
+    assert_eq!(z_deinterleave(&[0b1010_1010, 0b0101_0101]), (alloc::vec![0b1111_0000], alloc::vec![0b0000_1111]));
+    let key = |lat: f64, lng: f64| NP_Geo::new(4, lat, lng).z_order().unwrap();
+    assert!(key(-10.0, -10.0) < key(-10.0, 10.0) && key(-10.0, 10.0) < key(10.0, -10.0) && key(10.0, -10.0) < key(10.0, 10.0));
+    assert_eq!(NP_Geo::from_z_order(&key(-12.5, 40.25)), Some(NP_Geo::new(4, -12.5, 40.25)));
+    assert_eq!(NP_Geo::from_z_order(&[0; 3]), None);
+
+    // every point in a box has a key in it's range
+    let area = NP_GeoBox::new(NP_Geo::new(4, -20.0, 30.0), NP_Geo::new(4, 10.0, -5.5));
+    let (start, end) = area.z_range().unwrap();
+    for lat in -20..=10 {
+        for lng in -5..=30 {
+            let point = key(lat as f64, lng as f64);
+            assert!(point >= start && point <= end);
+        }
+    }
+    assert!(area.contains(&NP_Geo::new(4, 10.0, -5.5)));
+    assert!(!area.contains(&NP_Geo::new(4, 10.01, 0.0)));
+    let edge = NP_GeoBox::new(NP_Geo::new(4, 10.0, 0.0), NP_Geo::new(4, 20.0, 1.0));
+    assert_eq!(area.intersection(&edge), Some(NP_GeoBox::new(NP_Geo::new(4, 10.0, 0.0), NP_Geo::new(4, 10.0, 1.0))));
+    assert!(!area.contains_box(&edge));
+
+    // the schema flag survives compiling and changes the sortable bytes
+    let schema = r#"{"type":"tuple","values":[{"type":"geo16","default":{"lat":1.5,"lng":-2.25},"z_order":true}],"sorted":true}"#;
+    let factory = crate::NP_Factory::new(schema)?;
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(compiled.export_schema()?.stringify(), factory.export_schema()?.stringify());
+    let mut buffer = compiled.empty_buffer(None);
+    buffer.set(&["0"], NP_Geo::new(16, 45.509616, -122.714625))?;
+    let sortable = buffer.close_sortable()?;
+    assert_eq!(&sortable[..16], &NP_Geo::new(16, 45.509616, -122.714625).z_order().unwrap()[..]);
+    let reopened = compiled.open_sortable_buffer(sortable)?;
+    assert_eq!(reopened.get::<NP_Geo>(&["0"])?, Some(NP_Geo::new(16, 45.509616, -122.714625)));
+
+    // compiled defaults of every size
+    for size in [4u8, 16].iter() {
+        let schema = alloc::format!(r#"{{"type":"geo{}","default":{{"lat":1.5,"lng":-2.25}}}}"#, size);
+        let compiled = crate::NP_Factory::new_compiled(crate::NP_Factory::new(&schema)?.compile_schema());
+        assert_eq!(compiled.empty_buffer(None).get::<NP_Geo>(&[])?, Some(NP_Geo::new(*size, 1.5, -2.25)));
+    }
+
+    assert!(crate::NP_Factory::new(r#"{"type":"geo4","z_order":1}"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn auto_size_works() -> Result<(), NP_Error> {
+    assert_eq!(auto_size(45.5, -122.71), 4);
+    assert_eq!(auto_size(45.509616, -122.71), 8);
+    assert_eq!(auto_size(45.509616123, 0.0), 16);
+
+    let schema = r#"{"type":"geo","size":"auto","default":{"lat":1.5,"lng":-2.25}}"#;
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(factory.export_schema()?.stringify(), schema);
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(compiled.empty_buffer(None).get::<NP_Geo>(&[])?, Some(NP_Geo::new(4, 1.5, -2.25)));
+
+    // the size changes with the value, compaction only keeps the last one
+    let mut buffer = compiled.empty_buffer(None);
+    buffer.set(&[], NP_Geo::new(0, 45.509616123, -122.714625))?;
+    assert_eq!(buffer.get::<NP_Geo>(&[])?, Some(NP_Geo::new(16, 45.509616123, -122.714625)));
+    buffer.set(&[], NP_Geo::new(0, 20.23, -12.21))?;
+    buffer.set(&[], NP_Geo::new(0, 20.24, -12.21))?;
+    assert_eq!(buffer.get::<NP_Geo>(&[])?, Some(NP_Geo::new(4, 20.24, -12.21)));
+    buffer.compact(None)?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, 8usize);
+    assert_eq!(buffer.get::<NP_Geo>(&[])?, Some(NP_Geo::new(4, 20.24, -12.21)));
+
+    // sortable bytes use the geo16 resolution
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&[], NP_Geo::new(0, -12.5, 40.25))?;
+    let sortable = buffer.sortable_bytes(&[])?;
+    assert_eq!(sortable.len(), 16);
+
+    assert!(crate::NP_Factory::new(r#"{"type":"tuple","values":[{"type":"geo","size":"auto"}],"sorted":true}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type":"geo","size":5}"#).is_err());
+    assert_eq!(crate::NP_Factory::new(r#"{"type":"geo","size":8}"#)?.export_schema()?.stringify(), r#"{"type":"geo8"}"#);
+
+    Ok(())
+}
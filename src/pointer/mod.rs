@@ -23,214 +23,711 @@ pub mod uuid;
 pub mod option;
 pub mod date;
 
-use crate::buffer::ROOT_PTR_ADDR;
 use core::{fmt::{Debug}};
 
-use alloc::prelude::v1::Box;
+use alloc::boxed::Box;
 use crate::{pointer::dec::NP_Dec, schema::NP_Schema_Addr};
 use crate::NP_Parsed_Schema;
 use crate::{json_flex::NP_JSON};
-use crate::memory::{NP_Memory};
+use crate::memory::NP_Memory;
+use crate::buffer::NP_Tombstones;
+#[cfg(not(feature = "safe"))]
+use crate::memory::NP_Address_Size;
+#[cfg(not(feature = "safe"))]
+use core::{marker::PhantomData, ptr::NonNull};
 use crate::NP_Error;
+use crate::utils::opt_err;
 use crate::{schema::{NP_TypeKeys}, collection::{map::NP_Map, table::NP_Table, list::NP_List, tuple::NP_Tuple}};
 
 use alloc::{string::String, vec::Vec, borrow::ToOwned};
 use bytes::NP_Bytes;
 
-use self::{date::NP_Date, geo::NP_Geo, option::NP_Enum, string::NP_String, ulid::{NP_ULID, _NP_ULID}, uuid::{NP_UUID, _NP_UUID}};
+use self::{any::{NP_Any, NP_AnyValue}, date::NP_Date, geo::NP_Geo, option::NP_Enum, string::NP_String, ulid::{NP_ULID, _NP_ULID}, uuid::{NP_UUID, _NP_UUID}};
+
+/// Big endian address bytes inside a buffer, `[u8; 1]`, `[u8; 2]` or `[u8; 4]` depending on the address size of the buffer
+#[doc(hidden)]
+pub trait NP_Addr: Copy + Debug + Default {
+    /// Largest address that fits, never handed out by the allocator
+    const MAX: u32;
+    fn get(&self) -> u32;
+    fn set(&mut self, value: u32);
+}
+
+impl NP_Addr for [u8; 1] {
+    const MAX: u32 = u8::MAX as u32;
+    #[inline(always)]
+    fn get(&self) -> u32 { self[0] as u32 }
+    #[inline(always)]
+    fn set(&mut self, value: u32) { self[0] = value as u8 }
+}
+
+impl NP_Addr for [u8; 2] {
+    const MAX: u32 = u16::MAX as u32;
+    #[inline(always)]
+    fn get(&self) -> u32 { u16::from_be_bytes(*self) as u32 }
+    #[inline(always)]
+    fn set(&mut self, value: u32) { *self = (value as u16).to_be_bytes() }
+}
+
+impl NP_Addr for [u8; 4] {
+    const MAX: u32 = u32::MAX;
+    #[inline(always)]
+    fn get(&self) -> u32 { u32::from_be_bytes(*self) }
+    #[inline(always)]
+    fn set(&mut self, value: u32) { *self = value.to_be_bytes() }
+}
 
 #[doc(hidden)]
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
-pub struct NP_Pointer_Scalar {
-    pub addr_value: [u8; 2]
+pub struct NP_Pointer_Scalar<A: NP_Addr = [u8; 2]> {
+    pub addr_value: A
 }
 
-impl Default for NP_Pointer_Scalar {
+impl<A: NP_Addr> Default for NP_Pointer_Scalar<A> {
     fn default() -> Self {
-        Self { addr_value: [0; 2] }
+        Self { addr_value: A::default() }
     }
 }
 
 #[doc(hidden)]
 #[derive(Debug)]
 #[repr(C)]
-pub struct NP_Pointer_List_Item {
-    pub addr_value: [u8; 2],
-    pub next_value: [u8; 2],
+pub struct NP_Pointer_List_Item<A: NP_Addr = [u8; 2]> {
+    pub addr_value: A,
+    pub next_value: A,
     pub index: u8
 }
 
 #[doc(hidden)]
 #[derive(Debug)]
 #[repr(C)]
-pub struct NP_Pointer_Map_Item {
-    pub addr_value: [u8; 2],
-    pub next_value: [u8; 2],
-    pub key_addr: [u8; 2]
+pub struct NP_Pointer_Map_Item<A: NP_Addr = [u8; 2]> {
+    pub addr_value: A,
+    pub next_value: A,
+    pub key_addr: A
 }
 
+/// Value address of a pointer that was explicitly set to null.
+///
+/// Address 1 is always the root pointer or inside the buffer header, so no value can be stored there.  Pointers holding this address read as empty, `is_null` tells them apart from pointers that were never set.
+#[doc(hidden)]
+pub const NP_NULL_ADDR: u32 = 1;
+
+/// Value address of a pointer whose value was deleted while tombstones are on.
+///
+/// Every bit of the address is set, in the address size of the buffer this is past the largest allocation possible.  Pointers holding it read as empty, `is_tombstone` tells them apart from pointers that were never set.
+#[doc(hidden)]
+pub const NP_TOMBSTONE_ADDR: u32 = u32::MAX;
+
 #[doc(hidden)]
 #[allow(missing_docs, unused_variables)]
 pub trait NP_Pointer_Bytes {
     fn get_type(&self) -> &str                                     { "" }
-    fn get_addr_value(&self) -> u16                                { 0 }
-    fn set_addr_value(&mut self, addr: u16)                        {   }
-    fn get_next_addr(&self) -> u16                                 { 0 }
-    fn set_next_addr(&mut self, addr: u16)                         {   }
+    fn get_addr_value(&self) -> u32                                { 0 }
+    fn set_addr_value(&mut self, addr: u32)                        {   }
+    fn is_null(&self) -> bool                                      { false }
+    fn is_tombstone(&self) -> bool                                 { false }
+    fn get_next_addr(&self) -> u32                                 { 0 }
+    fn set_next_addr(&mut self, addr: u32)                         {   }
     fn set_index(&mut self, index: u8)                             {   }
     fn get_index(&self) -> u8                                      { 0 }
-    fn set_key_addr(&mut self, hash: u16)                          {   }
-    fn get_key_addr(&self) -> u16                                  { 0 }
+    fn set_key_addr(&mut self, hash: u32)                          {   }
+    fn get_key_addr(&self) -> u32                                  { 0 }
     fn reset(&mut self)                                            {   }
     fn get_size(&self) -> usize                                    { 0 }
     fn get_key<'key>(&self, memory: &'key NP_Memory) -> &'key str  { "" }
     fn get_key_size<'key>(&self, memory: &'key NP_Memory) -> usize { 0  }
 }
 
-impl NP_Pointer_Bytes for NP_Pointer_Scalar {
+impl<A: NP_Addr> NP_Pointer_Bytes for NP_Pointer_Scalar<A> {
     fn get_type(&self) -> &str { "Scalar" }
     #[inline(always)]
-    fn get_addr_value(&self) -> u16 { u16::from_be_bytes(self.addr_value) }
+    fn get_addr_value(&self) -> u32 { match self.addr_value.get() { NP_NULL_ADDR => 0, addr if addr == A::MAX => 0, addr => addr } }
+    #[inline(always)]
+    fn set_addr_value(&mut self, addr: u32) { self.addr_value.set(addr) }
     #[inline(always)]
-    fn set_addr_value(&mut self, addr: u16) { self.addr_value = addr.to_be_bytes() }
+    fn is_null(&self) -> bool { self.addr_value.get() == NP_NULL_ADDR }
     #[inline(always)]
-    fn reset(&mut self) { self.addr_value = [0; 2]; }
+    fn is_tombstone(&self) -> bool { self.addr_value.get() == A::MAX }
     #[inline(always)]
-    fn get_size(&self) -> usize { 2 }
+    fn reset(&mut self) { self.addr_value = A::default(); }
+    #[inline(always)]
+    fn get_size(&self) -> usize { core::mem::size_of::<Self>() }
 }
-impl NP_Pointer_Bytes for NP_Pointer_List_Item {
+impl<A: NP_Addr> NP_Pointer_Bytes for NP_Pointer_List_Item<A> {
     fn get_type(&self) -> &str { "List Item" }
     #[inline(always)]
-    fn get_addr_value(&self) -> u16 { u16::from_be_bytes(self.addr_value) }
+    fn get_addr_value(&self) -> u32 { match self.addr_value.get() { NP_NULL_ADDR => 0, addr if addr == A::MAX => 0, addr => addr } }
+    #[inline(always)]
+    fn set_addr_value(&mut self, addr: u32) { self.addr_value.set(addr) }
     #[inline(always)]
-    fn set_addr_value(&mut self, addr: u16) { self.addr_value = addr.to_be_bytes() }
+    fn is_null(&self) -> bool { self.addr_value.get() == NP_NULL_ADDR }
     #[inline(always)]
-    fn get_next_addr(&self) -> u16 { u16::from_be_bytes(self.next_value) }
+    fn is_tombstone(&self) -> bool { self.addr_value.get() == A::MAX }
     #[inline(always)]
-    fn set_next_addr(&mut self, addr: u16) { self.next_value = addr.to_be_bytes() }
+    fn get_next_addr(&self) -> u32 { self.next_value.get() }
+    #[inline(always)]
+    fn set_next_addr(&mut self, addr: u32) { self.next_value.set(addr) }
     #[inline(always)]
     fn set_index(&mut self, index: u8)  { self.index = index }
     #[inline(always)]
     fn get_index(&self) -> u8  { self.index }
     #[inline(always)]
-    fn reset(&mut self) { self.addr_value = [0; 2]; self.next_value = [0; 2]; self.index = 0; }
+    fn reset(&mut self) { self.addr_value = A::default(); self.next_value = A::default(); self.index = 0; }
     #[inline(always)]
-    fn get_size(&self) -> usize { 5 }
+    fn get_size(&self) -> usize { core::mem::size_of::<Self>() }
 }
-impl NP_Pointer_Bytes for NP_Pointer_Map_Item {
+impl<A: NP_Addr> NP_Pointer_Bytes for NP_Pointer_Map_Item<A> {
     fn get_type(&self) -> &str { "Map Item" }
     #[inline(always)]
-    fn get_addr_value(&self) -> u16 { u16::from_be_bytes(self.addr_value) }
+    fn get_addr_value(&self) -> u32 { match self.addr_value.get() { NP_NULL_ADDR => 0, addr if addr == A::MAX => 0, addr => addr } }
+    #[inline(always)]
+    fn set_addr_value(&mut self, addr: u32) { self.addr_value.set(addr) }
+    #[inline(always)]
+    fn is_null(&self) -> bool { self.addr_value.get() == NP_NULL_ADDR }
     #[inline(always)]
-    fn set_addr_value(&mut self, addr: u16) { self.addr_value = addr.to_be_bytes() }
+    fn is_tombstone(&self) -> bool { self.addr_value.get() == A::MAX }
     #[inline(always)]
-    fn get_next_addr(&self) -> u16 { u16::from_be_bytes(self.next_value) }
+    fn get_next_addr(&self) -> u32 { self.next_value.get() }
     #[inline(always)]
-    fn set_next_addr(&mut self, addr: u16) { self.next_value = addr.to_be_bytes() }
+    fn set_next_addr(&mut self, addr: u32) { self.next_value.set(addr) }
     #[inline(always)]
-    fn set_key_addr(&mut self, addr: u16)  { self.key_addr = addr.to_be_bytes(); }
+    fn set_key_addr(&mut self, addr: u32)  { self.key_addr.set(addr) }
     #[inline(always)]
-    fn get_key_addr(&self) -> u16  { u16::from_be_bytes(self.key_addr) }
+    fn get_key_addr(&self) -> u32  { self.key_addr.get() }
     #[inline(always)]
-    fn reset(&mut self) { self.addr_value = [0; 2]; self.next_value = [0; 2]; self.key_addr = [0;2 ]; }
+    fn reset(&mut self) { self.addr_value = A::default(); self.next_value = A::default(); self.key_addr = A::default(); }
     #[inline(always)]
-    fn get_size(&self) -> usize { 6 }
+    fn get_size(&self) -> usize { core::mem::size_of::<Self>() }
     #[inline(always)]
     fn get_key<'key>(&self, memory: &'key NP_Memory) -> &'key str {
-        let key_addr = self.get_key_addr() as usize;
-        if key_addr == 0 {
-            return "";
-        } else {
-            let key_length = memory.read_bytes()[key_addr] as usize;
-            let key_bytes = &memory.read_bytes()[(key_addr + 1)..(key_addr + 1 + key_length)];
-            unsafe { core::str::from_utf8_unchecked(key_bytes) }
-        }
+        map_key(self.get_key_addr() as usize, memory)
     }
     #[inline(always)]
     fn get_key_size<'key>(&self, memory: &'key NP_Memory) -> usize {
-        let key_addr = self.get_key_addr() as usize;
-        if key_addr == 0 {
-            return 0;
-        } else {
-            return memory.read_bytes()[key_addr] as usize;
+        map_key_size(self.get_key_addr() as usize, memory)
+    }
+}
+
+#[inline(always)]
+fn map_key<'key>(key_addr: usize, memory: &'key NP_Memory) -> &'key str {
+    if key_addr == 0 {
+        return "";
+    } else {
+        // damaged keys read as empty strings
+        let key_length = memory.get_1_byte(key_addr).unwrap_or(0) as usize;
+        match memory.get_bytes(key_addr + 1, key_length) {
+            Ok(key_bytes) => core::str::from_utf8(key_bytes).unwrap_or(""),
+            Err(_) => ""
         }
     }
 }
 
+#[inline(always)]
+fn map_key_size(key_addr: usize, memory: &NP_Memory) -> usize {
+    if key_addr == 0 {
+        return 0;
+    } else {
+        return memory.get_1_byte(key_addr).unwrap_or(0) as usize;
+    }
+}
+
+#[doc(hidden)]
+#[allow(missing_docs)]
+pub trait NP_Map_Bytes: Debug {
+    fn set_head(&mut self, head: u32);
+    fn get_head(&self) -> u32;
+}
+
 #[repr(C)]
 #[derive(Debug)]
 #[doc(hidden)]
 #[allow(missing_docs)]
-pub struct NP_Map_Bytes {
-    head: [u8; 2]
+pub struct NP_Map_Head<A: NP_Addr = [u8; 2]> {
+    head: A
 }
 
-#[allow(missing_docs)]
-impl NP_Map_Bytes {
+impl<A: NP_Addr> NP_Map_Bytes for NP_Map_Head<A> {
     #[inline(always)]
-    pub fn set_head(&mut self, head: u16) {
-        self.head = head.to_be_bytes();
+    fn set_head(&mut self, head: u32) {
+        self.head.set(head);
     }
     #[inline(always)]
-    pub fn get_head(&self) -> u16 {
-        u16::from_be_bytes(self.head)
+    fn get_head(&self) -> u32 {
+        self.head.get()
     }
 }
 
+#[doc(hidden)]
+#[allow(missing_docs)]
+pub trait NP_List_Bytes: Debug {
+    fn set_head(&mut self, head: u32);
+    fn get_head(&self) -> u32;
+    fn set_tail(&mut self, tail: u32);
+    fn get_tail(&self) -> u32;
+}
+
 #[repr(C)]
 #[derive(Debug)]
 #[doc(hidden)]
 #[allow(missing_docs)]
-pub struct NP_List_Bytes {
-    head: [u8; 2],
-    tail: [u8; 2]
+pub struct NP_List_Head<A: NP_Addr = [u8; 2]> {
+    head: A,
+    tail: A
 }
 
-#[allow(missing_docs)]
-impl NP_List_Bytes {
+impl<A: NP_Addr> NP_List_Bytes for NP_List_Head<A> {
     #[inline(always)]
-    pub fn set_head(&mut self, head: u16) {
-        self.head = head.to_be_bytes();
+    fn set_head(&mut self, head: u32) {
+        self.head.set(head);
     }
     #[inline(always)]
-    pub fn get_head(&self) -> u16 {
-        u16::from_be_bytes(self.head)
+    fn get_head(&self) -> u32 {
+        self.head.get()
     }
     #[inline(always)]
-    pub fn set_tail(&mut self, tail: u16) {
-        self.tail = tail.to_be_bytes();
+    fn set_tail(&mut self, tail: u32) {
+        self.tail.set(tail);
     }
     #[inline(always)]
-    pub fn get_tail(&self) -> u16 {
-        u16::from_be_bytes(self.tail)
+    fn get_tail(&self) -> u32 {
+        self.tail.get()
     }
 }
 
-// holds 4 u16 addresses and a next value (10 bytes)
+#[doc(hidden)]
+#[allow(missing_docs)]
+pub trait NP_Vtable: Debug {
+    fn get_next(&self) -> u32;
+    fn set_next(&mut self, value: u32);
+}
+
+// holds 4 addresses and a next value (5 bytes with u8 addresses, 10 bytes with u16 addresses, 20 bytes with u32 addresses)
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 #[doc(hidden)]
 #[allow(missing_docs)]
-pub struct NP_Vtable {
-    pub values: [NP_Pointer_Scalar; 4],
-    next: [u8; 2]
+pub struct NP_Vtable_Bytes<A: NP_Addr = [u8; 2]> {
+    pub values: [NP_Pointer_Scalar<A>; 4],
+    next: A
+}
+
+impl<A: NP_Addr> NP_Vtable for NP_Vtable_Bytes<A> {
+    #[inline(always)]
+    fn get_next(&self) -> u32 {
+        self.next.get()
+    }
+    #[inline(always)]
+    fn set_next(&mut self, value: u32) {
+        self.next.set(value);
+    }
+}
+
+/// Which pointer struct is at an address
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum NP_Overlay_Kind {
+    Scalar,
+    List_Item,
+    Map_Item,
+    List_Head,
+    Map_Head,
+    Vtable
+}
+
+/// Pointer bytes in the buffer, the raw bytes cast into a pointer struct or a [`NP_Safe_Overlay`] with the `safe` feature
+#[doc(hidden)]
+#[cfg(not(feature = "safe"))]
+pub type NP_Pointer_Ref<'r> = NP_Overlay<'r, dyn NP_Pointer_Bytes>;
+#[doc(hidden)]
+#[cfg(feature = "safe")]
+pub type NP_Pointer_Ref<'r> = NP_Safe_Overlay<'r>;
+
+/// Vtable bytes in the buffer
+#[doc(hidden)]
+#[cfg(not(feature = "safe"))]
+pub type NP_Vtable_Ref<'r> = NP_Overlay<'r, dyn NP_Vtable>;
+#[doc(hidden)]
+#[cfg(feature = "safe")]
+pub type NP_Vtable_Ref<'r> = NP_Safe_Overlay<'r>;
+
+/// List head bytes in the buffer
+#[doc(hidden)]
+#[cfg(not(feature = "safe"))]
+pub type NP_List_Ref<'r> = NP_Overlay<'r, dyn NP_List_Bytes>;
+#[doc(hidden)]
+#[cfg(feature = "safe")]
+pub type NP_List_Ref<'r> = NP_Safe_Overlay<'r>;
+
+/// Map head bytes in the buffer
+#[doc(hidden)]
+#[cfg(not(feature = "safe"))]
+pub type NP_Map_Ref<'r> = NP_Overlay<'r, dyn NP_Map_Bytes>;
+#[doc(hidden)]
+#[cfg(feature = "safe")]
+pub type NP_Map_Ref<'r> = NP_Safe_Overlay<'r>;
+
+/// Pointer bytes cast into a pointer struct, or a [`NP_Safe_Overlay`] for bytes that can't be cast.
+///
+/// Only bytes the memory is allowed to change are cast, the `&mut` to the struct lives for a single method call.  Read only and snapshot bytes are read through shared slices and addresses past the end of the buffer read as empty, so no `&mut` is ever made over bytes the buffer doesn't own or over a shared scratch area.
+#[doc(hidden)]
+#[cfg(not(feature = "safe"))]
+pub enum NP_Overlay<'r, T: ?Sized> {
+    /// Struct cast over buffer bytes that can be changed
+    Cast(NonNull<T>, PhantomData<&'r mut T>),
+    /// Bounds checked view for read only bytes and addresses past the end of the buffer
+    View(NP_Safe_Overlay<'r>)
+}
+
+#[cfg(not(feature = "safe"))]
+impl<'r, T: ?Sized> Clone for NP_Overlay<'r, T> {
+    fn clone(&self) -> Self { *self }
+}
+
+#[cfg(not(feature = "safe"))]
+impl<'r, T: ?Sized> Copy for NP_Overlay<'r, T> { }
+
+#[cfg(not(feature = "safe"))]
+impl<'r, T: ?Sized> Debug for NP_Overlay<'r, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            NP_Overlay::Cast(ptr, _) => f.debug_tuple("NP_Overlay::Cast").field(&(ptr.as_ptr() as *const u8)).finish(),
+            NP_Overlay::View(view) => f.debug_tuple("NP_Overlay::View").field(view).finish()
+        }
+    }
 }
 
+/// Forward methods of an overlay to the pointer struct or the bounds checked view
+#[cfg(not(feature = "safe"))]
+macro_rules! overlay_methods {
+    ($($name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            #[inline(always)]
+            pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+                match self {
+                    // SAFETY: `Cast` is only made over in bounds bytes of memory that can be changed, and the `&mut` doesn't outlive this call
+                    NP_Overlay::Cast(ptr, _) => unsafe { (*ptr.as_ptr()).$name($($arg),*) },
+                    NP_Overlay::View(view) => view.$name($($arg),*)
+                }
+            }
+        )*
+    }
+}
 
+#[cfg(not(feature = "safe"))]
 #[allow(missing_docs)]
-impl NP_Vtable {
+impl<'r> NP_Overlay<'r, dyn NP_Pointer_Bytes> {
+    overlay_methods! {
+        get_addr_value(&self) -> u32;
+        set_addr_value(&self, addr: u32) -> ();
+        is_null(&self) -> bool;
+        is_tombstone(&self) -> bool;
+        get_next_addr(&self) -> u32;
+        set_next_addr(&self, addr: u32) -> ();
+        set_index(&self, index: u8) -> ();
+        get_index(&self) -> u8;
+        set_key_addr(&self, addr: u32) -> ();
+        get_key_addr(&self) -> u32;
+        reset(&self) -> ();
+        get_size(&self) -> usize;
+    }
+    pub fn get_type(&self) -> &str {
+        match self {
+            // SAFETY: see `overlay_methods`
+            NP_Overlay::Cast(ptr, _) => unsafe { (*ptr.as_ptr()).get_type() },
+            NP_Overlay::View(view) => view.get_type()
+        }
+    }
+    pub fn get_key<'key>(&self, memory: &'key NP_Memory) -> &'key str { map_key(self.get_key_addr() as usize, memory) }
+    pub fn get_key_size<'key>(&self, memory: &'key NP_Memory) -> usize { map_key_size(self.get_key_addr() as usize, memory) }
+}
+
+#[cfg(not(feature = "safe"))]
+#[allow(missing_docs)]
+impl<'r> NP_Overlay<'r, dyn NP_Vtable> {
+    overlay_methods! {
+        get_next(&self) -> u32;
+        set_next(&self, value: u32) -> ();
+    }
+}
+
+#[cfg(not(feature = "safe"))]
+#[allow(missing_docs)]
+impl<'r> NP_Overlay<'r, dyn NP_List_Bytes> {
+    overlay_methods! {
+        get_head(&self) -> u32;
+        set_head(&self, head: u32) -> ();
+        get_tail(&self) -> u32;
+        set_tail(&self, tail: u32) -> ();
+    }
+}
+
+#[cfg(not(feature = "safe"))]
+#[allow(missing_docs)]
+impl<'r> NP_Overlay<'r, dyn NP_Map_Bytes> {
+    overlay_methods! {
+        get_head(&self) -> u32;
+        set_head(&self, head: u32) -> ();
+    }
+}
+
+/// Cast the bytes at `address` with a pointer struct of type `S`, or fall back to a bounds checked view of kind `kind`
+#[cfg(not(feature = "safe"))]
+macro_rules! overlay {
+    ($memory:expr, $address:expr, $kind:expr, $S:ty) => {
+        match overlay_bytes::<$S>($memory, $address) {
+            Some(ptr) => NP_Overlay::Cast(ptr, PhantomData),
+            None => NP_Overlay::View(NP_Safe_Overlay { memory: $memory, address: $address, kind: $kind })
+        }
+    }
+}
+
+/// Get the pointer bytes at `address`
+#[doc(hidden)]
+#[inline]
+#[cfg(not(feature = "safe"))]
+pub fn pointer_bytes<'r>(memory: &'r NP_Memory<'r>, address: usize, kind: NP_Overlay_Kind) -> NP_Pointer_Ref<'r> {
+    match (kind, memory.addr_size) {
+        (NP_Overlay_Kind::List_Item, NP_Address_Size::U8)  => overlay!(memory, address, kind, NP_Pointer_List_Item<[u8; 1]>),
+        (NP_Overlay_Kind::List_Item, NP_Address_Size::U16) => overlay!(memory, address, kind, NP_Pointer_List_Item<[u8; 2]>),
+        (NP_Overlay_Kind::List_Item, NP_Address_Size::U32) => overlay!(memory, address, kind, NP_Pointer_List_Item<[u8; 4]>),
+        (NP_Overlay_Kind::Map_Item, NP_Address_Size::U8)  => overlay!(memory, address, kind, NP_Pointer_Map_Item<[u8; 1]>),
+        (NP_Overlay_Kind::Map_Item, NP_Address_Size::U16) => overlay!(memory, address, kind, NP_Pointer_Map_Item<[u8; 2]>),
+        (NP_Overlay_Kind::Map_Item, NP_Address_Size::U32) => overlay!(memory, address, kind, NP_Pointer_Map_Item<[u8; 4]>),
+        (_, NP_Address_Size::U8)  => overlay!(memory, address, NP_Overlay_Kind::Scalar, NP_Pointer_Scalar<[u8; 1]>),
+        (_, NP_Address_Size::U16) => overlay!(memory, address, NP_Overlay_Kind::Scalar, NP_Pointer_Scalar<[u8; 2]>),
+        (_, NP_Address_Size::U32) => overlay!(memory, address, NP_Overlay_Kind::Scalar, NP_Pointer_Scalar<[u8; 4]>)
+    }
+}
+
+/// Get the pointer bytes at `address`
+#[doc(hidden)]
+#[inline(always)]
+#[cfg(feature = "safe")]
+pub fn pointer_bytes<'r>(memory: &'r NP_Memory<'r>, address: usize, kind: NP_Overlay_Kind) -> NP_Pointer_Ref<'r> {
+    NP_Safe_Overlay { memory, address, kind }
+}
+
+/// Get the vtable bytes at `address`
+#[doc(hidden)]
+#[inline(always)]
+#[cfg(not(feature = "safe"))]
+pub fn vtable_bytes<'r>(memory: &'r NP_Memory<'r>, address: usize) -> NP_Vtable_Ref<'r> {
+    match memory.addr_size {
+        NP_Address_Size::U8  => overlay!(memory, address, NP_Overlay_Kind::Vtable, NP_Vtable_Bytes<[u8; 1]>),
+        NP_Address_Size::U16 => overlay!(memory, address, NP_Overlay_Kind::Vtable, NP_Vtable_Bytes<[u8; 2]>),
+        NP_Address_Size::U32 => overlay!(memory, address, NP_Overlay_Kind::Vtable, NP_Vtable_Bytes<[u8; 4]>)
+    }
+}
+
+/// Get the vtable bytes at `address`
+#[doc(hidden)]
+#[inline]
+#[cfg(feature = "safe")]
+pub fn vtable_bytes<'r>(memory: &'r NP_Memory<'r>, address: usize) -> NP_Vtable_Ref<'r> {
+    NP_Safe_Overlay { memory, address, kind: NP_Overlay_Kind::Vtable }
+}
+
+/// Get the list head bytes at `address`
+#[doc(hidden)]
+#[inline(always)]
+#[cfg(not(feature = "safe"))]
+pub fn list_bytes<'r>(memory: &'r NP_Memory<'r>, address: usize) -> NP_List_Ref<'r> {
+    match memory.addr_size {
+        NP_Address_Size::U8  => overlay!(memory, address, NP_Overlay_Kind::List_Head, NP_List_Head<[u8; 1]>),
+        NP_Address_Size::U16 => overlay!(memory, address, NP_Overlay_Kind::List_Head, NP_List_Head<[u8; 2]>),
+        NP_Address_Size::U32 => overlay!(memory, address, NP_Overlay_Kind::List_Head, NP_List_Head<[u8; 4]>)
+    }
+}
+
+/// Get the list head bytes at `address`
+#[doc(hidden)]
+#[inline]
+#[cfg(feature = "safe")]
+pub fn list_bytes<'r>(memory: &'r NP_Memory<'r>, address: usize) -> NP_List_Ref<'r> {
+    NP_Safe_Overlay { memory, address, kind: NP_Overlay_Kind::List_Head }
+}
+
+/// Get the map head bytes at `address`
+#[doc(hidden)]
+#[inline(always)]
+#[cfg(not(feature = "safe"))]
+pub fn map_bytes<'r>(memory: &'r NP_Memory<'r>, address: usize) -> NP_Map_Ref<'r> {
+    match memory.addr_size {
+        NP_Address_Size::U8  => overlay!(memory, address, NP_Overlay_Kind::Map_Head, NP_Map_Head<[u8; 1]>),
+        NP_Address_Size::U16 => overlay!(memory, address, NP_Overlay_Kind::Map_Head, NP_Map_Head<[u8; 2]>),
+        NP_Address_Size::U32 => overlay!(memory, address, NP_Overlay_Kind::Map_Head, NP_Map_Head<[u8; 4]>)
+    }
+}
+
+/// Get the map head bytes at `address`
+#[doc(hidden)]
+#[inline]
+#[cfg(feature = "safe")]
+pub fn map_bytes<'r>(memory: &'r NP_Memory<'r>, address: usize) -> NP_Map_Ref<'r> {
+    NP_Safe_Overlay { memory, address, kind: NP_Overlay_Kind::Map_Head }
+}
+
+/// Bounds checked view of pointer bytes, used in place of the pointer struct casts with the `safe` feature and for bytes that can't be cast without it
+///
+/// Every read and write goes through slices of the buffer, addresses past the end read as zero and writes to them are dropped.  The methods match the pointer traits above but take `&self`, so the view can be copied around like the references it replaces.
+#[doc(hidden)]
+#[derive(Clone, Copy)]
+pub struct NP_Safe_Overlay<'o> {
+    memory: &'o NP_Memory<'o>,
+    address: usize,
+    kind: NP_Overlay_Kind
+}
+
+impl<'o> Debug for NP_Safe_Overlay<'o> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("NP_Safe_Overlay").field("address", &self.address).field("kind", &self.kind).finish()
+    }
+}
+
+#[allow(missing_docs)]
+impl<'o> NP_Safe_Overlay<'o> {
 
     #[inline(always)]
-    pub fn get_next(&self) -> u16 {
-        u16::from_be_bytes(unsafe { *(&self.next as *const [u8] as *const [u8; 2]) }) 
+    fn read_addr(&self, slot: usize) -> u32 {
+        let size = self.memory.addr_size.bytes();
+        match self.memory.get_bytes(self.address.saturating_add(slot * size), size) {
+            Ok(bytes) => bytes.iter().fold(0u32, |acc, byte| (acc << 8) | *byte as u32),
+            Err(_) => 0
+        }
     }
 
     #[inline(always)]
-    pub fn set_next(&mut self, value: u16) {
-        let bytes = value.to_be_bytes();
-        self.next[0] = bytes[0];
-        self.next[1] = bytes[1];
+    fn write_addr(&self, slot: usize, value: u32) {
+        let size = self.memory.addr_size.bytes();
+        if let Ok(bytes) = self.memory.get_bytes_mut(self.address.saturating_add(slot * size), size) {
+            for x in 0..size {
+                bytes[x] = (value >> (8 * (size - 1 - x))) as u8;
+            }
+        }
+    }
+
+    pub fn get_type(&self) -> &str {
+        match self.kind {
+            NP_Overlay_Kind::Scalar    => "Scalar",
+            NP_Overlay_Kind::List_Item => "List Item",
+            NP_Overlay_Kind::Map_Item  => "Map Item",
+            _ => ""
+        }
+    }
+    pub fn get_addr_value(&self) -> u32 { match self.read_addr(0) { NP_NULL_ADDR => 0, addr if addr == self.memory.addr_size.max_buffer_size() as u32 => 0, addr => addr } }
+    pub fn set_addr_value(&self, addr: u32) { self.write_addr(0, addr) }
+    pub fn is_null(&self) -> bool { self.read_addr(0) == NP_NULL_ADDR }
+    pub fn is_tombstone(&self) -> bool { self.read_addr(0) == self.memory.addr_size.max_buffer_size() as u32 }
+    pub fn get_next_addr(&self) -> u32 {
+        match self.kind {
+            NP_Overlay_Kind::List_Item | NP_Overlay_Kind::Map_Item => self.read_addr(1),
+            _ => 0
+        }
+    }
+    pub fn set_next_addr(&self, addr: u32) {
+        match self.kind {
+            NP_Overlay_Kind::List_Item | NP_Overlay_Kind::Map_Item => self.write_addr(1, addr),
+            _ => {}
+        }
+    }
+    pub fn get_index(&self) -> u8 {
+        match self.kind {
+            NP_Overlay_Kind::List_Item => self.memory.read_bytes().get(self.address.saturating_add(2 * self.memory.addr_size.bytes())).copied().unwrap_or(0),
+            _ => 0
+        }
+    }
+    pub fn set_index(&self, index: u8) {
+        if self.kind == NP_Overlay_Kind::List_Item {
+            if let Ok(bytes) = self.memory.get_bytes_mut(self.address.saturating_add(2 * self.memory.addr_size.bytes()), 1) {
+                bytes[0] = index;
+            }
+        }
+    }
+    pub fn get_key_addr(&self) -> u32 {
+        match self.kind {
+            NP_Overlay_Kind::Map_Item => self.read_addr(2),
+            _ => 0
+        }
+    }
+    pub fn set_key_addr(&self, addr: u32) {
+        if self.kind == NP_Overlay_Kind::Map_Item {
+            self.write_addr(2, addr);
+        }
+    }
+    pub fn reset(&self) {
+        if let Ok(bytes) = self.memory.get_bytes_mut(self.address, self.get_size()) {
+            for byte in bytes.iter_mut() {
+                *byte = 0;
+            }
+        }
+    }
+    pub fn get_size(&self) -> usize {
+        let size = self.memory.addr_size.bytes();
+        match self.kind {
+            NP_Overlay_Kind::Scalar    => size,
+            NP_Overlay_Kind::List_Item => size * 2 + 1,
+            NP_Overlay_Kind::Map_Item  => size * 3,
+            NP_Overlay_Kind::List_Head => size * 2,
+            NP_Overlay_Kind::Map_Head  => size,
+            NP_Overlay_Kind::Vtable    => size * 5
+        }
+    }
+    pub fn get_key<'key>(&self, memory: &'key NP_Memory) -> &'key str { map_key(self.get_key_addr() as usize, memory) }
+    pub fn get_key_size<'key>(&self, memory: &'key NP_Memory) -> usize { map_key_size(self.get_key_addr() as usize, memory) }
+    pub fn get_head(&self) -> u32 { self.read_addr(0) }
+    pub fn set_head(&self, head: u32) { self.write_addr(0, head) }
+    pub fn get_tail(&self) -> u32 { self.read_addr(1) }
+    pub fn set_tail(&self, tail: u32) { self.write_addr(1, tail) }
+    pub fn get_next(&self) -> u32 { self.read_addr(4) }
+    pub fn set_next(&self, value: u32) { self.write_addr(4, value) }
+}
+
+/// Pointer to the buffer bytes at `address` for one of the pointer structs above, `None` if the bytes can't be cast
+#[doc(hidden)]
+#[inline]
+#[cfg(not(feature = "safe"))]
+pub fn overlay_bytes<T>(memory: &NP_Memory, address: usize) -> Option<NonNull<T>> {
+    // damaged or hostile buffers can have addresses past the end of the bytes, those read as empty pointers
+    if address.saturating_add(core::mem::size_of::<T>()) > memory.read_bytes().len() {
+        return None;
+    }
+    // read only bytes are never cast, so there's no `&mut` over them
+    let bytes = memory.write_ptr()?;
+    // SAFETY: `address + size_of::<T>()` is inside the bytes and the pointer structs are byte arrays with an alignment of 1
+    NonNull::new(unsafe { bytes.add(address) } as *mut T)
+}
+
+/// How many value bytes are stored directly in the pointer address, `None` if the value gets it's own allocation
+///
+/// Inline values need a spare address byte to set a marker bit, so the address is never zero.  Buffers with addresses too small for the value fall back to allocating it.
+#[doc(hidden)]
+#[inline(always)]
+pub fn inline_size(schema: &NP_Parsed_Schema, memory: &NP_Memory) -> Option<usize> {
+    let size = match schema {
+        NP_Parsed_Schema::Int8 { inline: true, .. } | NP_Parsed_Schema::Uint8 { inline: true, .. } | NP_Parsed_Schema::Boolean { inline: true, .. } => 1,
+        NP_Parsed_Schema::Int16 { inline: true, .. } | NP_Parsed_Schema::Uint16 { inline: true, .. } => 2,
+        _ => return None
+    };
+    if memory.addr_size.bytes() > size { Some(size) } else { None }
+}
+
+/// Pack inline value bytes into a pointer address
+#[doc(hidden)]
+#[inline(always)]
+pub fn inline_encode(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(1u32, |acc, byte| (acc << 8) | *byte as u32)
+}
+
+/// Unpack inline value bytes from a pointer address
+#[doc(hidden)]
+#[inline(always)]
+pub fn inline_decode(addr: u32, bytes: &mut [u8]) {
+    let len = bytes.len();
+    for x in 0..len {
+        bytes[x] = (addr >> (8 * (len - 1 - x))) as u8;
     }
 }
 
@@ -247,6 +744,40 @@ pub struct NP_Cursor {
     pub parent_schema_addr: usize
 }
 
+/// Collection being exported by `NP_Cursor::json_encode`, with the items left to export
+struct NP_JSON_Frame<'item> {
+    json: NP_JSON,
+    items: Vec<(&'item str, Option<NP_Cursor>)>,
+    next: usize
+}
+
+/// Collection being copied by `NP_Cursor::compact`, with the items left to copy
+struct NP_Compact_Frame<'item> {
+    from_schema: usize,
+    to_cursor: NP_Cursor,
+    items: Vec<(usize, &'item str, NP_Cursor)>,
+    next: usize,
+    /// last vtable made in the new buffer for tables and tuples, and it's index
+    vtable: (usize, usize)
+}
+
+impl<'item> NP_Compact_Frame<'item> {
+
+    /// Address of a table column or tuple value in the new buffer, making the vtable it's in if needed
+    fn vtable_slot(&mut self, idx: usize, make_next_vtable: fn(usize, &NP_Memory) -> Result<usize, NP_Error>, to_memory: &NP_Memory) -> Result<usize, NP_Error> {
+
+        let v_table =  idx / 4; // which vtable
+        let v_table_idx = idx % 4; // which index on the selected vtable
+
+        if self.vtable.1 < v_table {
+            self.vtable.0 = make_next_vtable(self.vtable.0, to_memory)?;
+            self.vtable.1 += 1;
+        }
+
+        Ok(self.vtable.0 + (v_table_idx * to_memory.addr_size.bytes()))
+    }
+}
+
 impl NP_Cursor {
 
     /// Create a new cursor
@@ -260,34 +791,77 @@ impl NP_Cursor {
 
     #[inline(always)]
     /// Get the value bytes of this cursor
-    pub fn get_value<'value>(&self, memory: &'value NP_Memory<'value>) -> &'value mut dyn NP_Pointer_Bytes {
-        let ptr = memory.write_bytes().as_mut_ptr();
+    pub fn get_value<'value>(&self, memory: &'value NP_Memory<'value>) -> NP_Pointer_Ref<'value> {
         // if requesting root pointer or address is higher than buffer length
-        if self.buff_addr == ROOT_PTR_ADDR || self.buff_addr > memory.read_bytes().len() {
-            unsafe { &mut *(ptr.add(ROOT_PTR_ADDR) as *mut NP_Pointer_Scalar) }
+        let (address, parent) = if self.buff_addr == memory.root || self.buff_addr > memory.read_bytes().len() {
+            (memory.root, &NP_Parsed_Schema::None)
         } else {
-            match memory.schema[self.parent_schema_addr] {
-                NP_Parsed_Schema::List { .. } => {
-                    unsafe { &mut *(ptr.add(self.buff_addr) as *mut NP_Pointer_List_Item) }
-                },
-                NP_Parsed_Schema::Map { .. } => {
-                    unsafe { &mut *(ptr.add(self.buff_addr) as *mut NP_Pointer_Map_Item) }
-                },
-                _ => { // parent is scalar, table or tuple
-                    unsafe { &mut *(ptr.add(self.buff_addr) as *mut NP_Pointer_Scalar) }
-                }
-            }                   
-        }
+            (self.buff_addr, &memory.schema[self.parent_schema_addr])
+        };
+        let kind = match parent {
+            NP_Parsed_Schema::List { .. } => NP_Overlay_Kind::List_Item,
+            NP_Parsed_Schema::Map { .. }  => NP_Overlay_Kind::Map_Item,
+            // parent is scalar, table or tuple
+            _ => NP_Overlay_Kind::Scalar
+        };
+        pointer_bytes(memory, address, kind)
     }
 
     /// Exports this pointer and all it's descendants into a JSON object.
     /// This will create a copy of the underlying data and return default values where there isn't data.
     /// 
+    /// Collections are walked with a stack on the heap, deeply nested buffers don't use more of the call stack.
+    /// 
     pub fn json_encode(cursor: &NP_Cursor, memory: &NP_Memory) -> NP_JSON {
 
-        match memory.schema[cursor.schema_addr].get_type_key() {
+        let mut stack: Vec<NP_JSON_Frame> = Vec::new();
+
+        let mut value = Self::json_value(cursor, memory, &mut stack);
+
+        loop {
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => return value.unwrap_or(NP_JSON::Null)
+            };
+
+            // finished item goes into the collection it came from
+            if let Some(json) = value.take() {
+                let key = frame.items[frame.next - 1].0;
+                match &mut frame.json {
+                    NP_JSON::Array(list) => { list.push(json); },
+                    NP_JSON::Dictionary(map) => { map.insert(String::from(key), json); },
+                    _ => { }
+                }
+            }
+
+            match frame.items.get(frame.next) {
+                Some((_key, item)) => {
+                    let item = *item;
+                    frame.next += 1;
+                    value = match item {
+                        Some(item_cursor) => Self::json_value(&item_cursor, memory, &mut stack),
+                        None => Some(NP_JSON::Null)
+                    };
+                },
+                None => {
+                    value = stack.pop().map(|frame| frame.json);
+                }
+            }
+        }
+    }
+
+    /// JSON for a single value, collections are pushed onto the stack with their items and return `None`
+    fn json_value<'item>(cursor: &NP_Cursor, memory: &'item NP_Memory, stack: &mut Vec<NP_JSON_Frame<'item>>) -> Option<NP_JSON> {
+
+        if cursor.get_value(memory).is_null() {
+            return Some(NP_JSON::Null);
+        }
+
+        let type_key = *memory.schema[cursor.schema_addr].get_type_key();
+
+        let json = match type_key {
             NP_TypeKeys::None           => { NP_JSON::Null },
-            NP_TypeKeys::Any            => { NP_JSON::Null },
+            NP_TypeKeys::Any            => {     NP_Any::to_json(cursor, memory) },
             NP_TypeKeys::UTF8String     => { NP_String::to_json(cursor, memory) },
             NP_TypeKeys::Bytes          => {  NP_Bytes::to_json(cursor, memory) },
             NP_TypeKeys::Int8           => {        i8::to_json(cursor, memory) },
@@ -311,16 +885,83 @@ impl NP_Cursor {
             NP_TypeKeys::Map            => {    NP_Map::to_json(cursor, memory) },
             NP_TypeKeys::List           => {   NP_List::to_json(cursor, memory) },
             NP_TypeKeys::Tuple          => {  NP_Tuple::to_json(cursor, memory) }
-        }
+        };
 
+        match (type_key, &json) {
+            (NP_TypeKeys::Table, NP_JSON::Dictionary(_)) | (NP_TypeKeys::Map, NP_JSON::Dictionary(_)) |
+            (NP_TypeKeys::List, NP_JSON::Array(_)) | (NP_TypeKeys::Tuple, NP_JSON::Array(_)) => {
+                // empty list slots and table or tuple values without a pointer are exported as null
+                let items = Self::collection_slots(cursor, memory, false).into_iter().map(|(_idx, key, item)| (key, item)).collect();
+                stack.push(NP_JSON_Frame { json, items, next: 0 });
+                None
+            },
+            _ => Some(json)
+        }
     }
 
     /// Compact from old cursor and memory into new cursor and memory
     /// 
+    /// Items are copied in the same order a depth first walk would copy them, using a stack on the heap instead of the call stack.
+    /// 
     pub fn compact(from_cursor: NP_Cursor, from_memory: &NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory) -> Result<NP_Cursor, NP_Error> {
 
-        match from_memory.schema[from_cursor.schema_addr].get_type_key() {
-            NP_TypeKeys::Any           => { Ok(to_cursor) }
+        let mut stack: Vec<NP_Compact_Frame> = Vec::new();
+
+        let new_cursor = Self::compact_value(from_cursor, from_memory, to_cursor, to_memory, &mut stack)?;
+
+        while let Some(frame) = stack.last_mut() {
+
+            let (idx, key, item) = match frame.items.get(frame.next) {
+                Some(item) => *item,
+                None => { stack.pop(); continue; }
+            };
+            frame.next += 1;
+
+            let new_item = match &from_memory.schema[frame.from_schema] {
+                NP_Parsed_Schema::Table { columns, .. } => {
+                    let item_addr = frame.vtable_slot(idx, NP_Table::make_next_vtable, to_memory)?;
+                    NP_Cursor::new(item_addr, columns[idx].2, frame.to_cursor.schema_addr)
+                },
+                NP_Parsed_Schema::Tuple { values, .. } => {
+                    let item_addr = frame.vtable_slot(idx, NP_Tuple::make_next_vtable, to_memory)?;
+                    NP_Cursor::new(item_addr, values[idx], frame.to_cursor.schema_addr)
+                },
+                NP_Parsed_Schema::List { .. } => {
+                    opt_err(NP_List::push(&frame.to_cursor, to_memory, Some(idx))?)?.1
+                },
+                NP_Parsed_Schema::Map { .. } => {
+                    NP_Map::insert(&frame.to_cursor, to_memory, key)?
+                },
+                _ => return Err(NP_Error::new("unreachable"))
+            };
+
+            Self::compact_value(item, from_memory, new_item, to_memory, &mut stack)?;
+        }
+
+        Ok(new_cursor)
+    }
+
+    /// Compact a single value, collections are created in the new buffer and pushed onto the stack with their items
+    fn compact_value<'item>(from_cursor: NP_Cursor, from_memory: &'item NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory, stack: &mut Vec<NP_Compact_Frame<'item>>) -> Result<NP_Cursor, NP_Error> {
+
+        let from_value = from_cursor.get_value(from_memory);
+
+        if from_value.is_null() {
+            to_cursor.get_value(to_memory).set_addr_value(NP_NULL_ADDR);
+            return Ok(to_cursor);
+        }
+
+        if from_value.is_tombstone() {
+            if to_memory.tombstones == NP_Tombstones::Keep {
+                to_cursor.get_value(to_memory).set_addr_value(NP_TOMBSTONE_ADDR);
+            }
+            return Ok(to_cursor);
+        }
+
+        let type_key = *from_memory.schema[from_cursor.schema_addr].get_type_key();
+
+        let new_cursor = match type_key {
+            NP_TypeKeys::Any           => {    NP_Any::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             NP_TypeKeys::UTF8String    => { NP_String::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             NP_TypeKeys::Bytes         => {  NP_Bytes::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             NP_TypeKeys::Int8          => {        i8::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
@@ -345,7 +986,148 @@ impl NP_Cursor {
             NP_TypeKeys::List          => {   NP_List::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             NP_TypeKeys::Tuple         => {  NP_Tuple::do_compact(from_cursor, from_memory, to_cursor, to_memory) }
             _ => { Err(NP_Error::new("unreachable")) }
+        }?;
+
+        match type_key {
+            NP_TypeKeys::Table | NP_TypeKeys::Tuple | NP_TypeKeys::List | NP_TypeKeys::Map if from_value.get_addr_value() != 0 => {
+                let vtable = match type_key {
+                    NP_TypeKeys::Table | NP_TypeKeys::Tuple => new_cursor.get_value(to_memory).get_addr_value() as usize,
+                    _ => 0
+                };
+                stack.push(NP_Compact_Frame {
+                    from_schema: from_cursor.schema_addr,
+                    to_cursor: new_cursor,
+                    items: Self::collection_items(&from_cursor, from_memory),
+                    next: 0,
+                    vtable: (vtable, 0)
+                });
+            },
+            _ => { }
+        }
+
+        Ok(new_cursor)
+    }
+
+    /// Get the items of a collection that have a pointer, with their column, index or key
+    fn collection_items<'item>(cursor: &NP_Cursor, memory: &'item NP_Memory) -> Vec<(usize, &'item str, NP_Cursor)> {
+        Self::collection_slots(cursor, memory, true).into_iter().filter_map(|(idx, key, item)| Some((idx, key, item?))).collect()
+    }
+
+    /// Get the items of a collection with their column, index or key.  Table and tuple values without a pointer are `None`, so are empty list slots unless `only_real` is set.
+    fn collection_slots<'item>(cursor: &NP_Cursor, memory: &'item NP_Memory, only_real: bool) -> Vec<(usize, &'item str, Option<NP_Cursor>)> {
+
+        let mut items = Vec::new();
+
+        match memory.schema[cursor.schema_addr].get_type_key() {
+            NP_TypeKeys::Table => {
+                let mut table = NP_Table::new_iter(cursor, memory);
+                while let Some((idx, key, item)) = table.step_iter(memory) {
+                    items.push((idx, key, item));
+                }
+            },
+            NP_TypeKeys::Tuple => {
+                let mut tuple = NP_Tuple::new_iter(cursor, memory);
+                while let Some((idx, item)) = tuple.step_iter(memory) {
+                    items.push((idx, "", item));
+                }
+            },
+            NP_TypeKeys::List => {
+                let mut list = NP_List::new_iter(cursor, memory, only_real, 0);
+                while let Some((idx, item)) = list.step_iter(memory) {
+                    items.push((idx, "", item));
+                }
+            },
+            NP_TypeKeys::Map => {
+                let mut map = NP_Map::new_iter(cursor, memory);
+                while let Some((key, item)) = map.step_iter(memory) {
+                    items.push((0, key, Some(item)));
+                }
+            },
+            _ => { }
+        }
+
+        items
+    }
+
+    /// Check if this cursor or any of it's descendants hold a value, explicit nulls and kept tombstones count as values
+    pub fn has_values(cursor: &NP_Cursor, memory: &NP_Memory) -> bool {
+
+        let value = cursor.get_value(memory);
+
+        if value.is_null() {
+            return true;
+        }
+
+        if value.is_tombstone() {
+            return memory.tombstones == NP_Tombstones::Keep;
         }
+
+        if value.get_addr_value() == 0 {
+            return false;
+        }
+
+        match memory.schema[cursor.schema_addr].get_type_key() {
+            NP_TypeKeys::None => false,
+            NP_TypeKeys::Table | NP_TypeKeys::Tuple | NP_TypeKeys::List | NP_TypeKeys::Map => {
+                Self::collection_items(cursor, memory).iter().any(|(_idx, _key, item)| Self::has_values(item, memory))
+            },
+            _ => true
+        }
+    }
+
+    /// Copy from old cursor and memory into new cursor and memory so the new bytes only depend on the values.
+    ///
+    /// Table columns and tuple values are copied in schema order, list items by index and map items by key.  Collections without values and list or map items without values are left out.
+    ///
+    pub fn canonical(from_cursor: NP_Cursor, from_memory: &NP_Memory, to_cursor: NP_Cursor, to_memory: &NP_Memory) -> Result<NP_Cursor, NP_Error> {
+
+        if Self::has_values(&from_cursor, from_memory) == false {
+            return Ok(to_cursor);
+        }
+
+        let type_key = match &from_memory.schema[from_cursor.schema_addr] {
+            // null pointers and tombstones are copied as they are
+            _ if from_cursor.get_value(from_memory).is_null() || from_cursor.get_value(from_memory).is_tombstone() => NP_TypeKeys::None,
+            // sortable tuples always have every value in place, compaction already keeps them in order
+            NP_Parsed_Schema::Tuple { sortable: true, .. } => NP_TypeKeys::None,
+            schema => *schema.get_type_key()
+        };
+
+        let mut items = Self::collection_items(&from_cursor, from_memory);
+        items.retain(|(_idx, _key, item)| Self::has_values(item, from_memory));
+
+        match type_key {
+            NP_TypeKeys::Table => {
+                for (idx, _key, item) in items {
+                    let new_item = opt_err(NP_Table::select_column(to_cursor, idx, true, to_memory)?)?;
+                    Self::canonical(item, from_memory, new_item, to_memory)?;
+                }
+            },
+            NP_TypeKeys::Tuple => {
+                for (idx, _key, item) in items {
+                    let new_item = opt_err(NP_Tuple::select(to_cursor, idx, true, to_memory)?)?;
+                    Self::canonical(item, from_memory, new_item, to_memory)?;
+                }
+            },
+            NP_TypeKeys::List => {
+                NP_List::make_list(&to_cursor, to_memory)?;
+                for (idx, _key, item) in items {
+                    let (_new_index, new_item) = opt_err(NP_List::push(&to_cursor, to_memory, Some(idx))?)?;
+                    Self::canonical(item, from_memory, new_item, to_memory)?;
+                }
+            },
+            NP_TypeKeys::Map => {
+                // new map items go in front of the head, so insert the last key first
+                items.sort_by(|a, b| b.1.cmp(a.1));
+                for (_idx, key, item) in items {
+                    let new_item = NP_Map::insert(&to_cursor, to_memory, key)?;
+                    Self::canonical(item, from_memory, new_item, to_memory)?;
+                }
+            },
+            _ => return Self::compact(from_cursor, from_memory, to_cursor, to_memory)
+        }
+
+        Ok(to_cursor)
     }
 
     /// Set default for this value.  Not related to the schema default, this is the default value for this data type
@@ -385,48 +1167,198 @@ impl NP_Cursor {
 
     /// Calculate the number of bytes used by this pointer and it's descendants.
     /// 
+    /// Collections are walked with a stack on the heap, deeply nested buffers don't use more of the call stack.
+    /// 
     pub fn calc_size(cursor: &NP_Cursor, memory: &NP_Memory) -> Result<usize, NP_Error> {
-        
-        let value = cursor.get_value(&memory);
-    
-        // size of pointer
-        let base_size = value.get_size();
-
-        // pointer is in buffer but has no value set
-        if value.get_addr_value() == 0 { // no value, just base size
-            return Ok(base_size);
-        }
-
-        // get the size of the value based on schema
-        let type_size = match memory.schema[cursor.schema_addr].get_type_key() {
-            NP_TypeKeys::None         => { Ok(0) },
-            NP_TypeKeys::Any          => { Ok(0) },
-            NP_TypeKeys::UTF8String   => { NP_String::get_size(cursor, memory) },
-            NP_TypeKeys::Bytes        => {  NP_Bytes::get_size(cursor, memory) },
-            NP_TypeKeys::Int8         => {        i8::get_size(cursor, memory) },
-            NP_TypeKeys::Int16        => {       i16::get_size(cursor, memory) },
-            NP_TypeKeys::Int32        => {       i32::get_size(cursor, memory) },
-            NP_TypeKeys::Int64        => {       i64::get_size(cursor, memory) },
-            NP_TypeKeys::Uint8        => {        u8::get_size(cursor, memory) },
-            NP_TypeKeys::Uint16       => {       u16::get_size(cursor, memory) },
-            NP_TypeKeys::Uint32       => {       u32::get_size(cursor, memory) },
-            NP_TypeKeys::Uint64       => {       u64::get_size(cursor, memory) },
-            NP_TypeKeys::Float        => {       f32::get_size(cursor, memory) },
-            NP_TypeKeys::Double       => {       f64::get_size(cursor, memory) },
-            NP_TypeKeys::Decimal      => {    NP_Dec::get_size(cursor, memory) },
-            NP_TypeKeys::Boolean      => {      bool::get_size(cursor, memory) },
-            NP_TypeKeys::Geo          => {    NP_Geo::get_size(cursor, memory) },
-            NP_TypeKeys::Uuid         => {  _NP_UUID::get_size(cursor, memory) },
-            NP_TypeKeys::Ulid         => {  _NP_ULID::get_size(cursor, memory) },
-            NP_TypeKeys::Date         => {   NP_Date::get_size(cursor, memory) },
-            NP_TypeKeys::Enum         => {   NP_Enum::get_size(cursor, memory) },
-            NP_TypeKeys::Table        => {  NP_Table::get_size(cursor, memory) },
-            NP_TypeKeys::Map          => {    NP_Map::get_size(cursor, memory) },
-            NP_TypeKeys::List         => {   NP_List::get_size(cursor, memory) },
-            NP_TypeKeys::Tuple        => {  NP_Tuple::get_size(cursor, memory) }
-        }?;
 
-        Ok(type_size + base_size)
+        let mut acc_size = 0usize;
+
+        // cursors left to count, and if their pointer is counted with them
+        let mut stack: Vec<(NP_Cursor, bool)> = Vec::new();
+        stack.push((*cursor, true));
+
+        while let Some((cursor, with_pointer)) = stack.pop() {
+
+            let value = cursor.get_value(memory);
+
+            // size of pointer
+            if with_pointer {
+                acc_size += value.get_size();
+            }
+
+            // pointer is in buffer but has no value set
+            if value.get_addr_value() == 0 { // no value, just base size
+                continue;
+            }
+
+            // get the size of the value based on schema
+            let type_key = *memory.schema[cursor.schema_addr].get_type_key();
+            acc_size += match type_key {
+                NP_TypeKeys::None         => { Ok(0) },
+                NP_TypeKeys::Any          => {    NP_Any::get_size(&cursor, memory) },
+                NP_TypeKeys::UTF8String   => { NP_String::get_size(&cursor, memory) },
+                NP_TypeKeys::Bytes        => {  NP_Bytes::get_size(&cursor, memory) },
+                NP_TypeKeys::Int8         => {        i8::get_size(&cursor, memory) },
+                NP_TypeKeys::Int16        => {       i16::get_size(&cursor, memory) },
+                NP_TypeKeys::Int32        => {       i32::get_size(&cursor, memory) },
+                NP_TypeKeys::Int64        => {       i64::get_size(&cursor, memory) },
+                NP_TypeKeys::Uint8        => {        u8::get_size(&cursor, memory) },
+                NP_TypeKeys::Uint16       => {       u16::get_size(&cursor, memory) },
+                NP_TypeKeys::Uint32       => {       u32::get_size(&cursor, memory) },
+                NP_TypeKeys::Uint64       => {       u64::get_size(&cursor, memory) },
+                NP_TypeKeys::Float        => {       f32::get_size(&cursor, memory) },
+                NP_TypeKeys::Double       => {       f64::get_size(&cursor, memory) },
+                NP_TypeKeys::Decimal      => {    NP_Dec::get_size(&cursor, memory) },
+                NP_TypeKeys::Boolean      => {      bool::get_size(&cursor, memory) },
+                NP_TypeKeys::Geo          => {    NP_Geo::get_size(&cursor, memory) },
+                NP_TypeKeys::Uuid         => {  _NP_UUID::get_size(&cursor, memory) },
+                NP_TypeKeys::Ulid         => {  _NP_ULID::get_size(&cursor, memory) },
+                NP_TypeKeys::Date         => {   NP_Date::get_size(&cursor, memory) },
+                NP_TypeKeys::Enum         => {   NP_Enum::get_size(&cursor, memory) },
+                NP_TypeKeys::Table        => {  NP_Table::get_size(&cursor, memory) },
+                NP_TypeKeys::Map          => {    NP_Map::get_size(&cursor, memory) },
+                NP_TypeKeys::List         => {   NP_List::get_size(&cursor, memory) },
+                NP_TypeKeys::Tuple        => {  NP_Tuple::get_size(&cursor, memory) }
+            }?;
+
+            for (_idx, _key, item) in Self::collection_items(&cursor, memory) {
+                match type_key {
+                    // scalar cursor is part of vtable
+                    NP_TypeKeys::Table | NP_TypeKeys::Tuple => { stack.push((item, false)); },
+                    NP_TypeKeys::Map => {
+                        acc_size += 1; // length byte
+                        acc_size += item.get_value(memory).get_key_size(memory);
+                        stack.push((item, true));
+                    },
+                    _ => { stack.push((item, true)); }
+                }
+            }
+        }
+
+        Ok(acc_size)
+    }
+
+    /// Compare the values at two cursors, ignoring where they are stored in their buffers.
+    /// 
+    /// Values that aren't set are equal to values set to their schema default.  Cursors are `None` for values that aren't in the buffer.
+    /// 
+    pub fn deep_eq(a: Option<NP_Cursor>, a_schema: usize, a_memory: &NP_Memory, b: Option<NP_Cursor>, b_schema: usize, b_memory: &NP_Memory) -> Result<bool, NP_Error> {
+
+        let a = a.filter(|cursor| cursor.get_value(a_memory).get_addr_value() != 0);
+        let b = b.filter(|cursor| cursor.get_value(b_memory).get_addr_value() != 0);
+
+        match (&a_memory.schema[a_schema], &b_memory.schema[b_schema]) {
+            (NP_Parsed_Schema::Table { columns: a_columns, .. }, NP_Parsed_Schema::Table { columns: b_columns, .. }) => {
+                if a_columns.len() != b_columns.len() {
+                    return Ok(false);
+                }
+                for x in 0..a_columns.len() {
+                    if a_columns[x].1 != b_columns[x].1 {
+                        return Ok(false);
+                    }
+                    let a_child = match a { Some(cursor) => NP_Table::select_column(cursor, x, false, a_memory)?, None => None };
+                    let b_child = match b { Some(cursor) => NP_Table::select_column(cursor, x, false, b_memory)?, None => None };
+                    if !Self::deep_eq(a_child, a_columns[x].2, a_memory, b_child, b_columns[x].2, b_memory)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            },
+            (NP_Parsed_Schema::Tuple { values: a_values, .. }, NP_Parsed_Schema::Tuple { values: b_values, .. }) => {
+                if a_values.len() != b_values.len() {
+                    return Ok(false);
+                }
+                for x in 0..a_values.len() {
+                    let a_child = match a { Some(cursor) => NP_Tuple::select(cursor, x, false, a_memory)?, None => None };
+                    let b_child = match b { Some(cursor) => NP_Tuple::select(cursor, x, false, b_memory)?, None => None };
+                    if !Self::deep_eq(a_child, a_values[x], a_memory, b_child, b_values[x], b_memory)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            },
+            (NP_Parsed_Schema::List { of: a_of, .. }, NP_Parsed_Schema::List { of: b_of, .. }) => {
+                let a_items = Self::list_items(a, a_memory);
+                let b_items = Self::list_items(b, b_memory);
+                Self::deep_eq_items(&a_items, *a_of, a_memory, &b_items, *b_of, b_memory)
+            },
+            (NP_Parsed_Schema::Map { value: a_value, .. }, NP_Parsed_Schema::Map { value: b_value, .. }) => {
+                let a_items = Self::map_items(a, a_memory);
+                let b_items = Self::map_items(b, b_memory);
+                Self::deep_eq_items(&a_items, *a_value, a_memory, &b_items, *b_value, b_memory)
+            },
+            (a_type, b_type) => {
+                if a_type.get_type_key() != b_type.get_type_key() {
+                    return Ok(false);
+                }
+                match a_type.get_type_key() {
+                    NP_TypeKeys::None => Ok(true),
+                    NP_TypeKeys::Any => {
+                        let a_value = match a { Some(cursor) => NP_AnyValue::into_value(&cursor, a_memory)?, None => None };
+                        let b_value = match b { Some(cursor) => NP_AnyValue::into_value(&cursor, b_memory)?, None => None };
+                        Ok(a_value == b_value)
+                    },
+                    _ => Ok(Self::scalar_bytes(a, a_schema, a_memory)? == Self::scalar_bytes(b, b_schema, b_memory)?)
+                }
+            }
+        }
+    }
+
+    /// Sortable bytes of a scalar, `None` if it isn't set and has no default
+    fn scalar_bytes(cursor: Option<NP_Cursor>, schema_addr: usize, memory: &NP_Memory) -> Result<Option<Vec<u8>>, NP_Error> {
+        if cursor.is_none() && !memory.schema[schema_addr].has_default() {
+            return Ok(None);
+        }
+        let mut bytes: Vec<u8> = Vec::new();
+        crate::sortable::encode(cursor, schema_addr, memory, &mut bytes)?;
+        Ok(Some(bytes))
+    }
+
+    fn list_items(cursor: Option<NP_Cursor>, memory: &NP_Memory) -> Vec<(Vec<u8>, NP_Cursor)> {
+        let mut items: Vec<(Vec<u8>, NP_Cursor)> = Vec::new();
+        if let Some(cursor) = cursor {
+            let mut list_iter = NP_List::new_iter(&cursor, memory, true, 0);
+            while let Some((index, item)) = list_iter.step_iter(memory) {
+                if let Some(item) = item {
+                    items.push(((index as u32).to_be_bytes().to_vec(), item));
+                }
+            }
+        }
+        items
+    }
+
+    fn map_items(cursor: Option<NP_Cursor>, memory: &NP_Memory) -> Vec<(Vec<u8>, NP_Cursor)> {
+        let mut items: Vec<(Vec<u8>, NP_Cursor)> = Vec::new();
+        if let Some(cursor) = cursor {
+            let mut map_iter = NP_Map::new_iter(&cursor, memory);
+            while let Some((key, item)) = map_iter.step_iter(memory) {
+                items.push((key.as_bytes().to_vec(), item));
+            }
+        }
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        items
+    }
+
+    /// Compare collection items with the same keys, items only in one collection are compared against an unset value
+    fn deep_eq_items(a_items: &[(Vec<u8>, NP_Cursor)], a_schema: usize, a_memory: &NP_Memory, b_items: &[(Vec<u8>, NP_Cursor)], b_schema: usize, b_memory: &NP_Memory) -> Result<bool, NP_Error> {
+        let (mut x, mut y) = (0usize, 0usize);
+        while x < a_items.len() || y < b_items.len() {
+            let (a_child, b_child) = if y >= b_items.len() || (x < a_items.len() && a_items[x].0 < b_items[y].0) {
+                x += 1;
+                (Some(a_items[x - 1].1), None)
+            } else if x >= a_items.len() || b_items[y].0 < a_items[x].0 {
+                y += 1;
+                (None, Some(b_items[y - 1].1))
+            } else {
+                x += 1;
+                y += 1;
+                (Some(a_items[x - 1].1), Some(b_items[y - 1].1))
+            };
+            if !Self::deep_eq(a_child, a_schema, a_memory, b_child, b_schema, b_memory)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
     }
 }
 
@@ -467,25 +1399,25 @@ pub trait NP_Value<'value> {
     /// 
     fn set_value<'set>(_cursor: NP_Cursor, _memory: &'set NP_Memory, _value: Self) -> Result<NP_Cursor, NP_Error> where Self: 'set + Sized {
         let message = "This type doesn't support set_value!".to_owned();
-        Err(NP_Error::new(message.as_str()))
+        Err(NP_Error::SchemaMismatch(message))
     }
 
     /// Pull the data from the buffer and convert into type
     /// 
     fn into_value(_cursor: &NP_Cursor, _memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
         let message = "This type doesn't support into!".to_owned();
-        Err(NP_Error::new(message.as_str()))
+        Err(NP_Error::SchemaMismatch(message))
     }
 
-    /// Convert this type into a JSON value (recursive for collections)
+    /// Convert this type into a JSON value, collections return an empty array or object and `NP_Cursor::json_encode` adds their items
     /// 
     fn to_json(_cursor: &NP_Cursor, _memory: &'value NP_Memory) -> NP_JSON;
 
-    /// Calculate the size of this pointer and it's children (recursive for collections)
+    /// Calculate the size of this value without it's pointer, collections only count their own bytes and `NP_Cursor::calc_size` adds their items
     /// 
     fn get_size(cursor: &'value NP_Cursor, memory: &'value NP_Memory<'value>) -> Result<usize, NP_Error>;
     
-    /// Handle copying from old pointer/buffer to new pointer/buffer (recursive for collections)
+    /// Handle copying from old pointer/buffer to new pointer/buffer, collections are only created and `NP_Cursor::compact` copies their items
     /// 
     fn do_compact(from_cursor: NP_Cursor, from_memory: &'value NP_Memory, to_cursor: NP_Cursor, to_memory: &'value NP_Memory) -> Result<NP_Cursor, NP_Error> where Self: 'value + Sized {
 
@@ -505,4 +1437,108 @@ pub trait NP_Value<'value> {
 /*
 // unsigned integer size:        0 to (2^i) -1
 //   signed integer size: -2^(i-1) to  2^(i-1) 
-*/
\ No newline at end of file
+*/
+#[test]
+fn pointer_bytes_work() -> Result<(), NP_Error> {
+    use crate::memory::{NP_Address_Size, NP_HEADER_ADDR_U8, NP_HEADER_ADDR_U32};
+
+    let schema = Vec::new();
+    for (flags, addr_size) in [(NP_HEADER_ADDR_U8, NP_Address_Size::U8), (0, NP_Address_Size::U16), (NP_HEADER_ADDR_U32, NP_Address_Size::U32)].iter() {
+        let memory = NP_Memory::new_with_header(None, &schema, &[*flags]);
+        let size = addr_size.bytes();
+        let address = memory.malloc_pointers(5, 0)?;
+
+        let item = pointer_bytes(&memory, address, NP_Overlay_Kind::List_Item);
+        assert_eq!(item.get_size(), size * 2 + 1);
+        item.set_addr_value(0x48);
+        item.set_next_addr(0x22);
+        item.set_index(7);
+        assert_eq!((item.get_addr_value(), item.get_next_addr(), item.get_index()), (0x48, 0x22, 7));
+        assert_eq!(memory.read_bytes()[address + size - 1], 0x48);
+        assert_eq!(memory.read_bytes()[address + size * 2], 7);
+
+        let map_item = pointer_bytes(&memory, address, NP_Overlay_Kind::Map_Item);
+        assert_eq!(map_item.get_size(), size * 3);
+        assert_eq!(map_item.get_next_addr(), 0x22);
+        map_item.reset();
+        assert_eq!(item.get_addr_value(), 0);
+
+        let list = list_bytes(&memory, address);
+        list.set_tail(0x31);
+        assert_eq!(pointer_bytes(&memory, address, NP_Overlay_Kind::Scalar).get_size(), size);
+        assert_eq!(pointer_bytes(&memory, address + size, NP_Overlay_Kind::Scalar).get_addr_value(), 0x31);
+
+        let vtable = vtable_bytes(&memory, address);
+        vtable.set_next(0x40);
+        assert_eq!(memory.read_bytes()[address + size * 5 - 1], 0x40);
+        assert_eq!(map_bytes(&memory, address).get_head(), 0);
+
+        // read only bytes are never cast, changes to them are dropped
+        let bytes = memory.read_bytes().to_vec();
+        let read_only = NP_Memory::existing_read_only(&bytes, &schema);
+        let tail = pointer_bytes(&read_only, address + size, NP_Overlay_Kind::Scalar);
+        #[cfg(not(feature = "safe"))]
+        assert!(matches!(tail, NP_Overlay::View(_)));
+        tail.set_addr_value(9);
+        assert_eq!(tail.get_addr_value(), 0x31);
+        assert!(read_only.get_bytes_mut(address, 1).is_err());
+        assert_eq!(read_only.read_bytes(), &bytes[..]);
+
+        // neither are snapshots, their bytes are shared with the memory they came from
+        let snapshot = memory.snapshot();
+        let tail = pointer_bytes(&snapshot, address + size, NP_Overlay_Kind::Scalar);
+        #[cfg(not(feature = "safe"))]
+        assert!(matches!(tail, NP_Overlay::View(_)));
+        tail.set_addr_value(9);
+        assert_eq!(tail.get_addr_value(), 0x31);
+        assert!(snapshot.get_bytes_mut(address, 1).is_err());
+
+        // past the end of the buffer reads as empty and can't be changed
+        let len = memory.read_bytes().len();
+        pointer_bytes(&memory, len, NP_Overlay_Kind::Scalar).set_addr_value(9);
+        assert_eq!(pointer_bytes(&memory, len, NP_Overlay_Kind::Scalar).get_addr_value(), 0);
+        assert_eq!(memory.read_bytes().len(), len);
+
+        // and pointers past the end don't share any bytes
+        let (past, further) = (pointer_bytes(&memory, len, NP_Overlay_Kind::Scalar), pointer_bytes(&memory, len + 8, NP_Overlay_Kind::Scalar));
+        past.set_addr_value(9);
+        assert_eq!(further.get_addr_value(), 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn deep_walks_work() -> Result<(), NP_Error> {
+    extern crate std;
+    use crate::NP_Factory;
+    use crate::schema::NP_MAX_DEPTH;
+    use alloc::string::ToString;
+
+    let schema = r#"{"type": "list", "of": "#.repeat(NP_MAX_DEPTH - 1) + r#"{"type": "string"}"# + &"}".repeat(NP_MAX_DEPTH - 1);
+    let factory = NP_Factory::new(&schema)?;
+    let path: Vec<String> = (0..(NP_MAX_DEPTH - 1)).map(|x| (x % 3).to_string()).collect();
+    let path: Vec<&str> = path.iter().map(|x| x.as_str()).collect();
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&path, "deep")?;
+    buffer.set(&path, "deeper")?;
+
+    // exporting, measuring and compacting the deepest buffer fits in a small stack
+    std::thread::scope(|scope| {
+        std::thread::Builder::new().stack_size(64 * 1024).spawn_scoped(scope, || -> Result<(), NP_Error> {
+            let mut json = &buffer.json_encode(&[])?;
+            for _x in 0..(NP_MAX_DEPTH - 1) {
+                json = match json { NP_JSON::Array(items) => items.last().unwrap(), _ => panic!() };
+            }
+            assert!(matches!(json, NP_JSON::String(value) if value == "deeper"));
+            assert!(buffer.calc_bytes()?.wasted_bytes > 0);
+            buffer.compact(None)?;
+            assert_eq!(buffer.calc_bytes()?.wasted_bytes, 0);
+            Ok(())
+        }).unwrap().join().unwrap()
+    })?;
+
+    assert_eq!(buffer.get::<&str>(&path)?, Some("deeper"));
+
+    Ok(())
+}
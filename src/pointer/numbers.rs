@@ -31,17 +31,17 @@
 //! 
 
 
-use alloc::prelude::v1::Box;
-use crate::schema::NP_Parsed_Schema;
+use alloc::boxed::Box;
+use crate::schema::{NP_Parsed_Schema, NP_Overflow};
 use alloc::vec::Vec;
 use crate::utils::to_unsigned;
 use crate::utils::to_signed;
 use crate::error::NP_Error;
 use crate::{schema::NP_TypeKeys, pointer::NP_Value, json_flex::NP_JSON, json_flex::JSMAP};
 
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 use alloc::{borrow::ToOwned};
-use super::{NP_Cursor};
+use super::{NP_Cursor, inline_size, inline_encode, inline_decode};
 use crate::NP_Memory;
 
 /// The type of number being used
@@ -86,6 +86,28 @@ macro_rules! noproto_number {
                     };
                     
                 }
+
+                match &schema[address] {
+                    NP_Parsed_Schema::Int8 { inline: true, .. } | NP_Parsed_Schema::Int16 { inline: true, .. } |
+                    NP_Parsed_Schema::Uint8 { inline: true, .. } | NP_Parsed_Schema::Uint16 { inline: true, .. } => {
+                        schema_json.insert("inline".to_owned(), NP_JSON::True);
+                    },
+                    _ => { }
+                }
+
+                match &schema[address] {
+                    NP_Parsed_Schema::Int8 { overflow, .. } | NP_Parsed_Schema::Int16 { overflow, .. } |
+                    NP_Parsed_Schema::Int32 { overflow, .. } | NP_Parsed_Schema::Int64 { overflow, .. } |
+                    NP_Parsed_Schema::Uint8 { overflow, .. } | NP_Parsed_Schema::Uint16 { overflow, .. } |
+                    NP_Parsed_Schema::Uint32 { overflow, .. } | NP_Parsed_Schema::Uint64 { overflow, .. } => {
+                        match overflow {
+                            NP_Overflow::Error => { },
+                            NP_Overflow::Saturate => { schema_json.insert("overflow".to_owned(), NP_JSON::String("saturate".to_owned())); },
+                            NP_Overflow::Wrap => { schema_json.insert("overflow".to_owned(), NP_JSON::String("wrap".to_owned())); }
+                        }
+                    },
+                    _ => { }
+                }
         
                 Ok(NP_JSON::Dictionary(schema_json))
             }
@@ -100,6 +122,20 @@ macro_rules! noproto_number {
 
                 let mut value_address = c_value.get_addr_value() as usize;
 
+                if inline_size(&memory.schema[cursor.schema_addr], memory).is_some() {
+                    let mut bytes = value.to_be_bytes();
+
+                    match $numType {
+                        NP_NumType::signed => {
+                            bytes[0] = to_unsigned(bytes[0]);
+                        },
+                        _ => {}
+                    };
+
+                    c_value.set_addr_value(inline_encode(&bytes));
+                    return Ok(cursor);
+                }
+
                 if value_address != 0 { // existing value, replace
                     let mut bytes = value.to_be_bytes();
 
@@ -110,12 +146,8 @@ macro_rules! noproto_number {
                         _ => {}
                     };
         
-                    let write_bytes = memory.write_bytes();
-        
                     // overwrite existing values in buffer
-                    for x in 0..bytes.len() {
-                        write_bytes[value_address + x] = bytes[x];
-                    }
+                    memory.get_bytes_mut(value_address, bytes.len())?.copy_from_slice(&bytes);
                     return Ok(cursor);
                 } else { // new value
         
@@ -129,7 +161,7 @@ macro_rules! noproto_number {
                     };
         
                     value_address = memory.malloc_borrow(&bytes)?;
-                    c_value.set_addr_value(value_address as u16);
+                    cursor.get_value(memory).set_addr_value(value_address as u32);
 
                     return Ok(cursor);
                 }
@@ -147,10 +179,12 @@ macro_rules! noproto_number {
                     return Ok(None);
                 }
         
-                let read_memory = memory.read_bytes();
                 let mut be_bytes = <$t>::default().to_be_bytes();
-                for x in 0..be_bytes.len() {
-                    be_bytes[x] = read_memory[value_addr + x];
+                if inline_size(&memory.schema[cursor.schema_addr], memory).is_some() {
+                    inline_decode(value_addr as u32, &mut be_bytes);
+                } else {
+                    let size = be_bytes.len();
+                    be_bytes.copy_from_slice(memory.get_bytes(value_addr, size)?);
                 }
 
                 match $numType {
@@ -198,7 +232,7 @@ macro_rules! noproto_number {
 
                 let c_value = cursor.get_value(memory);
 
-                if c_value.get_addr_value() == 0 {
+                if c_value.get_addr_value() == 0 || inline_size(&memory.schema[cursor.schema_addr], memory).is_some() {
                     Ok(0) 
                 } else {
                     Ok(core::mem::size_of::<Self>())
@@ -209,53 +243,78 @@ macro_rules! noproto_number {
         
                 let mut schema_data: Vec<u8> = Vec::new();
                 schema_data.push($tkey as u8);
+
+                let inline = match json_schema["inline"] {
+                    NP_JSON::True => true,
+                    NP_JSON::False | NP_JSON::Null => false,
+                    _ => return Err(NP_Error::Schema("The inline property must be a boolean!".into()))
+                };
+
+                if inline && core::mem::size_of::<$t>() > 2 {
+                    return Err(NP_Error::Schema("Only 1 and 2 byte values can be inline!".into()));
+                }
+
+                let overflow = match &json_schema["overflow"] {
+                    NP_JSON::Null => NP_Overflow::Error,
+                    NP_JSON::String(x) if x == "error" => NP_Overflow::Error,
+                    NP_JSON::String(x) if x == "saturate" => NP_Overflow::Saturate,
+                    NP_JSON::String(x) if x == "wrap" => NP_Overflow::Wrap,
+                    _ => return Err(NP_Error::Schema("The overflow property must be \"error\", \"saturate\" or \"wrap\"!".into()))
+                };
+
+                if let (NP_NumType::floating, NP_JSON::String(_)) = ($numType, &json_schema["overflow"]) {
+                    return Err(NP_Error::Schema("Only integers can set overflow!".into()));
+                }
+
+                // first bit for default value, second bit for inline values, third and fourth bits for overflow
+                let flags = if inline { 2 } else { 0 } | ((overflow as u8) << 2);
     
                 match json_schema["default"] {
                     NP_JSON::Float(x) => {
-                        schema_data.push(1);
+                        schema_data.push(1 | flags);
                         schema_data.extend((x as $t).to_be_bytes().to_vec());
                     },
                     NP_JSON::Integer(x) => {
-                        schema_data.push(1);
+                        schema_data.push(1 | flags);
                         schema_data.extend((x as $t).to_be_bytes().to_vec());
                     },
                     _ => {
-                        schema_data.push(0);
+                        schema_data.push(flags);
                     }
                 };
 
                 let use_schema = match $tkey {
                     NP_TypeKeys::Int8 => {
-                        NP_Parsed_Schema::Int8 { sortable: true, i: $tkey, default: i8::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Int8 { sortable: true, i: $tkey, default: i8::np_get_default_from_json(&json_schema["default"]), inline, overflow }
                     },
                     NP_TypeKeys::Int16 => {
-                        NP_Parsed_Schema::Int16 { sortable: true, i: $tkey, default: i16::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Int16 { sortable: true, i: $tkey, default: i16::np_get_default_from_json(&json_schema["default"]), inline, overflow }
                     },
                     NP_TypeKeys::Int32 => {
-                        NP_Parsed_Schema::Int32 { sortable: true, i: $tkey, default: i32::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Int32 { sortable: true, i: $tkey, default: i32::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Int64 => {
-                        NP_Parsed_Schema::Int64 { sortable: true, i: $tkey, default: i64::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Int64 { sortable: true, i: $tkey, default: i64::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Uint8 => {
-                        NP_Parsed_Schema::Uint8 { sortable: true, i: $tkey, default: u8::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Uint8 { sortable: true, i: $tkey, default: u8::np_get_default_from_json(&json_schema["default"]), inline, overflow }
                     },
                     NP_TypeKeys::Uint16 => {
-                        NP_Parsed_Schema::Uint16 { sortable: true, i: $tkey, default: u16::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Uint16 { sortable: true, i: $tkey, default: u16::np_get_default_from_json(&json_schema["default"]), inline, overflow }
                     },
                     NP_TypeKeys::Uint32 => {
-                        NP_Parsed_Schema::Uint32 { sortable: true, i: $tkey, default: u32::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Uint32 { sortable: true, i: $tkey, default: u32::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Uint64 => {
-                        NP_Parsed_Schema::Uint64 { sortable: true, i: $tkey, default: u64::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Uint64 { sortable: true, i: $tkey, default: u64::np_get_default_from_json(&json_schema["default"]), overflow }
                     },
                     NP_TypeKeys::Float => {
-                        NP_Parsed_Schema::Float { sortable: false, i: $tkey, default: f32::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Float { sortable: true, i: $tkey, default: f32::np_get_default_from_json(&json_schema["default"])}
                     },
                     NP_TypeKeys::Double => {
-                        NP_Parsed_Schema::Double { sortable: false, i: $tkey, default: f64::np_get_default_from_json(&json_schema["default"])}
+                        NP_Parsed_Schema::Double { sortable: true, i: $tkey, default: f64::np_get_default_from_json(&json_schema["default"])}
                     },
-                    _ => { unreachable!() }
+                    _ => { return Err(NP_Error::new("unreachable")) }
                 };
 
                 schema.push(use_schema);
@@ -265,36 +324,38 @@ macro_rules! noproto_number {
             }
 
             fn from_bytes_to_schema(mut schema: Vec<NP_Parsed_Schema>, address: usize, bytes: &Vec<u8>) -> (bool, Vec<NP_Parsed_Schema>) {
+                let inline = bytes[address + 1] & 2 != 0;
+                let overflow = NP_Overflow::from((bytes[address + 1] >> 2) & 3);
                 schema.push(match $tkey {
                     NP_TypeKeys::Int8 => {
-                        NP_Parsed_Schema::Int8 { sortable: true, i: $tkey, default: i8::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Int8 { sortable: true, i: $tkey, default: i8::np_get_default_from_bytes(address, bytes), inline, overflow }
                     },
                     NP_TypeKeys::Int16 => {
-                        NP_Parsed_Schema::Int16 { sortable: true, i: $tkey, default: i16::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Int16 { sortable: true, i: $tkey, default: i16::np_get_default_from_bytes(address, bytes), inline, overflow }
                     },
                     NP_TypeKeys::Int32 => {
-                        NP_Parsed_Schema::Int32 { sortable: true, i: $tkey, default: i32::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Int32 { sortable: true, i: $tkey, default: i32::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Int64 => {
-                        NP_Parsed_Schema::Int64 { sortable: true, i: $tkey, default: i64::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Int64 { sortable: true, i: $tkey, default: i64::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Uint8 => {
-                        NP_Parsed_Schema::Uint8 { sortable: true, i: $tkey, default: u8::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Uint8 { sortable: true, i: $tkey, default: u8::np_get_default_from_bytes(address, bytes), inline, overflow }
                     },
                     NP_TypeKeys::Uint16 => {
-                        NP_Parsed_Schema::Uint16 { sortable: true, i: $tkey, default: u16::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Uint16 { sortable: true, i: $tkey, default: u16::np_get_default_from_bytes(address, bytes), inline, overflow }
                     },
                     NP_TypeKeys::Uint32 => {
-                        NP_Parsed_Schema::Uint32 { sortable: true, i: $tkey, default: u32::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Uint32 { sortable: true, i: $tkey, default: u32::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Uint64 => {
-                        NP_Parsed_Schema::Uint64 { sortable: true, i: $tkey, default: u64::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Uint64 { sortable: true, i: $tkey, default: u64::np_get_default_from_bytes(address, bytes), overflow }
                     },
                     NP_TypeKeys::Float => {
-                        NP_Parsed_Schema::Float { sortable: false, i: $tkey, default: f32::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Float { sortable: true, i: $tkey, default: f32::np_get_default_from_bytes(address, bytes)}
                     },
                     NP_TypeKeys::Double => {
-                        NP_Parsed_Schema::Double { sortable: false, i: $tkey, default: f64::np_get_default_from_bytes(address, bytes)}
+                        NP_Parsed_Schema::Double { sortable: true, i: $tkey, default: f64::np_get_default_from_bytes(address, bytes)}
                     },
                     _ => { unreachable!() }
                 });
@@ -332,6 +393,81 @@ impl super::NP_Scalar for u64 {}
 impl super::NP_Scalar for f32 {}
 impl super::NP_Scalar for f64 {}
 
+/// Integer types that narrower integers can be widened into without losing information, used by `NP_Buffer::get_coerced`.
+pub trait NP_Coerce: Sized {
+    /// Check if every value of a schema type fits in this type
+    fn can_coerce(type_key: &NP_TypeKeys) -> bool;
+    /// Convert a value already known to fit in this type
+    fn from_widened(value: i128) -> Self;
+}
+
+macro_rules! noproto_coerce {
+    ($t:ty, [$($tkey: ident),*]) => {
+        impl NP_Coerce for $t {
+            fn can_coerce(type_key: &NP_TypeKeys) -> bool {
+                match type_key {
+                    $(NP_TypeKeys::$tkey)|* => true,
+                    _ => false
+                }
+            }
+            fn from_widened(value: i128) -> Self { value as $t }
+        }
+    }
+}
+
+noproto_coerce!(i16, [Int8, Int16, Uint8]);
+noproto_coerce!(i32, [Int8, Int16, Int32, Uint8, Uint16]);
+noproto_coerce!(i64, [Int8, Int16, Int32, Int64, Uint8, Uint16, Uint32]);
+noproto_coerce!(u16, [Uint8, Uint16]);
+noproto_coerce!(u32, [Uint8, Uint16, Uint32]);
+noproto_coerce!(u64, [Uint8, Uint16, Uint32, Uint64]);
+
+/// Add `delta` to the integer at `cursor` in place and get the new value, used by `NP_Buffer::increment`.
+///
+/// Unset values start from the schema default or zero, the `overflow` setting of the schema decides what happens past the range of the type.
+#[doc(hidden)]
+pub fn increment_value(cursor: NP_Cursor, memory: &NP_Memory, delta: i64) -> Result<i128, NP_Error> {
+
+    macro_rules! step {
+        ($t:ty, $overflow:expr) => {{
+            let current = match <$t>::into_value(&cursor, memory)? {
+                Some(x) => x,
+                None => <$t>::schema_default(&memory.schema[cursor.schema_addr]).unwrap_or(0)
+            };
+            let next = current as i128 + delta as i128;
+            let value = if next < <$t>::MIN as i128 || next > <$t>::MAX as i128 {
+                match $overflow {
+                    NP_Overflow::Error => return Err(NP_Error::InvalidValue("Increment is past the range of the integer type!".into())),
+                    NP_Overflow::Saturate => if next < 0 { <$t>::MIN } else { <$t>::MAX },
+                    // truncating to the type wraps around like two's complement math
+                    NP_Overflow::Wrap => next as $t
+                }
+            } else {
+                next as $t
+            };
+            <$t>::set_value(cursor, memory, value)?;
+            Ok(value as i128)
+        }}
+    }
+
+    match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Int8 { overflow, .. }   => step!(i8, overflow),
+        NP_Parsed_Schema::Int16 { overflow, .. }  => step!(i16, overflow),
+        NP_Parsed_Schema::Int32 { overflow, .. }  => step!(i32, overflow),
+        NP_Parsed_Schema::Int64 { overflow, .. }  => step!(i64, overflow),
+        NP_Parsed_Schema::Uint8 { overflow, .. }  => step!(u8, overflow),
+        NP_Parsed_Schema::Uint16 { overflow, .. } => step!(u16, overflow),
+        NP_Parsed_Schema::Uint32 { overflow, .. } => step!(u32, overflow),
+        NP_Parsed_Schema::Uint64 { overflow, .. } => step!(u64, overflow),
+        schema => {
+            let mut err = String::from("TypeError: Attempted to increment a value of type (");
+            err.push_str(schema.get_type_data().0);
+            err.push_str("), only integers can be incremented\n");
+            Err(NP_Error::SchemaMismatch(err))
+        }
+    }
+}
+
 trait NP_BigEndian {
     fn np_get_default_from_json(json: &NP_JSON) -> Option<Self> where Self: Sized;
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> where Self: Sized;
@@ -343,7 +479,7 @@ impl NP_BigEndian for i8 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Int8 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Int8 { default, .. } => { *default },
             _ => None
         }
     }
@@ -361,11 +497,11 @@ impl NP_BigEndian for i8 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 1] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 2)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 3)]);
             Some(i8::from_be_bytes(slice))
         }
     }
@@ -414,7 +550,7 @@ impl NP_BigEndian for i16 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Int16 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Int16 { default, .. } => { *default },
             _ => None
         }
     }
@@ -432,11 +568,11 @@ impl NP_BigEndian for i16 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 2] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 3)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 4)]);
             Some(i16::from_be_bytes(slice))
         }
     }
@@ -487,7 +623,7 @@ impl NP_BigEndian for i32 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Int32 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Int32 { default, .. } => { *default },
             _ => None
         }
     }
@@ -505,11 +641,11 @@ impl NP_BigEndian for i32 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 4] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 5)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 6)]);
             Some(i32::from_be_bytes(slice))
         }
     }
@@ -561,7 +697,7 @@ impl NP_BigEndian for i64 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Int64 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Int64 { default, .. } => { *default },
             _ => None
         }
     }
@@ -579,11 +715,11 @@ impl NP_BigEndian for i64 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 8] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 9)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 10)]);
             Some(i64::from_be_bytes(slice))
         }
     }
@@ -635,7 +771,7 @@ impl NP_BigEndian for u8 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Uint8 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Uint8 { default, .. } => { *default },
             _ => None
         }
     }
@@ -653,11 +789,11 @@ impl NP_BigEndian for u8 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 1] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 2)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 3)]);
             Some(u8::from_be_bytes(slice))
         }
     }
@@ -709,7 +845,7 @@ impl NP_BigEndian for u16 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Uint16 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Uint16 { default, .. } => { *default },
             _ => None
         }
     }
@@ -727,11 +863,11 @@ impl NP_BigEndian for u16 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 2] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 3)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 4)]);
             Some(u16::from_be_bytes(slice))
         }
     }
@@ -783,7 +919,7 @@ impl NP_BigEndian for u32 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Uint32 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Uint32 { default, .. } => { *default },
             _ => None
         }
     }
@@ -801,11 +937,11 @@ impl NP_BigEndian for u32 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 4] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 5)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 6)]);
             Some(u32::from_be_bytes(slice))
         }
     }
@@ -856,7 +992,7 @@ impl NP_BigEndian for u64 {
            
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Uint64 { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Uint64 { default, .. } => { *default },
             _ => None
         }
     }
@@ -874,11 +1010,11 @@ impl NP_BigEndian for u64 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 8] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 9)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 10)]);
             Some(u64::from_be_bytes(slice))
         }
     }
@@ -928,7 +1064,7 @@ impl NP_BigEndian for f32 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Float { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Float { default, .. } => { *default },
             _ => None
         }
     }
@@ -946,11 +1082,11 @@ impl NP_BigEndian for f32 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 4] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 5)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 6)]);
             Some(f32::from_be_bytes(slice))
         }
     }
@@ -1000,7 +1136,7 @@ impl NP_BigEndian for f64 {
 
     fn np_get_default<'default>(ptr: &'default NP_Parsed_Schema) -> Option<Self> {
         match ptr {
-            NP_Parsed_Schema::Double { sortable: _, i: _, default } => { *default },
+            NP_Parsed_Schema::Double { default, .. } => { *default },
             _ => None
         }
     }
@@ -1018,11 +1154,11 @@ impl NP_BigEndian for f64 {
         }
     }
     fn np_get_default_from_bytes<'default>(address: usize, bytes: &'default Vec<u8>) -> Option<Self> {
-        if bytes[address + 1] == 0 {
+        if bytes[address + 1] & 1 == 0 {
             None
         } else {
             let mut slice: [u8; 8] = Default::default();
-            slice.copy_from_slice(&bytes[(address + 1)..(address + 9)]);
+            slice.copy_from_slice(&bytes[(address + 2)..(address + 10)]);
             Some(f64::from_be_bytes(slice))
         }
     }
@@ -1065,4 +1201,148 @@ fn double_set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn inline_values_work() -> Result<(), NP_Error> {
+    use crate::memory::NP_Address_Size;
+
+    let schema = r#"{"type":"table","columns":[["a",{"type":"int8","inline":true}],["b",{"type":"uint16","default":300,"inline":true}],["c",{"type":"bool","inline":true}],["d",{"type":"int16"}]]}"#;
+    let factory = crate::NP_Factory::new(schema)?;
+    assert_eq!(schema, factory.schema.to_json()?.stringify());
+    assert!(crate::NP_Factory::new(r#"{"type":"uint32","inline":true}"#).is_err());
+
+    // sortable buffers don't depend on where the value is stored
+    let sorted = crate::NP_Factory::new(r#"{"type":"tuple","sorted":true,"values":[{"type":"u8","inline":true},{"type":"bool","inline":true}]}"#)?;
+    let mut buffer = sorted.empty_buffer(None);
+    buffer.set(&["0"], 200u8)?;
+    buffer.set(&["1"], true)?;
+    let bytes = buffer.close_sortable()?;
+    assert_eq!(bytes, alloc::vec![200, 1]);
+    assert_eq!(sorted.open_sortable_buffer(bytes)?.get(&["0"])?, Some(200u8));
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["a"], -20i8)?;
+    buffer.set(&["b"], 5000u16)?;
+    buffer.set(&["c"], true)?;
+    buffer.set(&["d"], -20i16)?;
+    let size = buffer.calc_bytes()?.current_buffer;
+    buffer.set(&["a"], 100i8)?;
+    buffer.set(&["b"], 0u16)?;
+    buffer.set(&["c"], false)?;
+    // updates are done in the pointer
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size);
+    assert_eq!(buffer.get::<i8>(&["a"])?, Some(100));
+    assert_eq!(buffer.get::<u16>(&["b"])?, Some(0));
+    assert_eq!(buffer.get::<bool>(&["c"])?, Some(false));
+    assert_eq!(buffer.get::<i16>(&["d"])?, Some(-20));
+    assert!(buffer.validate().is_ok());
+
+    // inline values don't need their own allocation
+    let allocated = crate::NP_Factory::new(&schema.replace(r#","inline":true"#, ""))?;
+    let mut other = allocated.empty_buffer(None);
+    other.set(&["a"], -20i8)?;
+    other.set(&["b"], 5000u16)?;
+    other.set(&["c"], true)?;
+    other.set(&["d"], -20i16)?;
+    // uint16 values need 4 byte addresses to fit inline
+    assert_eq!(other.calc_bytes()?.current_buffer, size + 2);
+
+    buffer.compact(None)?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer, size);
+    assert_eq!(buffer.get::<i8>(&["a"])?, Some(100));
+    assert_eq!(buffer.get::<bool>(&["c"])?, Some(false));
+
+    // compiled schemas keep inline flags and defaults
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(schema, compiled.schema.to_json()?.stringify());
+    let buffer = compiled.open_buffer(buffer.close());
+    assert_eq!(buffer.get::<u16>(&["b"])?, Some(0));
+    assert_eq!(compiled.empty_buffer(None).get::<u16>(&["b"])?, Some(300));
+
+    let wide = crate::NP_Factory::new(schema)?.with_address_size(NP_Address_Size::U32);
+    let mut buffer = wide.empty_buffer(None);
+    buffer.set(&["b"], 5000u16)?;
+    let allocated = allocated.with_address_size(NP_Address_Size::U32);
+    let mut other = allocated.empty_buffer(None);
+    other.set(&["b"], 5000u16)?;
+    assert_eq!(buffer.calc_bytes()?.current_buffer + 2, other.calc_bytes()?.current_buffer);
+    assert_eq!(buffer.get::<u16>(&["b"])?, Some(5000));
+
+    // addresses too small for the value allocate it instead
+    let tiny = crate::NP_Factory::new(schema)?.with_address_size(NP_Address_Size::U8);
+    let mut buffer = tiny.empty_buffer(None);
+    buffer.set(&["a"], 7i8)?;
+    buffer.set(&["b"], 7u16)?;
+    assert_eq!(buffer.get::<i8>(&["a"])?, Some(7));
+    assert_eq!(buffer.get::<u16>(&["b"])?, Some(7));
+    assert!(buffer.validate().is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn coerced_numbers_work() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [
+        ["a", {"type": "i8"}],
+        ["b", {"type": "i16", "inline": true}],
+        ["c", {"type": "u32"}],
+        ["d", {"type": "u64"}],
+        ["e", {"type": "string"}],
+        ["f", {"type": "u16", "default": 300}]
+    ]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["a"], -128i8)?;
+    buffer.set(&["b"], -3000i16)?;
+    buffer.set(&["c"], u32::MAX)?;
+    buffer.set(&["d"], u64::MAX)?;
+
+    assert_eq!(buffer.get_coerced::<i64>(&["a"])?, Some(-128));
+    assert_eq!(buffer.get_coerced::<i32>(&["b"])?, Some(-3000));
+    assert_eq!(buffer.get_coerced::<i64>(&["c"])?, Some(u32::MAX as i64));
+    assert_eq!(buffer.get_coerced::<u64>(&["d"])?, Some(u64::MAX));
+    assert_eq!(buffer.get_coerced::<i32>(&["f"])?, Some(300));
+    assert_eq!(factory.empty_buffer(None).get_coerced::<i64>(&["a"])?, None);
+    assert_eq!(buffer.get_coerced::<i64>(&["missing"])?, None);
+
+    // conversions that could lose information fail
+    assert!(buffer.get_coerced::<i64>(&["d"]).is_err());
+    assert!(buffer.get_coerced::<u64>(&["a"]).is_err());
+    assert!(buffer.get_coerced::<i16>(&["c"]).is_err());
+    assert!(buffer.get_coerced::<i64>(&["e"]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn increments_work() -> Result<(), NP_Error> {
+    let schema = r#"{"type":"table","columns":[["a",{"type":"uint64","overflow":"wrap"}],["b",{"type":"int16","default":-5,"inline":true,"overflow":"saturate"}],["c",{"type":"uint32"}],["d",{"type":"float"}]]}"#;
+    let factory = crate::NP_Factory::new(schema)?;
+    assert!(crate::NP_Factory::new(r#"{"type":"float","overflow":"wrap"}"#).is_err());
+    assert!(crate::NP_Factory::new(r#"{"type":"u8","overflow":"sometimes"}"#).is_err());
+
+    // the overflow setting survives exporting and compiling
+    assert_eq!(factory.export_schema()?.stringify(), schema);
+    let compiled = crate::NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(compiled.export_schema()?.stringify(), schema);
+
+    let mut buffer = compiled.empty_buffer(None);
+    assert_eq!(buffer.increment(&["a"], -1)?, Some(u64::MAX as i128));
+    assert_eq!(buffer.increment(&["a"], 2)?, Some(1));
+    assert_eq!(buffer.increment(&["b"], 1)?, Some(-4));
+    assert_eq!(buffer.increment(&["b"], i64::MIN)?, Some(i16::MIN as i128));
+    assert_eq!(buffer.get::<i16>(&["b"])?, Some(i16::MIN));
+    assert_eq!(buffer.increment(&["c"], u32::MAX as i64)?, Some(u32::MAX as i128));
+    assert!(buffer.increment(&["c"], 1).is_err());
+    assert!(buffer.increment(&["d"], 1).is_err());
+    assert_eq!(buffer.increment(&["e"], 1)?, None);
+
+    // increments are recorded like any other change
+    buffer.start_journal();
+    buffer.increment(&["c"], -10)?;
+    assert!(buffer.increment(&["c"], -(u32::MAX as i64)).is_err());
+    assert_eq!(buffer.journal().len(), 1);
+    buffer.undo()?;
+    assert_eq!(buffer.get::<u32>(&["c"])?, Some(u32::MAX));
+
+    Ok(())
+}
@@ -24,7 +24,7 @@ use alloc::vec::Vec;
 use crate::json_flex::{JSMAP, NP_JSON};
 use crate::schema::{NP_TypeKeys};
 use crate::{pointer::NP_Value, error::NP_Error};
-use core::{fmt::{Debug}, hint::unreachable_unchecked};
+use core::fmt::Debug;
 
 use alloc::string::String;
 use alloc::boxed::Box;
@@ -147,7 +147,7 @@ impl<'value> NP_Value<'value> for NP_Enum {
                     };
         
                     if value_num == -1 {
-                        return Err(NP_Error::new("Option not found, cannot set uknown option!"));
+                        return Err(NP_Error::InvalidValue("Option not found, cannot set uknown option!".into()));
                     }
                 }
         
@@ -157,15 +157,13 @@ impl<'value> NP_Value<'value> for NP_Enum {
         
                 if addr_value != 0 { // existing value, replace
         
-                    let write_bytes = memory.write_bytes();
-        
-                    write_bytes[addr_value] = bytes;
+                    memory.get_bytes_mut(addr_value, 1)?[0] = bytes;
                     return Ok(cursor);
         
                 } else { // new value
         
                     addr_value = memory.malloc_borrow(&[bytes])?;
-                    c_value.set_addr_value(addr_value as u16);
+                    cursor.get_value(memory).set_addr_value(addr_value as u32);
 
                     return Ok(cursor);
                 }     
@@ -191,7 +189,7 @@ impl<'value> NP_Value<'value> for NP_Enum {
                     Some(x) => {
                         let value_num = x as usize;
         
-                        if value_num > choices.len() {
+                        if value_num >= choices.len() {
                             None
                         } else {
                             Some(choices[value_num].clone())
@@ -296,7 +294,7 @@ impl<'value> NP_Value<'value> for NP_Enum {
                     match opt {
                         NP_JSON::String(stir) => {
                             if stir.len() > 255 {
-                                return Err(NP_Error::new("'option' choices cannot be longer than 255 characters each!"))
+                                return Err(NP_Error::Schema("'option' choices cannot be longer than 255 characters each!".into()))
                             }
 
                             if let Some(def) = &default_stir {
@@ -312,12 +310,12 @@ impl<'value> NP_Value<'value> for NP_Enum {
                 }
             },
             _ => {
-                return Err(NP_Error::new("'option' type requires a 'choices' key with an array of strings!"))
+                return Err(NP_Error::Schema("'option' type requires a 'choices' key with an array of strings!".into()))
             }
         }
 
         if choices.len() > 254 {
-            return Err(NP_Error::new("'option' type cannot have more than 254 choices!"))
+            return Err(NP_Error::Schema("'option' type cannot have more than 254 choices!".into()))
         }
 
         // default value
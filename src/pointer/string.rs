@@ -17,8 +17,7 @@
 //! ```
 
 use alloc::string::String;
-use alloc::prelude::v1::Box;
-use core::hint::unreachable_unchecked;
+use alloc::boxed::Box;
 
 use crate::{error::NP_Error, schema::String_Case};
 use crate::{
@@ -99,7 +98,7 @@ impl<'value> NP_Value<'value> for &'value str {
                 i: NP_TypeKeys::UTF8String,
                 default: None,
                 case: case_byte,
-                sortable: fixed_size > 0,
+                sortable: true,
                 size: fixed_size,
             })
         } else {
@@ -110,11 +109,11 @@ impl<'value> NP_Value<'value> for &'value str {
                 default: Some(default_bytes.to_string()),
                 size: fixed_size,
                 case: case_byte,
-                sortable: fixed_size > 0,
+                sortable: true,
             })
         }
 
-        (fixed_size > 0, schema)
+        (true, schema)
     }
 
     fn into_value(cursor: &NP_Cursor, memory: &'value NP_Memory) -> Result<Option<Self>, NP_Error> where Self: Sized {
@@ -133,9 +132,9 @@ impl<'value> NP_Value<'value> for &'value str {
                     // fixed size
 
                     // get bytes
-                    let bytes = &memory.read_bytes()[(value_addr)..(value_addr + (size as usize))];
+                    let bytes = memory.get_bytes(value_addr, size as usize)?;
 
-                    return Ok(Some(unsafe { str::from_utf8_unchecked(bytes) }));
+                    return Ok(Some(str::from_utf8(bytes).map_err(|_| NP_Error::Utf8("String value is not valid UTF-8!".into()))?));
                 } else {
                     // dynamic size
                     // get size of bytes
@@ -143,9 +142,9 @@ impl<'value> NP_Value<'value> for &'value str {
                     let bytes_size: usize = u16::from_be_bytes(*memory.get_2_bytes(value_addr).unwrap_or(&[0u8; 2])) as usize;
 
                     // get bytes
-                    let bytes = &memory.read_bytes()[(value_addr + 2)..(value_addr + 2 + bytes_size)];
+                    let bytes = memory.get_bytes(value_addr + 2, bytes_size)?;
 
-                    return Ok(Some(unsafe { str::from_utf8_unchecked(bytes) }));
+                    return Ok(Some(str::from_utf8(bytes).map_err(|_| NP_Error::Utf8("String value is not valid UTF-8!".into()))?));
                 }
             }
             _ => Err(NP_Error::new("unreachable")),
@@ -207,39 +206,35 @@ impl<'value> NP_Value<'value> for &'value str {
         }
 
         if set == 2 {
-            return Err(NP_Error::new("Only one of uppercase and lowercase can be set!"));
+            return Err(NP_Error::Schema("Only one of uppercase and lowercase can be set!".into()));
         }
 
         schema_data.push(case_byte as u8);
 
-        let mut has_fixed_size = false;
-
         let size = match json_schema["size"] {
             NP_JSON::Integer(x) => {
-                has_fixed_size = true;
                 if x < 1 {
-                    return Err(NP_Error::new(
-                        "Fixed size for string must be larger than 1!",
+                    return Err(NP_Error::Schema(
+                        "Fixed size for string must be larger than 1!".into(),
                     ));
                 }
                 if x > u16::MAX.into() {
-                    return Err(NP_Error::new(
-                        "Fixed size for string cannot be larger than 2^16!",
+                    return Err(NP_Error::Schema(
+                        "Fixed size for string cannot be larger than 2^16!".into(),
                     ));
                 }
                 schema_data.extend((x as u16).to_be_bytes().to_vec());
                 x as u16
             }
             NP_JSON::Float(x) => {
-                has_fixed_size = true;
                 if x < 1.0 {
-                    return Err(NP_Error::new(
-                        "Fixed size for string must be larger than 1!",
+                    return Err(NP_Error::Schema(
+                        "Fixed size for string must be larger than 1!".into(),
                     ));
                 }
                 if x > u16::MAX.into() {
-                    return Err(NP_Error::new(
-                        "Fixed size for string cannot be larger than 2^16!",
+                    return Err(NP_Error::Schema(
+                        "Fixed size for string cannot be larger than 2^16!".into(),
                     ));
                 }
 
@@ -256,8 +251,8 @@ impl<'value> NP_Value<'value> for &'value str {
             NP_JSON::String(bytes) => {
                 let str_bytes = bytes.clone().into_bytes();
                 if str_bytes.len() > u16::max as usize - 1 {
-                    return Err(NP_Error::new(
-                        "Default string value cannot be larger than 2^16 bytes!",
+                    return Err(NP_Error::Schema(
+                        "Default string value cannot be larger than 2^16 bytes!".into(),
                     ));
                 }
                 schema_data.extend(((str_bytes.len() + 1) as u16).to_be_bytes().to_vec());
@@ -275,10 +270,10 @@ impl<'value> NP_Value<'value> for &'value str {
             size: size,
             default: default,
             case: case_byte,
-            sortable: has_fixed_size,
+            sortable: true,
         });
 
-        return Ok((has_fixed_size, schema_data, schema));
+        return Ok((true, schema_data, schema));
     }
 
     fn to_json(cursor: &NP_Cursor, memory: &'value NP_Memory) -> NP_JSON {
@@ -328,33 +323,26 @@ impl<'value> NP_Value<'value> for &'value str {
     
         let str_size = bytes.len() as usize;
     
-        let write_bytes = memory.write_bytes();
-    
 
         if size > 0 {
             // fixed size bytes
     
             if c_value.get_addr_value() == 0 {
-                // malloc new bytes
-    
-                let mut empty_bytes: Vec<u8> = Vec::with_capacity(size as usize);
-                for _x in 0..size {
-                    empty_bytes.push(32); // white space
-                }
-    
-                let new_addr = memory.malloc(empty_bytes)? as usize;
-                c_value.set_addr_value(new_addr as u16);
+                // malloc new bytes, they're filled in below
+                let new_addr = memory.malloc_pointers(0, size as usize)?;
+                cursor.get_value(memory).set_addr_value(new_addr as u32);
             }
 
-            let addr = c_value.get_addr_value() as usize;
+            let addr = cursor.get_value(memory).get_addr_value() as usize;
+            let write_bytes = memory.get_bytes_mut(addr, size as usize)?;
     
             for x in 0..(size as usize) {
                 if x < bytes.len() {
                     // assign values of bytes
-                    write_bytes[(addr + x)] = bytes[x];
+                    write_bytes[x] = bytes[x];
                 } else {
                     // rest is white space
-                    write_bytes[(addr + x)] = 32;
+                    write_bytes[x] = 32;
                 }
             }
     
@@ -376,19 +364,20 @@ impl<'value> NP_Value<'value> for &'value str {
     
             // update string length in buffer
             if str_size > core::u16::MAX as usize {
-                return Err(NP_Error::new("String too large!"));
+                return Err(NP_Error::InvalidValue("String too large!".into()));
             }
             let size_bytes = (str_size as u16).to_be_bytes();
+            let write_bytes = memory.get_bytes_mut(addr_value, 2 + str_size)?;
             // set string size
             for x in 0..size_bytes.len() {
-                write_bytes[(addr_value + x)] = size_bytes[x];
+                write_bytes[x] = size_bytes[x];
             }
 
             let offset = 2;
     
             // set bytes
             for x in 0..bytes.len() {
-                write_bytes[(addr_value + x + offset) as usize] = bytes[x];
+                write_bytes[x + offset] = bytes[x];
             }
     
             return Ok(cursor);
@@ -398,13 +387,13 @@ impl<'value> NP_Value<'value> for &'value str {
             // first bytes are string length
             let new_addr = {
                 if str_size > core::u16::MAX as usize {
-                    return Err(NP_Error::new("String too large!"));
+                    return Err(NP_Error::InvalidValue("String too large!".into()));
                 }
                 let size_bytes = (str_size as u16).to_be_bytes();
                 memory.malloc_borrow(&size_bytes)?
             };
     
-            c_value.set_addr_value(new_addr as u16);
+            cursor.get_value(memory).set_addr_value(new_addr as u32);
     
             memory.malloc_borrow(bytes)?;
     
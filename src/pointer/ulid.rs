@@ -101,6 +101,16 @@ impl NP_ULID {
         }
     }
     
+    /// Create a ULID from it's 16 raw bytes
+    pub fn from_bytes(value: [u8; 16]) -> NP_ULID {
+        NP_ULID { value }
+    }
+
+    /// Get the 16 raw bytes of this ULID
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.value
+    }
+
     /// Get just the time component for this ULID
     pub fn get_time(&self) -> u64 {
         let mut time_bytes: [u8; 8] = [0; 8];
@@ -167,17 +177,13 @@ impl<'value> NP_Value<'value> for &NP_ULID {
 
         if value_address != 0 { // existing value, replace
             let bytes = value.value;
-            let write_bytes = memory.write_bytes();
-
             // overwrite existing values in buffer
-            for x in 0..bytes.len() {
-                write_bytes[value_address + x] = bytes[x];
-            }
+            memory.get_bytes_mut(value_address, bytes.len())?.copy_from_slice(&bytes);
 
         } else { // new value
 
             value_address = memory.malloc_borrow(&value.value)?;
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value(memory).set_addr_value(value_address as u32);
         }                    
         
         Ok(cursor)
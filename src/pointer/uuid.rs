@@ -21,7 +21,7 @@
 //! ```
 //! 
 
-use alloc::prelude::v1::Box;
+use alloc::boxed::Box;
 use crate::pointer::NP_Scalar;
 use crate::{memory::NP_Memory, schema::{NP_Parsed_Schema}};
 use alloc::vec::Vec;
@@ -112,6 +112,32 @@ impl NP_UUID {
 
         result
     }
+
+    /// Parse a UUID from it's stringified version.  Dashes are optional and hex digits can be upper or lower case.
+    ///
+    pub fn from_string(value: &str) -> Result<Self, NP_Error> {
+
+        let mut uuid = NP_UUID {
+            value: [0; 16]
+        };
+
+        let mut digits = value.chars().filter(|c| *c != '-');
+
+        for x in 0..uuid.value.len() {
+            let high = digits.next().and_then(|c| c.to_digit(16));
+            let low = digits.next().and_then(|c| c.to_digit(16));
+            match (high, low) {
+                (Some(h), Some(l)) => uuid.value[x] = ((h << 4) | l) as u8,
+                _ => return Err(NP_Error::Parse("Invalid UUID string!".into()))
+            }
+        }
+
+        if digits.next().is_some() {
+            return Err(NP_Error::Parse("Invalid UUID string!".into()))
+        }
+
+        Ok(uuid)
+    }
 }
 
 impl Debug for NP_UUID {
@@ -146,17 +172,13 @@ impl<'value> NP_Value<'value> for &NP_UUID {
 
         if value_address != 0 { // existing value, replace
             let bytes = value.value;
-            let write_bytes = memory.write_bytes();
-
             // overwrite existing values in buffer
-            for x in 0..bytes.len() {
-                write_bytes[value_address + x] = bytes[x];
-            }
+            memory.get_bytes_mut(value_address, bytes.len())?.copy_from_slice(&bytes);
 
         } else { // new value
 
             value_address = memory.malloc_borrow(&value.value)?;
-            c_value.set_addr_value(value_address as u16);
+            cursor.get_value(memory).set_addr_value(value_address as u32);
         }                    
         
         Ok(cursor)
@@ -264,4 +286,14 @@ fn set_clear_value_and_compaction_works() -> Result<(), NP_Error> {
     assert_eq!(buffer.calc_bytes()?.current_buffer, 3usize);
 
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn from_string_works() -> Result<(), NP_Error> {
+    let uuid = NP_UUID::generate(212);
+    assert_eq!(NP_UUID::from_string(&uuid.to_string())?, uuid);
+    assert_eq!(NP_UUID::from_string("9ee6aab02c9441fefb8842f73253f217")?, uuid);
+    assert!(NP_UUID::from_string("9EE6AAB0-2C94-41FE-FB88-42F73253F2").is_err());
+    assert!(NP_UUID::from_string("9EE6AAB0-2C94-41FE-FB88-42F73253F21Z").is_err());
+
+    Ok(())
+}
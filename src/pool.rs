@@ -0,0 +1,220 @@
+//! Reuse buffer allocations between messages
+//!
+//! Allocating a new `Vec<u8>` for every message adds up quickly in hot loops.  Buffers from `NP_Factory::pooled_buffer` hand their allocation back to the factory when they're dropped or finished, the next pooled buffer picks it up again instead of allocating.
+//!
+//! Pooled buffers dereference into a normal `NP_Buffer`, so every buffer method is available.  Pools are locked with a `spin` mutex, so they're only available on targets with atomic pointers.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut sent: Vec<usize> = Vec::new();
+//!
+//! for (name, age) in [("Jeb", 30u8), ("Bill", 28u8), ("Bob", 29u8)].iter() {
+//!     let mut buffer = factory.pooled_buffer();
+//!     buffer.set(&["name"], *name)?;
+//!     buffer.set(&["age"], *age)?;
+//!     // write the bytes somewhere, then the allocation goes back into the pool
+//!     sent.push(buffer.finish(|bytes| bytes.len()));
+//! }
+//!
+//! assert_eq!(sent, vec![19, 20, 19]);
+//! // one allocation was used for every message
+//! assert_eq!(factory.pooled_allocations(), 1);
+//!
+//! // closing a pooled buffer takes the allocation out of the pool
+//! let buffer = factory.pooled_buffer();
+//! let bytes: Vec<u8> = buffer.close();
+//! assert_eq!(factory.pooled_allocations(), 0);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::NP_Buffer;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+use core::ops::{Deref, DerefMut};
+use spin::{Mutex, MutexGuard};
+
+/// Most allocations kept in a factory pool, extra allocations are freed
+const POOL_MAX_SIZE: usize = 32;
+
+/// Allocations waiting to be reused by pooled buffers
+///
+/// The pool is only ever locked for a push or pop, if another thread holds the lock the allocation is skipped instead of waiting.
+pub(crate) struct NP_Pool {
+    free: Mutex<Vec<Vec<u8>>>
+}
+
+impl Debug for NP_Pool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("NP_Pool").field("allocations", &self.len()).finish()
+    }
+}
+
+impl NP_Pool {
+
+    pub fn new() -> Self {
+        NP_Pool { free: Mutex::new(Vec::new()) }
+    }
+
+    /// Lock the pool, `None` if another thread holds the lock
+    fn try_lock(&self) -> Option<MutexGuard<'_, Vec<Vec<u8>>>> {
+        self.free.try_lock()
+    }
+
+    /// Take an allocation out of the pool
+    pub fn take(&self) -> Option<Vec<u8>> {
+        self.try_lock()?.pop()
+    }
+
+    /// Put an allocation back into the pool
+    pub fn put(&self, bytes: Vec<u8>) {
+        if bytes.capacity() == 0 {
+            return;
+        }
+        if let Some(mut free) = self.try_lock() {
+            if free.len() < POOL_MAX_SIZE {
+                free.push(bytes);
+            }
+        }
+    }
+
+    /// How many allocations are in the pool
+    pub fn len(&self) -> usize {
+        self.try_lock().map(|free| free.len()).unwrap_or(0)
+    }
+}
+
+/// A buffer that gives it's allocation back to the factory pool
+///
+/// Check out documentation [here](../pool/index.html).
+///
+pub struct NP_Pooled_Buffer<'buffer> {
+    // always `Some` until the buffer is finished, closed or dropped
+    buffer: Option<NP_Buffer<'buffer>>,
+    pool: &'buffer NP_Pool
+}
+
+impl<'buffer> NP_Pooled_Buffer<'buffer> {
+
+    pub(crate) fn new(buffer: NP_Buffer<'buffer>, pool: &'buffer NP_Pool) -> Self {
+        NP_Pooled_Buffer { buffer: Some(buffer), pool }
+    }
+
+    /// Read the finished bytes of the buffer, then put the allocation back into the pool
+    pub fn finish<F, R>(mut self, callback: F) -> R where F: FnOnce(&[u8]) -> R {
+        match self.buffer.take() {
+            Some(buffer) => {
+                let result = callback(buffer.read_bytes());
+                self.pool.put(buffer.close());
+                result
+            },
+            None => callback(&[])
+        }
+    }
+
+    /// Close the buffer and keep it's bytes, the allocation doesn't go back into the pool
+    pub fn close(mut self) -> Vec<u8> {
+        match self.buffer.take() {
+            Some(buffer) => buffer.close(),
+            None => Vec::new()
+        }
+    }
+}
+
+impl<'buffer> Deref for NP_Pooled_Buffer<'buffer> {
+    type Target = NP_Buffer<'buffer>;
+
+    fn deref(&self) -> &Self::Target {
+        match &self.buffer {
+            Some(buffer) => buffer,
+            None => unreachable!()
+        }
+    }
+}
+
+impl<'buffer> DerefMut for NP_Pooled_Buffer<'buffer> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match &mut self.buffer {
+            Some(buffer) => buffer,
+            None => unreachable!()
+        }
+    }
+}
+
+impl<'buffer> Drop for NP_Pooled_Buffer<'buffer> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            self.pool.put(buffer.close());
+        }
+    }
+}
+
+#[test]
+fn pooled_buffers_work() -> Result<(), crate::error::NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?.with_version(2);
+
+    {
+        let mut first = factory.pooled_buffer();
+        first.set(&["0"], "hello")?;
+        let mut second = factory.pooled_buffer();
+        second.set(&["1"], "world")?;
+    }
+    assert_eq!(factory.pooled_allocations(), 2);
+
+    // recycled buffers start out empty with the factory header
+    let buffer = factory.pooled_buffer();
+    assert_eq!(factory.pooled_allocations(), 1);
+    assert_eq!(buffer.get_version(), Some(2));
+    assert_eq!(buffer.json_encode(&[])?.stringify(), "null");
+    assert_eq!(buffer.read_bytes(), factory.empty_buffer(None).read_bytes());
+    assert!(buffer.close().capacity() >= 1024);
+
+    Ok(())
+}
+
+#[test]
+fn pool_lock_works() -> Result<(), crate::error::NP_Error> {
+    use alloc::vec;
+
+    let pool = NP_Pool::new();
+    pool.put(Vec::with_capacity(8));
+    assert_eq!(pool.len(), 1);
+
+    // while the pool is locked allocations are skipped instead of waiting
+    {
+        let free = pool.try_lock();
+        assert!(free.is_some());
+        assert!(pool.try_lock().is_none());
+        assert_eq!(pool.take(), None);
+        pool.put(vec![1u8]);
+        assert_eq!(pool.len(), 0);
+    }
+
+    // dropping the guard unlocks the pool
+    assert_eq!(pool.len(), 1);
+    assert!(pool.take().is_some());
+    assert_eq!(pool.len(), 0);
+
+    // the pool is capped
+    for _ in 0..(POOL_MAX_SIZE + 4) {
+        pool.put(Vec::with_capacity(8));
+    }
+    assert_eq!(pool.len(), POOL_MAX_SIZE);
+    pool.put(Vec::new());
+    assert_eq!(pool.len(), POOL_MAX_SIZE);
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+//! Copy parts of a buffer into a smaller buffer
+//!
+//! APIs often return only a few values of a larger document.  An `NP_Projection` derives a smaller schema from a list of paths, `NP_Buffer::project` then copies just the values at those paths into a new buffer of the smaller schema without exporting to JSON and importing it again.
+//!
+//! Paths are separated with dots and a `*` selects every item of a list or every value of a map, so `"users.*.name"` selects the name of every user.  Tables only keep the selected columns in the order they have in the original schema, and selecting a collection keeps everything inside it.  Tuples keep all of their values in the schema so the indexes don't change, only the selected values are copied.
+//!
+//! Buffers made by `project` use the factory of the projection, so the projection has to outlive them.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::projection::NP_Projection;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",  {"type": "string"}],
+//!         ["email", {"type": "string"}],
+//!         ["list",  {"type": "list", "of": {"type": "table", "columns": [
+//!             ["title",  {"type": "string"}],
+//!             ["rating", {"type": "u8"}]
+//!         ]}}]
+//!     ]
+//! }"#)?;
+//!
+//! let projection = NP_Projection::new(&factory, &["name", "list.*.rating"])?;
+//!
+//! let mut buffer = factory.empty_buffer(None);
+//! buffer.set(&["name"], "Jeb")?;
+//! buffer.set(&["email"], "jeb@example.com")?;
+//! buffer.set(&["list", "0", "title"], "Dune")?;
+//! buffer.set(&["list", "0", "rating"], 5u8)?;
+//!
+//! let trimmed = buffer.project(&projection)?;
+//! assert_eq!(trimmed.json_encode(&[])?.stringify(), r#"{"name":"Jeb","list":[{"rating":5}]}"#);
+//!
+//! // columns that weren't selected aren't in the smaller schema
+//! assert!(projection.factory().compile_path(&["email"]).is_err());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::json_flex::NP_JSON;
+use crate::owned::NP_Value_Owned;
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The smaller schema and the paths of a projection
+///
+/// Check out documentation [here](../projection/index.html).
+///
+#[derive(Debug)]
+pub struct NP_Projection {
+    factory: NP_Factory,
+    paths: Vec<Vec<String>>
+}
+
+/// Split a dotted path into it's steps
+fn to_path(path: &str) -> Vec<String> {
+    if path.is_empty() {
+        return Vec::new();
+    }
+    path.split('.').map(|x| x.to_owned()).collect()
+}
+
+/// Paths that start with `key`, without their first step
+fn following<'paths>(paths: &[&'paths [String]], key: &str, index: Option<usize>) -> Vec<&'paths [String]> {
+    paths.iter().filter(|path| {
+        path[0] == "*" || path[0] == key || (index.is_some() && path[0].parse::<usize>().ok() == index)
+    }).map(|path| &path[1..]).collect()
+}
+
+/// Remove everything from a schema that isn't on one of the paths
+fn prune(schema: &NP_JSON, paths: &[&[String]]) -> Result<NP_JSON, NP_Error> {
+
+    if paths.iter().any(|path| path.is_empty()) {
+        return Ok(schema.clone());
+    }
+
+    let mut pruned = schema.clone();
+
+    let kind = match schema["type"].into_string() {
+        Some(x) => x.as_str(),
+        None => return Err(NP_Error::Schema("Schema types must be a string!".into()))
+    };
+
+    match kind {
+        "table" => {
+            let columns = match schema["columns"].into_vec() {
+                Some(x) => x,
+                None => return Err(NP_Error::Schema("Tables require a 'columns' property that is an array of schemas!".into()))
+            };
+            for path in paths {
+                if !columns.iter().any(|column| column[0].into_string() == Some(&path[0])) {
+                    return Err(NP_Error::BadPath("Projection path goes to a column that isn't in the table: ".to_owned() + &path[0]));
+                }
+            }
+            let mut kept = Vec::new();
+            for column in columns {
+                let name = column[0].into_string().cloned().unwrap_or_default();
+                let column_paths = following(paths, &name, None);
+                if !column_paths.is_empty() {
+                    kept.push(NP_JSON::Array(alloc::vec![NP_JSON::String(name), prune(&column[1], &column_paths)?]));
+                }
+            }
+            if let NP_JSON::Dictionary(map) = &mut pruned {
+                map.insert("columns".to_owned(), NP_JSON::Array(kept));
+            }
+        },
+        "list" | "map" => {
+            let child = if kind == "list" { "of" } else { "value" };
+            if kind == "list" && paths.iter().any(|path| path[0] != "*" && path[0].parse::<usize>().is_err()) {
+                return Err(NP_Error::BadPath("Projection paths into lists need an index or '*'!".into()));
+            }
+            let child_paths: Vec<&[String]> = paths.iter().map(|path| &path[1..]).collect();
+            if let NP_JSON::Dictionary(map) = &mut pruned {
+                map.insert(child.to_owned(), prune(&schema[child], &child_paths)?);
+            }
+        },
+        "tuple" => {
+            let len = schema["values"].into_vec().map(|values| values.len()).unwrap_or(0);
+            if paths.iter().any(|path| path[0].parse::<usize>().map(|index| index >= len).unwrap_or(path[0] != "*")) {
+                return Err(NP_Error::BadPath("Projection paths into tuples need an index in the tuple or '*'!".into()));
+            }
+        },
+        _ => return Err(NP_Error::BadPath("Projection path goes past a value that isn't a collection!".into()))
+    }
+
+    Ok(pruned)
+}
+
+/// Copy the parts of a value tree that are on one of the paths
+fn select(value: &NP_Value_Owned, paths: &[&[String]]) -> NP_Value_Owned {
+
+    if paths.iter().any(|path| path.is_empty()) {
+        return value.clone();
+    }
+
+    match value {
+        NP_Value_Owned::Table(entries) | NP_Value_Owned::Map(entries) => {
+            let selected = entries.iter().filter_map(|(name, child)| {
+                let child_paths = following(paths, name, None);
+                if child_paths.is_empty() {
+                    None
+                } else {
+                    Some((name.clone(), select(child, &child_paths)))
+                }
+            }).collect();
+            match value {
+                NP_Value_Owned::Table(_) => NP_Value_Owned::Table(selected),
+                _ => NP_Value_Owned::Map(selected)
+            }
+        },
+        NP_Value_Owned::List(items) | NP_Value_Owned::Tuple(items) => {
+            // unselected items become null so the indexes stay the same
+            let selected = items.iter().enumerate().map(|(index, child)| {
+                let child_paths = following(paths, "*", Some(index));
+                if child_paths.is_empty() {
+                    NP_Value_Owned::Null
+                } else {
+                    select(child, &child_paths)
+                }
+            }).collect();
+            match value {
+                NP_Value_Owned::List(_) => NP_Value_Owned::List(selected),
+                _ => NP_Value_Owned::Tuple(selected)
+            }
+        },
+        _ => NP_Value_Owned::Null
+    }
+}
+
+impl NP_Projection {
+
+    /// Derive the smaller schema for `paths` from the schema of a factory.  Every path has to be in the schema.
+    pub fn new(factory: &NP_Factory, paths: &[&str]) -> Result<Self, NP_Error> {
+        let paths: Vec<Vec<String>> = paths.iter().map(|path| to_path(path)).collect();
+        let path_refs: Vec<&[String]> = paths.iter().map(|path| path.as_slice()).collect();
+        let schema = prune(&factory.export_schema()?, &path_refs)?;
+        Ok(NP_Projection { factory: NP_Factory::new(&schema.stringify())?, paths })
+    }
+
+    /// The factory of the smaller schema
+    pub fn factory(&self) -> &NP_Factory {
+        &self.factory
+    }
+
+    /// Copy the selected values of a buffer into a new buffer of the smaller schema
+    pub fn project(&self, buffer: &NP_Buffer) -> Result<NP_Buffer<'_>, NP_Error> {
+        let path_refs: Vec<&[String]> = self.paths.iter().map(|path| path.as_slice()).collect();
+        let tree = select(&buffer.root_owned_tree()?, &path_refs);
+        let mut projected = self.factory.empty_buffer(None);
+        projected.from_owned_tree(&tree)?;
+        Ok(projected)
+    }
+}
+
+#[test]
+fn projection_works() -> Result<(), NP_Error> {
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id",    {"type": "u32"}],
+            ["pair",  {"type": "tuple", "values": [{"type": "string"}, {"type": "u8"}]}],
+            ["tags",  {"type": "map", "value": {"type": "table", "columns": [["a", {"type": "u8"}], ["b", {"type": "u8"}]]}}],
+            ["items", {"type": "list", "of": {"type": "string"}}]
+        ]
+    }"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["id"], 4u32)?;
+    buffer.set(&["pair", "0"], "x")?;
+    buffer.set(&["pair", "1"], 2u8)?;
+    buffer.set(&["tags", "red", "a"], 1u8)?;
+    buffer.set(&["tags", "red", "b"], 2u8)?;
+    buffer.set(&["tags", "blue", "b"], 3u8)?;
+    buffer.list_push(&["items"], "first")?;
+    buffer.list_push(&["items"], "second")?;
+
+    // tuples keep their schema, only the selected values are copied
+    let projection = NP_Projection::new(&factory, &["pair.1", "tags.*.b", "items.1"])?;
+    assert!(projection.factory().compile_path(&["pair", "0"]).is_ok());
+    assert!(projection.factory().compile_path(&["tags", "red", "a"]).is_err());
+    let projected = buffer.project(&projection)?;
+    assert_eq!(projected.get::<&str>(&["pair", "0"])?, None);
+    assert_eq!(projected.get::<u8>(&["pair", "1"])?, Some(2));
+    assert_eq!(projected.get::<u8>(&["tags", "red", "b"])?, Some(2));
+    assert_eq!(projected.get::<u8>(&["tags", "blue", "b"])?, Some(3));
+    assert_eq!(projected.get::<&str>(&["items", "0"])?, None);
+    assert_eq!(projected.get::<&str>(&["items", "1"])?, Some("second"));
+
+    // a map key only copies that key, an empty path copies everything
+    let projection = NP_Projection::new(&factory, &["tags.red"])?;
+    let projected = buffer.project(&projection)?;
+    assert_eq!(projected.json_encode(&[])?.stringify(), r#"{"tags":{"red":{"a":1,"b":2}}}"#);
+    let projection = NP_Projection::new(&factory, &[""])?;
+    let projected = buffer.project(&projection)?;
+    assert!(projected.deep_eq(&buffer)?);
+
+    // paths have to be in the schema
+    assert!(NP_Projection::new(&factory, &["name"]).is_err());
+    assert!(NP_Projection::new(&factory, &["id.x"]).is_err());
+    assert!(NP_Projection::new(&factory, &["items.x"]).is_err());
+    assert!(NP_Projection::new(&factory, &["pair.2"]).is_err());
+
+    Ok(())
+}
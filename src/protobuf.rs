@@ -0,0 +1,652 @@
+//! Protocol Buffers bridge
+//!
+//! With the `protobuf` feature enabled, a table schema can be converted into a proto3 `.proto` message definition and buffers can be read from and written to the protobuf wire format of that message.
+//!
+//! Table columns become fields numbered by their column index (the first column is field `1`), nested tables and tuples become nested messages and lists become `repeated` fields.
+//!
+//! Types are mapped like this:
+//!
+//! | NoProto type                  | Protobuf type                                      |
+//! |-------------------------------|----------------------------------------------------|
+//! | `int8`, `int16`, `int32`      | `int32`                                            |
+//! | `uint8`, `uint16`, `uint32`   | `uint32`                                           |
+//! | `int64`, `uint64`             | `int64`, `uint64`                                  |
+//! | `float`, `double`, `bool`     | `float`, `double`, `bool`                          |
+//! | `decimal`                     | `sint64` holding the number scaled by the schema `exp` |
+//! | `date`                        | `uint64` milliseconds since the unix epoch         |
+//! | `string`, `option`            | `string`                                           |
+//! | `bytes`, `uuid`, `ulid`       | `bytes`                                            |
+//! | `geo`                         | nested message with `double lat = 1` and `double lng = 2` |
+//! | `table`, `tuple`              | nested message, tuple values are fields `1`, `2`, ... |
+//! | `list`                        | `repeated` field                                   |
+//! | `map`                         | `map<string, ...>`                                 |
+//!
+//! Protobuf can't express lists of lists or maps of lists and maps, these schemas produce an error.  Empty list items have no protobuf equivalent and are written as the zero value of the list type.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "uint8"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! assert_eq!(factory.schema.to_proto("User")?, concat!(
+//!     "syntax = \"proto3\";\n",
+//!     "\n",
+//!     "message User {\n",
+//!     "  string name = 1;\n",
+//!     "  uint32 age = 2;\n",
+//!     "  repeated string tags = 3;\n",
+//!     "}\n"
+//! ));
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//! new_buffer.set(&["age"], 30u8)?;
+//!
+//! let bytes: Vec<u8> = new_buffer.protobuf_encode()?;
+//! assert_eq!(bytes, vec![0x0a, 0x03, b'J', b'e', b'b', 0x10, 30]);
+//!
+//! let copy_buffer = factory.from_protobuf(&bytes)?;
+//! assert_eq!(copy_buffer.get::<&str>(&["name"])?, Some("Jeb"));
+//! assert_eq!(copy_buffer.get::<u8>(&["age"])?, Some(30));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::owned::NP_Value_Owned;
+use crate::schema::NP_Parsed_Schema;
+use crate::pointer::dec::NP_Dec;
+use crate::pointer::geo::NP_Geo;
+use crate::pointer::date::NP_Date;
+use crate::pointer::uuid::NP_UUID;
+use crate::pointer::ulid::NP_ULID;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_BYTES: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+/// Turn a key into a valid proto identifier
+fn field_name(name: &str) -> String {
+    let mut ident: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if ident.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(true) {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+/// Turn a key into a message name
+fn message_name(name: &str) -> String {
+    let mut ident = String::new();
+    let mut upper = true;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if upper {
+                ident.push(c.to_ascii_uppercase());
+            } else {
+                ident.push(c);
+            }
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    if ident.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(true) {
+        ident.insert(0, 'M');
+    }
+    ident
+}
+
+fn unsupported(reason: &str) -> NP_Error {
+    let mut err = String::from("Schema can't be represented in protobuf: ");
+    err.push_str(reason);
+    NP_Error::SchemaMismatch(err)
+}
+
+/// Get the proto type for the schema at `address`, nested messages are added to `nested`
+fn proto_type(schema: &Vec<NP_Parsed_Schema>, address: usize, name: &str, nested: &mut Vec<(String, usize)>) -> Result<String, NP_Error> {
+    Ok(match &schema[address] {
+        NP_Parsed_Schema::Int8 { .. } | NP_Parsed_Schema::Int16 { .. } | NP_Parsed_Schema::Int32 { .. } => "int32".to_string(),
+        NP_Parsed_Schema::Int64 { .. } => "int64".to_string(),
+        NP_Parsed_Schema::Uint8 { .. } | NP_Parsed_Schema::Uint16 { .. } | NP_Parsed_Schema::Uint32 { .. } => "uint32".to_string(),
+        NP_Parsed_Schema::Uint64 { .. } | NP_Parsed_Schema::Date { .. } => "uint64".to_string(),
+        NP_Parsed_Schema::Float { .. } => "float".to_string(),
+        NP_Parsed_Schema::Double { .. } => "double".to_string(),
+        NP_Parsed_Schema::Boolean { .. } => "bool".to_string(),
+        NP_Parsed_Schema::Decimal { .. } => "sint64".to_string(),
+        NP_Parsed_Schema::UTF8String { .. } | NP_Parsed_Schema::Enum { .. } => "string".to_string(),
+        NP_Parsed_Schema::Bytes { .. } | NP_Parsed_Schema::Uuid { .. } | NP_Parsed_Schema::Ulid { .. } => "bytes".to_string(),
+        NP_Parsed_Schema::Geo { .. } | NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Tuple { .. } => {
+            let message = message_name(name);
+            nested.push((message.clone(), address));
+            message
+        },
+        NP_Parsed_Schema::List { of, .. } => {
+            match &schema[*of] {
+                NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } => return Err(unsupported("lists can only hold scalars, tables and tuples")),
+                _ => { }
+            }
+            let mut repeated = String::from("repeated ");
+            repeated.push_str(&proto_type(schema, *of, name, nested)?);
+            repeated
+        },
+        NP_Parsed_Schema::Map { value, .. } => {
+            match &schema[*value] {
+                NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } => return Err(unsupported("maps can only hold scalars, tables and tuples")),
+                _ => { }
+            }
+            let mut map = String::from("map<string, ");
+            map.push_str(&proto_type(schema, *value, name, nested)?);
+            map.push('>');
+            map
+        },
+        NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::None => return Err(unsupported("any types have no protobuf equivalent"))
+    })
+}
+
+/// Write the message definition for the table, tuple or geo schema at `address`
+fn write_message(schema: &Vec<NP_Parsed_Schema>, address: usize, name: &str, depth: usize, out: &mut String) -> Result<(), NP_Error> {
+
+    let indent = |out: &mut String, depth: usize| {
+        for _x in 0..depth {
+            out.push_str("  ");
+        }
+    };
+
+    let mut fields: Vec<(String, String, usize)> = Vec::new();
+    let mut nested: Vec<(String, usize)> = Vec::new();
+
+    match &schema[address] {
+        NP_Parsed_Schema::Table { columns, .. } => {
+            for (index, column, addr) in columns {
+                fields.push((proto_type(schema, *addr, column, &mut nested)?, field_name(column), *index as usize + 1));
+            }
+        },
+        NP_Parsed_Schema::Tuple { values, .. } => {
+            for (index, addr) in values.iter().enumerate() {
+                let mut value_name = String::from("value");
+                value_name.push_str(&index.to_string());
+                fields.push((proto_type(schema, *addr, &value_name, &mut nested)?, value_name, index + 1));
+            }
+        },
+        NP_Parsed_Schema::Geo { .. } => {
+            fields.push(("double".to_string(), "lat".to_string(), 1));
+            fields.push(("double".to_string(), "lng".to_string(), 2));
+        },
+        _ => return Err(unsupported("messages must be tables"))
+    }
+
+    indent(out, depth);
+    out.push_str("message ");
+    out.push_str(name);
+    out.push_str(" {\n");
+
+    for (field_type, field, number) in fields {
+        indent(out, depth + 1);
+        out.push_str(&field_type);
+        out.push(' ');
+        out.push_str(&field);
+        out.push_str(" = ");
+        out.push_str(&number.to_string());
+        out.push_str(";\n");
+    }
+
+    for (nested_name, nested_addr) in nested {
+        write_message(schema, nested_addr, &nested_name, depth + 1, out)?;
+    }
+
+    indent(out, depth);
+    out.push_str("}\n");
+
+    Ok(())
+}
+
+/// Generate a proto3 file with the table schema at `address` as it's message
+pub(crate) fn schema_to_proto(schema: &Vec<NP_Parsed_Schema>, address: usize, name: &str) -> Result<String, NP_Error> {
+    match &schema[address] {
+        NP_Parsed_Schema::Table { .. } => { },
+        _ => return Err(unsupported("messages must be tables"))
+    }
+    let mut out = String::from("syntax = \"proto3\";\n\n");
+    write_message(schema, address, &message_name(name), 0, &mut out)?;
+    Ok(out)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_tag(out: &mut Vec<u8>, field: usize, wire: u8) {
+    write_varint(out, ((field as u64) << 3) | wire as u64);
+}
+
+fn write_len_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn wire_type(schema: &NP_Parsed_Schema) -> u8 {
+    match schema {
+        NP_Parsed_Schema::Float { .. } => WIRE_FIXED32,
+        NP_Parsed_Schema::Double { .. } => WIRE_FIXED64,
+        NP_Parsed_Schema::Int8 { .. } | NP_Parsed_Schema::Int16 { .. } | NP_Parsed_Schema::Int32 { .. } | NP_Parsed_Schema::Int64 { .. } |
+        NP_Parsed_Schema::Uint8 { .. } | NP_Parsed_Schema::Uint16 { .. } | NP_Parsed_Schema::Uint32 { .. } | NP_Parsed_Schema::Uint64 { .. } |
+        NP_Parsed_Schema::Boolean { .. } | NP_Parsed_Schema::Decimal { .. } | NP_Parsed_Schema::Date { .. } => WIRE_VARINT,
+        _ => WIRE_BYTES
+    }
+}
+
+fn encode_error(schema: &NP_Parsed_Schema) -> NP_Error {
+    let mut err = String::from("TypeError: Value doesn't match schema of type (");
+    err.push_str(schema.get_type_data().0);
+    err.push_str(")");
+    NP_Error::SchemaMismatch(err)
+}
+
+/// Write a value without it's tag, `Null` writes the zero value
+fn write_raw(out: &mut Vec<u8>, value: &NP_Value_Owned, schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<(), NP_Error> {
+    match value {
+        NP_Value_Owned::Null => match wire_type(&schema[address]) {
+            WIRE_VARINT => out.push(0),
+            WIRE_FIXED32 => out.extend_from_slice(&[0u8; 4]),
+            WIRE_FIXED64 => out.extend_from_slice(&[0u8; 8]),
+            _ => out.push(0)
+        },
+        NP_Value_Owned::Int8(x) => write_varint(out, *x as i64 as u64),
+        NP_Value_Owned::Int16(x) => write_varint(out, *x as i64 as u64),
+        NP_Value_Owned::Int32(x) => write_varint(out, *x as i64 as u64),
+        NP_Value_Owned::Int64(x) => write_varint(out, *x as u64),
+        NP_Value_Owned::Uint8(x) => write_varint(out, *x as u64),
+        NP_Value_Owned::Uint16(x) => write_varint(out, *x as u64),
+        NP_Value_Owned::Uint32(x) => write_varint(out, *x as u64),
+        NP_Value_Owned::Uint64(x) => write_varint(out, *x),
+        NP_Value_Owned::Bool(x) => out.push(*x as u8),
+        NP_Value_Owned::Date(x) => write_varint(out, x.value),
+        NP_Value_Owned::Dec(x) => write_varint(out, ((x.num << 1) ^ (x.num >> 63)) as u64),
+        NP_Value_Owned::Float(x) => out.extend_from_slice(&x.to_le_bytes()),
+        NP_Value_Owned::Double(x) => out.extend_from_slice(&x.to_le_bytes()),
+        NP_Value_Owned::String(x) | NP_Value_Owned::Enum(x) => write_len_bytes(out, x.as_bytes()),
+        NP_Value_Owned::Bytes(x) => write_len_bytes(out, x),
+        NP_Value_Owned::Uuid(x) => write_len_bytes(out, &x.value),
+        NP_Value_Owned::Ulid(x) => write_len_bytes(out, x.as_bytes()),
+        NP_Value_Owned::Geo(x) => {
+            let mut message = Vec::new();
+            write_tag(&mut message, 1, WIRE_FIXED64);
+            message.extend_from_slice(&x.lat.to_le_bytes());
+            write_tag(&mut message, 2, WIRE_FIXED64);
+            message.extend_from_slice(&x.lng.to_le_bytes());
+            write_len_bytes(out, &message);
+        },
+        NP_Value_Owned::Table(_) | NP_Value_Owned::Tuple(_) => {
+            let mut message = Vec::new();
+            encode_message(&mut message, value, schema, address)?;
+            write_len_bytes(out, &message);
+        },
+        NP_Value_Owned::List(_) | NP_Value_Owned::Map(_) => return Err(encode_error(&schema[address]))
+    }
+    Ok(())
+}
+
+/// Write a field and it's value
+fn encode_field(out: &mut Vec<u8>, field: usize, value: &NP_Value_Owned, schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<(), NP_Error> {
+    match (&schema[address], value) {
+        (NP_Parsed_Schema::List { of, .. }, NP_Value_Owned::List(items)) => {
+            if wire_type(&schema[*of]) == WIRE_BYTES {
+                for item in items {
+                    write_tag(out, field, WIRE_BYTES);
+                    write_raw(out, item, schema, *of)?;
+                }
+            } else if items.len() > 0 {
+                let mut packed = Vec::new();
+                for item in items {
+                    write_raw(&mut packed, item, schema, *of)?;
+                }
+                write_tag(out, field, WIRE_BYTES);
+                write_len_bytes(out, &packed);
+            }
+        },
+        (NP_Parsed_Schema::Map { value: of, .. }, NP_Value_Owned::Map(entries)) => {
+            for (key, item) in entries {
+                let mut entry = Vec::new();
+                write_tag(&mut entry, 1, WIRE_BYTES);
+                write_len_bytes(&mut entry, key.as_bytes());
+                encode_field(&mut entry, 2, item, schema, *of)?;
+                write_tag(out, field, WIRE_BYTES);
+                write_len_bytes(out, &entry);
+            }
+        },
+        (NP_Parsed_Schema::List { .. }, _) | (NP_Parsed_Schema::Map { .. }, _) => return Err(encode_error(&schema[address])),
+        (_, NP_Value_Owned::Null) => { },
+        _ => {
+            write_tag(out, field, wire_type(&schema[address]));
+            write_raw(out, value, schema, address)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the fields of a table or tuple value
+pub(crate) fn encode_message(out: &mut Vec<u8>, value: &NP_Value_Owned, schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<(), NP_Error> {
+    match (&schema[address], value) {
+        (NP_Parsed_Schema::Table { columns, .. }, NP_Value_Owned::Table(entries)) => {
+            for (key, item) in entries {
+                if let Some((index, _name, addr)) = columns.iter().find(|(_index, name, _addr)| name == key) {
+                    encode_field(out, *index as usize + 1, item, schema, *addr)?;
+                }
+            }
+            Ok(())
+        },
+        (NP_Parsed_Schema::Tuple { values, .. }, NP_Value_Owned::Tuple(items)) => {
+            for (index, (item, addr)) in items.iter().zip(values.iter()).enumerate() {
+                encode_field(out, index + 1, item, schema, *addr)?;
+            }
+            Ok(())
+        },
+        (NP_Parsed_Schema::Table { .. }, NP_Value_Owned::Null) | (NP_Parsed_Schema::Tuple { .. }, NP_Value_Owned::Null) => Ok(()),
+        _ => Err(unsupported("messages must be tables"))
+    }
+}
+
+/// A field value read off the wire
+enum PB_Value<'bytes> {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Fixed32([u8; 4]),
+    Bytes(&'bytes [u8])
+}
+
+struct PB_Reader<'bytes> {
+    bytes: &'bytes [u8],
+    index: usize
+}
+
+impl<'bytes> PB_Reader<'bytes> {
+
+    fn take(&mut self, len: usize) -> Result<&'bytes [u8], NP_Error> {
+        if self.index + len > self.bytes.len() {
+            return Err(NP_Error::Parse("Protobuf data ended unexpectedly!".into()));
+        }
+        let slice = &self.bytes[self.index..(self.index + len)];
+        self.index += len;
+        Ok(slice)
+    }
+
+    fn is_done(&self) -> bool {
+        self.index >= self.bytes.len()
+    }
+
+    fn read_varint(&mut self) -> Result<u64, NP_Error> {
+        let mut value = 0u64;
+        for shift in 0..10 {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << (shift * 7);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(NP_Error::Parse("Invalid protobuf varint!".into()))
+    }
+
+    fn read_value(&mut self, wire: u8) -> Result<PB_Value<'bytes>, NP_Error> {
+        Ok(match wire {
+            WIRE_VARINT => PB_Value::Varint(self.read_varint()?),
+            WIRE_FIXED64 => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(self.take(8)?);
+                PB_Value::Fixed64(bytes)
+            },
+            WIRE_FIXED32 => {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(self.take(4)?);
+                PB_Value::Fixed32(bytes)
+            },
+            WIRE_BYTES => {
+                let len = usize::try_from(self.read_varint()?).map_err(|_| NP_Error::Parse("Protobuf length is too large!".into()))?;
+                PB_Value::Bytes(self.take(len)?)
+            },
+            _ => return Err(NP_Error::Parse("Unsupported protobuf wire type!".into()))
+        })
+    }
+
+    fn read_field(&mut self) -> Result<(usize, PB_Value<'bytes>), NP_Error> {
+        let tag = self.read_varint()?;
+        let field = usize::try_from(tag >> 3).map_err(|_| NP_Error::Parse("Protobuf field number is too large!".into()))?;
+        Ok((field, self.read_value((tag & 0x7) as u8)?))
+    }
+}
+
+fn decode_error(schema: &NP_Parsed_Schema) -> NP_Error {
+    let mut err = String::from("TypeError: Protobuf value doesn't match schema of type (");
+    err.push_str(schema.get_type_data().0);
+    err.push_str(")");
+    NP_Error::SchemaMismatch(err)
+}
+
+/// Convert a single wire value using the schema at `address`
+fn decode_value(value: PB_Value, schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<NP_Value_Owned, NP_Error> {
+
+    let this_schema = &schema[address];
+
+    macro_rules! int {
+        ($x: expr, $t: ty, $variant: ident) => {
+            <$t>::try_from($x).map(NP_Value_Owned::$variant).map_err(|_| decode_error(this_schema))
+        }
+    }
+
+    match (this_schema, value) {
+        (NP_Parsed_Schema::Int8 { .. }, PB_Value::Varint(x)) => int!(x as i64, i8, Int8),
+        (NP_Parsed_Schema::Int16 { .. }, PB_Value::Varint(x)) => int!(x as i64, i16, Int16),
+        (NP_Parsed_Schema::Int32 { .. }, PB_Value::Varint(x)) => int!(x as i64, i32, Int32),
+        (NP_Parsed_Schema::Int64 { .. }, PB_Value::Varint(x)) => Ok(NP_Value_Owned::Int64(x as i64)),
+        (NP_Parsed_Schema::Uint8 { .. }, PB_Value::Varint(x)) => int!(x, u8, Uint8),
+        (NP_Parsed_Schema::Uint16 { .. }, PB_Value::Varint(x)) => int!(x, u16, Uint16),
+        (NP_Parsed_Schema::Uint32 { .. }, PB_Value::Varint(x)) => int!(x, u32, Uint32),
+        (NP_Parsed_Schema::Uint64 { .. }, PB_Value::Varint(x)) => Ok(NP_Value_Owned::Uint64(x)),
+        (NP_Parsed_Schema::Boolean { .. }, PB_Value::Varint(x)) => Ok(NP_Value_Owned::Bool(x != 0)),
+        (NP_Parsed_Schema::Date { .. }, PB_Value::Varint(x)) => Ok(NP_Value_Owned::Date(NP_Date::new(x))),
+        (NP_Parsed_Schema::Decimal { exp, .. }, PB_Value::Varint(x)) => Ok(NP_Value_Owned::Dec(NP_Dec::new(((x >> 1) as i64) ^ -((x & 1) as i64), *exp))),
+        (NP_Parsed_Schema::Float { .. }, PB_Value::Fixed32(x)) => Ok(NP_Value_Owned::Float(f32::from_le_bytes(x))),
+        (NP_Parsed_Schema::Double { .. }, PB_Value::Fixed64(x)) => Ok(NP_Value_Owned::Double(f64::from_le_bytes(x))),
+        (NP_Parsed_Schema::UTF8String { .. }, PB_Value::Bytes(x)) => Ok(NP_Value_Owned::String(String::from(core::str::from_utf8(x)?))),
+        (NP_Parsed_Schema::Enum { .. }, PB_Value::Bytes(x)) => Ok(NP_Value_Owned::Enum(String::from(core::str::from_utf8(x)?))),
+        (NP_Parsed_Schema::Bytes { .. }, PB_Value::Bytes(x)) => Ok(NP_Value_Owned::Bytes(x.to_vec())),
+        (NP_Parsed_Schema::Uuid { .. }, PB_Value::Bytes(x)) if x.len() == 16 => {
+            let mut uuid = NP_UUID { value: [0; 16] };
+            uuid.value.copy_from_slice(x);
+            Ok(NP_Value_Owned::Uuid(uuid))
+        },
+        (NP_Parsed_Schema::Ulid { .. }, PB_Value::Bytes(x)) if x.len() == 16 => {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(x);
+            Ok(NP_Value_Owned::Ulid(NP_ULID::from_bytes(bytes)))
+        },
+        (NP_Parsed_Schema::Geo { size, .. }, PB_Value::Bytes(x)) => {
+            let (mut lat, mut lng) = (0f64, 0f64);
+            let mut reader = PB_Reader { bytes: x, index: 0 };
+            while !reader.is_done() {
+                match reader.read_field()? {
+                    (1, PB_Value::Fixed64(v)) => lat = f64::from_le_bytes(v),
+                    (2, PB_Value::Fixed64(v)) => lng = f64::from_le_bytes(v),
+                    _ => { }
+                }
+            }
+            Ok(NP_Value_Owned::Geo(NP_Geo::new(*size, lat, lng)))
+        },
+        (NP_Parsed_Schema::Table { .. }, PB_Value::Bytes(x)) | (NP_Parsed_Schema::Tuple { .. }, PB_Value::Bytes(x)) => decode_message(x, schema, address),
+        _ => Err(decode_error(this_schema))
+    }
+}
+
+fn is_packable(schema: &NP_Parsed_Schema) -> bool {
+    wire_type(schema) != WIRE_BYTES
+}
+
+/// Read a table or tuple message using the schema at `address`
+pub(crate) fn decode_message(bytes: &[u8], schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<NP_Value_Owned, NP_Error> {
+
+    // the schema address for each field number
+    let fields: Vec<(usize, usize)> = match &schema[address] {
+        NP_Parsed_Schema::Table { columns, .. } => columns.iter().map(|(index, _name, addr)| (*index as usize + 1, *addr)).collect(),
+        NP_Parsed_Schema::Tuple { values, .. } => values.iter().enumerate().map(|(index, addr)| (index + 1, *addr)).collect(),
+        _ => return Err(unsupported("messages must be tables"))
+    };
+
+    let mut values: Vec<NP_Value_Owned> = fields.iter().map(|_x| NP_Value_Owned::Null).collect();
+
+    let mut reader = PB_Reader { bytes, index: 0 };
+
+    while !reader.is_done() {
+        let (field, value) = reader.read_field()?;
+
+        // unknown fields are skipped
+        let (slot, addr) = match fields.iter().position(|(number, _addr)| *number == field) {
+            Some(slot) => (slot, fields[slot].1),
+            None => continue
+        };
+
+        match &schema[addr] {
+            NP_Parsed_Schema::List { of, .. } => {
+                if let NP_Value_Owned::Null = values[slot] {
+                    values[slot] = NP_Value_Owned::List(Vec::new());
+                }
+                if let NP_Value_Owned::List(items) = &mut values[slot] {
+                    match value {
+                        PB_Value::Bytes(packed) if is_packable(&schema[*of]) => {
+                            let mut packed_reader = PB_Reader { bytes: packed, index: 0 };
+                            while !packed_reader.is_done() {
+                                let item = packed_reader.read_value(wire_type(&schema[*of]))?;
+                                items.push(decode_value(item, schema, *of)?);
+                            }
+                        },
+                        _ => items.push(decode_value(value, schema, *of)?)
+                    }
+                }
+            },
+            NP_Parsed_Schema::Map { value: of, .. } => {
+                let entry = match value {
+                    PB_Value::Bytes(x) => x,
+                    _ => return Err(decode_error(&schema[addr]))
+                };
+                let (mut key, mut item) = (String::new(), NP_Value_Owned::Null);
+                let mut entry_reader = PB_Reader { bytes: entry, index: 0 };
+                while !entry_reader.is_done() {
+                    match entry_reader.read_field()? {
+                        (1, PB_Value::Bytes(k)) => key = String::from(core::str::from_utf8(k)?),
+                        (2, v) => item = decode_value(v, schema, *of)?,
+                        _ => { }
+                    }
+                }
+                if let NP_Value_Owned::Null = values[slot] {
+                    values[slot] = NP_Value_Owned::Map(Vec::new());
+                }
+                if let NP_Value_Owned::Map(entries) = &mut values[slot] {
+                    entries.push((key, item));
+                }
+            },
+            _ => values[slot] = decode_value(value, schema, addr)?
+        }
+    }
+
+    Ok(match &schema[address] {
+        NP_Parsed_Schema::Table { columns, .. } => {
+            NP_Value_Owned::Table(columns.iter().zip(values.into_iter()).filter(|(_column, value)| *value != NP_Value_Owned::Null).map(|((_index, name, _addr), value)| (name.clone(), value)).collect())
+        },
+        _ => NP_Value_Owned::Tuple(values)
+    })
+}
+
+#[test]
+fn protobuf_round_trip_works() -> Result<(), NP_Error> {
+    let factory = crate::NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id",      {"type": "uuid"}],
+            ["when",    {"type": "date"}],
+            ["where",   {"type": "geo8"}],
+            ["price",   {"type": "dec", "exp": 2}],
+            ["small",   {"type": "i16"}],
+            ["big",     {"type": "u64"}],
+            ["pair",    {"type": "tuple", "values": [{"type": "string"}, {"type": "bool"}]}],
+            ["scores",  {"type": "list", "of": {"type": "float"}}],
+            ["names",   {"type": "list", "of": {"type": "string"}}],
+            ["meta",    {"type": "map", "value": {"type": "bytes"}}],
+            ["address", {"type": "table", "columns": [["street", {"type": "string"}]]}]
+        ]
+    }"#)?;
+
+    assert_eq!(factory.schema.to_proto("order form")?, r#"syntax = "proto3";
+
+message OrderForm {
+  bytes id = 1;
+  uint64 when = 2;
+  Where where = 3;
+  sint64 price = 4;
+  int32 small = 5;
+  uint64 big = 6;
+  Pair pair = 7;
+  repeated float scores = 8;
+  repeated string names = 9;
+  map<string, bytes> meta = 10;
+  Address address = 11;
+  message Where {
+    double lat = 1;
+    double lng = 2;
+  }
+  message Pair {
+    string value0 = 1;
+    bool value1 = 2;
+  }
+  message Address {
+    string street = 1;
+  }
+}
+"#);
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["id"], &NP_UUID::generate(22))?;
+    buffer.set(&["when"], NP_Date::new(1605138980392))?;
+    buffer.set(&["where"], NP_Geo::new(8, 45.5, -120.25))?;
+    buffer.set(&["price"], NP_Dec::new(-2050, 2))?;
+    buffer.set(&["small"], -300i16)?;
+    buffer.set(&["big"], u64::MAX)?;
+    buffer.set(&["pair", "1"], true)?;
+    buffer.set(&["scores", "0"], 2.5f32)?;
+    buffer.set(&["scores", "1"], -1f32)?;
+    buffer.set(&["names", "0"], "hello")?;
+    buffer.set(&["names", "1"], "world")?;
+    buffer.set(&["meta", "key"], &[1u8, 2, 3] as &[u8])?;
+    buffer.set(&["address", "street"], "Main")?;
+
+    let bytes = buffer.protobuf_encode()?;
+    let copy = factory.from_protobuf(&bytes)?;
+    assert_eq!(copy.to_owned_tree()?, buffer.to_owned_tree()?);
+
+    // unpacked repeated numbers and unknown fields are understood
+    let copy = factory.from_protobuf(&[0x45, 0x00, 0x00, 0xc0, 0x3f, 0x45, 0x00, 0x00, 0x00, 0x40, 0xf8, 0x01, 0x07])?;
+    assert_eq!(copy.get::<f32>(&["scores", "1"])?, Some(2.0));
+
+    // values that don't fit the schema are rejected
+    assert!(factory.from_protobuf(&[0x28, 0xff, 0xff, 0x03]).is_err());
+
+    // lists of lists can't be represented
+    let nested = crate::NP_Factory::new(r#"{"type": "table", "columns": [["grid", {"type": "list", "of": {"type": "list", "of": {"type": "u8"}}}]]}"#)?;
+    assert!(nested.schema.to_proto("Grid").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,294 @@
+//! Factories and buffers for Python
+//!
+//! Python code can read and write the same buffers as Rust services through the `Factory` and `Buffer` classes in this module.  Like the [WASM wrappers](../wasm/index.html) they own their bytes and a handle to the factory, since Python objects can't borrow from each other.  This module requires the `pyo3` feature.
+//!
+//! Values go in and out as Python objects: `None`, `bool`, `int`, `float`, `str`, `list`, `tuple` and `dict` with string keys.  `to_json` and `Factory.from_json` import and export whole buffers as JSON text.  Paths are strings with dots between each step, like `users.3.name`, an empty string is the root of the buffer.
+//!
+//! Errors are raised as `ValueError` with the message of the `NP_Error`, Python values that don't have a JSON type raise `TypeError`.
+//!
+//! The classes are in the `no_proto` extension module, build a `cdylib` crate that depends on NoProto with the `pyo3` feature and re-exports [`no_proto_module`] with `maturin`.  To put the classes in your own extension module instead, call [`add_classes`] from it's `#[pymodule]` function.
+//!
+//! ```python
+//! import no_proto
+//!
+//! factory = no_proto.Factory('{"type": "table", "columns": [["name", {"type": "string"}], ["tags", {"type": "list", "of": {"type": "string"}}]]}')
+//!
+//! buffer = factory.empty_buffer()
+//! buffer.set("name", "Jeb")
+//! buffer.set("tags", ["pilot", "brave"])
+//! assert buffer.get("tags.1") == "brave"
+//!
+//! opened = factory.open_buffer(buffer.read_bytes())
+//! assert opened.get("") == {"name": "Jeb", "tags": ["pilot", "brave"]}
+//! assert opened.to_json() == '{"name":"Jeb","tags":["pilot","brave"]}'
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::json_flex::{json_decode_value, JSMAP, NP_JSON};
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+
+impl From<NP_Error> for PyErr {
+    fn from(error: NP_Error) -> PyErr {
+        PyValueError::new_err(error.to_string())
+    }
+}
+
+/// Split a dotted path into the steps `NP_Buffer` takes
+fn python_path(path: &str) -> Vec<&str> {
+    if path.is_empty() {
+        Vec::new()
+    } else {
+        path.split('.').collect()
+    }
+}
+
+/// JSON value of a Python object
+fn python_to_json(value: &Bound<'_, PyAny>) -> PyResult<NP_JSON> {
+    if value.is_none() {
+        return Ok(NP_JSON::Null);
+    }
+    // bool is a subclass of int in Python, so it's checked first
+    if let Ok(boolean) = value.cast::<PyBool>() {
+        return Ok(if boolean.is_true() { NP_JSON::True } else { NP_JSON::False });
+    }
+    if value.is_instance_of::<PyInt>() {
+        return Ok(NP_JSON::Integer(value.extract::<i64>()?));
+    }
+    if let Ok(float) = value.cast::<PyFloat>() {
+        return Ok(NP_JSON::Float(float.value()));
+    }
+    if value.is_instance_of::<PyString>() {
+        return Ok(NP_JSON::String(value.extract::<String>()?));
+    }
+    if let Ok(dict) = value.cast::<PyDict>() {
+        let mut map = JSMAP::new();
+        for (key, item) in dict.iter() {
+            let key = key.extract::<String>().map_err(|_| PyTypeError::new_err("Dictionary keys must be strings!"))?;
+            map.insert(key, python_to_json(&item)?);
+        }
+        return Ok(NP_JSON::Dictionary(map));
+    }
+    if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+        let mut list = Vec::new();
+        for item in value.try_iter()? {
+            list.push(python_to_json(&item?)?);
+        }
+        return Ok(NP_JSON::Array(list));
+    }
+    Err(PyTypeError::new_err(alloc::format!("Python {} can't be set in a buffer!", value.get_type().name()?)))
+}
+
+/// Python object of a JSON value
+fn json_to_python(py: Python<'_>, value: &NP_JSON) -> PyResult<Py<PyAny>> {
+    Ok(match value {
+        NP_JSON::String(string) => string.into_pyobject(py)?.into_any().unbind(),
+        NP_JSON::Integer(integer) => integer.into_pyobject(py)?.into_any().unbind(),
+        NP_JSON::Float(float) => float.into_pyobject(py)?.into_any().unbind(),
+        NP_JSON::Dictionary(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map.values.iter() {
+                dict.set_item(key, json_to_python(py, item)?)?;
+            }
+            dict.into_any().unbind()
+        },
+        NP_JSON::Array(list) => {
+            let items = list.iter().map(|item| json_to_python(py, item)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any().unbind()
+        },
+        NP_JSON::Null => py.None(),
+        NP_JSON::False => PyBool::new(py, false).to_owned().into_any().unbind(),
+        NP_JSON::True => PyBool::new(py, true).to_owned().into_any().unbind()
+    })
+}
+
+/// A factory that hands out owned buffers, `Factory` in Python
+///
+/// Check out documentation [here](../python/index.html).
+///
+#[pyclass(name = "Factory", module = "no_proto", frozen, skip_from_py_object)]
+#[derive(Debug, Clone)]
+pub struct NP_Py_Factory {
+    factory: NP_Factory
+}
+
+#[pymethods]
+impl NP_Py_Factory {
+
+    /// Create a factory from a JSON schema
+    #[new]
+    pub fn new(json_schema: &str) -> Result<NP_Py_Factory, NP_Error> {
+        Ok(NP_Py_Factory { factory: NP_Factory::new(json_schema)? })
+    }
+
+    /// Create a factory from a compiled schema
+    #[staticmethod]
+    pub fn new_compiled(schema_bytes: Vec<u8>) -> NP_Py_Factory {
+        NP_Py_Factory { factory: NP_Factory::new_compiled(schema_bytes) }
+    }
+
+    /// Get the schema as JSON text
+    pub fn export_schema(&self) -> Result<String, NP_Error> {
+        Ok(self.factory.export_schema()?.stringify())
+    }
+
+    /// Get the compiled schema bytes
+    pub fn compile_schema(&self) -> Vec<u8> {
+        self.factory.compile_schema()
+    }
+
+    /// Create a new empty buffer
+    pub fn empty_buffer(&self) -> NP_Py_Buffer {
+        NP_Py_Buffer { factory: self.factory.clone(), bytes: self.factory.empty_buffer(None).close() }
+    }
+
+    /// Open the bytes of a closed buffer
+    pub fn open_buffer(&self, bytes: Vec<u8>) -> NP_Py_Buffer {
+        NP_Py_Buffer { factory: self.factory.clone(), bytes }
+    }
+
+    /// Create a new buffer from JSON text of the whole document, values that don't match the schema fail.
+    pub fn from_json(&self, json: &str) -> Result<NP_Py_Buffer, NP_Error> {
+        let value = json_decode_value(json)?;
+        let mut buffer = self.empty_buffer();
+        buffer.with_buffer(|buffer| buffer.set_json(&[], &value))?;
+        Ok(buffer)
+    }
+}
+
+/// A buffer that owns it's bytes and a handle to it's factory, `Buffer` in Python
+///
+/// Check out documentation [here](../python/index.html).
+///
+#[pyclass(name = "Buffer", module = "no_proto", skip_from_py_object)]
+#[derive(Debug, Clone)]
+pub struct NP_Py_Buffer {
+    factory: NP_Factory,
+    bytes: Vec<u8>
+}
+
+impl NP_Py_Buffer {
+
+    /// Open the bytes as an `NP_Buffer` for one call and keep the bytes it leaves behind, even if the call fails
+    fn with_buffer<F, R>(&mut self, callback: F) -> R where F: FnOnce(&mut NP_Buffer) -> R {
+        let mut buffer = self.factory.open_buffer(core::mem::take(&mut self.bytes));
+        let result = callback(&mut buffer);
+        self.bytes = buffer.close();
+        result
+    }
+
+    fn get_json(&self, path: &str) -> Result<NP_JSON, NP_Error> {
+        self.factory.open_buffer_read_only(&self.bytes).json_encode(&python_path(path))
+    }
+}
+
+#[pymethods]
+impl NP_Py_Buffer {
+
+    /// Set the value at a dotted path from a Python object, values that don't match the schema fail.
+    ///
+    /// Returns `False` if the path isn't in the schema.
+    ///
+    pub fn set(&mut self, path: &str, value: &Bound<'_, PyAny>) -> PyResult<bool> {
+        let value = python_to_json(value)?;
+        Ok(self.with_buffer(|buffer| buffer.set_json(&python_path(path), &value))?)
+    }
+
+    /// Get the value at a dotted path as a Python object, `None` if it hasn't been set
+    pub fn get(&self, py: Python<'_>, path: &str) -> PyResult<Py<PyAny>> {
+        json_to_python(py, &self.get_json(path)?)
+    }
+
+    /// Delete the value at a dotted path, returns `False` if there wasn't one
+    pub fn delete(&mut self, path: &str) -> Result<bool, NP_Error> {
+        self.with_buffer(|buffer| buffer.del(&python_path(path)))
+    }
+
+    /// Get the whole buffer as JSON text
+    pub fn to_json(&self) -> Result<String, NP_Error> {
+        Ok(self.get_json("")?.stringify())
+    }
+
+    /// Compact the buffer to drop old values
+    pub fn compact(&mut self) -> Result<(), NP_Error> {
+        self.with_buffer(|buffer| buffer.compact(None))
+    }
+
+    /// Copy of the buffer bytes
+    pub fn read_bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+}
+
+/// Add the `Factory` and `Buffer` classes to a Python module
+pub fn add_classes(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<NP_Py_Factory>()?;
+    module.add_class::<NP_Py_Buffer>()?;
+    Ok(())
+}
+
+/// The `no_proto` Python extension module
+#[pymodule]
+#[pyo3(name = "no_proto")]
+pub fn no_proto_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    add_classes(module)
+}
+
+#[test]
+fn python_buffers_work() -> Result<(), NP_Error> {
+    Python::initialize();
+    Python::attach(|py| -> PyResult<()> {
+        let module = PyModule::new(py, "no_proto")?;
+        no_proto_module(&module)?;
+        let locals = PyDict::new(py);
+        locals.set_item("no_proto", module)?;
+
+        py.run(pyo3::ffi::c_str!(r#"
+factory = no_proto.Factory('{"type": "table", "columns": [["id", {"type": "u32"}], ["ok", {"type": "bool"}], ["score", {"type": "map", "value": {"type": "float"}}], ["tags", {"type": "list", "of": {"type": "string"}}]]}')
+compiled = no_proto.Factory.new_compiled(factory.compile_schema())
+assert compiled.export_schema() == factory.export_schema()
+
+buffer = factory.empty_buffer()
+assert buffer.get("") is None
+assert buffer.set("id", 20)
+assert buffer.set("ok", True)
+assert buffer.set("score.math", 9.5)
+assert buffer.set("tags", ("pilot", "brave"))
+assert not buffer.set("missing", 1)
+
+# bad values fail without losing the buffer
+for bad in ["twenty", -1, object(), {1: 2}]:
+    try:
+        buffer.set("id", bad)
+        assert False
+    except (ValueError, TypeError):
+        pass
+assert buffer.get("id") == 20
+
+assert buffer.get("ok") is True
+assert buffer.get("score") == {"math": 9.5}
+assert buffer.get("tags.1") == "brave"
+assert buffer.delete("tags")
+buffer.compact()
+
+opened = compiled.open_buffer(buffer.read_bytes())
+assert opened.get("") == {"id": 20, "ok": True, "score": {"math": 9.5}, "tags": None}
+assert opened.to_json() == '{"id":20,"ok":true,"score":{"math":9.5},"tags":null}'
+
+imported = factory.from_json(opened.to_json())
+assert imported.read_bytes() == factory.from_json('{"id": 20, "ok": true, "score": {"math": 9.5}}').read_bytes()
+try:
+    factory.from_json('{"id": -1}')
+    assert False
+except ValueError:
+    pass
+"#), None, Some(&locals))
+    }).map_err(|error| NP_Error::Other(error.to_string()))
+}
@@ -0,0 +1,74 @@
+//! Map plain Rust structs to buffers.
+//!
+//! Types that implement `NP_Record` know their own schema and how to copy themselves into and out of a buffer.  You can implement the trait by hand, or enable the `derive` feature and use `#[derive(NP_Record)]` to generate it for structs with named fields.  [Derive docs are here.](https://docs.rs/no_proto_derive)
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::buffer::NP_Buffer;
+//! use no_proto::record::NP_Record;
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct User {
+//!     name: String,
+//!     age: u16
+//! }
+//!
+//! impl NP_Record for User {
+//!     fn schema() -> &'static str {
+//!         r#"{"type":"table","columns":[["name",{"type":"string"}],["age",{"type":"u16"}]]}"#
+//!     }
+//!
+//!     fn fill_buffer(&self, buffer: &mut NP_Buffer) -> Result<(), NP_Error> {
+//!         buffer.set(&["name"], self.name.as_str())?;
+//!         buffer.set(&["age"], self.age)?;
+//!         Ok(())
+//!     }
+//!
+//!     fn from_buffer(buffer: &NP_Buffer) -> Result<Self, NP_Error> {
+//!         Ok(User {
+//!             name: buffer.get::<&str>(&["name"])?.unwrap_or_default().to_string(),
+//!             age: buffer.get::<u16>(&["age"])?.unwrap_or_default()
+//!         })
+//!     }
+//! }
+//!
+//! let factory = NP_Factory::new(User::schema())?;
+//!
+//! let user = User { name: String::from("Billy Joel"), age: 71 };
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! user.fill_buffer(&mut new_buffer)?;
+//!
+//! assert_eq!(User::from_buffer(&new_buffer)?, user);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+
+/// Types that can be copied into and out of buffers.
+///
+/// Check out documentation [here](../record/index.html).
+///
+pub trait NP_Record: Sized {
+    /// The JSON schema used by buffers holding this type.
+    ///
+    fn schema() -> &'static str;
+
+    /// Set every value of this type into the provided buffer.
+    ///
+    fn fill_buffer(&self, buffer: &mut NP_Buffer) -> Result<(), NP_Error>;
+
+    /// Read a new copy of this type out of the provided buffer.
+    ///
+    fn from_buffer(buffer: &NP_Buffer) -> Result<Self, NP_Error>;
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+}
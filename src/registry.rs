@@ -0,0 +1,272 @@
+//! Share factories by schema fingerprint
+//!
+//! Parsing a schema is the slowest part of creating a factory.  Servers that see the same schema on every connection can keep the factories in an `NP_Factory_Registry` and parse each schema once, factories are cheap to clone since clones share the parsed schema.
+//!
+//! Factories are keyed by the fingerprint of their compiled schema, the same fingerprint `with_fingerprint` stamps into buffer headers, so stamped buffers can find the factory that made them.  The registry is locked with a `spin` mutex, so it's only available on targets with atomic pointers.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::registry::NP_Factory_Registry;
+//!
+//! let schema = r#"{"type": "table", "columns": [["name", {"type": "string"}]]}"#;
+//!
+//! let registry = NP_Factory_Registry::global();
+//! let factory = registry.register(NP_Factory::new(schema)?.with_fingerprint());
+//! let fingerprint = factory.schema.fingerprint();
+//!
+//! // later, on another connection or thread
+//! let factory = registry.get_or_insert_with(fingerprint, || NP_Factory::new(schema))?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//! let bytes = new_buffer.close();
+//!
+//! // stamped buffers find their own factory
+//! let factory = registry.factory_for(&bytes).unwrap();
+//! let buffer = factory.open_buffer_checked(bytes)?;
+//! assert_eq!(buffer.get::<&str>(&["name"])?, Some("Jeb"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::error::NP_Error;
+use crate::memory::NP_HEADER_FINGERPRINT;
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+use spin::{Mutex, MutexGuard};
+
+static GLOBAL_REGISTRY: NP_Factory_Registry = NP_Factory_Registry::new();
+
+/// Factories keyed by schema fingerprint
+///
+/// The first factory registered for a fingerprint is kept, factories with the same schema but different settings like `with_version` aren't told apart.
+///
+/// Check out documentation [here](../registry/index.html).
+///
+pub struct NP_Factory_Registry {
+    factories: Mutex<Vec<NP_Factory>>
+}
+
+impl Debug for NP_Factory_Registry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("NP_Factory_Registry").field("factories", &self.len()).finish()
+    }
+}
+
+impl Default for NP_Factory_Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NP_Factory_Registry {
+
+    /// Create an empty registry
+    pub const fn new() -> Self {
+        NP_Factory_Registry { factories: Mutex::new(Vec::new()) }
+    }
+
+    /// The registry shared by the whole program
+    pub fn global() -> &'static NP_Factory_Registry {
+        &GLOBAL_REGISTRY
+    }
+
+    /// Lock the registry, waiting for other threads to let go of it
+    fn lock(&self) -> MutexGuard<'_, Vec<NP_Factory>> {
+        // lookups only clone a factory, so the lock is never held for long
+        self.factories.lock()
+    }
+
+    /// Add a factory to the registry and get a clone of the registered one back.  If there's already a factory for the same schema fingerprint, that factory is returned instead.
+    pub fn register(&self, factory: NP_Factory) -> NP_Factory {
+        let fingerprint = factory.schema.fingerprint();
+        let mut factories = self.lock();
+        match factories.iter().find(|x| x.schema.fingerprint() == fingerprint) {
+            Some(existing) => existing.clone(),
+            None => {
+                let result = factory.clone();
+                factories.push(factory);
+                result
+            }
+        }
+    }
+
+    /// Get the factory for a schema fingerprint
+    pub fn get(&self, fingerprint: [u8; 8]) -> Option<NP_Factory> {
+        self.lock().iter().find(|x| x.schema.fingerprint() == fingerprint).cloned()
+    }
+
+    /// Get the factory for a schema fingerprint, creating and registering it with `create` if there isn't one yet
+    ///
+    /// `create` runs without holding the lock, if two threads create the same factory at once the first one registered wins.
+    ///
+    pub fn get_or_insert_with<F>(&self, fingerprint: [u8; 8], create: F) -> Result<NP_Factory, NP_Error> where F: FnOnce() -> Result<NP_Factory, NP_Error> {
+        if let Some(factory) = self.get(fingerprint) {
+            return Ok(factory);
+        }
+        let factory = create()?;
+        if factory.schema.fingerprint() != fingerprint {
+            return Err(NP_Error::SchemaMismatch("Created factory doesn't have the requested schema fingerprint!".into()));
+        }
+        Ok(self.register(factory))
+    }
+
+    /// Get the factory for the fingerprint stamped in the header of buffer bytes, see `NP_Factory::with_fingerprint`
+    pub fn factory_for(&self, bytes: &[u8]) -> Option<NP_Factory> {
+        match bytes.first() {
+            Some(flags) if flags & NP_HEADER_FINGERPRINT != 0 && bytes.len() >= 9 => {
+                let mut fingerprint = [0u8; 8];
+                fingerprint.copy_from_slice(&bytes[1..9]);
+                self.get(fingerprint)
+            },
+            _ => None
+        }
+    }
+
+    /// Take the factory for a schema fingerprint out of the registry
+    pub fn remove(&self, fingerprint: [u8; 8]) -> Option<NP_Factory> {
+        let mut factories = self.lock();
+        let index = factories.iter().position(|x| x.schema.fingerprint() == fingerprint)?;
+        Some(factories.remove(index))
+    }
+
+    /// How many factories are in the registry
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Is the registry empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[test]
+fn registry_works() -> Result<(), NP_Error> {
+
+    fn is_send_sync<T: Send + Sync>(_: &T) -> bool { true }
+
+    let schema = r#"{"type": "table", "columns": [["name", {"type": "string"}], ["age", {"type": "u8"}]]}"#;
+    let other = r#"{"type": "list", "of": {"type": "string"}}"#;
+
+    // clones share the schema
+    let factory = NP_Factory::new(schema)?;
+    let clone = factory.clone();
+    assert!(is_send_sync(&factory));
+    assert!(core::ptr::eq(&*factory.schema, &*clone.schema));
+    assert_eq!(clone.compile_schema(), factory.compile_schema());
+
+    let registry = NP_Factory_Registry::new();
+    assert!(registry.is_empty());
+    let fingerprint = factory.schema.fingerprint();
+
+    // the first factory for a fingerprint is kept
+    let first = registry.register(factory);
+    let second = registry.register(NP_Factory::new(schema)?);
+    assert_eq!(registry.len(), 1);
+    assert!(core::ptr::eq(&*first.schema, &*second.schema));
+
+    // existing factories aren't created again
+    let found = registry.get_or_insert_with(fingerprint, || panic!("schema parsed twice"))?;
+    assert!(core::ptr::eq(&*first.schema, &*found.schema));
+    assert!(registry.get([0; 8]).is_none());
+
+    // created factories need the requested fingerprint
+    assert!(registry.get_or_insert_with([0; 8], || NP_Factory::new(other)).is_err());
+    let list = registry.get_or_insert_with(NP_Factory::new(other)?.schema.fingerprint(), || NP_Factory::new(other))?;
+    assert_eq!(registry.len(), 2);
+
+    // buffers without a fingerprint can't be looked up
+    assert!(registry.factory_for(&list.empty_buffer(None).close()).is_none());
+    let stamped = NP_Factory::new(other)?.with_fingerprint();
+    let found = registry.factory_for(&stamped.empty_buffer(None).close()).unwrap();
+    assert!(core::ptr::eq(&*list.schema, &*found.schema));
+
+    assert!(registry.remove(fingerprint).is_some());
+    assert!(registry.get(fingerprint).is_none());
+    assert_eq!(registry.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn registry_lock_works() -> Result<(), NP_Error> {
+    let schema = r#"{"type": "list", "of": {"type": "u8"}}"#;
+    let registry = NP_Factory_Registry::new();
+
+    // dropping the guard unlocks the registry
+    {
+        let factories = registry.lock();
+        assert!(factories.is_empty());
+        assert!(registry.factories.is_locked());
+    }
+    assert!(!registry.factories.is_locked());
+    registry.register(NP_Factory::new(schema)?);
+    assert_eq!(registry.len(), 1);
+
+    // threads registering the same schema at once share one factory
+    #[cfg(feature = "std")]
+    {
+        let registry = std::sync::Arc::new(NP_Factory_Registry::new());
+        let threads: Vec<std::thread::JoinHandle<NP_Factory>> = (0..8).map(|_| {
+            let registry = std::sync::Arc::clone(&registry);
+            std::thread::spawn(move || registry.register(NP_Factory::new(schema).unwrap()))
+        }).collect();
+        let factories: Vec<NP_Factory> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+        assert_eq!(registry.len(), 1);
+        assert!(factories.iter().all(|x| core::ptr::eq(&*x.schema, &*factories[0].schema)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn registry_threads_work() -> Result<(), NP_Error> {
+    extern crate std;
+    use alloc::format;
+    use alloc::sync::Arc;
+
+    let schemas: Vec<alloc::string::String> = (0..8).map(|x| format!(r#"{{"type": "table", "columns": [["column_{}", {{"type": "u8"}}]]}}"#, x)).collect();
+    let fingerprints: Vec<[u8; 8]> = schemas.iter().map(|x| NP_Factory::new(x).map(|f| f.schema.fingerprint())).collect::<Result<_, _>>()?;
+    let registry = Arc::new(NP_Factory_Registry::new());
+
+    // every thread registers it's own schema and looks up the others while they're being added
+    let threads: Vec<std::thread::JoinHandle<Result<usize, NP_Error>>> = (0..schemas.len()).map(|x| {
+        let registry = Arc::clone(&registry);
+        let schemas = schemas.clone();
+        let fingerprints = fingerprints.clone();
+        std::thread::spawn(move || {
+            let mut found = 0;
+            for round in 0..100 {
+                let mine = (x + round) % schemas.len();
+                let factory = registry.get_or_insert_with(fingerprints[mine], || NP_Factory::new(&schemas[mine]))?;
+                assert_eq!(factory.schema.fingerprint(), fingerprints[mine]);
+                for (index, fingerprint) in fingerprints.iter().enumerate() {
+                    if let Some(factory) = registry.get(*fingerprint) {
+                        assert_eq!(factory.export_schema()?.stringify(), NP_Factory::new(&schemas[index])?.export_schema()?.stringify());
+                        found += 1;
+                    }
+                }
+            }
+            Ok(found)
+        })
+    }).collect();
+
+    for thread in threads {
+        let found = thread.join().map_err(|_| NP_Error::Other("registry thread panicked".into()))??;
+        assert!(found >= 100);
+    }
+
+    // each schema was registered once and every lookup gets the same factory
+    assert_eq!(registry.len(), schemas.len());
+    for fingerprint in fingerprints.iter() {
+        let first = registry.get(*fingerprint).ok_or_else(|| NP_Error::Other("missing factory".into()))?;
+        let second = registry.get(*fingerprint).ok_or_else(|| NP_Error::Other("missing factory".into()))?;
+        assert!(core::ptr::eq(&*first.schema, &*second.schema));
+    }
+
+    Ok(())
+}
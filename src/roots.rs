@@ -0,0 +1,161 @@
+//! Several named root types in one schema
+//!
+//! Related messages like the requests and events of one service can share a single schema document instead of needing a factory each.  A schema with a `roots` object instead of a `type` defines one root type per key, `NP_Factory::empty_buffer_for` and `NP_Factory::open_buffer_for` make and open buffers of a root by it's name.
+//!
+//! The first root is the default schema of the factory, it's used by `empty_buffer`, `open_buffer` and every other method that doesn't take a root name.  Compiled schemas from `compile_schema` and JSON from `export_schema` hold every root, the schema IDL can't express named roots.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "roots": {
+//!         "User":  {"type": "table", "columns": [["name", {"type": "string"}]]},
+//!         "Event": {"type": "table", "columns": [["kind", {"type": "string"}], ["at", {"type": "u64"}]]}
+//!     }
+//! }"#)?;
+//!
+//! assert_eq!(factory.root_names(), vec!["User", "Event"]);
+//!
+//! let mut event = factory.empty_buffer_for("Event")?;
+//! event.set(&["kind"], "login")?;
+//! event.set(&["at"], 1600000000u64)?;
+//! let bytes = event.close();
+//!
+//! let event = factory.open_buffer_for("Event", bytes)?;
+//! assert_eq!(event.get::<&str>(&["kind"])?, Some("login"));
+//!
+//! // the compiled schema holds every root
+//! let compiled = NP_Factory::new_compiled(factory.compile_schema());
+//! let mut user = compiled.empty_buffer_for("User")?;
+//! user.set(&["name"], "Jeb")?;
+//! assert!(compiled.empty_buffer_for("Account").is_err());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::json_flex::{JSMAP, NP_JSON};
+use crate::schema::{NP_Schema, NP_MAX_DEPTH};
+use crate::validation::NP_Constraints;
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// First byte of compiled schemas with named roots, type keys stop at 24
+pub(crate) const NP_ROOTS_MARKER: u8 = 255;
+
+/// Named root schemas and the compiled bytes holding all of them
+pub(crate) type NP_Roots = (Vec<(String, Arc<NP_Schema>)>, Vec<u8>);
+
+/// Parse the `roots` object of a schema
+pub(crate) fn from_json(json: &NP_JSON) -> Result<NP_Roots, NP_Error> {
+
+    let entries = match json {
+        NP_JSON::Dictionary(map) if !map.values.is_empty() => &map.values,
+        _ => return Err(NP_Error::Schema("Schema roots must be an object with at least one schema!".into()))
+    };
+
+    if entries.len() > 255 {
+        return Err(NP_Error::Schema("Schemas can't have more than 255 roots!".into()));
+    }
+
+    let mut roots = Vec::new();
+    let mut compiled = alloc::vec![NP_ROOTS_MARKER, entries.len() as u8];
+
+    for (name, root_json) in entries {
+        if name.is_empty() || name.len() > 255 {
+            return Err(NP_Error::Schema("Root names must be 1 to 255 bytes!".into()));
+        }
+        let root_json = Box::new(root_json.clone());
+        let (is_sortable, schema_bytes, parsed) = NP_Schema::from_json(Vec::new(), &root_json)?;
+        let constraints = NP_Constraints::from_json(&parsed, &root_json);
+        let schema = NP_Schema { is_sortable, fingerprint: NP_Schema::fingerprint_bytes(&schema_bytes), parsed, constraints };
+        if schema.depth() > NP_MAX_DEPTH {
+            return Err(NP_Error::Schema("Root schema is deeper than the max depth: ".to_owned() + name));
+        }
+        compiled.push(name.len() as u8);
+        compiled.extend_from_slice(name.as_bytes());
+        compiled.extend_from_slice(&(schema_bytes.len() as u32).to_be_bytes());
+        compiled.extend_from_slice(&schema_bytes);
+        roots.push((name.clone(), Arc::new(schema)));
+    }
+
+    Ok((roots, compiled))
+}
+
+/// Read the roots of a compiled schema that starts with `NP_ROOTS_MARKER`
+pub(crate) fn from_bytes(bytes: &[u8]) -> Vec<(String, Arc<NP_Schema>)> {
+
+    let mut roots = Vec::new();
+    let mut offset = 2;
+
+    for _x in 0..bytes[1] {
+        let name_len = bytes[offset] as usize;
+        let name = String::from_utf8_lossy(&bytes[(offset + 1)..(offset + 1 + name_len)]).into_owned();
+        offset += 1 + name_len;
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&bytes[offset..(offset + 4)]);
+        let len = u32::from_be_bytes(len) as usize;
+        let schema_bytes = bytes[(offset + 4)..(offset + 4 + len)].to_vec();
+        offset += 4 + len;
+        let (is_sortable, parsed) = NP_Schema::from_bytes(Vec::new(), 0, &schema_bytes);
+        roots.push((name, Arc::new(NP_Schema { is_sortable, fingerprint: NP_Schema::fingerprint_bytes(&schema_bytes), parsed, constraints: Vec::new() })));
+    }
+
+    roots
+}
+
+/// Export every root as a `roots` schema
+pub(crate) fn to_json(roots: &[(String, Arc<NP_Schema>)]) -> Result<NP_JSON, NP_Error> {
+    let mut json = JSMAP::new();
+    for (name, schema) in roots {
+        json.insert(name.clone(), schema.to_json()?);
+    }
+    let mut schema = JSMAP::new();
+    schema.insert("roots".to_owned(), NP_JSON::Dictionary(json));
+    Ok(NP_JSON::Dictionary(schema))
+}
+
+#[test]
+fn named_roots_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let schema = r#"{"roots": {"a": {"type": "u8", "default": 3}, "b": {"type": "list", "of": {"type": "string", "max_length": 2}}}}"#;
+    let factory = NP_Factory::new(schema)?.with_fingerprint();
+
+    // the first root is the default schema
+    assert_eq!(factory.empty_buffer(None).get::<u8>(&[])?, Some(3));
+    assert_eq!(factory.schema.fingerprint(), factory.root_schema("a").unwrap().fingerprint());
+
+    // buffers are stamped with the fingerprint of their root and checked against it
+    let mut buffer = factory.empty_buffer_for("b")?;
+    buffer.list_push(&[], "hey")?;
+    assert!(buffer.validate().is_err());
+    let bytes = buffer.close();
+    assert!(factory.open_buffer_checked(bytes.clone()).is_err());
+    assert_eq!(factory.open_buffer_for("b", bytes.clone())?.get::<&str>(&["0"])?, Some("hey"));
+    assert!(factory.open_buffer_for("a", bytes).is_err());
+
+    // roots survive exporting and compiling
+    let exported = NP_Factory::new(&factory.export_schema()?.stringify())?;
+    assert_eq!(exported.compile_schema(), factory.compile_schema());
+    let compiled = NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(compiled.root_names(), alloc::vec!["a", "b"]);
+    assert_eq!(compiled.compile_schema(), factory.compile_schema());
+    assert_eq!(compiled.root_schema("b").unwrap().fingerprint(), factory.root_schema("b").unwrap().fingerprint());
+    assert!(factory.export_idl().is_err());
+
+    // plain schemas don't have named roots
+    let plain = NP_Factory::new(r#"{"type": "string"}"#)?;
+    assert!(plain.root_names().is_empty());
+    assert!(plain.empty_buffer_for("a").is_err());
+
+    assert!(NP_Factory::new(r#"{"roots": {}}"#).is_err());
+    assert!(NP_Factory::new(r#"{"roots": {"a": {"type": "nope"}}}"#).is_err());
+
+    Ok(())
+}
@@ -1,67 +1,490 @@
 //! Remote Procedure Call APIs
-//! 
+//!
+//! RPC schemas declare the messages of an API and the services that use them.  Every method of a service has a request message, a response message and optionally an error message.  An `NP_RPC_Factory` made from the schema creates and opens requests and responses, each one is a normal `NP_Buffer` of the message schema framed with the method it belongs to.
+//!
+//! ```json
+//! {
+//!     "messages": [
+//!         ["user",  {"type": "table", "columns": [["name", {"type": "string"}], ["age", {"type": "u8"}]]}],
+//!         ["id",    {"type": "u32"}],
+//!         ["error", {"type": "string"}]
+//!     ],
+//!     "services": [
+//!         ["users", [
+//!             ["get", {"request": "id", "response": "user", "error": "error"}],
+//!             ["add", {"request": "user", "response": "id"}]
+//!         ]]
+//!     ]
+//! }
+//! ```
+//!
+//! Methods are named `service.method` and numbered in the order they're declared, the frames only hold the method number.  Adding new methods to the end of the last service keeps every existing method number.
+//!
+//! Frames are one byte for the frame kind (request, response or error), two bytes for the method number and then the buffer bytes.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::rpc::{NP_RPC_Factory, NP_RPC_Status};
+//!
+//! let rpc = NP_RPC_Factory::new(r#"{
+//!     "messages": [
+//!         ["user",  {"type": "table", "columns": [["name", {"type": "string"}], ["age", {"type": "u8"}]]}],
+//!         ["id",    {"type": "u32"}],
+//!         ["error", {"type": "string"}]
+//!     ],
+//!     "services": [
+//!         ["users", [
+//!             ["get", {"request": "id", "response": "user", "error": "error"}],
+//!             ["add", {"request": "user", "response": "id"}]
+//!         ]]
+//!     ]
+//! }"#)?;
+//!
+//! // client
+//! let mut request = rpc.new_request("users.get")?;
+//! request.data.set(&[], 20u32)?;
+//! let request_bytes = request.close();
+//!
+//! // server
+//! let request = rpc.open_request(request_bytes)?;
+//! assert_eq!(request.name, "users.get");
+//! assert_eq!(request.data.get::<u32>(&[])?, Some(20));
+//!
+//! let mut response = rpc.new_response(request.name)?;
+//! response.data.set(&["name"], "Jeb")?;
+//! let response_bytes = response.close();
+//!
+//! // client
+//! let response = rpc.open_response(response_bytes)?;
+//! assert_eq!(response.status, NP_RPC_Status::Ok);
+//! assert_eq!(response.data.get::<&str>(&["name"])?, Some("Jeb"));
+//!
+//! // errors use the error message of the method
+//! let mut error = rpc.new_error("users.get")?;
+//! error.data.set(&[], "user not found")?;
+//! let error = rpc.open_response(error.close())?;
+//! assert_eq!(error.status, NP_RPC_Status::Error);
+//! assert_eq!(error.data.get::<&str>(&[])?, Some("user not found"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
 
 use alloc::vec::Vec;
-use crate::{NP_JSON, buffer::NP_Buffer, error::NP_Error};
+use alloc::string::String;
+use alloc::borrow::ToOwned;
+use crate::{NP_JSON, NP_Factory, buffer::NP_Buffer, error::NP_Error, json_flex::{json_decode, JSMAP}};
 
+/// Frame kind byte of requests
+const RPC_REQUEST: u8 = 0;
+/// Frame kind byte of successful responses
+const RPC_RESPONSE: u8 = 1;
+/// Frame kind byte of error responses
+const RPC_ERROR: u8 = 2;
+/// Frame kind byte plus method number
+const RPC_FRAME_SIZE: usize = 3;
+/// Error message index of methods without an error message in compiled schemas
+const RPC_NO_ERROR: u16 = u16::MAX;
+
+/// A method of an RPC service
+#[derive(Debug)]
+struct NP_RPC_Method {
+    service: String,
+    method: String,
+    /// `service.method`
+    name: String,
+    request: usize,
+    response: usize,
+    error: Option<usize>
+}
+
+/// Factory for the requests and responses of an RPC schema
+///
+/// Check out documentation [here](../rpc/index.html).
+///
+#[derive(Debug)]
 pub struct NP_RPC_Factory {
+    messages: Vec<(String, NP_Factory)>,
+    methods: Vec<NP_RPC_Method>
+}
 
+/// Was a request successful?
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NP_RPC_Status {
+    /// The response holds the response message of the method
+    Ok,
+    /// The response holds the error message of the method
+    Error
 }
 
 impl NP_RPC_Factory {
 
-    pub fn new(json_rcp_schema: &str) -> Result<Self, NP_Error> {
-        todo!()
+    /// Generate a new RPC factory from a JSON RPC schema.
+    ///
+    /// Fails if the schema or any message schema is invalid, if a name is used twice or if a method uses a message that doesn't exist.
+    ///
+    pub fn new(json_rpc_schema: &str) -> Result<Self, NP_Error> {
+        let json = json_decode(json_rpc_schema.to_owned())?;
+
+        let mut messages: Vec<(String, NP_Factory)> = Vec::new();
+        for message in json["messages"].into_vec().ok_or_else(|| NP_Error::Schema("RPC schemas need a messages array!".into()))? {
+            let name = rpc_name(&message[0], "Message")?;
+            if !message[1].is_dictionary() {
+                return Err(NP_Error::Schema("RPC messages need a schema object!".into()));
+            }
+            if messages.iter().any(|(x, _)| *x == name) {
+                return Err(NP_Error::Schema("RPC message is declared twice: ".to_owned() + &name));
+            }
+            messages.push((name, NP_Factory::new(&message[1].stringify())?));
+        }
+
+        let mut methods: Vec<NP_RPC_Method> = Vec::new();
+        for service in json["services"].into_vec().ok_or_else(|| NP_Error::Schema("RPC schemas need a services array!".into()))? {
+            let service_name = rpc_name(&service[0], "Service")?;
+            if methods.iter().any(|x| x.service == service_name) {
+                return Err(NP_Error::Schema("RPC service is declared twice: ".to_owned() + &service_name));
+            }
+            for method in service[1].into_vec().ok_or_else(|| NP_Error::Schema("RPC services need a methods array!".into()))? {
+                let method_name = rpc_name(&method[0], "Method")?;
+                let name = service_name.clone() + "." + &method_name;
+                if methods.iter().any(|x| x.name == name) {
+                    return Err(NP_Error::Schema("RPC method is declared twice: ".to_owned() + &name));
+                }
+                let find = |key: &str| -> Result<Option<usize>, NP_Error> {
+                    match &method[1][key] {
+                        NP_JSON::Null => Ok(None),
+                        NP_JSON::String(x) => match messages.iter().position(|(message, _)| message == x) {
+                            Some(index) => Ok(Some(index)),
+                            None => Err(NP_Error::Schema("RPC method uses a message that isn't declared: ".to_owned() + x))
+                        },
+                        _ => Err(NP_Error::Schema("RPC methods name their messages with strings!".into()))
+                    }
+                };
+                let request = find("request")?.ok_or_else(|| NP_Error::Schema("RPC method doesn't have a request message: ".to_owned() + &name))?;
+                let response = find("response")?.ok_or_else(|| NP_Error::Schema("RPC method doesn't have a response message: ".to_owned() + &name))?;
+                let error = find("error")?;
+                methods.push(NP_RPC_Method { service: service_name.clone(), method: method_name, name, request, response, error });
+            }
+        }
+
+        if methods.len() >= RPC_NO_ERROR as usize || messages.len() >= RPC_NO_ERROR as usize {
+            return Err(NP_Error::Schema("RPC schemas can't have more than 65534 messages or methods!".into()));
+        }
+
+        Ok(NP_RPC_Factory { messages, methods })
     }
 
+    /// Create a new RPC factory from a compiled RPC schema, see `compile_schema`.
+    ///
     pub fn new_compiled(bytes_rpc_schema: Vec<u8>) -> Result<Self, NP_Error>  {
-        todo!()
+        let mut reader = RPC_Reader { bytes: &bytes_rpc_schema, pos: 0 };
+
+        let mut messages: Vec<(String, NP_Factory)> = Vec::new();
+        for _ in 0..reader.u16()? {
+            let name = reader.string()?;
+            let len = reader.u32()? as usize;
+            messages.push((name, NP_Factory::new_compiled(reader.bytes(len)?.to_vec())));
+        }
+
+        let mut methods: Vec<NP_RPC_Method> = Vec::new();
+        for _ in 0..reader.u16()? {
+            let service = reader.string()?;
+            let method = reader.string()?;
+            let (request, response, error) = (reader.u16()? as usize, reader.u16()? as usize, reader.u16()?);
+            if request >= messages.len() || response >= messages.len() || (error != RPC_NO_ERROR && error as usize >= messages.len()) {
+                return Err(NP_Error::Damaged("Compiled RPC schema uses a message that doesn't exist!".into()));
+            }
+            let name = service.clone() + "." + &method;
+            methods.push(NP_RPC_Method { service, method, name, request, response, error: if error == RPC_NO_ERROR { None } else { Some(error as usize) } });
+        }
+
+        Ok(NP_RPC_Factory { messages, methods })
     }
 
+    /// Get the compiled RPC schema, the compiled message schemas are stored inside it.
+    ///
     pub fn compile_schema(&self) -> Vec<u8> {
-        todo!()
+        let mut bytes: Vec<u8> = Vec::new();
+
+        bytes.extend_from_slice(&(self.messages.len() as u16).to_be_bytes());
+        for (name, factory) in self.messages.iter() {
+            rpc_write_string(name, &mut bytes);
+            let schema = factory.compile_schema();
+            bytes.extend_from_slice(&(schema.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&schema);
+        }
+
+        bytes.extend_from_slice(&(self.methods.len() as u16).to_be_bytes());
+        for method in self.methods.iter() {
+            rpc_write_string(&method.service, &mut bytes);
+            rpc_write_string(&method.method, &mut bytes);
+            bytes.extend_from_slice(&(method.request as u16).to_be_bytes());
+            bytes.extend_from_slice(&(method.response as u16).to_be_bytes());
+            bytes.extend_from_slice(&method.error.map(|x| x as u16).unwrap_or(RPC_NO_ERROR).to_be_bytes());
+        }
+
+        bytes
     }
 
+    /// Exports the RPC schema to JSON.  This works regardless of wether the factory was created with `new` or `new_compiled`.
+    ///
     pub fn export_schema(&self) -> Result<NP_JSON, NP_Error> {
-        todo!()
+        let mut messages: Vec<NP_JSON> = Vec::new();
+        for (name, factory) in self.messages.iter() {
+            messages.push(NP_JSON::Array(alloc::vec![NP_JSON::String(name.clone()), factory.export_schema()?]));
+        }
+
+        let mut services: Vec<NP_JSON> = Vec::new();
+        let mut service_methods: Vec<NP_JSON> = Vec::new();
+        for (index, method) in self.methods.iter().enumerate() {
+            let mut method_json = JSMAP::new();
+            method_json.insert("request".to_owned(), NP_JSON::String(self.messages[method.request].0.clone()));
+            method_json.insert("response".to_owned(), NP_JSON::String(self.messages[method.response].0.clone()));
+            if let Some(error) = method.error {
+                method_json.insert("error".to_owned(), NP_JSON::String(self.messages[error].0.clone()));
+            }
+            service_methods.push(NP_JSON::Array(alloc::vec![NP_JSON::String(method.method.clone()), NP_JSON::Dictionary(method_json)]));
+
+            // methods of a service are always next to each other
+            let last = self.methods.get(index + 1).map(|next| next.service != method.service).unwrap_or(true);
+            if last {
+                services.push(NP_JSON::Array(alloc::vec![NP_JSON::String(method.service.clone()), NP_JSON::Array(core::mem::take(&mut service_methods))]));
+            }
+        }
+
+        let mut schema = JSMAP::new();
+        schema.insert("messages".to_owned(), NP_JSON::Array(messages));
+        schema.insert("services".to_owned(), NP_JSON::Array(services));
+        Ok(NP_JSON::Dictionary(schema))
+    }
+
+    /// Get the factory of a message, to work with message buffers outside of requests and responses.
+    ///
+    pub fn message(&self, name: &str) -> Option<&NP_Factory> {
+        self.messages.iter().find(|(x, _)| x == name).map(|(_, factory)| factory)
+    }
+
+    /// Names of every method as `service.method`, in the order they're numbered
+    ///
+    pub fn methods(&self) -> Vec<&str> {
+        self.methods.iter().map(|method| method.name.as_str()).collect()
+    }
+
+    fn find_method(&self, name: &str) -> Result<(u16, &NP_RPC_Method), NP_Error> {
+        match self.methods.iter().position(|method| method.name == name) {
+            Some(index) => Ok((index as u16, &self.methods[index])),
+            None => Err(NP_Error::SchemaMismatch("RPC method isn't in the schema: ".to_owned() + name))
+        }
+    }
+
+    /// Split a frame into it's kind, method and buffer bytes
+    fn open_frame(&self, mut bytes: Vec<u8>) -> Result<(u8, u16, &NP_RPC_Method, Vec<u8>), NP_Error> {
+        if bytes.len() < RPC_FRAME_SIZE {
+            return Err(NP_Error::Damaged("RPC frame is too short!".into()));
+        }
+        let id = u16::from_be_bytes([bytes[1], bytes[2]]);
+        let method = match self.methods.get(id as usize) {
+            Some(method) => method,
+            None => return Err(NP_Error::SchemaMismatch("RPC frame is for a method that isn't in the schema!".into()))
+        };
+        let kind = bytes[0];
+        bytes.drain(0..RPC_FRAME_SIZE);
+        Ok((kind, id, method, bytes))
+    }
+
+    /// Create a new request for a method, named as `service.method`.
+    ///
+    pub fn new_request(&self, method: &str) -> Result<NP_RPC_Request<'_>, NP_Error> {
+        let (id, method) = self.find_method(method)?;
+        Ok(NP_RPC_Request { name: &method.name, id, data: self.messages[method.request].1.empty_buffer(None) })
     }
 
-    pub fn new_request(&self) -> NP_RCP_Request {
-        todo!()
+    /// Open a request that was closed with `NP_RPC_Request::close`.
+    ///
+    pub fn open_request(&self, bytes: Vec<u8>) -> Result<NP_RPC_Request<'_>, NP_Error> {
+        let (kind, id, method, bytes) = self.open_frame(bytes)?;
+        if kind != RPC_REQUEST {
+            return Err(NP_Error::SchemaMismatch("RPC frame isn't a request!".into()));
+        }
+        Ok(NP_RPC_Request { name: &method.name, id, data: self.messages[method.request].1.open_buffer(bytes) })
     }
 
-    pub fn open_request(&self, bytes: Vec<u8>) -> NP_RCP_Request {
-        todo!()
+    /// Create a new successful response for a method, named as `service.method`.
+    ///
+    pub fn new_response(&self, method: &str) -> Result<NP_RPC_Response<'_>, NP_Error> {
+        let (id, method) = self.find_method(method)?;
+        Ok(NP_RPC_Response { name: &method.name, id, status: NP_RPC_Status::Ok, data: self.messages[method.response].1.empty_buffer(None) })
     }
 
-    pub fn new_reponse(&self) -> NP_RCP_Response {
-        todo!()
+    /// Create a new error response for a method, named as `service.method`.  Fails if the method doesn't have an error message.
+    ///
+    pub fn new_error(&self, method: &str) -> Result<NP_RPC_Response<'_>, NP_Error> {
+        let (id, method) = self.find_method(method)?;
+        match method.error {
+            Some(error) => Ok(NP_RPC_Response { name: &method.name, id, status: NP_RPC_Status::Error, data: self.messages[error].1.empty_buffer(None) }),
+            None => Err(NP_Error::SchemaMismatch("RPC method doesn't have an error message: ".to_owned() + &method.name))
+        }
     }
 
-    pub fn open_response(&self, bytes: Vec<u8>) -> NP_RCP_Response {
-        todo!()
+    /// Open a response that was closed with `NP_RPC_Response::close`, the status says which message the buffer holds.
+    ///
+    pub fn open_response(&self, bytes: Vec<u8>) -> Result<NP_RPC_Response<'_>, NP_Error> {
+        let (kind, id, method, bytes) = self.open_frame(bytes)?;
+        let (status, message) = match (kind, method.error) {
+            (RPC_RESPONSE, _) => (NP_RPC_Status::Ok, method.response),
+            (RPC_ERROR, Some(error)) => (NP_RPC_Status::Error, error),
+            (RPC_ERROR, None) => return Err(NP_Error::SchemaMismatch("RPC frame is an error for a method without an error message!".into())),
+            _ => return Err(NP_Error::SchemaMismatch("RPC frame isn't a response!".into()))
+        };
+        Ok(NP_RPC_Response { name: &method.name, id, status, data: self.messages[message].1.open_buffer(bytes) })
     }
 }
 
-pub struct NP_RCP_Request<'response> {
-    pub name: &'response str,
-    pub data: NP_Buffer<'response>
+/// A request for an RPC method, the buffer uses the request message of the method
+///
+/// Check out documentation [here](../rpc/index.html).
+///
+pub struct NP_RPC_Request<'request> {
+    /// The method as `service.method`
+    pub name: &'request str,
+    id: u16,
+    /// The request message
+    pub data: NP_Buffer<'request>
 }
 
-impl<'response> NP_RCP_Request<'response> {
-    pub fn close() {
-        todo!()
+impl<'request> NP_RPC_Request<'request> {
+    /// Close the request and get the framed bytes
+    pub fn close(self) -> Vec<u8> {
+        rpc_frame(RPC_REQUEST, self.id, self.data)
     }
 }
 
-pub struct NP_RCP_Response<'response> {
+/// A response from an RPC method, the buffer uses the response or error message of the method depending on the status
+///
+/// Check out documentation [here](../rpc/index.html).
+///
+pub struct NP_RPC_Response<'response> {
+    /// The method as `service.method`
     pub name: &'response str,
+    id: u16,
+    /// Does the buffer hold the response or the error message?
+    pub status: NP_RPC_Status,
+    /// The response or error message
     pub data: NP_Buffer<'response>
 }
 
-impl<'response> NP_RCP_Response<'response> {
-    pub fn close() {
-        todo!()
+impl<'response> NP_RPC_Response<'response> {
+    /// Close the response and get the framed bytes
+    pub fn close(self) -> Vec<u8> {
+        let kind = match self.status {
+            NP_RPC_Status::Ok => RPC_RESPONSE,
+            NP_RPC_Status::Error => RPC_ERROR
+        };
+        rpc_frame(kind, self.id, self.data)
+    }
+}
+
+fn rpc_frame(kind: u8, id: u16, data: NP_Buffer) -> Vec<u8> {
+    let bytes = data.close();
+    let mut frame = Vec::with_capacity(RPC_FRAME_SIZE + bytes.len());
+    frame.push(kind);
+    frame.extend_from_slice(&id.to_be_bytes());
+    frame.extend_from_slice(&bytes);
+    frame
+}
+
+fn rpc_name(json: &NP_JSON, what: &str) -> Result<String, NP_Error> {
+    match json {
+        NP_JSON::String(x) if !x.is_empty() && x.len() <= 255 && !x.contains('.') => Ok(x.clone()),
+        _ => Err(NP_Error::Schema(what.to_owned() + " names must be strings of 1 to 255 bytes without dots!"))
     }
 }
 
+fn rpc_write_string(value: &str, bytes: &mut Vec<u8>) {
+    bytes.push(value.len() as u8);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+struct RPC_Reader<'bytes> {
+    bytes: &'bytes [u8],
+    pos: usize
+}
+
+impl<'bytes> RPC_Reader<'bytes> {
+    fn bytes(&mut self, len: usize) -> Result<&'bytes [u8], NP_Error> {
+        match self.bytes.get(self.pos..self.pos.saturating_add(len)) {
+            Some(x) => {
+                self.pos += len;
+                Ok(x)
+            },
+            None => Err(NP_Error::Damaged("Compiled RPC schema is too short!".into()))
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, NP_Error> {
+        let x = self.bytes(2)?;
+        Ok(u16::from_be_bytes([x[0], x[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, NP_Error> {
+        let x = self.bytes(4)?;
+        Ok(u32::from_be_bytes([x[0], x[1], x[2], x[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, NP_Error> {
+        let len = self.bytes(1)?[0] as usize;
+        match core::str::from_utf8(self.bytes(len)?) {
+            Ok(x) => Ok(x.to_owned()),
+            Err(_) => Err(NP_Error::Damaged("Compiled RPC schema has a name that isn't UTF-8!".into()))
+        }
+    }
+}
+
+#[test]
+fn rpc_works() -> Result<(), NP_Error> {
+    let schema = r#"{"messages":[["todo",{"type":"table","columns":[["title",{"type":"string"}],["done",{"type":"bool"}]]}],["id",{"type":"uint32"}],["empty",{"type":"bool"}]],"services":[["todos",[["get",{"request":"id","response":"todo","error":"empty"}],["add",{"request":"todo","response":"id"}]]],["admin",[["reset",{"request":"empty","response":"empty"}]]]]}"#;
+    let rpc = NP_RPC_Factory::new(schema)?;
+    assert_eq!(rpc.methods(), alloc::vec!["todos.get", "todos.add", "admin.reset"]);
+    assert_eq!(rpc.export_schema()?.stringify(), schema);
+
+    // compiled schemas work the same
+    let compiled = NP_RPC_Factory::new_compiled(rpc.compile_schema())?;
+    assert_eq!(compiled.export_schema()?.stringify(), schema);
+    assert!(NP_RPC_Factory::new_compiled(rpc.compile_schema()[0..20].to_vec()).is_err());
+
+    // requests made by one factory open in the other
+    let mut request = rpc.new_request("todos.add")?;
+    request.data.set(&["title"], "write docs")?;
+    let bytes = request.close();
+    assert_eq!(&bytes[0..3], &[RPC_REQUEST, 0, 1]);
+    let request = compiled.open_request(bytes.clone())?;
+    assert_eq!(request.name, "todos.add");
+    assert_eq!(request.data.get::<&str>(&["title"])?, Some("write docs"));
+
+    // frames are checked
+    assert!(compiled.open_response(bytes.clone()).is_err());
+    assert!(compiled.open_request(alloc::vec![RPC_REQUEST, 0]).is_err());
+    assert!(compiled.open_request(alloc::vec![RPC_REQUEST, 0, 9, 0]).is_err());
+    assert!(rpc.new_request("todos.remove").is_err());
+    assert!(rpc.new_error("todos.add").is_err());
+    let mut error = rpc.new_response("todos.add")?.close();
+    error[0] = RPC_ERROR;
+    assert!(rpc.open_response(error).is_err());
+
+    let mut response = compiled.new_response("admin.reset")?;
+    response.data.set(&[], true)?;
+    let response = rpc.open_response(response.close())?;
+    assert_eq!((response.name, response.status), ("admin.reset", NP_RPC_Status::Ok));
+    assert_eq!(response.data.get::<bool>(&[])?, Some(true));
+    assert!(rpc.message("todo").is_some());
+
+    // schemas are checked
+    assert!(NP_RPC_Factory::new(r#"{"messages":[],"services":[]}"#)?.methods().is_empty());
+    assert!(NP_RPC_Factory::new(r#"{"services":[]}"#).is_err());
+    assert!(NP_RPC_Factory::new(r#"{"messages":[["a",{"type":"u8"}],["a",{"type":"u8"}]],"services":[]}"#).is_err());
+    assert!(NP_RPC_Factory::new(r#"{"messages":[["a",{"type":"u8"}]],"services":[["s",[["m",{"request":"a","response":"b"}]]]]}"#).is_err());
+    assert!(NP_RPC_Factory::new(r#"{"messages":[["a",{"type":"u8"}]],"services":[["s",[["m",{"request":"a"}]]]]}"#).is_err());
+    assert!(NP_RPC_Factory::new(r#"{"messages":[["a.b",{"type":"u8"}]],"services":[]}"#).is_err());
+
+    Ok(())
+}
@@ -99,9 +99,9 @@
 //! | [`list`](#list)                        | [`NP_List`](../collection/list/struct.NP_List.html)                      |𐄂                 | 4 bytes - ~4GB | Linked list with integer indexed values and  up to 65,535 items.         |
 //! | [`map`](#map)                          | [`NP_Map`](../collection/map/struct.NP_Map.html)                         |𐄂                 | 2 bytes - ~4GB | Linked list with `Vec<u8>` keys.                                         |
 //! | [`tuple`](#tuple)                      | [`NP_Tuple`](../collection/tuple/struct.NP_Tuple.html)                   |✓ *               | 2 bytes - ~4GB | Static sized collection of specific values.                              |
-//! | [`any`](#any)                          | [`NP_Any`](../pointer/any/struct.NP_Any.html)                            |𐄂                 | 2 bytes - ~4GB | Generic type.                                                            |
-//! | [`string`](#string)                    | [`String`](../pointer/string/index.html)                                 |✓ **              | 2 bytes - ~4GB | Utf-8 formatted string.                                                  |
-//! | [`bytes`](#bytes)                      | [`NP_Bytes`](../pointer/bytes/struct.NP_Bytes.html)                      |✓ **              | 2 bytes - ~4GB | Arbitrary bytes.                                                         |
+//! | [`any`](#any)                          | [`NP_Any`](../pointer/any/struct.NP_Any.html)                            |𐄂                 | 2 bytes - ~4GB | Dynamic scalar stored with a type tag, see `NP_AnyValue`.                |
+//! | [`string`](#string)                    | [`String`](../pointer/string/index.html)                                 |✓                 | 2 bytes - ~4GB | Utf-8 formatted string.                                                  |
+//! | [`bytes`](#bytes)                      | [`NP_Bytes`](../pointer/bytes/struct.NP_Bytes.html)                      |✓                 | 2 bytes - ~4GB | Arbitrary bytes.                                                         |
 //! | [`int8`](#int8-int16-int32-int64)      | [`i8`](../pointer/numbers/index.html)                                    |✓                 | 1 byte         | -127 to 127                                                              |
 //! | [`int16`](#int8-int16-int32-int64)     | [`i16`](../pointer/numbers/index.html)                                   |✓                 | 2 bytes        | -32,768 to 32,768                                                        |
 //! | [`int32`](#int8-int16-int32-int64)     | [`i32`](../pointer/numbers/index.html)                                   |✓                 | 4 bytes        | -2,147,483,648 to 2,147,483,648                                          |
@@ -110,8 +110,8 @@
 //! | [`uint16`](#uint8-uint16-uint32-uint64)| [`u16`](../pointer/numbers/index.html)                                   |✓                 | 2 bytes        | 0 - 65,535                                                               |
 //! | [`uint32`](#uint8-uint16-uint32-uint64)| [`u32`](../pointer/numbers/index.html)                                   |✓                 | 4 bytes        | 0 - 4,294,967,295                                                        |
 //! | [`uint64`](#uint8-uint16-uint32-uint64)| [`u64`](../pointer/numbers/index.html)                                   |✓                 | 8 bytes        | 0 - 18,446,744,073,709,551,616                                           |
-//! | [`float`](#float-double)               | [`f32`](../pointer/numbers/index.html)                                   |𐄂                 | 4 bytes        | -3.4e38 to 3.4e38                                                        |
-//! | [`double`](#float-double)              | [`f64`](../pointer/numbers/index.html)                                   |𐄂                 | 8 bytes        | -1.7e308 to 1.7e308                                                      |
+//! | [`float`](#float-double)               | [`f32`](../pointer/numbers/index.html)                                   |✓                 | 4 bytes        | -3.4e38 to 3.4e38                                                        |
+//! | [`double`](#float-double)              | [`f64`](../pointer/numbers/index.html)                                   |✓                 | 8 bytes        | -1.7e308 to 1.7e308                                                      |
 //! | [`option`](#option)                    | [`NP_Enum`](../pointer/option/struct.NP_Enum.html)                   |✓                 | 1 byte         | Up to 255 string based options in schema.                                |
 //! | [`bool`](#bool)                        | [`bool`](../pointer/bool/index.html)                                     |✓                 | 1 byte         |                                                                          |
 //! | [`decimal`](#decimal)                  | [`NP_Dec`](../pointer/dec/struct.NP_Dec.html)                            |✓                 | 8 bytes        | Fixed point decimal number based on i64.                                 |
@@ -123,7 +123,6 @@
 //! | [`date`](#date)                        | [`NP_Date`](../pointer/date/struct.NP_Date.html)                         |✓                 | 8 bytes        | Good to store unix epoch (in milliseconds) until the year 584,866,263    |
 //!  
 //! - \* `sorting` must be set to `true` in the schema for this object to enable sorting.
-//! - \*\* String & Bytes can be bytewise sorted only if they have a `size` property in the schema
 //! 
 //! # Legend
 //! 
@@ -134,6 +133,8 @@
 //! 
 //! You can sort by multiple types/values if a tuple is used.  The ordering of values in the tuple will determine the sort order.  For example if you have a tuple with types (A, B) the ordering will first sort by A, then B where A is identical.  This is true for any number of items, for example a tuple with types (A,B,C,D) will sort by D when A, B & C are identical.
 //! 
+//! The bytes each type sorts with are listed [here](../sortable/index.html).
+//! 
 //! **Compaction**<br/>
 //! Campaction is an optional operation you can perform at any time on a buffer, typically used to recover free space.  NoProto Buffers are contiguous, growing arrays of bytes.  When you add or update a value sometimes additional memory is used and the old value is dereferenced, meaning the buffer is now occupying more space than it needs to.  This space can be recovered with compaction.  Compaction involves a recursive, full copy of all referenced & valid values of the buffer, it's an expensive operation that should be avoided.
 //! 
@@ -169,9 +170,12 @@
 //! 
 //! If you need flexible column names use a `map` type instead.
 //! 
+//! Columns are looked up by name with a hash map, the optional `key_hash` property picks the hash function.  The default is `"murmur3"`, `"fnv1a"` and `"xxhash32"` are faster for short and long column names, and `"siphash"` mixes the bits more thoroughly.  Only the column names are hashed, with a fixed seed, the choice doesn't affect map keys.  Read more about the choices in [`NP_Key_Hash`](./enum.NP_Key_Hash.html).
+//! 
 //! ```json
 //! {
 //!     "type": "table",
+//!     "key_hash": "fnv1a", // optional, defaults to "murmur3"
 //!     "columns": [ // can have between 1 and 255 columns
 //!         ["column name",  {"type": "data type for this column"}],
 //!         ["name",         {"type": "string"}],
@@ -290,7 +294,7 @@
 //! ## string
 //! A string is a fixed or dynamically sized collection of utf-8 encoded bytes.
 //! 
-//! - **Bytewise Sorting**: Supported only if `size` property is set in schema.
+//! - **Bytewise Sorting**: Supported, values without a `size` end with a terminator so shorter values sort first.
 //! - **Compaction**: If `size` property is set, compaction cannot reclaim space.  Otherwise it will reclaim space unless all updates have been identical in length.
 //! - **Schema Mutations**: If the `size` property is set it's safe to make it smaller, but not larger (this may cause existing string values to truncate, though).  If the field is being used for bytewise sorting, no mutation is safe.
 //! 
@@ -318,7 +322,7 @@
 //! ## bytes
 //! Bytes are fixed or dynimcally sized Vec<u8> collections. 
 //! 
-//! - **Bytewise Sorting**: Supported only if `size` property is set in schema.
+//! - **Bytewise Sorting**: Supported, values without a `size` end with a terminator so shorter values sort first.
 //! - **Compaction**: If `size` property is set, compaction cannot reclaim space.  Otherwise it will reclaim space unless all updates have been identical in length.
 //! - **Schema Mutations**: If the `size` property is set it's safe to make it smaller, but not larger (this may cause existing bytes values to truncate, though).  If the field is being used for bytewise sorting, no mutation is safe.
 //! 
@@ -353,8 +357,22 @@
 //!     "type": "int8",
 //!     "default": 20
 //! }
+//! // stored inside the pointer
+//! {
+//!     "type": "int8",
+//!     "inline": true
+//! }
+//! // counter that stops at the largest value
+//! {
+//!     "type": "int32",
+//!     "overflow": "saturate"
+//! }
 //! ```
 //! 
+//! `int8` and `int16` values can set `inline` to store the value in the pointer's address bytes instead of a separate allocation.  Buffers with addresses too small to fit the value (1 byte addresses, or 2 byte addresses for `int16`) allocate the value like normal.
+//! 
+//! `overflow` controls what `NP_Buffer::increment` does when a value would go past the range of it's type: `"error"` (the default) fails the increment, `"saturate"` stops at the smallest or largest value and `"wrap"` wraps around like two's complement math.
+//! 
 //! - **Bytewise Sorting**: Supported
 //! - **Compaction**: Updates are done in place, never use additional space.
 //! - **Schema Mutations**: None
@@ -378,15 +396,22 @@
 //!     "type": "uint8",
 //!     "default": 20
 //! }
+//! // stored inside the pointer
+//! {
+//!     "type": "uint8",
+//!     "inline": true
+//! }
 //! ```
 //! 
+//! Like signed integers, `uint8` and `uint16` values can be `inline` and every unsigned integer can set `overflow`.
+//! 
 //! More Details:
 //! - [Using number data types](../pointer/numbers/index.html)
 //! 
 //! ## float, double
 //! Allows the storage of floating point numbers of various sizes.  Bytes are stored in big endian format.
 //! 
-//! - **Bytewise Sorting**: Unsupported, use decimal type.
+//! - **Bytewise Sorting**: Supported, `NaN` sorts after infinity.
 //! - **Compaction**: Updates are done in place, never use additional space.
 //! - **Schema Mutations**: None
 //! 
@@ -445,8 +470,15 @@
 //!     "type": "bool",
 //!     "default": false
 //! }
+//! // stored inside the pointer
+//! {
+//!     "type": "bool",
+//!     "inline": true
+//! }
 //! ```
 //! 
+//! Like small integers, `inline` bools are stored in the pointer.
+//! 
 //! More Details:
 //! 
 //! ## decimal
@@ -477,7 +509,7 @@
 //! ## geo4, ge8, geo16
 //! Allows you to store geographic coordinates with varying levels of accuracy and space usage.  
 //! 
-//! - **Bytewise Sorting**: Not supported
+//! - **Bytewise Sorting**: Supported, sorts by latitude then longitude.  With `"z_order": true` the bits of both are interleaved so nearby coordinates sort near each other.
 //! - **Compaction**: Updates are done in place, never use additional space.
 //! - **Schema Mutations**: None
 //! 
@@ -489,6 +521,8 @@
 //! | geo8  | 8     | 11mm resolution (marble)               | 7              |
 //! | geo16 | 16    | 110 microns resolution (grain of sand) | 9              |
 //! 
+//! With `"type": "geo"` and `"size": "auto"` every value picks the smallest size that holds it's coordinates without losing digits, so a city level coordinate takes 4 bytes and a precise one 16.  Auto sized values use one more byte to record their size, updates that need a different size aren't done in place, and they can't be in sorted tuples.
+//! 
 //! ```json
 //! {
 //!     "type": "geo4"
@@ -498,6 +532,16 @@
 //!     "type": "geo4",
 //!     "default": {"lat": -20.283, "lng": 19.929}
 //! }
+//! // sorted by z-order
+//! {
+//!     "type": "geo8",
+//!     "z_order": true
+//! }
+//! // size picked for each value
+//! {
+//!     "type": "geo",
+//!     "size": "auto"
+//! }
 //! ```
 //! 
 //! More Details:
@@ -553,8 +597,20 @@
 //!     "type": "date",
 //!     "default": 1605909163951
 //! }
+//! // or an RFC3339 string
+//! {
+//!     "type": "date",
+//!     "default": "2020-11-20T21:52:43.951Z"
+//! }
+//! // or the time the buffer was created, read from the factory clock
+//! {
+//!     "type": "date",
+//!     "default": "now"
+//! }
 //! ```
 //! 
+//! Unlike other defaults, `"now"` is written into new buffers when they're created, check out `NP_Factory::with_clock`.
+//! 
 //! More Details:
 //! - [Using NP_Date data type](../pointer/date/struct.NP_Date.html)
 //!  
@@ -566,9 +622,9 @@
 //! [Go to NP_Factory docs](../struct.NP_Factory.html)
 //! 
 
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use core::{fmt::Debug};
-use crate::{hashmap::NP_HashMap, json_flex::NP_JSON, pointer::{string::NP_String, ulid::_NP_ULID, uuid::_NP_UUID}};
+use crate::{hashmap::NP_HashMap, json_flex::{JSMAP, NP_JSON}, pointer::{string::NP_String, ulid::_NP_ULID, uuid::_NP_UUID}};
 use crate::pointer::any::NP_Any;
 use crate::pointer::date::NP_Date;
 use crate::pointer::geo::NP_Geo;
@@ -578,8 +634,14 @@ use crate::pointer::bytes::NP_Bytes;
 use crate::collection::{list::NP_List, table::NP_Table, map::NP_Map};
 use crate::pointer::{option::NP_Enum, NP_Value};
 use crate::error::NP_Error;
+use crate::hashmap::murmurhash3_x86_32;
+pub use crate::hashmap::NP_Key_Hash;
+use crate::validation::{NP_Constraints, NP_Schema_Constraints};
+use crate::evolution::NP_Schema_Evolution;
+use crate::NP_Factory;
 use alloc::vec::Vec;
 use alloc::boxed::Box;
+use alloc::borrow::ToOwned;
 
 /// Simple enum to store the schema types
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
@@ -672,6 +734,23 @@ impl From<u8> for String_Case {
     }
 }
 
+/// What `NP_Buffer::increment` does when an integer goes past the range of it's type
+#[derive(Debug, Clone, Eq, PartialEq, Copy)]
+#[repr(u8)]
+#[allow(missing_docs)]
+pub enum NP_Overflow {
+    Error = 0,
+    Saturate = 1,
+    Wrap = 2,
+}
+
+impl From<u8> for NP_Overflow {
+    fn from(value: u8) -> Self {
+        if value > 2 { return NP_Overflow::Error; }
+        unsafe { core::mem::transmute(value) }
+    }
+}
+
 /// When a schema is parsed from JSON or Bytes, it is stored in this recursive type
 /// 
 #[allow(missing_docs)]
@@ -681,20 +760,20 @@ pub enum NP_Parsed_Schema {
     Any        { sortable: bool, i:NP_TypeKeys },
     UTF8String { sortable: bool, i:NP_TypeKeys, default: Option<String>, size: u16, case: String_Case },
     Bytes      { sortable: bool, i:NP_TypeKeys, default: Option<Vec<u8>>, size: u16 },
-    Int8       { sortable: bool, i:NP_TypeKeys, default: Option<i8> },
-    Int16      { sortable: bool, i:NP_TypeKeys, default: Option<i16> },
-    Int32      { sortable: bool, i:NP_TypeKeys, default: Option<i32> },
-    Int64      { sortable: bool, i:NP_TypeKeys, default: Option<i64> },
-    Uint8      { sortable: bool, i:NP_TypeKeys, default: Option<u8> },
-    Uint16     { sortable: bool, i:NP_TypeKeys, default: Option<u16> },
-    Uint32     { sortable: bool, i:NP_TypeKeys, default: Option<u32> },
-    Uint64     { sortable: bool, i:NP_TypeKeys, default: Option<u64> },
+    Int8       { sortable: bool, i:NP_TypeKeys, default: Option<i8>, inline: bool, overflow: NP_Overflow },
+    Int16      { sortable: bool, i:NP_TypeKeys, default: Option<i16>, inline: bool, overflow: NP_Overflow },
+    Int32      { sortable: bool, i:NP_TypeKeys, default: Option<i32>, overflow: NP_Overflow },
+    Int64      { sortable: bool, i:NP_TypeKeys, default: Option<i64>, overflow: NP_Overflow },
+    Uint8      { sortable: bool, i:NP_TypeKeys, default: Option<u8>, inline: bool, overflow: NP_Overflow },
+    Uint16     { sortable: bool, i:NP_TypeKeys, default: Option<u16>, inline: bool, overflow: NP_Overflow },
+    Uint32     { sortable: bool, i:NP_TypeKeys, default: Option<u32>, overflow: NP_Overflow },
+    Uint64     { sortable: bool, i:NP_TypeKeys, default: Option<u64>, overflow: NP_Overflow },
     Float      { sortable: bool, i:NP_TypeKeys, default: Option<f32> },
     Double     { sortable: bool, i:NP_TypeKeys, default: Option<f64> },
     Decimal    { sortable: bool, i:NP_TypeKeys, default: Option<NP_Dec>, exp: u8 },
-    Boolean    { sortable: bool, i:NP_TypeKeys, default: Option<bool> },
-    Geo        { sortable: bool, i:NP_TypeKeys, default: Option<NP_Geo>, size: u8 },
-    Date       { sortable: bool, i:NP_TypeKeys, default: Option<NP_Date> },
+    Boolean    { sortable: bool, i:NP_TypeKeys, default: Option<bool>, inline: bool },
+    Geo        { sortable: bool, i:NP_TypeKeys, default: Option<NP_Geo>, size: u8, z_order: bool },
+    Date       { sortable: bool, i:NP_TypeKeys, default: Option<NP_Date>, now: bool },
     Enum       { sortable: bool, i:NP_TypeKeys, default: Option<NP_Enum>, choices: Vec<NP_Enum> },
     Uuid       { sortable: bool, i:NP_TypeKeys },
     Ulid       { sortable: bool, i:NP_TypeKeys },
@@ -706,6 +785,30 @@ pub enum NP_Parsed_Schema {
 
 impl NP_Parsed_Schema {
 
+    /// Does this schema have a default value?
+    pub fn has_default(&self) -> bool {
+        match self {
+            NP_Parsed_Schema::UTF8String { default, .. } => default.is_some(),
+            NP_Parsed_Schema::Bytes { default, .. }      => default.is_some(),
+            NP_Parsed_Schema::Int8 { default, .. }       => default.is_some(),
+            NP_Parsed_Schema::Int16 { default, .. }      => default.is_some(),
+            NP_Parsed_Schema::Int32 { default, .. }      => default.is_some(),
+            NP_Parsed_Schema::Int64 { default, .. }      => default.is_some(),
+            NP_Parsed_Schema::Uint8 { default, .. }      => default.is_some(),
+            NP_Parsed_Schema::Uint16 { default, .. }     => default.is_some(),
+            NP_Parsed_Schema::Uint32 { default, .. }     => default.is_some(),
+            NP_Parsed_Schema::Uint64 { default, .. }     => default.is_some(),
+            NP_Parsed_Schema::Float { default, .. }      => default.is_some(),
+            NP_Parsed_Schema::Double { default, .. }     => default.is_some(),
+            NP_Parsed_Schema::Decimal { default, .. }    => default.is_some(),
+            NP_Parsed_Schema::Boolean { default, .. }    => default.is_some(),
+            NP_Parsed_Schema::Geo { default, .. }        => default.is_some(),
+            NP_Parsed_Schema::Date { default, .. }       => default.is_some(),
+            NP_Parsed_Schema::Enum { default, .. }       => default.is_some(),
+            _ => false
+        }
+    }
+
     /// Get the type key for this schema
     pub fn get_type_key(&self) -> &NP_TypeKeys {
         match self {
@@ -801,22 +904,653 @@ impl NP_Parsed_Schema {
 }
 
 
+/// How many levels deep a schema can nest collections, deeper schemas fail to parse so reading or compacting buffers can't overflow the stack
+pub const NP_MAX_DEPTH: usize = 128;
 
 /// New NP Schema
-#[doc(hidden)]
 #[derive(Debug)]
 pub struct NP_Schema {
     /// is this schema sortable?
     pub is_sortable: bool,
     /// recursive parsed schema
-    pub parsed: Vec<NP_Parsed_Schema>
+    pub parsed: Vec<NP_Parsed_Schema>,
+    /// hash of the compiled schema
+    pub(crate) fingerprint: [u8; 8],
+    /// validation constraints for each parsed schema address
+    pub(crate) constraints: Vec<NP_Constraints>
 }
 
 impl NP_Schema {
 
+    /// Get how many levels deep this schema nests, scalars and empty collections are 1 level deep.
+    ///
+    /// Buffers can't nest deeper than their schema, so this is also the deepest any read, compaction or export of a buffer will recurse.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    ///
+    /// let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "map", "value": {"type": "string"}}}"#)?;
+    /// assert_eq!(factory.schema.depth(), 3);
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    pub fn depth(&self) -> usize {
+
+        let mut deepest = 0usize;
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+
+        if self.parsed.len() > 0 {
+            stack.push((0, 1));
+        }
+
+        while let Some((address, depth)) = stack.pop() {
+            deepest = deepest.max(depth);
+            match self.parsed.get(address) {
+                Some(NP_Parsed_Schema::Table { columns, .. }) => {
+                    for column in columns.iter() {
+                        stack.push((column.2, depth + 1));
+                    }
+                },
+                Some(NP_Parsed_Schema::Tuple { values, .. }) => {
+                    for value in values.iter() {
+                        stack.push((*value, depth + 1));
+                    }
+                },
+                Some(NP_Parsed_Schema::List { of, .. }) => stack.push((*of, depth + 1)),
+                Some(NP_Parsed_Schema::Map { value, .. }) => stack.push((*value, depth + 1)),
+                _ => { }
+            }
+        }
+
+        deepest
+    }
+
+    /// Paths to date values with a `"default": "now"` schema that every buffer has, the ones reachable through tables and tuples
+    pub(crate) fn now_dates(&self) -> Vec<Vec<String>> {
+
+        let mut paths: Vec<Vec<String>> = Vec::new();
+        let mut stack: Vec<(usize, Vec<String>)> = Vec::new();
+
+        if self.parsed.len() > 0 {
+            stack.push((0, Vec::new()));
+        }
+
+        while let Some((address, path)) = stack.pop() {
+            match self.parsed.get(address) {
+                Some(NP_Parsed_Schema::Date { now: true, .. }) => paths.push(path),
+                Some(NP_Parsed_Schema::Table { columns, .. }) => {
+                    for (_index, name, column) in columns.iter() {
+                        let mut column_path = path.clone();
+                        column_path.push(name.clone());
+                        stack.push((*column, column_path));
+                    }
+                },
+                Some(NP_Parsed_Schema::Tuple { values, .. }) => {
+                    for (index, value) in values.iter().enumerate() {
+                        let mut value_path = path.clone();
+                        value_path.push(index.to_string());
+                        stack.push((*value, value_path));
+                    }
+                },
+                _ => { }
+            }
+        }
+
+        paths
+    }
+
+    /// Get a read only view of the root type, children can be walked from there to look at the whole schema.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// use no_proto::schema::NP_TypeKeys;
+    ///
+    /// let factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name", {"type": "string", "max_length": 20}],
+    ///         ["tags", {"type": "list", "of": {"type": "u8", "default": 2}}]
+    ///     ]
+    /// }"#)?;
+    ///
+    /// let root = factory.schema.root();
+    /// assert_eq!(root.type_key(), NP_TypeKeys::Table);
+    ///
+    /// let columns: Vec<&str> = root.children().iter().filter_map(|child| child.name).collect();
+    /// assert_eq!(columns, vec!["name", "tags"]);
+    ///
+    /// let name = root.child("name").unwrap();
+    /// assert_eq!(name.type_name(), "string");
+    /// assert_eq!(name.constraints().max_length, Some(20));
+    ///
+    /// let tag = root.child("tags").and_then(|tags| tags.child("0")).unwrap();
+    /// assert_eq!(tag.default()?.map(|x| x.stringify()), Some("2".to_owned()));
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    pub fn root(&self) -> NP_Schema_Node<'_> {
+        NP_Schema_Node { schema: self, address: 0 }
+    }
+
+    /// Get a read only view of the type at a schema address, `None` if the address isn't in the schema
+    pub fn node(&self, address: NP_Schema_Addr) -> Option<NP_Schema_Node<'_>> {
+        if address < self.parsed.len() {
+            Some(NP_Schema_Node { schema: self, address })
+        } else {
+            None
+        }
+    }
+
+    /// Get every path into buffers of this schema along with the type at that path, starting with the root at an empty path.
+    ///
+    /// Paths are listed depth first in schema order.  List items and map values are a `*` step, the same wildcard `NP_Migration` uses, so one entry covers every item.
+    ///
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    ///
+    /// let factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["name",  {"type": "string"}],
+    ///         ["users", {"type": "list", "of": {"type": "table", "columns": [
+    ///             ["tags", {"type": "map", "value": {"type": "u8"}}]
+    ///         ]}}]
+    ///     ]
+    /// }"#)?;
+    ///
+    /// let paths: Vec<(String, &str)> = factory.schema.paths().iter().map(|x| (x.path.join("."), x.node.type_name())).collect();
+    /// assert_eq!(paths, vec![
+    ///     ("".to_owned(), "table"),
+    ///     ("name".to_owned(), "string"),
+    ///     ("users".to_owned(), "list"),
+    ///     ("users.*".to_owned(), "table"),
+    ///     ("users.*.tags".to_owned(), "map"),
+    ///     ("users.*.tags.*".to_owned(), "uint8")
+    /// ]);
+    ///
+    /// # Ok::<(), NP_Error>(())
+    /// ```
+    pub fn paths(&self) -> Vec<NP_Schema_Path<'_>> {
+        let mut paths: Vec<NP_Schema_Path> = Vec::new();
+        if !self.parsed.is_empty() {
+            Self::collect_paths(self.root(), &mut Vec::new(), &mut paths);
+        }
+        paths
+    }
+
+    /// Start extending this schema with steps that keep old buffers readable.
+    ///
+    /// The evolution starts from a plain factory of this schema, use `NP_Schema_Evolution::new` to keep the version, address size and earlier evolutions of a factory.
+    ///
+    /// Check out documentation [here](../evolution/index.html).
+    ///
+    pub fn evolve(&self) -> Result<NP_Schema_Evolution, NP_Error> {
+        Ok(NP_Schema_Evolution::new(&NP_Factory::new(&self.to_json()?.stringify())?))
+    }
+
+    fn collect_paths<'schema>(node: NP_Schema_Node<'schema>, path: &mut Vec<String>, paths: &mut Vec<NP_Schema_Path<'schema>>) {
+        paths.push(NP_Schema_Path { path: path.clone(), node });
+        for child in node.children() {
+            path.push(match (node.type_key(), child.name) {
+                (NP_TypeKeys::List, _) | (NP_TypeKeys::Map, _) => "*".to_owned(),
+                (_, Some(name)) => name.to_owned(),
+                (_, None) => alloc::format!("{}", child.index)
+            });
+            Self::collect_paths(child.node, path, paths);
+            path.pop();
+        }
+    }
+
+    /// Get a stable 8 byte hash of the compiled schema.
+    /// 
+    /// Schemas that compile to the same bytes always have the same fingerprint, so it can be stored next to buffers to check they're opened with the schema they were written with.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory = NP_Factory::new(r#"{"type": "list", "of": {"type": "string"}}"#)?;
+    /// let same = NP_Factory::new(r#"{ "of": {"type": "string"}, "type": "list" }"#)?;
+    /// let other = NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?;
+    /// 
+    /// assert_eq!(factory.schema.fingerprint(), same.schema.fingerprint());
+    /// assert_ne!(factory.schema.fingerprint(), other.schema.fingerprint());
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn fingerprint(&self) -> [u8; 8] {
+        self.fingerprint
+    }
+
+    /// Hash compiled schema bytes into a fingerprint
+    pub(crate) fn fingerprint_bytes(schema_bytes: &[u8]) -> [u8; 8] {
+        let mut result = [0u8; 8];
+        result[0..4].copy_from_slice(&murmurhash3_x86_32(schema_bytes, 0).to_be_bytes());
+        result[4..8].copy_from_slice(&murmurhash3_x86_32(schema_bytes, 0x9747_b28c).to_be_bytes());
+        result
+    }
+
     /// Get a JSON represenatation of this schema
     pub fn to_json(&self) -> Result<NP_JSON, NP_Error> {
-        NP_Schema::_type_to_json(&self.parsed, 0)
+        let mut json = NP_Schema::_type_to_json(&self.parsed, 0)?;
+        NP_Constraints::to_json(&self.parsed, &self.constraints, 0, &mut json);
+        Ok(json)
+    }
+
+    /// Get a draft-07 [JSON Schema](https://json-schema.org/) that describes the JSON this schema's buffers produce with `json_encode`.
+    /// 
+    /// Tables become objects, maps become objects with `additionalProperties`, lists and tuples become arrays, enums become `enum` string choices and numeric types get `minimum`/`maximum` constraints.
+    /// 
+    /// Values without a default come out of `json_encode` as `null` when they haven't been set, so they also accept `null` here.
+    /// 
+    /// ```
+    /// use no_proto::error::NP_Error;
+    /// use no_proto::NP_Factory;
+    /// 
+    /// let factory: NP_Factory = NP_Factory::new(r#"{
+    ///    "type": "table",
+    ///    "columns": [
+    ///         ["age",   {"type": "uint8", "default": 20}],
+    ///         ["color", {"type": "enum", "choices": ["red", "blue"]}]
+    ///     ]
+    /// }"#)?;
+    /// 
+    /// assert_eq!(factory.schema.to_json_schema()?.stringify(), concat!(
+    ///     r#"{"$schema":"http://json-schema.org/draft-07/schema#","type":["object","null"],"properties":{"#,
+    ///     r#""age":{"type":"integer","minimum":0,"maximum":255,"default":20},"#,
+    ///     r#""color":{"type":["string","null"],"enum":["red","blue",null]}"#,
+    ///     r#"},"additionalProperties":false}"#
+    /// ));
+    /// 
+    /// # Ok::<(), NP_Error>(()) 
+    /// ```
+    /// 
+    pub fn to_json_schema(&self) -> Result<NP_JSON, NP_Error> {
+        let mut json_schema = JSMAP::new();
+        json_schema.insert("$schema".to_owned(), NP_JSON::String("http://json-schema.org/draft-07/schema#".to_owned()));
+        if let NP_JSON::Dictionary(root) = NP_Schema::_type_to_json_schema(&self.parsed, 0)? {
+            for (key, value) in root.values {
+                json_schema.insert(key, value);
+            }
+        }
+        Ok(NP_JSON::Dictionary(json_schema))
+    }
+
+    /// Recursive function to convert schema into JSON Schema
+    fn _type_to_json_schema(parsed_schema: &Vec<NP_Parsed_Schema>, address: usize) -> Result<NP_JSON, NP_Error> {
+
+        let mut json_schema = JSMAP::new();
+
+        // values without a default are null when they aren't set
+        let type_of = |json_type: &str, has_default: bool| {
+            if has_default {
+                NP_JSON::String(json_type.to_owned())
+            } else {
+                NP_JSON::Array(alloc::vec![NP_JSON::String(json_type.to_owned()), NP_JSON::String("null".to_owned())])
+            }
+        };
+
+        let integer = |json_schema: &mut JSMAP, has_default: bool, min: i64, max: Option<i64>| {
+            json_schema.insert("type".to_owned(), type_of("integer", has_default));
+            json_schema.insert("minimum".to_owned(), NP_JSON::Integer(min));
+            if let Some(max) = max {
+                json_schema.insert("maximum".to_owned(), NP_JSON::Integer(max));
+            }
+        };
+
+        let number = |min: f64, max: f64| {
+            let mut property = JSMAP::new();
+            property.insert("type".to_owned(), NP_JSON::String("number".to_owned()));
+            property.insert("minimum".to_owned(), NP_JSON::Float(min));
+            property.insert("maximum".to_owned(), NP_JSON::Float(max));
+            NP_JSON::Dictionary(property)
+        };
+
+        let required = |keys: &[&str]| {
+            NP_JSON::Array(keys.iter().map(|key| NP_JSON::String((*key).to_owned())).collect())
+        };
+
+        match &parsed_schema[address] {
+            NP_Parsed_Schema::UTF8String { default, size, .. } => {
+                json_schema.insert("type".to_owned(), type_of("string", default.is_some()));
+                if *size > 0 {
+                    json_schema.insert("maxLength".to_owned(), NP_JSON::Integer(*size as i64));
+                }
+            },
+            NP_Parsed_Schema::Bytes { default, size, .. } => {
+                let mut items = JSMAP::new();
+                integer(&mut items, true, 0, Some(u8::MAX as i64));
+                json_schema.insert("type".to_owned(), type_of("array", default.is_some()));
+                json_schema.insert("items".to_owned(), NP_JSON::Dictionary(items));
+                if *size > 0 {
+                    json_schema.insert("maxItems".to_owned(), NP_JSON::Integer(*size as i64));
+                }
+            },
+            NP_Parsed_Schema::Int8   { default, .. } => integer(&mut json_schema, default.is_some(), i8::MIN as i64, Some(i8::MAX as i64)),
+            NP_Parsed_Schema::Int16  { default, .. } => integer(&mut json_schema, default.is_some(), i16::MIN as i64, Some(i16::MAX as i64)),
+            NP_Parsed_Schema::Int32  { default, .. } => integer(&mut json_schema, default.is_some(), i32::MIN as i64, Some(i32::MAX as i64)),
+            NP_Parsed_Schema::Int64  { default, .. } => integer(&mut json_schema, default.is_some(), i64::MIN, Some(i64::MAX)),
+            NP_Parsed_Schema::Uint8  { default, .. } => integer(&mut json_schema, default.is_some(), 0, Some(u8::MAX as i64)),
+            NP_Parsed_Schema::Uint16 { default, .. } => integer(&mut json_schema, default.is_some(), 0, Some(u16::MAX as i64)),
+            NP_Parsed_Schema::Uint32 { default, .. } => integer(&mut json_schema, default.is_some(), 0, Some(u32::MAX as i64)),
+            // JSON integers can't hold the largest u64 values
+            NP_Parsed_Schema::Uint64 { default, .. } => integer(&mut json_schema, default.is_some(), 0, None),
+            NP_Parsed_Schema::Date   { default, .. } => integer(&mut json_schema, default.is_some(), 0, None),
+            NP_Parsed_Schema::Float  { default, .. } => {
+                json_schema.insert("type".to_owned(), type_of("number", default.is_some()));
+            },
+            NP_Parsed_Schema::Double { default, .. } => {
+                json_schema.insert("type".to_owned(), type_of("number", default.is_some()));
+            },
+            NP_Parsed_Schema::Boolean { default, .. } => {
+                json_schema.insert("type".to_owned(), type_of("boolean", default.is_some()));
+            },
+            NP_Parsed_Schema::Decimal { default, exp, .. } => {
+                let mut properties = JSMAP::new();
+                properties.insert("num".to_owned(), NP_JSON::Dictionary({
+                    let mut num = JSMAP::new();
+                    num.insert("type".to_owned(), NP_JSON::String("integer".to_owned()));
+                    num
+                }));
+                properties.insert("exp".to_owned(), NP_JSON::Dictionary({
+                    let mut exp_json = JSMAP::new();
+                    exp_json.insert("const".to_owned(), NP_JSON::Integer(*exp as i64));
+                    exp_json
+                }));
+                json_schema.insert("type".to_owned(), type_of("object", default.is_some()));
+                json_schema.insert("properties".to_owned(), NP_JSON::Dictionary(properties));
+                json_schema.insert("required".to_owned(), required(&["num", "exp"]));
+                if let Some(d) = default {
+                    let mut default_json = JSMAP::new();
+                    default_json.insert("num".to_owned(), NP_JSON::Integer(d.num));
+                    default_json.insert("exp".to_owned(), NP_JSON::Integer(*exp as i64));
+                    json_schema.insert("default".to_owned(), NP_JSON::Dictionary(default_json));
+                }
+                return Ok(NP_JSON::Dictionary(json_schema));
+            },
+            NP_Parsed_Schema::Geo { default, .. } => {
+                let mut properties = JSMAP::new();
+                properties.insert("lat".to_owned(), number(-90f64, 90f64));
+                properties.insert("lng".to_owned(), number(-180f64, 180f64));
+                json_schema.insert("type".to_owned(), type_of("object", default.is_some()));
+                json_schema.insert("properties".to_owned(), NP_JSON::Dictionary(properties));
+                json_schema.insert("required".to_owned(), required(&["lat", "lng"]));
+            },
+            NP_Parsed_Schema::Uuid { .. } => {
+                json_schema.insert("type".to_owned(), type_of("string", false));
+                json_schema.insert("pattern".to_owned(), NP_JSON::String("^[0-9A-F]{8}-[0-9A-F]{4}-[0-9A-F]{4}-[0-9A-F]{4}-[0-9A-F]{12}$".to_owned()));
+            },
+            NP_Parsed_Schema::Ulid { .. } => {
+                json_schema.insert("type".to_owned(), type_of("string", false));
+            },
+            NP_Parsed_Schema::Enum { default, choices, .. } => {
+                let mut options: Vec<NP_JSON> = choices.iter().filter_map(|choice| match choice {
+                    NP_Enum::Some(x) => Some(NP_JSON::String(x.clone())),
+                    NP_Enum::None => None
+                }).collect();
+                if default.is_none() {
+                    options.push(NP_JSON::Null);
+                }
+                json_schema.insert("type".to_owned(), type_of("string", default.is_some()));
+                json_schema.insert("enum".to_owned(), NP_JSON::Array(options));
+            },
+            NP_Parsed_Schema::Table { columns, .. } => {
+                let mut properties = JSMAP::new();
+                for (_index, name, column) in columns {
+                    properties.insert(name.clone(), NP_Schema::_type_to_json_schema(parsed_schema, *column)?);
+                }
+                json_schema.insert("type".to_owned(), type_of("object", false));
+                json_schema.insert("properties".to_owned(), NP_JSON::Dictionary(properties));
+                json_schema.insert("additionalProperties".to_owned(), NP_JSON::False);
+            },
+            NP_Parsed_Schema::Map { value, .. } => {
+                json_schema.insert("type".to_owned(), type_of("object", false));
+                json_schema.insert("additionalProperties".to_owned(), NP_Schema::_type_to_json_schema(parsed_schema, *value)?);
+            },
+            NP_Parsed_Schema::List { of, .. } => {
+                json_schema.insert("type".to_owned(), type_of("array", false));
+                json_schema.insert("items".to_owned(), NP_Schema::_type_to_json_schema(parsed_schema, *of)?);
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                let mut items = Vec::new();
+                for value in values {
+                    items.push(NP_Schema::_type_to_json_schema(parsed_schema, *value)?);
+                }
+                json_schema.insert("type".to_owned(), type_of("array", false));
+                json_schema.insert("items".to_owned(), NP_JSON::Array(items));
+                json_schema.insert("additionalItems".to_owned(), NP_JSON::False);
+            },
+            _ => { }
+        }
+
+        // scalar defaults are stored the same way in both schemas
+        if let NP_JSON::Dictionary(np_schema) = NP_Schema::_type_to_json(parsed_schema, address)? {
+            if let Some(default) = np_schema.get("default") {
+                json_schema.insert("default".to_owned(), default.clone());
+            }
+        }
+
+        Ok(NP_JSON::Dictionary(json_schema))
+    }
+
+    /// Get a proto3 `.proto` definition with this schema as a message named `message_name`.  The schema must be a table.
+    /// 
+    /// This method requires the `protobuf` feature, check out documentation [here](../protobuf/index.html).
+    /// 
+    #[cfg(feature = "protobuf")]
+    pub fn to_proto(&self, message_name: &str) -> Result<String, NP_Error> {
+        crate::protobuf::schema_to_proto(&self.parsed, 0, message_name)
+    }
+
+    /// Get an Avro schema for this schema, `name` is used for the root record and as the prefix for nested record and enum names.
+    /// 
+    /// This method requires the `avro` feature, check out documentation [here](../avro/index.html).
+    /// 
+    #[cfg(feature = "avro")]
+    pub fn to_avro_schema(&self, name: &str) -> Result<NP_JSON, NP_Error> {
+        crate::avro::schema_to_avro(&self.parsed, 0, name)
+    }
+
+    /// Recursive function to convert a JSON Schema into a schema for this library
+    pub(crate) fn _from_json_schema(json_schema: &NP_JSON) -> Result<NP_JSON, NP_Error> {
+
+        let json_map = match json_schema {
+            NP_JSON::Dictionary(x) => x,
+            NP_JSON::True => return NP_Schema::_from_json_schema(&NP_JSON::Dictionary(JSMAP::new())),
+            _ => return Err(NP_Error::Schema("JSON Schemas must be objects!".into()))
+        };
+
+        for unsupported in &["$ref", "allOf", "anyOf", "oneOf", "not", "if"] {
+            if json_map.get(unsupported).is_some() {
+                let mut err = String::from("Unsupported JSON Schema keyword: ");
+                err.push_str(unsupported);
+                return Err(NP_Error::Schema(err));
+            }
+        }
+
+        // a list of types is allowed as long as the only other type is null
+        let json_type = match json_map.get("type") {
+            Some(NP_JSON::String(x)) => Some(x.as_str()),
+            Some(NP_JSON::Array(types)) => {
+                let types: Vec<&str> = types.iter().filter_map(|t| t.into_string()).filter(|t| *t != "null").map(|t| t.as_str()).collect();
+                if types.len() > 1 {
+                    return Err(NP_Error::Schema("JSON Schema types can only be combined with 'null'!".into()));
+                }
+                types.first().map(|t| *t)
+            },
+            _ => None
+        };
+
+        let mut schema = JSMAP::new();
+
+        let set_type = |schema: &mut JSMAP, np_type: &str| {
+            schema.insert("type".to_owned(), NP_JSON::String(np_type.to_owned()));
+        };
+
+        let default = json_map.get("default");
+
+        if let Some(NP_JSON::Array(options)) = json_map.get("enum") {
+            let choices: Vec<NP_JSON> = options.iter().filter(|x| x.is_null() == false).map(|x| x.clone()).collect();
+            if choices.iter().any(|x| x.is_string() == false) {
+                return Err(NP_Error::Schema("Only string enums are supported!".into()));
+            }
+            set_type(&mut schema, "enum");
+            schema.insert("choices".to_owned(), NP_JSON::Array(choices));
+            if let Some(NP_JSON::String(x)) = default {
+                schema.insert("default".to_owned(), NP_JSON::String(x.clone()));
+            }
+            return Ok(NP_JSON::Dictionary(schema));
+        }
+
+        let properties = match json_map.get("properties") {
+            Some(NP_JSON::Dictionary(x)) => Some(x),
+            _ => None
+        };
+
+        let has_properties = |keys: &[&str]| {
+            match properties {
+                Some(props) => props.values.len() == keys.len() && keys.iter().all(|k| props.get(k).is_some()),
+                None => false
+            }
+        };
+
+        match json_type {
+            None => set_type(&mut schema, "any"),
+            Some("string") => {
+                let is_uuid = match (json_map.get("format"), json_map.get("pattern")) {
+                    (Some(NP_JSON::String(format)), _) => format == "uuid",
+                    (_, Some(NP_JSON::String(pattern))) => pattern == "^[0-9A-F]{8}-[0-9A-F]{4}-[0-9A-F]{4}-[0-9A-F]{4}-[0-9A-F]{12}$",
+                    _ => false
+                };
+                if is_uuid {
+                    set_type(&mut schema, "uuid");
+                } else {
+                    set_type(&mut schema, "string");
+                    if let Some(NP_JSON::Integer(x)) = json_map.get("maxLength") {
+                        schema.insert("size".to_owned(), NP_JSON::Integer(*x));
+                    }
+                    if let Some(NP_JSON::String(x)) = default {
+                        schema.insert("default".to_owned(), NP_JSON::String(x.clone()));
+                    }
+                }
+            },
+            Some("integer") => {
+                let bound = |key: &str| {
+                    match json_map.get(key) {
+                        Some(NP_JSON::Integer(x)) => Some(*x),
+                        Some(NP_JSON::Float(x)) => Some(*x as i64),
+                        _ => None
+                    }
+                };
+                // pick the smallest type that holds the whole range
+                let np_type = match (bound("minimum"), bound("maximum")) {
+                    (Some(min), Some(max)) if min >= 0 => {
+                        if max <= u8::MAX as i64 { "uint8" } else if max <= u16::MAX as i64 { "uint16" } else if max <= u32::MAX as i64 { "uint32" } else { "uint64" }
+                    },
+                    (Some(min), None) if min >= 0 => "uint64",
+                    (Some(min), Some(max)) => {
+                        if min >= i8::MIN as i64 && max <= i8::MAX as i64 { "int8" } else if min >= i16::MIN as i64 && max <= i16::MAX as i64 { "int16" } else if min >= i32::MIN as i64 && max <= i32::MAX as i64 { "int32" } else { "int64" }
+                    },
+                    _ => "int64"
+                };
+                set_type(&mut schema, np_type);
+                if let Some(NP_JSON::Integer(x)) = default {
+                    schema.insert("default".to_owned(), NP_JSON::Integer(*x));
+                }
+            },
+            Some("number") => {
+                set_type(&mut schema, "double");
+                match default {
+                    Some(NP_JSON::Integer(x)) => { schema.insert("default".to_owned(), NP_JSON::Float(*x as f64)); },
+                    Some(NP_JSON::Float(x)) => { schema.insert("default".to_owned(), NP_JSON::Float(*x)); },
+                    _ => { }
+                }
+            },
+            Some("boolean") => {
+                set_type(&mut schema, "bool");
+                match default {
+                    Some(NP_JSON::True) => { schema.insert("default".to_owned(), NP_JSON::True); },
+                    Some(NP_JSON::False) => { schema.insert("default".to_owned(), NP_JSON::False); },
+                    _ => { }
+                }
+            },
+            Some("object") => {
+                let decimal_exp = match properties.and_then(|props| props.get("exp")).map(|exp| &exp["const"]) {
+                    Some(NP_JSON::Integer(exp)) if has_properties(&["num", "exp"]) => Some(*exp),
+                    _ => None
+                };
+                if let Some(exp) = decimal_exp {
+                    set_type(&mut schema, "decimal");
+                    schema.insert("exp".to_owned(), NP_JSON::Integer(exp));
+                    if let Some(NP_JSON::Integer(num)) = default.map(|d| &d["num"]) {
+                        if exp >= 0 && exp < 19 {
+                            schema.insert("default".to_owned(), NP_JSON::Float(*num as f64 / 10i64.pow(exp as u32) as f64));
+                        }
+                    }
+                } else if has_properties(&["lat", "lng"]) {
+                    set_type(&mut schema, "geo16");
+                    if let Some(x) = default {
+                        schema.insert("default".to_owned(), x.clone());
+                    }
+                } else if let Some(props) = properties {
+                    let mut columns = Vec::new();
+                    for (key, value) in &props.values {
+                        columns.push(NP_JSON::Array(alloc::vec![NP_JSON::String(key.clone()), NP_Schema::_from_json_schema(value)?]));
+                    }
+                    set_type(&mut schema, "table");
+                    schema.insert("columns".to_owned(), NP_JSON::Array(columns));
+                } else {
+                    let value = match json_map.get("additionalProperties") {
+                        Some(x) if x.is_dictionary() => NP_Schema::_from_json_schema(x)?,
+                        _ => return Err(NP_Error::Schema("JSON Schema objects need 'properties' or an 'additionalProperties' schema!".into()))
+                    };
+                    set_type(&mut schema, "map");
+                    schema.insert("value".to_owned(), value);
+                }
+            },
+            Some("array") => {
+                match json_map.get("items") {
+                    Some(NP_JSON::Array(items)) => {
+                        let mut values = Vec::new();
+                        for item in items {
+                            values.push(NP_Schema::_from_json_schema(item)?);
+                        }
+                        set_type(&mut schema, "tuple");
+                        schema.insert("values".to_owned(), NP_JSON::Array(values));
+                    },
+                    Some(items) => {
+                        let is_byte = match (&items["type"], &items["minimum"], &items["maximum"]) {
+                            (NP_JSON::String(t), NP_JSON::Integer(0), NP_JSON::Integer(255)) => t == "integer",
+                            _ => false
+                        };
+                        if is_byte {
+                            set_type(&mut schema, "bytes");
+                            if let Some(NP_JSON::Integer(x)) = json_map.get("maxItems") {
+                                schema.insert("size".to_owned(), NP_JSON::Integer(*x));
+                            }
+                            if let Some(x @ NP_JSON::Array(_)) = default {
+                                schema.insert("default".to_owned(), x.clone());
+                            }
+                        } else {
+                            set_type(&mut schema, "list");
+                            schema.insert("of".to_owned(), NP_Schema::_from_json_schema(items)?);
+                        }
+                    },
+                    None => return Err(NP_Error::Schema("JSON Schema arrays need an 'items' schema!".into()))
+                }
+            },
+            Some(x) => {
+                let mut err = String::from("Unsupported JSON Schema type: ");
+                err.push_str(x);
+                return Err(NP_Error::Schema(err));
+            }
+        }
+
+        Ok(NP_JSON::Dictionary(schema))
     }
 
     /// Recursive function parse schema into JSON
@@ -859,7 +1593,7 @@ impl NP_Schema {
                 Ok(x.clone())
             },
             _ => {
-                Err(NP_Error::new("Schemas must have a 'type' property!"))
+                Err(NP_Error::Schema("Schemas must have a 'type' property!".into()))
             }
         }
     }
@@ -938,6 +1672,7 @@ impl NP_Schema {
                     "decimal"  => {    NP_Dec::from_json_to_schema(schema, &json_schema) },
                     "bool"     => {      bool::from_json_to_schema(schema, &json_schema) },
                     "boolean"  => {      bool::from_json_to_schema(schema, &json_schema) },
+                    "geo"      => {    NP_Geo::from_json_to_schema(schema, &json_schema) },
                     "geo4"     => {    NP_Geo::from_json_to_schema(schema, &json_schema) },
                     "geo8"     => {    NP_Geo::from_json_to_schema(schema, &json_schema) },
                     "geo16"    => {    NP_Geo::from_json_to_schema(schema, &json_schema) },
@@ -953,13 +1688,343 @@ impl NP_Schema {
                     _ => {
                         let mut err_msg = String::from("Can't find a type that matches this schema! ");
                         err_msg.push_str(json_schema.stringify().as_str());
-                        return Err(NP_Error::new(err_msg.as_str()))
+                        return Err(NP_Error::Schema(err_msg))
                     }
                 }
             },
             _ => {
-                Err(NP_Error::new("Schemas must have a 'type' property!"))
+                Err(NP_Error::Schema("Schemas must have a 'type' property!".into()))
             }
         }
     }
 }
+
+/// A read only view of one type in a parsed schema
+///
+/// Nodes are made by `NP_Schema::root` and `NP_Schema::node` and borrow the schema, so code generators and documentation tools can walk the parsed schema instead of parsing the output of `to_json`.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct NP_Schema_Node<'schema> {
+    schema: &'schema NP_Schema,
+    address: NP_Schema_Addr
+}
+
+/// One child type of a collection schema
+#[derive(Debug, Clone, Copy)]
+pub struct NP_Schema_Child<'schema> {
+    /// Column name for tables, `None` for other collections
+    pub name: Option<&'schema str>,
+    /// Column index for tables, value index for tuples and `0` for list items and map values
+    pub index: usize,
+    /// Schema of the child
+    pub node: NP_Schema_Node<'schema>
+}
+
+/// A path into buffers of a schema and the type at that path, made by `NP_Schema::paths`
+#[derive(Debug, Clone)]
+pub struct NP_Schema_Path<'schema> {
+    /// Steps from the root, `*` for list items and map values
+    pub path: Vec<String>,
+    /// Schema at the end of the path
+    pub node: NP_Schema_Node<'schema>
+}
+
+impl<'schema> NP_Schema_Node<'schema> {
+
+    /// Address of this type in the parsed schema
+    pub fn address(&self) -> NP_Schema_Addr {
+        self.address
+    }
+
+    /// The parsed schema of this type, with every type specific property
+    pub fn parsed(&self) -> &'schema NP_Parsed_Schema {
+        &self.schema.parsed[self.address]
+    }
+
+    /// Type key of this type
+    pub fn type_key(&self) -> NP_TypeKeys {
+        *self.parsed().get_type_key()
+    }
+
+    /// Name of this type, the same one `to_json` exports
+    pub fn type_name(&self) -> &'schema str {
+        self.type_key().into_type_idx().0
+    }
+
+    /// Is this type sortable?
+    pub fn is_sortable(&self) -> bool {
+        self.parsed().is_sortable()
+    }
+
+    /// Is this type a table, map, list or tuple?
+    pub fn is_collection(&self) -> bool {
+        matches!(self.type_key(), NP_TypeKeys::Table | NP_TypeKeys::Map | NP_TypeKeys::List | NP_TypeKeys::Tuple)
+    }
+
+    /// Default value of this type as JSON, as it's written in the schema
+    pub fn default(&self) -> Result<Option<NP_JSON>, NP_Error> {
+        if !self.parsed().has_default() {
+            return Ok(None);
+        }
+        match NP_Schema::_type_to_json(&self.schema.parsed, self.address)? {
+            NP_JSON::Dictionary(mut json) => Ok(json.get_mut("default").map(|x| core::mem::replace(x, NP_JSON::Null))),
+            _ => Ok(None)
+        }
+    }
+
+    /// Validation constraints of this type, they're empty for factories made from compiled schemas
+    pub fn constraints(&self) -> NP_Schema_Constraints {
+        NP_Constraints::describe(&self.schema.constraints, self.address)
+    }
+
+    /// Child types of this collection in schema order, empty for scalars
+    pub fn children(&self) -> Vec<NP_Schema_Child<'schema>> {
+        let schema = self.schema;
+        let node = |address: NP_Schema_Addr| NP_Schema_Node { schema, address };
+        match self.parsed() {
+            NP_Parsed_Schema::Table { columns, .. } => {
+                columns.iter().map(|(index, name, address)| NP_Schema_Child { name: Some(name.as_str()), index: *index as usize, node: node(*address) }).collect()
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                values.iter().enumerate().map(|(index, address)| NP_Schema_Child { name: None, index, node: node(*address) }).collect()
+            },
+            NP_Parsed_Schema::List { of, .. } => alloc::vec![NP_Schema_Child { name: None, index: 0, node: node(*of) }],
+            NP_Parsed_Schema::Map { value, .. } => alloc::vec![NP_Schema_Child { name: None, index: 0, node: node(*value) }],
+            _ => Vec::new()
+        }
+    }
+
+    /// Get the type one path step below this one, like a step in `NP_Buffer::get`.
+    ///
+    /// Tables take column names, tuples and lists take indexes and maps take any key.
+    ///
+    pub fn child(&self, key: &str) -> Option<NP_Schema_Node<'schema>> {
+        let address = match self.parsed() {
+            NP_Parsed_Schema::Table { columns, .. } => columns.iter().find(|(_, name, _)| name == key)?.2,
+            NP_Parsed_Schema::Tuple { values, .. } => *values.get(key.parse::<usize>().ok()?)?,
+            NP_Parsed_Schema::List { of, .. } => { key.parse::<u16>().ok()?; *of },
+            NP_Parsed_Schema::Map { value, .. } => *value,
+            _ => return None
+        };
+        Some(NP_Schema_Node { schema: self.schema, address })
+    }
+
+    /// Get the JSON schema of this type and it's children, including constraints
+    pub fn to_json(&self) -> Result<NP_JSON, NP_Error> {
+        let mut json = NP_Schema::_type_to_json(&self.schema.parsed, self.address)?;
+        NP_Constraints::to_json(&self.schema.parsed, &self.schema.constraints, self.address, &mut json);
+        Ok(json)
+    }
+}
+
+#[test]
+fn depth_limits_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use alloc::string::ToString;
+
+    let nested = |depth: usize| -> String {
+        r#"{"type": "list", "of": "#.repeat(depth - 1) + r#"{"type": "string"}"# + &"}".repeat(depth - 1)
+    };
+
+    // the deepest schema allowed still works end to end
+    let factory = NP_Factory::new(&nested(NP_MAX_DEPTH))?;
+    assert_eq!(factory.schema.depth(), NP_MAX_DEPTH);
+    let path: Vec<String> = (0..(NP_MAX_DEPTH - 1)).map(|x| (x % 3).to_string()).collect();
+    let path: Vec<&str> = path.iter().map(|x| x.as_str()).collect();
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&path, "deep")?;
+    buffer.set(&path, "deeper")?;
+    assert!(buffer.calc_bytes()?.wasted_bytes > 0);
+    buffer.compact(None)?;
+    assert_eq!(buffer.get::<&str>(&path)?, Some("deeper"));
+    assert!(buffer.json_encode(&[])?.stringify().contains("deeper"));
+    buffer.canonicalize()?;
+    assert!(buffer.verify()?.is_ok());
+
+    // one level more fails to parse
+    match NP_Factory::new(&nested(NP_MAX_DEPTH + 1)) {
+        Err(NP_Error::Schema(message)) => assert!(message.contains("max depth")),
+        _ => panic!()
+    }
+
+    // and so does JSON nested deep enough to overflow the parser
+    assert!(NP_Factory::new(&nested(100_000)).is_err());
+    assert!(crate::json_flex::json_decode("[".repeat(100_000)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn schema_nodes_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["id",     {"type": "u32", "required": true, "min": 1}],
+        ["point",  {"type": "tuple", "values": [{"type": "i8"}, {"type": "string", "default": "a"}]}],
+        ["scores", {"type": "map", "value": {"type": "float"}}]
+    ]}"#)?;
+    let root = factory.schema.root();
+    assert_eq!((root.address(), root.is_collection(), root.default()?.is_none()), (0, true, true));
+    assert_eq!(root.children().len(), 3);
+    assert_eq!(root.children()[2].index, 2);
+
+    let id = root.child("id").unwrap();
+    assert!(!id.is_collection() && id.children().is_empty() && id.child("0").is_none());
+    assert_eq!(id.constraints().min, Some(crate::validation::NP_Bound::Int(1)));
+    assert!(id.constraints().required);
+    assert_eq!(id.to_json()?.stringify(), r#"{"type":"uint32","min":1,"required":true}"#);
+
+    let point = root.child("point").unwrap();
+    assert_eq!(point.children().iter().map(|c| c.node.type_name()).collect::<Vec<&str>>(), alloc::vec!["int8", "string"]);
+    assert_eq!(point.child("1").unwrap().default()?.map(|x| x.stringify()), Some(r#""a""#.to_owned()));
+    assert!(point.child("2").is_none() && point.child("x").is_none());
+    assert_eq!(root.child("scores").and_then(|x| x.child("any key")).map(|x| x.type_key()), Some(NP_TypeKeys::Float));
+    assert!(root.child("missing").is_none());
+
+    // every node can be found by it's address
+    let address = point.child("0").unwrap().address();
+    assert_eq!(factory.schema.node(address).map(|x| x.type_key()), Some(NP_TypeKeys::Int8));
+    assert!(factory.schema.node(factory.schema.parsed.len()).is_none());
+
+    // paths cover every address once
+    let paths = factory.schema.paths();
+    assert_eq!(paths.len(), factory.schema.parsed.len());
+    assert_eq!(paths[4].path, alloc::vec!["point".to_owned(), "1".to_owned()]);
+    assert_eq!(paths[6].path.join("."), "scores.*");
+    for x in paths.iter().filter(|x| x.path.iter().all(|step| step != "*")) {
+        let path: Vec<&str> = x.path.iter().map(|x| x.as_str()).collect();
+        assert_eq!(factory.compile_path(&path)?.schema_addr, x.node.address());
+    }
+
+    // compiled schemas have no constraints
+    let compiled = NP_Factory::new_compiled(factory.compile_schema());
+    assert_eq!(compiled.schema.root().child("id").unwrap().constraints(), NP_Schema_Constraints::default());
+
+    Ok(())
+}
+
+#[test]
+fn schema_paths_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    fn list_paths(factory: &NP_Factory) -> Vec<(String, &str)> {
+        factory.schema.paths().iter().map(|x| (x.path.join("."), x.node.type_name())).collect()
+    }
+
+    // a scalar root is the only path
+    let factory = NP_Factory::new(r#"{"type": "string"}"#)?;
+    assert_eq!(list_paths(&factory), alloc::vec![("".to_owned(), "string")]);
+
+    // collections nested in each other
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["rows",  {"type": "list", "of": {"type": "tuple", "values": [
+            {"type": "map", "value": {"type": "u8"}},
+            {"type": "string"}
+        ]}}],
+        ["flags", {"type": "map", "value": {"type": "list", "of": {"type": "bool"}}}]
+    ]}"#)?;
+    let expected = alloc::vec![
+        ("".to_owned(), "table"),
+        ("rows".to_owned(), "list"),
+        ("rows.*".to_owned(), "tuple"),
+        ("rows.*.0".to_owned(), "map"),
+        ("rows.*.0.*".to_owned(), "uint8"),
+        ("rows.*.1".to_owned(), "string"),
+        ("flags".to_owned(), "map"),
+        ("flags.*".to_owned(), "list"),
+        ("flags.*.*".to_owned(), "bool")
+    ];
+    assert_eq!(list_paths(&factory), expected);
+
+    // compiled schemas have the same paths
+    assert_eq!(list_paths(&NP_Factory::new_compiled(factory.compile_schema())), expected);
+
+    // any index or key in place of a wildcard leads to the same type
+    let mut buffer = factory.empty_buffer(None);
+    for x in factory.schema.paths() {
+        let path: Vec<&str> = x.path.iter().map(|step| if step == "*" { "2" } else { step.as_str() }).collect();
+        assert_eq!(factory.compile_path(&path)?.schema_addr, x.node.address());
+        let value = match x.node.type_key() {
+            NP_TypeKeys::Uint8 => NP_JSON::Integer(1),
+            NP_TypeKeys::UTF8String => NP_JSON::String("a".to_owned()),
+            NP_TypeKeys::Boolean => NP_JSON::True,
+            _ => continue
+        };
+        assert!(buffer.set_json(&path, &value)?);
+    }
+    assert_eq!(buffer.length(&["rows"])?, Some(3));
+
+    Ok(())
+}
+
+#[test]
+fn to_json_schema_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let schema = |np_schema: &str| -> Result<String, NP_Error> {
+        let json = NP_Factory::new(np_schema)?.schema.to_json_schema()?.stringify();
+        let header = r#"{"$schema":"http://json-schema.org/draft-07/schema#","#;
+        assert!(json.starts_with(header));
+        Ok(String::from("{") + &json[header.len()..])
+    };
+
+    // scalars
+    assert_eq!(schema(r#"{"type": "string", "size": 3}"#)?, r#"{"type":["string","null"],"maxLength":3}"#);
+    assert_eq!(schema(r#"{"type": "i8", "default": -2}"#)?, r#"{"type":"integer","minimum":-128,"maximum":127,"default":-2}"#);
+    assert_eq!(schema(r#"{"type": "u64"}"#)?, r#"{"type":["integer","null"],"minimum":0}"#);
+    assert_eq!(schema(r#"{"type": "bool", "default": true}"#)?, r#"{"type":"boolean","default":true}"#);
+    assert_eq!(schema(r#"{"type": "bytes", "size": 2}"#)?, r#"{"type":["array","null"],"items":{"type":"integer","minimum":0,"maximum":255},"maxItems":2}"#);
+    assert_eq!(schema(r#"{"type": "dec", "exp": 2, "default": 1.5}"#)?, r#"{"type":"object","properties":{"num":{"type":"integer"},"exp":{"const":2}},"required":["num","exp"],"default":{"num":150,"exp":2}}"#);
+    assert!(schema(r#"{"type": "uuid"}"#)?.contains(r#""pattern":"^[0-9A-F]{8}-"#));
+    assert_eq!(schema(r#"{"type": "option", "choices": ["a", "b"], "default": "b"}"#)?, r#"{"type":"string","enum":["a","b"],"default":"b"}"#);
+
+    // collections
+    assert_eq!(schema(r#"{"type": "list", "of": {"type": "bool"}}"#)?, r#"{"type":["array","null"],"items":{"type":["boolean","null"]}}"#);
+    assert_eq!(schema(r#"{"type": "map", "value": {"type": "float"}}"#)?, r#"{"type":["object","null"],"additionalProperties":{"type":["number","null"]}}"#);
+    assert_eq!(schema(r#"{"type": "tuple", "values": [{"type": "u8"}, {"type": "string"}]}"#)?, r#"{"type":["array","null"],"items":[{"type":["integer","null"],"minimum":0,"maximum":255},{"type":["string","null"]}],"additionalItems":false}"#);
+    assert_eq!(schema(r#"{"type": "table", "columns": [["a", {"type": "table", "columns": [["b", {"type": "bool"}]]}]]}"#)?,
+        r#"{"type":["object","null"],"properties":{"a":{"type":["object","null"],"properties":{"b":{"type":["boolean","null"]}},"additionalProperties":false}},"additionalProperties":false}"#);
+
+    Ok(())
+}
+
+#[test]
+fn from_json_schema_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let import = |json_schema: &str| -> Result<String, NP_Error> {
+        Ok(NP_Factory::from_json_schema(json_schema)?.export_schema()?.stringify())
+    };
+
+    // integers get the smallest type that holds their range
+    assert_eq!(import(r#"{"type": "integer", "minimum": 0, "maximum": 70000}"#)?, r#"{"type":"uint32"}"#);
+    assert_eq!(import(r#"{"type": ["integer", "null"], "minimum": -1, "maximum": 100}"#)?, r#"{"type":"int8"}"#);
+    assert_eq!(import(r#"{"type": "integer", "minimum": 0}"#)?, r#"{"type":"uint64"}"#);
+    assert_eq!(import(r#"{"type": "integer"}"#)?, r#"{"type":"int64"}"#);
+    assert_eq!(import(r#"{"type": "number", "default": 2}"#)?, r#"{"type":"double","default":2}"#);
+    assert_eq!(import(r#"{"type": "string", "format": "uuid"}"#)?, r#"{"type":"uuid"}"#);
+    assert_eq!(import(r#"{"enum": ["a", "b", null], "default": "b"}"#)?, r#"{"type":"option","default":"b","choices":["a","b"]}"#);
+    assert_eq!(import(r#"{"description": "anything"}"#)?, r#"{"type":"any"}"#);
+    assert_eq!(import(r#"{"type": "object", "additionalProperties": {"type": "boolean"}}"#)?, r#"{"type":"map","value":{"type":"bool"}}"#);
+    assert_eq!(import(r#"{"type": "array", "items": [{"type": "string"}, {"type": "boolean"}]}"#)?, r#"{"type":"tuple","values":[{"type":"string"},{"type":"bool"}]}"#);
+
+    // schemas from to_json_schema come back as the same types
+    let np_schema = r#"{"type":"table","columns":[["name",{"type":"string","size":10,"default":"x"}],["id",{"type":"uuid"}],["hash",{"type":"bytes","size":4}],["age",{"type":"uint8","default":20}],["offset",{"type":"int16"}],["price",{"type":"decimal","exp":2,"default":1.5}],["color",{"type":"option","choices":["red","blue"]}],["ok",{"type":"bool"}],["score",{"type":"double"}],["tags",{"type":"list","of":{"type":"string"}}],["meta",{"type":"map","value":{"type":"uint32"}}],["pair",{"type":"tuple","values":[{"type":"int8"},{"type":"bytes"}]}]]}"#;
+    let factory = NP_Factory::new(np_schema)?;
+    assert_eq!(import(&factory.schema.to_json_schema()?.stringify())?, factory.export_schema()?.stringify());
+
+    // except the types JSON Schema can't tell apart
+    let exported = NP_Factory::new(r#"{"type": "tuple", "values": [{"type": "float"}, {"type": "date"}, {"type": "ulid"}, {"type": "geo8"}]}"#)?.schema.to_json_schema()?.stringify();
+    assert_eq!(import(&exported)?, r#"{"type":"tuple","values":[{"type":"double"},{"type":"uint64"},{"type":"string"},{"type":"geo16"}]}"#);
+
+    // unsupported schemas
+    assert!(import(r##"{"$ref": "#/definitions/a"}"##).is_err());
+    assert!(import(r#"{"oneOf": [{"type": "string"}]}"#).is_err());
+    assert!(import(r#"{"type": ["string", "integer"]}"#).is_err());
+    assert!(import(r#"{"type": "array"}"#).is_err());
+    assert!(import(r#"{"type": "object"}"#).is_err());
+    assert!(import(r#"{"enum": [1, 2]}"#).is_err());
+    assert!(import(r#""string""#).is_err());
+
+    Ok(())
+}
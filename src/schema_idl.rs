@@ -0,0 +1,463 @@
+//! Compact text schema language
+//!
+//! Large nested JSON schemas get noisy quickly, the schema IDL describes the same schemas with less punctuation.  IDL schemas are converted into the normal JSON schema format, so every type and property works the same way.
+//!
+//! ```text
+//! table {
+//!     name: string(size: 20),           // properties go in parentheses
+//!     age: u8(default: 18),
+//!     tags: list<string>,
+//!     scores: map<dec(exp: 2)>,
+//!     point: tuple(sorted: true)<u32, u32>,
+//!     color: enum(choices: ["red", "green", "blue"]),
+//!     "text column": string,            // quote column names that aren't identifiers
+//!     meta: table {
+//!         created: date
+//!     }
+//! }
+//! ```
+//!
+//! - Types are written with the same names used by JSON schemas (`string`, `u8`, `geo4`, `uuid`...).
+//! - Any JSON schema property besides `type` can be provided in parentheses after the type name as `key: value`, values are JSON.
+//! - Tables list their columns in braces, lists and maps put their value type in angle brackets and tuples list all of their value types in angle brackets.
+//! - Commas between columns and types are required, a trailing comma is allowed.  Comments start with `//` and run to the end of the line.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory: NP_Factory = NP_Factory::from_idl(r#"
+//!     table {
+//!         name: string,
+//!         tags: list<string>,
+//!         age: u8(default: 18)
+//!     }
+//! "#)?;
+//!
+//! assert_eq!(factory.export_schema()?.stringify(), concat!(
+//!     r#"{"type":"table","columns":["#,
+//!     r#"["name",{"type":"string"}],"#,
+//!     r#"["tags",{"type":"list","of":{"type":"string"}}],"#,
+//!     r#"["age",{"type":"uint8","default":18}]"#,
+//!     r#"]}"#
+//! ));
+//!
+//! // and back into IDL
+//! assert_eq!(factory.export_idl()?, "table {\n    name: string,\n    tags: list<string>,\n    age: uint8(default: 18)\n}");
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::error::NP_Error;
+use crate::json_flex::{JSMAP, NP_JSON};
+use alloc::string::{String, ToString};
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+struct IDL_Parser {
+    chars: Vec<char>,
+    pos: usize
+}
+
+impl IDL_Parser {
+
+    fn error(&self, message: &str) -> NP_Error {
+        // report the line so typos are easy to find in long schemas
+        let line = self.chars[..self.pos.min(self.chars.len())].iter().filter(|c| **c == '\n').count() + 1;
+        let mut err = String::from("Schema IDL error on line ");
+        err.push_str(&line.to_string());
+        err.push_str(": ");
+        err.push_str(message);
+        NP_Error::Schema(err)
+    }
+
+    fn skip_space(&mut self) {
+        while self.pos < self.chars.len() {
+            let c = self.chars[self.pos];
+            if c.is_whitespace() {
+                self.pos += 1;
+            } else if c == '/' && self.chars.get(self.pos + 1) == Some(&'/') {
+                while self.pos < self.chars.len() && self.chars[self.pos] != '\n' {
+                    self.pos += 1;
+                }
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_space();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), NP_Error> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            let mut message = String::from("expected '");
+            message.push(c);
+            message.push('\'');
+            Err(self.error(&message))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, NP_Error> {
+        self.skip_space();
+        let start = self.pos;
+        while self.pos < self.chars.len() && (self.chars[self.pos].is_alphanumeric() || self.chars[self.pos] == '_') {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.error("expected a name"));
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn string(&mut self) -> Result<String, NP_Error> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            let c = match self.chars.get(self.pos) {
+                Some(c) => *c,
+                None => return Err(self.error("unterminated string"))
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(result),
+                '\\' => {
+                    let escaped = match self.chars.get(self.pos) {
+                        Some(c) => *c,
+                        None => return Err(self.error("unterminated string"))
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        'b' => result.push('\u{8}'),
+                        'f' => result.push('\u{c}'),
+                        'u' => {
+                            let hex: String = self.chars.iter().skip(self.pos).take(4).collect();
+                            self.pos += 4;
+                            match u32::from_str_radix(&hex, 16).ok().and_then(core::char::from_u32) {
+                                Some(x) => result.push(x),
+                                None => return Err(self.error("invalid unicode escape"))
+                            }
+                        },
+                        other => result.push(other)
+                    }
+                },
+                other => result.push(other)
+            }
+        }
+    }
+
+    /// A column name or property key, either an identifier or a quoted string
+    fn key(&mut self) -> Result<String, NP_Error> {
+        if self.peek() == Some('"') {
+            self.string()
+        } else {
+            self.ident()
+        }
+    }
+
+    fn value(&mut self) -> Result<NP_JSON, NP_Error> {
+        match self.peek() {
+            Some('"') => Ok(NP_JSON::String(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                while !self.eat(']') {
+                    items.push(self.value()?);
+                    if !self.eat(',') {
+                        self.expect(']')?;
+                        break;
+                    }
+                }
+                Ok(NP_JSON::Array(items))
+            },
+            Some('{') => {
+                self.pos += 1;
+                let mut object = JSMAP::new();
+                while !self.eat('}') {
+                    let key = self.key()?;
+                    self.expect(':')?;
+                    object.insert(key, self.value()?);
+                    if !self.eat(',') {
+                        self.expect('}')?;
+                        break;
+                    }
+                }
+                Ok(NP_JSON::Dictionary(object))
+            },
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                self.pos += 1;
+                while self.pos < self.chars.len() && (self.chars[self.pos].is_ascii_digit() || "+-.eE".contains(self.chars[self.pos])) {
+                    self.pos += 1;
+                }
+                let number: String = self.chars[start..self.pos].iter().collect();
+                if let Ok(x) = i64::from_str(&number) {
+                    return Ok(NP_JSON::Integer(x));
+                }
+                match f64::from_str(&number) {
+                    Ok(x) => Ok(NP_JSON::Float(x)),
+                    Err(_) => Err(self.error("invalid number"))
+                }
+            },
+            Some(_) => {
+                match self.ident()?.as_str() {
+                    "true" => Ok(NP_JSON::True),
+                    "false" => Ok(NP_JSON::False),
+                    "null" => Ok(NP_JSON::Null),
+                    _ => Err(self.error("expected a JSON value"))
+                }
+            },
+            None => Err(self.error("expected a JSON value"))
+        }
+    }
+
+    fn schema(&mut self) -> Result<NP_JSON, NP_Error> {
+
+        let kind = self.ident()?;
+
+        let mut schema = JSMAP::new();
+        schema.insert("type".to_owned(), NP_JSON::String(kind.clone()));
+
+        if self.eat('(') {
+            while !self.eat(')') {
+                let key = self.key()?;
+                self.expect(':')?;
+                let value = self.value()?;
+                schema.insert(key, value);
+                if !self.eat(',') {
+                    self.expect(')')?;
+                    break;
+                }
+            }
+        }
+
+        match kind.as_str() {
+            "table" => {
+                self.expect('{')?;
+                let mut columns = Vec::new();
+                while !self.eat('}') {
+                    let name = self.key()?;
+                    self.expect(':')?;
+                    columns.push(NP_JSON::Array(alloc::vec![NP_JSON::String(name), self.schema()?]));
+                    if !self.eat(',') {
+                        self.expect('}')?;
+                        break;
+                    }
+                }
+                schema.insert("columns".to_owned(), NP_JSON::Array(columns));
+            },
+            "list" | "map" => {
+                self.expect('<')?;
+                let of = self.schema()?;
+                self.expect('>')?;
+                schema.insert((if kind == "list" { "of" } else { "value" }).to_owned(), of);
+            },
+            "tuple" => {
+                self.expect('<')?;
+                let mut values = Vec::new();
+                while !self.eat('>') {
+                    values.push(self.schema()?);
+                    if !self.eat(',') {
+                        self.expect('>')?;
+                        break;
+                    }
+                }
+                schema.insert("values".to_owned(), NP_JSON::Array(values));
+            },
+            _ => { }
+        }
+
+        Ok(NP_JSON::Dictionary(schema))
+    }
+}
+
+/// Convert an IDL schema into the equivalent JSON schema
+///
+/// ```
+/// use no_proto::error::NP_Error;
+/// use no_proto::schema_idl;
+///
+/// let json = schema_idl::idl_to_json("list<dec(exp: 2)>")?;
+/// assert_eq!(json.stringify(), r#"{"type":"list","of":{"type":"dec","exp":2}}"#);
+///
+/// # Ok::<(), NP_Error>(())
+/// ```
+///
+pub fn idl_to_json(idl: &str) -> Result<NP_JSON, NP_Error> {
+    let mut parser = IDL_Parser { chars: idl.chars().collect(), pos: 0 };
+    let schema = parser.schema()?;
+    if parser.peek().is_some() {
+        return Err(parser.error("unexpected text after schema"));
+    }
+    Ok(schema)
+}
+
+fn is_ident(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn write_key(key: &str, out: &mut String) {
+    if is_ident(key) {
+        out.push_str(key);
+    } else {
+        out.push_str(&NP_JSON::String(key.to_owned()).stringify());
+    }
+}
+
+fn write_schema(schema: &NP_JSON, indent: usize, out: &mut String) -> Result<(), NP_Error> {
+
+    let map = match schema {
+        NP_JSON::Dictionary(map) => map,
+        _ => return Err(NP_Error::Schema("Schema IDL can only be generated from schema objects!".into()))
+    };
+
+    let kind = match map.get("type") {
+        Some(NP_JSON::String(kind)) if is_ident(kind) => kind.as_str(),
+        Some(NP_JSON::String(kind)) => {
+            let mut err = String::from("Schema IDL can't represent type name: ");
+            err.push_str(kind);
+            return Err(NP_Error::SchemaMismatch(err));
+        },
+        _ => return Err(NP_Error::Schema("Schema IDL needs a string 'type' property!".into()))
+    };
+
+    out.push_str(kind);
+
+    let body_key = match kind {
+        "table" => "columns",
+        "list" => "of",
+        "map" => "value",
+        "tuple" => "values",
+        _ => ""
+    };
+
+    let props: Vec<&(String, NP_JSON)> = map.values.iter().filter(|(key, _value)| key != "type" && key != body_key).collect();
+
+    if !props.is_empty() {
+        out.push('(');
+        for (index, (key, value)) in props.iter().enumerate() {
+            if index > 0 {
+                out.push_str(", ");
+            }
+            write_key(key, out);
+            out.push_str(": ");
+            out.push_str(&value.stringify());
+        }
+        out.push(')');
+    }
+
+    match kind {
+        "table" => {
+            let columns = match map.get("columns") {
+                Some(NP_JSON::Array(columns)) => columns,
+                _ => return Err(NP_Error::Schema("Table schemas need a 'columns' array!".into()))
+            };
+            if props.is_empty() {
+                out.push(' ');
+            }
+            if columns.is_empty() {
+                out.push_str("{}");
+                return Ok(());
+            }
+            out.push_str("{\n");
+            for (index, column) in columns.iter().enumerate() {
+                let name = match &column[0] {
+                    NP_JSON::String(name) => name,
+                    _ => return Err(NP_Error::Schema("Table columns need a string name!".into()))
+                };
+                for _ in 0..(indent + 1) {
+                    out.push_str("    ");
+                }
+                write_key(name, out);
+                out.push_str(": ");
+                write_schema(&column[1], indent + 1, out)?;
+                if index + 1 < columns.len() {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            for _ in 0..indent {
+                out.push_str("    ");
+            }
+            out.push('}');
+        },
+        "list" | "map" => {
+            out.push('<');
+            write_schema(&map.get(body_key).unwrap_or(&NP_JSON::Null), indent, out)?;
+            out.push('>');
+        },
+        "tuple" => {
+            let values = match map.get("values") {
+                Some(NP_JSON::Array(values)) => values,
+                _ => return Err(NP_Error::Schema("Tuple schemas need a 'values' array!".into()))
+            };
+            out.push('<');
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                write_schema(value, indent, out)?;
+            }
+            out.push('>');
+        },
+        _ => { }
+    }
+
+    Ok(())
+}
+
+/// Convert a JSON schema into IDL, tables put each column on it's own line
+pub fn json_to_idl(schema: &NP_JSON) -> Result<String, NP_Error> {
+    let mut out = String::new();
+    write_schema(schema, 0, &mut out)?;
+    Ok(out)
+}
+
+#[test]
+fn schema_idl_round_trip_works() -> Result<(), NP_Error> {
+    let idl = r#"
+        // every kind of schema
+        table {
+            name: string(size: 20, default: "Bob \"B\""),
+            tags: list<string>,
+            scores: map<dec(exp: 2)>,
+            point: tuple(sorted: true)<u32, u32,>,
+            color: enum(choices: ["red", "green"]),
+            "text column": geo4,
+            meta: table { created: date },
+        }
+    "#;
+
+    let factory = crate::NP_Factory::from_idl(idl)?;
+    let exported = factory.export_idl()?;
+    let factory2 = crate::NP_Factory::from_idl(&exported)?;
+
+    assert_eq!(factory.export_schema()?.stringify(), factory2.export_schema()?.stringify());
+    assert_eq!(factory2.export_idl()?, exported);
+    assert!(exported.contains("\"text column\": geo4,"));
+    assert!(exported.contains("point: tuple(sorted: true)<uint32, uint32>,"));
+
+    assert!(idl_to_json("table { name string }").is_err());
+    assert!(idl_to_json("list<string> extra").is_err());
+    assert!(idl_to_json("table {\n name: string(size 2) }").unwrap_err().message().contains("line 2"));
+
+    Ok(())
+}
@@ -0,0 +1,1028 @@
+//! Serde integration, enabled with the `serde` feature.
+//!
+//! Any type that implements `Serialize` can be written into a buffer with `to_buffer`, and any type that implements `Deserialize` can be read out of one with `from_buffer`.  The buffer schema decides how each value is stored, so the Rust types only need to be compatible with the schema, not identical to it.
+//!
+//! - Structs and maps are written into `table` or `map` types.  Fields without a value in the buffer are left out when reading, so use `Option` or `#[serde(default)]` for fields that might be missing.
+//! - Sequences and tuples are written into `list` or `tuple` types.  A sequence can also be written into a `bytes` type.
+//! - Numbers are converted into the number type in the schema, including `dec` and `date`.  Values that don't fit the schema type fail with an error.
+//! - Strings can be written into `string` or `option` types, unit enum variants are written as strings.
+//! - `geo` types are read and written as a struct or map with `lat` and `lng` fields.
+//! - `None` clears the value at that location.
+//!
+//! Writes and reads begin at the current buffer cursor.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use serde::{Serialize, Deserialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct User {
+//!     name: String,
+//!     age: u16,
+//!     email: Option<String>,
+//!     tags: Vec<String>
+//! }
+//!
+//! let factory: NP_Factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name",  {"type": "string"}],
+//!         ["age",   {"type": "u16"}],
+//!         ["email", {"type": "string"}],
+//!         ["tags",  {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! let user = User {
+//!     name: String::from("Billy Joel"),
+//!     age: 71,
+//!     email: None,
+//!     tags: vec![String::from("piano")]
+//! };
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! no_proto::serde::to_buffer(&user, &mut new_buffer)?;
+//!
+//! assert_eq!(new_buffer.get::<&str>(&["tags", "0"])?, Some("piano"));
+//!
+//! let user_copy: User = no_proto::serde::from_buffer(&new_buffer)?;
+//! assert_eq!(user_copy, user);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::schema::NP_Parsed_Schema;
+use crate::json_flex::NP_JSON;
+use crate::pointer::dec::NP_Dec;
+use crate::pointer::geo::NP_Geo;
+use crate::pointer::date::NP_Date;
+use crate::pointer::option::NP_Enum;
+use crate::pointer::uuid::NP_UUID;
+use crate::pointer::ulid::NP_ULID;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::borrow::ToOwned;
+use core::convert::TryFrom;
+use core::fmt::Display;
+use ::serde::{ser, de, Serialize, Deserialize};
+use ::serde::de::IntoDeserializer;
+
+impl ser::Error for NP_Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        NP_Error::new(msg.to_string())
+    }
+}
+
+impl de::Error for NP_Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        NP_Error::new(msg.to_string())
+    }
+}
+
+// with `std` this is `std::error::Error`, which `error.rs` implements
+#[cfg(not(feature = "std"))]
+impl ser::StdError for NP_Error {}
+
+/// Write any `Serialize` type into the buffer, starting at the buffer cursor.
+///
+/// Check out documentation [here](./index.html).
+///
+pub fn to_buffer<T>(value: &T, buffer: &mut NP_Buffer) -> Result<(), NP_Error> where T: ?Sized + Serialize {
+    value.serialize(&mut NP_Serializer::new(buffer))
+}
+
+/// Read any `Deserialize` type out of the buffer, starting at the buffer cursor.
+///
+/// Check out documentation [here](./index.html).
+///
+pub fn from_buffer<'de, T>(buffer: &'de NP_Buffer) -> Result<T, NP_Error> where T: Deserialize<'de> {
+    T::deserialize(NP_Deserializer::new(buffer))
+}
+
+fn path_refs(path: &Vec<String>) -> Vec<&str> {
+    path.iter().map(|x| x.as_str()).collect()
+}
+
+fn type_error(found: &str, path: &Vec<String>, schema: &NP_Parsed_Schema) -> NP_Error {
+    let mut err = "SerdeError: Attempted to use value of type (".to_owned();
+    err.push_str(found);
+    err.push_str(") for schema of type (");
+    err.push_str(schema.get_type_data().0);
+    err.push_str(") at path (");
+    err.push_str(&path.join("."));
+    err.push_str(")\n");
+    NP_Error::SchemaMismatch(err)
+}
+
+fn range_error(path: &Vec<String>, schema: &NP_Parsed_Schema) -> NP_Error {
+    let mut err = "SerdeError: Value out of range for schema of type (".to_owned();
+    err.push_str(schema.get_type_data().0);
+    err.push_str(") at path (");
+    err.push_str(&path.join("."));
+    err.push_str(")\n");
+    NP_Error::InvalidValue(err)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Number {
+    Int(i64),
+    Uint(u64),
+    Float(f64)
+}
+
+impl Number {
+    fn as_i64(self) -> Option<i64> {
+        match self {
+            Number::Int(x) => Some(x),
+            Number::Uint(x) => i64::try_from(x).ok(),
+            Number::Float(_) => None
+        }
+    }
+
+    fn as_u64(self) -> Option<u64> {
+        match self {
+            Number::Int(x) => u64::try_from(x).ok(),
+            Number::Uint(x) => Some(x),
+            Number::Float(_) => None
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(x) => x as f64,
+            Number::Uint(x) => x as f64,
+            Number::Float(x) => x
+        }
+    }
+}
+
+/// Serde serializer that writes into a buffer.
+///
+/// Check out documentation [here](./index.html).
+///
+pub struct NP_Serializer<'ser, 'buffer> {
+    buffer: &'ser mut NP_Buffer<'buffer>,
+    path: Vec<String>
+}
+
+impl<'ser, 'buffer> NP_Serializer<'ser, 'buffer> {
+    /// Create a new serializer for the provided buffer
+    pub fn new(buffer: &'ser mut NP_Buffer<'buffer>) -> Self {
+        NP_Serializer { buffer, path: Vec::new() }
+    }
+
+    fn schema(&self) -> Result<&'buffer NP_Parsed_Schema, NP_Error> {
+        match self.buffer.schema_addr_at(&path_refs(&self.path)) {
+            Some(addr) => Ok(&self.buffer.parsed_schema()[addr]),
+            None => {
+                let mut err = "SerdeError: No schema found at path (".to_owned();
+                err.push_str(&self.path.join("."));
+                err.push_str(")\n");
+                Err(NP_Error::BadPath(err))
+            }
+        }
+    }
+
+    fn write_number(&mut self, value: Number) -> Result<(), NP_Error> {
+        let schema = self.schema()?;
+        let path = path_refs(&self.path);
+
+        macro_rules! write_int {
+            ($t: ty, $conv: ident) => {{
+                let x = value.$conv().and_then(|x| <$t>::try_from(x).ok());
+                match x {
+                    Some(x) => { self.buffer.set(&path, x)?; },
+                    None => return Err(range_error(&self.path, schema))
+                }
+            }}
+        }
+
+        match schema {
+            NP_Parsed_Schema::Int8   { .. } => write_int!(i8,  as_i64),
+            NP_Parsed_Schema::Int16  { .. } => write_int!(i16, as_i64),
+            NP_Parsed_Schema::Int32  { .. } => write_int!(i32, as_i64),
+            NP_Parsed_Schema::Int64  { .. } => write_int!(i64, as_i64),
+            NP_Parsed_Schema::Uint8  { .. } => write_int!(u8,  as_u64),
+            NP_Parsed_Schema::Uint16 { .. } => write_int!(u16, as_u64),
+            NP_Parsed_Schema::Uint32 { .. } => write_int!(u32, as_u64),
+            NP_Parsed_Schema::Uint64 { .. } => write_int!(u64, as_u64),
+            NP_Parsed_Schema::Date   { .. } => {
+                match value.as_u64() {
+                    Some(x) => { self.buffer.set(&path, NP_Date::new(x))?; },
+                    None => return Err(range_error(&self.path, schema))
+                }
+            },
+            NP_Parsed_Schema::Float  { .. } => { self.buffer.set(&path, value.as_f64() as f32)?; },
+            NP_Parsed_Schema::Double { .. } => { self.buffer.set(&path, value.as_f64())?; },
+            NP_Parsed_Schema::Decimal { exp, .. } => {
+                let mut dec: NP_Dec = match value {
+                    Number::Float(x) => x.into(),
+                    _ => match value.as_i64() {
+                        Some(x) => NP_Dec::new(x, 0),
+                        None => return Err(range_error(&self.path, schema))
+                    }
+                };
+                dec.shift_exp(*exp);
+                self.buffer.set(&path, dec)?;
+            },
+            _ => return Err(type_error("number", &self.path, schema))
+        }
+
+        Ok(())
+    }
+
+    fn write_str(&mut self, value: &str) -> Result<(), NP_Error> {
+        let schema = self.schema()?;
+        let path = path_refs(&self.path);
+
+        match schema {
+            NP_Parsed_Schema::UTF8String { .. } => { self.buffer.set(&path, value)?; },
+            NP_Parsed_Schema::Enum { .. } => { self.buffer.set(&path, NP_Enum::new(value))?; },
+            _ => return Err(type_error("string", &self.path, schema))
+        }
+
+        Ok(())
+    }
+
+    fn compound<'a>(&'a mut self, variant: Option<&'static str>) -> Result<NP_Compound<'a, 'ser, 'buffer>, NP_Error> {
+        if let Some(name) = variant {
+            self.path.push(name.to_owned());
+        }
+
+        let mode = match self.schema()? {
+            NP_Parsed_Schema::Bytes { .. } => Compound_Mode::Bytes(Vec::new()),
+            NP_Parsed_Schema::Geo { size, .. } => Compound_Mode::Geo(*size, 0.0, 0.0),
+            NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Map { .. } | NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Tuple { .. } => Compound_Mode::Collection,
+            schema => return Err(type_error("collection", &self.path, schema))
+        };
+
+        Ok(NP_Compound { ser: self, mode, index: 0, key: None, variant: variant.is_some() })
+    }
+}
+
+impl<'a, 'ser, 'buffer> ser::Serializer for &'a mut NP_Serializer<'ser, 'buffer> {
+    type Ok = ();
+    type Error = NP_Error;
+
+    type SerializeSeq = NP_Compound<'a, 'ser, 'buffer>;
+    type SerializeTuple = NP_Compound<'a, 'ser, 'buffer>;
+    type SerializeTupleStruct = NP_Compound<'a, 'ser, 'buffer>;
+    type SerializeTupleVariant = NP_Compound<'a, 'ser, 'buffer>;
+    type SerializeMap = NP_Compound<'a, 'ser, 'buffer>;
+    type SerializeStruct = NP_Compound<'a, 'ser, 'buffer>;
+    type SerializeStructVariant = NP_Compound<'a, 'ser, 'buffer>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), NP_Error> {
+        let schema = self.schema()?;
+        match schema {
+            NP_Parsed_Schema::Boolean { .. } => {
+                self.buffer.set(&path_refs(&self.path), v)?;
+                Ok(())
+            },
+            _ => Err(type_error("bool", &self.path, schema))
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), NP_Error> { self.write_number(Number::Int(v as i64)) }
+    fn serialize_i16(self, v: i16) -> Result<(), NP_Error> { self.write_number(Number::Int(v as i64)) }
+    fn serialize_i32(self, v: i32) -> Result<(), NP_Error> { self.write_number(Number::Int(v as i64)) }
+    fn serialize_i64(self, v: i64) -> Result<(), NP_Error> { self.write_number(Number::Int(v)) }
+    fn serialize_u8(self, v: u8) -> Result<(), NP_Error> { self.write_number(Number::Uint(v as u64)) }
+    fn serialize_u16(self, v: u16) -> Result<(), NP_Error> { self.write_number(Number::Uint(v as u64)) }
+    fn serialize_u32(self, v: u32) -> Result<(), NP_Error> { self.write_number(Number::Uint(v as u64)) }
+    fn serialize_u64(self, v: u64) -> Result<(), NP_Error> { self.write_number(Number::Uint(v)) }
+    fn serialize_f32(self, v: f32) -> Result<(), NP_Error> { self.write_number(Number::Float(v as f64)) }
+    fn serialize_f64(self, v: f64) -> Result<(), NP_Error> { self.write_number(Number::Float(v)) }
+
+    fn serialize_char(self, v: char) -> Result<(), NP_Error> {
+        let mut bytes = [0u8; 4];
+        self.write_str(v.encode_utf8(&mut bytes))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), NP_Error> {
+        self.write_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), NP_Error> {
+        let schema = self.schema()?;
+        match schema {
+            NP_Parsed_Schema::Bytes { .. } => {
+                self.buffer.set(&path_refs(&self.path), v)?;
+                Ok(())
+            },
+            _ => Err(type_error("bytes", &self.path, schema))
+        }
+    }
+
+    fn serialize_none(self) -> Result<(), NP_Error> {
+        self.buffer.del(&path_refs(&self.path))?;
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), NP_Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), NP_Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<(), NP_Error> {
+        self.write_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize {
+        self.path.push(variant.to_owned());
+        value.serialize(&mut *self)?;
+        self.path.pop();
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, NP_Error> {
+        self.compound(None)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, NP_Error> {
+        self.compound(None)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, NP_Error> {
+        self.compound(None)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, NP_Error> {
+        self.compound(Some(variant))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, NP_Error> {
+        self.compound(None)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, NP_Error> {
+        self.compound(None)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, NP_Error> {
+        self.compound(Some(variant))
+    }
+}
+
+#[derive(Debug)]
+enum Compound_Mode {
+    Collection,
+    Bytes(Vec<u8>),
+    Geo(u8, f64, f64)
+}
+
+/// Serializer state for collections
+#[doc(hidden)]
+pub struct NP_Compound<'a, 'ser, 'buffer> {
+    ser: &'a mut NP_Serializer<'ser, 'buffer>,
+    mode: Compound_Mode,
+    index: usize,
+    key: Option<String>,
+    variant: bool
+}
+
+impl<'a, 'ser, 'buffer> NP_Compound<'a, 'ser, 'buffer> {
+
+    fn element<T>(&mut self, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize {
+        match &mut self.mode {
+            Compound_Mode::Collection => {
+                self.ser.path.push(self.index.to_string());
+                value.serialize(&mut *self.ser)?;
+                self.ser.path.pop();
+            },
+            Compound_Mode::Bytes(bytes) => {
+                match value.serialize(Capture)?.as_number().and_then(|x| x.as_u64()).and_then(|x| u8::try_from(x).ok()) {
+                    Some(x) => bytes.push(x),
+                    None => return Err(range_error(&self.ser.path, self.ser.schema()?))
+                }
+            },
+            Compound_Mode::Geo(_, _, _) => {
+                return Err(type_error("sequence", &self.ser.path, self.ser.schema()?));
+            }
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    fn field<T>(&mut self, key: String, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize {
+        match &mut self.mode {
+            Compound_Mode::Collection => {
+                self.ser.path.push(key);
+                value.serialize(&mut *self.ser)?;
+                self.ser.path.pop();
+            },
+            Compound_Mode::Geo(_, lat, lng) => {
+                let coord = match value.serialize(Capture)?.as_number() {
+                    Some(x) => x.as_f64(),
+                    None => return Err(type_error("geo", &self.ser.path, self.ser.schema()?))
+                };
+                match key.as_str() {
+                    "lat" => *lat = coord,
+                    "lng" => *lng = coord,
+                    _ => return Err(type_error("geo", &self.ser.path, self.ser.schema()?))
+                }
+            },
+            Compound_Mode::Bytes(_) => {
+                return Err(type_error("map", &self.ser.path, self.ser.schema()?));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), NP_Error> {
+        let path = path_refs(&self.ser.path);
+        match self.mode {
+            Compound_Mode::Collection => { },
+            Compound_Mode::Bytes(bytes) => { self.ser.buffer.set(&path, bytes.as_slice())?; },
+            Compound_Mode::Geo(size, lat, lng) => { self.ser.buffer.set(&path, NP_Geo::new(size, lat, lng))?; }
+        }
+        if self.variant {
+            self.ser.path.pop();
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'ser, 'buffer> ser::SerializeSeq for NP_Compound<'a, 'ser, 'buffer> {
+    type Ok = ();
+    type Error = NP_Error;
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize { self.element(value) }
+    fn end(self) -> Result<(), NP_Error> { self.finish() }
+}
+
+impl<'a, 'ser, 'buffer> ser::SerializeTuple for NP_Compound<'a, 'ser, 'buffer> {
+    type Ok = ();
+    type Error = NP_Error;
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize { self.element(value) }
+    fn end(self) -> Result<(), NP_Error> { self.finish() }
+}
+
+impl<'a, 'ser, 'buffer> ser::SerializeTupleStruct for NP_Compound<'a, 'ser, 'buffer> {
+    type Ok = ();
+    type Error = NP_Error;
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize { self.element(value) }
+    fn end(self) -> Result<(), NP_Error> { self.finish() }
+}
+
+impl<'a, 'ser, 'buffer> ser::SerializeTupleVariant for NP_Compound<'a, 'ser, 'buffer> {
+    type Ok = ();
+    type Error = NP_Error;
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize { self.element(value) }
+    fn end(self) -> Result<(), NP_Error> { self.finish() }
+}
+
+impl<'a, 'ser, 'buffer> ser::SerializeMap for NP_Compound<'a, 'ser, 'buffer> {
+    type Ok = ();
+    type Error = NP_Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize {
+        self.key = Some(match key.serialize(Capture)? {
+            Captured::Str(x) => x,
+            Captured::Bool(x) => x.to_string(),
+            Captured::Number(Number::Int(x)) => x.to_string(),
+            Captured::Number(Number::Uint(x)) => x.to_string(),
+            Captured::Number(Number::Float(_)) => return Err(NP_Error::Parse("SerdeError: Map keys must be strings or integers!".into()))
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize {
+        match self.key.take() {
+            Some(key) => self.field(key, value),
+            None => Err(NP_Error::Parse("SerdeError: Map value serialized before key!".into()))
+        }
+    }
+
+    fn end(self) -> Result<(), NP_Error> { self.finish() }
+}
+
+impl<'a, 'ser, 'buffer> ser::SerializeStruct for NP_Compound<'a, 'ser, 'buffer> {
+    type Ok = ();
+    type Error = NP_Error;
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize { self.field(key.to_owned(), value) }
+    fn end(self) -> Result<(), NP_Error> { self.finish() }
+}
+
+impl<'a, 'ser, 'buffer> ser::SerializeStructVariant for NP_Compound<'a, 'ser, 'buffer> {
+    type Ok = ();
+    type Error = NP_Error;
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), NP_Error> where T: ?Sized + Serialize { self.field(key.to_owned(), value) }
+    fn end(self) -> Result<(), NP_Error> { self.finish() }
+}
+
+// Used to capture map keys, geo coordinates and bytes, which are scalar values that aren't written at a path of their own
+enum Captured {
+    Bool(bool),
+    Number(Number),
+    Str(String)
+}
+
+impl Captured {
+    fn as_number(self) -> Option<Number> {
+        match self {
+            Captured::Number(x) => Some(x),
+            _ => None
+        }
+    }
+}
+
+struct Capture;
+
+fn capture_error() -> NP_Error {
+    NP_Error::Parse("SerdeError: Expected a string or number value!".into())
+}
+
+impl ser::Serializer for Capture {
+    type Ok = Captured;
+    type Error = NP_Error;
+
+    type SerializeSeq = ser::Impossible<Captured, NP_Error>;
+    type SerializeTuple = ser::Impossible<Captured, NP_Error>;
+    type SerializeTupleStruct = ser::Impossible<Captured, NP_Error>;
+    type SerializeTupleVariant = ser::Impossible<Captured, NP_Error>;
+    type SerializeMap = ser::Impossible<Captured, NP_Error>;
+    type SerializeStruct = ser::Impossible<Captured, NP_Error>;
+    type SerializeStructVariant = ser::Impossible<Captured, NP_Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Captured, NP_Error> { Ok(Captured::Bool(v)) }
+    fn serialize_i8(self, v: i8) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Int(v as i64))) }
+    fn serialize_i16(self, v: i16) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Int(v as i64))) }
+    fn serialize_i32(self, v: i32) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Int(v as i64))) }
+    fn serialize_i64(self, v: i64) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Int(v))) }
+    fn serialize_u8(self, v: u8) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Uint(v as u64))) }
+    fn serialize_u16(self, v: u16) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Uint(v as u64))) }
+    fn serialize_u32(self, v: u32) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Uint(v as u64))) }
+    fn serialize_u64(self, v: u64) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Uint(v))) }
+    fn serialize_f32(self, v: f32) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Float(v as f64))) }
+    fn serialize_f64(self, v: f64) -> Result<Captured, NP_Error> { Ok(Captured::Number(Number::Float(v))) }
+    fn serialize_char(self, v: char) -> Result<Captured, NP_Error> { Ok(Captured::Str(v.to_string())) }
+    fn serialize_str(self, v: &str) -> Result<Captured, NP_Error> { Ok(Captured::Str(v.to_owned())) }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Captured, NP_Error> { Err(capture_error()) }
+    fn serialize_none(self) -> Result<Captured, NP_Error> { Err(capture_error()) }
+    fn serialize_some<T>(self, value: &T) -> Result<Captured, NP_Error> where T: ?Sized + Serialize { value.serialize(self) }
+    fn serialize_unit(self) -> Result<Captured, NP_Error> { Err(capture_error()) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Captured, NP_Error> { Err(capture_error()) }
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Captured, NP_Error> { Ok(Captured::Str(variant.to_owned())) }
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Captured, NP_Error> where T: ?Sized + Serialize { value.serialize(self) }
+    fn serialize_newtype_variant<T>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<Captured, NP_Error> where T: ?Sized + Serialize { Err(capture_error()) }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, NP_Error> { Err(capture_error()) }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, NP_Error> { Err(capture_error()) }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, NP_Error> { Err(capture_error()) }
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, NP_Error> { Err(capture_error()) }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, NP_Error> { Err(capture_error()) }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, NP_Error> { Err(capture_error()) }
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, NP_Error> { Err(capture_error()) }
+}
+
+/// Serde deserializer that reads from a buffer.
+///
+/// Check out documentation [here](./index.html).
+///
+pub struct NP_Deserializer<'de, 'buffer> {
+    buffer: &'de NP_Buffer<'buffer>,
+    path: Vec<String>
+}
+
+impl<'de, 'buffer> NP_Deserializer<'de, 'buffer> {
+    /// Create a new deserializer for the provided buffer
+    pub fn new(buffer: &'de NP_Buffer<'buffer>) -> Self {
+        NP_Deserializer { buffer, path: Vec::new() }
+    }
+
+    fn child(&self, key: String) -> Self {
+        let mut path = self.path.clone();
+        path.push(key);
+        NP_Deserializer { buffer: self.buffer, path }
+    }
+
+    fn schema(&self) -> Result<&'buffer NP_Parsed_Schema, NP_Error> {
+        match self.buffer.schema_addr_at(&path_refs(&self.path)) {
+            Some(addr) => Ok(&self.buffer.parsed_schema()[addr]),
+            None => {
+                let mut err = "SerdeError: No schema found at path (".to_owned();
+                err.push_str(&self.path.join("."));
+                err.push_str(")\n");
+                Err(NP_Error::BadPath(err))
+            }
+        }
+    }
+
+    // does this location have a value (or a default value from the schema)
+    fn present(&self) -> Result<bool, NP_Error> {
+        let path = path_refs(&self.path);
+        match self.schema()? {
+            NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Tuple { .. } => Ok(true),
+            NP_Parsed_Schema::List { .. } | NP_Parsed_Schema::Map { .. } => Ok(self.buffer.length(&path)?.is_some()),
+            _ => match self.buffer.json_encode(&path)? {
+                NP_JSON::Null => Ok(false),
+                _ => Ok(true)
+            }
+        }
+    }
+
+    fn map_keys(&self) -> Result<Vec<String>, NP_Error> {
+        let path = path_refs(&self.path);
+        let mut keys: Vec<String> = Vec::new();
+        if let Some(iter) = self.buffer.get_iter(&path)? {
+            for item in iter {
+                if item.has_value() {
+                    keys.push(item.key.to_owned());
+                }
+            }
+        }
+        Ok(keys)
+    }
+}
+
+impl<'de, 'buffer> de::Deserializer<'de> for NP_Deserializer<'de, 'buffer> {
+    type Error = NP_Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        let buffer = self.buffer;
+        let path_strs = path_refs(&self.path);
+        let path = path_strs.as_slice();
+
+        macro_rules! visit_scalar {
+            ($t: ty, $visit: ident) => {
+                match buffer.get::<$t>(path)? {
+                    Some(x) => visitor.$visit(x),
+                    None => visitor.visit_none()
+                }
+            }
+        }
+
+        match self.schema()? {
+            NP_Parsed_Schema::Table { columns, .. } => {
+                visitor.visit_map(NP_Table_Access { de: &self, columns, index: 0 })
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                visitor.visit_seq(NP_Seq_Access { de: &self, length: values.len(), index: 0 })
+            },
+            NP_Parsed_Schema::List { .. } => {
+                let length = buffer.length(path)?.unwrap_or(0);
+                visitor.visit_seq(NP_Seq_Access { de: &self, length, index: 0 })
+            },
+            NP_Parsed_Schema::Map { .. } => {
+                let keys = self.map_keys()?;
+                visitor.visit_map(NP_Map_Access { de: &self, keys, index: 0 })
+            },
+            NP_Parsed_Schema::UTF8String { .. } => visit_scalar!(&'de str, visit_borrowed_str),
+            NP_Parsed_Schema::Bytes { .. } => visit_scalar!(&'de [u8], visit_borrowed_bytes),
+            NP_Parsed_Schema::Int8 { .. } => visit_scalar!(i8, visit_i8),
+            NP_Parsed_Schema::Int16 { .. } => visit_scalar!(i16, visit_i16),
+            NP_Parsed_Schema::Int32 { .. } => visit_scalar!(i32, visit_i32),
+            NP_Parsed_Schema::Int64 { .. } => visit_scalar!(i64, visit_i64),
+            NP_Parsed_Schema::Uint8 { .. } => visit_scalar!(u8, visit_u8),
+            NP_Parsed_Schema::Uint16 { .. } => visit_scalar!(u16, visit_u16),
+            NP_Parsed_Schema::Uint32 { .. } => visit_scalar!(u32, visit_u32),
+            NP_Parsed_Schema::Uint64 { .. } => visit_scalar!(u64, visit_u64),
+            NP_Parsed_Schema::Float { .. } => visit_scalar!(f32, visit_f32),
+            NP_Parsed_Schema::Double { .. } => visit_scalar!(f64, visit_f64),
+            NP_Parsed_Schema::Boolean { .. } => visit_scalar!(bool, visit_bool),
+            NP_Parsed_Schema::Decimal { .. } => {
+                match buffer.get::<NP_Dec>(path)? {
+                    Some(x) => visitor.visit_f64(x.to_float()),
+                    None => visitor.visit_none()
+                }
+            },
+            NP_Parsed_Schema::Date { .. } => {
+                match buffer.get::<NP_Date>(path)? {
+                    Some(x) => visitor.visit_u64(x.value),
+                    None => visitor.visit_none()
+                }
+            },
+            NP_Parsed_Schema::Geo { .. } => {
+                match buffer.get::<NP_Geo>(path)? {
+                    Some(x) => {
+                        let coords: Vec<(&str, f64)> = alloc::vec![("lat", x.lat), ("lng", x.lng)];
+                        visitor.visit_map(de::value::MapDeserializer::new(coords.into_iter()))
+                    },
+                    None => visitor.visit_none()
+                }
+            },
+            NP_Parsed_Schema::Enum { .. } => {
+                match buffer.get::<NP_Enum>(path)? {
+                    Some(NP_Enum::Some(x)) => visitor.visit_string(x),
+                    _ => visitor.visit_none()
+                }
+            },
+            NP_Parsed_Schema::Uuid { .. } => {
+                match buffer.get::<&NP_UUID>(path)? {
+                    Some(x) => visitor.visit_string(x.to_string()),
+                    None => visitor.visit_none()
+                }
+            },
+            NP_Parsed_Schema::Ulid { .. } => {
+                match buffer.get::<&NP_ULID>(path)? {
+                    Some(x) => visitor.visit_string(x.to_string()),
+                    None => visitor.visit_none()
+                }
+            },
+            NP_Parsed_Schema::Any { .. } | NP_Parsed_Schema::None => visitor.visit_unit()
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        if self.present()? {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        match self.schema()? {
+            NP_Parsed_Schema::Bytes { .. } => {
+                let bytes = self.buffer.get::<&'de [u8]>(&path_refs(&self.path))?.unwrap_or(&[]);
+                de::Deserializer::deserialize_any(de::value::SeqDeserializer::<_, NP_Error>::new(bytes.iter().cloned()), visitor)
+            },
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(self, _name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        let path = path_refs(&self.path);
+        match self.schema()? {
+            NP_Parsed_Schema::Enum { .. } => {
+                match self.buffer.get::<NP_Enum>(&path)? {
+                    Some(NP_Enum::Some(x)) => visitor.visit_enum(x.into_deserializer()),
+                    _ => Err(NP_Error::Parse("SerdeError: No enum value found!".into()))
+                }
+            },
+            NP_Parsed_Schema::UTF8String { .. } => {
+                match self.buffer.get::<&str>(&path)? {
+                    Some(x) => visitor.visit_enum(x.into_deserializer()),
+                    None => Err(NP_Error::Parse("SerdeError: No enum value found!".into()))
+                }
+            },
+            NP_Parsed_Schema::Table { .. } | NP_Parsed_Schema::Map { .. } => {
+                for variant in variants {
+                    let child = self.child((*variant).to_owned());
+                    if self.buffer.schema_addr_at(&path_refs(&child.path)).is_some() && child.present()? {
+                        return visitor.visit_enum(NP_Variant_Access { de: child, variant });
+                    }
+                }
+                Err(NP_Error::Parse("SerdeError: No enum value found!".into()))
+            },
+            schema => Err(type_error("enum", &self.path, schema))
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        visitor.visit_unit()
+    }
+
+    ::serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf tuple tuple_struct map struct identifier
+    }
+}
+
+struct NP_Table_Access<'a, 'de, 'buffer> {
+    de: &'a NP_Deserializer<'de, 'buffer>,
+    columns: &'buffer Vec<(u8, String, usize)>,
+    index: usize
+}
+
+impl<'a, 'de, 'buffer> de::MapAccess<'de> for NP_Table_Access<'a, 'de, 'buffer> {
+    type Error = NP_Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, NP_Error> where K: de::DeserializeSeed<'de> {
+        // skip columns without values
+        while self.index < self.columns.len() {
+            let column = &self.columns[self.index];
+            if self.de.child(column.1.clone()).present()? {
+                return seed.deserialize(column.1.as_str().into_deserializer()).map(Some);
+            }
+            self.index += 1;
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, NP_Error> where V: de::DeserializeSeed<'de> {
+        let column = &self.columns[self.index];
+        self.index += 1;
+        seed.deserialize(self.de.child(column.1.clone()))
+    }
+}
+
+struct NP_Map_Access<'a, 'de, 'buffer> {
+    de: &'a NP_Deserializer<'de, 'buffer>,
+    keys: Vec<String>,
+    index: usize
+}
+
+impl<'a, 'de, 'buffer> de::MapAccess<'de> for NP_Map_Access<'a, 'de, 'buffer> {
+    type Error = NP_Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, NP_Error> where K: de::DeserializeSeed<'de> {
+        if self.index < self.keys.len() {
+            seed.deserialize(self.keys[self.index].as_str().into_deserializer()).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, NP_Error> where V: de::DeserializeSeed<'de> {
+        let key = self.keys[self.index].clone();
+        self.index += 1;
+        seed.deserialize(self.de.child(key))
+    }
+}
+
+struct NP_Seq_Access<'a, 'de, 'buffer> {
+    de: &'a NP_Deserializer<'de, 'buffer>,
+    length: usize,
+    index: usize
+}
+
+impl<'a, 'de, 'buffer> de::SeqAccess<'de> for NP_Seq_Access<'a, 'de, 'buffer> {
+    type Error = NP_Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, NP_Error> where T: de::DeserializeSeed<'de> {
+        if self.index < self.length {
+            let item = self.de.child(self.index.to_string());
+            self.index += 1;
+            seed.deserialize(item).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.length - self.index)
+    }
+}
+
+struct NP_Variant_Access<'de, 'buffer> {
+    de: NP_Deserializer<'de, 'buffer>,
+    variant: &'static str
+}
+
+impl<'de, 'buffer> de::EnumAccess<'de> for NP_Variant_Access<'de, 'buffer> {
+    type Error = NP_Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), NP_Error> where V: de::DeserializeSeed<'de> {
+        let variant = seed.deserialize(IntoDeserializer::<NP_Error>::into_deserializer(self.variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'buffer> de::VariantAccess<'de> for NP_Variant_Access<'de, 'buffer> {
+    type Error = NP_Error;
+
+    fn unit_variant(self) -> Result<(), NP_Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, NP_Error> where T: de::DeserializeSeed<'de> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, NP_Error> where V: de::Visitor<'de> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+#[cfg(test)]
+use ::serde::{Serialize as Ser, Deserialize as De};
+#[cfg(test)]
+use alloc::collections::BTreeMap;
+
+#[cfg(test)]
+#[derive(Ser, De, Debug, PartialEq)]
+enum Test_Enum {
+    Red,
+    Blue(u32),
+    Green { shade: u8 }
+}
+
+#[test]
+fn scalars_and_collections_round_trip() -> Result<(), NP_Error> {
+
+    #[derive(Ser, De, Debug, PartialEq)]
+    struct Point { lat: f64, lng: f64 }
+
+    #[derive(Ser, De, Debug, PartialEq)]
+    struct Record {
+        name: String,
+        age: u8,
+        price: f64,
+        when: u64,
+        color: String,
+        kind: Test_Enum,
+        other: Test_Enum,
+        location: Point,
+        data: Vec<u8>,
+        pair: (i16, bool),
+        tags: BTreeMap<String, i64>,
+        nickname: Option<String>
+    }
+
+    let factory = crate::NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["name",     {"type": "string"}],
+            ["age",      {"type": "u8"}],
+            ["price",    {"type": "dec", "exp": 2}],
+            ["when",     {"type": "date"}],
+            ["color",    {"type": "option", "choices": ["red", "blue"]}],
+            ["kind",     {"type": "option", "choices": ["Red", "Blue"]}],
+            ["other",    {"type": "table", "columns": [["Blue", {"type": "u32"}], ["Green", {"type": "table", "columns": [["shade", {"type": "u8"}]]}]]}],
+            ["location", {"type": "geo8"}],
+            ["data",     {"type": "bytes"}],
+            ["pair",     {"type": "tuple", "values": [{"type": "i16"}, {"type": "bool"}]}],
+            ["tags",     {"type": "map", "value": {"type": "i64"}}],
+            ["nickname", {"type": "string"}]
+        ]
+    }"#)?;
+
+    let mut tags = BTreeMap::new();
+    tags.insert(String::from("alpha"), -20i64);
+    tags.insert(String::from("beta"), 400i64);
+
+    let record = Record {
+        name: String::from("hello"),
+        age: 30,
+        price: 20.25,
+        when: 1605138980392,
+        color: String::from("blue"),
+        kind: Test_Enum::Red,
+        other: Test_Enum::Green { shade: 4 },
+        location: Point { lat: 45.5, lng: -120.25 },
+        data: alloc::vec![1, 2, 3],
+        pair: (-5, true),
+        tags,
+        nickname: None
+    };
+
+    let mut buffer = factory.empty_buffer(None);
+    to_buffer(&record, &mut buffer)?;
+
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("hello"));
+    assert_eq!(buffer.get::<NP_Dec>(&["price"])?, Some(NP_Dec::new(2025, 2)));
+    assert_eq!(buffer.get::<NP_Enum>(&["color"])?, Some(NP_Enum::new("blue")));
+    assert_eq!(buffer.get::<&[u8]>(&["data"])?, Some(&[1u8, 2, 3][..]));
+    assert_eq!(buffer.get::<i64>(&["tags", "beta"])?, Some(400));
+
+    let decoded: Record = from_buffer(&buffer)?;
+    assert_eq!(decoded, record);
+
+    buffer.move_cursor(&["other"])?;
+    to_buffer(&Test_Enum::Blue(9), &mut buffer)?;
+    assert_eq!(buffer.get::<u32>(&["Blue"])?, Some(9));
+
+    Ok(())
+}
+
+#[test]
+fn schema_mismatch_fails() -> Result<(), NP_Error> {
+
+    #[derive(Ser, De, Debug, PartialEq)]
+    struct Record { age: i32 }
+
+    let factory = crate::NP_Factory::new(r#"{"type": "table", "columns": [["age", {"type": "u8"}]]}"#)?;
+    let mut buffer = factory.empty_buffer(None);
+
+    assert!(to_buffer(&Record { age: 300 }, &mut buffer).is_err());
+    assert!(to_buffer(&Record { age: -1 }, &mut buffer).is_err());
+    assert!(to_buffer(&String::from("hello"), &mut buffer).is_err());
+    assert!(from_buffer::<Record>(&buffer).is_err()); // missing field
+
+    to_buffer(&Record { age: 200 }, &mut buffer)?;
+    assert_eq!(from_buffer::<Record>(&buffer)?, Record { age: 200 });
+
+    Ok(())
+}
@@ -0,0 +1,476 @@
+//! Order preserving encodings for scalar values
+//!
+//! Buffers closed with `close_sortable` hold each value of the root tuple one after another in an encoding that sorts the same way the values do.  Comparing the closed bytes of two buffers compares their tuples value by value, without opening either of them.  `NP_Buffer::cmp_sortable` compares two open buffers the same way, and `NP_Buffer::sortable_bytes` encodes a single value or tuple from anywhere in a buffer.  `NP_Factory::sortable_range` gets the start and end bytes for key range queries over the leading values of the tuple.
+//!
+//! | Type                     | Encoding                                                                                       |
+//! |--------------------------|------------------------------------------------------------------------------------------------|
+//! | `uint8` ... `uint64`     | Big endian bytes                                                                               |
+//! | `int8` ... `int64`       | Big endian bytes with the sign bit flipped                                                     |
+//! | `float`, `double`        | Big endian IEEE bits, negative values have every bit flipped, positive values the sign bit     |
+//! | `decimal`                | Number at the schema `exp` as an `int64`                                                       |
+//! | `bool`                   | 1 byte, 1 for true and 0 for false                                                             |
+//! | `geo`                    | Latitude then longitude as signed integers at the schema resolution (geo16 for `auto`), bits interleaved with `z_order` |
+//! | `date`                   | Big endian milliseconds                                                                        |
+//! | `option`                 | 1 byte index of the choice                                                                     |
+//! | `uuid`, `ulid`           | 16 bytes as stored                                                                             |
+//! | `string`, `bytes`        | With `size`, the padded value.  Without, every zero byte is followed by `255` and the value ends with two zero bytes |
+//!
+//! The string terminator keeps shorter values in front of longer values that start with the same bytes, so values after a string in the tuple don't change it's order.  Values that haven't been set use the schema default, or the zero value of the type if there is no default.  Floats sort negative zero in front of zero and `NaN` after infinity.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "tuple",
+//!    "sorted": true,
+//!    "values": [
+//!         {"type": "float"},
+//!         {"type": "string"}
+//!     ]
+//! }"#)?;
+//!
+//! let mut keys: Vec<Vec<u8>> = Vec::new();
+//! for (score, name) in [(2.5f32, "b"), (-1.0f32, "zed"), (2.5f32, "a"), (2.5f32, "ab")].iter() {
+//!     let mut buffer = factory.empty_buffer(None);
+//!     buffer.set(&["0"], *score)?;
+//!     buffer.set(&["1"], *name)?;
+//!     keys.push(buffer.close_sortable()?);
+//! }
+//! keys.sort();
+//!
+//! let mut names: Vec<String> = Vec::new();
+//! for key in keys {
+//!     let buffer = factory.open_sortable_buffer(key)?;
+//!     names.push(buffer.get::<&str>(&["1"])?.unwrap_or("").to_string());
+//! }
+//! assert_eq!(names, vec!["zed", "a", "ab", "b"]);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::collection::tuple::NP_Tuple;
+use crate::error::NP_Error;
+use crate::memory::NP_Memory;
+use crate::pointer::{NP_Cursor, NP_Value};
+use crate::pointer::bytes::NP_Bytes;
+use crate::pointer::date::NP_Date;
+use crate::pointer::dec::NP_Dec;
+use crate::pointer::geo::{NP_Geo, NP_Geo_Bytes, rounded_bytes, z_interleave, z_deinterleave};
+use crate::pointer::option::NP_Enum;
+use crate::pointer::string::NP_String;
+use crate::pointer::ulid::{NP_ULID, _NP_ULID};
+use crate::pointer::uuid::{NP_UUID, _NP_UUID};
+use crate::schema::NP_Parsed_Schema;
+use crate::utils::{to_signed, to_unsigned};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Read the value at a cursor, falling back to the schema default
+fn value<'value, X: NP_Value<'value>>(cursor: &Option<NP_Cursor>, schema_addr: usize, memory: &'value NP_Memory) -> Result<Option<X>, NP_Error> {
+    if let Some(cursor) = cursor {
+        if let Some(x) = X::into_value(cursor, memory)? {
+            return Ok(Some(x));
+        }
+    }
+    Ok(X::schema_default(&memory.schema[schema_addr]))
+}
+
+/// Write the last `size` bytes of an integer, flipping the sign bit of signed integers
+fn write_int(out: &mut Vec<u8>, raw: u64, size: usize, signed: bool) {
+    let bytes = raw.to_be_bytes();
+    let start = out.len();
+    out.extend_from_slice(&bytes[(8 - size)..]);
+    if signed {
+        out[start] = to_unsigned(out[start]);
+    }
+}
+
+fn float_bits(bits: u64, sign: u64) -> u64 {
+    if bits & sign != 0 { !bits } else { bits | sign }
+}
+
+fn float_unbits(bits: u64, sign: u64) -> u64 {
+    if bits & sign != 0 { bits & !sign } else { !bits }
+}
+
+/// Write a fixed size value, padding or cutting it to `size` bytes
+fn write_fixed(out: &mut Vec<u8>, value: &[u8], size: usize, pad: u8) {
+    for x in 0..size {
+        out.push(if x < value.len() { value[x] } else { pad });
+    }
+}
+
+/// Write a variable size value with escaped zero bytes and a terminator
+fn write_escaped(out: &mut Vec<u8>, value: &[u8]) {
+    for byte in value {
+        out.push(*byte);
+        if *byte == 0 {
+            out.push(255);
+        }
+    }
+    out.extend_from_slice(&[0, 0]);
+}
+
+/// Encode the value at `cursor` into `out`, `cursor` is `None` for values that aren't in the buffer yet
+pub(crate) fn encode(cursor: Option<NP_Cursor>, schema_addr: usize, memory: &NP_Memory, out: &mut Vec<u8>) -> Result<(), NP_Error> {
+    match &memory.schema[schema_addr] {
+        NP_Parsed_Schema::Int8 { .. }   => write_int(out, value::<i8>(&cursor, schema_addr, memory)?.unwrap_or(0) as u8 as u64, 1, true),
+        NP_Parsed_Schema::Int16 { .. }  => write_int(out, value::<i16>(&cursor, schema_addr, memory)?.unwrap_or(0) as u16 as u64, 2, true),
+        NP_Parsed_Schema::Int32 { .. }  => write_int(out, value::<i32>(&cursor, schema_addr, memory)?.unwrap_or(0) as u32 as u64, 4, true),
+        NP_Parsed_Schema::Int64 { .. }  => write_int(out, value::<i64>(&cursor, schema_addr, memory)?.unwrap_or(0) as u64, 8, true),
+        NP_Parsed_Schema::Uint8 { .. }  => write_int(out, value::<u8>(&cursor, schema_addr, memory)?.unwrap_or(0) as u64, 1, false),
+        NP_Parsed_Schema::Uint16 { .. } => write_int(out, value::<u16>(&cursor, schema_addr, memory)?.unwrap_or(0) as u64, 2, false),
+        NP_Parsed_Schema::Uint32 { .. } => write_int(out, value::<u32>(&cursor, schema_addr, memory)?.unwrap_or(0) as u64, 4, false),
+        NP_Parsed_Schema::Uint64 { .. } => write_int(out, value::<u64>(&cursor, schema_addr, memory)?.unwrap_or(0), 8, false),
+        NP_Parsed_Schema::Float { .. }  => {
+            let bits = value::<f32>(&cursor, schema_addr, memory)?.unwrap_or(0.0).to_bits() as u64;
+            write_int(out, float_bits(bits, 1 << 31), 4, false)
+        },
+        NP_Parsed_Schema::Double { .. } => {
+            let bits = value::<f64>(&cursor, schema_addr, memory)?.unwrap_or(0.0).to_bits();
+            write_int(out, float_bits(bits, 1 << 63), 8, false)
+        },
+        NP_Parsed_Schema::Decimal { exp, .. } => {
+            let mut dec = value::<NP_Dec>(&cursor, schema_addr, memory)?.unwrap_or(NP_Dec::new(0, *exp));
+            write_int(out, dec.shift_exp(*exp).num as u64, 8, true)
+        },
+        NP_Parsed_Schema::Boolean { .. } => {
+            out.push(if value::<bool>(&cursor, schema_addr, memory)?.unwrap_or(false) { 1 } else { 0 });
+        },
+        NP_Parsed_Schema::Geo { size, z_order, .. } => {
+            let stored = match &cursor {
+                Some(cursor) => NP_Geo_Bytes::into_value(cursor, memory)?,
+                None => None
+            };
+            // auto sized values are all encoded at the geo16 resolution
+            let stored = if *size == 0 {
+                let geo = value::<NP_Geo>(&cursor, schema_addr, memory)?.unwrap_or_default();
+                Some(rounded_bytes(16, geo.lat, geo.lng))
+            } else {
+                stored
+            };
+            let bytes = match stored {
+                Some(x) => Some(x),
+                None => match value::<NP_Geo>(&None, schema_addr, memory)? {
+                    Some(x) => NP_Geo::new(*size, x.lat, x.lng).get_bytes(),
+                    None => NP_Geo::new(*size, 0.0, 0.0).get_bytes()
+                }
+            };
+            if let Some(bytes) = bytes {
+                if *z_order {
+                    out.extend_from_slice(&z_interleave(&bytes.lat, &bytes.lng));
+                } else {
+                    out.extend_from_slice(&bytes.lat);
+                    out.extend_from_slice(&bytes.lng);
+                }
+            }
+        },
+        NP_Parsed_Schema::Date { .. } => write_int(out, value::<NP_Date>(&cursor, schema_addr, memory)?.unwrap_or(NP_Date::new(0)).value, 8, false),
+        NP_Parsed_Schema::Enum { choices, .. } => {
+            let index = match value::<NP_Enum>(&cursor, schema_addr, memory)? {
+                Some(x) => choices.iter().position(|choice| *choice == x).unwrap_or(0),
+                None => 0
+            };
+            out.push(index as u8);
+        },
+        NP_Parsed_Schema::Uuid { .. } => {
+            match value::<_NP_UUID>(&cursor, schema_addr, memory)? {
+                Some(x) => out.extend_from_slice(&x.value),
+                None => out.extend_from_slice(&[0u8; 16])
+            }
+        },
+        NP_Parsed_Schema::Ulid { .. } => {
+            match value::<_NP_ULID>(&cursor, schema_addr, memory)? {
+                Some(x) => out.extend_from_slice(x.as_bytes()),
+                None => out.extend_from_slice(&[0u8; 16])
+            }
+        },
+        NP_Parsed_Schema::UTF8String { size, .. } => {
+            let string = value::<NP_String>(&cursor, schema_addr, memory)?.unwrap_or("");
+            if *size > 0 {
+                write_fixed(out, string.as_bytes(), *size as usize, b' ');
+            } else {
+                write_escaped(out, string.as_bytes());
+            }
+        },
+        NP_Parsed_Schema::Bytes { size, .. } => {
+            let bytes = value::<NP_Bytes>(&cursor, schema_addr, memory)?.unwrap_or(&[]);
+            if *size > 0 {
+                write_fixed(out, bytes, *size as usize, 0);
+            } else {
+                write_escaped(out, bytes);
+            }
+        },
+        NP_Parsed_Schema::Tuple { values, .. } => {
+            for (index, child) in values.iter().enumerate() {
+                let child_cursor = match cursor {
+                    Some(x) => NP_Tuple::select(x, index, false, memory)?,
+                    None => None
+                };
+                encode(child_cursor, *child, memory, out)?;
+            }
+        },
+        _ => return Err(NP_Error::SchemaMismatch("Only scalars and tuples of scalars have a sortable encoding!".into()))
+    }
+    Ok(())
+}
+
+/// Take the next `len` bytes of an encoded value
+fn take<'take>(bytes: &'take [u8], offset: &mut usize, len: usize) -> Result<&'take [u8], NP_Error> {
+    if *offset + len > bytes.len() {
+        return Err(NP_Error::Damaged("Sortable bytes ended before the last value!".into()));
+    }
+    let taken = &bytes[*offset..(*offset + len)];
+    *offset += len;
+    Ok(taken)
+}
+
+fn read_int(bytes: &[u8], offset: &mut usize, size: usize, signed: bool) -> Result<u64, NP_Error> {
+    let taken = take(bytes, offset, size)?;
+    let mut raw = [0u8; 8];
+    raw[(8 - size)..].copy_from_slice(taken);
+    if signed {
+        raw[8 - size] = to_signed(raw[8 - size]);
+    }
+    Ok(u64::from_be_bytes(raw))
+}
+
+fn read_escaped(bytes: &[u8], offset: &mut usize) -> Result<Vec<u8>, NP_Error> {
+    let mut value: Vec<u8> = Vec::new();
+    loop {
+        let byte = take(bytes, offset, 1)?[0];
+        if byte == 0 {
+            match take(bytes, offset, 1)?[0] {
+                0 => return Ok(value),
+                255 => value.push(0),
+                _ => return Err(NP_Error::Damaged("Bad escape in sortable bytes!".into()))
+            }
+        } else {
+            value.push(byte);
+        }
+    }
+}
+
+/// Decode the value at `offset` in `bytes` and set it at `cursor`, `offset` is moved past the value
+pub(crate) fn decode(bytes: &[u8], offset: &mut usize, cursor: NP_Cursor, memory: &NP_Memory) -> Result<(), NP_Error> {
+    match &memory.schema[cursor.schema_addr] {
+        NP_Parsed_Schema::Int8 { .. }   => { i8::set_value(cursor, memory, read_int(bytes, offset, 1, true)? as u8 as i8)?; },
+        NP_Parsed_Schema::Int16 { .. }  => { i16::set_value(cursor, memory, read_int(bytes, offset, 2, true)? as u16 as i16)?; },
+        NP_Parsed_Schema::Int32 { .. }  => { i32::set_value(cursor, memory, read_int(bytes, offset, 4, true)? as u32 as i32)?; },
+        NP_Parsed_Schema::Int64 { .. }  => { i64::set_value(cursor, memory, read_int(bytes, offset, 8, true)? as i64)?; },
+        NP_Parsed_Schema::Uint8 { .. }  => { u8::set_value(cursor, memory, read_int(bytes, offset, 1, false)? as u8)?; },
+        NP_Parsed_Schema::Uint16 { .. } => { u16::set_value(cursor, memory, read_int(bytes, offset, 2, false)? as u16)?; },
+        NP_Parsed_Schema::Uint32 { .. } => { u32::set_value(cursor, memory, read_int(bytes, offset, 4, false)? as u32)?; },
+        NP_Parsed_Schema::Uint64 { .. } => { u64::set_value(cursor, memory, read_int(bytes, offset, 8, false)?)?; },
+        NP_Parsed_Schema::Float { .. }  => {
+            let bits = float_unbits(read_int(bytes, offset, 4, false)?, 1 << 31) as u32;
+            f32::set_value(cursor, memory, f32::from_bits(bits))?;
+        },
+        NP_Parsed_Schema::Double { .. } => {
+            let bits = float_unbits(read_int(bytes, offset, 8, false)?, 1 << 63);
+            f64::set_value(cursor, memory, f64::from_bits(bits))?;
+        },
+        NP_Parsed_Schema::Decimal { exp, .. } => {
+            NP_Dec::set_value(cursor, memory, NP_Dec::new(read_int(bytes, offset, 8, true)? as i64, *exp))?;
+        },
+        NP_Parsed_Schema::Boolean { .. } => { bool::set_value(cursor, memory, take(bytes, offset, 1)?[0] == 1)?; },
+        NP_Parsed_Schema::Geo { size, z_order, .. } => {
+            let value = take(bytes, offset, if *size == 0 { 16 } else { *size as usize })?;
+            let value = if *z_order {
+                let (lat, lng) = z_deinterleave(value);
+                [lat, lng].concat()
+            } else {
+                value.to_vec()
+            };
+            if *size == 0 { // auto sized values pick their own size again
+                let (lat, lng) = value.split_at(8);
+                NP_Geo::set_value(cursor, memory, NP_Geo_Bytes { size: 16, lat: lat.to_vec(), lng: lng.to_vec() }.into_geo())?;
+            } else {
+                // copy the bytes into place, converting through floats could round them
+                NP_Geo::set_value(cursor, memory, NP_Geo::new(*size, 0.0, 0.0))?;
+                let addr = cursor.get_value(memory).get_addr_value() as usize;
+                memory.get_bytes_mut(addr, value.len())?.copy_from_slice(&value);
+            }
+        },
+        NP_Parsed_Schema::Date { .. } => { NP_Date::set_value(cursor, memory, NP_Date::new(read_int(bytes, offset, 8, false)?))?; },
+        NP_Parsed_Schema::Enum { choices, .. } => {
+            let index = take(bytes, offset, 1)?[0] as usize;
+            match choices.get(index) {
+                Some(x) => { NP_Enum::set_value(cursor, memory, x.clone())?; },
+                None => return Err(NP_Error::Damaged("Option index in sortable bytes is outside of the choices!".into()))
+            }
+        },
+        NP_Parsed_Schema::Uuid { .. } => {
+            let mut uuid = NP_UUID { value: [0u8; 16] };
+            uuid.value.copy_from_slice(take(bytes, offset, 16)?);
+            _NP_UUID::set_value(cursor, memory, &uuid)?;
+        },
+        NP_Parsed_Schema::Ulid { .. } => {
+            let mut value = [0u8; 16];
+            value.copy_from_slice(take(bytes, offset, 16)?);
+            _NP_ULID::set_value(cursor, memory, &NP_ULID::from_bytes(value))?;
+        },
+        NP_Parsed_Schema::UTF8String { size, .. } => {
+            let value = if *size > 0 { take(bytes, offset, *size as usize)?.to_vec() } else { read_escaped(bytes, offset)? };
+            match String::from_utf8(value) {
+                Ok(x) => { NP_String::set_value(cursor, memory, &x)?; },
+                Err(_e) => return Err(NP_Error::Utf8("String in sortable bytes isn't valid utf8!".into()))
+            }
+        },
+        NP_Parsed_Schema::Bytes { size, .. } => {
+            let value = if *size > 0 { take(bytes, offset, *size as usize)?.to_vec() } else { read_escaped(bytes, offset)? };
+            NP_Bytes::set_value(cursor, memory, &value)?;
+        },
+        NP_Parsed_Schema::Tuple { values, .. } => {
+            for index in 0..values.len() {
+                match NP_Tuple::select(cursor, index, true, memory)? {
+                    Some(x) => decode(bytes, offset, x, memory)?,
+                    None => return Err(NP_Error::new("unreachable"))
+                }
+            }
+        },
+        _ => return Err(NP_Error::SchemaMismatch("Only scalars and tuples of scalars have a sortable encoding!".into()))
+    }
+    Ok(())
+}
+
+#[test]
+fn sortable_encodings_work() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use crate::json_flex::NP_JSON;
+    use alloc::vec;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "tuple",
+        "sorted": true,
+        "values": [
+            {"type": "i16"},
+            {"type": "double"},
+            {"type": "bytes"},
+            {"type": "geo4"}
+        ]
+    }"#)?;
+
+    let values: Vec<(i16, f64, Vec<u8>)> = vec![
+        (-300, 2.0, vec![1, 0, 2]),
+        (-300, 2.0, vec![1, 0]),
+        (-300, 2.0, vec![1]),
+        (-300, -0.5, vec![]),
+        (-300, -7.25, vec![255]),
+        (5, f64::INFINITY, vec![0, 0]),
+        (5, f64::NEG_INFINITY, vec![0]),
+        (-301, 0.0, vec![])
+    ];
+
+    let mut keys: Vec<Vec<u8>> = Vec::new();
+    for (int, double, bytes) in values.iter() {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&["0"], *int)?;
+        buffer.set(&["1"], *double)?;
+        buffer.set(&["2"], &bytes[..])?;
+        buffer.set(&["3"], NP_Geo::new(4, -12.5, 40.25))?;
+        keys.push(buffer.close_sortable()?);
+    }
+
+    let mut sorted = values.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    keys.sort();
+
+    for (key, expected) in keys.into_iter().zip(sorted.iter()) {
+        let buffer = factory.open_sortable_buffer(key.clone())?;
+        assert_eq!(buffer.get::<i16>(&["0"])?, Some(expected.0));
+        assert_eq!(buffer.get::<f64>(&["1"])?, Some(expected.1));
+        assert_eq!(buffer.get::<&[u8]>(&["2"])?.unwrap_or(&[]), &expected.2[..]);
+        assert_eq!(buffer.get::<NP_Geo>(&["3"])?, Some(NP_Geo::new(4, -12.5, 40.25)));
+        assert_eq!(buffer.close_sortable()?, key);
+    }
+
+    // truncated and padded bytes are rejected
+    assert!(factory.open_sortable_buffer(vec![128, 0, 0]).is_err());
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["2"], &[1u8, 2][..])?;
+    let mut key = buffer.close_sortable()?;
+    key.push(0);
+    assert!(factory.open_sortable_buffer(key).is_err());
+
+    // ranges over the leading values
+    let (start, end) = factory.sortable_range(&[NP_JSON::Integer(-300), NP_JSON::Float(-1.0)], &[NP_JSON::Integer(-300), NP_JSON::Float(2.0)])?;
+    let in_range = values.iter().filter(|(int, double, bytes)| {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&["0"], *int).unwrap();
+        buffer.set(&["1"], *double).unwrap();
+        buffer.set(&["2"], &bytes[..]).unwrap();
+        let key = buffer.close_sortable().unwrap();
+        key >= start && key < end
+    }).count();
+    assert_eq!(in_range, 1);
+
+    let (start, end) = factory.sortable_range(&[], &[NP_JSON::Integer(5)])?;
+    assert_eq!(start.len(), 0);
+    assert_eq!(end, vec![128, 5]);
+    assert!(factory.sortable_range(&[NP_JSON::String("abc".into())], &[]).is_err());
+    assert!(factory.sortable_range(&[], &[NP_JSON::Null, NP_JSON::Null, NP_JSON::Null, NP_JSON::Null, NP_JSON::Null]).is_err());
+    assert!(NP_Factory::new(r#"{"type": "list", "of": {"type": "u8"}}"#)?.sortable_range(&[NP_JSON::Integer(1)], &[]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn sortable_range_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use crate::json_flex::NP_JSON;
+    use alloc::borrow::ToOwned;
+    use alloc::vec;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "tuple",
+        "sorted": true,
+        "values": [
+            {"type": "u32", "default": 5},
+            {"type": "string", "size": 8, "default": "m"}
+        ]
+    }"#)?;
+
+    let mut keys: Vec<Vec<u8>> = Vec::new();
+    for (user, item) in [(1u32, "boots"), (2, "helmet"), (2, "jetpack"), (2, "m"), (3, "boots")].iter() {
+        let mut buffer = factory.empty_buffer(None);
+        buffer.set(&["0"], *user)?;
+        buffer.set(&["1"], *item)?;
+        keys.push(buffer.close_sortable()?);
+    }
+    keys.sort();
+
+    let in_range = |start: &Vec<u8>, end: &Vec<u8>| -> Vec<usize> {
+        keys.iter().enumerate().filter(|(_, key)| *key >= start && *key < end).map(|(index, _)| index).collect()
+    };
+
+    // leading values only
+    let (start, end) = factory.sortable_range(&[NP_JSON::Integer(2)], &[NP_JSON::Integer(3)])?;
+    assert_eq!(in_range(&start, &end), vec![1, 2, 3]);
+
+    // start is inclusive, end is exclusive
+    let (start, end) = factory.sortable_range(&[NP_JSON::Integer(2), NP_JSON::String("helmet".to_owned())], &[NP_JSON::Integer(2), NP_JSON::String("jetpack".to_owned())])?;
+    assert_eq!(in_range(&start, &end), vec![1]);
+
+    // null is the same as a value that hasn't been set, the default of it's type
+    let (start, end) = factory.sortable_range(&[NP_JSON::Integer(2), NP_JSON::Null], &[NP_JSON::Integer(2), NP_JSON::String("i".to_owned())])?;
+    assert_eq!(in_range(&start, &end), vec![1]);
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["1"], "boots")?;
+    let unset_user = buffer.close_sortable()?;
+    let (start, end) = factory.sortable_range(&[NP_JSON::Null], &[NP_JSON::Integer(1)])?;
+    assert!(unset_user >= start && unset_user < end);
+    assert_eq!(in_range(&start, &end), Vec::<usize>::new());
+
+    // empty start is the start of everything
+    let (start, end) = factory.sortable_range(&[], &[NP_JSON::Integer(2)])?;
+    assert_eq!(in_range(&start, &end), vec![0]);
+
+    // bounds that don't fit the schema
+    assert!(factory.sortable_range(&[NP_JSON::String("abc".to_owned())], &[]).is_err());
+    assert!(factory.sortable_range(&[NP_JSON::Integer(1), NP_JSON::Null, NP_JSON::Null], &[]).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Share one buffer between threads
+//!
+//! Buffers change their bytes through shared references internally, so `NP_Buffer` can't be `Sync`.  `NP_Buffer_Sync` holds the finished bytes instead and hands each reader it's own read only `NP_Buffer` over them.  Opening a view doesn't copy or parse anything, it's as cheap as `NP_Factory::open_buffer_read_only`.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["age",  {"type": "u8"}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Jeb")?;
+//! new_buffer.set(&["age"], 30u8)?;
+//!
+//! let shared = new_buffer.into_sync();
+//!
+//! std::thread::scope(|scope| {
+//!     for _ in 0..4 {
+//!         scope.spawn(|| {
+//!             let view = shared.buffer();
+//!             assert_eq!(view.get::<&str>(&["name"]).unwrap(), Some("Jeb"));
+//!             assert_eq!(view.get::<u8>(&["age"]).unwrap(), Some(30));
+//!         });
+//!     }
+//! });
+//!
+//! // views can't change the bytes
+//! assert!(shared.buffer().is_read_only());
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::buffer::NP_Buffer;
+use crate::memory::NP_Memory;
+use crate::schema::NP_Parsed_Schema;
+use crate::validation::NP_Constraints;
+use alloc::vec::Vec;
+
+/// A finished buffer that can be read from many threads at once, see `NP_Buffer::into_sync`
+pub struct NP_Buffer_Sync<'buffer> {
+    bytes: Vec<u8>,
+    schema: &'buffer Vec<NP_Parsed_Schema>,
+    constraints: &'buffer [NP_Constraints]
+}
+
+impl<'buffer> NP_Buffer_Sync<'buffer> {
+
+    pub(crate) fn new(memory: NP_Memory<'buffer>) -> Self {
+        let schema = memory.schema;
+        let constraints = memory.constraints;
+        NP_Buffer_Sync { bytes: memory.dump(), schema, constraints }
+    }
+
+    /// Open a read only buffer over the shared bytes.  Every method that changes the buffer returns an error.
+    ///
+    pub fn buffer(&self) -> NP_Buffer<'_> {
+        NP_Buffer::_new(NP_Memory::existing_read_only(&self.bytes, self.schema).with_constraints(self.constraints))
+    }
+
+    /// The shared bytes
+    ///
+    pub fn read_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Stop sharing and get the bytes back, open them with `NP_Factory::open_buffer` to make more changes.
+    ///
+    pub fn close(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[test]
+fn sync_buffers_work() -> Result<(), crate::error::NP_Error> {
+    use crate::NP_Factory;
+    extern crate std;
+
+    fn is_send_sync<T: Send + Sync>(_: &T) -> bool { true }
+
+    let factory = NP_Factory::new(r#"{"type": "table", "columns": [
+        ["title", {"type": "string"}],
+        ["tags",  {"type": "list", "of": {"type": "string"}}]
+    ]}"#)?;
+
+    let mut buffer = factory.empty_buffer(None);
+    buffer.set(&["title"], "shared")?;
+    buffer.list_push(&["tags"], "one")?;
+    let bytes = buffer.read_bytes().to_vec();
+
+    let shared = buffer.into_sync();
+    assert!(is_send_sync(&shared));
+    assert_eq!(shared.read_bytes(), &bytes[..]);
+
+    // every view reads the same bytes without copying them
+    let mut first = shared.buffer();
+    let second = shared.buffer();
+    assert_eq!(first.read_bytes().as_ptr(), shared.read_bytes().as_ptr());
+    assert_eq!(second.get::<&str>(&["title"])?, Some("shared"));
+    assert_eq!(second.length(&["tags"])?, Some(1));
+    assert!(first.set(&["title"], "changed").is_err());
+    assert_eq!(first.get::<&str>(&["title"])?, Some("shared"));
+
+    // views on other threads only ever read the shared bytes
+    std::thread::scope(|scope| {
+        for _x in 0..4 {
+            scope.spawn(|| {
+                let mut view = shared.buffer();
+                assert_eq!(view.get::<&str>(&["tags", "0"]).unwrap(), Some("one"));
+                assert!(view.set(&["tags", "0"], "two").is_err());
+                assert_eq!(view.json_encode(&[]).unwrap().stringify(), r#"{"title":"shared","tags":["one"]}"#);
+            });
+        }
+    });
+
+    assert_eq!(shared.close(), bytes);
+
+    Ok(())
+}
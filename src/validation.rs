@@ -0,0 +1,613 @@
+//! Check buffers against their schema before processing them
+//!
+//! Buffers from untrusted sources can hold values that the schema doesn't allow, like an enum index past the last choice or a string that isn't valid UTF-8.  `NP_Buffer::validate` walks every set value in the buffer and reports each problem it finds.
+//!
+//! On top of the checks that always apply, schemas can constrain values with these optional properties:
+//!
+//! | Property                   | Applies to                          | Meaning                                                  |
+//! |----------------------------|-------------------------------------|----------------------------------------------------------|
+//! | `min`, `max`               | numbers, `decimal`, `date`          | The value must be inside the (inclusive) range           |
+//! | `min_length`, `max_length` | `string`, `bytes`, `list`, `map`    | Characters, bytes, list length or map entries            |
+//! | `required`                 | table columns, tuple values, root   | The value must be set whenever it's parent is set        |
+//!
+//! The constraints are only checked by `validate`, reads and writes ignore them.  They aren't part of compiled schemas, so factories created with `NP_Factory::new_compiled` only run the checks that always apply.
+//!
+//! Any value can also be marked `"sensitive": true`.  That doesn't change validation, but `NP_Buffer::json_encode_redacted` replaces sensitive values with `"***"` so buffers holding personal data can be logged.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//!
+//! let factory = NP_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string", "max_length": 10, "required": true}],
+//!         ["age",  {"type": "u8", "min": 18}]
+//!     ]
+//! }"#)?;
+//!
+//! let mut new_buffer = factory.empty_buffer(None);
+//! new_buffer.set(&["name"], "Billy Joel")?;
+//! new_buffer.set(&["age"], 42u8)?;
+//! assert!(new_buffer.validate().is_ok());
+//!
+//! new_buffer.set(&["age"], 12u8)?;
+//! new_buffer.del(&["name"])?;
+//!
+//! let errors = new_buffer.validate().unwrap_err();
+//! assert_eq!(errors.len(), 2);
+//! assert_eq!(errors[0].path, vec!["name"]);
+//! assert_eq!(errors[0].message, "Required value isn't set");
+//! assert_eq!(errors[1].path, vec!["age"]);
+//! assert_eq!(errors[1].message, "Value 12 is less than min 18");
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::collection::{list::NP_List, map::NP_Map, table::NP_Table, tuple::NP_Tuple};
+use crate::json_flex::{JSMAP, NP_JSON};
+use crate::memory::NP_Memory;
+use crate::pointer::{NP_Cursor, NP_Value, inline_size};
+use crate::pointer::{dec::NP_Dec, geo::NP_Geo, date::NP_Date};
+use crate::schema::NP_Parsed_Schema;
+use crate::error::NP_Error;
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::format;
+use core::fmt;
+
+/// A value in a buffer that doesn't match it's schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct NP_Validation_Error {
+    /// Path to the value from the root of the buffer
+    pub path: Vec<String>,
+    /// What is wrong with the value
+    pub message: String
+}
+
+/// Inclusive numeric bound from the `min` or `max` schema properties
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NP_Bound {
+    /// Bound given as a JSON integer
+    Int(i128),
+    /// Bound given as a JSON float
+    Float(f64)
+}
+
+impl NP_Bound {
+    fn from_json(json: &NP_JSON) -> Option<Self> {
+        match json {
+            NP_JSON::Integer(x) => Some(NP_Bound::Int(*x as i128)),
+            NP_JSON::Float(x) => Some(NP_Bound::Float(*x)),
+            _ => None
+        }
+    }
+
+    fn to_json(&self) -> NP_JSON {
+        match self {
+            NP_Bound::Int(x) => NP_JSON::Integer(*x as i64),
+            NP_Bound::Float(x) => NP_JSON::Float(*x)
+        }
+    }
+
+    fn to_float(&self) -> f64 {
+        match self {
+            NP_Bound::Int(x) => *x as f64,
+            NP_Bound::Float(x) => *x
+        }
+    }
+
+    fn less_than(&self, other: &NP_Bound) -> bool {
+        match (self, other) {
+            (NP_Bound::Int(a), NP_Bound::Int(b)) => a < b,
+            _ => self.to_float() < other.to_float()
+        }
+    }
+}
+
+impl fmt::Display for NP_Bound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NP_Bound::Int(x) => write!(f, "{}", x),
+            NP_Bound::Float(x) => write!(f, "{}", x)
+        }
+    }
+}
+
+/// Optional constraints for a single schema address
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NP_Constraints {
+    min: Option<NP_Bound>,
+    max: Option<NP_Bound>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    required: bool,
+    /// masked by `NP_Buffer::json_encode_redacted`
+    sensitive: bool
+}
+
+/// The optional constraints of one schema type, see `NP_Schema_Node::constraints`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NP_Schema_Constraints {
+    /// Inclusive lower bound from the `min` property
+    pub min: Option<NP_Bound>,
+    /// Inclusive upper bound from the `max` property
+    pub max: Option<NP_Bound>,
+    /// The `min_length` property
+    pub min_length: Option<usize>,
+    /// The `max_length` property
+    pub max_length: Option<usize>,
+    /// The `required` property
+    pub required: bool,
+    /// The `sensitive` property
+    pub sensitive: bool
+}
+
+impl NP_Constraints {
+
+    /// Read the constraints for every address of a parsed schema from the JSON it was parsed from
+    pub(crate) fn from_json(parsed: &Vec<NP_Parsed_Schema>, json: &NP_JSON) -> Vec<NP_Constraints> {
+        let mut result: Vec<NP_Constraints> = alloc::vec![NP_Constraints::default(); parsed.len()];
+        Self::read_json(parsed, 0, json, &mut result);
+        result
+    }
+
+    fn read_json(parsed: &Vec<NP_Parsed_Schema>, address: usize, json: &NP_JSON, result: &mut Vec<NP_Constraints>) {
+
+        let length = |json: &NP_JSON| match json {
+            NP_JSON::Integer(x) if *x >= 0 => Some(*x as usize),
+            _ => None
+        };
+
+        result[address] = NP_Constraints {
+            min: NP_Bound::from_json(&json["min"]),
+            max: NP_Bound::from_json(&json["max"]),
+            min_length: length(&json["min_length"]),
+            max_length: length(&json["max_length"]),
+            required: if let NP_JSON::True = json["required"] { true } else { false },
+            sensitive: if let NP_JSON::True = json["sensitive"] { true } else { false }
+        };
+
+        match &parsed[address] {
+            NP_Parsed_Schema::Table { columns, .. } => {
+                for (index, (_i, _name, addr)) in columns.iter().enumerate() {
+                    Self::read_json(parsed, *addr, &json["columns"][index][1], result);
+                }
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                for (index, addr) in values.iter().enumerate() {
+                    Self::read_json(parsed, *addr, &json["values"][index], result);
+                }
+            },
+            NP_Parsed_Schema::List { of, .. } => Self::read_json(parsed, *of, &json["of"], result),
+            NP_Parsed_Schema::Map { value, .. } => Self::read_json(parsed, *value, &json["value"], result),
+            _ => { }
+        }
+    }
+
+    /// Public copy of the constraints at a schema address, empty if there aren't any
+    pub(crate) fn describe(constraints: &[NP_Constraints], address: usize) -> NP_Schema_Constraints {
+        match constraints.get(address) {
+            Some(c) => NP_Schema_Constraints {
+                min: c.min,
+                max: c.max,
+                min_length: c.min_length,
+                max_length: c.max_length,
+                required: c.required,
+                sensitive: c.sensitive
+            },
+            None => NP_Schema_Constraints::default()
+        }
+    }
+
+    /// Is the schema address marked `"sensitive": true`?
+    pub(crate) fn is_sensitive(constraints: &[NP_Constraints], address: usize) -> bool {
+        constraints.get(address).map(|c| c.sensitive).unwrap_or(false)
+    }
+
+    /// Replace every value from a sensitive schema address in exported JSON with `"***"`, empty values are left as `null`
+    pub(crate) fn redact(parsed: &Vec<NP_Parsed_Schema>, constraints: &[NP_Constraints], address: usize, json: &mut NP_JSON) {
+
+        if let NP_JSON::Null = json {
+            return;
+        }
+
+        if Self::is_sensitive(constraints, address) {
+            *json = NP_JSON::String("***".to_owned());
+            return;
+        }
+
+        match (&parsed[address], json) {
+            (NP_Parsed_Schema::Table { columns, .. }, NP_JSON::Dictionary(map)) => {
+                for (_i, name, addr) in columns.iter() {
+                    if let Some(value) = map.get_mut(name) {
+                        Self::redact(parsed, constraints, *addr, value);
+                    }
+                }
+            },
+            (NP_Parsed_Schema::Tuple { values, .. }, NP_JSON::Array(items)) => {
+                for (addr, item) in values.iter().zip(items.iter_mut()) {
+                    Self::redact(parsed, constraints, *addr, item);
+                }
+            },
+            (NP_Parsed_Schema::List { of, .. }, NP_JSON::Array(items)) => {
+                for item in items.iter_mut() {
+                    Self::redact(parsed, constraints, *of, item);
+                }
+            },
+            (NP_Parsed_Schema::Map { value, .. }, NP_JSON::Dictionary(map)) => {
+                for (_key, item) in map.values.iter_mut() {
+                    Self::redact(parsed, constraints, *value, item);
+                }
+            },
+            _ => { }
+        }
+    }
+
+    /// Put the constraints back into JSON generated from a parsed schema
+    pub(crate) fn to_json(parsed: &Vec<NP_Parsed_Schema>, constraints: &[NP_Constraints], address: usize, json: &mut NP_JSON) {
+
+        let schema_json: &mut JSMAP = match json {
+            NP_JSON::Dictionary(x) => x,
+            _ => return
+        };
+
+        if let Some(c) = constraints.get(address) {
+            if let Some(min) = c.min { schema_json.insert("min".to_owned(), min.to_json()); }
+            if let Some(max) = c.max { schema_json.insert("max".to_owned(), max.to_json()); }
+            if let Some(x) = c.min_length { schema_json.insert("min_length".to_owned(), NP_JSON::Integer(x as i64)); }
+            if let Some(x) = c.max_length { schema_json.insert("max_length".to_owned(), NP_JSON::Integer(x as i64)); }
+            if c.required { schema_json.insert("required".to_owned(), NP_JSON::True); }
+            if c.sensitive { schema_json.insert("sensitive".to_owned(), NP_JSON::True); }
+        }
+
+        match &parsed[address] {
+            NP_Parsed_Schema::Table { columns, .. } => {
+                if let Some(NP_JSON::Array(cols)) = schema_json.get_mut("columns") {
+                    for ((_i, _name, addr), col) in columns.iter().zip(cols.iter_mut()) {
+                        if let NP_JSON::Array(col) = col {
+                            if let Some(col_schema) = col.get_mut(1) {
+                                Self::to_json(parsed, constraints, *addr, col_schema);
+                            }
+                        }
+                    }
+                }
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                if let Some(NP_JSON::Array(values_json)) = schema_json.get_mut("values") {
+                    for (addr, value_json) in values.iter().zip(values_json.iter_mut()) {
+                        Self::to_json(parsed, constraints, *addr, value_json);
+                    }
+                }
+            },
+            NP_Parsed_Schema::List { of, .. } => {
+                if let Some(of_json) = schema_json.get_mut("of") {
+                    Self::to_json(parsed, constraints, *of, of_json);
+                }
+            },
+            NP_Parsed_Schema::Map { value, .. } => {
+                if let Some(value_json) = schema_json.get_mut("value") {
+                    Self::to_json(parsed, constraints, *value, value_json);
+                }
+            },
+            _ => { }
+        }
+    }
+}
+
+struct NP_Validator<'v> {
+    memory: &'v NP_Memory<'v>,
+    path: Vec<String>,
+    errors: Vec<NP_Validation_Error>
+}
+
+impl<'v> NP_Validator<'v> {
+
+    fn error(&mut self, message: String) {
+        self.errors.push(NP_Validation_Error { path: self.path.clone(), message });
+    }
+
+    fn constraints(&self, schema_addr: usize) -> Option<&'v NP_Constraints> {
+        self.memory.constraints.get(schema_addr)
+    }
+
+    fn is_set(&self, cursor: &NP_Cursor) -> bool {
+        cursor.get_value(self.memory).get_addr_value() != 0
+    }
+
+    fn missing(&mut self, schema_addr: usize) {
+        if self.constraints(schema_addr).map(|c| c.required).unwrap_or(false) {
+            self.error("Required value isn't set".to_owned());
+        }
+    }
+
+    fn length(&mut self, schema_addr: usize, length: usize) {
+        if let Some(c) = self.constraints(schema_addr) {
+            if let Some(min) = c.min_length {
+                if length < min {
+                    self.error(format!("Length {} is less than min_length {}", length, min));
+                }
+            }
+            if let Some(max) = c.max_length {
+                if length > max {
+                    self.error(format!("Length {} is more than max_length {}", length, max));
+                }
+            }
+        }
+    }
+
+    fn range(&mut self, schema_addr: usize, value: NP_Bound) {
+        if let Some(c) = self.constraints(schema_addr) {
+            if let Some(min) = c.min {
+                if value.less_than(&min) {
+                    self.error(format!("Value {} is less than min {}", value, min));
+                }
+            }
+            if let Some(max) = c.max {
+                if max.less_than(&value) {
+                    self.error(format!("Value {} is more than max {}", value, max));
+                }
+            }
+        }
+    }
+
+    /// Bytes of a string or bytes value, `None` if they don't fit in the buffer
+    fn raw_bytes(&self, value_addr: usize, size: u16) -> Option<&'v [u8]> {
+        let (start, length) = if size > 0 {
+            (value_addr, size as usize)
+        } else {
+            (value_addr + 2, u16::from_be_bytes(*self.memory.get_2_bytes(value_addr)?) as usize)
+        };
+        self.memory.read_bytes().get(start..(start + length))
+    }
+
+    fn scalar<T: NP_Value<'v>>(&mut self, cursor: &NP_Cursor, to_bound: fn(T) -> NP_Bound) {
+        match T::into_value(cursor, self.memory) {
+            Ok(Some(value)) => self.range(cursor.schema_addr, to_bound(value)),
+            Ok(None) => { },
+            Err(e) => self.error(e.into_message())
+        }
+    }
+
+    fn walk(&mut self, cursor: &NP_Cursor) {
+
+        let memory = self.memory;
+
+        let value_addr = cursor.get_value(memory).get_addr_value() as usize;
+
+        if value_addr == 0 {
+            return;
+        }
+
+        // inline values are stored in the pointer, not at an address
+        if value_addr >= memory.read_bytes().len() && inline_size(&memory.schema[cursor.schema_addr], memory).is_none() {
+            self.error("Value address is outside of the buffer".to_owned());
+            return;
+        }
+
+        match &memory.schema[cursor.schema_addr] {
+            NP_Parsed_Schema::Table { columns, .. } => {
+                let mut table_iter = NP_Table::new_iter(cursor, memory);
+                while let Some((index, key, item)) = table_iter.step_iter(memory) {
+                    self.path.push(key.to_owned());
+                    match item {
+                        Some(child) if self.is_set(&child) => self.walk(&child),
+                        _ => self.missing(columns[index].2)
+                    }
+                    self.path.pop();
+                }
+            },
+            NP_Parsed_Schema::Tuple { values, .. } => {
+                let mut tuple_iter = NP_Tuple::new_iter(cursor, memory);
+                while let Some((index, item)) = tuple_iter.step_iter(memory) {
+                    self.path.push(index.to_string());
+                    match item {
+                        Some(child) if self.is_set(&child) => self.walk(&child),
+                        _ => self.missing(values[index])
+                    }
+                    self.path.pop();
+                }
+            },
+            NP_Parsed_Schema::List { .. } => {
+                let mut length = 0usize;
+                let mut list_iter = NP_List::new_iter(cursor, memory, true, 0);
+                while let Some((index, item)) = list_iter.step_iter(memory) {
+                    if let Some(child) = item {
+                        if self.is_set(&child) {
+                            self.path.push(index.to_string());
+                            self.walk(&child);
+                            self.path.pop();
+                            length = index + 1;
+                        }
+                    }
+                }
+                self.length(cursor.schema_addr, length);
+            },
+            NP_Parsed_Schema::Map { .. } => {
+                let mut length = 0usize;
+                let mut map_iter = NP_Map::new_iter(cursor, memory);
+                while let Some((key, child)) = map_iter.step_iter(memory) {
+                    if self.is_set(&child) {
+                        self.path.push(key.to_owned());
+                        self.walk(&child);
+                        self.path.pop();
+                        length += 1;
+                    }
+                }
+                self.length(cursor.schema_addr, length);
+            },
+            NP_Parsed_Schema::UTF8String { size, .. } => {
+                match self.raw_bytes(value_addr, *size).map(core::str::from_utf8) {
+                    None => self.error("String is outside of the buffer".to_owned()),
+                    Some(Err(_)) => self.error("String isn't valid UTF-8".to_owned()),
+                    Some(Ok(value)) => self.length(cursor.schema_addr, value.chars().count())
+                }
+            },
+            NP_Parsed_Schema::Bytes { size, .. } => {
+                match self.raw_bytes(value_addr, *size) {
+                    None => self.error("Bytes are outside of the buffer".to_owned()),
+                    Some(value) => self.length(cursor.schema_addr, value.len())
+                }
+            },
+            NP_Parsed_Schema::Enum { choices, .. } => {
+                if let Some(index) = memory.get_1_byte(value_addr) {
+                    if index as usize >= choices.len() {
+                        self.error(format!("Enum index {} isn't one of the {} choices", index, choices.len()));
+                    }
+                }
+            },
+            NP_Parsed_Schema::Geo { .. } => {
+                match NP_Geo::into_value(cursor, memory) {
+                    Ok(Some(geo)) => {
+                        if !(-90f64..=90f64).contains(&geo.lat) || !(-180f64..=180f64).contains(&geo.lng) {
+                            self.error(format!("Geo value {}, {} is outside of the globe", geo.lat, geo.lng));
+                        }
+                    },
+                    Ok(None) => { },
+                    Err(e) => self.error(e.into_message())
+                }
+            },
+            NP_Parsed_Schema::Int8    { .. } => self.scalar(cursor, |x: i8| NP_Bound::Int(x as i128)),
+            NP_Parsed_Schema::Int16   { .. } => self.scalar(cursor, |x: i16| NP_Bound::Int(x as i128)),
+            NP_Parsed_Schema::Int32   { .. } => self.scalar(cursor, |x: i32| NP_Bound::Int(x as i128)),
+            NP_Parsed_Schema::Int64   { .. } => self.scalar(cursor, |x: i64| NP_Bound::Int(x as i128)),
+            NP_Parsed_Schema::Uint8   { .. } => self.scalar(cursor, |x: u8| NP_Bound::Int(x as i128)),
+            NP_Parsed_Schema::Uint16  { .. } => self.scalar(cursor, |x: u16| NP_Bound::Int(x as i128)),
+            NP_Parsed_Schema::Uint32  { .. } => self.scalar(cursor, |x: u32| NP_Bound::Int(x as i128)),
+            NP_Parsed_Schema::Uint64  { .. } => self.scalar(cursor, |x: u64| NP_Bound::Int(x as i128)),
+            NP_Parsed_Schema::Float   { .. } => self.scalar(cursor, |x: f32| NP_Bound::Float(x as f64)),
+            NP_Parsed_Schema::Double  { .. } => self.scalar(cursor, |x: f64| NP_Bound::Float(x)),
+            NP_Parsed_Schema::Decimal { .. } => self.scalar(cursor, |x: NP_Dec| NP_Bound::Float(x.to_float())),
+            NP_Parsed_Schema::Date    { .. } => self.scalar(cursor, |x: NP_Date| NP_Bound::Int(x.value as i128)),
+            _ => { }
+        }
+    }
+}
+
+/// Validate every value below the root of the memory
+pub(crate) fn validate(memory: &NP_Memory) -> Result<(), Vec<NP_Validation_Error>> {
+
+    let mut validator = NP_Validator { memory, path: Vec::new(), errors: Vec::new() };
+
+    if memory.root + memory.addr_size.bytes() > memory.read_bytes().len() {
+        validator.error("Buffer is too short to hold a root pointer".to_owned());
+        return Err(validator.errors);
+    }
+
+    let root = NP_Cursor::new(memory.root, 0, 0);
+
+    if validator.is_set(&root) {
+        validator.walk(&root);
+    } else {
+        validator.missing(0);
+    }
+
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(validator.errors)
+    }
+}
+
+impl From<NP_Validation_Error> for NP_Error {
+    fn from(error: NP_Validation_Error) -> Self {
+        let mut message = String::from("Validation failed at '");
+        message.push_str(&error.path.join("."));
+        message.push_str("': ");
+        message.push_str(&error.message);
+        NP_Error::InvalidValue(message)
+    }
+}
+
+#[test]
+fn validation_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+    use crate::pointer::option::NP_Enum;
+    use alloc::vec;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["tags",  {"type": "list", "max_length": 2, "of": {"type": "string", "min_length": 2}}],
+            ["color", {"type": "option", "choices": ["red", "blue"]}],
+            ["point", {"type": "tuple", "values": [{"type": "i32", "required": true}, {"type": "float", "max": 1.5}]}]
+        ]
+    }"#)?;
+
+    // constraints survive exporting the schema
+    let exported = NP_Factory::new(&factory.export_schema()?.stringify())?;
+    assert_eq!(exported.export_schema()?.stringify(), factory.export_schema()?.stringify());
+
+    let mut new_buffer = exported.empty_buffer(None);
+    new_buffer.set(&["tags", "0"], "ok")?;
+    new_buffer.set(&["color"], NP_Enum::new("blue"))?;
+    new_buffer.set(&["point", "0"], 5i32)?;
+    assert_eq!(new_buffer.validate(), Ok(()));
+
+    new_buffer.set(&["tags", "2"], "x")?;
+    new_buffer.set(&["point", "1"], 2.5f32)?;
+    new_buffer.del(&["point", "0"])?;
+
+    let errors = new_buffer.validate().unwrap_err();
+    let found: Vec<(String, String)> = errors.into_iter().map(|e| (e.path.join("."), e.message)).collect();
+    assert_eq!(found, vec![
+        ("tags.2".to_owned(), "Length 1 is less than min_length 2".to_owned()),
+        ("tags".to_owned(), "Length 3 is more than max_length 2".to_owned()),
+        ("point.0".to_owned(), "Required value isn't set".to_owned()),
+        ("point.1".to_owned(), "Value 2.5 is more than max 1.5".to_owned())
+    ]);
+
+    // enum index past the last choice
+    let factory = NP_Factory::new(r#"{"type": "option", "choices": ["red", "blue"]}"#)?;
+    let mut new_buffer = factory.empty_buffer(None);
+    new_buffer.set(&[], NP_Enum::new("blue"))?;
+    let mut bytes = new_buffer.close();
+    let last = bytes.len() - 1;
+    bytes[last] = 9;
+    let errors = factory.open_buffer(bytes).validate().unwrap_err();
+    assert_eq!(errors[0].message, "Enum index 9 isn't one of the 2 choices");
+
+    Ok(())
+}
+
+#[test]
+fn redacted_json_works() -> Result<(), NP_Error> {
+    use crate::NP_Factory;
+
+    let factory = NP_Factory::new(r#"{
+        "type": "table",
+        "columns": [
+            ["id", {"type": "u32"}],
+            ["login", {"type": "table", "sensitive": true, "columns": [
+                ["user", {"type": "string"}],
+                ["pass", {"type": "string"}]
+            ]}],
+            ["notes", {"type": "map", "value": {"type": "string", "sensitive": true}}],
+            ["point", {"type": "tuple", "values": [{"type": "geo4", "sensitive": true}, {"type": "u8"}]}]
+        ]
+    }"#)?;
+
+    let mut new_buffer = factory.empty_buffer(None);
+    new_buffer.set(&["id"], 7u32)?;
+    new_buffer.set(&["login", "user"], "bill")?;
+    new_buffer.set(&["login", "pass"], "hunter2")?;
+    new_buffer.set(&["notes", "first"], "lives at the KSC")?;
+    new_buffer.set(&["point", "1"], 3u8)?;
+
+    assert_eq!(new_buffer.json_encode_redacted(&[])?.stringify(), r#"{"id":7,"login":"***","notes":{"first":"***"},"point":[null,3]}"#);
+    assert_eq!(new_buffer.json_encode_redacted(&["login", "pass"])?.stringify(), r#""***""#);
+    assert_eq!(new_buffer.json_encode_redacted(&["notes"])?.stringify(), r#"{"first":"***"}"#);
+    assert_eq!(new_buffer.json_encode_redacted(&["id"])?.stringify(), "7");
+
+    // the plain export is unchanged
+    assert_eq!(new_buffer.json_encode(&["login", "pass"])?.stringify(), r#""hunter2""#);
+
+    // sensitive markers survive exporting the schema
+    let exported = NP_Factory::new(&factory.export_schema()?.stringify())?;
+    let new_buffer = exported.open_buffer(new_buffer.close());
+    assert_eq!(new_buffer.json_encode_redacted(&["login"])?.stringify(), r#""***""#);
+
+    Ok(())
+}
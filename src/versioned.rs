@@ -0,0 +1,169 @@
+//! Open buffers with the schema version they were written with
+//!
+//! Long lived storage ends up holding buffers written with many versions of a schema.  Factories created with `with_version` stamp their version number into the header of every new buffer, an `NP_Versioned_Factory` holds one factory per version and opens each buffer with the factory that matches it's header.
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::NP_Factory;
+//! use no_proto::versioned::NP_Versioned_Factory;
+//!
+//! let v1 = NP_Factory::new(r#"{"type": "table", "columns": [["name", {"type": "string"}]]}"#)?.with_version(1);
+//! let v2 = NP_Factory::new(r#"{"type": "table", "columns": [["name", {"type": "string"}], ["age", {"type": "u8"}]]}"#)?.with_version(2);
+//!
+//! let mut old_buffer = v1.empty_buffer(None);
+//! old_buffer.set(&["name"], "Jeb")?;
+//! let old_bytes = old_buffer.close();
+//!
+//! let mut versions = NP_Versioned_Factory::new();
+//! versions.add(v1)?;
+//! versions.add(v2)?;
+//!
+//! // new buffers use the latest version
+//! let new_buffer = versions.empty_buffer(None)?;
+//! assert_eq!(new_buffer.get_version(), Some(2));
+//!
+//! // old buffers are opened with their own schema
+//! let old_buffer = versions.open_buffer(old_bytes)?;
+//! assert_eq!(old_buffer.get_version(), Some(1));
+//! assert_eq!(old_buffer.get::<&str>(&["name"])?, Some("Jeb"));
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::memory::header_version;
+use alloc::vec::Vec;
+use alloc::string::{String, ToString};
+
+/// A set of factories for each version of a schema
+///
+/// Check out documentation [here](../versioned/index.html).
+///
+#[derive(Debug, Default)]
+pub struct NP_Versioned_Factory {
+    factories: Vec<NP_Factory>
+}
+
+impl NP_Versioned_Factory {
+
+    /// Create an empty set of factories
+    pub fn new() -> Self {
+        NP_Versioned_Factory { factories: Vec::new() }
+    }
+
+    /// Add the factory for a schema version, the factory must have been created with `with_version`
+    pub fn add(&mut self, factory: NP_Factory) -> Result<(), NP_Error> {
+        let version = match factory.version() {
+            Some(x) => x,
+            None => return Err(NP_Error::Schema("Versioned factories need a version, use NP_Factory::with_version!".into()))
+        };
+        if self.get(version).is_some() {
+            let mut err = String::from("Schema version is already in versioned factory: ");
+            err.push_str(&version.to_string());
+            return Err(NP_Error::Schema(err));
+        }
+        self.factories.push(factory);
+        self.factories.sort_by_key(|factory| factory.version());
+        Ok(())
+    }
+
+    /// Get the factory for a schema version
+    pub fn get(&self, version: u32) -> Option<&NP_Factory> {
+        self.factories.iter().find(|factory| factory.version() == Some(version))
+    }
+
+    /// Get the factory with the highest schema version
+    pub fn latest(&self) -> Option<&NP_Factory> {
+        self.factories.last()
+    }
+
+    /// Generate a new empty buffer with the latest schema version
+    pub fn empty_buffer<'buffer>(&'buffer self, capacity: Option<usize>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        match self.latest() {
+            Some(factory) => Ok(factory.empty_buffer(capacity)),
+            None => Err(NP_Error::Schema("Versioned factory doesn't have any versions!".into()))
+        }
+    }
+
+    /// Open existing Vec<u8> as buffer with the factory matching the version in it's header
+    ///
+    /// Fails if the buffer doesn't have a version or the version is unknown.  If the factory stamps fingerprints, the fingerprint is checked too.
+    ///
+    pub fn open_buffer<'buffer>(&'buffer self, bytes: Vec<u8>) -> Result<NP_Buffer<'buffer>, NP_Error> {
+        let version = match header_version(&bytes) {
+            Some((version, _flags)) => version,
+            None => return Err(NP_Error::SchemaMismatch("Buffer header doesn't have a version!".into()))
+        };
+        match self.get(version) {
+            Some(factory) => factory.open_buffer_checked(bytes),
+            None => {
+                let mut err = String::from("Unknown schema version in buffer: ");
+                err.push_str(&version.to_string());
+                Err(NP_Error::Damaged(err))
+            }
+        }
+    }
+}
+
+#[test]
+fn versioned_factories_work() -> Result<(), NP_Error> {
+
+    let schema = |columns: &str| -> Result<NP_Factory, NP_Error> {
+        let mut json = String::from(r#"{"type": "table", "columns": ["#);
+        json.push_str(columns);
+        json.push_str("]}");
+        NP_Factory::new(&json)
+    };
+
+    let mut versions = NP_Versioned_Factory::new();
+    assert!(versions.empty_buffer(None).is_err());
+    assert!(versions.latest().is_none());
+
+    // factories need a unique version, they can be added in any order
+    assert!(versions.add(schema(r#"["name", {"type": "string"}]"#)?).is_err());
+    versions.add(schema(r#"["name", {"type": "string"}], ["age", {"type": "u8"}]"#)?.with_version(7).with_fingerprint())?;
+    versions.add(schema(r#"["name", {"type": "string"}]"#)?.with_version(1))?;
+    assert!(versions.add(schema(r#"["id", {"type": "u32"}]"#)?.with_version(1)).is_err());
+    assert_eq!(versions.latest().and_then(|x| x.version()), Some(7));
+    assert!(versions.get(2).is_none());
+
+    // version numbers wider than a byte survive the header
+    let mut buffer = versions.empty_buffer(None)?;
+    assert_eq!(buffer.get_version(), Some(7));
+    assert_eq!(buffer.fingerprint(), versions.get(7).map(|x| x.schema.fingerprint()));
+    buffer.set(&["age"], 30u8)?;
+    buffer.set_user_flags(0xff)?;
+    let latest_bytes = buffer.close();
+
+    let mut old_buffer = versions.get(1).map(|x| x.empty_buffer(None)).unwrap_or_else(|| panic!());
+    old_buffer.set(&["name"], "old")?;
+    let old_bytes = old_buffer.close();
+
+    let buffer = versions.open_buffer(latest_bytes.clone())?;
+    assert_eq!((buffer.get_version(), buffer.get_user_flags()), (Some(7), Some(0xff)));
+    assert_eq!(buffer.get::<u8>(&["age"])?, Some(30));
+    let buffer = versions.open_buffer(old_bytes)?;
+    assert_eq!((buffer.get_version(), buffer.get_user_flags()), (Some(1), Some(0)));
+    assert_eq!(buffer.get::<&str>(&["name"])?, Some("old"));
+
+    let large = schema(r#"["name", {"type": "string"}]"#)?.with_version(70_000);
+    let buffer = large.open_buffer(large.empty_buffer(None).close());
+    assert_eq!(buffer.get_version(), Some(70_000));
+
+    // unknown, missing and mismatched versions
+    assert!(matches!(versions.open_buffer(large.empty_buffer(None).close()), Err(NP_Error::Damaged(_))));
+    let unversioned = schema(r#"["name", {"type": "string"}]"#)?;
+    assert!(matches!(versions.open_buffer(unversioned.empty_buffer(None).close()), Err(NP_Error::SchemaMismatch(_))));
+    assert!(unversioned.empty_buffer(None).set_user_flags(1).is_err());
+    assert_eq!(unversioned.empty_buffer(None).get_user_flags(), None);
+
+    // the fingerprint is still checked for the matching version
+    let mut damaged = latest_bytes;
+    damaged[1] ^= 0xff;
+    assert!(matches!(versions.open_buffer(damaged), Err(NP_Error::SchemaMismatch(_))));
+
+    Ok(())
+}
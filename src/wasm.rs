@@ -0,0 +1,267 @@
+//! Factories and buffers for WASM clients
+//!
+//! Browser clients can read the same buffers as the server by compiling this crate to WASM.  `NP_Factory` and `NP_Buffer` don't cross into JavaScript well since buffers borrow their factory, so this module has an owned, string typed wrapper around them instead: `NP_Wasm_Factory` and `NP_Wasm_Buffer`.  This module requires the `wasm` feature.
+//!
+//! Both wrappers are exported with `#[wasm_bindgen]`, so building a crate that depends on NoProto with `wasm-pack` or `wasm-bindgen` makes them JavaScript classes.  Errors are thrown as JavaScript `Error`s with the message of the `NP_Error`.
+//!
+//! Values go in and out two ways:
+//! - `set_value`, `get_value`, `to_value` and `from_value` take and return JavaScript values, which go through `JSON.stringify` and `JSON.parse` on the way.
+//! - `set`, `get`, `to_json` and `from_json` take and return JSON text, these also work outside of WASM like in the example below.
+//!
+//! Paths are strings with dots between each step, like `users.3.name`, an empty string is the root of the buffer.
+//!
+//! ```js
+//! import { NP_Wasm_Factory } from "./pkg/my_app.js";
+//!
+//! const factory = new NP_Wasm_Factory(`{"type": "table", "columns": [["name", {"type": "string"}]]}`);
+//! const buffer = factory.from_value({ name: "Jeb" });
+//! buffer.get_value("name"); // "Jeb"
+//! const bytes = buffer.close(); // Uint8Array
+//! ```
+//!
+//! ```
+//! use no_proto::error::NP_Error;
+//! use no_proto::wasm::NP_Wasm_Factory;
+//!
+//! let factory = NP_Wasm_Factory::new(r#"{
+//!    "type": "table",
+//!    "columns": [
+//!         ["name", {"type": "string"}],
+//!         ["tags", {"type": "list", "of": {"type": "string"}}]
+//!     ]
+//! }"#)?;
+//!
+//! // server
+//! let mut new_buffer = factory.empty_buffer();
+//! new_buffer.set("name", r#""Jeb""#)?;
+//! new_buffer.set("tags.0", r#""pilot""#)?;
+//! let bytes: Vec<u8> = new_buffer.close();
+//!
+//! // browser
+//! let buffer = factory.open_buffer(bytes);
+//! assert_eq!(buffer.get("name")?, r#""Jeb""#);
+//! assert_eq!(buffer.get("tags")?, r#"["pilot"]"#);
+//! assert_eq!(buffer.to_json()?, r#"{"name":"Jeb","tags":["pilot"]}"#);
+//!
+//! // and whole documents can be imported
+//! let imported = factory.from_json(r#"{"name": "Bill", "tags": ["engineer"]}"#)?;
+//! assert_eq!(imported.get("tags.0")?, r#""engineer""#);
+//!
+//! # Ok::<(), NP_Error>(())
+//! ```
+//!
+
+use crate::NP_Factory;
+use crate::buffer::NP_Buffer;
+use crate::error::NP_Error;
+use crate::json_flex::json_decode_value;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use wasm_bindgen::prelude::*;
+
+impl From<NP_Error> for JsValue {
+    fn from(error: NP_Error) -> JsValue {
+        js_sys::Error::new(&error.to_string()).into()
+    }
+}
+
+/// Split a dotted path into the steps `NP_Buffer` takes
+fn wasm_path(path: &str) -> Vec<&str> {
+    if path.is_empty() {
+        Vec::new()
+    } else {
+        path.split('.').collect()
+    }
+}
+
+/// JSON text of a JavaScript value, `undefined` is `null`
+fn js_to_json(value: &JsValue) -> Result<String, NP_Error> {
+    if value.is_undefined() {
+        return Ok("null".into());
+    }
+    match js_sys::JSON::stringify(value) {
+        Ok(json) => json.as_string().ok_or_else(|| NP_Error::InvalidValue("JavaScript value can't be converted to JSON!".into())),
+        Err(_) => Err(NP_Error::InvalidValue("JavaScript value can't be converted to JSON!".into()))
+    }
+}
+
+/// JavaScript value of JSON text
+fn json_to_js(json: &str) -> Result<JsValue, NP_Error> {
+    js_sys::JSON::parse(json).map_err(|_| NP_Error::Parse("JSON can't be converted to a JavaScript value!".into()))
+}
+
+/// A factory that hands out owned buffers
+///
+/// Check out documentation [here](../wasm/index.html).
+///
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct NP_Wasm_Factory {
+    factory: NP_Factory
+}
+
+#[wasm_bindgen]
+impl NP_Wasm_Factory {
+
+    /// Create a factory from a JSON schema
+    #[wasm_bindgen(constructor)]
+    pub fn new(json_schema: &str) -> Result<NP_Wasm_Factory, NP_Error> {
+        Ok(NP_Wasm_Factory { factory: NP_Factory::new(json_schema)? })
+    }
+
+    /// Create a factory from a compiled schema
+    pub fn new_compiled(schema_bytes: Vec<u8>) -> NP_Wasm_Factory {
+        NP_Wasm_Factory { factory: NP_Factory::new_compiled(schema_bytes) }
+    }
+
+    /// Get the schema as JSON text
+    pub fn export_schema(&self) -> Result<String, NP_Error> {
+        Ok(self.factory.export_schema()?.stringify())
+    }
+
+    /// Create a new empty buffer
+    pub fn empty_buffer(&self) -> NP_Wasm_Buffer {
+        NP_Wasm_Buffer { factory: self.factory.clone(), bytes: self.factory.empty_buffer(None).close() }
+    }
+
+    /// Open the bytes of a closed buffer
+    pub fn open_buffer(&self, bytes: Vec<u8>) -> NP_Wasm_Buffer {
+        NP_Wasm_Buffer { factory: self.factory.clone(), bytes }
+    }
+
+    /// Create a new buffer from JSON text of the whole document, values that don't match the schema fail.
+    pub fn from_json(&self, json: &str) -> Result<NP_Wasm_Buffer, NP_Error> {
+        let mut buffer = self.empty_buffer();
+        buffer.set("", json)?;
+        Ok(buffer)
+    }
+
+    /// Create a new buffer from a JavaScript value of the whole document, values that don't match the schema fail.
+    pub fn from_value(&self, value: &JsValue) -> Result<NP_Wasm_Buffer, NP_Error> {
+        self.from_json(&js_to_json(value)?)
+    }
+}
+
+/// A buffer that owns it's bytes and a handle to it's factory
+///
+/// Check out documentation [here](../wasm/index.html).
+///
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct NP_Wasm_Buffer {
+    factory: NP_Factory,
+    bytes: Vec<u8>
+}
+
+impl NP_Wasm_Buffer {
+
+    /// Open the bytes as an `NP_Buffer` for one call and keep the bytes it leaves behind, even if the call fails
+    fn with_buffer<F, R>(&mut self, callback: F) -> R where F: FnOnce(&mut NP_Buffer) -> R {
+        let mut buffer = self.factory.open_buffer(core::mem::take(&mut self.bytes));
+        let result = callback(&mut buffer);
+        self.bytes = buffer.close();
+        result
+    }
+}
+
+#[wasm_bindgen]
+impl NP_Wasm_Buffer {
+
+    /// Set the value at a dotted path from JSON text, values that don't match the schema fail.
+    ///
+    /// Returns `false` if the path isn't in the schema.
+    ///
+    pub fn set(&mut self, path: &str, json_value: &str) -> Result<bool, NP_Error> {
+        let value = json_decode_value(json_value)?;
+        self.with_buffer(|buffer| buffer.set_json(&wasm_path(path), &value))
+    }
+
+    /// Get the value at a dotted path as JSON text, `null` if it hasn't been set
+    pub fn get(&self, path: &str) -> Result<String, NP_Error> {
+        Ok(self.factory.open_buffer_read_only(&self.bytes).json_encode(&wasm_path(path))?.stringify())
+    }
+
+    /// Set the value at a dotted path from a JavaScript value, values that don't match the schema fail.
+    ///
+    /// Returns `false` if the path isn't in the schema.
+    ///
+    pub fn set_value(&mut self, path: &str, value: &JsValue) -> Result<bool, NP_Error> {
+        self.set(path, &js_to_json(value)?)
+    }
+
+    /// Get the value at a dotted path as a JavaScript value, `null` if it hasn't been set
+    pub fn get_value(&self, path: &str) -> Result<JsValue, NP_Error> {
+        json_to_js(&self.get(path)?)
+    }
+
+    /// Delete the value at a dotted path, returns `false` if there wasn't one
+    pub fn del(&mut self, path: &str) -> Result<bool, NP_Error> {
+        self.with_buffer(|buffer| buffer.del(&wasm_path(path)))
+    }
+
+    /// Get the whole buffer as JSON text
+    pub fn to_json(&self) -> Result<String, NP_Error> {
+        self.get("")
+    }
+
+    /// Get the whole buffer as a JavaScript value
+    pub fn to_value(&self) -> Result<JsValue, NP_Error> {
+        self.get_value("")
+    }
+
+    /// Compact the buffer to drop old values
+    pub fn compact(&mut self) -> Result<(), NP_Error> {
+        self.with_buffer(|buffer| buffer.compact(None))
+    }
+
+    /// Copy of the buffer bytes
+    pub fn read_bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    /// Close the buffer and get the bytes
+    pub fn close(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[test]
+fn wasm_buffers_work() -> Result<(), NP_Error> {
+    let factory = NP_Wasm_Factory::new(r#"{"type": "table", "columns": [
+        ["id",    {"type": "u32"}],
+        ["score", {"type": "map", "value": {"type": "float"}}]
+    ]}"#)?;
+    let compiled = NP_Wasm_Factory::new_compiled(factory.factory.compile_schema());
+    assert_eq!(compiled.export_schema()?, factory.export_schema()?);
+
+    let mut buffer = factory.empty_buffer();
+    assert_eq!(buffer.to_json()?, "null");
+    assert!(buffer.set("id", "20")?);
+    assert!(buffer.set("score.math", "9.5")?);
+    assert!(!buffer.set("missing", "1")?);
+
+    // bad values fail without losing the buffer
+    assert!(buffer.set("id", r#""twenty""#).is_err());
+    assert!(buffer.set("id", "{").is_err());
+    assert!(buffer.set("id", "1, 2").is_err());
+    assert_eq!(buffer.get("id")?, "20");
+
+    assert_eq!(buffer.get("score.art")?, "null");
+    assert!(buffer.set("score.art", "3.5")?);
+    assert!(buffer.del("score.art")?);
+    buffer.compact()?;
+
+    let opened = compiled.open_buffer(buffer.close());
+    assert_eq!(opened.to_json()?, r#"{"id":20,"score":{"math":9.5,"art":null}}"#);
+    assert_eq!(opened.read_bytes(), opened.clone().close());
+
+    // whole documents go in as JSON too
+    fn is_send<T: Send + 'static>(_: &T) -> bool { true }
+    let imported = factory.from_json(&opened.to_json()?)?;
+    assert!(is_send(&imported));
+    assert_eq!(imported.to_json()?, r#"{"id":20,"score":{"math":9.5}}"#);
+    assert!(factory.from_json(r#"{"id": -1}"#).is_err());
+
+    Ok(())
+}